@@ -1882,6 +1882,116 @@ async fn finish_shulker_round_trip(
     Ok(container)
 }
 
+/// Finish a shulker operation a crash left half-done (see
+/// `store::journal_replay`).
+///
+/// Collects the shulker from the station if one is there, otherwise expects
+/// it in the bot's inventory or already back in `entry.slot_index`, and puts
+/// it into that slot. Then runs one ordinary journaled round trip on the
+/// slot purely to count `item` inside it, so the store can re-sync.
+///
+/// Returns where the shulker was found and the slot's count. `Err` when no
+/// shulker turns up anywhere, or when a station shulker cannot go back
+/// because the slot is already occupied (it is then left in the inventory).
+pub async fn replay_journal_entry(
+    bot: &Bot,
+    entry: &crate::store::journal::JournalEntry,
+    chest_pos: BlockPos,
+    item: &str,
+    node_position: &Position,
+) -> Result<(crate::store::journal::ShulkerLocation, i32), String> {
+    use crate::store::journal::ShulkerLocation;
+
+    prepare_for_chest_io(bot, node_position).await?;
+    let station_pos = super::shulker::shulker_station_position(node_position);
+    let client = bot
+        .client
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "Bot not connected".to_string())?;
+    info!(
+        "[ChestIO] Replaying journal op {} ({:?}) for chest {} slot {}, expected {}",
+        entry.operation_id,
+        entry.state,
+        entry.chest_id,
+        entry.slot_index,
+        entry.state.expected_location()
+    );
+
+    let mut found_at = None;
+    let station_block = BlockPos::new(station_pos.x, station_pos.y, station_pos.z);
+    if block_is_shulker(&client, station_block) {
+        super::shulker::pickup_shulker_from_station(bot, &station_pos, node_position).await?;
+        found_at = Some(ShulkerLocation::Station);
+    }
+
+    let container = open_chest_container(bot, chest_pos).await?;
+    let slot_holds_shulker = container
+        .contents()
+        .ok_or_else(|| "Chest closed".to_string())?
+        .get(entry.slot_index)
+        .is_some_and(|s| s.count() > 0 && super::shulker::is_shulker_box(&s.kind().to_string()));
+
+    let found_at = if slot_holds_shulker {
+        if found_at.is_some() {
+            return Err(format!(
+                "Chest {} slot {} already holds a shulker; the one collected from the station is in the bot's inventory",
+                entry.chest_id, entry.slot_index
+            ));
+        }
+        ShulkerLocation::ChestSlot
+    } else {
+        let Some(container_slot) = find_shulker_in_inventory_view(&container)? else {
+            return Err(format!(
+                "No shulker on the station, in the bot's inventory, or in chest {} slot {}",
+                entry.chest_id, entry.slot_index
+            ));
+        };
+        place_shulker_in_chest_slot_verified(&container, container_slot, entry.slot_index).await?;
+        found_at.unwrap_or(ShulkerLocation::Inventory)
+    };
+
+    // Count the slot through a normal round trip, journaled like any other.
+    let target_id = Bot::normalize_item_id(item);
+    let on_station = place_shulker_on_station(
+        bot,
+        chest_pos,
+        entry.chest_id,
+        entry.slot_index,
+        node_position,
+        &station_pos,
+        entry.operation_type,
+        container,
+        "journal replay",
+    )
+    .await?;
+    let count: i32 = on_station
+        .shulker_container
+        .contents()
+        .ok_or_else(|| "Shulker closed while counting".to_string())?
+        .iter()
+        .filter(|s| s.count() > 0 && Bot::normalize_item_id(&s.kind().to_string()) == target_id)
+        .map(|s| s.count())
+        .sum();
+    finish_shulker_round_trip(
+        bot,
+        chest_pos,
+        entry.slot_index,
+        &station_pos,
+        node_position,
+        on_station.shulker_container,
+        false,
+    )
+    .await?;
+
+    info!(
+        "[ChestIO] Journal op {} replayed: shulker was {}, chest {} slot {} holds {} {}",
+        entry.operation_id, found_at, entry.chest_id, entry.slot_index, count, item
+    );
+    Ok((found_at, count))
+}
+
 /// Per-slot withdraw loop, extracted from `automated_chest_io` for readability.
 ///
 /// Walks the 54 chest slots, opening shulkers that might contain the target item,
@@ -7,6 +7,7 @@ use tracing::{debug, error, info, warn};
 
 use super::{Bot, shulker};
 use crate::constants::{HOTBAR_SLOT_0, VERIFY_POLL_FAST_MS, VERIFY_POLL_SLOW_MS};
+use crate::messages::TradeItem;
 
 /// Container slot range for the player's main inventory rows (rows 1-3).
 /// Slots 0-8 are crafting/armor; 9-35 are the main 3x9 inventory grid.
@@ -54,6 +55,47 @@ pub fn find_shulker_slot(slots: &[ItemStack]) -> Option<usize> {
     None
 }
 
+/// Total count per item across the main inventory and hotbar slots of
+/// `slots` plus `cursor`, item ids without the `minecraft:` prefix.
+pub fn tally_items(slots: &[ItemStack], cursor: &ItemStack) -> Vec<TradeItem> {
+    let held = slots
+        .get(INVENTORY_RANGE.start..HOTBAR_RANGE.end)
+        .unwrap_or_default();
+    let mut counts: Vec<TradeItem> = Vec::new();
+    for stack in held.iter().chain(std::iter::once(cursor)) {
+        if stack.count() <= 0 {
+            continue;
+        }
+        let item = Bot::normalize_item_id(&stack.kind().to_string());
+        match counts.iter_mut().find(|t| t.item == item) {
+            Some(t) => t.amount += stack.count(),
+            None => counts.push(TradeItem {
+                item,
+                amount: stack.count(),
+            }),
+        }
+    }
+    counts
+}
+
+/// What the bot is holding right now; see [`tally_items`].
+pub async fn count_inventory(bot: &Bot) -> Result<Vec<TradeItem>, String> {
+    let client = bot
+        .client
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "Bot not connected".to_string())?;
+    let inv_handle = client
+        .open_inventory()
+        .ok_or_else(|| "Failed to open inventory (another container is open?)".to_string())?;
+    let slots = inv_handle
+        .slots()
+        .ok_or_else(|| "Inventory closed while counting items".to_string())?;
+    drop(inv_handle);
+    Ok(tally_items(&slots, &carried_item(&client)))
+}
+
 /// Ensure inventory is empty by dumping items to buffer chest if configured.
 ///
 /// Also clears any leftover cursor stack before dumping — a held cursor stack makes
@@ -1257,6 +1299,26 @@ mod tests {
         assert_eq!(slot_kind(usize::MAX), "unknown");
     }
 
+    #[test]
+    fn tally_items_sums_inventory_hotbar_and_cursor_only() {
+        let mut slots = empty_inv();
+        put(&mut slots, 1, ItemKind::Stone, 5); // crafting grid: not held
+        put(&mut slots, 9, ItemKind::Stone, 64);
+        put(&mut slots, 40, ItemKind::Stone, 10);
+        put(&mut slots, 12, ItemKind::Diamond, 3);
+        let cursor = ItemStack::new(ItemKind::Diamond, 2);
+
+        let counts: Vec<(String, i32)> = tally_items(&slots, &cursor)
+            .into_iter()
+            .map(|t| (t.item, t.amount))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("stone".to_string(), 74), ("diamond".to_string(), 5)]
+        );
+        assert!(tally_items(&empty_inv(), &ItemStack::Empty).is_empty());
+    }
+
     #[test]
     fn find_empty_inventory_slot_returns_first_match() {
         let mut slots = empty_inv();
@@ -36,7 +36,7 @@ use tracing::{debug, error, info, warn};
 use crate::config::ChatConfig;
 use crate::messages::{
     BotInstruction, BotMessage, ChatCommand, ChatEvent, ChatEventKind, ChestAction,
-    ChestSyncReport, StoreMessage,
+    ChestSyncReport, JournalReplayReport, StoreMessage,
 };
 use crate::types::Position;
 
@@ -430,10 +430,9 @@ pub async fn bot_task(
     // Load the operation journal and surface any leftover in-flight entry.
     //
     // A leftover entry means the previous run crashed between shulker lifecycle
-    // steps. We don't attempt automatic resume (that would require verifying
-    // live world state, which is easy to get wrong and leaks items); instead we
-    // log prominently so an operator can reconcile, then zero the file so the
-    // bot can proceed with fresh operations.
+    // steps. We log it prominently and archive the file so the bot can proceed
+    // with fresh operations; the store read the same entry before we started
+    // and sends it back as a `ReplayJournalEntry` once it is running.
     let journal = match crate::store::journal::Journal::load() {
         Ok((journal, leftover)) => {
             if let Some(entry) = &leftover {
@@ -916,6 +915,62 @@ pub async fn bot_task(
                     error!("[Bot] ValidateNode response channel dropped for node {}", node_id);
                 }
             }
+            BotInstruction::ReplayJournalEntry {
+                entry,
+                target_chest,
+                node_position,
+                respond_to,
+            } => {
+                let _critical = CriticalGuard::enter(&bot.in_critical_section);
+                let result = match navigation::go_to_chest(&bot, &target_chest, &node_position).await {
+                    Err(e) => {
+                        error!("[Bot] Navigation to chest {} for journal replay failed: {}", target_chest.id, e);
+                        Err(e)
+                    }
+                    Ok(()) => {
+                        let chest_block_pos = azalea::BlockPos::new(
+                            target_chest.position.x,
+                            target_chest.position.y,
+                            target_chest.position.z,
+                        );
+                        chest_io::replay_journal_entry(
+                            &bot,
+                            &entry,
+                            chest_block_pos,
+                            target_chest.item.as_str(),
+                            &node_position,
+                        )
+                        .await
+                        .map(|(found_at, count)| {
+                            let mut amounts = [-1i32; crate::constants::DOUBLE_CHEST_SLOTS];
+                            amounts[entry.slot_index] = count;
+                            JournalReplayReport {
+                                found_at,
+                                sync: ChestSyncReport {
+                                    chest_id: target_chest.id,
+                                    item: target_chest.item.as_str().to_string(),
+                                    amounts,
+                                },
+                            }
+                        })
+                    }
+                };
+                if let Err(e) = &result {
+                    error!("[Bot] Journal replay for chest {} failed: {}", target_chest.id, e);
+                }
+                if respond_to.send(result).is_err() {
+                    error!("[Bot] ReplayJournalEntry response channel dropped for chest {}", target_chest.id);
+                }
+            }
+            BotInstruction::CountInventory { respond_to } => {
+                let result = inventory::count_inventory(&bot).await;
+                if let Err(e) = &result {
+                    error!("[Bot] Inventory count failed: {}", e);
+                }
+                if respond_to.send(result).is_err() {
+                    error!("[Bot] CountInventory response channel dropped");
+                }
+            }
             BotInstruction::Restart => {
                 info!("Restarting bot");
 
@@ -1261,7 +1316,22 @@ async fn handle_event(client: Client, event: Event, state: &mut BotState) -> any
                 let _ = state.chat_events_tx.send(event);
             }
 
-            // Step 4: whisper router. If chat is disabled or this is a
+            // Step 4: a salvaged join broadcast lets the Store deliver
+            // notices it held while the player was offline.
+            if let Some(p) = &parsed
+                && p.kind == ChatEventKind::Public
+                && p.content == "*just joined the server*"
+                && let Some(store_tx) = &state.store_tx
+            {
+                let joined = BotMessage::PlayerJoined {
+                    player_name: p.sender.clone(),
+                };
+                if let Err(e) = store_tx.send(StoreMessage::FromBot(joined)).await {
+                    warn!("Failed to forward join of {} to store: {}", p.sender, e);
+                }
+            }
+
+            // Step 5: whisper router. If chat is disabled or this is a
             // command-shaped whisper, forward to Store; otherwise the
             // chat module owns the response (and we don't pipe to Store
             // to avoid the "Unknown command" double-reply).
@@ -584,10 +584,7 @@ fn validate_player_items(
         }
     } else {
         // Build normalized expected item IDs for quick lookup
-        let expected_items: std::collections::HashMap<String, i32> = player_offers
-            .iter()
-            .map(|ti| (Bot::normalize_item_id(&ti.item), ti.amount))
-            .collect();
+        let expected_items = offer_totals(player_offers);
 
         // Scan all player slots for items
         for &slot_idx in player_slots {
@@ -644,6 +641,19 @@ fn validate_player_items(
     (found_items, validation_errors)
 }
 
+/// Sum `offers` by normalized item ID.
+///
+/// A basket order puts several item types on one side of the window, and
+/// nothing stops a caller from listing the same item twice; collecting into
+/// a map directly would keep only the last amount and under-check the trade.
+fn offer_totals(offers: &[TradeItem]) -> std::collections::HashMap<String, i32> {
+    let mut totals = std::collections::HashMap::new();
+    for ti in offers {
+        *totals.entry(Bot::normalize_item_id(&ti.item)).or_insert(0) += ti.amount;
+    }
+    totals
+}
+
 /// Execute a full trade with a player via the trade GUI
 ///
 /// Returns the actual items received from the player (may differ from player_offers
@@ -732,14 +742,13 @@ pub async fn execute_trade_with_player(
             }
         }
 
-        // Check each expected offer
-        for ti in bot_offers {
-            let expected_id = Bot::normalize_item_id(&ti.item);
+        // Check each expected offer (summed per item type for baskets)
+        for (expected_id, expected_amount) in offer_totals(bot_offers) {
             let actual = total_offered.get(&expected_id).copied().unwrap_or(0);
-            if actual != ti.amount {
+            if actual != expected_amount {
                 let error_msg = format!(
                     "Bot offer verification failed for trade with {}: expected {}x {}, but only {}x placed in trade GUI",
-                    target_username, ti.amount, ti.item, actual
+                    target_username, expected_amount, expected_id, actual
                 );
                 warn!("{}", error_msg);
                 inv.close();
@@ -747,7 +756,7 @@ pub async fn execute_trade_with_player(
             }
             debug!(
                 "Bot offer verified for trade with {}: {}x {} in trade GUI",
-                target_username, actual, ti.item
+                target_username, actual, expected_id
             );
         }
     }
@@ -780,13 +789,10 @@ pub async fn execute_trade_with_player(
     // Track if we've ever successfully validated items (used for logging)
     let mut ever_validated = false;
     // Track what items the bot placed in the trade (for verifying trade success)
-    let mut bot_items_placed: Vec<TradeItem> = Vec::new();
-    for ti in bot_offers {
-        bot_items_placed.push(TradeItem {
-            item: Bot::normalize_item_id(&ti.item),
-            amount: ti.amount,
-        });
-    }
+    let bot_items_placed: Vec<TradeItem> = offer_totals(bot_offers)
+        .into_iter()
+        .map(|(item, amount)| TradeItem { item, amount })
+        .collect();
 
     while start.elapsed() < tokio::time::Duration::from_millis(bot.trade_timeout_ms) {
         // Check if trade menu is still open before trying to get contents
@@ -1442,6 +1448,75 @@ mod tests {
         assert_eq!(found.get("diamond").copied(), Some(7));
     }
 
+    #[test]
+    fn validate_player_items_accepts_mixed_basket_offer() {
+        let mut contents = empty_contents();
+        let player_slots = trade_player_offer_slots();
+        place(&mut contents, player_slots[0], ItemKind::Cobblestone, 64);
+        place(&mut contents, player_slots[1], ItemKind::Glass, 32);
+        let offers = vec![
+            TradeItem {
+                item: "cobblestone".into(),
+                amount: 64,
+            },
+            TradeItem {
+                item: "minecraft:glass".into(),
+                amount: 32,
+            },
+        ];
+        let (found, errors) = validate_player_items(&contents, &player_slots, &offers, true, false);
+        assert!(
+            errors.is_empty(),
+            "mixed offer should validate: {:?}",
+            errors
+        );
+        assert_eq!(found.get("cobblestone").copied(), Some(64));
+        assert_eq!(found.get("glass").copied(), Some(32));
+    }
+
+    #[test]
+    fn validate_player_items_rejects_basket_with_one_type_short() {
+        let mut contents = empty_contents();
+        let player_slots = trade_player_offer_slots();
+        place(&mut contents, player_slots[0], ItemKind::Cobblestone, 64);
+        place(&mut contents, player_slots[1], ItemKind::Glass, 10);
+        let offers = vec![
+            TradeItem {
+                item: "cobblestone".into(),
+                amount: 64,
+            },
+            TradeItem {
+                item: "glass".into(),
+                amount: 32,
+            },
+        ];
+        let (_found, errors) =
+            validate_player_items(&contents, &player_slots, &offers, true, false);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("Insufficient") && errors[0].contains("glass"));
+    }
+
+    #[test]
+    fn validate_player_items_sums_repeated_expected_item() {
+        // Listing diamond twice must expect 7, not just the last entry's 3.
+        let mut contents = empty_contents();
+        let player_slots = trade_player_offer_slots();
+        place(&mut contents, player_slots[0], ItemKind::Diamond, 7);
+        let offers = vec![
+            TradeItem {
+                item: "diamond".into(),
+                amount: 4,
+            },
+            TradeItem {
+                item: "diamond".into(),
+                amount: 3,
+            },
+        ];
+        let (_found, errors) =
+            validate_player_items(&contents, &player_slots, &offers, true, false);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
     #[test]
     fn validate_player_items_ignores_zero_count_stacks() {
         // Stacks with count==0 are sometimes left in the slot after partial
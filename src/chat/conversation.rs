@@ -934,15 +934,28 @@ mod tests {
             "b",
             "sell",
             "s",
+            "swap",
             "deposit",
             "d",
             "withdraw",
             "w",
+            "lp",
             "price",
             "p",
+            "stats",
+            "history",
+            "receipt",
+            "quote",
+            "confirm",
             "balance",
             "bal",
             "pay",
+            "request",
+            "accept",
+            "decline",
+            "escrow",
+            "alert",
+            "notify",
             "items",
             "queue",
             "q",
@@ -26,13 +26,45 @@ pub const PAIRS_DIR: &str = "data/pairs";
 
 /// Minimal deserializer for one pair JSON file. Skips orphan/internal
 /// fields not needed by chat (`stack_size` is kept because it lets the
-/// model answer "how many shulkers worth" follow-up questions).
+/// model answer "how many shulkers worth" follow-up questions). The fee
+/// overrides and curve are kept so quoted spot prices match what the store
+/// charges; `stats` lets the model answer "how busy is this pair".
 #[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct PairView {
     pub item: String,
     pub stack_size: i32,
     pub item_stock: i32,
     pub currency_stock: f64,
+    #[serde(default)]
+    pub buy_fee: Option<f64>,
+    #[serde(default)]
+    pub sell_fee: Option<f64>,
+    #[serde(default)]
+    pub curve: crate::store::curve::PricingCurve,
+    #[serde(default)]
+    pub stats: PairStatsView,
+}
+
+/// Cumulative trading stats as written by the trade bot's `PairStats`.
+/// Absent on pairs that have never traded, which reads as all zeros.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct PairStatsView {
+    #[serde(default)]
+    pub items_bought: u64,
+    #[serde(default)]
+    pub items_sold: u64,
+    #[serde(default)]
+    pub diamonds_in: f64,
+    #[serde(default)]
+    pub diamonds_out: f64,
+    #[serde(default)]
+    pub fees_collected: f64,
+    #[serde(default)]
+    pub buy_count: u64,
+    #[serde(default)]
+    pub sell_count: u64,
+    #[serde(default)]
+    pub last_trade: Option<String>,
 }
 
 /// Load every pair file in `data/pairs/`. Files that fail to
@@ -195,6 +227,21 @@ mod tests {
         assert_eq!(p.stack_size, 64);
         assert_eq!(p.item_stock, 42);
         assert!((p.currency_stock - 120.5).abs() < 1e-9);
+        assert_eq!((p.buy_fee, p.sell_fee), (None, None));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn load_all_reads_fee_overrides() {
+        let dir = fixture_dir("fee-overrides");
+        std::fs::write(
+            dir.join("diamond.json"),
+            r#"{"item":"diamond","stack_size":64,"item_stock":42,"currency_stock":120.5,"buy_fee":0.05}"#,
+        )
+        .unwrap();
+        let map = load_all_in_dir(&dir).unwrap();
+        let p = map.get("diamond").unwrap();
+        assert_eq!((p.buy_fee, p.sell_fee), (Some(0.05), None));
         let _ = std::fs::remove_dir_all(&dir);
     }
 
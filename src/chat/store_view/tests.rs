@@ -47,6 +47,13 @@ fn trade_view_handles_every_trade_type_variant() {
         TradeType::WithdrawBalance,
         TradeType::AddCurrency,
         TradeType::RemoveCurrency,
+        TradeType::AddLiquidity,
+        TradeType::RemoveLiquidity,
+        TradeType::Transfer,
+        TradeType::EscrowDeposit,
+        TradeType::EscrowRelease,
+        TradeType::EscrowReturn,
+        TradeType::Refund,
     ] {
         let t = Trade {
             trade_type: variant.clone(),
@@ -55,6 +62,13 @@ fn trade_view_handles_every_trade_type_variant() {
             amount_currency: 1.0,
             user_uuid: "u".to_string(),
             timestamp: chrono::Utc::now(),
+            fee: None,
+            item_stock_after: None,
+            currency_stock_after: None,
+            spot_price: None,
+            balance_change: None,
+            counterparty: None,
+            memo: None,
         };
         let json = serde_json::to_string(&t).unwrap();
         let view: store_view::trade::TradeView = serde_json::from_str(&json).unwrap();
@@ -74,6 +88,11 @@ fn pair_view_round_trips_a_real_pair() {
         stack_size: 64,
         item_stock: 100,
         currency_stock: 1000.5,
+        buy_fee: None,
+        sell_fee: None,
+        curve: Default::default(),
+        stats: Default::default(),
+        lp_total_shares: 0.0,
     };
     let json = serde_json::to_string(&p).unwrap();
     let view: store_view::pair::PairView = serde_json::from_str(&json).unwrap();
@@ -83,6 +102,33 @@ fn pair_view_round_trips_a_real_pair() {
     assert!((view.currency_stock - 1000.5).abs() < 1e-9);
 }
 
+#[test]
+fn pair_view_reads_stats_written_by_the_bot() {
+    use crate::types::{ItemId, Pair, Trade, TradeType};
+    let mut p = Pair {
+        item: ItemId::new("iron_ingot").unwrap(),
+        stack_size: 64,
+        item_stock: 100,
+        currency_stock: 500.0,
+        ..Default::default()
+    };
+    let trade = Trade::new(
+        TradeType::Buy,
+        ItemId::new("iron_ingot").unwrap(),
+        3,
+        16.875,
+        "u".to_string(),
+    )
+    .with_fee(1.875);
+    p.stats.record(&trade);
+    let json = serde_json::to_string(&p).unwrap();
+    let view: store_view::pair::PairView = serde_json::from_str(&json).unwrap();
+    assert_eq!(view.stats.buy_count, 1);
+    assert_eq!(view.stats.items_bought, 3);
+    assert!((view.stats.fees_collected - 1.875).abs() < 1e-9);
+    assert!(view.stats.last_trade.is_some());
+}
+
 #[test]
 fn user_view_drops_operator_field_via_deserialize() {
     use crate::types::User;
@@ -93,6 +139,7 @@ fn user_view_drops_operator_field_via_deserialize() {
         username: "alice".to_string(),
         balance: 5.0,
         operator: true,
+        lp_shares: Default::default(),
     };
     let json = serde_json::to_string(&u).unwrap();
     assert!(
@@ -5,6 +5,10 @@
 //! lets us prune by `since` BEFORE opening any file: parse the filename,
 //! drop survivors that are older than the cursor, deserialize the rest.
 //!
+//! Closed days are compacted into `data/trades/segments/<YYYY-MM-DD>.jsonl`
+//! (one trade per line). Segments are read after the per-trade files, newest
+//! day first, and pruned by `since` on the day in their name.
+//!
 //! Why not call `crate::types::Trade::load_all_with_limit`: it returns
 //! the last N filenames with no content filter. Asking it for "the
 //! latest iron trade" returns wrong answers when iron is older than N.
@@ -18,6 +22,10 @@ use serde::Deserialize;
 /// but owned by chat so the chat module never imports `crate::types::`.
 pub const TRADES_DIR: &str = "data/trades";
 
+/// Daily segment subdirectory. Mirrors
+/// `crate::types::trade_segment::SEGMENTS_DIR`.
+pub const SEGMENTS_DIR: &str = "segments";
+
 /// Cap on how many trade files we'll actually `read_to_string` +
 /// deserialize per scan. The full directory listing is still collected
 /// and lex-sorted (newest-first) so prune-by-`since` and ordering stay
@@ -180,6 +188,20 @@ pub fn scan_filtered_in_dir(
     });
     let user_uuid_filter_lc = filter.user_uuid.as_ref().map(|s| s.to_lowercase());
     let trade_type_filter = filter.trade_type.clone();
+    let matches = |trade: &TradeView| {
+        item_filter
+            .as_ref()
+            .is_none_or(|it| trade.item.eq_ignore_ascii_case(it))
+            && user_uuid_filter_lc
+                .as_ref()
+                .is_none_or(|uu| trade.user_uuid.eq_ignore_ascii_case(uu))
+            && trade_type_filter
+                .as_ref()
+                .is_none_or(|tt| trade.trade_type == *tt)
+    };
+    // Timestamps read from per-trade files. A file can outlive its copy
+    // in a segment by one compaction run; skip the segment copy.
+    let mut from_files: std::collections::HashSet<DateTime<Utc>> = std::collections::HashSet::new();
 
     let mut out: Vec<TradeView> = Vec::new();
     // Count files actually opened (read_to_string attempted), not just
@@ -221,22 +243,77 @@ pub fn scan_filtered_in_dir(
                 continue;
             }
         };
-        if let Some(ref it) = item_filter
-            && !trade.item.eq_ignore_ascii_case(it)
-        {
-            continue;
+        from_files.insert(trade.timestamp);
+        if matches(&trade) {
+            out.push(trade);
         }
-        if let Some(ref uu) = user_uuid_filter_lc
-            && !trade.user_uuid.eq_ignore_ascii_case(uu)
-        {
-            continue;
+    }
+
+    if out.len() < limit && !scan_truncated {
+        let since_day = filter.since.map(|t| t.date_naive());
+        let mut segments: Vec<(chrono::NaiveDate, std::path::PathBuf)> = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(dir.join(SEGMENTS_DIR)) {
+            for ent in entries.flatten() {
+                let path = ent.path();
+                let day = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_suffix(".jsonl"))
+                    .and_then(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+                // A segment holds one whole day: it can only contain trades
+                // newer than `since` if its day is not before `since`'s.
+                if let Some(day) = day
+                    && since_day.is_none_or(|s| day >= s)
+                {
+                    segments.push((day, path));
+                }
+            }
         }
-        if let Some(ref tt) = trade_type_filter
-            && trade.trade_type != *tt
-        {
-            continue;
+        segments.sort_by_key(|s| std::cmp::Reverse(s.0));
+
+        'segments: for (_, path) in segments {
+            let body = match std::fs::read_to_string(&path) {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("[chat/trade] read failed for {}: {e}", path.display());
+                    continue;
+                }
+            };
+            let mut day: Vec<TradeView> = Vec::new();
+            for line in body.lines().filter(|l| !l.trim().is_empty()) {
+                if deserialized >= MAX_DESERIALIZE {
+                    scan_truncated = true;
+                    break;
+                }
+                deserialized += 1;
+                match serde_json::from_str::<TradeView>(line) {
+                    Ok(t) => day.push(t),
+                    Err(e) => {
+                        tracing::warn!("[chat/trade] parse failed in {}: {e}", path.display());
+                    }
+                }
+            }
+            day.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
+            for trade in day {
+                if out.len() >= limit {
+                    break 'segments;
+                }
+                if filter.since.is_some_and(|s| trade.timestamp <= s)
+                    || from_files.contains(&trade.timestamp)
+                {
+                    continue;
+                }
+                if matches(&trade) {
+                    out.push(trade);
+                }
+            }
+            if scan_truncated {
+                break;
+            }
         }
-        out.push(trade);
+        // A per-trade file left over from a closed day is older than the
+        // newest segment trades; restore newest-first across both.
+        out.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
     }
     Ok((out, scan_truncated))
 }
@@ -244,7 +321,7 @@ pub fn scan_filtered_in_dir(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{TimeZone, Timelike};
+    use chrono::{Datelike, TimeZone, Timelike};
 
     fn write_trade(dir: &std::path::Path, ts: DateTime<Utc>, json: &str) {
         let stem = ts.to_rfc3339().replace(':', "-");
@@ -634,4 +711,49 @@ mod tests {
         assert_eq!(out.len(), MAX_DESERIALIZE);
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn scan_reads_segments_after_files_and_skips_leftover_duplicates() {
+        let dir = fixture_dir("segments");
+        let line = |day: u32, item: &str| {
+            format!(
+                r#"{{"trade_type":"Buy","item":"{item}","amount":1,"amount_currency":2.0,"user_uuid":"u","timestamp":"2026-01-0{day}T10:00:00Z"}}"#
+            )
+        };
+        let seg = dir.join(SEGMENTS_DIR);
+        std::fs::create_dir_all(&seg).unwrap();
+        std::fs::write(
+            seg.join("2026-01-01.jsonl"),
+            format!("{}\n", line(1, "iron_ingot")),
+        )
+        .unwrap();
+        // Day 2 was compacted but its file was not removed yet.
+        std::fs::write(
+            seg.join("2026-01-02.jsonl"),
+            format!("{}\n{{torn\n", line(2, "iron_ingot")),
+        )
+        .unwrap();
+        let t2 = Utc.with_ymd_and_hms(2026, 1, 2, 10, 0, 0).unwrap();
+        let t3 = Utc.with_ymd_and_hms(2026, 1, 3, 10, 0, 0).unwrap();
+        write_trade(&dir, t2, &line(2, "iron_ingot"));
+        write_trade(&dir, t3, &line(3, "iron_ingot"));
+
+        let f = TradeFilter {
+            item: Some("iron_ingot".to_string()),
+            ..Default::default()
+        };
+        let (out, truncated) = scan_filtered_in_dir(&dir, f, 10).unwrap();
+        assert!(!truncated);
+        let days: Vec<u32> = out.iter().map(|t| t.timestamp.day()).collect();
+        assert_eq!(days, vec![3, 2, 1], "newest first, day 2 once");
+
+        let f = TradeFilter {
+            since: Some(t2),
+            ..Default::default()
+        };
+        let (out, _) = scan_filtered_in_dir(&dir, f, 10).unwrap();
+        assert_eq!(out.len(), 1, "segments before `since` are pruned");
+        assert_eq!(out[0].timestamp, t3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -433,7 +433,7 @@ pub fn tool_definitions(
                     },
                     "trade_type": {
                         "type": "string",
-                        "description": "Pascal-case: Buy, Sell, AddStock, RemoveStock, DepositBalance, WithdrawBalance, AddCurrency, RemoveCurrency."
+                        "description": "Pascal-case: Buy, Sell, AddStock, RemoveStock, DepositBalance, WithdrawBalance, AddCurrency, RemoveCurrency, AddLiquidity, RemoveLiquidity."
                     },
                     "since": {
                         "type": "string",
@@ -450,7 +450,8 @@ pub fn tool_definitions(
                           pricing threshold and the store is not currently quoting. \
                           Indicative prices are SPOT — real order quotes scale with \
                           slippage, so do not multiply for large-order math without \
-                          caveating."
+                          caveating. `stats` holds cumulative trade count, volume, \
+                          and fee revenue for the pair."
                 .to_string(),
             input_schema: json!({
                 "type": "object",
@@ -1537,6 +1538,8 @@ async fn query_trades_tool(input: &Value, ctx: &ToolContext<'_>) -> Result<Strin
                 "WithdrawBalance",
                 "AddCurrency",
                 "RemoveCurrency",
+                "AddLiquidity",
+                "RemoveLiquidity",
             ];
             if !ALLOWED.contains(&t) {
                 return Err(format!("trade_type must be one of {ALLOWED:?} (got '{t}')",));
@@ -1695,11 +1698,25 @@ async fn get_pair_tool(input: &Value, ctx: &ToolContext<'_>) -> Result<String, S
     // hard requirement 3 (no flat-ratio price). The chat tool treats
     // `None` as "below MIN_RESERVE_FOR_PRICE; not currently quoting"
     // rather than inventing a number.
+    // Per-pair overrides win over the global fee, mirroring
+    // `Pair::effective_{buy,sell}_fee`. An out-of-range override is
+    // ignored by the store at load, so ignore it here too.
     let fee = read_store_fee_or_default().await;
-    let buy_price =
-        crate::store::pricing::indicative_spot_buy_price(p.item_stock, p.currency_stock, fee);
-    let sell_price =
-        crate::store::pricing::indicative_spot_sell_price(p.item_stock, p.currency_stock, fee);
+    let valid = |f: Option<f64>| f.filter(|f| crate::store::pricing::validate_fee(*f));
+    let buy_fee = valid(p.buy_fee).unwrap_or(fee);
+    let sell_fee = valid(p.sell_fee).unwrap_or(fee);
+    let buy_price = crate::store::pricing::indicative_spot_buy_price(
+        &p.curve,
+        p.item_stock,
+        p.currency_stock,
+        buy_fee,
+    );
+    let sell_price = crate::store::pricing::indicative_spot_sell_price(
+        &p.curve,
+        p.item_stock,
+        p.currency_stock,
+        sell_fee,
+    );
     let price_available = buy_price.is_some() && sell_price.is_some();
 
     Ok(serde_json::json!({
@@ -1712,6 +1729,20 @@ async fn get_pair_tool(input: &Value, ctx: &ToolContext<'_>) -> Result<String, S
         "indicative_buy_price": buy_price,
         "indicative_sell_price": sell_price,
         "fee": fee,
+        "buy_fee": buy_fee,
+        "sell_fee": sell_fee,
+        "pricing_curve": p.curve.label(),
+        "stats": {
+            "trade_count": p.stats.buy_count + p.stats.sell_count,
+            "buy_count": p.stats.buy_count,
+            "sell_count": p.stats.sell_count,
+            "items_bought": p.stats.items_bought,
+            "items_sold": p.stats.items_sold,
+            "diamonds_in": p.stats.diamonds_in,
+            "diamonds_out": p.stats.diamonds_out,
+            "fees_collected": p.stats.fees_collected,
+            "last_trade": p.stats.last_trade,
+        },
         "note": "indicative prices are spot; real order quotes scale with slippage",
     })
     .to_string())
@@ -19,14 +19,19 @@ use tracing::{error, info, warn};
 /// every `.interact()` was wrapped in `.expect(..)`, which killed the entire
 /// CLI task on the first hiccup. The loop re-prompts with a short backoff so
 /// the operator sees the prompt again instead of the process exiting.
-/// Compute buy/sell quotes from a pair's reserves and fee spread.
+/// Compute buy/sell quotes from a pair's reserves and per-side fees.
 ///
 /// Returns `(None, None)` when either reserve is zero: the pair is untradeable
 /// and the constant-product mid-price would be undefined or infinite.
-fn quote_prices(item_stock: i32, currency_stock: f64, fee: f64) -> (Option<f64>, Option<f64>) {
+fn quote_prices(
+    item_stock: i32,
+    currency_stock: f64,
+    buy_fee: f64,
+    sell_fee: f64,
+) -> (Option<f64>, Option<f64>) {
     if item_stock > 0 && currency_stock > 0.0 {
         let base = currency_stock / (item_stock as f64);
-        (Some(base * (1.0 + fee)), Some(base * (1.0 - fee)))
+        (Some(base * (1.0 + buy_fee)), Some(base * (1.0 - sell_fee)))
     } else {
         (None, None)
     }
@@ -77,6 +82,12 @@ pub fn cli_task(store_tx: mpsc::Sender<StoreMessage>, chat_tx: Option<mpsc::Send
             "Repair state (recompute pair stock)",
             "Restart Bot",
             "Clear stuck order",
+            "Set pair fees",
+            "Set pair pricing curve",
+            "View pair stats",
+            "Export price history (CSV)",
+            "Replay interrupted shulker op",
+            "View order audit trail",
         ];
         if chat_enabled {
             // CHAT.md: full set of operator-facing chat actions. The label
@@ -123,12 +134,18 @@ pub fn cli_task(store_tx: mpsc::Sender<StoreMessage>, chat_tx: Option<mpsc::Send
             "Remove node" => remove_node(&store_tx),
             "Add pair" => add_pair(&store_tx),
             "Remove pair" => remove_pair(&store_tx),
+            "Set pair fees" => set_pair_fees(&store_tx),
+            "Set pair pricing curve" => set_pair_curve(&store_tx),
+            "View pair stats" => view_pair_stats(&store_tx),
+            "Export price history (CSV)" => export_price_history(&store_tx),
             "View storage" => view_storage(&store_tx),
             "View recent trades" => view_trades(&store_tx),
             "Audit state" => audit_state(&store_tx, false),
             "Repair state (recompute pair stock)" => audit_state(&store_tx, true),
             "Restart Bot" => restart_bot(&store_tx),
             "Clear stuck order" => clear_stuck_order(&store_tx),
+            "Replay interrupted shulker op" => replay_journal(&store_tx),
+            "View order audit trail" => view_order_audit(),
             "Chat: status" => chat_status(chat_tx.as_ref()),
             "Chat: pause" => chat_set_paused(chat_tx.as_ref(), true),
             "Chat: resume" => chat_set_paused(chat_tx.as_ref(), false),
@@ -805,6 +822,119 @@ fn get_balances(store_tx: &mpsc::Sender<StoreMessage>) {
 
 /// Sends a QueryPairs request and displays the results, including
 /// AMM-style buy/sell prices derived from each pair's current reserves.
+/// Sends a QueryPairs request and prints each pair's cumulative trading
+/// statistics, busiest first, followed by store-wide totals.
+fn view_pair_stats(store_tx: &mpsc::Sender<StoreMessage>) {
+    let (response_tx, response_rx) = oneshot::channel();
+    let msg = StoreMessage::FromCli(CliMessage::QueryPairs {
+        respond_to: response_tx,
+    });
+
+    if store_tx.blocking_send(msg).is_err() {
+        error!("[CLI] QueryPairs send failed: Store channel closed");
+        return;
+    }
+
+    let Ok(mut pairs) = response_rx.blocking_recv() else {
+        error!("[CLI] QueryPairs response channel closed without reply");
+        return;
+    };
+    if pairs.is_empty() {
+        println!("No pairs found.");
+        return;
+    }
+    pairs.sort_by(|a, b| {
+        b.stats
+            .trade_count()
+            .cmp(&a.stats.trade_count())
+            .then_with(|| a.item.as_str().cmp(b.item.as_str()))
+    });
+
+    println!("\n=== Pair stats ===");
+    let (mut trades, mut fees) = (0u64, 0.0f64);
+    for pair in &pairs {
+        println!("{}: {}", pair.item, pair.stats.summary());
+        trades += pair.stats.trade_count();
+        fees += pair.stats.fees_collected;
+    }
+    println!(
+        "\nTotal: {} trades across {} pairs, {:.2} diamonds in fees",
+        trades,
+        pairs.len(),
+        fees
+    );
+}
+
+/// Prompts for item, resolution and output path, fetches the candles with a
+/// QueryPriceHistory request, and writes them as CSV.
+fn export_price_history(store_tx: &mpsc::Sender<StoreMessage>) {
+    use crate::store::price_history::{Resolution, to_csv};
+
+    let item_name: String = with_retry("Failed to read item name", || {
+        Input::new().with_prompt("Enter item name").interact_text()
+    });
+    let resolution = match with_retry("Failed to read resolution", || {
+        Select::new()
+            .with_prompt("Candle resolution")
+            .items(["Hourly", "Daily"])
+            .default(0)
+            .interact()
+    }) {
+        0 => Resolution::Hourly,
+        _ => Resolution::Daily,
+    };
+
+    let (response_tx, response_rx) = oneshot::channel();
+    let msg = StoreMessage::FromCli(CliMessage::QueryPriceHistory {
+        item_name: item_name.clone(),
+        resolution,
+        respond_to: response_tx,
+    });
+    if store_tx.blocking_send(msg).is_err() {
+        error!("[CLI] QueryPriceHistory send failed: Store channel closed");
+        return;
+    }
+    let candles = match response_rx.blocking_recv() {
+        Ok(Ok(c)) => c,
+        Ok(Err(e)) => {
+            println!("Failed to export price history: {}", e);
+            return;
+        }
+        Err(_) => {
+            error!("[CLI] QueryPriceHistory response channel closed without reply");
+            return;
+        }
+    };
+    if candles.is_empty() {
+        println!("No price history for '{}' yet.", item_name);
+        return;
+    }
+
+    let default_path = format!(
+        "data/exports/{}_{}.csv",
+        item_name.trim_start_matches("minecraft:"),
+        resolution.label()
+    );
+    let path: String = with_retry("Failed to read output path", || {
+        Input::new()
+            .with_prompt("Output file")
+            .default(default_path.clone())
+            .interact_text()
+    });
+    match crate::fsutil::write_atomic(&path, &to_csv(&candles)) {
+        Ok(()) => println!(
+            "Wrote {} {} candles to {}.",
+            candles.len(),
+            resolution.label(),
+            path
+        ),
+        Err(e) => {
+            println!("Failed to write {}: {}", path, e);
+            error!("[CLI] Price history export to {path} failed: {e}");
+        }
+    }
+}
+
 fn get_pairs(store_tx: &mpsc::Sender<StoreMessage>) {
     // Fall back to the default configured fee on query failure so the operator
     // still sees a price estimate. A non-default fee would make the displayed
@@ -849,12 +979,41 @@ fn get_pairs(store_tx: &mpsc::Sender<StoreMessage>) {
             } else {
                 println!("\n=== Pairs ===");
                 for pair in pairs {
-                    let (price_buy, price_sell) =
-                        quote_prices(pair.item_stock, pair.currency_stock, fee);
+                    let (buy_fee, sell_fee) =
+                        (pair.effective_buy_fee(fee), pair.effective_sell_fee(fee));
+                    let (price_buy, price_sell) = if pair.curve.is_constant_product() {
+                        quote_prices(pair.item_stock, pair.currency_stock, buy_fee, sell_fee)
+                    } else {
+                        let mid = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+                        (
+                            mid.map(|m| m * (1.0 + buy_fee)),
+                            mid.map(|m| m * (1.0 - sell_fee)),
+                        )
+                    };
                     println!(
                         "Item: {}, Stock: {}, Currency: {:.2}",
                         pair.item, pair.item_stock, pair.currency_stock
                     );
+                    if !pair.curve.is_constant_product() {
+                        println!("  Curve: {}", pair.curve.label());
+                    }
+                    if pair.has_fee_override() {
+                        println!(
+                            "  Fees: buy {:.4}{}, sell {:.4}{}",
+                            pair.effective_buy_fee(fee),
+                            if pair.buy_fee.is_some() {
+                                " (override)"
+                            } else {
+                                ""
+                            },
+                            pair.effective_sell_fee(fee),
+                            if pair.sell_fee.is_some() {
+                                " (override)"
+                            } else {
+                                ""
+                            },
+                        );
+                    }
                     if let Some(pb) = price_buy {
                         println!("  Buy price: {:.2} diamonds/item", pb);
                     }
@@ -1162,6 +1321,163 @@ fn remove_pair(store_tx: &mpsc::Sender<StoreMessage>) {
     }
 }
 
+/// Prompt for a fee override; blank means "use the global fee".
+fn prompt_fee_override(prompt: &str) -> Option<f64> {
+    loop {
+        let raw: String = with_retry("Failed to read fee", || {
+            Input::new()
+                .with_prompt(prompt)
+                .allow_empty(true)
+                .interact_text()
+        });
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return None;
+        }
+        match raw.parse::<f64>() {
+            Ok(f) => return Some(f),
+            Err(_) => println!("'{}' is not a number (e.g. 0.05 for 5%).", raw),
+        }
+    }
+}
+
+/// Prompts for item name and per-side fee overrides, then sends a
+/// SetPairFees request. Takes effect on the next priced order.
+fn set_pair_fees(store_tx: &mpsc::Sender<StoreMessage>) {
+    let item_name: String = with_retry("Failed to read item name", || {
+        Input::new().with_prompt("Enter item name").interact_text()
+    });
+    let buy_fee = prompt_fee_override("Buy fee, e.g. 0.05 (blank = global fee)");
+    let sell_fee = prompt_fee_override("Sell fee, e.g. 0.05 (blank = global fee)");
+
+    info!(
+        "[CLI] Requesting fee overrides for {}: buy {:?}, sell {:?}",
+        item_name, buy_fee, sell_fee
+    );
+
+    let (response_tx, response_rx) = oneshot::channel();
+    let msg = StoreMessage::FromCli(CliMessage::SetPairFees {
+        item_name: item_name.clone(),
+        buy_fee,
+        sell_fee,
+        respond_to: response_tx,
+    });
+
+    if store_tx.blocking_send(msg).is_err() {
+        error!("[CLI] SetPairFees send failed: Store channel closed");
+        return;
+    }
+
+    match response_rx.blocking_recv() {
+        Ok(Ok(())) => println!("Fees for '{}' updated.", item_name),
+        Ok(Err(e)) => {
+            println!("Failed to set fees: {}", e);
+            error!("[CLI] SetPairFees for {item_name} failed: {e}");
+        }
+        Err(_) => error!("[CLI] SetPairFees response channel closed without reply"),
+    }
+}
+
+/// Prompts for item name and a pricing curve with its parameters, then
+/// sends a SetPairCurve request. Takes effect on the next priced order.
+fn set_pair_curve(store_tx: &mpsc::Sender<StoreMessage>) {
+    use crate::store::curve::PricingCurve;
+
+    let item_name: String = with_retry("Failed to read item name", || {
+        Input::new().with_prompt("Enter item name").interact_text()
+    });
+    let kind = with_retry("Failed to read curve selection", || {
+        Select::new()
+            .with_prompt("Select pricing curve")
+            .items([
+                "Constant product (x * y = k, default)",
+                "Fixed price",
+                "Linear bonding curve",
+                "Stable-swap (pegged to diamonds)",
+            ])
+            .default(0)
+            .interact()
+    });
+    let curve = match kind {
+        0 => PricingCurve::ConstantProduct,
+        1 => {
+            let price: f64 = with_retry("Failed to read price", || {
+                Input::new()
+                    .with_prompt("Price per item (diamonds)")
+                    .interact_text()
+            });
+            let max_stock: i32 = with_retry("Failed to read max stock", || {
+                Input::new()
+                    .with_prompt("Stop buying from players above this stock (0 = no cap)")
+                    .default(0)
+                    .interact_text()
+            });
+            PricingCurve::Fixed {
+                price,
+                max_stock: (max_stock > 0).then_some(max_stock),
+            }
+        }
+        2 => {
+            let base_price: f64 = with_retry("Failed to read base price", || {
+                Input::new()
+                    .with_prompt("Price per item at the anchor stock (diamonds)")
+                    .interact_text()
+            });
+            let anchor_stock: i32 = with_retry("Failed to read anchor stock", || {
+                Input::new().with_prompt("Anchor stock").interact_text()
+            });
+            let slope: f64 = with_retry("Failed to read slope", || {
+                Input::new()
+                    .with_prompt("Price change per item of stock (diamonds)")
+                    .interact_text()
+            });
+            PricingCurve::Linear {
+                base_price,
+                slope,
+                anchor_stock,
+            }
+        }
+        3 => {
+            let peg: f64 = with_retry("Failed to read peg", || {
+                Input::new()
+                    .with_prompt("Peg: diamonds per item (e.g. 9 for diamond_block)")
+                    .interact_text()
+            });
+            let amplification: f64 = with_retry("Failed to read amplification", || {
+                Input::new()
+                    .with_prompt("Amplification (higher = flatter near the peg)")
+                    .default(100.0)
+                    .interact_text()
+            });
+            PricingCurve::StableSwap { peg, amplification }
+        }
+        _ => unreachable!("Select bounded by items() above"),
+    };
+
+    info!("[CLI] Requesting curve {} for {}", curve.label(), item_name);
+
+    let (response_tx, response_rx) = oneshot::channel();
+    let msg = StoreMessage::FromCli(CliMessage::SetPairCurve {
+        item_name: item_name.clone(),
+        curve,
+        respond_to: response_tx,
+    });
+
+    if store_tx.blocking_send(msg).is_err() {
+        error!("[CLI] SetPairCurve send failed: Store channel closed");
+        return;
+    }
+
+    match response_rx.blocking_recv() {
+        Ok(Ok(())) => println!("'{}' now prices on: {}.", item_name, curve.label()),
+        Ok(Err(e)) => {
+            println!("Failed to set curve: {}", e);
+            error!("[CLI] SetPairCurve for {item_name} failed: {e}");
+        }
+        Err(_) => error!("[CLI] SetPairCurve response channel closed without reply"),
+    }
+}
+
 /// Sends a QueryStorage request and displays the storage state.
 fn view_storage(store_tx: &mpsc::Sender<StoreMessage>) {
     let (response_tx, response_rx) = oneshot::channel();
@@ -1251,6 +1567,13 @@ fn view_trades(store_tx: &mpsc::Sender<StoreMessage>) {
                         TradeType::WithdrawBalance => "WITHDRAW",
                         TradeType::AddCurrency => "ADD_CURRENCY",
                         TradeType::RemoveCurrency => "REMOVE_CURRENCY",
+                        TradeType::AddLiquidity => "ADD_LIQUIDITY",
+                        TradeType::RemoveLiquidity => "REMOVE_LIQUIDITY",
+                        TradeType::Transfer => "TRANSFER",
+                        TradeType::EscrowDeposit => "ESCROW_DEPOSIT",
+                        TradeType::EscrowRelease => "ESCROW_RELEASE",
+                        TradeType::EscrowReturn => "ESCROW_RETURN",
+                        TradeType::Refund => "REFUND",
                     };
                     println!(
                         "[{}] {} - {}x {} for {:.2} diamonds (user: {})",
@@ -1316,6 +1639,63 @@ fn clear_stuck_order(store_tx: &mpsc::Sender<StoreMessage>) {
     }
 }
 
+/// Has the bot finish a shulker operation a crash left half-done: the one
+/// found at startup, or the entry in a named journal archive.
+fn replay_journal(store_tx: &mpsc::Sender<StoreMessage>) {
+    let raw: String = with_retry("Failed to read journal path", || {
+        Input::new()
+            .with_prompt("Journal file to replay (blank = the one found at startup)")
+            .allow_empty(true)
+            .interact_text()
+    });
+    let path = Some(raw.trim().to_string()).filter(|p| !p.is_empty());
+
+    let (response_tx, response_rx) = oneshot::channel();
+    let msg = StoreMessage::FromCli(CliMessage::ReplayJournal {
+        path,
+        respond_to: response_tx,
+    });
+
+    if store_tx.blocking_send(msg).is_err() {
+        error!("[CLI] ReplayJournal send failed: Store channel closed");
+        return;
+    }
+
+    match response_rx.blocking_recv() {
+        Ok(Ok(lines)) => {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        Ok(Err(e)) => {
+            println!("Replay failed: {}", e);
+            println!("See RECOVERY.md section 2 to finish the operation by hand.");
+        }
+        Err(_) => error!("[CLI] ReplayJournal response channel closed without reply"),
+    }
+}
+
+/// Prints every audit log record for one order id. Reads the log files
+/// directly: they are append-only, so no round trip through the Store is
+/// needed.
+fn view_order_audit() {
+    let order_id: u64 = with_retry("Failed to read order id", || {
+        Input::new().with_prompt("Order id").interact_text()
+    });
+    match crate::store::audit_log::story(order_id) {
+        Ok(records) if records.is_empty() => {
+            println!("No audit records for order #{}.", order_id);
+        }
+        Ok(records) => {
+            println!("\n=== Order #{} ({} event(s)) ===", order_id, records.len());
+            for record in records {
+                println!("{}", record.describe());
+            }
+        }
+        Err(e) => println!("Failed to read the audit log: {}", e),
+    }
+}
+
 /// Sends an AuditState request and displays any invariant violations found.
 /// If `repair` is true, also applies safe automatic repairs (e.g. recomputing pair stock).
 fn audit_state(store_tx: &mpsc::Sender<StoreMessage>, repair: bool) {
@@ -1355,18 +1735,18 @@ mod tests {
 
     #[test]
     fn quote_prices_returns_none_when_item_stock_is_zero() {
-        assert_eq!(quote_prices(0, 100.0, 0.125), (None, None));
+        assert_eq!(quote_prices(0, 100.0, 0.125, 0.125), (None, None));
     }
 
     #[test]
     fn quote_prices_returns_none_when_currency_stock_is_zero() {
-        assert_eq!(quote_prices(10, 0.0, 0.125), (None, None));
+        assert_eq!(quote_prices(10, 0.0, 0.125, 0.125), (None, None));
     }
 
     #[test]
     fn quote_prices_returns_none_when_currency_stock_is_negative() {
         // Defensive: constant-product is undefined for non-positive reserves.
-        assert_eq!(quote_prices(10, -1.0, 0.125), (None, None));
+        assert_eq!(quote_prices(10, -1.0, 0.125, 0.125), (None, None));
     }
 
     #[test]
@@ -1374,14 +1754,22 @@ mod tests {
         // item_stock=10, currency_stock=100 -> mid = 10.0, fee = 0.125
         // buy  = 10.0 * 1.125 = 11.25
         // sell = 10.0 * 0.875 =  8.75
-        let (buy, sell) = quote_prices(10, 100.0, 0.125);
+        let (buy, sell) = quote_prices(10, 100.0, 0.125, 0.125);
         assert!((buy.unwrap() - 11.25).abs() < 1e-9);
         assert!((sell.unwrap() - 8.75).abs() < 1e-9);
     }
 
+    #[test]
+    fn quote_prices_applies_buy_and_sell_fees_independently() {
+        // mid = 10.0; buy fee 5% -> 10.5, sell fee 20% -> 8.0
+        let (buy, sell) = quote_prices(10, 100.0, 0.05, 0.2);
+        assert!((buy.unwrap() - 10.5).abs() < 1e-9);
+        assert!((sell.unwrap() - 8.0).abs() < 1e-9);
+    }
+
     #[test]
     fn quote_prices_with_zero_fee_collapses_buy_and_sell_to_mid() {
-        let (buy, sell) = quote_prices(4, 8.0, 0.0);
+        let (buy, sell) = quote_prices(4, 8.0, 0.0, 0.0);
         assert_eq!(buy, sell);
         assert!((buy.unwrap() - 2.0).abs() < 1e-9);
     }
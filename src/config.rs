@@ -43,6 +43,17 @@ pub struct Config {
     pub max_trades_in_memory: usize,
     #[serde(default = "default_autosave_interval_secs")]
     pub autosave_interval_secs: u64,
+    /// Days of compacted trade history kept readable. Daily segments older
+    /// than this move to `data/trades/archive/`; `None` keeps them all.
+    #[serde(default)]
+    pub trade_retention_days: Option<u32>,
+    /// Seconds between scheduled snapshots of `data/` into `data/backups/`;
+    /// `None` turns the schedule off (risky CLI actions still snapshot).
+    #[serde(default = "default_snapshot_interval_secs")]
+    pub snapshot_interval_secs: Option<u64>,
+    /// Snapshots kept in `data/backups/`; the oldest beyond this are deleted.
+    #[serde(default = "default_snapshot_keep")]
+    pub snapshot_keep: usize,
 
     /// Chat AI module configuration. Defaults disable the module entirely so
     /// existing operators are unaffected; see [`ChatConfig`] for the full
@@ -874,16 +885,29 @@ fn default_chat_command_prefixes() -> Vec<String> {
         "b",
         "sell",
         "s",
+        "swap",
         "deposit",
         "d",
         "withdraw",
         "w",
+        "lp",
         // Quick commands + aliases
         "price",
         "p",
+        "stats",
+        "history",
+        "receipt",
+        "quote",
+        "confirm",
         "balance",
         "bal",
         "pay",
+        "request",
+        "accept",
+        "decline",
+        "escrow",
+        "alert",
+        "notify",
         "items",
         "queue",
         "q",
@@ -927,6 +951,12 @@ fn default_max_trades_in_memory() -> usize {
 fn default_autosave_interval_secs() -> u64 {
     2
 }
+fn default_snapshot_interval_secs() -> Option<u64> {
+    Some(6 * 3600)
+}
+fn default_snapshot_keep() -> usize {
+    10
+}
 
 impl Config {
     /// Validates every field and returns a single error message listing
@@ -1048,6 +1078,15 @@ impl Config {
         if self.max_trades_in_memory == 0 {
             errors.push("max_trades_in_memory must be greater than 0".to_string());
         }
+        if self.trade_retention_days == Some(0) {
+            errors.push("trade_retention_days must be greater than 0 (or null)".to_string());
+        }
+        if self.snapshot_interval_secs == Some(0) {
+            errors.push("snapshot_interval_secs must be greater than 0 (or null)".to_string());
+        }
+        if self.snapshot_keep == 0 {
+            errors.push("snapshot_keep must be greater than 0".to_string());
+        }
 
         // Chat config validation. Reads, but does not mutate, `self.chat`.
         if let Err(e) = self.chat.validate() {
@@ -1082,6 +1121,9 @@ impl Config {
             max_orders: 1000,
             max_trades_in_memory: 1000,
             autosave_interval_secs: 10,
+            trade_retention_days: None,
+            snapshot_interval_secs: None,
+            snapshot_keep: default_snapshot_keep(),
             chat: ChatConfig::default(),
         }
     }
@@ -1119,6 +1161,9 @@ impl Config {
                 max_orders: default_max_orders(),
                 max_trades_in_memory: default_max_trades_in_memory(),
                 autosave_interval_secs: default_autosave_interval_secs(),
+                trade_retention_days: None,
+                snapshot_interval_secs: default_snapshot_interval_secs(),
+                snapshot_keep: default_snapshot_keep(),
                 chat: ChatConfig::default(),
             };
 
@@ -1160,6 +1205,9 @@ mod tests {
             max_orders: default_max_orders(),
             max_trades_in_memory: default_max_trades_in_memory(),
             autosave_interval_secs: default_autosave_interval_secs(),
+            trade_retention_days: None,
+            snapshot_interval_secs: default_snapshot_interval_secs(),
+            snapshot_keep: default_snapshot_keep(),
             chat: ChatConfig::default(),
         }
     }
@@ -1411,6 +1459,29 @@ mod tests {
         assert!(err.contains("max_trades_in_memory"), "got: {err}");
     }
 
+    #[test]
+    fn zero_trade_retention_is_rejected_but_none_is_allowed() {
+        let mut c = valid_config();
+        c.trade_retention_days = Some(0);
+        let err = c.validate().unwrap_err();
+        assert!(err.contains("trade_retention_days"), "got: {err}");
+        c.trade_retention_days = None;
+        assert!(c.validate().is_ok());
+    }
+
+    #[test]
+    fn zero_snapshot_interval_or_keep_is_rejected() {
+        let mut c = valid_config();
+        c.snapshot_interval_secs = Some(0);
+        c.snapshot_keep = 0;
+        let err = c.validate().unwrap_err();
+        assert!(err.contains("snapshot_interval_secs"), "got: {err}");
+        assert!(err.contains("snapshot_keep"), "got: {err}");
+        c.snapshot_interval_secs = None;
+        c.snapshot_keep = 1;
+        assert!(c.validate().is_ok());
+    }
+
     #[test]
     fn multiple_violations_are_all_reported() {
         let mut c = valid_config();
@@ -192,11 +192,17 @@ pub const MAX_TRANSACTION_QUANTITY: i32 = 1_000_000;
 /// `bot::trade`). A single trade can therefore move at most
 /// `TRADE_OFFER_SLOTS_PER_SIDE * stack_size` of one item — orders above that
 /// can be withdrawn into the bot's inventory but then cannot all be placed
-/// into the offer grid, so they must be rejected up front (see
-/// `validate_and_plan_buy` / `validate_and_plan_sell`) rather than failing
-/// mid-trade with "Bot trade offer slots are full" and forcing a rollback.
+/// into the offer grid, so plain buys and sells above it are split into
+/// consecutive trades up front (see `orders::run_order_sessions`) rather than
+/// failing mid-trade with "Bot trade offer slots are full" and forcing a
+/// rollback. Other order types reject them.
 pub const TRADE_OFFER_SLOTS_PER_SIDE: i32 = 12;
 
+/// Most `/trade` sessions one buy or sell may be split into. Each session can
+/// hold the bot for a full trade timeout, so an unbounded split would let one
+/// order stall the queue for everyone behind it.
+pub const MAX_ORDER_SESSIONS: u32 = 16;
+
 /// Maximum diamonds movable in a single trade.
 ///
 /// Minecraft's vanilla trade UI exposes 12 offer slots (4x3 grid); each
@@ -242,6 +248,158 @@ pub const MAX_QUEUE_SIZE: usize = 128;
 
 pub const QUEUE_FILE: &str = "data/queue.json";
 
+/// Resting limit orders waiting for the spot price to cross. Lives next to
+/// the queue file and follows the same save-before-commit rules.
+pub const ORDER_BOOK_FILE: &str = "data/order_book.json";
+
+/// Whispers held for players who were not around to receive them; see
+/// `store::notices`.
+pub const NOTICES_FILE: &str = "data/notices.json";
+
+/// Open `request` bills between players; see `store::pay_requests`.
+pub const PAY_REQUESTS_FILE: &str = "data/pay_requests.json";
+
+/// Open player-to-player escrows; see `store::escrow`.
+pub const ESCROW_FILE: &str = "data/escrow.json";
+
+/// Price alerts and restock notifications; see `store::alerts`.
+pub const ALERTS_FILE: &str = "data/alerts.json";
+
+/// Append-only JSONL record of every order's lifecycle; see
+/// `store::audit_log`.
+pub const AUDIT_LOG_FILE: &str = "data/audit/orders.jsonl";
+
+/// Size at which the live audit log is rotated to a timestamped sibling.
+pub const AUDIT_LOG_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Append-only double-entry postings for balances and reserves; see
+/// `store::ledger`.
+pub const LEDGER_FILE: &str = "data/ledger.jsonl";
+
+/// Root of all persisted state.
+pub const DATA_DIR: &str = "data";
+
+/// Snapshots of `DATA_DIR`; see `store::snapshot`.
+pub const BACKUPS_DIR: &str = "data/backups";
+
+/// Single-writer lease on `data/`; see `data_lock`.
+pub const DATA_LOCK_FILE: &str = "data/store.lock";
+
+/// How often the lease holder refreshes its heartbeat (seconds).
+pub const LOCK_HEARTBEAT_SECS: u64 = 10;
+
+/// A lease whose heartbeat has not moved for this long belongs to a dead
+/// process (seconds). Three missed heartbeats, so a slow disk is not
+/// mistaken for a crash; also the longest a restart after a crash waits.
+pub const LOCK_STALE_SECS: u64 = 30;
+
+/// Postings kept in memory since the last clean reconciliation. Older ones
+/// are only counted; they stay in `LEDGER_FILE`.
+pub const MAX_PENDING_POSTINGS: usize = 10_000;
+
+/// Largest gap (diamonds) between sum(balances) + sum(reserves) and the
+/// diamonds in storage that still counts as reconciled. Absorbs f64
+/// rounding across many fractional postings; well below the two decimals
+/// any whisper shows.
+pub const RECONCILE_TOLERANCE: f64 = 1e-3;
+
+/// Per-user cap on held notices. The oldest are dropped past it, so a player
+/// who never comes back cannot grow the file without bound.
+pub const MAX_NOTICES_PER_USER: usize = 16;
+
+/// Global cap on resting limit orders. Every sweep prices each resting order
+/// against its pair, so the cap bounds the per-sweep cost as well as memory.
+pub const MAX_ORDER_BOOK_SIZE: usize = 256;
+
+/// Per-user cap on resting limit orders. Kept below `MAX_ORDERS_PER_USER`
+/// so a triggered batch from one user always fits in their queue allowance.
+pub const MAX_LIMIT_ORDERS_PER_USER: usize = 4;
+
+const _: () = assert!(MAX_LIMIT_ORDERS_PER_USER <= MAX_ORDERS_PER_USER);
+
+/// Lifetime of a limit order placed without an explicit TTL (seconds).
+pub const LIMIT_ORDER_DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest TTL a player may request for a limit order (seconds). Bounds how
+/// long a stale order can sit in the book after the player has logged off.
+pub const LIMIT_ORDER_MAX_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+const _: () = assert!(LIMIT_ORDER_DEFAULT_TTL_SECS <= LIMIT_ORDER_MAX_TTL_SECS);
+
+/// Global cap on open payment requests.
+pub const MAX_PAY_REQUESTS: usize = 256;
+
+/// Per-user cap on open payment requests a player has sent. Stops one
+/// player from flooding another with bills.
+pub const MAX_PAY_REQUESTS_PER_USER: usize = 4;
+
+/// How long a payment request can be accepted (seconds).
+pub const PAY_REQUEST_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest memo a `pay` or `request` may carry (characters).
+pub const MAX_PAY_MEMO_CHARS: usize = 64;
+
+/// Global cap on open escrows. Each one can tie up a chest's worth of
+/// shulker space, so this is kept well below the queue cap.
+pub const MAX_ESCROWS: usize = 64;
+
+/// Per-user cap on open escrows a player is selling through.
+pub const MAX_ESCROWS_PER_USER: usize = 3;
+
+/// How long a buyer has to accept a funded escrow before the items go back
+/// to the seller (seconds).
+pub const ESCROW_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Global cap on open price and restock alerts. Every sweep prices each
+/// alert's pair, so the cap bounds the per-sweep cost as well as memory.
+pub const MAX_ALERTS: usize = 512;
+
+/// Per-user cap on open alerts. Each one can end in a whisper, so this
+/// bounds how much unprompted chat one player can queue up for themselves.
+pub const MAX_ALERTS_PER_USER: usize = 5;
+
+/// Lifetime of an alert set without an explicit TTL (seconds). Also the
+/// longest a player may ask for, via `LIMIT_ORDER_MAX_TTL_SECS`.
+pub const ALERT_DEFAULT_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+const _: () = assert!(ALERT_DEFAULT_TTL_SECS <= LIMIT_ORDER_MAX_TTL_SECS);
+
+/// A player who whispered the bot this recently (seconds) counts as online
+/// for transfer, request and alert notifications; anyone else has them held
+/// in `NOTICES_FILE` until their next whisper or join. Read from the rate limiter, so
+/// it cannot outlast the limiter's memory of the player.
+pub const PLAYER_PRESENCE_SECS: u64 = 5 * 60;
+
+const _: () = assert!(PLAYER_PRESENCE_SECS <= RATE_LIMIT_STALE_AFTER_SECS);
+
+/// How long a `quote` stays confirmable (seconds). Long enough to read the
+/// whisper and type `confirm`, short enough that the pool rarely drifts.
+pub const QUOTE_TTL_SECS: u64 = 30;
+
+/// How far (as a fraction of the quoted total) the live price may drift
+/// before `confirm`, or the queued order itself, refuses to honor a quote.
+pub const QUOTE_TOLERANCE: f64 = 0.01;
+
+/// Live quotes a single player may hold at once; issuing another drops
+/// their oldest. Quotes are in-memory only, so this bounds memory per user.
+pub const MAX_QUOTES_PER_USER: usize = 3;
+
+/// One `<item>.json` of OHLC candles per traded item. Derived from the trade
+/// log; deleting the directory rebuilds it from the trades loaded at startup.
+pub const PRICE_HISTORY_DIR: &str = "data/price_history";
+
+/// Hourly candles kept per item: 8 days, so a `history <item> 7d` window is
+/// always fully covered.
+pub const PRICE_HISTORY_HOURLY_CANDLES: usize = 24 * 8;
+
+/// Daily candles kept per item (one year).
+pub const PRICE_HISTORY_DAILY_CANDLES: usize = 365;
+
+/// How often the run loop checks the order book for crossed or expired
+/// limit orders while the book is non-empty (seconds). The book is also
+/// swept right after every processed order, since that is when prices move.
+pub const ORDER_BOOK_SWEEP_INTERVAL_SECS: u64 = 15;
+
 pub const RATE_LIMIT_BASE_COOLDOWN_MS: u64 = 2_000;
 
 /// Time-to-live for cached Mojang UUID lookups (seconds).
@@ -269,6 +427,11 @@ const _: () = assert!(RATE_LIMIT_RESET_AFTER_MS >= RATE_LIMIT_MAX_COOLDOWN_MS);
 /// passes are O(n) on inner HashMaps and dominate nothing on an idle store.
 pub const CLEANUP_INTERVAL_SECS: u64 = 300;
 
+/// Interval between trade-history compaction runs (seconds). A day is only
+/// compacted once it has closed, so the first run after midnight UTC does
+/// the work and the rest find nothing; the first run is at startup.
+pub const TRADE_COMPACTION_INTERVAL_SECS: u64 = 3600;
+
 /// Rate-limiter entries older than this are dropped by the periodic sweep (seconds).
 /// Five minutes is well past any legitimate cooldown window, so the entry
 /// cannot still be throttling a user when it is removed.
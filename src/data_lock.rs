@@ -0,0 +1,524 @@
+//! # Data-directory lease
+//!
+//! Two processes writing the same `data/` directory race each other's
+//! atomic writes and corrupt the ledger; `fsutil::write_atomic` only stops
+//! torn files. At startup the process takes `data/store.lock`, created with
+//! `create_new` so exactly one contender wins, and records who holds it:
+//!
+//! ```json
+//! { "pid": 4242, "hostname": "mc-host", "token": "9f1c…",
+//!   "started_at": "…", "heartbeat": "…" }
+//! ```
+//!
+//! The holder rewrites `heartbeat` every `LOCK_HEARTBEAT_SECS`. A lease whose
+//! heartbeat is older than `LOCK_STALE_SECS` belongs to a process that is gone
+//! (crash, power loss, kill -9) and is taken over. A fresh-looking lease is
+//! watched for up to that long: if the heartbeat moves, another instance is
+//! alive and startup is refused; if it does not, the lease is stale. A crash
+//! therefore costs at most `LOCK_STALE_SECS` on the next start, with no
+//! operator action. `--force-steal-lock` takes the lease without waiting.
+//!
+//! The heartbeat also checks the token: if the file now names someone else
+//! (an operator forced a steal), this process has lost the directory and
+//! exits without saving, so it cannot overwrite the new holder's files.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{DATA_LOCK_FILE, LOCK_HEARTBEAT_SECS, LOCK_STALE_SECS};
+use crate::fsutil::write_atomic;
+
+/// Contents of `data/store.lock`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub hostname: String,
+    /// Random per process, so a PID reused after a reboot, or the same PID
+    /// on another host sharing the directory, is not mistaken for us.
+    pub token: String,
+    pub started_at: DateTime<Utc>,
+    pub heartbeat: DateTime<Utc>,
+}
+
+impl LockOwner {
+    fn ours() -> Self {
+        let mut buf = [0u8; 8];
+        let token = match getrandom::fill(&mut buf) {
+            Ok(()) => u64::from_le_bytes(buf),
+            // Only has to differ from the previous holder's.
+            Err(_) => Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64,
+        };
+        let now = Utc::now();
+        Self {
+            pid: std::process::id(),
+            hostname: hostname(),
+            token: format!("{token:016x}"),
+            started_at: now,
+            heartbeat: now,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let age = (Utc::now() - self.heartbeat).num_seconds().max(0);
+        format!(
+            "pid {} on {} (started {}, last heartbeat {}s ago)",
+            self.pid,
+            self.hostname,
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            age
+        )
+    }
+}
+
+/// Why the lease could not be taken.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another instance is alive and heartbeating.
+    Held(LockOwner),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::Held(owner) => write!(
+                f,
+                "{} is held by another running instance: {}. Stop that instance first; \
+                 if you are certain it is gone, start with --force-steal-lock",
+                DATA_LOCK_FILE,
+                owner.describe()
+            ),
+            LockError::Io(e) => write!(f, "cannot take {}: {}", DATA_LOCK_FILE, e),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+/// The lease this process holds. Cheap to clone so the heartbeat task can
+/// keep its own copy; [`DataLock::release`] is explicit because a clone
+/// dropping must not delete the file.
+#[derive(Debug, Clone)]
+pub struct DataLock {
+    path: PathBuf,
+    owner: LockOwner,
+}
+
+impl DataLock {
+    /// Take `data/store.lock`. `on_wait` is called once if a fresh-looking
+    /// lease has to be watched before it can be judged stale.
+    pub async fn acquire(force: bool, on_wait: impl FnMut(&LockOwner)) -> Result<Self, LockError> {
+        Self::acquire_at(
+            Path::new(DATA_LOCK_FILE),
+            force,
+            Duration::from_secs(LOCK_STALE_SECS),
+            on_wait,
+        )
+        .await
+    }
+
+    /// Path- and timing-parameterized form of `acquire`.
+    pub(crate) async fn acquire_at(
+        path: &Path,
+        force: bool,
+        stale_after: Duration,
+        mut on_wait: impl FnMut(&LockOwner),
+    ) -> Result<Self, LockError> {
+        let mut ours = LockOwner::ours();
+        // Bounded: each pass either creates the file or removes a lease it
+        // judged stale; only a contender re-creating it between the two
+        // makes us go round again. A lease is only removed if the file still
+        // holds the bytes it was judged on: two instances restarting after a
+        // crash both find the same stale lease, and the slower one must not
+        // delete the lease the faster one has just created.
+        for _ in 0..3 {
+            match create_new(path, &ours) {
+                Ok(()) => {
+                    info!(path = %path.display(), pid = ours.pid, "[Lock] Acquired data directory lease");
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                        owner: ours,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let seen = match read_lease(path)? {
+                Some(seen) => seen,
+                // Vanished between the two calls: try again.
+                None => continue,
+            };
+            let holder = match parse_owner(&seen) {
+                Ok(holder) => holder,
+                Err(e) => {
+                    // Torn by a crash mid-write: a live holder rewrites it
+                    // atomically on its next heartbeat, so only an old file
+                    // is treated as abandoned.
+                    if !force && modified_within(path, stale_after) {
+                        return Err(LockError::Io(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "{} is unreadable ({}) and recently written",
+                                path.display(),
+                                e
+                            ),
+                        )));
+                    }
+                    warn!(path = %path.display(), error = %e, "[Lock] Replacing an unreadable lease");
+                    remove_if_unchanged(path, &seen)?;
+                    continue;
+                }
+            };
+
+            if force {
+                warn!(holder = %holder.describe(), "[Lock] --force-steal-lock: taking the lease");
+            } else if holder.hostname == ours.hostname && holder.pid == ours.pid {
+                // Our own PID on our own host cannot be another live process:
+                // the lease outlived a crash and the PID was reused (PID 1 in
+                // a restarted container, typically).
+                info!(holder = %holder.describe(), "[Lock] Lease names our own PID, taking it over");
+            } else if !is_stale(path, &holder, stale_after, &mut on_wait).await? {
+                return Err(LockError::Held(holder));
+            } else {
+                warn!(holder = %holder.describe(), "[Lock] Taking over a stale lease");
+            }
+            if !remove_if_unchanged(path, &seen)? {
+                info!("[Lock] Lease changed while we judged it, looking again");
+                continue;
+            }
+            ours.heartbeat = Utc::now();
+        }
+        Err(LockError::Io(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            format!(
+                "{} keeps being re-created by another process",
+                path.display()
+            ),
+        )))
+    }
+
+    /// Refresh the heartbeat. `Ok(false)` means the file no longer names
+    /// this process: another instance took the lease.
+    pub fn heartbeat(&mut self) -> io::Result<bool> {
+        match read_owner(&self.path)? {
+            Some(holder) if holder.token == self.owner.token => {}
+            _ => return Ok(false),
+        }
+        self.owner.heartbeat = Utc::now();
+        let json = serde_json::to_string_pretty(&self.owner).map_err(io::Error::other)?;
+        write_atomic(&self.path, &json)?;
+        Ok(true)
+    }
+
+    /// Delete the lease on a clean shutdown, unless someone else now holds it.
+    pub fn release(self) {
+        match read_owner(&self.path) {
+            Ok(Some(holder)) if holder.token == self.owner.token => {
+                if let Err(e) = fs::remove_file(&self.path) {
+                    warn!(path = %self.path.display(), error = %e, "[Lock] Failed to remove the lease on shutdown");
+                } else {
+                    info!("[Lock] Released data directory lease");
+                }
+            }
+            _ => warn!("[Lock] Lease no longer ours at shutdown, leaving it in place"),
+        }
+    }
+}
+
+/// Refresh `lock` every `LOCK_HEARTBEAT_SECS`. Losing the lease ends the
+/// process immediately: saving on the way out would overwrite the files
+/// of the instance that now owns `data/`. The read and fsynced rewrite run
+/// on the blocking pool, so a slow disk does not stall a runtime worker.
+pub fn spawn_heartbeat(mut lock: DataLock) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(LOCK_HEARTBEAT_SECS));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let beat = tokio::task::spawn_blocking(move || {
+                let result = lock.heartbeat();
+                (lock, result)
+            })
+            .await;
+            let result = match beat {
+                Ok((returned, result)) => {
+                    lock = returned;
+                    result
+                }
+                Err(e) => {
+                    error!(error = %e, "[Lock] Heartbeat task panicked; the lease will go stale");
+                    return;
+                }
+            };
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    error!(
+                        "[Lock] Another instance took {}; exiting without saving",
+                        DATA_LOCK_FILE
+                    );
+                    eprintln!(
+                        "❌ Another instance took {DATA_LOCK_FILE}; exiting without saving so its data is not overwritten"
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => warn!(error = %e, "[Lock] Heartbeat write failed, will retry"),
+            }
+        }
+    });
+}
+
+/// Whether `holder`'s lease is abandoned: its heartbeat is older than
+/// `stale_after`, or does not move while we watch for that long.
+async fn is_stale(
+    path: &Path,
+    holder: &LockOwner,
+    stale_after: Duration,
+    on_wait: &mut impl FnMut(&LockOwner),
+) -> io::Result<bool> {
+    let stale_after = chrono::Duration::from_std(stale_after).unwrap_or(chrono::Duration::MAX);
+    if Utc::now() - holder.heartbeat > stale_after {
+        return Ok(true);
+    }
+    on_wait(holder);
+    let poll = (stale_after / 10)
+        .to_std()
+        .unwrap_or(Duration::from_secs(1));
+    let deadline = Utc::now() + stale_after;
+    while Utc::now() < deadline {
+        tokio::time::sleep(poll).await;
+        match read_owner(path) {
+            // Heartbeat moved, or a different process holds it now.
+            Ok(Some(now)) if now != *holder => return Ok(false),
+            Ok(Some(_)) => {}
+            // Released or being rewritten: let the caller retry.
+            Ok(None) | Err(_) => return Ok(true),
+        }
+    }
+    Ok(true)
+}
+
+fn create_new(path: &Path, owner: &LockOwner) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    let json = serde_json::to_string_pretty(owner).map_err(io::Error::other)?;
+    f.write_all(json.as_bytes())?;
+    f.sync_all()
+}
+
+/// `Ok(None)` when there is no lease file.
+fn read_owner(path: &Path) -> io::Result<Option<LockOwner>> {
+    read_lease(path)?
+        .map(|bytes| parse_owner(&bytes))
+        .transpose()
+}
+
+/// The lease file's raw bytes, `Ok(None)` when there is none.
+fn read_lease(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn parse_owner(bytes: &[u8]) -> io::Result<LockOwner> {
+    serde_json::from_slice(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Remove the lease only if the file still holds `seen`. `Ok(false)` means
+/// it changed since it was read: a heartbeat, or a contender that has
+/// already replaced it.
+fn remove_if_unchanged(path: &Path, seen: &[u8]) -> io::Result<bool> {
+    match read_lease(path)? {
+        Some(now) if now == seen => {
+            remove_lease(path)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn remove_lease(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn modified_within(path: &Path, window: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .is_some_and(|age| age < window)
+}
+
+/// Best-effort host name for the lease, without a platform dependency.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: Duration = Duration::from_millis(200);
+
+    fn write_owner(path: &Path, owner: &LockOwner) {
+        fs::write(path, serde_json::to_string(owner).unwrap()).unwrap();
+    }
+
+    fn other(heartbeat_age_secs: i64) -> LockOwner {
+        let mut owner = LockOwner::ours();
+        owner.pid = owner.pid.wrapping_add(1);
+        owner.hostname = "elsewhere".into();
+        owner.heartbeat = Utc::now() - chrono::Duration::seconds(heartbeat_age_secs);
+        owner
+    }
+
+    #[tokio::test]
+    async fn acquire_heartbeat_and_release_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+        let mut lock = DataLock::acquire_at(&path, false, FAST, |_| {})
+            .await
+            .unwrap();
+        assert!(lock.heartbeat().unwrap());
+        assert_eq!(read_owner(&path).unwrap().unwrap().token, lock.owner.token);
+        lock.release();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn a_live_holder_is_refused_and_force_steals() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+        let mut holder = DataLock::acquire_at(&path, false, FAST, |_| {})
+            .await
+            .unwrap();
+        // Posing as another process: our own PID would be reclaimed.
+        holder.owner.pid = holder.owner.pid.wrapping_add(1);
+        assert!(holder.heartbeat().unwrap());
+
+        // Keep the holder's heartbeat moving while the contender watches.
+        let beating = holder.clone();
+        let beater = std::thread::spawn(move || {
+            let mut lock = beating;
+            for _ in 0..10 {
+                std::thread::sleep(FAST / 8);
+                lock.heartbeat().unwrap();
+            }
+        });
+        let mut waited = false;
+        let refused = DataLock::acquire_at(&path, false, FAST, |_| waited = true).await;
+        beater.join().unwrap();
+        assert!(matches!(refused, Err(LockError::Held(_))));
+        assert!(waited);
+
+        let mut stolen = DataLock::acquire_at(&path, true, FAST, |_| {})
+            .await
+            .unwrap();
+        let mut old = holder;
+        assert!(
+            !old.heartbeat().unwrap(),
+            "the old holder sees it lost the lease"
+        );
+        assert!(stolen.heartbeat().unwrap());
+    }
+
+    #[tokio::test]
+    async fn a_stale_or_silent_lease_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+
+        write_owner(&path, &other(3600));
+        let mut waited = false;
+        DataLock::acquire_at(&path, false, FAST, |_| waited = true)
+            .await
+            .unwrap()
+            .release();
+        assert!(!waited, "an old heartbeat is stale without watching");
+
+        // Fresh heartbeat but nobody refreshing it: the crash-restart path.
+        write_owner(&path, &other(0));
+        let lock = DataLock::acquire_at(&path, false, FAST, |_| waited = true)
+            .await
+            .unwrap();
+        assert!(waited);
+        assert_eq!(read_owner(&path).unwrap().unwrap().token, lock.owner.token);
+    }
+
+    #[tokio::test]
+    async fn contenders_racing_a_stale_lease_do_not_both_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+        write_owner(&path, &other(3600));
+
+        // Both contenders read the crashed lease and judge it stale; the
+        // first one takes it over before the second gets to remove it.
+        let seen = read_lease(&path).unwrap().unwrap();
+        let first = DataLock::acquire_at(&path, false, FAST, |_| {})
+            .await
+            .unwrap();
+        assert!(!remove_if_unchanged(&path, &seen).unwrap());
+        assert_eq!(
+            read_owner(&path).unwrap().unwrap().token,
+            first.owner.token,
+            "the winner's lease must survive"
+        );
+        assert!(remove_if_unchanged(&path, &read_lease(&path).unwrap().unwrap()).unwrap());
+    }
+
+    #[tokio::test]
+    async fn a_lease_naming_our_own_pid_is_reclaimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+        let mut leftover = LockOwner::ours();
+        leftover.token = "leftover".into();
+        write_owner(&path, &leftover);
+        let mut waited = false;
+        DataLock::acquire_at(&path, false, Duration::from_secs(3600), |_| waited = true)
+            .await
+            .unwrap();
+        assert!(!waited);
+    }
+
+    #[tokio::test]
+    async fn a_recent_unreadable_lease_is_not_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.lock");
+        fs::write(&path, "{\"pid\":").unwrap();
+        let err = DataLock::acquire_at(&path, false, Duration::from_secs(3600), |_| {}).await;
+        assert!(matches!(err, Err(LockError::Io(_))));
+        assert!(
+            DataLock::acquire_at(&path, true, FAST, |_| {})
+                .await
+                .is_ok()
+        );
+    }
+}
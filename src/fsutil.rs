@@ -219,6 +219,22 @@ pub(crate) fn archive_aside(src: &Path, dst: &Path) -> io::Result<()> {
     }
 }
 
+/// Append `line` plus a newline to `path`, creating the file and its parent
+/// directory if needed, and fsync before returning. Used by the append-only
+/// JSONL logs (order audit, ledger), where one `write` per line keeps a
+/// crash from tearing more than the last line.
+pub(crate) fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    f.write_all(format!("{line}\n").as_bytes())?;
+    f.sync_all()
+}
+
 #[cfg(unix)]
 fn sync_parent_dir(parent: &Path) {
     match fs::File::open(parent) {
@@ -29,6 +29,7 @@ mod chat;
 mod cli;
 mod config;
 mod constants;
+mod data_lock;
 mod error;
 mod fsutil;
 mod messages;
@@ -47,14 +48,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // CLI flag parsing — kept tiny on purpose (no clap dependency).
     // Supported:
     //   --validate-only / --dry-run : load + validate config, then exit.
+    //   --resume-dry-run            : print what journal replay / crash-resume would do, then exit.
+    //   --rebuild-ledger [--from <dir> --since <time>]
+    //                               : recompute balances/reserves from data/trades/, then exit.
+    //   --restore [<snapshot>]      : list snapshots, or swap one into data/, then exit.
+    //   --force-steal-lock          : start normally, taking data/store.lock even if held.
     //   --help / -h                 : usage and exit.
     // Only the first non-program arg is considered — if future flags combine
     // (e.g. `--validate-only --quiet`) this scan will need to change, but the
     // current set are all mutually-exclusive "do one thing then exit" actions.
     let args: Vec<String> = std::env::args().collect();
+    let mut force_steal_lock = false;
     if let Some(a) = args.get(1) {
         match a.as_str() {
             "--validate-only" | "--dry-run" => return run_validate_only(),
+            "--resume-dry-run" => return run_resume_dry_run(),
+            "--rebuild-ledger" => return run_rebuild_ledger(&args[2..]).await,
+            "--restore" => return run_restore(args.get(2).map(String::as_str)).await,
+            "--force-steal-lock" => force_steal_lock = true,
             "--help" | "-h" => {
                 print_usage();
                 return Ok(());
@@ -105,6 +116,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Err(e) => warn!(cwd = %cwd, error = %e, "[Main] .env present but failed to parse"),
     }
 
+    // Single-writer lease on data/ — taken before anything loads or writes.
+    // Held across the whole run and released after the tasks join; a crash
+    // leaves it behind and the next start finds it stale.
+    let data_lock = crate::data_lock::DataLock::acquire(force_steal_lock, |holder| {
+        println!(
+            "⏳ {} names {}; waiting up to {}s to see whether it is still alive...",
+            crate::constants::DATA_LOCK_FILE,
+            holder.describe(),
+            crate::constants::LOCK_STALE_SECS
+        );
+    })
+    .await
+    .inspect_err(|e| {
+        error!("[Main] {e}");
+        eprintln!("❌ {e}");
+    })?;
+    crate::data_lock::spawn_heartbeat(data_lock.clone());
+
     println!("🚀 Starting bot application...");
     println!("📋 To view logs in another terminal, run:");
     println!("   PowerShell: Get-Content data\\logs\\store.log -Wait -Tail 20");
@@ -295,6 +324,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ))
     .await;
 
+    data_lock.release();
     if had_error {
         std::process::exit(1);
     }
@@ -311,9 +341,249 @@ fn print_usage() {
     println!("OPTIONS:");
     println!("    --validate-only, --dry-run   Load and validate data/config.json, then exit");
     println!("                                 without connecting to the server");
+    println!("    --resume-dry-run             Show what journal replay and crash-resume");
+    println!("                                 would do with data/journal.json and");
+    println!("                                 data/current_trade.json, then exit");
+    println!("    --rebuild-ledger             Recompute balances and reserves from");
+    println!("                                 data/trades/ and repair data/users/ and");
+    println!("                                 data/pairs/ after confirmation. Refused");
+    println!("                                 while the store is running");
+    println!("        --from <snapshot> | --from <dir> --since <time>");
+    println!("                                 Start from the users/ and pairs/ of a");
+    println!("                                 snapshot, or of a copy taken at <time>");
+    println!("                                 (RFC 3339), instead of from zero");
+    println!("    --restore [<snapshot>|latest]");
+    println!("                                 Validate a data/backups/ snapshot and swap");
+    println!("                                 it in after confirmation, keeping the");
+    println!("                                 current data/ as a pre-restore snapshot.");
+    println!("                                 Without an argument, list snapshots");
+    println!("    --force-steal-lock           Start even if data/store.lock names another");
+    println!("                                 live instance. Only when that instance is");
+    println!("                                 certainly gone: two writers corrupt data/");
     println!("    -h, --help                   Show this help");
 }
 
+/// Print the replay plan for a leftover `data/journal.json` and the
+/// crash-resume plan for `data/current_trade.json` without acting on or
+/// archiving either, so an operator can see what the
+/// next start will do before it does.
+fn run_resume_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let reports = [
+        crate::store::journal_replay::dry_run_report(),
+        crate::store::resume::dry_run_report(),
+    ];
+    let mut failed = None;
+    for report in reports {
+        match report {
+            Ok(lines) => {
+                for line in lines {
+                    println!("{line}");
+                }
+            }
+            Err(e) => {
+                eprintln!("❌ {e}");
+                failed = Some(e);
+            }
+        }
+    }
+    match failed {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// List the snapshots in `data/backups/`, or stage `arg`, show what it holds
+/// and swap it into `data/` after the operator confirms. Takes the data
+/// lease, so it refuses to run while the store is up.
+async fn run_restore(arg: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::constants::{BACKUPS_DIR, DATA_DIR};
+    use crate::store::snapshot;
+    use std::path::Path;
+
+    let Some(arg) = arg else {
+        let snapshots = snapshot::list_in_dir(Path::new(BACKUPS_DIR))?;
+        if snapshots.is_empty() {
+            println!("No snapshots in {BACKUPS_DIR}");
+        }
+        for s in &snapshots {
+            println!("  {}", s.describe());
+        }
+        return Ok(());
+    };
+    let target = snapshot::resolve_in_dir(Path::new(BACKUPS_DIR), arg)?;
+    let lock = crate::data_lock::DataLock::acquire(false, |holder| {
+        println!("⏳ Waiting to see whether {} is still alive...", holder.describe());
+    })
+    .await?;
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        println!("🔍 Validating {} ...", target.describe());
+        let summary = snapshot::stage_in_dir(Path::new(DATA_DIR), &target.path)?;
+        for line in &summary {
+            println!("   {line}");
+        }
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt(format!(
+                "Replace data/ with the snapshot taken {}?",
+                target.manifest.taken_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            snapshot::discard_staging_in_dir(Path::new(DATA_DIR))?;
+            println!("Nothing replaced");
+            return Ok(());
+        }
+        let pre = snapshot::swap_in_dir(
+            Path::new(DATA_DIR),
+            Path::new(BACKUPS_DIR),
+            chrono::Utc::now(),
+        )?;
+        println!("✅ Restored. The previous data/ is kept as {}", pre.describe());
+        Ok(())
+    })();
+    lock.release();
+    result
+}
+
+/// Replay the trade log, checked against the ledger's payment and refund
+/// postings, show every balance and reserve that disagrees with
+/// `data/users/` and `data/pairs/`, and write the replayed values after the
+/// operator confirms. See `store::rebuild` for what the replay can and
+/// cannot recover.
+async fn run_rebuild_ledger(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::store::rebuild::Baseline;
+    use crate::types::{Pair, User};
+    use std::path::Path;
+
+    let mut from = None;
+    let mut since = None;
+    let mut rest = args.iter();
+    while let Some(a) = rest.next() {
+        match (a.as_str(), rest.next()) {
+            ("--from", Some(v)) => from = Some(v.clone()),
+            ("--since", Some(v)) => since = Some(v.clone()),
+            _ => return Err(format!("unexpected argument to --rebuild-ledger: {a}").into()),
+        }
+    }
+    let baseline = match (from, since) {
+        (None, None) => Baseline::default(),
+        (None, Some(_)) => return Err("--since needs --from".into()),
+        (Some(dir), since) => {
+            // A snapshot under data/backups/ (or its path) knows when it was
+            // taken; any other directory needs --since.
+            let snapshot = crate::store::snapshot::resolve_in_dir(
+                Path::new(crate::constants::BACKUPS_DIR),
+                &dir,
+            )
+            .ok();
+            let since = match (since, &snapshot) {
+                (Some(since), _) => chrono::DateTime::parse_from_rfc3339(&since)
+                    .map_err(|e| format!("--since {since}: {e}"))?
+                    .with_timezone(&chrono::Utc),
+                (None, Some(s)) => s.manifest.taken_at,
+                (None, None) => {
+                    return Err(format!("{dir} is not a snapshot; give --since as well").into());
+                }
+            };
+            let dir = snapshot.map_or_else(|| std::path::PathBuf::from(&dir), |s| s.path);
+            let dir = dir.as_path();
+            let users = User::load_all_in_dir(&dir.join("users"))?;
+            let pairs = Pair::load_all_in_dir(&dir.join("pairs"))?;
+            println!(
+                "📂 Baseline: {} user(s) and {} pair(s) from {} as of {}",
+                users.len(),
+                pairs.len(),
+                dir.display(),
+                since.to_rfc3339()
+            );
+            Baseline::from_snapshot(&users, &pairs, since)
+        }
+    };
+
+    // The store would overwrite the repaired files on its next save, so the
+    // rebuild needs the same lease a running store holds.
+    let lock = crate::data_lock::DataLock::acquire(false, |holder| {
+        println!("⏳ Waiting to see whether {} is still alive...", holder.describe());
+    })
+    .await?;
+    let result = rebuild_ledger_locked(baseline);
+    lock.release();
+    result
+}
+
+fn rebuild_ledger_locked(
+    baseline: crate::store::rebuild::Baseline,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::store::rebuild;
+    use crate::types::{Pair, User};
+    use std::path::Path;
+
+    let trades = rebuild::load_trades()?;
+    let postings =
+        crate::store::ledger::Ledger::read_postings(Path::new(crate::constants::LEDGER_FILE))?;
+    let users = User::load_all()?;
+    let pairs = Pair::load_all()?;
+
+    let replay = rebuild::replay(baseline, &trades, &postings);
+    println!(
+        "🔁 Replayed {} trade(s) and {} payment(s)",
+        replay.trades, replay.payments
+    );
+    if replay.ledger_only > 0 {
+        println!(
+            "ℹ️  {} payment(s) and refund(s) have no trade and were replayed from {}",
+            replay.ledger_only,
+            crate::constants::LEDGER_FILE
+        );
+    }
+    if replay.estimated > 0 {
+        println!(
+            "⚠️  {} buy(s) predate balance tracking in the trade log and were assumed paid \
+             in physical diamonds; check those players' balances by hand",
+            replay.estimated
+        );
+    }
+    let diffs = rebuild::diff(&replay, &users, &pairs);
+    if diffs.is_empty() {
+        println!("✅ Balances and reserves on disk match the trade log");
+        return Ok(());
+    }
+    for d in &diffs {
+        println!("  {}", d.describe(&users));
+    }
+    let (fixed_users, fixed_pairs, skipped) = rebuild::apply(&diffs, &users, &pairs);
+    for line in &skipped {
+        println!("⚠️  {line}");
+    }
+    if fixed_users.is_empty() && fixed_pairs.is_empty() {
+        return Ok(());
+    }
+    let confirmed = dialoguer::Confirm::new()
+        .with_prompt(format!(
+            "Write {} user(s) and {} pair(s)?",
+            fixed_users.len(),
+            fixed_pairs.len()
+        ))
+        .default(false)
+        .interact()?;
+    if !confirmed {
+        println!("Nothing written");
+        return Ok(());
+    }
+    for user in &fixed_users {
+        user.save()?;
+    }
+    for pair in &fixed_pairs {
+        pair.save()?;
+    }
+    println!(
+        "✅ Wrote {} user(s) and {} pair(s); run \"Audit state\" after the next start",
+        fixed_users.len(),
+        fixed_pairs.len()
+    );
+    Ok(())
+}
+
 /// Load config, run validation, print result, and exit without connecting.
 ///
 /// Useful for CI checks or for operators to sanity-check a config edit before
@@ -346,6 +616,17 @@ fn run_validate_only() -> Result<(), Box<dyn std::error::Error>> {
             println!("   max_orders:          {}", cfg.max_orders);
             println!("   max_trades_in_memory: {}", cfg.max_trades_in_memory);
             println!("   autosave_interval_secs: {}", cfg.autosave_interval_secs);
+            match cfg.trade_retention_days {
+                Some(d) => println!("   trade_retention_days: {}", d),
+                None => println!("   trade_retention_days: <keep all>"),
+            }
+            match cfg.snapshot_interval_secs {
+                Some(s) => println!(
+                    "   snapshots: every {}s, keep {}",
+                    s, cfg.snapshot_keep
+                ),
+                None => println!("   snapshots: off (keep {})", cfg.snapshot_keep),
+            }
             Ok(())
         }
         Err(e) => {
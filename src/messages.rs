@@ -146,6 +146,47 @@ pub enum QueuedOrderType {
         /// Specific amount to withdraw, or `None` to withdraw the full balance.
         amount: Option<f64>,
     },
+    /// Basket `buy`: every line in one trade, paid from the balance.
+    /// `QueuedOrder::item` / `quantity` hold the first item and the total
+    /// item count, for queue listings.
+    BuyBasket {
+        lines: Vec<TradeItem>,
+    },
+    /// Basket `sell`: the player offers every line in one trade and the
+    /// payout goes to the balance.
+    SellBasket {
+        lines: Vec<TradeItem>,
+    },
+    /// `sell all <item>`: the player offers any amount of
+    /// `QueuedOrder::item`, at most `QueuedOrder::quantity`, and the payout
+    /// goes to the balance.
+    SellAll,
+    /// `swap`: sell `QueuedOrder::quantity` of `QueuedOrder::item` and spend
+    /// the payout on `to_item`, in a single trade.
+    Swap {
+        to_item: String,
+    },
+    /// `lp add`: the player trades in `QueuedOrder::quantity` items and the
+    /// matching diamonds are debited from their balance.
+    AddLiquidity,
+    /// `lp remove`: burn shares of `QueuedOrder::item`'s pool.
+    RemoveLiquidity {
+        /// Shares to burn, or `None` for the player's whole position.
+        shares: Option<f64>,
+    },
+    /// `escrow sell`: the seller trades `QueuedOrder::quantity` of
+    /// `QueuedOrder::item` into escrow.
+    EscrowDeposit {
+        escrow_id: u64,
+    },
+    /// `escrow accept`: the buyer pays and receives the escrowed items.
+    EscrowRelease {
+        escrow_id: u64,
+    },
+    /// `escrow cancel` or expiry: the escrowed items go back to the seller.
+    EscrowReturn {
+        escrow_id: u64,
+    },
 }
 
 /// An item with quantity, used in trade negotiations.
@@ -173,6 +214,16 @@ pub struct ChestSyncReport {
     pub amounts: [i32; crate::constants::DOUBLE_CHEST_SLOTS],
 }
 
+/// Outcome of [`BotInstruction::ReplayJournalEntry`].
+#[derive(Debug, Clone)]
+pub struct JournalReplayReport {
+    /// Where the bot found the stranded shulker.
+    pub found_at: crate::store::journal::ShulkerLocation,
+    /// The recorded slot's count after the shulker is back; every other
+    /// slot is `-1`.
+    pub sync: ChestSyncReport,
+}
+
 /// Actions that can be performed on a chest.
 #[derive(Debug, Clone)]
 pub enum ChestAction {
@@ -221,6 +272,9 @@ pub enum BotMessage {
         /// Raw command text as received, with the whisper prefix already stripped.
         command: String,
     },
+    /// A join broadcast named `player_name`; the Store delivers anything it
+    /// was holding for them.
+    PlayerJoined { player_name: String },
 }
 
 /// Messages from CLI to Store.
@@ -265,6 +319,29 @@ pub enum CliMessage {
         item_name: String,
         respond_to: oneshot::Sender<Result<(), String>>,
     },
+    /// Set or clear a pair's `buy_fee` / `sell_fee` overrides. `None`
+    /// clears the override so the pair falls back to the global fee.
+    /// Applied live; the next priced order uses the new rates.
+    SetPairFees {
+        item_name: String,
+        buy_fee: Option<f64>,
+        sell_fee: Option<f64>,
+        respond_to: oneshot::Sender<Result<(), String>>,
+    },
+    /// Fetch one item's OHLC candles at `resolution`, oldest first, for CSV
+    /// export. `Err` when the item is not a pair.
+    QueryPriceHistory {
+        item_name: String,
+        resolution: crate::store::price_history::Resolution,
+        respond_to: oneshot::Sender<Result<Vec<crate::store::price_history::Candle>, String>>,
+    },
+    /// Switch a pair to a different pricing curve. Applied live; reserves
+    /// are untouched, only how they are turned into prices changes.
+    SetPairCurve {
+        item_name: String,
+        curve: crate::store::curve::PricingCurve,
+        respond_to: oneshot::Sender<Result<(), String>>,
+    },
     QueryStorage {
         respond_to: oneshot::Sender<crate::types::Storage>,
     },
@@ -296,6 +373,14 @@ pub enum CliMessage {
     ClearStuckOrder {
         respond_to: oneshot::Sender<Option<String>>,
     },
+    /// Replay a leftover shulker operation. `path` names a journal file (such
+    /// as a `journal.leftover-*.json` archive); `None` replays the entry found
+    /// at startup if its automatic replay did not finish. Returns the report
+    /// lines.
+    ReplayJournal {
+        path: Option<String>,
+        respond_to: oneshot::Sender<Result<Vec<String>, String>>,
+    },
 }
 
 /// Instructions from Store to Bot.
@@ -363,6 +448,20 @@ pub enum BotInstruction {
         node_position: crate::types::Position,
         respond_to: oneshot::Sender<Result<(), String>>,
     },
+    /// Finish a shulker operation a crash left half-done: find the shulker
+    /// `entry` points at (station, inventory, or already in its slot), put it
+    /// back in `entry.slot_index` of `target_chest`, and count that slot.
+    ReplayJournalEntry {
+        entry: crate::store::journal::JournalEntry,
+        target_chest: Chest,
+        node_position: crate::types::Position,
+        respond_to: oneshot::Sender<Result<JournalReplayReport, String>>,
+    },
+    /// Count the items the bot holds (main inventory, hotbar and cursor),
+    /// one entry per item. Crash-resume returns only these to storage.
+    CountInventory {
+        respond_to: oneshot::Sender<Result<Vec<TradeItem>, String>>,
+    },
     /// Restart the bot.
     ///
     /// Fire-and-forget: no response channel because the bot task tears itself
@@ -401,6 +500,30 @@ mod tests {
         assert!(matches!(roundtrip_order(&order), QueuedOrderType::Sell));
     }
 
+    #[test]
+    fn queued_order_type_basket_preserves_lines() {
+        let order = QueuedOrderType::SellBasket {
+            lines: vec![
+                TradeItem {
+                    item: "cobblestone".into(),
+                    amount: 64,
+                },
+                TradeItem {
+                    item: "glass".into(),
+                    amount: 32,
+                },
+            ],
+        };
+        match roundtrip_order(&order) {
+            QueuedOrderType::SellBasket { lines } => {
+                let got: Vec<(&str, i32)> =
+                    lines.iter().map(|l| (l.item.as_str(), l.amount)).collect();
+                assert_eq!(got, vec![("cobblestone", 64), ("glass", 32)]);
+            }
+            other => panic!("expected SellBasket, got {other:?}"),
+        }
+    }
+
     #[test]
     fn queued_order_type_deposit_with_amount_preserves_value() {
         let order = QueuedOrderType::Deposit { amount: Some(12.5) };
@@ -0,0 +1,371 @@
+//! Open price alerts and restock notifications.
+//!
+//! `alert <item> below|above <price>` watches a pair's indicative spot buy
+//! price and `notify <item> restock` watches its `item_stock` leave zero.
+//! Each alert fires once: `handlers::alert::sweep` removes it and tells the
+//! player, whispering them if they are around and otherwise holding the
+//! notice for their next whisper or join. Alerts nobody triggered expire at
+//! `expires_at`.
+//!
+//! The set is rewritten to `ALERTS_FILE` on every change, before the
+//! in-memory view changes, like payment requests: a failed write leaves
+//! both untouched, so a restart never loses or repeats an alert. A corrupt
+//! file is quarantined and the store starts with no alerts.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{ALERTS_FILE, MAX_ALERTS, MAX_ALERTS_PER_USER};
+use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
+
+/// Per-process disambiguator for quarantined alert files. Mirrors the
+/// same-named static in `queue.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// What an alert waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AlertTrigger {
+    /// The spot buy price is at or below `price` diamonds per item.
+    Below { price: f64 },
+    /// The spot buy price is at or above `price` diamonds per item.
+    Above { price: f64 },
+    /// The pair has stock again.
+    Restock,
+}
+
+impl AlertTrigger {
+    /// Whether a pair quoting `spot` per item (`None` when it cannot quote)
+    /// with `item_stock` items meets the trigger.
+    pub fn is_met(self, spot: Option<f64>, item_stock: i32) -> bool {
+        match self {
+            Self::Below { price } => spot.is_some_and(|s| s <= price),
+            Self::Above { price } => spot.is_some_and(|s| s >= price),
+            Self::Restock => item_stock > 0,
+        }
+    }
+}
+
+/// An alert owned by one player. Persisted as part of [`Alerts`]; any field
+/// rename is a persisted-format break.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub user_uuid: String,
+    /// Name at the time the alert was set; used to whisper the player.
+    pub username: String,
+    pub item: String,
+    pub trigger: AlertTrigger,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Human-readable summary, e.g. `cobblestone below 0.5` or
+    /// `cobblestone restock`.
+    pub fn description(&self) -> String {
+        match self.trigger {
+            AlertTrigger::Below { price } => format!("{} below {}", self.item, price),
+            AlertTrigger::Above { price } => format!("{} above {}", self.item, price),
+            AlertTrigger::Restock => format!("{} restock", self.item),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlertsPersist {
+    alerts: Vec<Alert>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+pub struct Alerts {
+    /// `None` for test stores: alerts are kept in memory only.
+    path: Option<PathBuf>,
+    alerts: Vec<Alert>,
+    /// Persisted so ids never recycle across restarts. Shown as `A<id>`.
+    next_id: u64,
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self {
+            path: None,
+            alerts: Vec::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl Alerts {
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(Path::new(ALERTS_FILE))
+    }
+
+    /// Path-parameterized load. A corrupt file is quarantined to an
+    /// `alerts.corrupt-<unix_ms>-<seq>.json` sibling and the set starts
+    /// empty, like the queue and the order book.
+    pub(crate) fn load_from(path: &Path) -> io::Result<Self> {
+        let mut loaded = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(loaded),
+            Err(e) => return Err(e),
+        };
+        match serde_json::from_str::<AlertsPersist>(&contents) {
+            Ok(data) => {
+                info!(
+                    "[Alerts] Loaded {} alert(s) from {:?} (next_id={})",
+                    data.alerts.len(),
+                    path,
+                    data.next_id
+                );
+                loaded.alerts = data.alerts;
+                loaded.next_id = data.next_id;
+            }
+            Err(parse_err) => {
+                let archived = pick_archive_path(path.parent(), "alerts", "corrupt", &ARCHIVE_SEQ)?;
+                archive_aside(path, &archived)?;
+                error!(
+                    "[Alerts] ALERTS LOST: corrupt {:?} moved to {:?}; parse error: {}",
+                    path, archived, parse_err
+                );
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Write `alerts` with `next_id`, which may differ from the current
+    /// state when the caller is persisting a projection.
+    fn write_projection(&self, alerts: &[Alert], next_id: u64) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = AlertsPersist {
+            alerts: alerts.to_vec(),
+            next_id,
+        };
+        let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+        write_atomic(path, &json)
+    }
+
+    /// Set an alert on `item` for `user_uuid`, valid for `ttl`.
+    ///
+    /// `Err(message)` when a cap is hit or the save fails; the message is
+    /// safe to whisper.
+    pub fn open(
+        &mut self,
+        user_uuid: &str,
+        username: &str,
+        item: &str,
+        trigger: AlertTrigger,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Result<Alert, String> {
+        if self.alerts.len() >= MAX_ALERTS {
+            warn!(
+                "[Alerts] Rejected alert from {} ({}): {} open, cap {}",
+                username,
+                user_uuid,
+                self.alerts.len(),
+                MAX_ALERTS
+            );
+            return Err("Too many alerts set right now. Please try again later.".to_string());
+        }
+        let owned = self.for_user(user_uuid).len();
+        if owned >= MAX_ALERTS_PER_USER {
+            return Err(format!(
+                "You already have {} alerts (max {}). Remove one with 'alert cancel A<id>'.",
+                owned, MAX_ALERTS_PER_USER
+            ));
+        }
+
+        let alert = Alert {
+            id: self.next_id,
+            user_uuid: user_uuid.to_string(),
+            username: username.to_string(),
+            item: item.to_string(),
+            trigger,
+            created_at: now,
+            expires_at: now + ttl,
+        };
+        let mut projected = self.alerts.clone();
+        projected.push(alert.clone());
+        if let Err(e) = self.write_projection(&projected, alert.id + 1) {
+            error!(
+                "[Alerts] Failed to persist alert A{}: {} (not set)",
+                alert.id, e
+            );
+            return Err("Alerts are temporarily unavailable, please retry.".to_string());
+        }
+        self.alerts = projected;
+        self.next_id = alert.id + 1;
+        info!(
+            "[Alerts] A{} set: {} watches {} (expires_at={})",
+            alert.id,
+            alert.username,
+            alert.description(),
+            alert.expires_at.to_rfc3339()
+        );
+        Ok(alert)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Alert> {
+        self.alerts.iter().find(|a| a.id == id)
+    }
+
+    /// Remove alert `id` regardless of owner (trigger and expiry paths). The
+    /// shrunken set is written before the in-memory removal so a failed save
+    /// leaves both views unchanged.
+    pub fn remove(&mut self, id: u64) -> Result<Alert, String> {
+        let pos = self
+            .alerts
+            .iter()
+            .position(|a| a.id == id)
+            .ok_or_else(|| format!("Alert A{} not found.", id))?;
+        let projected: Vec<Alert> = self.alerts.iter().filter(|a| a.id != id).cloned().collect();
+        if let Err(e) = self.write_projection(&projected, self.next_id) {
+            error!(
+                "[Alerts] Failed to persist removal of A{}: {} (leaving it set)",
+                id, e
+            );
+            return Err(format!("failed to persist alerts: {}", e));
+        }
+        Ok(self.alerts.remove(pos))
+    }
+
+    /// Remove the caller's own alert `id`. Anyone else's id reads as not
+    /// found, so ids reveal nothing about other players' alerts.
+    pub fn cancel(&mut self, user_uuid: &str, id: u64) -> Result<Alert, String> {
+        if self.get(id).is_none_or(|a| a.user_uuid != user_uuid) {
+            return Err(format!("Alert A{} not found. 'alert' lists yours.", id));
+        }
+        self.remove(id)
+            .map_err(|_| "Could not remove the alert right now, please retry.".to_string())
+    }
+
+    /// The alerts `user_uuid` has set, oldest first.
+    pub fn for_user(&self, user_uuid: &str) -> Vec<&Alert> {
+        self.alerts
+            .iter()
+            .filter(|a| a.user_uuid == user_uuid)
+            .collect()
+    }
+
+    pub fn expired_ids(&self, now: DateTime<Utc>) -> Vec<u64> {
+        self.alerts
+            .iter()
+            .filter(|a| a.is_expired(now))
+            .map(|a| a.id)
+            .collect()
+    }
+
+    /// Ids of unexpired alerts for which `met` holds, oldest first.
+    pub fn triggered(&self, now: DateTime<Utc>, met: impl Fn(&Alert) -> bool) -> Vec<u64> {
+        self.alerts
+            .iter()
+            .filter(|a| !a.is_expired(now) && met(a))
+            .map(|a| a.id)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(alerts: &mut Alerts, user: &str, trigger: AlertTrigger) -> Result<Alert, String> {
+        alerts.open(
+            user,
+            user,
+            "cobblestone",
+            trigger,
+            Utc::now(),
+            Duration::hours(1),
+        )
+    }
+
+    #[test]
+    fn alerts_round_trip_through_disk_and_ids_never_recycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.json");
+        let mut alerts = Alerts::load_from(&path).unwrap();
+        let first = open(&mut alerts, "u1", AlertTrigger::Below { price: 0.5 }).unwrap();
+        let second = open(&mut alerts, "u2", AlertTrigger::Restock).unwrap();
+        alerts.remove(second.id).unwrap();
+
+        let mut reloaded = Alerts::load_from(&path).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded.get(first.id), Some(&first));
+        assert_eq!(
+            open(&mut reloaded, "u1", AlertTrigger::Restock).unwrap().id,
+            3
+        );
+    }
+
+    #[test]
+    fn per_user_cap_and_cancel_only_touch_the_callers_alerts() {
+        let mut alerts = Alerts::default();
+        for _ in 0..MAX_ALERTS_PER_USER {
+            open(&mut alerts, "u1", AlertTrigger::Restock).unwrap();
+        }
+        assert!(open(&mut alerts, "u1", AlertTrigger::Restock).is_err());
+        let theirs = open(&mut alerts, "u2", AlertTrigger::Restock).unwrap();
+        assert!(alerts.cancel("u1", theirs.id).is_err());
+        assert!(alerts.cancel("u2", theirs.id).is_ok());
+        assert!(alerts.for_user("u2").is_empty());
+    }
+
+    #[test]
+    fn triggers_compare_spot_price_and_stock() {
+        let below = AlertTrigger::Below { price: 0.5 };
+        let above = AlertTrigger::Above { price: 0.5 };
+        assert!(below.is_met(Some(0.5), 10));
+        assert!(!below.is_met(Some(0.6), 10));
+        assert!(!below.is_met(None, 10));
+        assert!(above.is_met(Some(0.6), 10));
+        assert!(!above.is_met(Some(0.4), 10));
+        assert!(AlertTrigger::Restock.is_met(None, 1));
+        assert!(!AlertTrigger::Restock.is_met(Some(1.0), 0));
+    }
+
+    #[test]
+    fn expired_alerts_never_trigger() {
+        let mut alerts = Alerts::default();
+        let a = open(&mut alerts, "u1", AlertTrigger::Restock).unwrap();
+        assert_eq!(alerts.triggered(Utc::now(), |_| true), vec![a.id]);
+        assert!(alerts.triggered(a.expires_at, |_| true).is_empty());
+        assert_eq!(alerts.expired_ids(a.expires_at), vec![a.id]);
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.json");
+        fs::write(&path, "{not json").unwrap();
+        let alerts = Alerts::load_from(&path).unwrap();
+        assert!(alerts.is_empty());
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,383 @@
+//! Append-only order audit log.
+//!
+//! `data/trades/` only holds committed trades, and `data/orders.json` is
+//! cleared on every start, so an order that was cancelled, rolled back or
+//! cleared by an operator used to leave nothing behind but log lines. Every
+//! lifecycle event of a queued order is appended here as one JSON line keyed
+//! by the order id:
+//!
+//! | Event       | Written by                                             |
+//! | ----------- | ------------------------------------------------------ |
+//! | `queued`    | `OrderQueue::add*` once the queue file is saved        |
+//! | `cancelled` | `OrderQueue::cancel` once the queue file is saved      |
+//! | `started`   | `Store::process_next_order` after `pop_committed`      |
+//! | `phase`     | `Store::advance_trade`, and each multi-session restart |
+//! | `rollback`  | `rollback::deposit_transfers` during an active trade   |
+//! | `finished`  | `Store::process_next_order` when the handler returns   |
+//! | `resumed`   | `resume::resume` for a trade interrupted by a crash    |
+//! | `operator`  | CLI actions on an order, e.g. "Clear stuck order"      |
+//!
+//! Writes are best-effort: a failed append is logged and the order goes on,
+//! the same trade-off as the chat operator audit. Once the live file reaches
+//! `AUDIT_LOG_MAX_BYTES` it is renamed to `orders-<unix_ms>-<seq>.jsonl`
+//! next to it and a new file is started. Archives are never deleted by the
+//! store. [`story`] reads the archives oldest first and then the live file,
+//! and backs the CLI entry "View order audit trail".
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::rollback::RollbackResult;
+use crate::constants::{AUDIT_LOG_FILE, AUDIT_LOG_MAX_BYTES};
+use crate::fsutil::{append_line, archive_aside};
+
+/// Per-process disambiguator for rotated file names. Mirrors the same-named
+/// statics in `queue.rs` and `journal.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// One line of the audit log. Field names are the on-disk format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub ts: DateTime<Utc>,
+    pub order_id: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    Queued {
+        user_uuid: String,
+        username: String,
+        description: String,
+    },
+    Cancelled {
+        by: String,
+        reason: String,
+    },
+    Started {
+        description: String,
+    },
+    /// A `TradeState` transition; `detail` carries the rollback reason or
+    /// the session number where there is one.
+    Phase {
+        phase: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    /// The counters of a `RollbackResult`, tagged with the handler context.
+    Rollback {
+        context: String,
+        items_returned: i32,
+        items_unplanned: i32,
+        items_stuck_on_bot: i32,
+        operations_succeeded: usize,
+        operations_failed: usize,
+    },
+    Finished {
+        ok: bool,
+        detail: String,
+    },
+    /// Crash-resume of the order; `outcome` is the resume report.
+    Resumed {
+        phase: String,
+        outcome: Vec<String>,
+    },
+    Operator {
+        action: String,
+        detail: String,
+    },
+}
+
+impl AuditEvent {
+    pub fn rollback(context: &str, result: &RollbackResult) -> Self {
+        AuditEvent::Rollback {
+            context: context.to_string(),
+            items_returned: result.items_returned,
+            items_unplanned: result.items_unplanned,
+            items_stuck_on_bot: result.items_stuck_on_bot,
+            operations_succeeded: result.operations_succeeded,
+            operations_failed: result.operations_failed,
+        }
+    }
+
+    /// One-line rendering for the CLI viewer.
+    pub fn describe(&self) -> String {
+        match self {
+            AuditEvent::Queued {
+                username,
+                user_uuid,
+                description,
+            } => format!("queued by {} ({}): {}", username, user_uuid, description),
+            AuditEvent::Cancelled { by, reason } => format!("cancelled by {}: {}", by, reason),
+            AuditEvent::Started { description } => format!("started: {}", description),
+            AuditEvent::Phase {
+                phase,
+                detail: Some(detail),
+            } => format!("phase {} ({})", phase, detail),
+            AuditEvent::Phase {
+                phase,
+                detail: None,
+            } => format!("phase {}", phase),
+            AuditEvent::Rollback {
+                context,
+                items_returned,
+                items_unplanned,
+                items_stuck_on_bot,
+                operations_succeeded,
+                operations_failed,
+            } => format!(
+                "rollback {}: {} returned, {} unplanned, {} stuck on bot ({} step(s) ok, {} failed)",
+                context,
+                items_returned,
+                items_unplanned,
+                items_stuck_on_bot,
+                operations_succeeded,
+                operations_failed
+            ),
+            AuditEvent::Finished { ok: true, detail } => format!("finished: {}", detail),
+            AuditEvent::Finished { ok: false, detail } => format!("FAILED: {}", detail),
+            AuditEvent::Resumed { phase, outcome } => {
+                format!("resumed from {}: {}", phase, outcome.join(" "))
+            }
+            AuditEvent::Operator { action, detail } => {
+                format!("operator {}: {}", action, detail)
+            }
+        }
+    }
+}
+
+impl AuditRecord {
+    pub fn describe(&self) -> String {
+        format!(
+            "[{}] #{} {}",
+            self.ts.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.order_id,
+            self.event.describe()
+        )
+    }
+}
+
+/// Handle to the audit log. Cheap to clone; the queue and the store each
+/// hold one. A disabled handle, used by test stores, drops every record.
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    /// The production log at `AUDIT_LOG_FILE`.
+    pub fn open() -> Self {
+        Self::at(AUDIT_LOG_FILE)
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Append one event for `order_id`. Never fails the caller.
+    pub fn record(&self, order_id: u64, event: AuditEvent) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let record = AuditRecord {
+            ts: Utc::now(),
+            order_id,
+            event,
+        };
+        if let Err(e) = append(path, &record, AUDIT_LOG_MAX_BYTES) {
+            warn!(
+                order_id,
+                path = %path.display(),
+                error = %e,
+                "[Audit] Failed to append order audit record"
+            );
+        }
+    }
+}
+
+/// Rotate `path` if it has reached `max_bytes`, then append `record` and
+/// fsync.
+fn append(path: &Path, record: &AuditRecord, max_bytes: u64) -> io::Result<()> {
+    if fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes) {
+        let archived = rotate(path)?;
+        info!("[Audit] Rotated {:?} to {:?}", path, archived);
+    }
+    let line = serde_json::to_string(record).map_err(io::Error::other)?;
+    append_line(path, &line)
+}
+
+fn rotate(path: &Path) -> io::Result<PathBuf> {
+    let unix_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let seq = ARCHIVE_SEQ.fetch_add(1, Ordering::Relaxed);
+    let archived = path.with_file_name(format!("{}-{unix_ms}-{seq}.jsonl", stem(path)));
+    archive_aside(path, &archived)?;
+    Ok(archived)
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "orders".to_string())
+}
+
+/// Rotated files next to `path`, oldest first.
+fn archives(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => dir,
+        None => Path::new("."),
+    };
+    let prefix = format!("{}-", stem(path));
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut found: Vec<((u128, u64), PathBuf)> = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(key) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".jsonl"))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(ms, seq)| Some((ms.parse().ok()?, seq.parse().ok()?)))
+        else {
+            continue;
+        };
+        found.push((key, entry.path()));
+    }
+    found.sort();
+    Ok(found.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Every record for `order_id` in the production log, oldest first.
+pub fn story(order_id: u64) -> io::Result<Vec<AuditRecord>> {
+    story_at(Path::new(AUDIT_LOG_FILE), order_id)
+}
+
+/// Path-parameterized [`story`]. Lines that do not parse (a torn write at
+/// crash time, a hand edit) are skipped with a warning rather than hiding
+/// the rest of the history.
+pub fn story_at(path: &Path, order_id: u64) -> io::Result<Vec<AuditRecord>> {
+    let mut files = archives(path)?;
+    files.push(path.to_path_buf());
+    let mut records = Vec::new();
+    for file in files {
+        let contents = match fs::read_to_string(&file) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for (n, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditRecord>(line) {
+                Ok(r) if r.order_id == order_id => records.push(r),
+                Ok(_) => {}
+                Err(e) => warn!("[Audit] Skipping {:?} line {}: {}", file, n + 1, e),
+            }
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(id: u64) -> AuditRecord {
+        AuditRecord {
+            ts: Utc::now(),
+            order_id: id,
+            event: AuditEvent::Queued {
+                user_uuid: "u-1".into(),
+                username: "alice".into(),
+                description: "buy 5 iron_ingot".into(),
+            },
+        }
+    }
+
+    #[test]
+    fn story_keeps_one_order_in_write_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.jsonl");
+        let log = AuditLog::at(&path);
+        log.record(
+            1,
+            AuditEvent::Started {
+                description: "x".into(),
+            },
+        );
+        log.record(
+            2,
+            AuditEvent::Phase {
+                phase: "withdrawing".into(),
+                detail: None,
+            },
+        );
+        log.record(
+            1,
+            AuditEvent::Finished {
+                ok: false,
+                detail: "bot timed out".into(),
+            },
+        );
+
+        let story = story_at(&path, 1).unwrap();
+        assert_eq!(story.len(), 2);
+        assert!(matches!(story[0].event, AuditEvent::Started { .. }));
+        assert!(story[1].describe().contains("FAILED: bot timed out"));
+    }
+
+    #[test]
+    fn rotation_keeps_earlier_records_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.jsonl");
+        // A one-byte cap rotates before every append after the first.
+        for id in [7, 8, 7] {
+            append(&path, &queued(id), 1).unwrap();
+        }
+        assert_eq!(archives(&path).unwrap().len(), 2);
+
+        let story = story_at(&path, 7).unwrap();
+        assert_eq!(story.len(), 2);
+        assert!(story.iter().all(|r| r.order_id == 7));
+    }
+
+    #[test]
+    fn story_skips_a_torn_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.jsonl");
+        append(&path, &queued(3), u64::MAX).unwrap();
+        append_line(&path, "{\"ts\":\"2026-").unwrap();
+        append(&path, &queued(3), u64::MAX).unwrap();
+
+        assert_eq!(story_at(&path, 3).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn record_round_trips_with_a_flat_event_tag() {
+        let record = queued(4);
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains("\"event\":\"queued\""), "{line}");
+        assert_eq!(serde_json::from_str::<AuditRecord>(&line).unwrap(), record);
+    }
+}
@@ -12,34 +12,403 @@
 //! any syntactically-valid operator command and lets the dispatcher reject
 //! it for non-operators, so the error message can be consistent with the
 //! rest of the permission system.
-
-use crate::constants::MAX_TRADE_DIAMONDS;
+//!
+//! Item quantities may be written in stacks and shulkers (`3s`, `1 shulker`,
+//! `2s+10`). How many items that is depends on the pair's stack size, so
+//! the parser keeps it as a [`Quantity`] and the dispatcher settles it with
+//! [`Quantity::resolve`].
+
+use std::fmt;
+
+use crate::constants::{
+    ALERT_DEFAULT_TTL_SECS, LIMIT_ORDER_DEFAULT_TTL_SECS, LIMIT_ORDER_MAX_TTL_SECS,
+    MAX_PAY_MEMO_CHARS, MAX_TRADE_DIAMONDS, MAX_TRANSACTION_QUANTITY, SHULKER_BOX_SLOTS,
+    TRADE_OFFER_SLOTS_PER_SIDE,
+};
 use crate::types::ItemId;
 
+use super::alerts::AlertTrigger;
+use super::order_book::OrderSide;
+use super::price_history::HistoryWindow;
+
 use super::handlers::validation::{validate_item_name, validate_quantity, validate_username};
 
 /// A parsed player command.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     // Order commands (enqueued for the bot task to process)
-    Buy { item: ItemId, quantity: u32 },
-    Sell { item: ItemId, quantity: u32 },
-    Deposit { amount: Option<f64> },
-    Withdraw { amount: Option<f64> },
+    /// `buy <item> <qty> [max <diamonds>]`: `max_cost` caps the total price,
+    /// re-checked at execution time.
+    Buy {
+        item: ItemId,
+        quantity: Quantity,
+        max_cost: Option<f64>,
+    },
+    /// `sell <item> <qty> [min <diamonds>]`: `min_payout` floors the total
+    /// payout, re-checked at execution time.
+    Sell {
+        item: ItemId,
+        quantity: Quantity,
+        min_payout: Option<f64>,
+    },
+    /// `buy|sell <item> <qty> limit <price> [ttl]`: rests in the order book
+    /// until the market price reaches `limit_price` (per item).
+    Limit {
+        side: OrderSide,
+        item: ItemId,
+        quantity: Quantity,
+        limit_price: f64,
+        ttl_secs: u64,
+    },
+    /// `buy|sell <item> upto <qty>` / `buy|sell <item> all`: fill as much as
+    /// stock, reserves, balance and storage space allow, up to `quantity`
+    /// (`None` = one full trade window).
+    UpTo {
+        side: OrderSide,
+        item: ItemId,
+        quantity: Option<Quantity>,
+    },
+    /// `sell all <item>`: sell however many the player offers, up to what
+    /// the store can take in one trade. The payout goes to the balance.
+    SellAll {
+        item: ItemId,
+    },
+    /// `buy|sell <item> <qty>, <item> <qty>, ...`: several items in one
+    /// trade, settled against the balance. Items are distinct.
+    Basket {
+        side: OrderSide,
+        lines: Vec<(ItemId, Quantity)>,
+    },
+    /// `swap <from> <qty> <to>`: sell `quantity` of `from` and spend the
+    /// payout on `to`, in one trade.
+    Swap {
+        from: ItemId,
+        quantity: Quantity,
+        to: ItemId,
+    },
+    Deposit {
+        amount: Option<f64>,
+    },
+    Withdraw {
+        amount: Option<f64>,
+    },
+    /// `quote buy|sell <item> <qty>`: price now and hold the price for
+    /// `QUOTE_TTL_SECS`.
+    Quote {
+        side: OrderSide,
+        item: ItemId,
+        quantity: Quantity,
+    },
+    /// `confirm <quote_id>`: enqueue a previously issued quote at its price.
+    Confirm {
+        quote_id: u64,
+    },
+    /// `lp add <item> <qty>`: provide liquidity; the matching diamonds
+    /// come from the balance.
+    LpAdd {
+        item: ItemId,
+        quantity: Quantity,
+    },
+    /// `lp remove <item> <shares|all>`: `None` burns the whole position.
+    LpRemove {
+        item: ItemId,
+        shares: Option<f64>,
+    },
+    /// `lp`: list the caller's liquidity positions (handled inline).
+    LpPositions,
     // Quick commands (handled inline on the Store task)
-    Price { item: ItemId, quantity: Option<u32> },
-    Balance { target: Option<String> },
-    Pay { target: String, amount: f64 },
-    Items { page: usize },
-    Queue { page: usize },
-    Cancel { order_id: u64 },
+    Price {
+        item: ItemId,
+        quantity: Option<Quantity>,
+    },
+    /// `stats <item>`: cumulative volume, fees, and trade count for a pair.
+    Stats {
+        item: ItemId,
+    },
+    /// `history <item> [24h|7d]`: OHLC + volume summary from price history.
+    History {
+        item: ItemId,
+        window: HistoryWindow,
+    },
+    /// `history [page]`: the caller's own trades, balance moves and
+    /// payments, newest first.
+    MyHistory {
+        page: usize,
+    },
+    /// `receipt <n>`: details of entry `n` of `history` (1 = newest).
+    Receipt {
+        index: usize,
+    },
+    Balance {
+        target: Option<String>,
+    },
+    /// `pay <player> <amount> [memo]`: transfer from the caller's balance.
+    Pay {
+        target: String,
+        amount: f64,
+        memo: Option<String>,
+    },
+    /// `request <player> <amount> [memo]`: bill another player.
+    Request {
+        target: String,
+        amount: f64,
+        memo: Option<String>,
+    },
+    /// Bare `request`: list the caller's open payment requests.
+    Requests,
+    /// `accept <request_id>`: pay a request addressed to the caller.
+    Accept {
+        request_id: u64,
+    },
+    /// `decline <request_id>`: refuse a request, or withdraw one's own.
+    Decline {
+        request_id: u64,
+    },
+    /// `escrow sell <buyer> <item> <qty> <price>`: hold items for a buyer
+    /// until they pay `price` diamonds for the lot.
+    EscrowSell {
+        buyer: String,
+        item: ItemId,
+        quantity: Quantity,
+        price: f64,
+    },
+    /// Bare `escrow`: list the caller's escrows.
+    Escrows,
+    /// `escrow accept <escrow_id>`: pay for an escrow addressed to the caller.
+    EscrowAccept {
+        escrow_id: u64,
+    },
+    /// `escrow cancel <escrow_id>`: refuse an escrow, or take one's own back.
+    EscrowCancel {
+        escrow_id: u64,
+    },
+    /// `alert <item> below|above <price> [ttl]` or `notify <item> restock
+    /// [ttl]`: tell the caller once when the pair reaches `trigger`.
+    Alert {
+        item: ItemId,
+        trigger: AlertTrigger,
+        ttl_secs: u64,
+    },
+    /// Bare `alert` or `notify`: list the caller's alerts.
+    Alerts,
+    /// `alert cancel <alert_id>`: remove one of the caller's alerts.
+    CancelAlert {
+        alert_id: u64,
+    },
+    Items {
+        page: usize,
+    },
+    Queue {
+        page: usize,
+    },
+    Cancel {
+        order_id: u64,
+    },
+    CancelLimit {
+        limit_id: u64,
+    },
     Status,
-    Help { topic: Option<String> },
+    Help {
+        topic: Option<String>,
+    },
     // Operator commands (permission checked by dispatcher)
-    AddItem { item: ItemId, quantity: u32 },
-    RemoveItem { item: ItemId, quantity: u32 },
-    AddCurrency { item: ItemId, amount: f64 },
-    RemoveCurrency { item: ItemId, amount: f64 },
+    AddItem {
+        item: ItemId,
+        quantity: Quantity,
+    },
+    RemoveItem {
+        item: ItemId,
+        quantity: Quantity,
+    },
+    AddCurrency {
+        item: ItemId,
+        amount: f64,
+    },
+    RemoveCurrency {
+        item: ItemId,
+        amount: f64,
+    },
+}
+
+/// An item count as the player typed it: `64`, `3s` / `3 stacks`, `1sh` /
+/// `1 shulker`, or a sum of those like `2s+10` or `1sh-1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quantity {
+    items: i64,
+    stacks: i64,
+    shulkers: i64,
+}
+
+/// Most terms one quantity may add up, e.g. `1sh+2s+10` is three. Also
+/// keeps [`Quantity::resolve`] clear of overflow.
+const MAX_QUANTITY_TERMS: usize = 8;
+
+/// The stack sizes Minecraft items come in.
+const STACK_SIZES: [i32; 3] = [1, 16, 64];
+
+/// Verbs whose arguments include an item quantity, which may then be
+/// spread over several words (`3 stacks`, `2s + 10`).
+const QUANTITY_VERBS: [&str; 14] = [
+    "buy",
+    "b",
+    "sell",
+    "s",
+    "swap",
+    "quote",
+    "lp",
+    "price",
+    "p",
+    "escrow",
+    "additem",
+    "ai",
+    "removeitem",
+    "ri",
+];
+
+impl Quantity {
+    /// A plain count of items.
+    pub const fn items(n: u32) -> Self {
+        Self {
+            items: n as i64,
+            stacks: 0,
+            shulkers: 0,
+        }
+    }
+
+    /// Whether the count depends on the pair's stack size.
+    pub fn is_stack_relative(&self) -> bool {
+        self.stacks != 0 || self.shulkers != 0
+    }
+
+    /// The count in items for a pair whose stacks hold `stack_size`, held
+    /// to the same `1..=MAX_TRANSACTION_QUANTITY` range as a plain number.
+    pub fn resolve(&self, stack_size: i32) -> Result<u32, String> {
+        let stack = i64::from(stack_size.max(1));
+        let total =
+            self.items + self.stacks * stack + self.shulkers * stack * SHULKER_BOX_SLOTS as i64;
+        if total < 1 {
+            return Err(format!(
+                "{} comes to {} items. Quantity must be at least 1.",
+                self, total
+            ));
+        }
+        if total > i64::from(MAX_TRANSACTION_QUANTITY) {
+            return Err(format!(
+                "{} is {} items, too many. Maximum is {} items per transaction.",
+                self, total, MAX_TRANSACTION_QUANTITY
+            ));
+        }
+        Ok(total as u32)
+    }
+
+    /// Parse `raw` as one or more `<n>[unit]` terms joined by `+` or `-`.
+    /// `None` when it is not of that shape.
+    fn parse_terms(raw: &str) -> Option<Self> {
+        let mut quantity = Self {
+            items: 0,
+            stacks: 0,
+            shulkers: 0,
+        };
+        let mut rest = raw;
+        let mut sign = 1;
+        for _ in 0..MAX_QUANTITY_TERMS {
+            let (term, tail) = rest.split_at(rest.find(['+', '-']).unwrap_or(rest.len()));
+            let (digits, unit) = term.split_at(
+                term.find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(term.len()),
+            );
+            let n = i64::from(digits.parse::<u32>().ok()?);
+            let slot = match unit.to_ascii_lowercase().as_str() {
+                "" => &mut quantity.items,
+                "s" | "stack" | "stacks" => &mut quantity.stacks,
+                "sh" | "shulker" | "shulkers" => &mut quantity.shulkers,
+                _ => return None,
+            };
+            *slot += sign * n;
+            let Some(op) = tail.chars().next() else {
+                return Some(quantity);
+            };
+            sign = if op == '+' { 1 } else { -1 };
+            rest = &tail[1..];
+        }
+        None
+    }
+}
+
+impl fmt::Display for Quantity {
+    /// Canonical form: shulkers, then stacks, then items, e.g. `1sh+2s-3`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (n, unit) in [(self.shulkers, "sh"), (self.stacks, "s"), (self.items, "")] {
+            if n == 0 {
+                continue;
+            }
+            match (first, n < 0) {
+                (_, true) => f.write_str("-")?,
+                (false, false) => f.write_str("+")?,
+                (true, false) => {}
+            }
+            write!(f, "{}{}", n.unsigned_abs(), unit)?;
+            first = false;
+        }
+        if first {
+            f.write_str("0")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `word` is a quantity unit on its own, as in `3 stacks`.
+fn is_quantity_unit(word: &str) -> bool {
+    ["s", "stack", "stacks", "sh", "shulker", "shulkers"]
+        .iter()
+        .any(|unit| word.eq_ignore_ascii_case(unit))
+}
+
+/// Split `input` into words, gluing a quantity typed over several words
+/// back into one: `3 stacks` -> `3stacks`, `2s + 10` -> `2s+10`. Words are
+/// only glued onto one that starts with a digit, and only while the result
+/// still reads as a quantity, so item names, prices and durations
+/// (`limit 1 -3d`) are left alone.
+fn join_quantity_words(input: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for word in input.split_whitespace() {
+        if let Some(last) = words.last_mut()
+            && last.starts_with(|c: char| c.is_ascii_digit())
+            && (last.ends_with(['+', '-'])
+                || word.starts_with(['+', '-'])
+                || is_quantity_unit(word))
+        {
+            let joined = format!("{}{}", last, word);
+            if Quantity::parse_terms(joined.trim_end_matches(['+', '-'])).is_some() {
+                *last = joined;
+                continue;
+            }
+        }
+        words.push(word.to_string());
+    }
+    words
+}
+
+/// Parse an item quantity for `verb`. A plain number is checked here; a
+/// stack-relative one only where no stack size could make it valid.
+fn parse_quantity(raw: &str, verb: &str) -> Result<Quantity, String> {
+    if raw.bytes().all(|b| b.is_ascii_digit()) {
+        return validate_quantity(raw, verb).map(Quantity::items);
+    }
+    let quantity = Quantity::parse_terms(raw).ok_or_else(|| {
+        format!(
+            "Invalid quantity '{}'. Use a whole number, stacks or shulkers. Example: {} cobblestone 64 (or 3s, 1sh, 2s+10)",
+            raw, verb
+        )
+    })?;
+    if !quantity.is_stack_relative() {
+        return quantity.resolve(1).map(Quantity::items);
+    }
+    match STACK_SIZES.map(|size| quantity.resolve(size)) {
+        [Err(_), Err(_), Err(e)] => Err(e),
+        _ => Ok(quantity),
+    }
 }
 
 /// Parse a raw command string into a [`Command`].
@@ -47,7 +416,11 @@ pub enum Command {
 /// Returns a user-friendly error message on failure; callers should relay
 /// the error verbatim to the player (via `send_message_to_player`).
 pub fn parse_command(input: &str) -> Result<Command, String> {
-    let parts: Vec<&str> = input.split_whitespace().collect();
+    let words: Vec<String> = match input.split_whitespace().next() {
+        Some(verb) if QUANTITY_VERBS.contains(&verb) => join_quantity_words(input),
+        _ => input.split_whitespace().map(str::to_string).collect(),
+    };
+    let parts: Vec<&str> = words.iter().map(String::as_str).collect();
 
     let verb = match parts.first() {
         Some(v) => *v,
@@ -57,10 +430,11 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
     };
 
     match verb {
-        "buy" | "b" => parse_item_quantity(&parts, "buy")
-            .map(|(item, quantity)| Command::Buy { item, quantity }),
-        "sell" | "s" => parse_item_quantity(&parts, "sell")
-            .map(|(item, quantity)| Command::Sell { item, quantity }),
+        "buy" | "b" if input.contains(',') => parse_basket(input, OrderSide::Buy),
+        "sell" | "s" if input.contains(',') => parse_basket(input, OrderSide::Sell),
+        "buy" | "b" => parse_trade(&parts, OrderSide::Buy),
+        "sell" | "s" => parse_trade(&parts, OrderSide::Sell),
+        "swap" => parse_swap(&parts),
 
         "deposit" | "d" => {
             parse_optional_amount(&parts, "deposit").map(|amount| Command::Deposit { amount })
@@ -69,9 +443,37 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
             parse_optional_amount(&parts, "withdraw").map(|amount| Command::Withdraw { amount })
         }
 
+        "lp" => parse_lp(&parts),
+
+        "quote" => parse_quote(&parts),
+        "confirm" => parse_confirm(&parts),
+
         "price" | "p" => parse_price(&parts),
+        "stats" => parse_stats(&parts),
+        "history" => parse_history(&parts),
+        "receipt" => parse_receipt(&parts),
         "balance" | "bal" => parse_balance(&parts),
-        "pay" => parse_pay(&parts),
+        "pay" => parse_transfer(&parts, "pay").map(|(target, amount, memo)| Command::Pay {
+            target,
+            amount,
+            memo,
+        }),
+        "request" if parts.len() == 1 => Ok(Command::Requests),
+        "request" => {
+            parse_transfer(&parts, "request").map(|(target, amount, memo)| Command::Request {
+                target,
+                amount,
+                memo,
+            })
+        }
+        "accept" => {
+            parse_request_id(&parts, "accept").map(|request_id| Command::Accept { request_id })
+        }
+        "decline" => {
+            parse_request_id(&parts, "decline").map(|request_id| Command::Decline { request_id })
+        }
+        "escrow" => parse_escrow(&parts),
+        "alert" | "notify" => parse_alert(&parts),
         "items" => Ok(Command::Items {
             page: parse_page(&parts),
         }),
@@ -100,7 +502,7 @@ pub fn parse_command(input: &str) -> Result<Command, String> {
     }
 }
 
-fn parse_item_quantity(parts: &[&str], verb: &str) -> Result<(ItemId, u32), String> {
+fn parse_item_quantity(parts: &[&str], verb: &str) -> Result<(ItemId, Quantity), String> {
     if parts.len() < 3 {
         return Err(format!(
             "Usage: {} <item> <quantity>. Example: {} cobblestone 64",
@@ -108,10 +510,223 @@ fn parse_item_quantity(parts: &[&str], verb: &str) -> Result<(ItemId, u32), Stri
         ));
     }
     let item = validate_item_name(parts[1])?;
-    let quantity = validate_quantity(parts[2], verb)?;
+    let quantity = parse_quantity(parts[2], verb)?;
     Ok((item, quantity))
 }
 
+/// `buy`/`sell` share one grammar: `<verb> <item> <qty>` is a market order,
+/// a trailing `max <diamonds>` (buy) / `min <diamonds>` (sell) bounds its
+/// total, and a trailing `limit <price> [ttl]` turns it into a resting
+/// limit order.
+fn parse_trade(parts: &[&str], side: OrderSide) -> Result<Command, String> {
+    let verb = side.verb();
+    if side == OrderSide::Sell && parts.get(1) == Some(&"all") {
+        return parse_sell_all(parts);
+    }
+    if matches!(parts.get(2), Some(&"upto") | Some(&"all")) {
+        return parse_up_to(parts, side);
+    }
+    let (item, quantity) = parse_item_quantity(parts, verb)?;
+    let (bound_keyword, wrong_keyword) = match side {
+        OrderSide::Buy => ("max", "min"),
+        OrderSide::Sell => ("min", "max"),
+    };
+    match parts.get(3) {
+        Some(&"limit") => {}
+        // Silently ignoring the wrong keyword would leave the player thinking
+        // they are protected.
+        Some(&kw) if kw == wrong_keyword => {
+            return Err(format!(
+                "A {} takes '{} <diamonds>', not '{}'. Example: {} cobblestone 64 {} 12",
+                verb, bound_keyword, wrong_keyword, verb, bound_keyword
+            ));
+        }
+        Some(&kw) if kw == bound_keyword => {
+            let bound = parse_price_bound(parts.get(4).copied(), verb, bound_keyword)?;
+            return Ok(match side {
+                OrderSide::Buy => Command::Buy {
+                    item,
+                    quantity,
+                    max_cost: Some(bound),
+                },
+                OrderSide::Sell => Command::Sell {
+                    item,
+                    quantity,
+                    min_payout: Some(bound),
+                },
+            });
+        }
+        _ => {
+            return Ok(match side {
+                OrderSide::Buy => Command::Buy {
+                    item,
+                    quantity,
+                    max_cost: None,
+                },
+                OrderSide::Sell => Command::Sell {
+                    item,
+                    quantity,
+                    min_payout: None,
+                },
+            });
+        }
+    }
+
+    let usage = format!(
+        "Usage: {} <item> <qty> limit <price> [ttl]. Example: {} cobblestone 64 limit 0.5 12h",
+        verb, verb
+    );
+    let raw_price = parts.get(4).ok_or_else(|| usage.clone())?;
+    let limit_price: f64 = raw_price.parse().map_err(|_| {
+        format!(
+            "Invalid limit price '{}'. Use diamonds per item, e.g. 0.5",
+            raw_price
+        )
+    })?;
+    if !limit_price.is_finite() || limit_price <= 0.0 {
+        return Err("Limit price must be a positive number.".to_string());
+    }
+    if limit_price > 1_000_000.0 {
+        return Err("Limit price too large. Maximum is 1,000,000.".to_string());
+    }
+    let ttl_secs = match parts.get(5) {
+        Some(raw) => parse_duration_secs(raw)?,
+        None => LIMIT_ORDER_DEFAULT_TTL_SECS,
+    };
+
+    Ok(Command::Limit {
+        side,
+        item,
+        quantity,
+        limit_price,
+        ttl_secs,
+    })
+}
+
+/// `sell all <item>`: the quantity is whatever the player offers.
+fn parse_sell_all(parts: &[&str]) -> Result<Command, String> {
+    match parts {
+        [_, _, raw_item] => Ok(Command::SellAll {
+            item: validate_item_name(raw_item)?,
+        }),
+        _ => Err("Usage: sell all <item>. Example: sell all cobblestone".to_string()),
+    }
+}
+
+/// `<verb> <item> upto <qty>` or `<verb> <item> all`. Bounds and limits
+/// don't combine with a quantity that is only settled when the order runs.
+fn parse_up_to(parts: &[&str], side: OrderSide) -> Result<Command, String> {
+    let verb = side.verb();
+    let usage = format!(
+        "Usage: {} <item> upto <qty> or {} <item> all. Example: {} cobblestone upto 640",
+        verb, verb, verb
+    );
+    let item = validate_item_name(parts[1])?;
+    let quantity = match parts[2..] {
+        ["all"] => None,
+        ["upto", raw] => Some(parse_quantity(raw, verb)?),
+        _ => return Err(usage),
+    };
+    Ok(Command::UpTo {
+        side,
+        item,
+        quantity,
+    })
+}
+
+/// A comma in a buy/sell makes it a basket: each comma-separated entry is
+/// `<item> <qty>`. `max`/`min`/`limit` stay single-item, and each item
+/// needs at least one offer slot, so a basket lists at most 12 items.
+fn parse_basket(input: &str, side: OrderSide) -> Result<Command, String> {
+    let verb = side.verb();
+    let usage = format!(
+        "Usage: {} <item> <qty>, <item> <qty>, ... Example: {} cobblestone 64, glass 32, torch 16",
+        verb, verb
+    );
+    let rest = input
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, rest)| rest);
+    let mut lines: Vec<(ItemId, Quantity)> = Vec::new();
+    for entry in rest.split(',') {
+        let words = join_quantity_words(entry);
+        let [raw_item, raw_qty] = words.as_slice() else {
+            return Err(usage);
+        };
+        let item = validate_item_name(raw_item)?;
+        let quantity = parse_quantity(raw_qty, verb)?;
+        if lines.iter().any(|(listed, _)| *listed == item) {
+            return Err(format!(
+                "{} is listed twice. Combine it into one entry.",
+                item
+            ));
+        }
+        lines.push((item, quantity));
+    }
+    if lines.len() < 2 {
+        return Err(usage);
+    }
+    if lines.len() > TRADE_OFFER_SLOTS_PER_SIDE as usize {
+        return Err(format!(
+            "A basket holds at most {} different items.",
+            TRADE_OFFER_SLOTS_PER_SIDE
+        ));
+    }
+    Ok(Command::Basket { side, lines })
+}
+
+/// Parse the total-diamond value after `max`/`min` on a buy/sell.
+fn parse_price_bound(raw: Option<&str>, verb: &str, keyword: &str) -> Result<f64, String> {
+    let raw = raw.ok_or_else(|| {
+        format!(
+            "Usage: {} <item> <qty> {} <diamonds>. Example: {} cobblestone 64 {} 12",
+            verb, keyword, verb, keyword
+        )
+    })?;
+    let bound: f64 = raw.parse().map_err(|_| {
+        format!(
+            "Invalid {} '{}'. Use a total in diamonds, e.g. 12.5",
+            keyword, raw
+        )
+    })?;
+    if !bound.is_finite() || bound <= 0.0 {
+        return Err(format!("The {} amount must be a positive number.", keyword));
+    }
+    if bound > 1_000_000.0 {
+        return Err(format!(
+            "The {} amount is too large. Maximum is 1,000,000.",
+            keyword
+        ));
+    }
+    Ok(bound)
+}
+
+/// Parse a player-supplied duration like `30m`, `12h` or `7d` into seconds,
+/// bounded by `LIMIT_ORDER_MAX_TTL_SECS`.
+fn parse_duration_secs(raw: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid duration '{}'. Use e.g. 30m, 12h or 2d.", raw);
+    let unit = raw.chars().last().ok_or_else(invalid)?;
+    let digits = &raw[..raw.len() - unit.len_utf8()];
+    let multiplier = match unit.to_ascii_lowercase() {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let n: u64 = digits.parse().map_err(|_| invalid())?;
+    if n == 0 {
+        return Err(invalid());
+    }
+    let secs = n.saturating_mul(multiplier);
+    if secs > LIMIT_ORDER_MAX_TTL_SECS {
+        return Err(format!(
+            "Duration too long. Maximum is {} days.",
+            LIMIT_ORDER_MAX_TTL_SECS / (24 * 60 * 60)
+        ));
+    }
+    Ok(secs)
+}
+
 fn parse_item_amount(parts: &[&str], verb: &str) -> Result<(ItemId, f64), String> {
     if parts.len() < 3 {
         return Err(format!("Usage: {} <item> <amount>", verb));
@@ -132,8 +747,9 @@ fn parse_item_amount(parts: &[&str], verb: &str) -> Result<(ItemId, f64), String
     Ok((item, amount))
 }
 
+/// `None` for a bare `deposit`/`withdraw` or for `all`.
 fn parse_optional_amount(parts: &[&str], verb: &str) -> Result<Option<f64>, String> {
-    if parts.len() < 2 {
+    if parts.len() < 2 || parts[1] == "all" {
         return Ok(None);
     }
     let amt: f64 = parts[1].parse().map_err(|_| {
@@ -158,18 +774,38 @@ fn parse_optional_amount(parts: &[&str], verb: &str) -> Result<Option<f64>, Stri
     Ok(Some(amt))
 }
 
+fn parse_swap(parts: &[&str]) -> Result<Command, String> {
+    if parts.len() < 4 {
+        return Err(
+            "Usage: swap <from_item> <quantity> <to_item>. Example: swap iron_ingot 64 gold_ingot"
+                .to_string(),
+        );
+    }
+    let (from, quantity) = parse_item_quantity(&parts[..3], "swap")?;
+    let to = validate_item_name(parts[3])?;
+    if from == to {
+        return Err(format!("Cannot swap {} for itself.", from));
+    }
+    if from.as_str() == "diamond" || to.as_str() == "diamond" {
+        return Err(
+            "Swaps are item-to-item; use buy or sell to trade against diamonds.".to_string(),
+        );
+    }
+    Ok(Command::Swap { from, quantity, to })
+}
+
 fn parse_price(parts: &[&str]) -> Result<Command, String> {
     if parts.len() < 2 {
         return Err("Usage: price <item> [quantity]. Example: price cobblestone 64".to_string());
     }
     let item = validate_item_name(parts[1])?;
 
-    let quantity: Option<u32> = if parts.len() >= 3 {
-        match parts[2].parse::<u32>() {
-            Ok(q) if q > 0 => Some(q),
-            _ => {
+    let quantity = if parts.len() >= 3 {
+        match parse_quantity(parts[2], "price") {
+            Ok(q) => Some(q),
+            Err(_) => {
                 return Err(format!(
-                    "Invalid quantity '{}'. Use a positive number.",
+                    "Invalid quantity '{}'. Use a positive number, stacks or shulkers, e.g. 64 or 3s.",
                     parts[2]
                 ));
             }
@@ -181,6 +817,111 @@ fn parse_price(parts: &[&str]) -> Result<Command, String> {
     Ok(Command::Price { item, quantity })
 }
 
+fn parse_stats(parts: &[&str]) -> Result<Command, String> {
+    let Some(raw) = parts.get(1) else {
+        return Err("Usage: stats <item>. Example: stats cobblestone".to_string());
+    };
+    let item = validate_item_name(raw)?;
+    Ok(Command::Stats { item })
+}
+
+fn parse_history(parts: &[&str]) -> Result<Command, String> {
+    // A bare `history` or a page number is the caller's own history; no
+    // Minecraft item id is all digits.
+    let Some(raw) = parts.get(1) else {
+        return Ok(Command::MyHistory { page: 1 });
+    };
+    if let Ok(page) = raw.parse::<usize>() {
+        return Ok(Command::MyHistory { page: page.max(1) });
+    }
+    let item = validate_item_name(raw)?;
+    let window = match parts.get(2) {
+        None => HistoryWindow::Day,
+        Some(w) => HistoryWindow::parse(w)
+            .ok_or_else(|| format!("Invalid window '{}'. Use 24h or 7d.", w))?,
+    };
+    Ok(Command::History { item, window })
+}
+
+fn parse_receipt(parts: &[&str]) -> Result<Command, String> {
+    let Some(raw) = parts.get(1) else {
+        return Err("Usage: receipt <n>. 'history' lists your entries, newest is 1.".to_string());
+    };
+    match raw.parse::<usize>() {
+        Ok(index) if index >= 1 => Ok(Command::Receipt { index }),
+        _ => Err(format!(
+            "Invalid entry '{}'. Use the number shown by 'history', e.g. receipt 1",
+            raw
+        )),
+    }
+}
+
+fn parse_quote(parts: &[&str]) -> Result<Command, String> {
+    let side = match parts.get(1) {
+        Some(&"buy") | Some(&"b") => OrderSide::Buy,
+        Some(&"sell") | Some(&"s") => OrderSide::Sell,
+        _ => {
+            return Err(
+                "Usage: quote buy|sell <item> <quantity>. Example: quote buy cobblestone 64"
+                    .to_string(),
+            );
+        }
+    };
+    let verb = format!("quote {}", side.verb());
+    let (item, quantity) = parse_item_quantity(&parts[1..], &verb)?;
+    Ok(Command::Quote {
+        side,
+        item,
+        quantity,
+    })
+}
+
+fn parse_lp(parts: &[&str]) -> Result<Command, String> {
+    match parts.get(1).copied() {
+        None | Some("positions") => Ok(Command::LpPositions),
+        Some("add") => parse_item_quantity(&parts[1..], "lp add")
+            .map(|(item, quantity)| Command::LpAdd { item, quantity }),
+        Some("remove") => {
+            if parts.len() < 4 {
+                return Err(
+                    "Usage: lp remove <item> <shares|all>. Example: lp remove cobblestone 50"
+                        .to_string(),
+                );
+            }
+            let item = validate_item_name(parts[2])?;
+            let shares = if parts[3].eq_ignore_ascii_case("all") {
+                None
+            } else {
+                match parts[3].parse::<f64>() {
+                    Ok(s) if s.is_finite() && s > 0.0 => Some(s),
+                    _ => {
+                        return Err(format!(
+                            "Invalid share amount '{}'. Use a positive number or 'all'.",
+                            parts[3]
+                        ));
+                    }
+                }
+            };
+            Ok(Command::LpRemove { item, shares })
+        }
+        Some(_) => Err(
+            "Usage: lp [positions] | lp add <item> <quantity> | lp remove <item> <shares|all>"
+                .to_string(),
+        ),
+    }
+}
+
+fn parse_confirm(parts: &[&str]) -> Result<Command, String> {
+    let raw = parts
+        .get(1)
+        .ok_or_else(|| "Usage: confirm <quote_id>. Example: confirm Q3".to_string())?;
+    let quote_id: u64 = raw
+        .trim_start_matches(['Q', 'q'])
+        .parse()
+        .map_err(|_| format!("Invalid quote ID '{}'. Use: confirm Q<id>", raw))?;
+    Ok(Command::Confirm { quote_id })
+}
+
 fn parse_balance(parts: &[&str]) -> Result<Command, String> {
     let target = if parts.len() >= 2 {
         validate_username(parts[1])?;
@@ -191,26 +932,158 @@ fn parse_balance(parts: &[&str]) -> Result<Command, String> {
     Ok(Command::Balance { target })
 }
 
-fn parse_pay(parts: &[&str]) -> Result<Command, String> {
+/// `<verb> <player> <amount> [memo...]`, shared by `pay` and `request`.
+fn parse_transfer(parts: &[&str], verb: &str) -> Result<(String, f64, Option<String>), String> {
     if parts.len() < 3 {
-        return Err("Usage: pay <player> <amount>. Example: pay Steve 10.5".to_string());
+        return Err(format!(
+            "Usage: {verb} <player> <amount> [memo]. Example: {verb} Steve 10.5"
+        ));
     }
     validate_username(parts[1])?;
     let amount: f64 = parts[2].parse().map_err(|_| {
         format!(
-            "Invalid amount '{}'. Please enter a number. Example: pay Steve 10.5",
+            "Invalid amount '{}'. Please enter a number. Example: {verb} Steve 10.5",
             parts[2]
         )
     })?;
     if !amount.is_finite() || amount <= 0.0 {
-        return Err("Amount must be positive. Example: pay Steve 10.5".to_string());
+        return Err(format!(
+            "Amount must be positive. Example: {verb} Steve 10.5"
+        ));
     }
     if amount > 1_000_000.0 {
         return Err("Amount too large. Maximum is 1,000,000 per payment.".to_string());
     }
-    Ok(Command::Pay {
-        target: parts[1].to_string(),
+    let memo = parts[3..].join(" ");
+    if memo.chars().count() > MAX_PAY_MEMO_CHARS {
+        return Err(format!(
+            "Memo too long. Keep it to {} characters.",
+            MAX_PAY_MEMO_CHARS
+        ));
+    }
+    Ok((
+        parts[1].to_string(),
         amount,
+        (!memo.is_empty()).then_some(memo),
+    ))
+}
+
+fn parse_request_id(parts: &[&str], verb: &str) -> Result<u64, String> {
+    let raw = parts
+        .get(1)
+        .ok_or_else(|| format!("Usage: {verb} <request_id>. Example: {verb} R3"))?;
+    raw.trim_start_matches(['R', 'r'])
+        .parse()
+        .map_err(|_| format!("Invalid request ID '{}'. Use: {verb} R<id>", raw))
+}
+
+fn parse_escrow(parts: &[&str]) -> Result<Command, String> {
+    match parts.get(1).copied() {
+        None => Ok(Command::Escrows),
+        Some("sell") => {
+            if parts.len() < 6 {
+                return Err(
+                    "Usage: escrow sell <buyer> <item> <quantity> <price>. Example: escrow sell Steve cobblestone 64 10"
+                        .to_string(),
+                );
+            }
+            validate_username(parts[2])?;
+            let (item, quantity) = parse_item_quantity(&parts[2..], "escrow sell")?;
+            let price = match parts[5].parse::<f64>() {
+                Ok(p) if p.is_finite() && p > 0.0 => p,
+                _ => {
+                    return Err(format!(
+                        "Invalid price '{}'. Use a positive number of diamonds for the whole lot.",
+                        parts[5]
+                    ));
+                }
+            };
+            if price > 1_000_000.0 {
+                return Err("Price too large. Maximum is 1,000,000.".to_string());
+            }
+            Ok(Command::EscrowSell {
+                buyer: parts[2].to_string(),
+                item,
+                quantity,
+                price,
+            })
+        }
+        Some("accept") => {
+            parse_escrow_id(parts).map(|escrow_id| Command::EscrowAccept { escrow_id })
+        }
+        Some("cancel") => {
+            parse_escrow_id(parts).map(|escrow_id| Command::EscrowCancel { escrow_id })
+        }
+        Some(_) => Err(
+            "Usage: escrow | escrow sell <buyer> <item> <quantity> <price> | escrow accept|cancel E<id>"
+                .to_string(),
+        ),
+    }
+}
+
+fn parse_escrow_id(parts: &[&str]) -> Result<u64, String> {
+    let verb = parts[1];
+    let raw = parts
+        .get(2)
+        .ok_or_else(|| format!("Usage: escrow {verb} <escrow_id>. Example: escrow {verb} E3"))?;
+    raw.trim_start_matches(['E', 'e'])
+        .parse()
+        .map_err(|_| format!("Invalid escrow ID '{}'. Use: escrow {verb} E<id>", raw))
+}
+
+/// `alert <item> below|above <price> [ttl]`, `notify <item> restock [ttl]`,
+/// `alert|notify cancel <alert_id>`, or either verb alone to list.
+fn parse_alert(parts: &[&str]) -> Result<Command, String> {
+    let verb = parts[0];
+    let usage = || {
+        "Usage: alert <item> below|above <price> [ttl] | notify <item> restock [ttl] | alert cancel A<id>. Example: alert cobblestone below 0.5"
+            .to_string()
+    };
+    let Some(&first) = parts.get(1) else {
+        return Ok(Command::Alerts);
+    };
+    if first == "cancel" {
+        let raw = parts
+            .get(2)
+            .ok_or_else(|| format!("Usage: {verb} cancel <alert_id>. Example: {verb} cancel A3"))?;
+        let alert_id = raw
+            .trim_start_matches(['A', 'a'])
+            .parse()
+            .map_err(|_| format!("Invalid alert ID '{}'. Use: {verb} cancel A<id>", raw))?;
+        return Ok(Command::CancelAlert { alert_id });
+    }
+
+    let item = validate_item_name(first)?;
+    let (trigger, ttl_at) = match parts.get(2).copied() {
+        Some("restock") => (AlertTrigger::Restock, 3),
+        Some(direction @ ("below" | "above")) => {
+            let raw = parts.get(3).ok_or_else(usage)?;
+            let price: f64 = raw
+                .parse()
+                .map_err(|_| format!("Invalid price '{}'. Use diamonds per item, e.g. 0.5", raw))?;
+            if !price.is_finite() || price <= 0.0 {
+                return Err("Alert price must be a positive number.".to_string());
+            }
+            if price > 1_000_000.0 {
+                return Err("Alert price too large. Maximum is 1,000,000.".to_string());
+            }
+            let trigger = if direction == "below" {
+                AlertTrigger::Below { price }
+            } else {
+                AlertTrigger::Above { price }
+            };
+            (trigger, 4)
+        }
+        _ => return Err(usage()),
+    };
+    let ttl_secs = match parts.get(ttl_at) {
+        Some(raw) => parse_duration_secs(raw)?,
+        None => ALERT_DEFAULT_TTL_SECS,
+    };
+    Ok(Command::Alert {
+        item,
+        trigger,
+        ttl_secs,
     })
 }
 
@@ -226,6 +1099,15 @@ fn parse_cancel(parts: &[&str]) -> Result<Command, String> {
     if parts.len() < 2 {
         return Err("Usage: cancel <order_id>. Use 'queue' to see your orders.".to_string());
     }
+    if let Some(rest) = parts[1]
+        .strip_prefix('L')
+        .or_else(|| parts[1].strip_prefix('l'))
+    {
+        let limit_id: u64 = rest
+            .parse()
+            .map_err(|_| format!("Invalid limit order ID '{}'. Use: cancel L<id>", parts[1]))?;
+        return Ok(Command::CancelLimit { limit_id });
+    }
     let order_id: u64 = parts[1]
         .trim_start_matches('#')
         .parse()
@@ -274,7 +1156,8 @@ mod tests {
             parse_command("buy cobblestone 64").unwrap(),
             Command::Buy {
                 item: ItemId::new("cobblestone").unwrap(),
-                quantity: 64
+                quantity: Quantity::items(64),
+                max_cost: None,
             }
         );
     }
@@ -285,7 +1168,8 @@ mod tests {
             parse_command("b diamond 1").unwrap(),
             Command::Buy {
                 item: ItemId::new("diamond").unwrap(),
-                quantity: 1
+                quantity: Quantity::items(1),
+                max_cost: None,
             }
         );
     }
@@ -296,7 +1180,8 @@ mod tests {
             parse_command("buy minecraft:iron_ingot 32").unwrap(),
             Command::Buy {
                 item: ItemId::new("iron_ingot").unwrap(),
-                quantity: 32
+                quantity: Quantity::items(32),
+                max_cost: None,
             }
         );
     }
@@ -336,13 +1221,118 @@ mod tests {
         assert!(err.contains("invalid character"));
     }
 
+    #[test]
+    fn buy_max_parses_total_cost_bound() {
+        assert_eq!(
+            parse_command("buy cobblestone 64 max 12.5").unwrap(),
+            Command::Buy {
+                item: ItemId::new("cobblestone").unwrap(),
+                quantity: Quantity::items(64),
+                max_cost: Some(12.5),
+            }
+        );
+    }
+
+    #[test]
+    fn sell_min_parses_total_payout_bound() {
+        assert_eq!(
+            parse_command("s iron_ingot 32 min 4").unwrap(),
+            Command::Sell {
+                item: ItemId::new("iron_ingot").unwrap(),
+                quantity: Quantity::items(32),
+                min_payout: Some(4.0),
+            }
+        );
+    }
+
+    #[test]
+    fn buy_max_without_value_reports_usage() {
+        let err = parse_command("buy cobblestone 64 max").unwrap_err();
+        assert!(err.contains("max <diamonds>"));
+    }
+
+    #[test]
+    fn price_bound_rejects_non_positive_and_non_finite() {
+        for bad in ["0", "-2", "NaN", "inf", "1e7", "abc"] {
+            let input = format!("buy cobblestone 64 max {}", bad);
+            assert!(parse_command(&input).is_err(), "{} must be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn bound_keyword_is_side_specific() {
+        let err = parse_command("buy cobblestone 64 min 5").unwrap_err();
+        assert!(err.contains("'max <diamonds>'"), "{}", err);
+        let err = parse_command("sell cobblestone 64 max 5").unwrap_err();
+        assert!(err.contains("'min <diamonds>'"), "{}", err);
+    }
+
+    #[test]
+    fn buy_limit_parses_price_with_default_ttl() {
+        assert_eq!(
+            parse_command("buy cobblestone 64 limit 0.5").unwrap(),
+            Command::Limit {
+                side: OrderSide::Buy,
+                item: ItemId::new("cobblestone").unwrap(),
+                quantity: Quantity::items(64),
+                limit_price: 0.5,
+                ttl_secs: LIMIT_ORDER_DEFAULT_TTL_SECS,
+            }
+        );
+    }
+
+    #[test]
+    fn sell_limit_alias_parses_explicit_ttl() {
+        assert_eq!(
+            parse_command("s iron_ingot 32 limit 2 12h").unwrap(),
+            Command::Limit {
+                side: OrderSide::Sell,
+                item: ItemId::new("iron_ingot").unwrap(),
+                quantity: Quantity::items(32),
+                limit_price: 2.0,
+                ttl_secs: 12 * 60 * 60,
+            }
+        );
+    }
+
+    #[test]
+    fn limit_without_price_reports_usage() {
+        let err = parse_command("buy cobblestone 64 limit").unwrap_err();
+        assert!(err.contains("limit <price>"));
+    }
+
+    #[test]
+    fn limit_rejects_non_positive_and_non_finite_price() {
+        for bad in ["0", "-1", "NaN", "inf"] {
+            let input = format!("buy cobblestone 64 limit {}", bad);
+            assert!(parse_command(&input).is_err(), "{} must be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn limit_rejects_malformed_ttl() {
+        for bad in ["12", "h", "0h", "5s", "-3d", "1é"] {
+            let input = format!("buy cobblestone 64 limit 1 {}", bad);
+            let err = parse_command(&input).unwrap_err();
+            assert!(err.contains("duration"), "{}: {}", bad, err);
+        }
+    }
+
+    #[test]
+    fn limit_rejects_ttl_above_max() {
+        let err = parse_command("buy cobblestone 64 limit 1 8d").unwrap_err();
+        assert!(err.contains("too long"));
+        assert!(parse_command("buy cobblestone 64 limit 1 7d").is_ok());
+    }
+
     #[test]
     fn sell_command_parses_item_and_quantity() {
         assert_eq!(
             parse_command("sell iron_ingot 128").unwrap(),
             Command::Sell {
                 item: ItemId::new("iron_ingot").unwrap(),
-                quantity: 128
+                quantity: Quantity::items(128),
+                min_payout: None,
             }
         );
     }
@@ -353,7 +1343,8 @@ mod tests {
             parse_command("s diamond 5").unwrap(),
             Command::Sell {
                 item: ItemId::new("diamond").unwrap(),
-                quantity: 5
+                quantity: Quantity::items(5),
+                min_payout: None,
             }
         );
     }
@@ -425,6 +1416,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn withdraw_and_deposit_all_match_the_bare_commands() {
+        assert_eq!(
+            parse_command("withdraw all").unwrap(),
+            Command::Withdraw { amount: None }
+        );
+        assert_eq!(
+            parse_command("d all").unwrap(),
+            Command::Deposit { amount: None }
+        );
+    }
+
     #[test]
     fn withdraw_with_amount_parses_as_float() {
         assert_eq!(
@@ -485,7 +1488,7 @@ mod tests {
             parse_command("p cobblestone 64").unwrap(),
             Command::Price {
                 item: ItemId::new("cobblestone").unwrap(),
-                quantity: Some(64)
+                quantity: Some(Quantity::items(64))
             }
         );
     }
@@ -502,6 +1505,85 @@ mod tests {
         assert!(err.contains("positive"));
     }
 
+    // ---- stats -------------------------------------------------------------
+
+    #[test]
+    fn stats_parses_item() {
+        assert_eq!(
+            parse_command("stats minecraft:Cobblestone").unwrap(),
+            Command::Stats {
+                item: ItemId::new("cobblestone").unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn stats_without_item_reports_usage() {
+        let err = parse_command("stats").unwrap_err();
+        assert!(err.contains("Usage: stats"));
+    }
+
+    // ---- history -----------------------------------------------------------
+
+    #[test]
+    fn history_defaults_to_24h() {
+        assert_eq!(
+            parse_command("history cobblestone").unwrap(),
+            Command::History {
+                item: ItemId::new("cobblestone").unwrap(),
+                window: HistoryWindow::Day
+            }
+        );
+    }
+
+    #[test]
+    fn history_accepts_week_window() {
+        assert_eq!(
+            parse_command("history cobblestone 7d").unwrap(),
+            Command::History {
+                item: ItemId::new("cobblestone").unwrap(),
+                window: HistoryWindow::Week
+            }
+        );
+    }
+
+    #[test]
+    fn history_rejects_unknown_window() {
+        let err = parse_command("history cobblestone 30d").unwrap_err();
+        assert!(err.contains("24h or 7d"), "got: {err}");
+    }
+
+    #[test]
+    fn bare_history_is_the_callers_own_history() {
+        assert_eq!(
+            parse_command("history").unwrap(),
+            Command::MyHistory { page: 1 }
+        );
+        assert_eq!(
+            parse_command("history 3").unwrap(),
+            Command::MyHistory { page: 3 }
+        );
+        assert_eq!(
+            parse_command("history 0").unwrap(),
+            Command::MyHistory { page: 1 }
+        );
+    }
+
+    #[test]
+    fn receipt_takes_a_positive_entry_number() {
+        assert_eq!(
+            parse_command("receipt 2").unwrap(),
+            Command::Receipt { index: 2 }
+        );
+        assert!(
+            parse_command("receipt")
+                .unwrap_err()
+                .contains("Usage: receipt")
+        );
+        assert!(parse_command("receipt 0").is_err());
+        assert!(parse_command("receipt abc").is_err());
+    }
+
     #[test]
     fn price_with_negative_quantity_is_rejected() {
         // u32 parse rejects the leading `-`, so this hits the same error arm.
@@ -559,7 +1641,8 @@ mod tests {
             parse_command("pay Steve 10.5").unwrap(),
             Command::Pay {
                 target: "Steve".to_string(),
-                amount: 10.5
+                amount: 10.5,
+                memo: None
             }
         );
     }
@@ -601,7 +1684,8 @@ mod tests {
             parse_command("pay Steve 1000000").unwrap(),
             Command::Pay {
                 target: "Steve".to_string(),
-                amount: 1_000_000.0
+                amount: 1_000_000.0,
+                memo: None
             }
         );
     }
@@ -618,6 +1702,182 @@ mod tests {
         assert!(parse_command("pay Steve NaN").is_err());
     }
 
+    #[test]
+    fn pay_keeps_the_rest_of_the_line_as_memo() {
+        assert_eq!(
+            parse_command("pay Steve 3 Iron  farm rent").unwrap(),
+            Command::Pay {
+                target: "Steve".to_string(),
+                amount: 3.0,
+                memo: Some("Iron farm rent".to_string())
+            }
+        );
+        let long = format!("pay Steve 3 {}", "x".repeat(MAX_PAY_MEMO_CHARS + 1));
+        assert!(parse_command(&long).unwrap_err().contains("Memo too long"));
+    }
+
+    // ---- request / accept / decline ---------------------------------------
+
+    #[test]
+    fn request_parses_like_pay_and_lists_when_bare() {
+        assert_eq!(
+            parse_command("request Steve 12 for the beacon").unwrap(),
+            Command::Request {
+                target: "Steve".to_string(),
+                amount: 12.0,
+                memo: Some("for the beacon".to_string())
+            }
+        );
+        assert_eq!(parse_command("request").unwrap(), Command::Requests);
+        assert!(
+            parse_command("request Steve")
+                .unwrap_err()
+                .contains("Usage: request")
+        );
+        assert!(parse_command("request Steve -1").is_err());
+    }
+
+    #[test]
+    fn accept_and_decline_take_bare_or_prefixed_ids() {
+        for input in ["accept 4", "accept R4", "accept r4"] {
+            assert_eq!(
+                parse_command(input).unwrap(),
+                Command::Accept { request_id: 4 },
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            parse_command("decline R9").unwrap(),
+            Command::Decline { request_id: 9 }
+        );
+        assert!(parse_command("accept").unwrap_err().contains("Usage"));
+        assert!(parse_command("decline Rx").unwrap_err().contains("Invalid"));
+    }
+
+    // ---- escrow -------------------------------------------------------------
+
+    #[test]
+    fn escrow_sell_takes_buyer_item_quantity_and_price() {
+        assert_eq!(
+            parse_command("escrow sell Steve cobblestone 64 10.5").unwrap(),
+            Command::EscrowSell {
+                buyer: "Steve".to_string(),
+                item: ItemId::new("cobblestone").unwrap(),
+                quantity: Quantity::items(64),
+                price: 10.5,
+            }
+        );
+        assert_eq!(parse_command("escrow").unwrap(), Command::Escrows);
+        assert!(
+            parse_command("escrow sell Steve cobblestone 64")
+                .unwrap_err()
+                .contains("Usage: escrow sell")
+        );
+        assert!(parse_command("escrow sell Steve cobblestone 64 0").is_err());
+        assert!(parse_command("escrow sell Steve cobblestone 0 5").is_err());
+        assert!(parse_command("escrow sell St@ve cobblestone 64 5").is_err());
+    }
+
+    #[test]
+    fn escrow_accept_and_cancel_take_bare_or_prefixed_ids() {
+        for input in ["escrow accept 3", "escrow accept E3", "escrow accept e3"] {
+            assert_eq!(
+                parse_command(input).unwrap(),
+                Command::EscrowAccept { escrow_id: 3 },
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            parse_command("escrow cancel E7").unwrap(),
+            Command::EscrowCancel { escrow_id: 7 }
+        );
+        assert!(
+            parse_command("escrow accept")
+                .unwrap_err()
+                .contains("Usage")
+        );
+        assert!(
+            parse_command("escrow cancel Ex")
+                .unwrap_err()
+                .contains("Invalid")
+        );
+        assert!(parse_command("escrow buy").unwrap_err().contains("Usage"));
+    }
+
+    // ---- alert / notify -----------------------------------------------------
+
+    #[test]
+    fn alert_and_notify_parse_triggers_with_default_ttl() {
+        let cobble = ItemId::new("cobblestone").unwrap();
+        assert_eq!(
+            parse_command("alert cobblestone below 0.5").unwrap(),
+            Command::Alert {
+                item: cobble.clone(),
+                trigger: AlertTrigger::Below { price: 0.5 },
+                ttl_secs: ALERT_DEFAULT_TTL_SECS,
+            }
+        );
+        assert_eq!(
+            parse_command("alert cobblestone above 2 12h").unwrap(),
+            Command::Alert {
+                item: cobble.clone(),
+                trigger: AlertTrigger::Above { price: 2.0 },
+                ttl_secs: 12 * 60 * 60,
+            }
+        );
+        assert_eq!(
+            parse_command("notify cobblestone restock").unwrap(),
+            Command::Alert {
+                item: cobble,
+                trigger: AlertTrigger::Restock,
+                ttl_secs: ALERT_DEFAULT_TTL_SECS,
+            }
+        );
+        assert_eq!(parse_command("alert").unwrap(), Command::Alerts);
+        assert_eq!(parse_command("notify").unwrap(), Command::Alerts);
+    }
+
+    #[test]
+    fn alert_rejects_bad_direction_price_and_ttl() {
+        assert!(
+            parse_command("alert cobblestone near 0.5")
+                .unwrap_err()
+                .contains("Usage")
+        );
+        assert!(
+            parse_command("alert cobblestone below")
+                .unwrap_err()
+                .contains("Usage")
+        );
+        assert!(parse_command("alert cobblestone below -1").is_err());
+        assert!(
+            parse_command("alert cobblestone below abc")
+                .unwrap_err()
+                .contains("Invalid")
+        );
+        assert!(parse_command("notify cobblestone restock 30d").is_err());
+    }
+
+    #[test]
+    fn alert_cancel_takes_bare_or_prefixed_ids() {
+        for input in ["alert cancel 3", "alert cancel A3", "notify cancel a3"] {
+            assert_eq!(
+                parse_command(input).unwrap(),
+                Command::CancelAlert { alert_id: 3 },
+                "{}",
+                input
+            );
+        }
+        assert!(parse_command("alert cancel").unwrap_err().contains("Usage"));
+        assert!(
+            parse_command("alert cancel Ax")
+                .unwrap_err()
+                .contains("Invalid")
+        );
+    }
+
     // ---- items / queue (paged) --------------------------------------------
 
     #[test]
@@ -693,6 +1953,263 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cancel_with_l_prefix_targets_limit_order() {
+        assert_eq!(
+            parse_command("cancel L7").unwrap(),
+            Command::CancelLimit { limit_id: 7 }
+        );
+        assert_eq!(
+            parse_command("c l7").unwrap(),
+            Command::CancelLimit { limit_id: 7 }
+        );
+    }
+
+    #[test]
+    fn cancel_rejects_bare_l() {
+        let err = parse_command("cancel L").unwrap_err();
+        assert!(err.contains("limit order ID"));
+    }
+
+    // ---- quote / confirm ---------------------------------------------------
+
+    #[test]
+    fn quote_parses_side_item_and_quantity() {
+        assert_eq!(
+            parse_command("quote s iron_ingot 32").unwrap(),
+            Command::Quote {
+                side: OrderSide::Sell,
+                item: ItemId::new("iron_ingot").unwrap(),
+                quantity: Quantity::items(32),
+            }
+        );
+    }
+
+    #[test]
+    fn quote_without_side_reports_usage() {
+        let err = parse_command("quote cobblestone 64").unwrap_err();
+        assert!(err.contains("quote buy|sell"), "{}", err);
+    }
+
+    #[test]
+    fn quote_with_bad_quantity_names_quote_in_error() {
+        let err = parse_command("quote buy cobblestone abc").unwrap_err();
+        assert!(err.contains("quote buy"), "{}", err);
+    }
+
+    #[test]
+    fn confirm_accepts_bare_and_prefixed_ids() {
+        for input in ["confirm 3", "confirm Q3", "confirm q3"] {
+            assert_eq!(
+                parse_command(input).unwrap(),
+                Command::Confirm { quote_id: 3 },
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn confirm_rejects_missing_or_garbage_id() {
+        assert!(parse_command("confirm").unwrap_err().contains("Usage"));
+        assert!(parse_command("confirm Qx").unwrap_err().contains("Invalid"));
+    }
+
+    // ---- upto / all --------------------------------------------------------
+
+    #[test]
+    fn upto_and_all_parse_as_partial_orders() {
+        assert_eq!(
+            parse_command("buy diamond_pickaxe upto 10").unwrap(),
+            Command::UpTo {
+                side: OrderSide::Buy,
+                item: ItemId::new("diamond_pickaxe").unwrap(),
+                quantity: Some(Quantity::items(10)),
+            }
+        );
+        assert_eq!(
+            parse_command("s minecraft:iron_ingot all").unwrap(),
+            Command::UpTo {
+                side: OrderSide::Sell,
+                item: ItemId::new("iron_ingot").unwrap(),
+                quantity: None,
+            }
+        );
+    }
+
+    #[test]
+    fn sell_all_takes_whatever_is_offered() {
+        assert_eq!(
+            parse_command("s all minecraft:iron_ingot").unwrap(),
+            Command::SellAll {
+                item: ItemId::new("iron_ingot").unwrap(),
+            }
+        );
+        assert!(
+            parse_command("sell all iron_ingot 64")
+                .unwrap_err()
+                .contains("sell all <item>")
+        );
+    }
+
+    #[test]
+    fn upto_rejects_missing_quantity_and_extra_words() {
+        for input in [
+            "buy cobblestone upto",
+            "buy cobblestone upto 64 max 5",
+            "sell cobblestone all 64",
+        ] {
+            let err = parse_command(input).unwrap_err();
+            assert!(err.contains("upto <qty>"), "{input}: {err}");
+        }
+        assert!(
+            parse_command("buy cobblestone upto 0")
+                .unwrap_err()
+                .contains("at least 1")
+        );
+    }
+
+    // ---- basket ------------------------------------------------------------
+
+    #[test]
+    fn basket_parses_comma_separated_lines() {
+        assert_eq!(
+            parse_command("buy cobblestone 64, glass 32,torch 16").unwrap(),
+            Command::Basket {
+                side: OrderSide::Buy,
+                lines: vec![
+                    (ItemId::new("cobblestone").unwrap(), Quantity::items(64)),
+                    (ItemId::new("glass").unwrap(), Quantity::items(32)),
+                    (ItemId::new("torch").unwrap(), Quantity::items(16)),
+                ],
+            }
+        );
+        assert!(matches!(
+            parse_command("s iron_ingot 10, gold_ingot 5").unwrap(),
+            Command::Basket {
+                side: OrderSide::Sell,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn basket_rejects_malformed_duplicate_and_single_entries() {
+        assert!(
+            parse_command("buy cobblestone 64, glass")
+                .unwrap_err()
+                .contains("Usage: buy <item> <qty>, <item> <qty>")
+        );
+        assert!(
+            parse_command("buy cobblestone 64, glass 32 max 10")
+                .unwrap_err()
+                .contains("Usage")
+        );
+        assert!(
+            parse_command("sell cobblestone 64, minecraft:cobblestone 1")
+                .unwrap_err()
+                .contains("listed twice")
+        );
+        assert!(
+            parse_command("buy cobblestone 64,")
+                .unwrap_err()
+                .contains("Usage")
+        );
+        assert!(parse_command("buy cobblestone 64, glass 0").is_err());
+    }
+
+    #[test]
+    fn basket_caps_distinct_items_at_offer_slots() {
+        let entries: Vec<String> = (0..13).map(|i| format!("item{} 1", i)).collect();
+        let err = parse_command(&format!("buy {}", entries.join(", "))).unwrap_err();
+        assert!(err.contains("at most 12"), "{}", err);
+    }
+
+    // ---- swap --------------------------------------------------------------
+
+    #[test]
+    fn swap_parses_from_quantity_to() {
+        assert_eq!(
+            parse_command("swap iron_ingot 64 minecraft:gold_ingot").unwrap(),
+            Command::Swap {
+                from: ItemId::new("iron_ingot").unwrap(),
+                quantity: Quantity::items(64),
+                to: ItemId::new("gold_ingot").unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn swap_rejects_same_item_diamond_and_missing_target() {
+        assert!(
+            parse_command("swap iron_ingot 64 iron_ingot")
+                .unwrap_err()
+                .contains("itself")
+        );
+        assert!(
+            parse_command("swap iron_ingot 64 diamond")
+                .unwrap_err()
+                .contains("buy or sell")
+        );
+        assert!(
+            parse_command("swap iron_ingot 64")
+                .unwrap_err()
+                .contains("Usage: swap")
+        );
+        assert!(
+            parse_command("swap iron_ingot many gold_ingot")
+                .unwrap_err()
+                .contains("swap")
+        );
+    }
+
+    // ---- lp ----------------------------------------------------------------
+
+    #[test]
+    fn lp_parses_add_remove_and_positions() {
+        assert_eq!(parse_command("lp").unwrap(), Command::LpPositions);
+        assert_eq!(
+            parse_command("lp add iron_ingot 64").unwrap(),
+            Command::LpAdd {
+                item: ItemId::new("iron_ingot").unwrap(),
+                quantity: Quantity::items(64),
+            }
+        );
+        assert_eq!(
+            parse_command("lp remove iron_ingot 12.5").unwrap(),
+            Command::LpRemove {
+                item: ItemId::new("iron_ingot").unwrap(),
+                shares: Some(12.5),
+            }
+        );
+        assert_eq!(
+            parse_command("lp remove iron_ingot ALL").unwrap(),
+            Command::LpRemove {
+                item: ItemId::new("iron_ingot").unwrap(),
+                shares: None,
+            }
+        );
+    }
+
+    #[test]
+    fn lp_rejects_bad_shares_and_unknown_action() {
+        for bad in ["0", "-3", "NaN", "inf", "lots"] {
+            let err = parse_command(&format!("lp remove iron_ingot {}", bad)).unwrap_err();
+            assert!(err.contains("Invalid share amount"), "{}: {}", bad, err);
+        }
+        assert!(
+            parse_command("lp remove iron_ingot")
+                .unwrap_err()
+                .contains("Usage: lp remove")
+        );
+        assert!(parse_command("lp swap").unwrap_err().contains("Usage: lp"));
+        assert!(
+            parse_command("lp add iron_ingot")
+                .unwrap_err()
+                .contains("lp add")
+        );
+    }
+
     #[test]
     fn cancel_without_id_reports_usage() {
         let err = parse_command("cancel").unwrap_err();
@@ -751,7 +2268,7 @@ mod tests {
             parse_command("additem diamond 100").unwrap(),
             Command::AddItem {
                 item: ItemId::new("diamond").unwrap(),
-                quantity: 100
+                quantity: Quantity::items(100)
             }
         );
     }
@@ -762,7 +2279,7 @@ mod tests {
             parse_command("ai diamond 100").unwrap(),
             Command::AddItem {
                 item: ItemId::new("diamond").unwrap(),
-                quantity: 100
+                quantity: Quantity::items(100)
             }
         );
     }
@@ -780,7 +2297,7 @@ mod tests {
             parse_command("removeitem coal 50").unwrap(),
             Command::RemoveItem {
                 item: ItemId::new("coal").unwrap(),
-                quantity: 50
+                quantity: Quantity::items(50)
             }
         );
     }
@@ -791,7 +2308,7 @@ mod tests {
             parse_command("ri coal 50").unwrap(),
             Command::RemoveItem {
                 item: ItemId::new("coal").unwrap(),
-                quantity: 50
+                quantity: Quantity::items(50)
             }
         );
     }
@@ -911,4 +2428,176 @@ mod tests {
             }
         );
     }
+
+    // ---- quantity shorthands -----------------------------------------------
+
+    fn buy_quantity(input: &str) -> Quantity {
+        match parse_command(input).unwrap() {
+            Command::Buy { quantity, .. } => quantity,
+            other => panic!("expected Buy, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stacks_and_shulkers_resolve_against_the_stack_size() {
+        for input in ["buy cobblestone 3s", "buy cobblestone 3 stacks"] {
+            let quantity = buy_quantity(input);
+            assert_eq!(quantity.resolve(64), Ok(192), "{input}");
+            assert_eq!(quantity.resolve(16), Ok(48), "{input}");
+        }
+        for input in ["buy cobblestone 1sh", "buy cobblestone 1 Shulker"] {
+            assert_eq!(buy_quantity(input).resolve(64), Ok(1728), "{input}");
+            assert_eq!(buy_quantity(input).resolve(1), Ok(27), "{input}");
+        }
+    }
+
+    #[test]
+    fn quantity_terms_add_and_subtract() {
+        for input in [
+            "buy cobblestone 2s+10",
+            "buy cobblestone 2s + 10",
+            "buy cobblestone 2 stacks +10",
+        ] {
+            assert_eq!(buy_quantity(input).resolve(64), Ok(138), "{input}");
+        }
+        assert_eq!(buy_quantity("buy glass 1sh-1").resolve(64), Ok(1727));
+        // Plain arithmetic needs no stack size and is settled at once.
+        assert_eq!(buy_quantity("buy glass 64+10"), Quantity::items(74));
+    }
+
+    #[test]
+    fn stack_quantities_work_after_keywords_and_in_baskets() {
+        assert!(matches!(
+            parse_command("sell cobblestone 2 stacks min 3").unwrap(),
+            Command::Sell { quantity, min_payout: Some(3.0), .. } if quantity.resolve(64) == Ok(128)
+        ));
+        let Command::Basket { lines, .. } =
+            parse_command("buy cobblestone 1 stack + 5, glass 2s").unwrap()
+        else {
+            panic!("expected a basket");
+        };
+        assert_eq!(lines[0].1.resolve(64), Ok(69));
+        assert_eq!(lines[1].1.resolve(64), Ok(128));
+        assert!(matches!(
+            parse_command("escrow sell Steve cobblestone 1 sh 10").unwrap(),
+            Command::EscrowSell { quantity, price: 10.0, .. } if quantity.resolve(64) == Ok(1728)
+        ));
+    }
+
+    #[test]
+    fn quantities_that_no_stack_size_makes_valid_are_rejected_when_parsed() {
+        for input in [
+            "buy cobblestone 0s",
+            "buy cobblestone 1s-64",
+            "buy cobblestone 10-10",
+        ] {
+            let err = parse_command(input).unwrap_err();
+            assert!(err.contains("at least 1"), "{input}: {err}");
+        }
+        let err = parse_command("buy cobblestone 100000sh").unwrap_err();
+        assert!(err.contains("Maximum"), "{err}");
+        for input in [
+            "buy cobblestone 3x",
+            "buy cobblestone 2s+",
+            "buy cobblestone s",
+        ] {
+            let err = parse_command(input).unwrap_err();
+            assert!(err.contains("Invalid quantity"), "{input}: {err}");
+        }
+    }
+
+    #[test]
+    fn unit_words_only_join_a_number() {
+        // `s` is also the sell alias and an item is never a unit.
+        assert_eq!(
+            join_quantity_words("s cobblestone 3 s"),
+            ["s", "cobblestone", "3s"]
+        );
+        assert_eq!(
+            join_quantity_words("buy music_disc_13 1 + 2"),
+            ["buy", "music_disc_13", "1+2"]
+        );
+        // Memos are free text, so only quantity verbs glue words.
+        assert!(matches!(
+            parse_command("pay Steve 5 s").unwrap(),
+            Command::Pay { amount: 5.0, memo: Some(m), .. } if m == "s"
+        ));
+    }
+
+    #[test]
+    fn quantity_display_is_canonical() {
+        let quantity = buy_quantity("buy cobblestone 10 + 2s + 1sh - 1s");
+        assert_eq!(quantity.to_string(), "1sh+1s+10");
+        assert_eq!(Quantity::items(64).to_string(), "64");
+    }
+
+    use proptest::prelude::*;
+
+    proptest! {
+        /// The parser never panics, whatever is whispered.
+        #[test]
+        fn parse_command_never_panics(input in "\\PC{0,40}") {
+            let _ = parse_command(&input);
+        }
+
+        /// Nor on strings built from quantity characters, which reach the
+        /// term parser rather than bouncing off the verb.
+        #[test]
+        fn quantity_parser_never_panics(raw in "[0-9sSthulkerack+ -]{0,24}") {
+            let _ = parse_command(&format!("buy cobblestone {raw}"));
+        }
+
+        /// `<n>sh+<m>s+<k>` parses, spelled short or long, to the sum of its
+        /// terms at any stack size.
+        #[test]
+        fn stack_terms_resolve_to_their_sum(
+            shulkers in 0u32..3,
+            stacks in 0u32..100,
+            items in 0u32..1_000,
+            stack_size in prop::sample::select(STACK_SIZES.to_vec()),
+        ) {
+            let per_shulker = SHULKER_BOX_SLOTS as u32;
+            let expected = (shulkers * per_shulker + stacks) * stack_size as u32 + items;
+            prop_assume!(expected >= 1);
+            for raw in [
+                format!("{shulkers}sh+{stacks}s+{items}"),
+                format!("{shulkers} shulkers + {stacks} stacks + {items}"),
+            ] {
+                match parse_command(&format!("sell cobblestone {raw}")) {
+                    Ok(Command::Sell { quantity, .. }) => {
+                        prop_assert_eq!(quantity.resolve(stack_size), Ok(expected), "{}", raw);
+                    }
+                    // Only rejected when no stack size gives at least 1.
+                    Err(err) => prop_assert!(err.contains("at least 1"), "{}: {}", raw, err),
+                    Ok(other) => prop_assert!(false, "{}: {:?}", raw, other),
+                }
+            }
+        }
+
+        /// A quantity prints in a form that parses back to itself.
+        #[test]
+        fn display_round_trips(
+            shulkers in 0i64..50,
+            stacks in -100i64..100,
+            items in -1_000i64..1_000,
+        ) {
+            let quantity = Quantity { items, stacks, shulkers };
+            prop_assume!(!quantity.to_string().starts_with('-'));
+            prop_assert_eq!(Quantity::parse_terms(&quantity.to_string()), Some(quantity));
+        }
+
+        /// A resolved quantity is always a valid plain quantity.
+        #[test]
+        fn resolved_quantities_are_in_range(
+            raw in "[0-9]{1,4}(s|sh)?([+-][0-9]{1,4}(s|sh)?){0,3}",
+        ) {
+            if let Some(quantity) = Quantity::parse_terms(&raw) {
+                for size in STACK_SIZES {
+                    if let Ok(n) = quantity.resolve(size) {
+                        prop_assert!((1..=MAX_TRANSACTION_QUANTITY as u32).contains(&n));
+                    }
+                }
+            }
+        }
+    }
 }
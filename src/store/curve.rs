@@ -0,0 +1,631 @@
+//! Per-pair pricing curves.
+//!
+//! Every pair prices through a [`PricingCurve`] stored on `Pair::curve`.
+//! `ConstantProduct` is the original `x * y = k` AMM from [`super::pricing`]
+//! and the default, so pair files written before curves existed price
+//! exactly as before. The others:
+//!
+//! - `Fixed`: operator-set price per item. Buys are limited by `item_stock`,
+//!   sells by the diamond reserve and an optional `max_stock` cap.
+//! - `Linear`: bonding curve `p(x) = base_price + slope * (anchor_stock - x)`.
+//!   Price rises as the store's stock falls below `anchor_stock`. Costs are
+//!   the exact integral of `p` over the traded range, so a buy followed by
+//!   the matching sell is lossless before fees.
+//! - `StableSwap`: Curve-style invariant with the item valued at `peg`
+//!   diamonds. Near balance (`item_stock * peg ≈ currency_stock`) it prices
+//!   close to `peg` with little slippage; `amplification` controls how flat
+//!   that region is. Meant for items pegged to diamonds (diamond blocks).
+//!
+//! All variants take the same inputs as `buy_cost_pure` / `sell_payout_pure`
+//! and return `None` under the same conditions (invalid fee, non-positive
+//! amount, a trade the reserves cannot cover, or a non-finite result), plus
+//! whenever the curve's own parameters fail [`PricingCurve::validate`].
+
+use serde::{Deserialize, Serialize};
+
+use super::pricing::{buy_cost_pure, reserves_sufficient, sell_payout_pure, validate_fee};
+
+/// Newton iterations for the stable-swap solvers. The Curve reference
+/// implementation uses 255; convergence normally takes well under 10.
+const STABLESWAP_MAX_ITERATIONS: usize = 255;
+
+/// Relative convergence threshold for the stable-swap solvers.
+const STABLESWAP_EPSILON: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PricingCurve {
+    /// `x * y = k` (see [`buy_cost_pure`]).
+    #[default]
+    ConstantProduct,
+    /// Flat `price` per item. `max_stock` stops sells once the store holds
+    /// that many items.
+    Fixed {
+        price: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_stock: Option<i32>,
+    },
+    /// `p(x) = base_price + slope * (anchor_stock - x)` per item.
+    Linear {
+        base_price: f64,
+        slope: f64,
+        anchor_stock: i32,
+    },
+    /// Two-asset stable-swap invariant with the item worth `peg` diamonds.
+    StableSwap { peg: f64, amplification: f64 },
+}
+
+impl PricingCurve {
+    pub fn is_constant_product(&self) -> bool {
+        matches!(self, PricingCurve::ConstantProduct)
+    }
+
+    /// Check the curve's parameters. Pricing refuses to quote an invalid
+    /// curve rather than falling back to another one, so a typo in a pair
+    /// file makes the pair untradable instead of mispriced.
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            PricingCurve::ConstantProduct => Ok(()),
+            PricingCurve::Fixed { price, max_stock } => {
+                if !(price.is_finite() && price > 0.0) {
+                    return Err(format!("fixed price must be positive, got {price}"));
+                }
+                if let Some(max) = max_stock
+                    && max <= 0
+                {
+                    return Err(format!("max_stock must be positive, got {max}"));
+                }
+                Ok(())
+            }
+            PricingCurve::Linear {
+                base_price,
+                slope,
+                anchor_stock,
+            } => {
+                if !(base_price.is_finite() && base_price > 0.0) {
+                    return Err(format!("base_price must be positive, got {base_price}"));
+                }
+                if !(slope.is_finite() && slope >= 0.0) {
+                    return Err(format!("slope must be non-negative, got {slope}"));
+                }
+                if anchor_stock < 0 {
+                    return Err(format!(
+                        "anchor_stock must be non-negative, got {anchor_stock}"
+                    ));
+                }
+                Ok(())
+            }
+            PricingCurve::StableSwap { peg, amplification } => {
+                if !(peg.is_finite() && peg > 0.0) {
+                    return Err(format!("peg must be positive, got {peg}"));
+                }
+                if !(amplification.is_finite() && amplification >= 1.0) {
+                    return Err(format!(
+                        "amplification must be at least 1, got {amplification}"
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Short operator-facing description, e.g. `fixed 2.5000/ea`.
+    pub fn label(&self) -> String {
+        match *self {
+            PricingCurve::ConstantProduct => "constant-product".to_string(),
+            PricingCurve::Fixed { price, max_stock } => match max_stock {
+                Some(max) => format!("fixed {price:.4}/ea (max stock {max})"),
+                None => format!("fixed {price:.4}/ea"),
+            },
+            PricingCurve::Linear {
+                base_price,
+                slope,
+                anchor_stock,
+            } => format!("linear {base_price:.4}/ea at stock {anchor_stock}, slope {slope}"),
+            PricingCurve::StableSwap { peg, amplification } => {
+                format!("stable-swap peg {peg:.4}, A={amplification}")
+            }
+        }
+    }
+
+    /// Cost in diamonds to buy `amount` items, fee included.
+    pub fn buy_cost(
+        &self,
+        item_stock: i32,
+        currency_stock: f64,
+        amount: i32,
+        fee: f64,
+    ) -> Option<f64> {
+        let base = match *self {
+            PricingCurve::ConstantProduct => {
+                return buy_cost_pure(item_stock, currency_stock, amount, fee);
+            }
+            _ if !self.accepts(fee, amount) => return None,
+            PricingCurve::Fixed { price, .. } => {
+                if amount > item_stock {
+                    return None;
+                }
+                price * f64::from(amount)
+            }
+            PricingCurve::Linear { .. } => {
+                if amount > item_stock {
+                    return None;
+                }
+                // Price rises as stock falls, so the cheapest unit in the
+                // range is the first one, priced at the current stock.
+                if self.linear_price(f64::from(item_stock))? <= 0.0 {
+                    return None;
+                }
+                let mid = f64::from(item_stock) - f64::from(amount) / 2.0;
+                self.linear_price(mid)? * f64::from(amount)
+            }
+            PricingCurve::StableSwap { peg, amplification } => {
+                if !reserves_sufficient(item_stock, currency_stock) || amount >= item_stock {
+                    return None;
+                }
+                let x = f64::from(item_stock) * peg;
+                let d = stableswap_d(x, currency_stock, amplification)?;
+                let new_x = f64::from(item_stock - amount) * peg;
+                let new_y = stableswap_y(new_x, d, amplification)?;
+                new_y - currency_stock
+            }
+        };
+        let cost = base * (1.0 + fee);
+        (cost.is_finite() && cost > 0.0).then_some(cost)
+    }
+
+    /// Payout in diamonds for selling `amount` items, fee deducted.
+    pub fn sell_payout(
+        &self,
+        item_stock: i32,
+        currency_stock: f64,
+        amount: i32,
+        fee: f64,
+    ) -> Option<f64> {
+        let base = match *self {
+            PricingCurve::ConstantProduct => {
+                return sell_payout_pure(item_stock, currency_stock, amount, fee);
+            }
+            _ if !self.accepts(fee, amount) => return None,
+            PricingCurve::Fixed { price, max_stock } => {
+                let new_stock = item_stock.checked_add(amount)?;
+                if max_stock.is_some_and(|max| new_stock > max) {
+                    return None;
+                }
+                price * f64::from(amount)
+            }
+            PricingCurve::Linear { .. } => {
+                let new_stock = item_stock.checked_add(amount)?;
+                // The last unit sold is the cheapest; once the curve reaches
+                // zero the store stops buying.
+                if self.linear_price(f64::from(new_stock))? <= 0.0 {
+                    return None;
+                }
+                let mid = f64::from(item_stock) + f64::from(amount) / 2.0;
+                self.linear_price(mid)? * f64::from(amount)
+            }
+            PricingCurve::StableSwap { peg, amplification } => {
+                if !reserves_sufficient(item_stock, currency_stock) {
+                    return None;
+                }
+                let x = f64::from(item_stock) * peg;
+                let d = stableswap_d(x, currency_stock, amplification)?;
+                let new_x = f64::from(item_stock.checked_add(amount)?) * peg;
+                let new_y = stableswap_y(new_x, d, amplification)?;
+                currency_stock - new_y
+            }
+        };
+        // Fixed and linear curves pay out of the reserve without the
+        // asymptote that protects constant-product pools, so the reserve
+        // itself is the limit.
+        if base >= currency_stock {
+            return None;
+        }
+        let payout = base * (1.0 - fee);
+        (payout.is_finite() && payout > 0.0).then_some(payout)
+    }
+
+    /// Marginal price per item before fees, for display.
+    pub fn mid_price(&self, item_stock: i32, currency_stock: f64) -> Option<f64> {
+        if self.validate().is_err() {
+            return None;
+        }
+        let price = match *self {
+            PricingCurve::ConstantProduct => {
+                if !reserves_sufficient(item_stock, currency_stock) {
+                    return None;
+                }
+                currency_stock / f64::from(item_stock)
+            }
+            PricingCurve::Fixed { price, .. } => price,
+            PricingCurve::Linear { .. } => self.linear_price(f64::from(item_stock))?,
+            PricingCurve::StableSwap { peg, amplification } => {
+                if !reserves_sufficient(item_stock, currency_stock) {
+                    return None;
+                }
+                let x = f64::from(item_stock) * peg;
+                let y = currency_stock;
+                let d = stableswap_d(x, y, amplification)?;
+                // Implicit derivative of the invariant: -dy/dx = F_x / F_y.
+                let ann = 4.0 * amplification;
+                let d3 = d * d * d;
+                let f_x = ann + d3 / (4.0 * x * x * y);
+                let f_y = ann + d3 / (4.0 * x * y * y);
+                peg * f_x / f_y
+            }
+        };
+        (price.is_finite() && price > 0.0).then_some(price)
+    }
+
+    /// Shared guards for the non-constant-product variants.
+    fn accepts(&self, fee: f64, amount: i32) -> bool {
+        if !validate_fee(fee) {
+            tracing::warn!("Invalid fee rate: {}", fee);
+            return false;
+        }
+        amount > 0 && self.validate().is_ok()
+    }
+
+    fn linear_price(&self, stock: f64) -> Option<f64> {
+        match *self {
+            PricingCurve::Linear {
+                base_price,
+                slope,
+                anchor_stock,
+            } => Some(base_price + slope * (f64::from(anchor_stock) - stock)),
+            _ => None,
+        }
+    }
+}
+
+/// Solve the two-asset stable-swap invariant for `D` given balances `x`, `y`:
+/// `4A(x + y) + D = 4AD + D³ / (4xy)`.
+fn stableswap_d(x: f64, y: f64, amplification: f64) -> Option<f64> {
+    if !(x > 0.0 && y > 0.0) {
+        return None;
+    }
+    let ann = 4.0 * amplification;
+    let s = x + y;
+    let mut d = s;
+    for _ in 0..STABLESWAP_MAX_ITERATIONS {
+        let d_p = d * d * d / (4.0 * x * y);
+        let prev = d;
+        d = (ann * s + 2.0 * d_p) * d / ((ann - 1.0) * d + 3.0 * d_p);
+        if !d.is_finite() {
+            return None;
+        }
+        if (d - prev).abs() <= STABLESWAP_EPSILON * d {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Solve the invariant for the diamond balance `y` given item value `x`
+/// and a fixed `D`.
+fn stableswap_y(x: f64, d: f64, amplification: f64) -> Option<f64> {
+    if !(x > 0.0 && d > 0.0) {
+        return None;
+    }
+    let ann = 4.0 * amplification;
+    let c = d * d * d / (4.0 * x * ann);
+    let b = x + d / ann;
+    let mut y = d;
+    for _ in 0..STABLESWAP_MAX_ITERATIONS {
+        let prev = y;
+        y = (y * y + c) / (2.0 * y + b - d);
+        if !y.is_finite() {
+            return None;
+        }
+        if (y - prev).abs() <= STABLESWAP_EPSILON * y.max(1.0) {
+            return (y > 0.0).then_some(y);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FEE: f64 = 0.125;
+
+    const FIXED: PricingCurve = PricingCurve::Fixed {
+        price: 2.5,
+        max_stock: Some(1_000),
+    };
+    const LINEAR: PricingCurve = PricingCurve::Linear {
+        base_price: 4.0,
+        slope: 0.01,
+        anchor_stock: 500,
+    };
+    const STABLE: PricingCurve = PricingCurve::StableSwap {
+        peg: 9.0,
+        amplification: 100.0,
+    };
+
+    #[test]
+    fn default_curve_is_constant_product_and_matches_pricing() {
+        let curve = PricingCurve::default();
+        assert!(curve.is_constant_product());
+        assert_eq!(
+            curve.buy_cost(100, 1000.0, 10, TEST_FEE),
+            buy_cost_pure(100, 1000.0, 10, TEST_FEE)
+        );
+        assert_eq!(
+            curve.sell_payout(100, 1000.0, 10, TEST_FEE),
+            sell_payout_pure(100, 1000.0, 10, TEST_FEE)
+        );
+    }
+
+    #[test]
+    fn curve_serializes_with_kind_tag() {
+        let json = serde_json::to_string(&FIXED).unwrap();
+        assert_eq!(json, r#"{"kind":"fixed","price":2.5,"max_stock":1000}"#);
+        let back: PricingCurve =
+            serde_json::from_str(r#"{"kind":"stable_swap","peg":9.0,"amplification":100.0}"#)
+                .unwrap();
+        assert_eq!(back, STABLE);
+    }
+
+    #[test]
+    fn validate_rejects_bad_parameters() {
+        let bad = [
+            PricingCurve::Fixed {
+                price: 0.0,
+                max_stock: None,
+            },
+            PricingCurve::Fixed {
+                price: 1.0,
+                max_stock: Some(0),
+            },
+            PricingCurve::Linear {
+                base_price: 1.0,
+                slope: -0.1,
+                anchor_stock: 10,
+            },
+            PricingCurve::StableSwap {
+                peg: 9.0,
+                amplification: 0.5,
+            },
+            PricingCurve::StableSwap {
+                peg: f64::NAN,
+                amplification: 10.0,
+            },
+        ];
+        for curve in bad {
+            assert!(curve.validate().is_err(), "{:?}", curve);
+            assert_eq!(
+                curve.buy_cost(100, 1000.0, 1, TEST_FEE),
+                None,
+                "{:?}",
+                curve
+            );
+            assert_eq!(
+                curve.sell_payout(100, 1000.0, 1, TEST_FEE),
+                None,
+                "{:?}",
+                curve
+            );
+        }
+    }
+
+    #[test]
+    fn fixed_respects_stock_limits() {
+        // Buys may take the whole stock but no more.
+        assert!(FIXED.buy_cost(10, 100.0, 10, TEST_FEE).is_some());
+        assert_eq!(FIXED.buy_cost(10, 100.0, 11, TEST_FEE), None);
+        // Sells stop at max_stock and at the diamond reserve.
+        assert!(FIXED.sell_payout(990, 1_000.0, 10, TEST_FEE).is_some());
+        assert_eq!(FIXED.sell_payout(990, 1_000.0, 11, TEST_FEE), None);
+        assert_eq!(FIXED.sell_payout(0, 20.0, 10, TEST_FEE), None);
+    }
+
+    #[test]
+    fn linear_stops_buying_where_price_reaches_zero() {
+        // p(x) = 4 + 0.01 * (500 - x) hits zero at x = 900.
+        assert!(LINEAR.sell_payout(800, 10_000.0, 50, TEST_FEE).is_some());
+        assert_eq!(LINEAR.sell_payout(800, 10_000.0, 150, TEST_FEE), None);
+    }
+
+    #[test]
+    fn stableswap_prices_near_peg_when_balanced() {
+        // 1000 blocks at 9 diamonds each against 9000 diamonds.
+        let mid = STABLE.mid_price(1_000, 9_000.0).unwrap();
+        assert!((mid - 9.0).abs() < 1e-6, "mid {}", mid);
+        let cost = STABLE.buy_cost(1_000, 9_000.0, 10, 0.0).unwrap();
+        assert!((cost / 10.0 - 9.0).abs() < 0.01, "per item {}", cost / 10.0);
+    }
+
+    #[test]
+    fn mid_price_matches_constant_product_ratio() {
+        let mid = PricingCurve::ConstantProduct
+            .mid_price(100, 1000.0)
+            .unwrap();
+        assert!((mid - 10.0).abs() < 1e-12);
+        assert_eq!(FIXED.mid_price(0, 0.0), Some(2.5));
+    }
+
+    // -- Property-based tests -------------------------------------------------
+    //
+    // Per-curve invariants, mirroring the constant-product proptests in
+    // `pricing`:
+    //   - fixed: cost is exactly linear in quantity; payout never exceeds
+    //     the reserve
+    //   - linear: per-item price rises with quantity; buy-then-sell-back is
+    //     lossless before fees (costs are path-independent integrals)
+    //   - stable-swap: `D` never decreases (fees only grow the pool); per-item
+    //     price rises with quantity
+    //   - all: positive spread, non-positive quantity rejected
+
+    use proptest::prelude::*;
+
+    fn any_curve() -> impl Strategy<Value = PricingCurve> {
+        prop_oneof![
+            Just(PricingCurve::ConstantProduct),
+            (0.01f64..1_000.0, proptest::option::of(1i32..100_000))
+                .prop_map(|(price, max_stock)| PricingCurve::Fixed { price, max_stock }),
+            (0.01f64..100.0, 0.0f64..1.0, 0i32..20_000).prop_map(
+                |(base_price, slope, anchor_stock)| PricingCurve::Linear {
+                    base_price,
+                    slope,
+                    anchor_stock,
+                }
+            ),
+            (0.1f64..100.0, 1.0f64..1_000.0)
+                .prop_map(|(peg, amplification)| PricingCurve::StableSwap { peg, amplification }),
+        ]
+    }
+
+    proptest! {
+        /// For the same reserves and quantity, buy cost exceeds sell payout
+        /// on every curve: the fee spread makes round trips lossy.
+        #[test]
+        fn every_curve_buy_cost_exceeds_sell_payout(
+            curve in any_curve(),
+            stock in 2i32..10_000,
+            currency in 10.0f64..100_000.0,
+            qty in 1i32..5_000,
+        ) {
+            let (Some(cost), Some(payout)) = (
+                curve.buy_cost(stock, currency, qty, TEST_FEE),
+                curve.sell_payout(stock, currency, qty, TEST_FEE),
+            ) else { return Ok(()); };
+            prop_assert!(cost > payout, "{:?}: cost {} <= payout {}", curve, cost, payout);
+        }
+
+        /// No curve quotes a zero or negative quantity.
+        #[test]
+        fn every_curve_rejects_non_positive_qty(
+            curve in any_curve(),
+            stock in 1i32..10_000,
+            currency in 10.0f64..100_000.0,
+            qty in -100i32..=0,
+        ) {
+            prop_assert!(curve.buy_cost(stock, currency, qty, TEST_FEE).is_none());
+            prop_assert!(curve.sell_payout(stock, currency, qty, TEST_FEE).is_none());
+        }
+
+        /// No curve pays out the whole diamond reserve.
+        #[test]
+        fn every_curve_sell_payout_bounded_by_currency(
+            curve in any_curve(),
+            stock in 1i32..10_000,
+            currency in 10.0f64..100_000.0,
+            qty in 1i32..100_000,
+        ) {
+            let Some(payout) = curve.sell_payout(stock, currency, qty, TEST_FEE) else { return Ok(()); };
+            prop_assert!(payout < currency, "{:?}: payout {} >= currency {}", curve, payout, currency);
+        }
+
+        /// A buy never takes more items than the store holds.
+        #[test]
+        fn every_curve_buy_bounded_by_stock(
+            curve in any_curve(),
+            stock in 0i32..10_000,
+            currency in 10.0f64..100_000.0,
+            extra in 1i32..1_000,
+        ) {
+            prop_assert!(curve.buy_cost(stock, currency, stock + extra, TEST_FEE).is_none());
+        }
+
+        /// Fixed pricing is exactly linear in quantity.
+        #[test]
+        fn fixed_cost_is_linear(
+            price in 0.01f64..1_000.0,
+            stock in 2i32..10_000,
+            n in 1i32..5_000,
+        ) {
+            prop_assume!(2 * n <= stock);
+            let curve = PricingCurve::Fixed { price, max_stock: None };
+            let (Some(c1), Some(c2)) = (
+                curve.buy_cost(stock, 0.0, n, TEST_FEE),
+                curve.buy_cost(stock, 0.0, 2 * n, TEST_FEE),
+            ) else { return Err(TestCaseError::fail("fixed buy within stock must quote")); };
+            prop_assert!((c2 - 2.0 * c1).abs() <= c1 * 1e-12, "{} vs 2 * {}", c2, c1);
+        }
+
+        /// On a linear curve with a positive slope, buying `n+1` items costs
+        /// strictly more per item than buying `n`.
+        #[test]
+        fn linear_buy_price_per_item_increases(
+            base_price in 0.01f64..100.0,
+            slope in 0.001f64..1.0,
+            anchor_stock in 0i32..20_000,
+            stock in 10i32..10_000,
+            n in 1i32..1_000,
+        ) {
+            prop_assume!(n < stock);
+            let curve = PricingCurve::Linear { base_price, slope, anchor_stock };
+            let (Some(c1), Some(c2)) = (
+                curve.buy_cost(stock, 0.0, n, TEST_FEE),
+                curve.buy_cost(stock, 0.0, n + 1, TEST_FEE),
+            ) else { return Ok(()); };
+            let p1 = c1 / f64::from(n);
+            let p2 = c2 / f64::from(n + 1);
+            prop_assert!(p2 > p1, "price per item did not increase: {} -> {}", p1, p2);
+        }
+
+        /// With fee == 0, buying `q` and selling `q` back returns exactly
+        /// what was paid: the curve itself extracts nothing.
+        #[test]
+        fn linear_round_trip_lossless_at_fee_zero(
+            base_price in 0.01f64..100.0,
+            slope in 0.0f64..1.0,
+            anchor_stock in 0i32..20_000,
+            stock in 2i32..10_000,
+            currency in 10.0f64..100_000.0,
+            qty in 1i32..5_000,
+        ) {
+            prop_assume!(qty <= stock);
+            let curve = PricingCurve::Linear { base_price, slope, anchor_stock };
+            let Some(cost) = curve.buy_cost(stock, currency, qty, 0.0) else { return Ok(()); };
+            let Some(payout) = curve.sell_payout(stock - qty, currency + cost, qty, 0.0) else { return Ok(()); };
+            prop_assert!((payout - cost).abs() <= cost * 1e-9, "paid {} got back {}", cost, payout);
+        }
+
+        /// After a stable-swap buy or sell, the invariant `D` of the new
+        /// reserves is at least the old one: fees only grow the pool.
+        #[test]
+        fn stableswap_never_decreases_d(
+            peg in 0.1f64..100.0,
+            amplification in 1.0f64..1_000.0,
+            stock in 2i32..10_000,
+            currency in 10.0f64..100_000.0,
+            qty in 1i32..5_000,
+        ) {
+            let curve = PricingCurve::StableSwap { peg, amplification };
+            let d_old = stableswap_d(f64::from(stock) * peg, currency, amplification).unwrap();
+            let tol = d_old * 1e-9;
+            if let Some(cost) = curve.buy_cost(stock, currency, qty, TEST_FEE) {
+                let d_new = stableswap_d(f64::from(stock - qty) * peg, currency + cost, amplification).unwrap();
+                prop_assert!(d_new + tol >= d_old, "buy: D {} -> {}", d_old, d_new);
+            }
+            if let Some(payout) = curve.sell_payout(stock, currency, qty, TEST_FEE) {
+                let d_new = stableswap_d(f64::from(stock + qty) * peg, currency - payout, amplification).unwrap();
+                prop_assert!(d_new + tol >= d_old, "sell: D {} -> {}", d_old, d_new);
+            }
+        }
+
+        /// Stable-swap slippage: buying `n+1` items costs at least as much
+        /// per item as buying `n`.
+        #[test]
+        fn stableswap_buy_price_per_item_non_decreasing(
+            peg in 0.1f64..100.0,
+            amplification in 1.0f64..1_000.0,
+            stock in 10i32..10_000,
+            currency in 10.0f64..100_000.0,
+            n in 1i32..1_000,
+        ) {
+            prop_assume!(n + 1 < stock);
+            let curve = PricingCurve::StableSwap { peg, amplification };
+            let (Some(c1), Some(c2)) = (
+                curve.buy_cost(stock, currency, n, TEST_FEE),
+                curve.buy_cost(stock, currency, n + 1, TEST_FEE),
+            ) else { return Ok(()); };
+            let p1 = c1 / f64::from(n);
+            let p2 = c2 / f64::from(n + 1);
+            prop_assert!(p2 + p1 * 1e-9 >= p1, "price per item decreased: {} -> {}", p1, p2);
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Open player-to-player escrows.
+//!
+//! `escrow sell <buyer> <item> <qty> <price>` opens an escrow: the seller
+//! trades the items to the bot, which keeps them in escrow chests (see
+//! `types::chest::ChestClass`) until the buyer pays with `escrow accept
+//! E<id>`, or either side calls it off with `escrow cancel E<id>`. The
+//! `/trade` steps run as queued orders; `handlers::escrow` holds the logic
+//! and its `sweep` expires escrows the buyer never accepted.
+//!
+//! Opening is written to `ESCROW_FILE` before the in-memory view changes,
+//! like payment requests, so a failed write opens nothing. Every later
+//! change follows a chest or `/trade` that already happened and cannot be
+//! refused: it is applied in memory first, and a failed write is retried by
+//! the sweep. A corrupt file is quarantined and the store starts with no
+//! escrows; the items stay in their escrow chests for an operator.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{ESCROW_FILE, MAX_ESCROWS, MAX_ESCROWS_PER_USER};
+use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
+
+/// Per-process disambiguator for quarantined escrow files. Mirrors the
+/// same-named static in `queue.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Where an escrow is in its life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscrowStatus {
+    /// Waiting for the seller's `/trade`; nothing is held yet.
+    Depositing,
+    /// The items are in escrow chests, waiting for the buyer.
+    Held,
+    /// The buyer accepted; their payment `/trade` is queued or running.
+    Releasing,
+    /// Cancelled or expired; the return `/trade` to the seller is queued or
+    /// running.
+    Returning,
+    /// The items are held but a return failed. Only the seller's `escrow
+    /// cancel` moves it on.
+    Unclaimed,
+}
+
+impl EscrowStatus {
+    /// Whether the escrow chests hold this escrow's items.
+    pub fn holds_items(self) -> bool {
+        !matches!(self, Self::Depositing)
+    }
+}
+
+/// An escrowed sale from `seller` to `buyer`. Persisted as part of
+/// [`EscrowBook`]; any field rename is a persisted-format break.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Escrow {
+    pub id: u64,
+    pub seller_uuid: String,
+    pub seller_name: String,
+    pub buyer_uuid: String,
+    pub buyer_name: String,
+    pub item: String,
+    pub quantity: i32,
+    /// Diamonds the buyer pays for the whole lot.
+    pub price: f64,
+    pub status: EscrowStatus,
+    pub created_at: DateTime<Utc>,
+    /// When a `Held` escrow goes back to the seller unaccepted.
+    pub expires_at: DateTime<Utc>,
+    /// The queued order driving a `Depositing`, `Releasing` or `Returning`
+    /// escrow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<u64>,
+}
+
+impl Escrow {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EscrowPersist {
+    escrows: Vec<Escrow>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+pub struct EscrowBook {
+    /// `None` for test stores: escrows are kept in memory only.
+    path: Option<PathBuf>,
+    escrows: Vec<Escrow>,
+    /// Persisted so ids never recycle across restarts. Shown as `E<id>`.
+    next_id: u64,
+    /// A change is in memory but not yet on disk.
+    unsaved: bool,
+}
+
+impl Default for EscrowBook {
+    fn default() -> Self {
+        Self {
+            path: None,
+            escrows: Vec::new(),
+            next_id: 1,
+            unsaved: false,
+        }
+    }
+}
+
+impl EscrowBook {
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(Path::new(ESCROW_FILE))
+    }
+
+    /// Path-parameterized load. A corrupt file is quarantined to an
+    /// `escrow.corrupt-<unix_ms>-<seq>.json` sibling and the book starts
+    /// empty, like the queue and the payment requests.
+    pub(crate) fn load_from(path: &Path) -> io::Result<Self> {
+        let mut loaded = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(loaded),
+            Err(e) => return Err(e),
+        };
+        match serde_json::from_str::<EscrowPersist>(&contents) {
+            Ok(data) => {
+                info!(
+                    "[Escrow] Loaded {} escrow(s) from {:?} (next_id={})",
+                    data.escrows.len(),
+                    path,
+                    data.next_id
+                );
+                loaded.escrows = data.escrows;
+                loaded.next_id = data.next_id;
+            }
+            Err(parse_err) => {
+                let archived = pick_archive_path(path.parent(), "escrow", "corrupt", &ARCHIVE_SEQ)?;
+                archive_aside(path, &archived)?;
+                error!(
+                    "[Escrow] ESCROWS LOST: corrupt {:?} moved to {:?}; escrowed items stay in their chests; parse error: {}",
+                    path, archived, parse_err
+                );
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn write_projection(&self, escrows: &[Escrow], next_id: u64) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = EscrowPersist {
+            escrows: escrows.to_vec(),
+            next_id,
+        };
+        let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+        write_atomic(path, &json)
+    }
+
+    /// Write the current book, or flag it for [`Self::flush`].
+    fn persist(&mut self) {
+        match self.write_projection(&self.escrows, self.next_id) {
+            Ok(()) => self.unsaved = false,
+            Err(e) => {
+                error!("[Escrow] Failed to persist escrows: {} (will retry)", e);
+                self.unsaved = true;
+            }
+        }
+    }
+
+    /// Retry a write that failed earlier. Returns whether the book is now
+    /// on disk.
+    pub fn flush(&mut self) -> bool {
+        if self.unsaved {
+            self.persist();
+        }
+        !self.unsaved
+    }
+
+    /// Open a `Depositing` escrow of `quantity` `item` for `price` diamonds.
+    ///
+    /// `Err(message)` when a cap is hit or the save fails; the message is
+    /// safe to whisper.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &mut self,
+        seller_uuid: &str,
+        seller_name: &str,
+        buyer_uuid: &str,
+        buyer_name: &str,
+        item: &str,
+        quantity: i32,
+        price: f64,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Result<Escrow, String> {
+        if self.escrows.len() >= MAX_ESCROWS {
+            warn!(
+                "[Escrow] Rejected escrow from {} ({}): {} open, cap {}",
+                seller_name,
+                seller_uuid,
+                self.escrows.len(),
+                MAX_ESCROWS
+            );
+            return Err("Too many open escrows right now. Please try again later.".to_string());
+        }
+        let selling = self
+            .escrows
+            .iter()
+            .filter(|e| e.seller_uuid == seller_uuid)
+            .count();
+        if selling >= MAX_ESCROWS_PER_USER {
+            return Err(format!(
+                "You already have {} open escrows (max {}). Cancel one with 'escrow cancel E<id>'.",
+                selling, MAX_ESCROWS_PER_USER
+            ));
+        }
+
+        let escrow = Escrow {
+            id: self.next_id,
+            seller_uuid: seller_uuid.to_string(),
+            seller_name: seller_name.to_string(),
+            buyer_uuid: buyer_uuid.to_string(),
+            buyer_name: buyer_name.to_string(),
+            item: item.to_string(),
+            quantity,
+            price,
+            status: EscrowStatus::Depositing,
+            created_at: now,
+            expires_at: now + ttl,
+            order_id: None,
+        };
+        let mut projected = self.escrows.clone();
+        projected.push(escrow.clone());
+        if let Err(e) = self.write_projection(&projected, escrow.id + 1) {
+            error!(
+                "[Escrow] Failed to persist escrow E{}: {} (not opened)",
+                escrow.id, e
+            );
+            return Err("Escrow is temporarily unavailable, please retry.".to_string());
+        }
+        self.escrows = projected;
+        self.next_id = escrow.id + 1;
+        self.unsaved = false;
+        info!(
+            "[Escrow] E{} opened: {} sells {} {} to {} for {}",
+            escrow.id,
+            escrow.seller_name,
+            escrow.quantity,
+            escrow.item,
+            escrow.buyer_name,
+            escrow.price
+        );
+        Ok(escrow)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Escrow> {
+        self.escrows.iter().find(|e| e.id == id)
+    }
+
+    /// Apply `change` to escrow `id` and persist. Returns the updated
+    /// escrow, or `None` when there is no such escrow.
+    pub fn update(&mut self, id: u64, change: impl FnOnce(&mut Escrow)) -> Option<Escrow> {
+        let escrow = self.escrows.iter_mut().find(|e| e.id == id)?;
+        change(escrow);
+        let updated = escrow.clone();
+        self.persist();
+        Some(updated)
+    }
+
+    /// Close escrow `id` and persist.
+    pub fn remove(&mut self, id: u64) -> Option<Escrow> {
+        let pos = self.escrows.iter().position(|e| e.id == id)?;
+        let removed = self.escrows.remove(pos);
+        self.persist();
+        info!("[Escrow] E{} closed ({:?})", id, removed.status);
+        Some(removed)
+    }
+
+    /// Escrows `user_uuid` sells or buys through, oldest first.
+    pub fn involving(&self, user_uuid: &str) -> Vec<&Escrow> {
+        self.escrows
+            .iter()
+            .filter(|e| e.seller_uuid == user_uuid || e.buyer_uuid == user_uuid)
+            .collect()
+    }
+
+    /// `Held` escrows past their deadline.
+    pub fn expired_ids(&self, now: DateTime<Utc>) -> Vec<u64> {
+        self.escrows
+            .iter()
+            .filter(|e| e.status == EscrowStatus::Held && e.is_expired(now))
+            .map(|e| e.id)
+            .collect()
+    }
+
+    /// Escrows waiting on a queued order, with that order's id.
+    pub fn awaiting_orders(&self) -> Vec<(u64, Option<u64>)> {
+        self.escrows
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e.status,
+                    EscrowStatus::Depositing | EscrowStatus::Releasing | EscrowStatus::Returning
+                )
+            })
+            .map(|e| (e.id, e.order_id))
+            .collect()
+    }
+
+    /// How many `item` the escrows that hold items account for, leaving out
+    /// escrow `except`.
+    pub fn held_quantity(&self, item: &str, except: u64) -> i32 {
+        self.escrows
+            .iter()
+            .filter(|e| e.id != except && e.item == item && e.status.holds_items())
+            .map(|e| e.quantity)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.escrows.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.escrows.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(book: &mut EscrowBook, seller: &str, buyer: &str) -> Result<Escrow, String> {
+        book.open(
+            seller,
+            seller,
+            buyer,
+            buyer,
+            "iron_ingot",
+            64,
+            10.0,
+            Utc::now(),
+            Duration::hours(1),
+        )
+    }
+
+    #[test]
+    fn escrows_round_trip_through_disk_and_ids_never_recycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("escrow.json");
+        let mut book = EscrowBook::load_from(&path).unwrap();
+        let first = open(&mut book, "u1", "u2").unwrap();
+        let second = open(&mut book, "u2", "u1").unwrap();
+        book.remove(second.id).unwrap();
+        let held = book
+            .update(first.id, |e| {
+                e.status = EscrowStatus::Held;
+                e.order_id = None;
+            })
+            .unwrap();
+
+        let mut reloaded = EscrowBook::load_from(&path).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded.get(first.id), Some(&held));
+        assert_eq!(reloaded.involving("u2").len(), 1);
+        assert_eq!(open(&mut reloaded, "u1", "u3").unwrap().id, 3);
+    }
+
+    #[test]
+    fn per_user_cap_counts_only_escrows_the_player_sells() {
+        let mut book = EscrowBook::default();
+        for _ in 0..MAX_ESCROWS_PER_USER {
+            open(&mut book, "u1", "u2").unwrap();
+        }
+        assert!(open(&mut book, "u1", "u3").is_err());
+        assert!(open(&mut book, "u2", "u1").is_ok());
+    }
+
+    #[test]
+    fn only_held_escrows_expire_and_only_holding_ones_count_as_stock() {
+        let mut book = EscrowBook::default();
+        let depositing = open(&mut book, "u1", "u2").unwrap();
+        let held = open(&mut book, "u1", "u3").unwrap();
+        book.update(held.id, |e| e.status = EscrowStatus::Held);
+
+        assert_eq!(book.expired_ids(held.expires_at), vec![held.id]);
+        assert_eq!(book.held_quantity("iron_ingot", depositing.id), 64);
+        assert_eq!(book.held_quantity("iron_ingot", held.id), 0);
+        assert_eq!(book.awaiting_orders(), vec![(depositing.id, None)]);
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("escrow.json");
+        fs::write(&path, "{not json").unwrap();
+        let book = EscrowBook::load_from(&path).unwrap();
+        assert!(book.is_empty());
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,339 @@
+//! Price alerts and restock notifications: `alert <item> below|above
+//! <price> [ttl]`, `notify <item> restock [ttl]`, bare `alert` and `alert
+//! cancel A<id>`, plus the [`sweep`] that fires and expires them.
+//!
+//! Price alerts read the pair's indicative spot buy price, the marginal
+//! cost of one item; restock alerts read `item_stock`. An alert whose
+//! trigger already holds is refused, so firing always means the pair
+//! crossed the threshold after it was set. Firing goes through
+//! `utils::notify_player`: a player who is away gets it on their next
+//! whisper, or when the bot sees them join.
+
+use chrono::{Duration, Utc};
+use tracing::{info, warn};
+
+use super::super::alerts::{Alert, AlertTrigger};
+use super::super::{Store, pricing, utils};
+use super::limit::format_ttl;
+use crate::error::StoreError;
+use crate::types::ItemId;
+
+/// Spot buy price per item of `item`'s pair, or `None` when the pair is
+/// unknown or cannot quote.
+fn spot_price(store: &Store, item: &str) -> Option<f64> {
+    let pair = store.pairs.get(item)?;
+    pricing::indicative_spot_buy_price(
+        &pair.curve,
+        pair.item_stock,
+        pair.currency_stock,
+        pair.effective_buy_fee(store.config.fee),
+    )
+}
+
+/// Whether `alert`'s pair meets its trigger right now. A pair that was
+/// removed never does.
+fn is_met(store: &Store, alert: &Alert) -> bool {
+    store.pairs.get(&alert.item).is_some_and(|pair| {
+        alert
+            .trigger
+            .is_met(spot_price(store, &alert.item), pair.item_stock)
+    })
+}
+
+/// The pair's current state in the alert's terms, e.g. `cobblestone costs
+/// 0.4800 diamonds each` or `cobblestone has 64 in stock`.
+fn describe_state(store: &Store, item: &str, trigger: AlertTrigger) -> String {
+    match trigger {
+        AlertTrigger::Below { .. } | AlertTrigger::Above { .. } => match spot_price(store, item) {
+            Some(price) => format!("{} costs {:.4} diamonds each", item, price),
+            None => format!("{} has no price right now", item),
+        },
+        AlertTrigger::Restock => {
+            let stock = store.pairs.get(item).map_or(0, |p| p.item_stock);
+            format!("{} has {} in stock", item, stock)
+        }
+    }
+}
+
+pub(super) async fn handle_set(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+    trigger: AlertTrigger,
+    ttl_secs: u64,
+) -> Result<(), StoreError> {
+    let Some(pair) = store.pairs.get(item.as_str()) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    if trigger.is_met(spot_price(store, item.as_str()), pair.item_stock) {
+        let msg = format!(
+            "Not set: {} already. Alerts fire when that changes.",
+            describe_state(store, item.as_str(), trigger)
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    let ttl = Duration::seconds(i64::try_from(ttl_secs).unwrap_or(i64::MAX));
+    let alert = match store.alerts.open(
+        user_uuid,
+        player_name,
+        item.as_str(),
+        trigger,
+        Utc::now(),
+        ttl,
+    ) {
+        Ok(a) => a,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    let msg = format!(
+        "Alert A{} set: {} (now {}). Expires in {}. Remove it with 'alert cancel A{}'.",
+        alert.id,
+        alert.description(),
+        describe_state(store, item.as_str(), trigger),
+        format_ttl(ttl_secs),
+        alert.id
+    );
+    utils::send_message_to_player(store, player_name, &msg).await
+}
+
+/// Whisper the caller's alerts with the time each has left.
+pub(super) async fn handle_list(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+) -> Result<(), StoreError> {
+    let now = Utc::now();
+    let parts: Vec<String> = store
+        .alerts
+        .for_user(user_uuid)
+        .into_iter()
+        .filter(|a| !a.is_expired(now))
+        .map(|a| {
+            let left = (a.expires_at - now).num_seconds().max(0) as u64;
+            format!("A{} {} ({} left)", a.id, a.description(), format_ttl(left))
+        })
+        .collect();
+    let message = if parts.is_empty() {
+        "You have no alerts. Set one with 'alert <item> below|above <price>' or 'notify <item> restock'."
+            .to_string()
+    } else {
+        format!("Your alerts: {}", parts.join(", "))
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+pub(super) async fn handle_cancel(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    alert_id: u64,
+) -> Result<(), StoreError> {
+    let msg = match store.alerts.cancel(user_uuid, alert_id) {
+        Ok(alert) => {
+            info!(
+                "[Alerts] A{} removed by {} ({})",
+                alert.id,
+                player_name,
+                alert.description()
+            );
+            format!("Alert A{} removed.", alert.id)
+        }
+        Err(e) => e,
+    };
+    utils::send_message_to_player(store, player_name, &msg).await
+}
+
+/// Fire alerts whose trigger now holds, and drop expired ones and ones on a
+/// pair that was removed, telling each owner.
+///
+/// Called after every processed order (the only time reserves move besides
+/// operator edits) and from the periodic cleanup while any alert is set.
+/// Never fails: an alert whose removal cannot be persisted stays set and
+/// is retried on the next sweep, so it is never delivered twice.
+pub(crate) async fn sweep(store: &mut Store) {
+    let now = Utc::now();
+
+    for id in store.alerts.expired_ids(now) {
+        match store.alerts.remove(id) {
+            Ok(alert) => {
+                info!(
+                    "[Alerts] A{} expired ({} for {})",
+                    alert.id,
+                    alert.description(),
+                    alert.username
+                );
+                let msg = format!(
+                    "Your alert A{} ({}) expired without firing.",
+                    alert.id,
+                    alert.description()
+                );
+                notify(store, &alert, msg).await;
+            }
+            Err(e) => warn!("[Alerts] could not expire A{}: {}", id, e),
+        }
+    }
+
+    for id in store
+        .alerts
+        .triggered(now, |a| !store.pairs.contains_key(&a.item))
+    {
+        match store.alerts.remove(id) {
+            Ok(alert) => {
+                let msg = format!(
+                    "Your alert A{} ({}) was removed: {} is no longer traded.",
+                    alert.id,
+                    alert.description(),
+                    alert.item
+                );
+                notify(store, &alert, msg).await;
+            }
+            Err(e) => warn!("[Alerts] could not drop A{}: {}", id, e),
+        }
+    }
+
+    for id in store.alerts.triggered(now, |a| is_met(store, a)) {
+        match store.alerts.remove(id) {
+            Ok(alert) => {
+                let state = describe_state(store, &alert.item, alert.trigger);
+                info!(
+                    "[Alerts] A{} fired for {} ({}: {})",
+                    alert.id,
+                    alert.username,
+                    alert.description(),
+                    state
+                );
+                let msg = format!(
+                    "Alert A{} ({}): {} now.",
+                    alert.id,
+                    alert.description(),
+                    state
+                );
+                notify(store, &alert, msg).await;
+            }
+            Err(e) => warn!("[Alerts] could not fire A{}: {}", id, e),
+        }
+    }
+}
+
+async fn notify(store: &mut Store, alert: &Alert, msg: String) {
+    utils::notify_player(store, &alert.user_uuid, &alert.username, msg).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::messages::BotInstruction;
+    use crate::types::{Pair, Storage};
+
+    fn pair(item: &str, item_stock: i32, currency_stock: f64) -> Pair {
+        Pair {
+            item: ItemId::from_normalized(item.to_string()),
+            stack_size: 64,
+            item_stock,
+            currency_stock,
+            ..Pair::default()
+        }
+    }
+
+    /// A store with one `cobblestone` pair and a bot that acknowledges
+    /// whispers and records their text.
+    fn store_with_pair(
+        item_stock: i32,
+        currency_stock: f64,
+    ) -> (Store, mpsc::UnboundedReceiver<String>) {
+        let (tx, mut rx) = mpsc::channel(16);
+        let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let BotInstruction::Whisper {
+                    message,
+                    respond_to,
+                    ..
+                } = msg
+                {
+                    let _ = seen_tx.send(message);
+                    let _ = respond_to.send(Ok(()));
+                }
+            }
+        });
+        let mut pairs = HashMap::new();
+        pairs.insert(
+            "cobblestone".to_string(),
+            pair("cobblestone", item_stock, currency_stock),
+        );
+        let store = Store::new_for_test(
+            tx,
+            Config::test_default(),
+            pairs,
+            HashMap::new(),
+            Storage::default(),
+        );
+        (store, seen_rx)
+    }
+
+    fn set(store: &mut Store, trigger: AlertTrigger) -> Alert {
+        // Present, so firing whispers instead of holding a notice in
+        // `data/notices.json`. A second check inside the cooldown is
+        // throttled but still counts as seen.
+        let _ = store.rate_limiter.check("u:uuid-alice");
+        store
+            .alerts
+            .open(
+                "uuid-alice",
+                "Alice",
+                "cobblestone",
+                trigger,
+                Utc::now(),
+                Duration::hours(1),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn price_alert_fires_once_when_the_spot_price_crosses() {
+        let (mut store, mut seen) = store_with_pair(100, 100.0);
+        let spot = spot_price(&store, "cobblestone").unwrap();
+        let alert = set(&mut store, AlertTrigger::Below { price: spot * 0.5 });
+
+        sweep(&mut store).await;
+        assert!(store.alerts.get(alert.id).is_some(), "not crossed yet");
+
+        store.pairs.get_mut("cobblestone").unwrap().currency_stock = 40.0;
+        sweep(&mut store).await;
+        assert!(store.alerts.is_empty());
+        let msg = seen.try_recv().unwrap();
+        assert!(msg.starts_with(&format!("Alert A{} (cobblestone below", alert.id)));
+
+        sweep(&mut store).await;
+        assert!(seen.try_recv().is_err(), "an alert fires only once");
+    }
+
+    #[tokio::test]
+    async fn restock_alert_fires_when_stock_leaves_zero_and_dies_with_its_pair() {
+        let (mut store, mut seen) = store_with_pair(0, 100.0);
+        let restock = set(&mut store, AlertTrigger::Restock);
+        let above = set(&mut store, AlertTrigger::Above { price: 1_000.0 });
+
+        store.pairs.get_mut("cobblestone").unwrap().item_stock = 64;
+        sweep(&mut store).await;
+        assert!(store.alerts.get(restock.id).is_none());
+        assert!(seen.try_recv().unwrap().contains("has 64 in stock now"));
+        assert!(store.alerts.get(above.id).is_some());
+
+        store.pairs.remove("cobblestone");
+        sweep(&mut store).await;
+        assert!(store.alerts.is_empty());
+        assert!(seen.try_recv().unwrap().contains("no longer traded"));
+    }
+}
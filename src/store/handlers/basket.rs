@@ -0,0 +1,887 @@
+//! Basket orders: `buy|sell <item> <qty>, <item> <qty>, ...`.
+//!
+//! Every line moves through a single `/trade`, so the 12 offer slots on the
+//! item side are shared across the basket. Diamonds never cross the window:
+//! a buy basket is paid from the balance and a sell basket's payout is
+//! credited to it, as for `lp`. Each line is priced on its own pair and
+//! committed as an ordinary `Buy` / `Sell` trade; a failure in any phase
+//! before the commit unwinds every line.
+//!
+//! `sell all <item>` runs as a one-line sell basket whose quantity is
+//! whatever the player puts in the trade.
+
+use tracing::{debug, error, info, warn};
+
+use super::super::ledger::Account;
+use super::super::order_book::OrderSide;
+use super::super::orders::{ChestDirection, execute_chest_transfers, perform_trade};
+use super::super::queue::basket_label;
+use super::super::{Store, pricing, rollback, state, utils};
+use super::sell::max_sell_fill;
+use crate::constants::TRADE_OFFER_SLOTS_PER_SIDE;
+use crate::error::StoreError;
+use crate::messages::{QueuedOrderType, TradeItem};
+use crate::types::storage::ChestTransfer;
+use crate::types::{ItemId, Order, Trade, TradeType};
+
+/// A basket line priced and planned against current reserves.
+#[derive(Clone)]
+struct PlannedLine {
+    item: String,
+    qty: i32,
+    stack_size: i32,
+    /// Cost (buy) or payout (sell) of this line, fees included.
+    total: f64,
+    plan: Vec<ChestTransfer>,
+}
+
+/// Price every line against its own pair and check the basket fits in the
+/// trade window. `Err` is the player-facing reason it cannot trade now.
+fn price_lines(store: &Store, side: OrderSide, lines: &[TradeItem]) -> Result<Vec<f64>, String> {
+    let mut stacks = 0;
+    let mut totals = Vec::with_capacity(lines.len());
+    for line in lines {
+        let Some(pair) = store.pairs.get(&line.item) else {
+            return Err(format!("Item '{}' is not available for trading", line.item));
+        };
+        if line.amount <= 0 {
+            return Err("Quantity must be positive".to_string());
+        }
+        let stack_size = pair.stack_size.max(1);
+        stacks += (line.amount + stack_size - 1) / stack_size;
+        let total = match side {
+            OrderSide::Buy => pricing::calculate_buy_cost(store, &line.item, line.amount),
+            OrderSide::Sell => pricing::calculate_sell_payout(store, &line.item, line.amount)
+                .filter(|payout| *payout <= pair.currency_stock),
+        };
+        match total {
+            Some(t) if t.is_finite() && t > 0.0 => totals.push(t),
+            _ => {
+                return Err(format!(
+                    "Cannot {} {} {} right now: not enough stock or reserves.",
+                    side.verb(),
+                    line.amount,
+                    line.item
+                ));
+            }
+        }
+    }
+    if stacks > TRADE_OFFER_SLOTS_PER_SIDE {
+        return Err(format!(
+            "That basket needs {} stacks but the trade window holds {}. Split it into smaller baskets.",
+            stacks, TRADE_OFFER_SLOTS_PER_SIDE
+        ));
+    }
+    Ok(totals)
+}
+
+fn trade_lines(lines: &[PlannedLine]) -> Vec<TradeItem> {
+    lines
+        .iter()
+        .map(|l| TradeItem {
+            item: l.item.clone(),
+            amount: l.qty,
+        })
+        .collect()
+}
+
+/// Put withdrawn `lines` back into storage. Returns a note for the player
+/// when some of it could not be returned.
+async fn return_withdrawn(store: &mut Store, lines: &[PlannedLine], ctx: &str) -> Option<String> {
+    let mut notes = Vec::new();
+    for line in lines {
+        let rb =
+            rollback::deposit_transfers(store, &line.plan, &line.item, line.stack_size, ctx).await;
+        if let Some(detail) = rb.partial_message() {
+            notes.push(format!("{}: {}", line.item, detail));
+        }
+    }
+    (!notes.is_empty()).then(|| notes.join("; "))
+}
+
+/// Record one committed line as an ordinary buy or sell on its pair.
+/// Returns the fee it carried.
+fn commit_line(
+    store: &mut Store,
+    side: OrderSide,
+    line: &PlannedLine,
+    user_uuid: &str,
+) -> Result<f64, StoreError> {
+    let global_fee = store.config.fee;
+    let pair = store.expect_pair(&line.item, "basket/commit-fee")?;
+    let (trade_type, fee) = match side {
+        OrderSide::Buy => {
+            let buy_fee = pair.effective_buy_fee(global_fee);
+            (TradeType::Buy, line.total - line.total / (1.0 + buy_fee))
+        }
+        OrderSide::Sell => {
+            let sell_fee = pair.effective_sell_fee(global_fee);
+            (TradeType::Sell, line.total / (1.0 - sell_fee) - line.total)
+        }
+    };
+    let item = ItemId::from_normalized(line.item.clone());
+    let mut trade = Trade::new(
+        trade_type,
+        item.clone(),
+        line.qty,
+        line.total,
+        user_uuid.to_string(),
+    )
+    .with_fee(fee)
+    .with_balance_change(match side {
+        OrderSide::Buy => -line.total,
+        OrderSide::Sell => line.total,
+    });
+
+    let item_stock = store.storage.total_item_amount(&line.item);
+    let pair = store.expect_pair_mut(&line.item, "basket/commit-pair")?;
+    pair.item_stock = item_stock;
+    pair.stats.record(&trade);
+    match side {
+        OrderSide::Buy => pair.currency_stock += line.total,
+        OrderSide::Sell => pair.currency_stock -= line.total,
+    }
+    let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+    trade = trade.with_reserves(pair.item_stock, pair.currency_stock, spot);
+    store.dirty = true;
+    // The caller moves the basket total on the balance in one step; each
+    // line posts its own share against its pair.
+    let reserve = Account::Reserve(line.item.clone());
+    let player = Account::Player(user_uuid.to_string());
+    match side {
+        OrderSide::Buy => store.post_ledger(player, reserve, line.total, "basket buy"),
+        OrderSide::Sell => store.post_ledger(reserve, player, line.total, "basket sell"),
+    }
+
+    store.price_history.record(&trade);
+    store.trades.push(trade);
+    store.orders.push_back(match side {
+        OrderSide::Buy => Order::buy(item, line.qty, line.total, user_uuid.to_string()),
+        OrderSide::Sell => Order::sell(item, line.qty, line.total, user_uuid.to_string()),
+    });
+    Ok(fee)
+}
+
+pub(super) async fn handle_enqueue(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    side: OrderSide,
+    lines: &[(ItemId, u32)],
+) -> Result<(), StoreError> {
+    let lines: Vec<TradeItem> = lines
+        .iter()
+        .map(|(item, qty)| TradeItem {
+            item: item.as_str().to_string(),
+            amount: i32::try_from(*qty).unwrap_or(i32::MAX),
+        })
+        .collect();
+    let total = match price_lines(store, side, &lines) {
+        Ok(totals) => totals.iter().sum::<f64>(),
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    // Cheap pre-check so an unfunded basket never takes a queue slot; the
+    // queued handler re-prices and re-checks at execution time.
+    let balance = store.users.get(user_uuid).map_or(0.0, |u| u.balance);
+    if side == OrderSide::Buy && balance < total {
+        let msg = format!(
+            "That basket costs {:.2} diamonds; your balance is {:.2}. Baskets are paid from your balance, so use 'deposit' first.",
+            total, balance
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    debug!(
+        user = player_name,
+        uuid = user_uuid,
+        side = side.verb(),
+        lines = lines.len(),
+        "Queueing basket order"
+    );
+    let label = basket_label(&lines);
+    let first_item = lines[0].item.clone();
+    let total_qty: u32 = lines.iter().map(|l| l.amount.max(0) as u32).sum();
+    let order_type = match side {
+        OrderSide::Buy => QueuedOrderType::BuyBasket { lines },
+        OrderSide::Sell => QueuedOrderType::SellBasket { lines },
+    };
+    match store.order_queue.add(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        order_type,
+        first_item,
+        total_qty,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let settle = match side {
+                OrderSide::Buy => "from your balance",
+                OrderSide::Sell => "to your balance",
+            };
+            let msg = format!(
+                "Basket order #{} queued (position {}/{}). Est. wait: {}. {} {}: about {:.2} diamonds {}.",
+                order_id,
+                position,
+                queue_len,
+                wait_estimate,
+                side.verb(),
+                label,
+                total,
+                settle
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+/// Execute a queued buy basket: withdraw every line, hand them all over in
+/// one trade, then debit the balance.
+pub async fn handle_buy_basket_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    lines: &[TradeItem],
+) -> Result<(), StoreError> {
+    info!(phase = "basket_buy.start", player = %player_name, lines = lines.len(), "Basket buy starting");
+    for line in lines {
+        state::assert_tradeable(store, &line.item, user_uuid, "pre-basket-buy")?;
+    }
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    let totals = match price_lines(store, OrderSide::Buy, lines) {
+        Ok(t) => t,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    let total: f64 = totals.iter().sum();
+    let balance = store.users.get(&user_uuid).map_or(0.0, |u| u.balance);
+    if balance < total {
+        let msg = format!(
+            "Insufficient balance for basket: costs {:.2}, balance {:.2}. Use 'deposit' first.",
+            total, balance
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Plan every withdrawal before touching a chest. Lines are distinct
+    // items, so their plans read disjoint chests.
+    let mut planned = Vec::with_capacity(lines.len());
+    for (line, &cost) in lines.iter().zip(&totals) {
+        let (plan, planned_total) = store
+            .storage
+            .simulate_withdraw_plan(&line.item, line.amount);
+        if planned_total != line.amount {
+            let msg = format!(
+                "Out of physical stock for '{}'. Storage has {}, requested {}.",
+                line.item, planned_total, line.amount
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        planned.push(PlannedLine {
+            item: line.item.clone(),
+            qty: line.amount,
+            stack_size: store.expect_pair(&line.item, "basket-buy/plan")?.stack_size,
+            total: cost,
+            plan,
+        });
+    }
+    let label = basket_label(&trade_lines(&planned));
+
+    let full_plan: Vec<ChestTransfer> = planned.iter().flat_map(|l| l.plan.clone()).collect();
+    store.advance_trade(|s| s.begin_withdrawal(full_plan));
+    for (i, line) in planned.iter().enumerate() {
+        if let Err(e) = execute_chest_transfers(
+            store,
+            &line.plan,
+            &line.item,
+            line.stack_size,
+            ChestDirection::Withdraw,
+            "[BasketBuy]",
+        )
+        .await
+        {
+            // This line's own prefix was already put back by
+            // `execute_chest_transfers`; return the lines before it.
+            let residue = return_withdrawn(store, &planned[..i], "[BasketBuy]").await;
+            store.advance_trade(|s| s.rollback("basket-buy/chest-withdrawal-failed".to_string()));
+            let suffix = residue.map(|r| format!(" Rollback partial: {}.", r));
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "Basket buy",
+                &e.user_message(),
+                suffix.as_deref(),
+            )
+            .await;
+        }
+    }
+
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Basket buy {}: Total {:.2} diamonds, paid from your balance. Accept the trade; no diamonds needed.",
+            label, total
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    if let Err(err) = perform_trade(
+        store,
+        player_name,
+        trade_lines(&planned),
+        vec![],
+        false,
+        false,
+        "[BasketBuy]",
+    )
+    .await
+    {
+        warn!(phase = "basket_buy.rollback", player = %player_name, "Basket trade failed, rolling back: {}", err);
+        let residue = return_withdrawn(store, &planned, "[BasketBuy]").await;
+        store.advance_trade(|s| s.rollback("basket-buy/trade-failed".to_string()));
+        let msg = match residue {
+            Some(detail) => format!(
+                "Basket buy aborted: trade failed: {}. Rollback partial: {}.",
+                err.user_message(),
+                detail
+            ),
+            None => format!(
+                "Basket buy aborted: trade failed: {} (items rolled back to storage)",
+                err.user_message()
+            ),
+        };
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Commit ledgers.
+    let new_balance = {
+        let user = store.expect_user_mut(&user_uuid, "basket-buy/commit-user")?;
+        user.balance -= total;
+        user.username = player_name.to_owned();
+        user.balance
+    };
+    store.dirty_users.insert(user_uuid.clone());
+    let mut fees = 0.0;
+    for line in &planned {
+        fees += commit_line(store, OrderSide::Buy, line, &user_uuid)?;
+    }
+    let total_qty: i32 = planned.iter().map(|l| l.qty).sum();
+    store.advance_trade(|s| s.commit(label.clone(), total_qty, total));
+
+    info!(
+        phase = "basket_buy.done",
+        player = %player_name,
+        lines = planned.len(),
+        total = format_args!("{:.2}", total),
+        "Basket buy completed"
+    );
+    let invariant_ok = state::assert_invariants(store, "post-basket-buy", true).is_ok();
+    if !invariant_ok {
+        error!(phase = "basket_buy.invariant", player = %player_name, "Invariant violation after basket buy — operator must audit store state");
+        let _ = state::save(store);
+    }
+    let alert_suffix = if invariant_ok {
+        ""
+    } else {
+        " (Note: store self-check flagged an inconsistency; operator notified.)"
+    };
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Bought {} for {:.2} diamonds (fees {:.2}). New balance: {:.2}. Trade complete.{}",
+            label, total, fees, new_balance, alert_suffix
+        ),
+    )
+    .await
+}
+
+/// Deposit the items a sell received, credit `total` to the balance and
+/// record each line. On a failed deposit everything received goes back to
+/// the player. `action` names the order in player messages.
+#[allow(clippy::too_many_arguments)]
+async fn deposit_and_commit_sell(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    action: &str,
+    planned: &[PlannedLine],
+    actual_received: Vec<TradeItem>,
+    label: &str,
+    total: f64,
+) -> Result<(), StoreError> {
+    let full_plan: Vec<ChestTransfer> = planned.iter().flat_map(|l| l.plan.clone()).collect();
+    store.advance_trade(|s| {
+        s.begin_depositing(
+            super::super::trade_state::TradeResult {
+                items_received: actual_received.clone(),
+            },
+            full_plan,
+        )
+    });
+
+    let mut deposited: Vec<PlannedLine> = Vec::with_capacity(planned.len());
+    for line in planned {
+        let (plan, planned_total) =
+            store
+                .storage
+                .simulate_deposit_plan(&line.item, line.qty, line.stack_size);
+        let result = if planned_total < line.qty {
+            Err(StoreError::ChestOp(format!(
+                "no storage space left for {} {}",
+                line.qty, line.item
+            )))
+        } else {
+            execute_chest_transfers(
+                store,
+                &plan,
+                &line.item,
+                line.stack_size,
+                ChestDirection::Deposit,
+                "[BasketSell]",
+            )
+            .await
+        };
+        if let Err(err) = result {
+            // Pull the lines already stored back out and return the whole
+            // basket. No ledger has moved yet. As with a failed single sell,
+            // steps of the failing line that did land cannot be unwound here.
+            let mut unreturned = Vec::new();
+            for done in &deposited {
+                if execute_chest_transfers(
+                    store,
+                    &done.plan,
+                    &done.item,
+                    done.stack_size,
+                    ChestDirection::Withdraw,
+                    "[BasketSell] deposit-failed",
+                )
+                .await
+                .is_err()
+                {
+                    unreturned.push(done.item.clone());
+                }
+            }
+            let returned = perform_trade(
+                store,
+                player_name,
+                trade_lines(planned),
+                vec![],
+                false,
+                false,
+                "[BasketSell] deposit-failed",
+            )
+            .await;
+            store.advance_trade(|s| s.rollback("basket-sell/deposit-failed".to_string()));
+            let msg = match returned {
+                Ok(_) if unreturned.is_empty() => format!(
+                    "{} aborted: failed to deposit items into storage: {}. Items returned via trade.",
+                    action,
+                    err.user_message()
+                ),
+                Ok(_) => format!(
+                    "{} aborted: failed to deposit items into storage: {}. Items returned via trade, but {} could not be taken back out of storage. Contact an operator.",
+                    action,
+                    err.user_message(),
+                    unreturned.join(", ")
+                ),
+                Err(rerr) => {
+                    error!(
+                        phase = "basket_sell.deposit_failed",
+                        player = %player_name,
+                        basket = %label,
+                        "Basket deposit failed and return trade failed ({}); operator must reconcile",
+                        rerr
+                    );
+                    format!(
+                        "{} aborted: failed to deposit items into storage: {}. Return-trade also failed ({}). Contact an operator.",
+                        action,
+                        err.user_message(),
+                        rerr.user_message()
+                    )
+                }
+            };
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        deposited.push(PlannedLine {
+            plan,
+            ..line.clone()
+        });
+    }
+
+    // Commit ledgers.
+    let new_balance = {
+        let user = store.expect_user_mut(user_uuid, "basket-sell/commit-user")?;
+        user.balance += total;
+        user.username = player_name.to_owned();
+        user.balance
+    };
+    store.dirty_users.insert(user_uuid.to_string());
+    let mut fees = 0.0;
+    for line in &deposited {
+        fees += commit_line(store, OrderSide::Sell, line, user_uuid)?;
+    }
+    let total_qty: i32 = deposited.iter().map(|l| l.qty).sum();
+    store.advance_trade(|s| s.commit(label.to_string(), total_qty, total));
+
+    info!(
+        phase = "basket_sell.done",
+        player = %player_name,
+        lines = deposited.len(),
+        total = format_args!("{:.2}", total),
+        "{} completed",
+        action
+    );
+    let invariant_ok = state::assert_invariants(store, "post-basket-sell", true).is_ok();
+    if !invariant_ok {
+        error!(phase = "basket_sell.invariant", player = %player_name, "Invariant violation after {} — operator must audit store state", action.to_lowercase());
+        let _ = state::save(store);
+    }
+    let alert_suffix = if invariant_ok {
+        ""
+    } else {
+        " (Note: store self-check flagged an inconsistency; operator notified.)"
+    };
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Sold {} for {:.2} diamonds (fees {:.2}), credited to your balance. New balance: {:.2}. Trade complete.{}",
+            label, total, fees, new_balance, alert_suffix
+        ),
+    )
+    .await
+}
+
+/// Execute a queued sell basket: take every line in one trade, deposit
+/// them, then credit the payout to the balance.
+pub async fn handle_sell_basket_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    lines: &[TradeItem],
+) -> Result<(), StoreError> {
+    info!(phase = "basket_sell.start", player = %player_name, lines = lines.len(), "Basket sell starting");
+    for line in lines {
+        state::assert_tradeable(store, &line.item, user_uuid, "pre-basket-sell")?;
+    }
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    let totals = match price_lines(store, OrderSide::Sell, lines) {
+        Ok(t) => t,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    let total: f64 = totals.iter().sum();
+
+    // Check every line fits before the trade. The plans are redone line by
+    // line at deposit time: two items new to storage would otherwise both
+    // be planned into the same empty chest.
+    let mut planned = Vec::with_capacity(lines.len());
+    for (line, &payout) in lines.iter().zip(&totals) {
+        let stack_size = store
+            .expect_pair(&line.item, "basket-sell/plan")?
+            .stack_size;
+        let (plan, planned_total) =
+            store
+                .storage
+                .simulate_deposit_plan(&line.item, line.amount, stack_size);
+        if planned_total < line.amount {
+            let msg = format!(
+                "Storage space validation failed for '{}': can only store {} items, but {} requested. Please contact an operator to add more storage nodes.",
+                line.item, planned_total, line.amount
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        planned.push(PlannedLine {
+            item: line.item.clone(),
+            qty: line.amount,
+            stack_size,
+            total: payout,
+            plan,
+        });
+    }
+    let label = basket_label(&trade_lines(&planned));
+
+    // Nothing to withdraw: the empty plan only satisfies the state machine's
+    // Queued -> Withdrawing -> Trading progression (same as `lp add`).
+    store.advance_trade(|s| s.begin_withdrawal(vec![]));
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Basket sell: Please offer {} in the trade. {:.2} diamonds will be credited to your balance.",
+            label, total
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    let actual_received = match perform_trade(
+        store,
+        player_name,
+        vec![],
+        trade_lines(&planned),
+        true, // require EXACT amounts, as for sells
+        false,
+        "[BasketSell]",
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(phase = "basket_sell.rollback", player = %player_name, "Basket trade failed: {}", err);
+            store.advance_trade(|s| s.rollback("basket-sell/trade-failed".to_string()));
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "Basket sell",
+                &err.user_message(),
+                None,
+            )
+            .await;
+        }
+    };
+
+    // Defensive recheck of every line, as for single sells.
+    let short_line = planned.iter().find(|line| {
+        let target = crate::bot::Bot::normalize_item_id(&line.item);
+        let received: i32 = actual_received
+            .iter()
+            .filter(|t| crate::bot::Bot::normalize_item_id(&t.item) == target)
+            .map(|t| t.amount)
+            .sum();
+        received != line.qty
+    });
+    if let Some(line) = short_line {
+        warn!(
+            phase = "basket_sell.validation",
+            player = %player_name,
+            item = %line.item,
+            expected = line.qty,
+            "Basket sell validation failed: item count mismatch"
+        );
+        let returnable: Vec<TradeItem> = actual_received
+            .iter()
+            .filter(|t| t.amount > 0)
+            .cloned()
+            .collect();
+        if !returnable.is_empty() {
+            let _ = perform_trade(
+                store,
+                player_name,
+                returnable,
+                vec![],
+                false,
+                false,
+                "[BasketSell] return-items",
+            )
+            .await;
+        }
+        store.advance_trade(|s| s.rollback("basket-sell/item-count-mismatch".to_string()));
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Basket sell REJECTED: the trade did not contain exactly {} {}. Trade cancelled, items returned.",
+                line.qty, line.item
+            ),
+        )
+        .await;
+    }
+
+    deposit_and_commit_sell(
+        store,
+        player_name,
+        &user_uuid,
+        "Basket sell",
+        &planned,
+        actual_received,
+        &label,
+        total,
+    )
+    .await
+}
+
+/// Execute a queued `sell all <item>`: take any amount of `item` the store
+/// can pay for, at most `cap`, deposit it, then credit the payout to the
+/// balance.
+pub async fn handle_sell_all_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    cap: u32,
+) -> Result<(), StoreError> {
+    info!(phase = "sell_all.start", player = %player_name, item = %item, "Sell all starting");
+    state::assert_tradeable(store, item, user_uuid, "pre-sell-all")?;
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    // Settled now, as for `upto`: reserves and storage may have moved while
+    // the order was queued.
+    let fill = match max_sell_fill(store, item, cap) {
+        Ok(fill) => fill,
+        Err(reason) => {
+            let msg = format!("Sell all cancelled, nothing traded: {}", reason);
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+    };
+    let most = fill.quantity as i32;
+
+    store.advance_trade(|s| s.begin_withdrawal(vec![]));
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Sell all: Please offer up to {} {} in the trade{}. The payout is credited to your balance ({:.2} diamonds for all {}).",
+            most,
+            item,
+            fill.limit_note(),
+            fill.total,
+            most
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    // Flexible: any amount of `item` passes the trade, nothing else does.
+    let actual_received = match perform_trade(
+        store,
+        player_name,
+        vec![],
+        vec![TradeItem {
+            item: item.to_string(),
+            amount: most,
+        }],
+        false,
+        true,
+        "[SellAll]",
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(phase = "sell_all.rollback", player = %player_name, "Sell all trade failed: {}", err);
+            store.advance_trade(|s| s.rollback("sell-all/trade-failed".to_string()));
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "Sell all",
+                &err.user_message(),
+                None,
+            )
+            .await;
+        }
+    };
+
+    let target = crate::bot::Bot::normalize_item_id(item);
+    let received: i32 = actual_received
+        .iter()
+        .filter(|t| crate::bot::Bot::normalize_item_id(&t.item) == target)
+        .map(|t| t.amount)
+        .sum();
+    let line = TradeItem {
+        item: item.to_string(),
+        amount: received,
+    };
+    let stack_size = store.expect_pair(item, "sell-all/plan")?.stack_size;
+    let (plan, planned_total) = store
+        .storage
+        .simulate_deposit_plan(item, received, stack_size);
+    let priced = if received > most {
+        Err(format!(
+            "you offered {} {} but the store can take at most {}",
+            received, item, most
+        ))
+    } else if planned_total < received {
+        Err(format!(
+            "the store has no storage space for {} {}",
+            received, item
+        ))
+    } else {
+        price_lines(store, OrderSide::Sell, std::slice::from_ref(&line)).map(|t| t[0])
+    };
+    let total = match priced {
+        Ok(total) => total,
+        Err(reason) => {
+            warn!(
+                phase = "sell_all.validation",
+                player = %player_name,
+                item = %item,
+                received,
+                most,
+                "Sell all rejected after trade: {}",
+                reason
+            );
+            let returnable: Vec<TradeItem> = actual_received
+                .into_iter()
+                .filter(|t| t.amount > 0)
+                .collect();
+            let returned = if returnable.is_empty() {
+                Ok(())
+            } else {
+                perform_trade(
+                    store,
+                    player_name,
+                    returnable,
+                    vec![],
+                    false,
+                    false,
+                    "[SellAll] return-items",
+                )
+                .await
+                .map(|_| ())
+            };
+            store.advance_trade(|s| s.rollback("sell-all/not-sellable".to_string()));
+            let msg = match returned {
+                Ok(()) => format!(
+                    "Sell all REJECTED: {}. Trade cancelled, items returned.",
+                    reason
+                ),
+                Err(rerr) => {
+                    error!(
+                        phase = "sell_all.return_items",
+                        player = %player_name,
+                        item = %item,
+                        received,
+                        "Sell all rejected and the return trade failed ({}); the items are on the bot, operator must return them",
+                        rerr
+                    );
+                    format!(
+                        "Sell all REJECTED: {}. Returning your {} {} also failed ({}). Contact an operator.",
+                        reason,
+                        received,
+                        item,
+                        rerr.user_message()
+                    )
+                }
+            };
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+    };
+
+    let planned = [PlannedLine {
+        item: item.to_string(),
+        qty: received,
+        stack_size,
+        total,
+        plan,
+    }];
+    let label = basket_label(&trade_lines(&planned));
+    deposit_and_commit_sell(
+        store,
+        player_name,
+        &user_uuid,
+        "Sell all",
+        &planned,
+        actual_received,
+        &label,
+        total,
+    )
+    .await
+}
@@ -2,11 +2,17 @@
 //!
 //! Input validation (item name, quantity) happens in `store::command::parse_command`.
 //! This handler only checks runtime preconditions (is the pair tradable?) and
-//! enqueues the order.
+//! enqueues the order, carrying the optional `max_cost` slippage bound.
+//!
+//! `buy <item> upto <qty>` / `buy <item> all` enqueue a partial order instead:
+//! [`max_fill`] works out how much of it can fill, here as an estimate for
+//! the player and again in `orders::fill_partial` when the order runs.
 
 use tracing::debug;
 
-use super::super::{Store, utils};
+use super::super::orders::PartialFill;
+use super::super::{Store, pricing, utils};
+use crate::constants::TRADE_OFFER_SLOTS_PER_SIDE;
 use crate::error::StoreError;
 use crate::messages::QueuedOrderType;
 use crate::types::ItemId;
@@ -17,6 +23,7 @@ pub(super) async fn handle(
     user_uuid: &str,
     item: &ItemId,
     quantity: u32,
+    max_cost: Option<f64>,
 ) -> Result<(), StoreError> {
     if !store.pairs.contains_key(item.as_str()) {
         debug!(
@@ -42,12 +49,13 @@ pub(super) async fn handle(
         "Queueing buy order"
     );
 
-    match store.order_queue.add(
+    match store.order_queue.add_bounded(
         user_uuid.to_string(),
         player_name.to_string(),
         QueuedOrderType::Buy,
         item.as_str().to_string(),
         quantity,
+        max_cost,
     ) {
         Ok((order_id, position)) => {
             let queue_len = store.order_queue.len();
@@ -65,3 +73,116 @@ pub(super) async fn handle(
         Err(e) => utils::send_message_to_player(store, player_name, &e).await,
     }
 }
+
+/// The most of `item`, at most `cap`, a partial buy for `user_uuid` can fill
+/// right now: bounded by physical and pair stock and by what the player's
+/// balance pays for. Partial buys settle from the balance so the amount is
+/// known before the trade opens. `Err` is the player-facing reason nothing
+/// fits.
+pub fn max_buy_fill(
+    store: &Store,
+    user_uuid: &str,
+    item: &str,
+    cap: u32,
+) -> Result<PartialFill, String> {
+    let pair = store
+        .pairs
+        .get(item)
+        .ok_or_else(|| format!("Item '{}' is not available for trading", item))?;
+    let cap = i32::try_from(cap).unwrap_or(i32::MAX);
+    let in_stock = cap
+        .min(store.storage.total_item_amount(item))
+        .min(pair.item_stock);
+    if in_stock < 1 {
+        return Err(format!("'{}' is out of stock.", item));
+    }
+    let balance = store.users.get(user_uuid).map_or(0.0, |u| u.balance);
+    let fee = store.config.fee;
+    let Some((quantity, total)) = pricing::pair_max_buy_within(pair, balance, in_stock, fee) else {
+        return Err(format!(
+            "Your balance of {:.2} diamonds doesn't cover one {}. Partial buys are paid from your balance, so deposit first.",
+            balance, item
+        ));
+    };
+    let limited_by = if quantity == cap {
+        None
+    } else if quantity == in_stock || pricing::pair_buy_cost(pair, quantity + 1, fee).is_none() {
+        Some("stock")
+    } else {
+        Some("your balance")
+    };
+    Ok(PartialFill {
+        quantity: quantity as u32,
+        total,
+        limited_by,
+    })
+}
+
+/// `buy <item> upto <qty>` / `buy <item> all`: enqueue a partial buy.
+/// `quantity` is `None` for `all`, which means one full trade window.
+pub(super) async fn handle_up_to(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+    quantity: Option<u32>,
+) -> Result<(), StoreError> {
+    let Some(pair) = store.pairs.get(item.as_str()) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let cap = quantity.unwrap_or((TRADE_OFFER_SLOTS_PER_SIDE * pair.stack_size) as u32);
+    let fill = match max_buy_fill(store, user_uuid, item.as_str(), cap) {
+        Ok(fill) => fill,
+        Err(reason) => {
+            debug!(
+                user = player_name,
+                uuid = user_uuid,
+                item = %item,
+                reason = %reason,
+                "Partial buy rejected: nothing can fill"
+            );
+            return utils::send_message_to_player(store, player_name, &reason).await;
+        }
+    };
+
+    debug!(
+        user = player_name,
+        uuid = user_uuid,
+        item = %item,
+        cap = cap,
+        estimate = fill.quantity,
+        "Queueing partial buy order"
+    );
+
+    match store.order_queue.add_partial(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::Buy,
+        item.as_str().to_string(),
+        cap,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Order #{} queued (position {}/{}): buy up to {} {}. Est. wait: {}. Right now that fills {}{} for {:.2} diamonds; the final amount is set when it runs.",
+                order_id,
+                position,
+                queue_len,
+                cap,
+                item,
+                wait_estimate,
+                fill.quantity,
+                fill.limit_note(),
+                fill.total
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
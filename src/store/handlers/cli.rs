@@ -7,7 +7,8 @@
 use tokio::sync::oneshot;
 use tracing::{debug, error, info, warn};
 
-use super::super::{Store, state, trade_state, utils};
+use super::super::audit_log::AuditEvent;
+use super::super::{Store, journal_replay, pricing, state, trade_state, utils};
 use crate::error::StoreError;
 use crate::messages::{BotInstruction, CliMessage};
 use crate::types::ItemId;
@@ -30,6 +31,25 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
             let _ = respond_to.send(pairs);
             Ok(())
         }
+        CliMessage::QueryPriceHistory {
+            item_name,
+            resolution,
+            respond_to,
+        } => {
+            let result = match ItemId::new(&item_name) {
+                Err(_) => Err("Invalid item name".to_string()),
+                Ok(id) if !store.pairs.contains_key(id.as_str()) => {
+                    Err(format!("Pair '{}' not found", id))
+                }
+                Ok(id) => Ok(store
+                    .price_history
+                    .get(id.as_str())
+                    .map(|h| h.candles(resolution).to_vec())
+                    .unwrap_or_default()),
+            };
+            let _ = respond_to.send(result);
+            Ok(())
+        }
         CliMessage::QueryFee { respond_to } => {
             debug!("[CLI-Store] Querying fee rate");
             let _ = respond_to.send(store.config.fee);
@@ -274,6 +294,18 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
 
             let idx = store.storage.nodes.iter().position(|n| n.id == node_id);
             if let Some(idx) = idx {
+                // Risky and not undoable from the CLI: keep a copy of data/
+                // first, and refuse rather than remove without one.
+                if let Err(e) =
+                    crate::store::snapshot::take(store, &format!("remove-node-{}", node_id)).await
+                {
+                    warn!("[CLI-Store] RemoveNode: {}", e);
+                    let _ = respond_to.send(Err(format!(
+                        "Snapshot before removal failed, node {} not removed: {}",
+                        node_id, e
+                    )));
+                    return Ok(());
+                }
                 store.storage.nodes.remove(idx);
                 // Delete data/storage/{node_id}.json so a stale file isn't
                 // reloaded on next startup.
@@ -360,6 +392,11 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
                         stack_size,
                         item_stock: 0,
                         currency_stock: 0.0,
+                        buy_fee: None,
+                        sell_fee: None,
+                        curve: Default::default(),
+                        stats: Default::default(),
+                        lp_total_shares: 0.0,
                     },
                 );
                 store.dirty = true;
@@ -408,6 +445,17 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
                     );
                 }
 
+                if let Err(e) =
+                    crate::store::snapshot::take(store, &format!("remove-pair-{}", normalized_item))
+                        .await
+                {
+                    warn!("[CLI-Store] RemovePair: {}", e);
+                    let _ = respond_to.send(Err(format!(
+                        "Snapshot before removal failed, pair '{}' not removed: {}",
+                        normalized_item, e
+                    )));
+                    return Ok(());
+                }
                 store.pairs.remove(&normalized_item);
 
                 let file_path = crate::types::Pair::get_pair_file_path(&normalized_item);
@@ -431,6 +479,93 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
             }
             Ok(())
         }
+        CliMessage::SetPairFees {
+            item_name,
+            buy_fee,
+            sell_fee,
+            respond_to,
+        } => {
+            let normalized_item = match ItemId::new(&item_name) {
+                Ok(id) => id.to_string(),
+                Err(_) => {
+                    let _ = respond_to.send(Err("Invalid item name".to_string()));
+                    return Ok(());
+                }
+            };
+            if let Some(bad) = [buy_fee, sell_fee]
+                .into_iter()
+                .flatten()
+                .find(|f| !pricing::validate_fee(*f))
+            {
+                warn!(
+                    "[CLI-Store] SetPairFees: rejecting fee {} for '{}'",
+                    bad, normalized_item
+                );
+                let _ = respond_to.send(Err(format!(
+                    "Fee {} is out of range (must be within [{}, {}])",
+                    bad,
+                    crate::constants::FEE_MIN,
+                    crate::constants::FEE_MAX
+                )));
+                return Ok(());
+            }
+            let Some(pair) = store.pairs.get_mut(&normalized_item) else {
+                warn!(
+                    "[CLI-Store] SetPairFees: pair '{}' not found",
+                    normalized_item
+                );
+                let _ = respond_to.send(Err(format!("Pair '{}' not found", normalized_item)));
+                return Ok(());
+            };
+            info!(
+                "[CLI-Store] Pair '{}' fees: buy {:?} -> {:?}, sell {:?} -> {:?}",
+                normalized_item, pair.buy_fee, buy_fee, pair.sell_fee, sell_fee
+            );
+            pair.buy_fee = buy_fee;
+            pair.sell_fee = sell_fee;
+            store.dirty = true;
+            let _ = respond_to.send(Ok(()));
+            Ok(())
+        }
+        CliMessage::SetPairCurve {
+            item_name,
+            curve,
+            respond_to,
+        } => {
+            let normalized_item = match ItemId::new(&item_name) {
+                Ok(id) => id.to_string(),
+                Err(_) => {
+                    let _ = respond_to.send(Err("Invalid item name".to_string()));
+                    return Ok(());
+                }
+            };
+            if let Err(e) = curve.validate() {
+                warn!(
+                    "[CLI-Store] SetPairCurve: rejecting {:?} for '{}': {}",
+                    curve, normalized_item, e
+                );
+                let _ = respond_to.send(Err(format!("Invalid curve: {}", e)));
+                return Ok(());
+            }
+            let Some(pair) = store.pairs.get_mut(&normalized_item) else {
+                warn!(
+                    "[CLI-Store] SetPairCurve: pair '{}' not found",
+                    normalized_item
+                );
+                let _ = respond_to.send(Err(format!("Pair '{}' not found", normalized_item)));
+                return Ok(());
+            };
+            info!(
+                "[CLI-Store] Pair '{}' curve: {} -> {}",
+                normalized_item,
+                pair.curve.label(),
+                curve.label()
+            );
+            pair.curve = curve;
+            store.dirty = true;
+            let _ = respond_to.send(Ok(()));
+            Ok(())
+        }
         CliMessage::QueryStorage { respond_to } => {
             debug!("[CLI-Store] Querying storage state");
             let _ = respond_to.send(store.storage.clone());
@@ -598,6 +733,13 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
                     let desc =
                         format!("Order #{} [{}]: {}", trade.order().id, trade.phase(), trade);
                     warn!("[CLI-Store] Clearing stuck order: {}", desc);
+                    store.audit_log.record(
+                        trade.order().id,
+                        AuditEvent::Operator {
+                            action: "clear_stuck_order".to_string(),
+                            detail: format!("cleared in phase {}", trade.phase()),
+                        },
+                    );
                     Some(desc)
                 } else {
                     warn!(
@@ -639,6 +781,36 @@ pub async fn handle_cli_message(store: &mut Store, message: CliMessage) -> Resul
             let _ = respond_to.send(stuck_order_desc);
             Ok(())
         }
+        CliMessage::ReplayJournal { path, respond_to } => {
+            let result = match path {
+                Some(path) => {
+                    info!("[CLI-Store] Replaying shulker op from {}", path);
+                    journal_replay::replay_file(store, std::path::Path::new(&path)).await
+                }
+                None => match store.interrupted_journal.clone() {
+                    Some(entry) => {
+                        info!(
+                            "[CLI-Store] Replaying shulker op #{} found at startup",
+                            entry.operation_id
+                        );
+                        let result = journal_replay::replay(store, &entry).await;
+                        if result.is_ok() {
+                            store.interrupted_journal = None;
+                        }
+                        result
+                    }
+                    None => Ok(vec![
+                        "No interrupted shulker operation is waiting; name a journal archive to replay one."
+                            .to_string(),
+                    ]),
+                },
+            };
+            if let Err(e) = &result {
+                error!("[CLI-Store] Journal replay failed: {}", e);
+            }
+            let _ = respond_to.send(result);
+            Ok(())
+        }
         CliMessage::Shutdown { respond_to } => {
             // Graceful shutdown sequence (also documented in README):
             //   1. Signal Bot to shut down and wait for confirmation
@@ -713,6 +885,7 @@ mod tests {
                 username: "Alice".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let (bot_tx, _bot_rx) = mpsc::channel::<BotInstruction>(16);
@@ -749,6 +922,162 @@ mod tests {
         assert!(user.operator, "operator flag should be set to true");
     }
 
+    fn store_with_pair(item: &str) -> Store {
+        let (bot_tx, _bot_rx) = mpsc::channel::<BotInstruction>(16);
+        let mut pairs = HashMap::new();
+        pairs.insert(
+            item.to_string(),
+            crate::types::Pair {
+                item: ItemId::new(item).unwrap(),
+                stack_size: 64,
+                item_stock: 10,
+                currency_stock: 100.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
+            },
+        );
+        Store::new_for_test(
+            bot_tx,
+            test_config(),
+            pairs,
+            HashMap::new(),
+            crate::types::Storage::default(),
+        )
+    }
+
+    async fn set_pair_fees(
+        store: &mut Store,
+        item: &str,
+        buy_fee: Option<f64>,
+        sell_fee: Option<f64>,
+    ) -> Result<(), String> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let msg = CliMessage::SetPairFees {
+            item_name: item.to_string(),
+            buy_fee,
+            sell_fee,
+            respond_to: resp_tx,
+        };
+        handle_cli_message(store, msg).await.expect("handler ok");
+        resp_rx.await.expect("response received")
+    }
+
+    #[tokio::test]
+    async fn set_pair_fees_applies_and_clears_overrides() {
+        let mut store = store_with_pair("cobblestone");
+        set_pair_fees(&mut store, "cobblestone", Some(0.05), Some(0.2))
+            .await
+            .expect("valid fees accepted");
+        let pair = &store.pairs["cobblestone"];
+        assert_eq!((pair.buy_fee, pair.sell_fee), (Some(0.05), Some(0.2)));
+        assert!(store.dirty);
+
+        set_pair_fees(&mut store, "cobblestone", None, None)
+            .await
+            .expect("clearing accepted");
+        assert!(!store.pairs["cobblestone"].has_fee_override());
+    }
+
+    #[tokio::test]
+    async fn set_pair_fees_rejects_out_of_range_fee_without_mutating() {
+        let mut store = store_with_pair("cobblestone");
+        let err = set_pair_fees(&mut store, "cobblestone", Some(0.05), Some(1.5))
+            .await
+            .unwrap_err();
+        assert!(err.contains("out of range"), "{}", err);
+        assert!(!store.pairs["cobblestone"].has_fee_override());
+        assert!(!store.dirty);
+
+        let err = set_pair_fees(&mut store, "gravel", Some(0.05), None)
+            .await
+            .unwrap_err();
+        assert!(err.contains("not found"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn query_price_history_requires_a_known_pair() {
+        use crate::store::price_history::Resolution;
+        let mut store = store_with_pair("cobblestone");
+        let mut trade = crate::types::Trade::new(
+            crate::types::TradeType::Buy,
+            ItemId::new("cobblestone").unwrap(),
+            4,
+            10.0,
+            "uuid".to_string(),
+        )
+        .with_reserves(60, 510.0, Some(8.5));
+        trade.timestamp = chrono::Utc::now();
+        store.price_history.record(&trade);
+
+        let query = |item: &str| {
+            let (resp_tx, resp_rx) = oneshot::channel();
+            (
+                CliMessage::QueryPriceHistory {
+                    item_name: item.to_string(),
+                    resolution: Resolution::Daily,
+                    respond_to: resp_tx,
+                },
+                resp_rx,
+            )
+        };
+
+        let (msg, rx) = query("minecraft:cobblestone");
+        handle_cli_message(&mut store, msg)
+            .await
+            .expect("handler ok");
+        let candles = rx.await.unwrap().expect("known pair");
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].close, 8.5);
+
+        let (msg, rx) = query("gold_ingot");
+        handle_cli_message(&mut store, msg)
+            .await
+            .expect("handler ok");
+        assert!(rx.await.unwrap().unwrap_err().contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn set_pair_curve_validates_before_applying() {
+        use crate::store::curve::PricingCurve;
+        let mut store = store_with_pair("cobblestone");
+        let send = |curve| {
+            let (resp_tx, resp_rx) = oneshot::channel();
+            (
+                CliMessage::SetPairCurve {
+                    item_name: "cobblestone".to_string(),
+                    curve,
+                    respond_to: resp_tx,
+                },
+                resp_rx,
+            )
+        };
+
+        let (msg, rx) = send(PricingCurve::Fixed {
+            price: -1.0,
+            max_stock: None,
+        });
+        handle_cli_message(&mut store, msg)
+            .await
+            .expect("handler ok");
+        assert!(rx.await.unwrap().is_err());
+        assert!(store.pairs["cobblestone"].curve.is_constant_product());
+
+        let fixed = PricingCurve::Fixed {
+            price: 2.0,
+            max_stock: None,
+        };
+        let (msg, rx) = send(fixed);
+        handle_cli_message(&mut store, msg)
+            .await
+            .expect("handler ok");
+        rx.await.unwrap().expect("valid curve accepted");
+        assert_eq!(store.pairs["cobblestone"].curve, fixed);
+        assert!(store.dirty);
+    }
+
     #[tokio::test]
     async fn set_operator_by_unknown_uuid_is_rejected() {
         // No pre-existing user. Typing an unknown UUID must be a hard error
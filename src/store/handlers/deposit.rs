@@ -2,6 +2,7 @@
 
 use tracing::{debug, error, info, warn};
 
+use super::super::ledger::Account;
 use super::super::{Store, state, utils};
 use crate::constants::MAX_TRADE_DIAMONDS;
 use crate::error::StoreError;
@@ -237,6 +238,12 @@ pub async fn handle_deposit_balance_queued(
         user.username = player_name.to_owned();
         user.balance
     };
+    store.post_ledger(
+        Account::Vault,
+        Account::Player(user_uuid.clone()),
+        actual_amount,
+        "deposit",
+    );
     store.dirty = true;
     store.dirty_users.insert(user_uuid.clone());
 
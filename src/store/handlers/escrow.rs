@@ -0,0 +1,1120 @@
+//! `escrow` command: player-to-player sales held by the bot.
+//!
+//! `escrow sell <buyer> <item> <qty> <price>` opens an escrow and queues the
+//! seller's deposit `/trade`; `escrow accept E<id>` queues the buyer's
+//! payment `/trade`, which hands over the items; `escrow cancel E<id>`
+//! queues the return `/trade` to the seller; bare `escrow` lists the
+//! caller's escrows. Each `/trade` walks the usual `TradeState` machine and
+//! rolls back like a buy or sell: a failed payment puts the items back in
+//! escrow and refunds what the buyer handed over, a failed return leaves
+//! them `Unclaimed` for the seller to retry.
+//!
+//! The buyer pays from their balance first and tops up with diamonds in the
+//! trade, as for a buy; the seller is paid into their balance. [`sweep`]
+//! returns escrows the buyer never accepted and settles escrows whose order
+//! was cancelled or lost to a restart.
+
+use chrono::{Duration, Utc};
+use tracing::{error, info, warn};
+
+use super::super::escrow::{Escrow, EscrowStatus};
+use super::super::ledger::{Account, ESCROW_REFUND_MEMO};
+use super::super::orders::{
+    ChestDirection, diamonds_to_offer_for_buy, execute_chest_transfers, perform_trade,
+};
+use super::super::{Store, rollback, state, utils};
+use super::limit::format_ttl;
+use crate::constants::{ESCROW_TTL_SECS, MAX_TRADE_DIAMONDS, TRADE_OFFER_SLOTS_PER_SIDE};
+use crate::error::StoreError;
+use crate::messages::{QueuedOrderType, TradeItem};
+use crate::types::{ItemId, Trade, TradeType};
+
+/// Stack size used to plan escrow chests for `item`.
+fn stack_size(store: &Store, item: &str) -> i32 {
+    store.pairs.get(item).map_or(64, |p| p.stack_size)
+}
+
+/// Queue `order_type` for `escrow` on behalf of `uuid`/`name` and record the
+/// order on the escrow. `Err` carries the queue's whisperable reason.
+fn enqueue(
+    store: &mut Store,
+    escrow: &Escrow,
+    uuid: &str,
+    name: &str,
+    order_type: QueuedOrderType,
+) -> Result<(u64, usize), String> {
+    let (order_id, position) = store.order_queue.add(
+        uuid.to_string(),
+        name.to_string(),
+        order_type,
+        escrow.item.clone(),
+        escrow.quantity.max(0) as u32,
+    )?;
+    store
+        .escrow
+        .update(escrow.id, |e| e.order_id = Some(order_id));
+    Ok((order_id, position))
+}
+
+/// Move escrow `id` to `status` and drop its order.
+fn settle(store: &mut Store, id: u64, status: EscrowStatus) {
+    store.escrow.update(id, |e| {
+        e.status = status;
+        e.order_id = None;
+    });
+}
+
+pub(super) async fn handle_sell_enqueue(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    buyer: &str,
+    item: &ItemId,
+    quantity: u32,
+    price: f64,
+) -> Result<(), StoreError> {
+    let buyer_uuid = match crate::mojang::resolve_user_uuid(buyer).await {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let err: StoreError = e.into();
+            return utils::whisper_error_to_player(store, player_name, &err).await;
+        }
+    };
+    if buyer_uuid == user_uuid {
+        return utils::send_message_to_player(store, player_name, "You cannot sell to yourself.")
+            .await;
+    }
+    if item.as_str() == "diamond" {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            "Diamonds cannot be escrowed. Use 'pay' to send them.",
+        )
+        .await;
+    }
+    if !store.pairs.contains_key(item.as_str()) {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    }
+    let stack_size = stack_size(store, item.as_str());
+    let qty = i32::try_from(quantity).unwrap_or(i32::MAX);
+    let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE * stack_size;
+    if qty > max_per_trade {
+        let msg = format!(
+            "Cannot escrow {} {} - the trade window holds at most {}.",
+            qty, item, max_per_trade
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+    let (_, planned) = store
+        .storage
+        .simulate_escrow_deposit_plan(item.as_str(), qty, stack_size);
+    if planned < qty {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            "No storage is free for escrow right now. Please contact an operator.",
+        )
+        .await;
+    }
+    let buyer_name = store
+        .users
+        .get(&buyer_uuid)
+        .map_or_else(|| buyer.to_string(), |u| u.username.clone());
+
+    let escrow = match store.escrow.open(
+        user_uuid,
+        player_name,
+        &buyer_uuid,
+        &buyer_name,
+        item.as_str(),
+        qty,
+        price,
+        Utc::now(),
+        Duration::seconds(ESCROW_TTL_SECS as i64),
+    ) {
+        Ok(e) => e,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    let order_type = QueuedOrderType::EscrowDeposit {
+        escrow_id: escrow.id,
+    };
+    match enqueue(store, &escrow, user_uuid, player_name, order_type) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Escrow E{} opened: {} {} to {} for {:.2} diamonds. Order #{} queued (position {}/{}). Est. wait: {}. Offer the items when the bot trades you.",
+                escrow.id,
+                qty,
+                item,
+                buyer_name,
+                price,
+                order_id,
+                position,
+                queue_len,
+                wait_estimate
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => {
+            store.escrow.remove(escrow.id);
+            utils::send_message_to_player(store, player_name, &e).await
+        }
+    }
+}
+
+/// Whisper the escrows the caller sells or buys through.
+pub(super) async fn handle_list(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+) -> Result<(), StoreError> {
+    let now = Utc::now();
+    let parts: Vec<String> = store
+        .escrow
+        .involving(user_uuid)
+        .into_iter()
+        .map(|e| {
+            let who = if e.seller_uuid == user_uuid {
+                format!("to {}", e.buyer_name)
+            } else {
+                format!("from {}", e.seller_name)
+            };
+            let state = match e.status {
+                EscrowStatus::Held => {
+                    let left = (e.expires_at - now).num_seconds().max(0) as u64;
+                    format!("held, {} left", format_ttl(left))
+                }
+                EscrowStatus::Depositing => "awaiting deposit".to_string(),
+                EscrowStatus::Releasing => "paying".to_string(),
+                EscrowStatus::Returning => "returning".to_string(),
+                EscrowStatus::Unclaimed => "unclaimed".to_string(),
+            };
+            format!(
+                "E{} {} {} {} for {:.2} ({})",
+                e.id, e.quantity, e.item, who, e.price, state
+            )
+        })
+        .collect();
+    let message = if parts.is_empty() {
+        "You have no escrows. Sell to a player with 'escrow sell <buyer> <item> <quantity> <price>'."
+            .to_string()
+    } else {
+        format!("Your escrows: {}", parts.join(", "))
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+/// Escrow `id` if `user_uuid` is its seller or buyer. Anyone else is told
+/// it does not exist, as for payment requests.
+fn find_own(store: &Store, user_uuid: &str, id: u64) -> Result<Escrow, String> {
+    store
+        .escrow
+        .get(id)
+        .filter(|e| e.seller_uuid == user_uuid || e.buyer_uuid == user_uuid)
+        .cloned()
+        .ok_or_else(|| format!("Escrow E{} not found. 'escrow' lists yours.", id))
+}
+
+pub(super) async fn handle_accept(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let escrow = match find_own(store, user_uuid, id) {
+        Ok(e) if e.buyer_uuid != user_uuid => {
+            let msg = format!(
+                "E{} is your own sale. Call it off with 'escrow cancel E{}'.",
+                id, id
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        Ok(e) => e,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    if escrow.status != EscrowStatus::Held || escrow.is_expired(Utc::now()) {
+        let msg = format!("Escrow E{} is not waiting for payment.", id);
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    settle(store, id, EscrowStatus::Releasing);
+    let order_type = QueuedOrderType::EscrowRelease { escrow_id: id };
+    match enqueue(store, &escrow, user_uuid, player_name, order_type) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let balance = store.users.get(user_uuid).map_or(0.0, |u| u.balance);
+            let offer = diamonds_to_offer_for_buy(escrow.price, balance).unwrap_or(0);
+            let payment = if offer > 0 {
+                format!(" Bring {} diamonds for the trade.", offer)
+            } else {
+                " It is paid from your balance.".to_string()
+            };
+            let msg = format!(
+                "Escrow E{} payment order #{} queued (position {}/{}). Est. wait: {}. Price {:.2} diamonds.{}",
+                id, order_id, position, queue_len, wait_estimate, escrow.price, payment
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => {
+            settle(store, id, EscrowStatus::Held);
+            utils::send_message_to_player(store, player_name, &e).await
+        }
+    }
+}
+
+/// Call off an escrow: the buyer refuses it or the seller takes it back.
+pub(super) async fn handle_cancel(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let escrow = match find_own(store, user_uuid, id) {
+        Ok(e) => e,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    let is_seller = escrow.seller_uuid == user_uuid;
+    match escrow.status {
+        EscrowStatus::Held => {}
+        EscrowStatus::Unclaimed if is_seller => {}
+        EscrowStatus::Depositing if is_seller => {
+            if let Some(order_id) = escrow.order_id
+                && let Err(e) = store.order_queue.cancel(user_uuid, order_id)
+            {
+                return utils::send_message_to_player(store, player_name, &e).await;
+            }
+            store.escrow.remove(id);
+            let notice = format!(
+                "{} called off escrow E{} before depositing.",
+                player_name, id
+            );
+            utils::notify_player(store, &escrow.buyer_uuid, &escrow.buyer_name, notice).await;
+            let reply = format!("Escrow E{} cancelled. Nothing was deposited.", id);
+            return utils::send_message_to_player(store, player_name, &reply).await;
+        }
+        _ => {
+            let msg = format!("Escrow E{} cannot be cancelled right now.", id);
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+    }
+
+    let previous = escrow.status;
+    settle(store, id, EscrowStatus::Returning);
+    let order_type = QueuedOrderType::EscrowReturn { escrow_id: id };
+    let (seller_uuid, seller_name) = (escrow.seller_uuid.clone(), escrow.seller_name.clone());
+    if let Err(e) = enqueue(store, &escrow, &seller_uuid, &seller_name, order_type) {
+        settle(store, id, previous);
+        return utils::send_message_to_player(store, player_name, &e).await;
+    }
+    info!(escrow_id = id, by = player_name, "Escrow cancelled");
+    if is_seller {
+        if previous == EscrowStatus::Held {
+            let notice = format!("{} withdrew escrow E{}.", player_name, id);
+            utils::notify_player(store, &escrow.buyer_uuid, &escrow.buyer_name, notice).await;
+        }
+        let reply = format!(
+            "Escrow E{} cancelled. The bot will trade your {} {} back to you.",
+            id, escrow.quantity, escrow.item
+        );
+        utils::send_message_to_player(store, player_name, &reply).await
+    } else {
+        let notice = format!(
+            "{} declined escrow E{}. The bot will trade your {} {} back to you.",
+            player_name, id, escrow.quantity, escrow.item
+        );
+        utils::notify_player(store, &seller_uuid, &seller_name, notice).await;
+        let reply = format!("Declined escrow E{} from {}.", id, seller_name);
+        utils::send_message_to_player(store, player_name, &reply).await
+    }
+}
+
+/// The escrow `id` in `status` for `user_uuid`, or a whisper saying it is
+/// gone. Queued handlers start here: the escrow may have been settled while
+/// the order waited.
+async fn expect_escrow(
+    store: &Store,
+    player_name: &str,
+    id: u64,
+    status: EscrowStatus,
+    owner: impl Fn(&Escrow) -> &str,
+    user_uuid: &str,
+) -> Result<Option<Escrow>, StoreError> {
+    match store.escrow.get(id) {
+        Some(e) if e.status == status && owner(e) == user_uuid => Ok(Some(e.clone())),
+        _ => {
+            let msg = format!("Escrow E{} is no longer open.", id);
+            utils::send_message_to_player(store, player_name, &msg).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Take the seller's items into escrow chests. Called by the order queue
+/// processor.
+pub async fn handle_deposit_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let Some(escrow) = expect_escrow(
+        store,
+        player_name,
+        id,
+        EscrowStatus::Depositing,
+        |e| &e.seller_uuid,
+        user_uuid,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+    info!(phase = "escrow_deposit.start", escrow_id = id, player = %player_name, item = %escrow.item, qty = escrow.quantity, "Escrow deposit starting");
+    let item = escrow.item.as_str();
+    let qty = escrow.quantity;
+    let stack_size = stack_size(store, item);
+    let (deposit_plan, planned) = store
+        .storage
+        .simulate_escrow_deposit_plan(item, qty, stack_size);
+    if planned < qty {
+        store.escrow.remove(id);
+        let msg = format!(
+            "Escrow E{} closed: no storage is free for escrow right now. Please contact an operator.",
+            id
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Nothing to withdraw: the empty plan only satisfies the state machine's
+    // Queued -> Withdrawing -> Trading progression (same as deposit).
+    store.advance_trade(|s| s.begin_withdrawal(vec![]));
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Escrow E{}: Please offer {} {} in the trade. They are held for {} until paid.",
+            id, qty, item, escrow.buyer_name
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    let actual_received = match perform_trade(
+        store,
+        player_name,
+        vec![],
+        vec![TradeItem {
+            item: item.to_string(),
+            amount: qty,
+        }],
+        true,
+        false,
+        "[EscrowDeposit]",
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(phase = "escrow_deposit.rollback", escrow_id = id, player = %player_name, "Escrow deposit trade failed: {}", err);
+            store.advance_trade(|s| s.rollback("escrow-deposit/trade-failed".to_string()));
+            store.escrow.remove(id);
+            let suffix = format!(" Escrow E{} closed.", id);
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "Escrow deposit",
+                &err.user_message(),
+                Some(&suffix),
+            )
+            .await;
+        }
+    };
+
+    let target_item_id = crate::bot::Bot::normalize_item_id(item);
+    let items_received: i32 = actual_received
+        .iter()
+        .filter(|t| crate::bot::Bot::normalize_item_id(&t.item) == target_item_id)
+        .map(|t| t.amount)
+        .sum();
+    if items_received != qty {
+        warn!(
+            phase = "escrow_deposit.validation",
+            escrow_id = id,
+            player = %player_name,
+            expected = qty,
+            received = items_received,
+            "Escrow deposit validation failed: item count mismatch"
+        );
+        if items_received > 0 {
+            let _ = perform_trade(
+                store,
+                player_name,
+                vec![TradeItem {
+                    item: item.to_string(),
+                    amount: items_received,
+                }],
+                vec![],
+                false,
+                false,
+                "[EscrowDeposit] return-items",
+            )
+            .await;
+        }
+        store.advance_trade(|s| s.rollback("escrow-deposit/item-count-mismatch".to_string()));
+        store.escrow.remove(id);
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Escrow E{} REJECTED: You put {} {} in the trade but promised {}. Trade cancelled, items returned.",
+                id, items_received, item, qty
+            ),
+        )
+        .await;
+    }
+
+    store.advance_trade(|s| {
+        s.begin_depositing(
+            super::super::trade_state::TradeResult {
+                items_received: actual_received.clone(),
+            },
+            deposit_plan.clone(),
+        )
+    });
+    store.storage.claim_escrow_chests(&deposit_plan, item);
+    store.dirty = true;
+    if let Err(err) = execute_chest_transfers(
+        store,
+        &deposit_plan,
+        item,
+        stack_size,
+        ChestDirection::Deposit,
+        "[EscrowDeposit]",
+    )
+    .await
+    {
+        // Same best-effort return as a failed LP add deposit.
+        let returned = perform_trade(
+            store,
+            player_name,
+            vec![TradeItem {
+                item: item.to_string(),
+                amount: qty,
+            }],
+            vec![],
+            false,
+            false,
+            "[EscrowDeposit] deposit-failed",
+        )
+        .await;
+        store.advance_trade(|s| s.rollback("escrow-deposit/deposit-failed".to_string()));
+        store.escrow.remove(id);
+        store.storage.release_empty_escrow_chests();
+        let msg = match returned {
+            Ok(_) => format!(
+                "Escrow E{} closed: failed to deposit items into storage: {}. Items returned via trade.",
+                id,
+                err.user_message()
+            ),
+            Err(rerr) => format!(
+                "Escrow E{} closed: failed to deposit items into storage: {}. Return-trade also failed ({}). Contact an operator.",
+                id,
+                err.user_message(),
+                rerr.user_message()
+            ),
+        };
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    let expires_at = Utc::now() + Duration::seconds(ESCROW_TTL_SECS as i64);
+    store.escrow.update(id, |e| {
+        e.status = EscrowStatus::Held;
+        e.order_id = None;
+        e.expires_at = expires_at;
+    });
+    store.trades.push(
+        Trade::new(
+            TradeType::EscrowDeposit,
+            ItemId::from_normalized(item.to_string()),
+            qty,
+            escrow.price,
+            escrow.seller_uuid.clone(),
+        )
+        .with_counterparty(escrow.buyer_uuid.clone()),
+    );
+    store.advance_trade(|s| s.commit(item.to_string(), qty, escrow.price));
+    info!(phase = "escrow_deposit.done", escrow_id = id, player = %player_name, item = %item, qty, "Escrow deposit completed");
+    if let Err(e) = state::assert_invariants(store, "post-escrow-deposit", true) {
+        error!(phase = "escrow_deposit.invariant", escrow_id = id, error = %e, "Invariant violation after escrow deposit");
+        let _ = state::save(store);
+    }
+
+    let offer = format!(
+        "{} put {} {} in escrow for you at {:.2} diamonds (E{}). Reply 'escrow accept E{}' to buy or 'escrow cancel E{}' to refuse. Expires in {}.",
+        player_name,
+        qty,
+        item,
+        escrow.price,
+        id,
+        id,
+        id,
+        format_ttl(ESCROW_TTL_SECS)
+    );
+    utils::notify_player(store, &escrow.buyer_uuid, &escrow.buyer_name, offer).await;
+    let reply = format!(
+        "Escrow E{}: {} {} held for {}. You are paid when they accept; 'escrow cancel E{}' takes them back.",
+        id, qty, item, escrow.buyer_name, id
+    );
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Take the buyer's payment and hand over the escrowed items. Called by the
+/// order queue processor.
+pub async fn handle_release_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let Some(escrow) = expect_escrow(
+        store,
+        player_name,
+        id,
+        EscrowStatus::Releasing,
+        |e| &e.buyer_uuid,
+        user_uuid,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+    info!(phase = "escrow_release.start", escrow_id = id, player = %player_name, item = %escrow.item, qty = escrow.quantity, "Escrow release starting");
+    let item = escrow.item.as_str();
+    let qty = escrow.quantity;
+    let price = escrow.price;
+    if let Err(e) = state::assert_tradeable(store, item, user_uuid, "pre-escrow-release") {
+        settle(store, id, EscrowStatus::Held);
+        return Err(e);
+    }
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let stack_size = stack_size(store, item);
+
+    let balance = store.users.get(user_uuid).map_or(0.0, |u| u.balance);
+    let diamonds_to_offer = match diamonds_to_offer_for_buy(price, balance) {
+        Some(d) if d <= MAX_TRADE_DIAMONDS => d,
+        _ => {
+            settle(store, id, EscrowStatus::Held);
+            let msg = format!(
+                "Escrow E{} needs more diamonds than one trade can carry. 'deposit' some first, then accept again.",
+                id
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+    };
+    let (withdraw_plan, planned) = store.storage.simulate_escrow_withdraw_plan(item, qty);
+    if planned < qty {
+        error!(
+            phase = "escrow_release.plan",
+            escrow_id = id,
+            item = %item,
+            expected = qty,
+            found = planned,
+            "Escrowed items missing from storage"
+        );
+        settle(store, id, EscrowStatus::Held);
+        let msg = format!(
+            "Escrow E{} cannot be released right now; an operator has been alerted.",
+            id
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    store.advance_trade(|s| s.begin_withdrawal(withdraw_plan.clone()));
+    if let Err(e) = execute_chest_transfers(
+        store,
+        &withdraw_plan,
+        item,
+        stack_size,
+        ChestDirection::Withdraw,
+        "[EscrowRelease]",
+    )
+    .await
+    {
+        store.advance_trade(|s| s.rollback("escrow-release/chest-withdrawal-failed".to_string()));
+        settle(store, id, EscrowStatus::Held);
+        let msg = format!(
+            "Escrow E{} payment aborted: bot failed chest withdrawal step: {}. Accept again to retry.",
+            id,
+            e.user_message()
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    let terms = if diamonds_to_offer > 0 {
+        format!(
+            "Escrow E{}: {} {} for {:.2} diamonds. Please offer {} diamonds in the trade.",
+            id, qty, item, price, diamonds_to_offer
+        )
+    } else {
+        format!(
+            "Escrow E{}: {} {} for {:.2} diamonds (paid from balance). No diamonds needed in trade.",
+            id, qty, item, price
+        )
+    };
+    utils::send_message_to_player(store, player_name, &terms).await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    let player_offers = if diamonds_to_offer > 0 {
+        vec![TradeItem {
+            item: "diamond".to_string(),
+            amount: diamonds_to_offer,
+        }]
+    } else {
+        vec![]
+    };
+    let trade_result = perform_trade(
+        store,
+        player_name,
+        vec![TradeItem {
+            item: item.to_string(),
+            amount: qty,
+        }],
+        player_offers,
+        false,
+        false,
+        "[EscrowRelease]",
+    )
+    .await;
+    let actual_received = match trade_result {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(phase = "escrow_release.rollback", escrow_id = id, player = %player_name, "Trade failed, rolling back: {}", err);
+            let rb = rollback::deposit_transfers(
+                store,
+                &withdraw_plan,
+                item,
+                stack_size,
+                "[EscrowRelease]",
+            )
+            .await;
+            store.advance_trade(|s| s.rollback("escrow-release/trade-failed".to_string()));
+            settle(store, id, EscrowStatus::Held);
+            let suffix = match rb.partial_message() {
+                Some(detail) => format!(" Rollback partial: {}.", detail),
+                None => " Items are back in escrow; accept again to retry.".to_string(),
+            };
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "Escrow payment",
+                &err.user_message(),
+                Some(&suffix),
+            )
+            .await;
+        }
+    };
+
+    let diamonds_received: i32 = actual_received
+        .iter()
+        .filter(|t| t.item == "diamond")
+        .map(|t| t.amount)
+        .sum();
+    // Any diamonds handed over go to storage first: they back the seller's
+    // credit on success and the buyer's refund on a short payment.
+    let physical_diamonds = if diamonds_received > 0 {
+        let rb = rollback::rollback_amount_to_storage(
+            store,
+            "diamond",
+            diamonds_received,
+            64,
+            "[EscrowRelease] diamond-deposit",
+        )
+        .await;
+        if rb.has_failures() {
+            error!(
+                phase = "escrow_release.diamond_deposit",
+                escrow_id = id,
+                diamonds_received,
+                items_returned = rb.items_returned,
+                "Diamond deposit partially failed. Operator must reconcile stranded diamonds."
+            );
+        }
+        rb.items_returned
+    } else {
+        0
+    };
+    let physical_f64 = physical_diamonds as f64;
+
+    let total_available = (diamonds_received as f64) + balance;
+    if total_available < price {
+        error!(
+            phase = "escrow_release.payment_check",
+            escrow_id = id,
+            player = %player_name,
+            diamonds_received,
+            balance = format_args!("{:.2}", balance),
+            price = format_args!("{:.2}", price),
+            "Insufficient payment after trade"
+        );
+        let _ = rollback::deposit_transfers(
+            store,
+            &withdraw_plan,
+            item,
+            stack_size,
+            "[EscrowRelease] insufficient-payment",
+        )
+        .await;
+        if physical_diamonds > 0 {
+            store
+                .expect_user_mut(user_uuid, "escrow-release/refund")?
+                .balance += physical_f64;
+            store.dirty_users.insert(user_uuid.to_string());
+            store.post_ledger(
+                Account::Vault,
+                Account::Player(user_uuid.to_string()),
+                physical_f64,
+                ESCROW_REFUND_MEMO,
+            );
+            store.trades.push(Trade::refund(
+                ItemId::from_normalized(item.to_string()),
+                physical_f64,
+                user_uuid.to_string(),
+            ));
+            store.dirty = true;
+        }
+        store.advance_trade(|s| s.rollback("escrow-release/insufficient-payment".to_string()));
+        settle(store, id, EscrowStatus::Held);
+        let msg = format!(
+            "Escrow E{} payment aborted: you paid {} diamonds but need {:.2} total (your balance: {:.2}). Items are back in escrow.{}",
+            id,
+            diamonds_received,
+            price,
+            balance,
+            if physical_diamonds > 0 {
+                format!(
+                    " Your {} diamonds have been credited to your balance.",
+                    physical_diamonds
+                )
+            } else {
+                String::new()
+            }
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Same split as a buy: the balance covers what the diamonds did not,
+    // and only diamonds that reached storage back a surplus credit.
+    let balance_needed = price - diamonds_received as f64;
+    let (balance_deduction, surplus) = if balance_needed > 0.0 {
+        (balance_needed.min(balance), 0.0)
+    } else {
+        (0.0, (physical_f64 - price).max(0.0))
+    };
+    let tendered = physical_f64 - surplus;
+    let seller_credit = tendered + balance_deduction;
+    {
+        let buyer = store.expect_user_mut(user_uuid, "escrow-release/commit-buyer")?;
+        buyer.balance += surplus - balance_deduction;
+        buyer.username = player_name.to_owned();
+    }
+    if !store.users.contains_key(&escrow.seller_uuid) {
+        utils::ensure_user_exists(store, &escrow.seller_name, &escrow.seller_uuid);
+    }
+    store
+        .expect_user_mut(&escrow.seller_uuid, "escrow-release/commit-seller")?
+        .balance += seller_credit;
+    store.dirty_users.insert(user_uuid.to_string());
+    store.dirty_users.insert(escrow.seller_uuid.clone());
+    let buyer = Account::Player(user_uuid.to_string());
+    let seller = Account::Player(escrow.seller_uuid.clone());
+    store.post_ledger(Account::Vault, seller.clone(), tendered, "escrow");
+    store.post_ledger(buyer.clone(), seller, balance_deduction, "escrow");
+    store.post_ledger(Account::Vault, buyer, surplus, "escrow/surplus");
+
+    store.escrow.remove(id);
+    store.storage.release_empty_escrow_chests();
+    store.dirty = true;
+    store.trades.push(
+        Trade::new(
+            TradeType::EscrowRelease,
+            ItemId::from_normalized(item.to_string()),
+            qty,
+            seller_credit,
+            user_uuid.to_string(),
+        )
+        .with_balance_change(surplus - balance_deduction)
+        .with_counterparty(escrow.seller_uuid.clone()),
+    );
+    store.advance_trade(|s| s.commit(item.to_string(), qty, seller_credit));
+    info!(
+        phase = "escrow_release.done",
+        escrow_id = id,
+        buyer = %player_name,
+        seller = %escrow.seller_name,
+        item = %item,
+        qty,
+        paid = format_args!("{:.2}", seller_credit),
+        "Escrow released"
+    );
+    if let Err(e) = state::assert_invariants(store, "post-escrow-release", true) {
+        error!(phase = "escrow_release.invariant", escrow_id = id, error = %e, "Invariant violation after escrow release");
+        let _ = state::save(store);
+    }
+
+    let notice = format!(
+        "{} accepted escrow E{}: {:.2} diamonds for {} {} credited to your balance.",
+        player_name, id, seller_credit, qty, item
+    );
+    utils::notify_player(store, &escrow.seller_uuid, &escrow.seller_name, notice).await;
+    let payment = if surplus > 0.001 {
+        format!(" {:.2} surplus credited to balance.", surplus)
+    } else if balance_deduction > 0.001 {
+        format!(" {:.2} deducted from balance.", balance_deduction)
+    } else {
+        String::new()
+    };
+    let reply = format!(
+        "Bought {} {} from {} for {:.2} diamonds (escrow E{}).{}",
+        qty, item, escrow.seller_name, price, id, payment
+    );
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Trade escrowed items back to the seller. Called by the order queue
+/// processor; a failure leaves the escrow `Unclaimed`.
+pub async fn handle_return_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let Some(escrow) = expect_escrow(
+        store,
+        player_name,
+        id,
+        EscrowStatus::Returning,
+        |e| &e.seller_uuid,
+        user_uuid,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+    info!(phase = "escrow_return.start", escrow_id = id, player = %player_name, item = %escrow.item, qty = escrow.quantity, "Escrow return starting");
+    let item = escrow.item.as_str();
+    let qty = escrow.quantity;
+    let stack_size = stack_size(store, item);
+    let retry = format!(" Use 'escrow cancel E{}' to try again.", id);
+
+    let (withdraw_plan, planned) = store.storage.simulate_escrow_withdraw_plan(item, qty);
+    if planned < qty {
+        error!(
+            phase = "escrow_return.plan",
+            escrow_id = id,
+            item = %item,
+            expected = qty,
+            found = planned,
+            "Escrowed items missing from storage"
+        );
+        settle(store, id, EscrowStatus::Unclaimed);
+        let msg = format!(
+            "Escrow E{} cannot be returned right now; an operator has been alerted.",
+            id
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    store.advance_trade(|s| s.begin_withdrawal(withdraw_plan.clone()));
+    if let Err(e) = execute_chest_transfers(
+        store,
+        &withdraw_plan,
+        item,
+        stack_size,
+        ChestDirection::Withdraw,
+        "[EscrowReturn]",
+    )
+    .await
+    {
+        store.advance_trade(|s| s.rollback("escrow-return/chest-withdrawal-failed".to_string()));
+        settle(store, id, EscrowStatus::Unclaimed);
+        let msg = format!(
+            "Escrow E{} return aborted: bot failed chest withdrawal step: {}.{}",
+            id,
+            e.user_message(),
+            retry
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Escrow E{}: returning your {} {}. Accept the trade.",
+            id, qty, item
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+    if let Err(err) = perform_trade(
+        store,
+        player_name,
+        vec![TradeItem {
+            item: item.to_string(),
+            amount: qty,
+        }],
+        vec![],
+        false,
+        false,
+        "[EscrowReturn]",
+    )
+    .await
+    {
+        warn!(phase = "escrow_return.rollback", escrow_id = id, player = %player_name, "Trade failed, rolling back: {}", err);
+        let rb =
+            rollback::deposit_transfers(store, &withdraw_plan, item, stack_size, "[EscrowReturn]")
+                .await;
+        store.advance_trade(|s| s.rollback("escrow-return/trade-failed".to_string()));
+        settle(store, id, EscrowStatus::Unclaimed);
+        let suffix = match rb.partial_message() {
+            Some(detail) => format!(" Rollback partial: {}. Contact an operator.", detail),
+            None => retry,
+        };
+        return utils::whisper_action_aborted(
+            store,
+            player_name,
+            "Escrow return",
+            &err.user_message(),
+            Some(&suffix),
+        )
+        .await;
+    }
+
+    store.escrow.remove(id);
+    store.storage.release_empty_escrow_chests();
+    store.dirty = true;
+    store.trades.push(
+        Trade::new(
+            TradeType::EscrowReturn,
+            ItemId::from_normalized(item.to_string()),
+            qty,
+            0.0,
+            user_uuid.to_string(),
+        )
+        .with_counterparty(escrow.buyer_uuid.clone()),
+    );
+    store.advance_trade(|s| s.commit(item.to_string(), qty, 0.0));
+    info!(phase = "escrow_return.done", escrow_id = id, player = %player_name, item = %item, qty, "Escrow returned");
+    if let Err(e) = state::assert_invariants(store, "post-escrow-return", true) {
+        error!(phase = "escrow_return.invariant", escrow_id = id, error = %e, "Invariant violation after escrow return");
+        let _ = state::save(store);
+    }
+    let reply = format!("Escrow E{} closed: {} {} returned to you.", id, qty, item);
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Settle an escrow whose order is gone (cancelled with `cancel`, or lost
+/// when the store restarted mid-trade): a payment or return that never ran
+/// leaves the items held, and a deposit that never ran closes the escrow.
+/// A deposit cut off after the chests were filled keeps the escrow for the
+/// seller to reclaim.
+async fn unwind(store: &mut Store, id: u64) {
+    let Some(escrow) = store.escrow.get(id).cloned() else {
+        return;
+    };
+    match escrow.status {
+        EscrowStatus::Releasing => {
+            settle(store, id, EscrowStatus::Held);
+            let notice = format!(
+                "Your payment for escrow E{} did not go through. Reply 'escrow accept E{}' to try again.",
+                id, id
+            );
+            utils::notify_player(store, &escrow.buyer_uuid, &escrow.buyer_name, notice).await;
+        }
+        EscrowStatus::Returning => {
+            settle(store, id, EscrowStatus::Unclaimed);
+            let notice = format!(
+                "Escrow E{} was not returned to you. Reply 'escrow cancel E{}' to collect your {} {}.",
+                id, id, escrow.quantity, escrow.item
+            );
+            utils::notify_player(store, &escrow.seller_uuid, &escrow.seller_name, notice).await;
+        }
+        EscrowStatus::Depositing => {
+            let stored = store.storage.escrow_item_amount(&escrow.item);
+            let accounted = store.escrow.held_quantity(&escrow.item, id);
+            if stored - accounted >= escrow.quantity {
+                warn!(
+                    "[Escrow] E{} deposit was interrupted after the items were stored; marking unclaimed",
+                    id
+                );
+                settle(store, id, EscrowStatus::Unclaimed);
+                let notice = format!(
+                    "Escrow E{} was interrupted. Reply 'escrow cancel E{}' to collect your {} {}.",
+                    id, id, escrow.quantity, escrow.item
+                );
+                utils::notify_player(store, &escrow.seller_uuid, &escrow.seller_name, notice).await;
+            } else {
+                store.escrow.remove(id);
+                store.storage.release_empty_escrow_chests();
+            }
+        }
+        EscrowStatus::Held | EscrowStatus::Unclaimed => {}
+    }
+}
+
+/// Retry unsaved escrow changes, send back escrows the buyer let expire,
+/// and [`unwind`] escrows whose order no longer exists. Called from the run
+/// loop's periodic cleanup while any escrow is open.
+pub(crate) async fn sweep(store: &mut Store) {
+    store.escrow.flush();
+
+    let in_flight = store
+        .current_trade
+        .as_ref()
+        .filter(|t| !t.is_terminal())
+        .map(|t| t.order().id);
+    for (id, order_id) in store.escrow.awaiting_orders() {
+        let live = order_id
+            .is_some_and(|o| in_flight == Some(o) || store.order_queue.get_position(o).is_some());
+        if !live {
+            unwind(store, id).await;
+        }
+    }
+
+    for id in store.escrow.expired_ids(Utc::now()) {
+        let Some(escrow) = store.escrow.get(id).cloned() else {
+            continue;
+        };
+        info!(
+            "[Escrow] E{} expired unaccepted ({} to {})",
+            id, escrow.seller_name, escrow.buyer_name
+        );
+        settle(store, id, EscrowStatus::Returning);
+        let order_type = QueuedOrderType::EscrowReturn { escrow_id: id };
+        let (seller_uuid, seller_name) = (escrow.seller_uuid.clone(), escrow.seller_name.clone());
+        let notice = match enqueue(store, &escrow, &seller_uuid, &seller_name, order_type) {
+            Ok(_) => format!(
+                "Escrow E{} expired: {} did not accept. The bot will trade your {} {} back to you.",
+                id, escrow.buyer_name, escrow.quantity, escrow.item
+            ),
+            Err(e) => {
+                warn!("[Escrow] could not queue the return of E{}: {}", id, e);
+                settle(store, id, EscrowStatus::Unclaimed);
+                format!(
+                    "Escrow E{} expired: {} did not accept. Reply 'escrow cancel E{}' to collect your {} {}.",
+                    id, escrow.buyer_name, id, escrow.quantity, escrow.item
+                )
+            }
+        };
+        utils::notify_player(store, &seller_uuid, &seller_name, notice).await;
+        let expired = format!("Escrow E{} from {} expired.", id, seller_name);
+        utils::notify_player(store, &escrow.buyer_uuid, &escrow.buyer_name, expired).await;
+    }
+}
@@ -1,15 +1,19 @@
 //! Read-only / quick informational commands:
-//! `price`, `balance`, `pay`, `items`, `queue`, `cancel`, `status`, `help`.
+//! `price`, `stats`, `history`, `receipt`, `balance`, `pay`, `items`, `queue`,
+//! `cancel`, `status`, `help`.
 //!
 //! These run inline on the Store task (no bot trade round-trip) and therefore
 //! live outside the queued-order path.
 
 use tracing::{info, warn};
 
+use super::super::ledger::{Account, PAY_MEMO};
+use super::super::price_history::HistoryWindow;
 use super::super::pricing;
 use super::super::{Store, state, utils};
+use super::limit;
 use crate::error::StoreError;
-use crate::types::ItemId;
+use crate::types::{ItemId, Trade, TradeType};
 
 pub(super) async fn handle_price(
     store: &mut Store,
@@ -20,6 +24,105 @@ pub(super) async fn handle_price(
     handle_price_command(store, player_name, item, quantity).await
 }
 
+/// Whisper the pair's cumulative trading statistics (`Pair::stats`).
+pub(super) async fn handle_stats(
+    store: &mut Store,
+    player_name: &str,
+    item: &ItemId,
+) -> Result<(), StoreError> {
+    let message = match store.pairs.get(item.as_str()) {
+        Some(pair) => format!("{} stats: {}", item, pair.stats.summary()),
+        None => format!("Item '{}' is not available for trading", item),
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+/// Whisper an OHLC + volume summary of `item` over `window`.
+pub(super) async fn handle_history(
+    store: &mut Store,
+    player_name: &str,
+    item: &ItemId,
+    window: HistoryWindow,
+) -> Result<(), StoreError> {
+    let message = if !store.pairs.contains_key(item.as_str()) {
+        format!("Item '{}' is not available for trading", item)
+    } else {
+        store
+            .price_history
+            .summary(item.as_str(), window, chrono::Utc::now())
+            .unwrap_or_else(|| format!("No trades for {} in the last {}.", item, window.label()))
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+/// Whisper one page of the caller's own history, newest first.
+pub(super) async fn handle_my_history(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    page: usize,
+) -> Result<(), StoreError> {
+    const ENTRIES_PER_PAGE: usize = 4;
+    let entries = own_history(store, user_uuid);
+    if entries.is_empty() {
+        return utils::send_message_to_player(store, player_name, "You have no history yet.").await;
+    }
+
+    let total_pages = entries.len().div_ceil(ENTRIES_PER_PAGE);
+    if page > total_pages {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Invalid page. Use 'history 1' to 'history {}'.",
+                total_pages
+            ),
+        )
+        .await;
+    }
+
+    let start = (page - 1) * ENTRIES_PER_PAGE;
+    let lines: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(ENTRIES_PER_PAGE)
+        .map(|(i, entry)| format!("{}. {}", i + 1, entry.summary()))
+        .collect();
+    let message = if total_pages == 1 {
+        format!("Your history: {}", lines.join(", "))
+    } else {
+        format!(
+            "Your history (page {}/{}): {}",
+            page,
+            total_pages,
+            lines.join(", ")
+        )
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+/// Whisper the details of entry `index` (1 = newest) of the caller's history.
+pub(super) async fn handle_receipt(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    index: usize,
+) -> Result<(), StoreError> {
+    let entries = own_history(store, user_uuid);
+    let message = match entries.get(index - 1) {
+        Some(entry) => format!("Receipt #{}: {}", index, entry.receipt()),
+        None if entries.is_empty() => "You have no history yet.".to_string(),
+        None => format!(
+            "No entry #{}. Your history has {} entr{}.",
+            index,
+            entries.len(),
+            if entries.len() == 1 { "y" } else { "ies" }
+        ),
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
 pub(super) async fn handle_balance(
     store: &mut Store,
     player_name: &str,
@@ -85,9 +188,11 @@ pub(super) async fn handle_pay(
     payer_uuid: &str,
     recipient: &str,
     amount: f64,
+    memo: Option<String>,
 ) -> Result<(), StoreError> {
-    match pay_async(store, player_name, payer_uuid, recipient, amount).await {
-        Ok(()) => {
+    let note = memo_suffix(memo.as_deref());
+    match pay_async(store, player_name, payer_uuid, recipient, amount, memo).await {
+        Ok(payee_uuid) => {
             info!(
                 payer = player_name,
                 payee = recipient,
@@ -95,10 +200,13 @@ pub(super) async fn handle_pay(
                 "Payment completed"
             );
 
-            let payee_message = format!("You received {:.2} diamonds from {}", amount, player_name);
-            let _ = utils::send_message_to_player(store, recipient, &payee_message).await;
+            let payee_message = format!(
+                "You received {:.2} diamonds from {}{}",
+                amount, player_name, note
+            );
+            utils::notify_player(store, &payee_uuid, recipient, payee_message).await;
 
-            let payer_message = format!("Paid {:.2} diamonds to {}", amount, recipient);
+            let payer_message = format!("Paid {:.2} diamonds to {}{}", amount, recipient, note);
             utils::send_message_to_player(store, player_name, &payer_message).await
         }
         Err(e) => {
@@ -114,6 +222,11 @@ pub(super) async fn handle_pay(
     }
 }
 
+/// ` for "<memo>"` for whispers about a transfer or request, or nothing.
+pub(super) fn memo_suffix(memo: Option<&str>) -> String {
+    memo.map_or_else(String::new, |m| format!(" for \"{}\"", m))
+}
+
 pub(super) async fn handle_items(
     store: &mut Store,
     player_name: &str,
@@ -129,6 +242,9 @@ pub(super) async fn handle_queue(
     page: usize,
 ) -> Result<(), StoreError> {
     let user_orders = store.order_queue.get_user_orders(user_uuid);
+    // Resting limit orders are not in the queue yet; list them on a second
+    // line so players can find the `L<id>` to cancel.
+    let limits_msg = limit::describe_user_limits(store, user_uuid);
 
     if user_orders.is_empty() {
         let total_queue = store.order_queue.len();
@@ -140,7 +256,11 @@ pub(super) async fn handle_queue(
         } else {
             "You have no orders queued. Queue is empty.".to_string()
         };
-        return utils::send_message_to_player(store, player_name, &msg).await;
+        utils::send_message_to_player(store, player_name, &msg).await?;
+        if let Some(limits) = limits_msg {
+            utils::send_message_to_player(store, player_name, &limits).await?;
+        }
+        return Ok(());
     }
 
     const ORDERS_PER_PAGE: usize = 4;
@@ -186,7 +306,11 @@ pub(super) async fn handle_queue(
             orders_str.join(", ")
         )
     };
-    utils::send_message_to_player(store, player_name, &msg).await
+    utils::send_message_to_player(store, player_name, &msg).await?;
+    if let Some(limits) = limits_msg {
+        utils::send_message_to_player(store, player_name, &limits).await?;
+    }
+    Ok(())
 }
 
 pub(super) async fn handle_cancel(
@@ -257,6 +381,19 @@ async fn handle_price_command(
 
     let qty = quantity.unwrap_or(pair.stack_size as u32);
     let qty_i32 = qty as i32;
+    // Only pairs priced differently from the rest of the shop mention their
+    // curve or fees.
+    let mut pricing_note = String::new();
+    if !pair.curve.is_constant_product() {
+        pricing_note.push_str(&format!(" Pricing: {}.", pair.curve.label()));
+    }
+    if pair.has_fee_override() {
+        pricing_note.push_str(&format!(
+            " Fees: buy {:.1}%, sell {:.1}%.",
+            pair.effective_buy_fee(store.config.fee) * 100.0,
+            pair.effective_sell_fee(store.config.fee) * 100.0
+        ));
+    }
 
     let buy_total = pricing::calculate_buy_cost(store, item, qty_i32);
     let sell_total = pricing::calculate_sell_payout(store, item, qty_i32);
@@ -271,8 +408,8 @@ async fn handle_price_command(
             let sell_per = sell_payout / (qty as f64);
             let pair = store.pairs.get(item.as_str()).expect("pair existed above");
             let message = format!(
-                "{} x{}: Buy for {:.2} diamonds ({:.4}/ea), Sell for {:.2} diamonds ({:.4}/ea). Stock: {}",
-                item, qty, buy_cost, buy_per, sell_payout, sell_per, pair.item_stock
+                "{} x{}: Buy for {:.2} diamonds ({:.4}/ea), Sell for {:.2} diamonds ({:.4}/ea). Stock: {}{}",
+                item, qty, buy_cost, buy_per, sell_payout, sell_per, pair.item_stock, pricing_note
             );
             utils::send_message_to_player(store, player_name, &message).await
         }
@@ -280,8 +417,8 @@ async fn handle_price_command(
             let sell_per = sell_payout / (qty as f64);
             let pair = store.pairs.get(item.as_str()).expect("pair existed above");
             let message = format!(
-                "{} x{}: Buy unavailable (exceeds stock {}), Sell for {:.2} diamonds ({:.4}/ea)",
-                item, qty, pair.item_stock, sell_payout, sell_per
+                "{} x{}: Buy unavailable (exceeds stock {}), Sell for {:.2} diamonds ({:.4}/ea){}",
+                item, qty, pair.item_stock, sell_payout, sell_per, pricing_note
             );
             utils::send_message_to_player(store, player_name, &message).await
         }
@@ -382,6 +519,175 @@ async fn handle_items_command(
     utils::send_message_to_player(store, player_name, &message).await
 }
 
+/// One entry of a player's own history: a trade recorded under their UUID,
+/// or a transfer paid to them or an escrow sold through them.
+#[derive(Debug)]
+struct OwnEntry<'a> {
+    trade: &'a Trade,
+    /// On a `Transfer` or escrow trade: whether the trade is recorded under
+    /// the caller (payer, buyer or escrow seller), and the other player's
+    /// name (their UUID if they have no record).
+    transfer: Option<(bool, String)>,
+}
+
+impl OwnEntry<'_> {
+    /// What the entry did to the player's balance, when that is known.
+    fn balance_change(&self) -> Option<f64> {
+        let t = self.trade;
+        match t.trade_type {
+            TradeType::Buy | TradeType::Sell => t.balance_change,
+            TradeType::DepositBalance | TradeType::RemoveLiquidity => Some(t.amount_currency),
+            TradeType::WithdrawBalance | TradeType::AddLiquidity => Some(-t.amount_currency),
+            TradeType::Transfer => match self.transfer {
+                Some((false, _)) => Some(t.amount_currency),
+                _ => Some(-t.amount_currency),
+            },
+            TradeType::EscrowRelease => match self.transfer {
+                Some((false, _)) => Some(t.amount_currency),
+                _ => t.balance_change,
+            },
+            TradeType::Refund => t.balance_change.or(Some(t.amount_currency)),
+            _ => None,
+        }
+    }
+
+    /// The entry as its player sees it.
+    fn description(&self) -> String {
+        let t = self.trade;
+        let (qty, item, dia) = (t.amount, &t.item, t.amount_currency);
+        match t.trade_type {
+            TradeType::Buy => format!("bought {} {} for {:.2} diamonds", qty, item, dia),
+            TradeType::Sell => format!("sold {} {} for {:.2} diamonds", qty, item, dia),
+            TradeType::DepositBalance => format!("deposited {:.2} diamonds", dia),
+            TradeType::WithdrawBalance => format!("withdrew {:.2} diamonds", dia),
+            TradeType::AddLiquidity => {
+                format!("added {} {} + {:.2} diamonds of liquidity", qty, item, dia)
+            }
+            TradeType::RemoveLiquidity => {
+                format!(
+                    "removed {} {} + {:.2} diamonds of liquidity",
+                    qty, item, dia
+                )
+            }
+            TradeType::AddStock => format!("added {} {} to stock", qty, item),
+            TradeType::RemoveStock => format!("removed {} {} from stock", qty, item),
+            TradeType::AddCurrency => format!("added {:.2} diamonds to {}", dia, item),
+            TradeType::RemoveCurrency => format!("removed {:.2} diamonds from {}", dia, item),
+            TradeType::Transfer => match &self.transfer {
+                Some((false, other)) => format!("received {:.2} diamonds from {}", dia, other),
+                Some((true, other)) => format!("paid {:.2} diamonds to {}", dia, other),
+                None => format!("paid {:.2} diamonds", dia),
+            },
+            TradeType::EscrowDeposit => {
+                format!(
+                    "escrowed {} {} for {} at {:.2} diamonds",
+                    qty,
+                    item,
+                    self.other(),
+                    dia
+                )
+            }
+            TradeType::EscrowRelease => match &self.transfer {
+                Some((false, other)) => {
+                    format!(
+                        "sold {} {} to {} by escrow for {:.2} diamonds",
+                        qty, item, other, dia
+                    )
+                }
+                _ => format!(
+                    "bought {} {} from {} by escrow for {:.2} diamonds",
+                    qty,
+                    item,
+                    self.other(),
+                    dia
+                ),
+            },
+            TradeType::EscrowReturn => {
+                format!("got back {} {} escrowed for {}", qty, item, self.other())
+            }
+            TradeType::Refund => format!(
+                "credited {:.2} diamonds after a failed {} trade",
+                t.balance_change.unwrap_or(dia),
+                item
+            ),
+        }
+    }
+
+    /// The other player's name, or `?` on a trade that does not record one.
+    fn other(&self) -> &str {
+        self.transfer
+            .as_ref()
+            .map_or("?", |(_, name)| name.as_str())
+    }
+
+    /// One short line for `history`.
+    fn summary(&self) -> String {
+        format!(
+            "{} {}",
+            self.trade.timestamp.format("%m-%d %H:%M"),
+            self.description()
+        )
+    }
+
+    /// The full details for `receipt`.
+    fn receipt(&self) -> String {
+        let t = self.trade;
+        let mut out = format!(
+            "{} UTC, {}",
+            t.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.description()
+        );
+        if matches!(t.trade_type, TradeType::Buy | TradeType::Sell) && t.amount > 0 {
+            out.push_str(&format!(
+                ", {:.4} per item",
+                t.amount_currency / f64::from(t.amount)
+            ));
+        }
+        if let Some(fee) = t.fee {
+            out.push_str(&format!(", fee {:.2}", fee));
+        }
+        if let Some(memo) = &t.memo {
+            out.push_str(&format!(", memo \"{}\"", memo));
+        }
+        if let Some(change) = self.balance_change() {
+            out.push_str(&format!(", balance {:+.2}", change));
+        }
+        out
+    }
+}
+
+/// The trades of `user_uuid` still held in memory, and the transfers paid to
+/// them and escrows they sold, newest first. Another player is named only as
+/// the counterparty of the caller's own transfer.
+fn own_history<'a>(store: &'a Store, user_uuid: &str) -> Vec<OwnEntry<'a>> {
+    let name_of = |uuid: &str| {
+        store
+            .users
+            .get(uuid)
+            .map_or_else(|| uuid.to_string(), |u| u.username.clone())
+    };
+    let mut entries: Vec<OwnEntry> = store
+        .trades
+        .iter()
+        .filter_map(|t| {
+            // Only payments and escrow sales show up for the other player.
+            let shared = matches!(t.trade_type, TradeType::Transfer | TradeType::EscrowRelease);
+            let transfer = match t.counterparty.as_deref() {
+                Some(other) if t.user_uuid == user_uuid => Some((true, name_of(other))),
+                Some(other) if shared && other == user_uuid => Some((false, name_of(&t.user_uuid))),
+                _ if t.user_uuid == user_uuid => None,
+                _ => return None,
+            };
+            Some(OwnEntry { trade: t, transfer })
+        })
+        .collect();
+    // Trades are kept oldest first; reversing before the stable sort keeps
+    // entries with the same timestamp newest first too.
+    entries.reverse();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.trade.timestamp));
+    entries
+}
+
 /// Sends usage text for a single command, or (when `command` is `None`) the
 /// full command list. Operator-only commands are included only for operators.
 async fn handle_help_command(
@@ -397,7 +703,7 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "buy <item> <quantity> - Buy items from the store. Example: buy cobblestone 64",
+                "buy <item> <quantity> [max <diamonds>] [limit <price> [ttl]] - Buy items from the store. 'max' cancels the order if the total has risen above it by the time it runs. Example: buy cobblestone 64 max 12. Over 12 stacks runs as several trades at one up-front price. 'buy <item> upto <qty>' buys as much as stock and your balance allow (paid from balance); 'buy <item> all' means up to 12 stacks. Quantities can be stacks or shulkers, e.g. buy cobblestone 3s (see 'help quantity'). See 'help limit' for limit orders and 'help basket' for several items at once.",
             )
             .await
         }
@@ -405,7 +711,7 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "sell <item> <quantity> - Sell items to the store. Example: sell iron_ingot 128",
+                "sell <item> <quantity> [min <diamonds>] [limit <price> [ttl]] - Sell items to the store. 'min' cancels the order if the payout has dropped below it by the time it runs. Example: sell iron_ingot 128 min 20. Over 12 stacks runs as several trades at one up-front price. 'sell <item> upto <qty>' sells as much as the store can take; 'sell <item> all' means up to 12 stacks; 'sell all <item>' sells however many you put in the trade (up to what the store can take) and credits the payout to your balance. Quantities can be stacks or shulkers, e.g. sell iron_ingot 2s+10 (see 'help quantity'). See 'help limit' for limit orders and 'help basket' for several items at once.",
             )
             .await
         }
@@ -429,7 +735,31 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "pay <player> <amount> - Pay diamonds to another player. Example: pay Steve 10.5",
+                "pay <player> <amount> [memo] - Pay diamonds to another player from your balance. The memo shows in both players' history. Example: pay Steve 10.5 rent",
+            )
+            .await
+        }
+        Some("request") | Some("accept") | Some("decline") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "request <player> <amount> [memo] - Ask a player to pay you; it expires after 24h. They answer with 'accept R<id>' (paid from their balance) or 'decline R<id>'; you can withdraw it with 'decline R<id>'. 'request' alone lists your open requests. Example: request Steve 5 rent",
+            )
+            .await
+        }
+        Some("alert") | Some("notify") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "alert <item> below|above <price> [ttl] - Tell me once when the price per item crosses <price>. notify <item> restock [ttl] - Tell me once when a sold-out item is back. Both last 7d unless ttl is shorter (e.g. 12h); if you are offline you get it on your next whisper or when you join. 'alert' alone lists yours, 'alert cancel A<id>' removes one. Example: alert cobblestone below 0.5",
+            )
+            .await
+        }
+        Some("escrow") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "escrow sell <player> <item> <quantity> <price> - Sell items to another player through the bot. You trade the items to the bot, which holds them until the buyer pays with 'escrow accept E<id>' (from balance and diamonds in the trade); you are paid into your balance. Either side can call it off with 'escrow cancel E<id>'; unaccepted escrows go back to you after 24h. 'escrow' alone lists yours. Example: escrow sell Steve iron_ingot 64 40",
             )
             .await
         }
@@ -437,7 +767,7 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "deposit [amount] - Deposit physical diamonds into your balance. If no amount specified, credits whatever you put in the trade (max 768 / 12 stacks). Example: deposit, deposit 64",
+                "deposit [amount|all] - Deposit physical diamonds into your balance. With no amount, or 'all', credits whatever you put in the trade (max 768 / 12 stacks). Example: deposit, deposit 64",
             )
             .await
         }
@@ -445,7 +775,7 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "withdraw [amount] - Withdraw diamonds from your balance. If no amount specified, withdraws your full balance (whole diamonds only, max 768 / 12 stacks). Example: withdraw, withdraw 32",
+                "withdraw [amount|all] - Withdraw diamonds from your balance. With no amount, or 'all', withdraws your full balance (whole diamonds only, max 768 / 12 stacks). Example: withdraw all, withdraw 32",
             )
             .await
         }
@@ -461,7 +791,79 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "queue [page] (or q) - Show your pending orders (4 per page). Example: queue, queue 2",
+                "queue [page] (or q) - Show your pending orders (4 per page) and resting limit orders. Example: queue, queue 2",
+            )
+            .await
+        }
+        Some("limit") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "buy|sell <item> <quantity> limit <price> [ttl] - Rest an order until the average price per item is at or below (buy) / at or above (sell) <price>, then queue it. TTL like 30m, 12h, 7d (default 24h, max 7d). You must be online to accept the /trade. Example: buy cobblestone 64 limit 0.5 12h",
+            )
+            .await
+        }
+        Some("stats") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "stats <item> - Trading history for an item: trade count, volume bought/sold, and fees collected. Example: stats cobblestone",
+            )
+            .await
+        }
+        Some("history") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "history <item> [24h|7d] - Price movement for an item: open, high, low and close spot price, change, and volume. Defaults to 24h. Example: history cobblestone 7d. 'history [page]' lists your own trades, deposits, withdrawals and payments, newest first (4 per page); see 'help receipt'.",
+            )
+            .await
+        }
+        Some("receipt") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "receipt <n> - Details of entry <n> from your 'history' (1 is the newest): time, amounts, price, fee and the change to your balance. Example: receipt 1",
+            )
+            .await
+        }
+        Some("quantity") | Some("stacks") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "Item quantities can be counted in stacks (3s, 3 stacks) or shulker boxes (1sh, 1 shulker = 27 stacks), and added or subtracted: 2s+10, 1sh-1. A stack is the item's own stack size, so 1s of ender pearls is 16. Works in buy, sell, baskets, swap, quote, price, lp add and escrow sell. Example: buy cobblestone 2 stacks",
+            )
+            .await
+        }
+        Some("basket") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "buy|sell <item> <qty>, <item> <qty>, ... - Trade several items in one trade (up to 12 stacks in total). Baskets settle through your balance: a buy is paid from it and a sell is credited to it. Example: buy cobblestone 64, glass 32, torch 16",
+            )
+            .await
+        }
+        Some("swap") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "swap <from_item> <quantity> <to_item> - Trade one item for another in a single trade. Your items are sold and the diamonds buy as many of the other item as they cover; any leftover goes to your balance. Example: swap iron_ingot 64 gold_ingot",
+            )
+            .await
+        }
+        Some("lp") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "lp add <item> <quantity> | lp remove <item> <shares|all> | lp - Provide liquidity to a pair and earn a share of its fees. 'add' takes the items in a trade and the matching diamonds from your balance; 'remove' returns your share of items in a trade and diamonds to your balance; 'lp' lists your positions. Example: lp add cobblestone 640",
+            )
+            .await
+        }
+        Some("quote") | Some("confirm") => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                "quote buy|sell <item> <quantity> - Get a price valid for 30s, then 'confirm Q<id>' to queue the order at exactly that price. Rejected if the price has since moved more than 1%. Example: quote buy cobblestone 64, confirm Q3",
             )
             .await
         }
@@ -469,7 +871,7 @@ async fn handle_help_command(
             utils::send_message_to_player(
                 store,
                 player_name,
-                "cancel <order_id> (or c) - Cancel a pending order. Use 'queue' to see your order IDs. Example: c 5",
+                "cancel <order_id> (or c) - Cancel a pending order, or a limit order with L<id>. Use 'queue' to see your order IDs. Example: c 5, c L2",
             )
             .await
         }
@@ -520,7 +922,7 @@ async fn handle_help_command(
         )
         .await,
         None => {
-            let base_commands = "Commands: buy (b), sell (s), price (p), items, balance (bal), pay, deposit (d), withdraw (w), queue (q), cancel (c), status, help (h). Use 'help <command>' for details.";
+            let base_commands = "Commands: buy (b), sell (s), swap, price (p), stats, history, receipt, quote, confirm, lp, items, balance (bal), pay, request, escrow, alert, notify, deposit (d), withdraw (w), queue (q), cancel (c), status, help (h). Use 'help <command>' for details.";
             if is_op {
                 utils::send_message_to_player(
                     store,
@@ -575,14 +977,15 @@ async fn get_user_balance_async(store: &mut Store, username: &str) -> Result<f64
 /// dispatcher-provided `payer_uuid` (the server-reported whisper sender), but
 /// an existing payee record's canonical username is preserved — the payer's
 /// typed casing must not overwrite a third-party's stored display name.
+/// Returns the payee's UUID.
 pub async fn pay_async(
     store: &mut Store,
     payer_username: &str,
     payer_uuid: &str,
     payee_username: &str,
     amount: f64,
-) -> Result<(), StoreError> {
-    state::assert_invariants(store, "pre-pay", false)?;
+    memo: Option<String>,
+) -> Result<String, StoreError> {
     if !amount.is_finite() || amount <= 0.0 {
         warn!(
             payer = payer_username,
@@ -628,11 +1031,27 @@ pub async fn pay_async(
         utils::ensure_user_exists(store, payee_username, &payee_uuid);
     }
 
+    transfer(store, payer_username, payer_uuid, &payee_uuid, amount, memo)?;
+    Ok(payee_uuid)
+}
+
+/// Move `amount` from the payer's balance to the payee's, both already
+/// resolved and on record, and log it as a `Transfer` trade. Shared by `pay`
+/// and accepted payment requests.
+pub(super) fn transfer(
+    store: &mut Store,
+    payer_username: &str,
+    payer_uuid: &str,
+    payee_uuid: &str,
+    amount: f64,
+    memo: Option<String>,
+) -> Result<(), StoreError> {
+    state::assert_invariants(store, "pre-pay", false)?;
     let payer_balance = store.expect_user(payer_uuid, "pay/payer-balance")?.balance;
     if payer_balance < amount {
         warn!(
             payer = payer_username,
-            payee = payee_username,
+            payee = payee_uuid,
             balance = payer_balance,
             amount,
             "Rejected payment: insufficient payer balance"
@@ -649,12 +1068,24 @@ pub async fn pay_async(
         payer.username = payer_username.to_owned();
     }
     {
-        let payee = store.expect_user_mut(&payee_uuid, "pay/payee-credit")?;
+        let payee = store.expect_user_mut(payee_uuid, "pay/payee-credit")?;
         payee.balance += amount;
     }
+    store.post_ledger(
+        Account::Player(payer_uuid.to_string()),
+        Account::Player(payee_uuid.to_string()),
+        amount,
+        PAY_MEMO,
+    );
+    store.trades.push(Trade::transfer(
+        payer_uuid.to_string(),
+        payee_uuid.to_string(),
+        amount,
+        memo,
+    ));
     store.dirty = true;
     store.dirty_users.insert(payer_uuid.to_string());
-    store.dirty_users.insert(payee_uuid.clone());
+    store.dirty_users.insert(payee_uuid.to_string());
 
     state::assert_invariants(store, "post-pay", true)?;
     Ok(())
@@ -670,6 +1101,7 @@ mod tests {
     use super::*;
     use crate::config::Config;
     use crate::types::Storage;
+    use chrono::Utc;
     use std::collections::HashMap;
     use tokio::sync::mpsc;
 
@@ -695,7 +1127,7 @@ mod tests {
     #[tokio::test]
     async fn pay_async_rejects_zero_amount() {
         let mut store = empty_store();
-        let err = pay_async(&mut store, "Alice", &test_uuid("Alice"), "Bob", 0.0)
+        let err = pay_async(&mut store, "Alice", &test_uuid("Alice"), "Bob", 0.0, None)
             .await
             .unwrap_err();
         assert!(
@@ -707,7 +1139,7 @@ mod tests {
     #[tokio::test]
     async fn pay_async_rejects_negative_amount() {
         let mut store = empty_store();
-        let err = pay_async(&mut store, "Alice", &test_uuid("Alice"), "Bob", -1.0)
+        let err = pay_async(&mut store, "Alice", &test_uuid("Alice"), "Bob", -1.0, None)
             .await
             .unwrap_err();
         assert!(
@@ -719,9 +1151,16 @@ mod tests {
     #[tokio::test]
     async fn pay_async_rejects_nan_amount() {
         let mut store = empty_store();
-        let err = pay_async(&mut store, "Alice", &test_uuid("Alice"), "Bob", f64::NAN)
-            .await
-            .unwrap_err();
+        let err = pay_async(
+            &mut store,
+            "Alice",
+            &test_uuid("Alice"),
+            "Bob",
+            f64::NAN,
+            None,
+        )
+        .await
+        .unwrap_err();
         assert!(
             matches!(err, StoreError::ValidationError(_)),
             "expected ValidationError for NaN, got {err:?}"
@@ -737,6 +1176,7 @@ mod tests {
             &test_uuid("Alice"),
             "Bob",
             f64::INFINITY,
+            None,
         )
         .await
         .unwrap_err();
@@ -751,7 +1191,7 @@ mod tests {
         // No users in the store: the payer-not-found branch fires after the
         // amount guard passes.
         let mut store = empty_store();
-        let err = pay_async(&mut store, "Ghost", &test_uuid("Ghost"), "Bob", 5.0)
+        let err = pay_async(&mut store, "Ghost", &test_uuid("Ghost"), "Bob", 5.0, None)
             .await
             .unwrap_err();
         assert!(
@@ -759,4 +1199,95 @@ mod tests {
             "expected ValidationError(not found), got {err:?}"
         );
     }
+
+    fn add_user(store: &mut Store, name: &str) -> String {
+        let uuid = test_uuid(name);
+        store.users.insert(
+            uuid.clone(),
+            crate::types::User {
+                uuid: uuid.clone(),
+                username: name.to_string(),
+                balance: 0.0,
+                operator: false,
+                lp_shares: HashMap::new(),
+            },
+        );
+        uuid
+    }
+
+    fn trade_at(kind: TradeType, uuid: &str, diamonds: f64, hours_ago: i64) -> Trade {
+        let mut t = Trade::new(
+            kind,
+            ItemId::new("cobblestone").unwrap(),
+            64,
+            diamonds,
+            uuid.to_string(),
+        );
+        t.timestamp = Utc::now() - chrono::Duration::hours(hours_ago);
+        t
+    }
+
+    #[test]
+    fn own_history_holds_only_the_callers_entries_newest_first() {
+        let mut store = empty_store();
+        let alice = add_user(&mut store, "Alice");
+        let bob = add_user(&mut store, "Bob");
+        let carol = add_user(&mut store, "Carol");
+        store
+            .trades
+            .push(trade_at(TradeType::DepositBalance, &alice, 20.0, 3));
+        store.trades.push(trade_at(TradeType::Buy, &bob, 9.0, 2));
+        store.trades.push(trade_at(TradeType::Buy, &alice, 4.0, 1));
+        store
+            .trades
+            .push(Trade::transfer(bob.clone(), carol.clone(), 2.0, None));
+        store.trades.push(Trade::transfer(
+            alice.clone(),
+            bob.clone(),
+            5.0,
+            Some("rent".into()),
+        ));
+
+        let lines: Vec<String> = own_history(&store, &alice)
+            .iter()
+            .map(|e| e.description())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "paid 5.00 diamonds to Bob",
+                "bought 64 cobblestone for 4.00 diamonds",
+                "deposited 20.00 diamonds",
+            ]
+        );
+        let bobs = own_history(&store, &bob);
+        assert_eq!(bobs.len(), 3);
+        assert_eq!(bobs[0].description(), "received 5.00 diamonds from Alice");
+        assert!(bobs[0].receipt().contains("rent"), "{}", bobs[0].receipt());
+        assert!(own_history(&store, &test_uuid("Dave")).is_empty());
+    }
+
+    #[test]
+    fn receipt_shows_price_fee_and_balance_change() {
+        let mut store = empty_store();
+        let alice = add_user(&mut store, "Alice");
+        store.trades.push(
+            trade_at(TradeType::Sell, &alice, 12.8, 1)
+                .with_fee(0.4)
+                .with_balance_change(0.8),
+        );
+        store
+            .trades
+            .push(trade_at(TradeType::WithdrawBalance, &alice, 10.0, 0));
+
+        let entries = own_history(&store, &alice);
+        assert!(
+            entries[0]
+                .receipt()
+                .ends_with("withdrew 10.00 diamonds, balance -10.00")
+        );
+        assert!(entries[1].receipt().ends_with(
+            "sold 64 cobblestone for 12.80 diamonds, 0.2000 per item, fee 0.40, balance +0.80"
+        ));
+    }
 }
@@ -0,0 +1,268 @@
+//! Limit orders: `buy|sell <item> <qty> limit <price> [ttl]` and
+//! `cancel L<id>`, plus the periodic [`sweep`] that promotes crossed orders
+//! into the order queue and drops expired ones.
+//!
+//! Placement only records the order in `store.order_book`; no chest or trade
+//! I/O happens until the sweep moves it into the queue, where it executes as
+//! an ordinary buy/sell through `orders::execute_queued_order` with the limit
+//! carried as its `price_bound`.
+
+use chrono::{Duration, Utc};
+use tracing::{debug, error, info, warn};
+
+use super::super::order_book::{LimitOrder, OrderSide};
+use super::super::{Store, pricing, utils};
+use crate::error::StoreError;
+use crate::messages::QueuedOrderType;
+use crate::types::ItemId;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn handle_place(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    side: OrderSide,
+    item: &ItemId,
+    quantity: u32,
+    limit_price: f64,
+    ttl_secs: u64,
+) -> Result<(), StoreError> {
+    if !store.pairs.contains_key(item.as_str()) {
+        debug!(
+            user = player_name,
+            uuid = user_uuid,
+            item = %item,
+            "Limit order rejected: item not in pairs"
+        );
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    }
+
+    let ttl = Duration::seconds(i64::try_from(ttl_secs).unwrap_or(i64::MAX));
+    match store.order_book.place(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        side,
+        item.as_str().to_string(),
+        quantity,
+        limit_price,
+        ttl,
+    ) {
+        Ok(order) => {
+            let msg = format!(
+                "Limit order L{} placed: {}. Expires in {}. When it triggers it joins the queue, so be online to accept the /trade. Cancel with 'cancel L{}'.",
+                order.id,
+                order.description(),
+                format_ttl(ttl_secs),
+                order.id
+            );
+            utils::send_message_to_player(store, player_name, &msg).await?;
+            // A limit that is already crossed should not wait for the next
+            // scheduled sweep.
+            sweep(store).await;
+            Ok(())
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+pub(super) async fn handle_cancel(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    limit_id: u64,
+) -> Result<(), StoreError> {
+    match store.order_book.cancel(user_uuid, limit_id) {
+        Ok(order) => {
+            info!(
+                "[OrderBook] Limit order L{} cancelled by {} ({})",
+                order.id, player_name, user_uuid
+            );
+            let msg = format!("Limit order L{} cancelled.", limit_id);
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+/// Whisper line listing the player's resting limit orders, or `None` when
+/// they have none. Appended to the `queue` reply.
+pub(super) fn describe_user_limits(store: &Store, user_uuid: &str) -> Option<String> {
+    let orders = store.order_book.get_user_orders(user_uuid);
+    if orders.is_empty() {
+        return None;
+    }
+    let now = Utc::now();
+    let parts: Vec<String> = orders
+        .iter()
+        .map(|o| {
+            let left = (o.expires_at - now).num_seconds().max(0) as u64;
+            format!("L{} {} ({} left)", o.id, o.description(), format_ttl(left))
+        })
+        .collect();
+    Some(format!(
+        "Your limit orders ({}): {}",
+        orders.len(),
+        parts.join(", ")
+    ))
+}
+
+/// Expire stale limit orders and promote crossed ones into the order queue.
+///
+/// Called from the run loop on a timer while the book is non-empty, after
+/// every processed order (the only time reserves move), and right after a
+/// new limit is placed. Never fails: persistence or whisper errors are
+/// logged and the affected order is retried on the next sweep.
+pub(crate) async fn sweep(store: &mut Store) {
+    let now = Utc::now();
+
+    for id in store.order_book.expired_ids(now) {
+        match store.order_book.remove(id) {
+            Ok(order) => {
+                info!(
+                    "[OrderBook] Limit order L{} expired (user={} {})",
+                    order.id,
+                    order.username,
+                    order.description()
+                );
+                let msg = format!(
+                    "Limit order L{} ({}) expired without filling.",
+                    order.id,
+                    order.description()
+                );
+                notify(store, &order, &msg).await;
+            }
+            Err(e) => warn!("[OrderBook] could not expire limit order L{}: {}", id, e),
+        }
+    }
+
+    let global_fee = store.config.fee;
+    let triggered = store.order_book.triggered(now, |o| {
+        let pair = store.pairs.get(&o.item)?;
+        let qty = i32::try_from(o.quantity).ok()?;
+        let total = match o.side {
+            OrderSide::Buy => pricing::pair_buy_cost(pair, qty, global_fee)?,
+            OrderSide::Sell => pricing::pair_sell_payout(pair, qty, global_fee)?,
+        };
+        Some(total / f64::from(o.quantity))
+    });
+
+    for (id, price) in triggered {
+        promote(store, id, price).await;
+    }
+}
+
+/// Move one crossed limit order from the book into the queue.
+///
+/// The book removal is persisted first: if the process dies between the two
+/// writes the order is lost rather than executed twice. When the queue
+/// rejects it (full, per-user cap) the order is restored to the book and
+/// retried on a later sweep.
+async fn promote(store: &mut Store, id: u64, price: f64) {
+    let order = match store.order_book.remove(id) {
+        Ok(o) => o,
+        Err(e) => {
+            warn!(
+                "[OrderBook] could not take triggered limit order L{}: {}",
+                id, e
+            );
+            return;
+        }
+    };
+
+    let order_type = match order.side {
+        OrderSide::Buy => QueuedOrderType::Buy,
+        OrderSide::Sell => QueuedOrderType::Sell,
+    };
+    // Carry the limit as the queued order's slippage bound so orders
+    // executed ahead of it cannot make it fill worse than the limit.
+    let price_bound = order.limit_price * f64::from(order.quantity);
+    match store.order_queue.add_bounded(
+        order.user_uuid.clone(),
+        order.username.clone(),
+        order_type,
+        order.item.clone(),
+        order.quantity,
+        Some(price_bound),
+    ) {
+        Ok((order_id, position)) => {
+            info!(
+                "[OrderBook] Limit order L{} triggered at {:.4}/ea -> queued as #{} (user={})",
+                order.id, price, order_id, order.username
+            );
+            let msg = format!(
+                "Limit order L{} triggered ({:.4}/ea): queued as order #{} (position {}/{}).",
+                order.id,
+                price,
+                order_id,
+                position,
+                store.order_queue.len()
+            );
+            notify(store, &order, &msg).await;
+        }
+        Err(reason) => {
+            debug!(
+                "[OrderBook] Limit order L{} triggered but not queued: {}",
+                order.id, reason
+            );
+            if let Err(e) = store.order_book.restore(order.clone()) {
+                error!(
+                    "[OrderBook] LIMIT ORDER LOST: L{} ({} for {}) could not be queued ({}) or restored ({})",
+                    order.id,
+                    order.description(),
+                    order.username,
+                    reason,
+                    e
+                );
+                let msg = format!(
+                    "Limit order L{} triggered but could not be queued and was dropped: {}",
+                    order.id, reason
+                );
+                notify(store, &order, &msg).await;
+            }
+        }
+    }
+}
+
+async fn notify(store: &Store, order: &LimitOrder, msg: &str) {
+    if let Err(e) = utils::send_message_to_player(store, &order.username, msg).await {
+        warn!(
+            limit_id = order.id,
+            player = %order.username,
+            error = %e,
+            "failed to notify user about limit order"
+        );
+    }
+}
+
+/// Render a duration in seconds as the coarsest whole unit (`3d`, `12h`, `45m`).
+pub(super) fn format_ttl(secs: u64) -> String {
+    const DAY: u64 = 24 * 60 * 60;
+    const HOUR: u64 = 60 * 60;
+    if secs >= DAY && secs.is_multiple_of(DAY) {
+        format!("{}d", secs / DAY)
+    } else if secs >= HOUR {
+        format!("{}h", secs / HOUR)
+    } else {
+        format!("{}m", (secs / 60).max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_ttl_uses_coarsest_whole_unit() {
+        assert_eq!(format_ttl(24 * 60 * 60), "1d");
+        assert_eq!(format_ttl(7 * 24 * 60 * 60), "7d");
+        assert_eq!(format_ttl(36 * 60 * 60), "36h");
+        assert_eq!(format_ttl(90 * 60), "1h");
+        assert_eq!(format_ttl(30 * 60), "30m");
+        assert_eq!(format_ttl(5), "1m");
+    }
+}
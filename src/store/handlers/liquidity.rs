@@ -0,0 +1,623 @@
+//! `lp` command: liquidity-provider deposits, redemptions and positions.
+//!
+//! `lp add <item> <qty>` and `lp remove <item> <shares|all>` are queued like
+//! buy/sell because the items move through a `/trade`; `lp` alone lists
+//! positions inline. Diamonds never cross the trade window: an add debits
+//! the matching diamonds from the balance and a removal credits them back,
+//! so players fund and cash out with the ordinary `deposit` / `withdraw`
+//! commands. Share math lives in `types::liquidity`.
+
+use tracing::{debug, error, info, warn};
+
+use super::super::ledger::Account;
+use super::super::orders::{ChestDirection, execute_chest_transfers, perform_trade};
+use super::super::{Store, rollback, state, utils};
+use crate::constants::TRADE_OFFER_SLOTS_PER_SIDE;
+use crate::error::StoreError;
+use crate::messages::{QueuedOrderType, TradeItem};
+use crate::types::liquidity::{self, SHARE_EPSILON};
+use crate::types::{ItemId, Trade, TradeType};
+
+/// Shares `user_uuid` holds in `item`'s pool (zero when none).
+fn held_shares(store: &Store, user_uuid: &str, item: &str) -> f64 {
+    store
+        .users
+        .get(user_uuid)
+        .and_then(|u| u.lp_shares.get(item))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+pub(super) async fn handle_add_enqueue(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+    quantity: u32,
+) -> Result<(), StoreError> {
+    let Some(pair) = store.pairs.get(item.as_str()) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let qty = i32::try_from(quantity).unwrap_or(i32::MAX);
+    let Some(terms) = liquidity::deposit_terms(pair, qty) else {
+        let msg = format!(
+            "Cannot add liquidity to {}: the pool has no price to match (a reserve is empty).",
+            item
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    };
+    // Cheap pre-check so an unfunded request never takes a queue slot; the
+    // queued handler re-checks against the ratio at execution time.
+    let balance = store.users.get(user_uuid).map_or(0.0, |u| u.balance);
+    if balance < terms.diamonds {
+        let msg = format!(
+            "Adding {} {} needs {:.2} diamonds from your balance at the current ratio; you have {:.2}. Use 'deposit' first.",
+            quantity, item, terms.diamonds, balance
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    debug!(
+        user = player_name,
+        uuid = user_uuid,
+        item = %item,
+        quantity = quantity,
+        "Queueing LP add order"
+    );
+    match store.order_queue.add(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::AddLiquidity,
+        item.as_str().to_string(),
+        quantity,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "LP add order #{} queued (position {}/{}). Est. wait: {}. At the current ratio it takes {:.2} diamonds from your balance.",
+                order_id, position, queue_len, wait_estimate, terms.diamonds
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+pub(super) async fn handle_remove_enqueue(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+    shares: Option<f64>,
+) -> Result<(), StoreError> {
+    let held = held_shares(store, user_uuid, item.as_str());
+    if held <= SHARE_EPSILON {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("You have no liquidity in {}.", item),
+        )
+        .await;
+    }
+    if let Some(s) = shares
+        && s > held + SHARE_EPSILON
+    {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("You only hold {:.4} {} shares.", held, item),
+        )
+        .await;
+    }
+
+    debug!(
+        user = player_name,
+        uuid = user_uuid,
+        item = %item,
+        shares = ?shares,
+        "Queueing LP remove order"
+    );
+    match store.order_queue.add(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::RemoveLiquidity { shares },
+        item.as_str().to_string(),
+        0,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "LP remove order #{} queued (position {}/{}). Est. wait: {}.",
+                order_id, position, queue_len, wait_estimate
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+/// Whisper every position the caller holds with its current value.
+pub(super) async fn handle_positions(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+) -> Result<(), StoreError> {
+    let mut lines: Vec<String> = store
+        .users
+        .get(user_uuid)
+        .map(|u| {
+            u.lp_shares
+                .iter()
+                .filter(|(_, s)| **s > SHARE_EPSILON)
+                .filter_map(|(item, s)| {
+                    store
+                        .pairs
+                        .get(item)
+                        .map(|p| liquidity::position_summary(p, *s))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if lines.is_empty() {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            "You have no liquidity positions. Use 'lp add <item> <quantity>' to provide liquidity.",
+        )
+        .await;
+    }
+    lines.sort();
+    let msg = format!("Liquidity: {}", lines.join("; "));
+    utils::send_message_to_player(store, player_name, &msg).await
+}
+
+/// Take `quantity` items via trade, debit the matching diamonds from the
+/// balance and mint shares. Called by the order queue processor.
+pub async fn handle_add_liquidity_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    quantity: u32,
+) -> Result<(), StoreError> {
+    info!(phase = "lp_add.start", player = %player_name, item = %item, qty = quantity, "LP add starting");
+    state::assert_tradeable(store, item, user_uuid, "pre-lp-add")?;
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    let Some(pair) = store.pairs.get(item) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let qty_i32: i32 = quantity
+        .try_into()
+        .map_err(|_| StoreError::ValidationError("Quantity too large".to_string()))?;
+    let stack_size = pair.stack_size;
+    let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE * stack_size;
+    if qty_i32 > max_per_trade {
+        let msg = format!(
+            "Cannot add {} {} in one trade - the trade window holds at most {}. Please add {} or fewer at a time.",
+            qty_i32, item, max_per_trade, max_per_trade
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+    let Some(terms) = liquidity::deposit_terms(pair, qty_i32) else {
+        let msg = format!(
+            "Cannot add liquidity to {}: the pool has no price to match (a reserve is empty).",
+            item
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    };
+    let balance = store.users.get(&user_uuid).map_or(0.0, |u| u.balance);
+    if balance < terms.diamonds {
+        let msg = format!(
+            "LP add cancelled: {} {} now needs {:.2} diamonds from your balance; you have {:.2}.",
+            qty_i32, item, terms.diamonds, balance
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+    let (deposit_plan, planned_deposited) = store
+        .storage
+        .simulate_deposit_plan(item, qty_i32, stack_size);
+    if planned_deposited < qty_i32 {
+        let msg = format!(
+            "Storage space validation failed for '{}': can only store {} items, but {} requested. Please contact an operator to add more storage nodes.",
+            item, planned_deposited, qty_i32
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Nothing to withdraw: the empty plan only satisfies the state machine's
+    // Queued -> Withdrawing -> Trading progression (same as deposit).
+    store.advance_trade(|s| s.begin_withdrawal(vec![]));
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "LP add {} {}: Please offer {} {} in the trade. {:.2} diamonds will be taken from your balance.",
+            qty_i32, item, qty_i32, item, terms.diamonds
+        ),
+    )
+    .await?;
+    store.advance_trade(|s| s.begin_trading());
+
+    let actual_received = match perform_trade(
+        store,
+        player_name,
+        vec![],
+        vec![TradeItem {
+            item: item.to_string(),
+            amount: qty_i32,
+        }],
+        true, // require EXACT amount, as for sells
+        false,
+        "[LpAdd]",
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(phase = "lp_add.rollback", player = %player_name, item = %item, "LP add trade failed: {}", err);
+            store.advance_trade(|s| s.rollback("lp-add/trade-failed".to_string()));
+            return utils::whisper_action_aborted(
+                store,
+                player_name,
+                "LP add",
+                &err.user_message(),
+                None,
+            )
+            .await;
+        }
+    };
+
+    let target_item_id = crate::bot::Bot::normalize_item_id(item);
+    let items_received: i32 = actual_received
+        .iter()
+        .filter(|t| crate::bot::Bot::normalize_item_id(&t.item) == target_item_id)
+        .map(|t| t.amount)
+        .sum();
+    if items_received != qty_i32 {
+        warn!(
+            phase = "lp_add.validation",
+            player = %player_name,
+            item = %item,
+            expected = qty_i32,
+            received = items_received,
+            "LP add validation failed: item count mismatch"
+        );
+        if items_received > 0 {
+            let _ = perform_trade(
+                store,
+                player_name,
+                vec![TradeItem {
+                    item: item.to_string(),
+                    amount: items_received,
+                }],
+                vec![],
+                false,
+                false,
+                "[LpAdd] return-items",
+            )
+            .await;
+        }
+        store.advance_trade(|s| s.rollback("lp-add/item-count-mismatch".to_string()));
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "LP add REJECTED: You put {} {} in the trade but promised {}. Trade cancelled, items returned.",
+                items_received, item, qty_i32
+            ),
+        )
+        .await;
+    }
+
+    store.advance_trade(|s| {
+        s.begin_depositing(
+            super::super::trade_state::TradeResult {
+                items_received: actual_received.clone(),
+            },
+            deposit_plan.clone(),
+        )
+    });
+    if let Err(err) = execute_chest_transfers(
+        store,
+        &deposit_plan,
+        item,
+        stack_size,
+        ChestDirection::Deposit,
+        "[LpAdd]",
+    )
+    .await
+    {
+        // Same best-effort return as a failed sell deposit. No ledger has
+        // moved yet, so there is nothing else to unwind.
+        let returned = perform_trade(
+            store,
+            player_name,
+            vec![TradeItem {
+                item: item.to_string(),
+                amount: qty_i32,
+            }],
+            vec![],
+            false,
+            false,
+            "[LpAdd] deposit-failed",
+        )
+        .await;
+        store.advance_trade(|s| s.rollback("lp-add/deposit-failed".to_string()));
+        let msg = match returned {
+            Ok(_) => format!(
+                "LP add aborted: failed to deposit items into storage: {}. Items returned via trade.",
+                err.user_message()
+            ),
+            Err(rerr) => format!(
+                "LP add aborted: failed to deposit items into storage: {}. Return-trade also failed ({}). Contact an operator.",
+                err.user_message(),
+                rerr.user_message()
+            ),
+        };
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Commit ledgers. `terms` was priced against the reserves this task
+    // still holds exclusively, so it is current.
+    let new_balance = {
+        let user = store.expect_user_mut(&user_uuid, "lp-add/commit-user")?;
+        user.balance -= terms.diamonds;
+        *user.lp_shares.entry(item.to_string()).or_insert(0.0) += terms.shares;
+        user.username = player_name.to_owned();
+        user.balance
+    };
+    store.dirty_users.insert(user_uuid.clone());
+
+    let new_item_stock = store.storage.total_item_amount(item);
+    let pair = store.expect_pair_mut(item, "lp-add/commit-pair")?;
+    pair.lp_total_shares = liquidity::share_supply(pair) + terms.shares;
+    pair.item_stock = new_item_stock;
+    pair.currency_stock += terms.diamonds;
+    let pool_fraction = terms.shares / pair.lp_total_shares;
+    store.dirty = true;
+    store.post_ledger(
+        Account::Player(user_uuid.clone()),
+        Account::Reserve(item.to_string()),
+        terms.diamonds,
+        "lp add",
+    );
+
+    store.trades.push(Trade::new(
+        TradeType::AddLiquidity,
+        ItemId::from_normalized(item.to_string()),
+        qty_i32,
+        terms.diamonds,
+        user_uuid.clone(),
+    ));
+    store.advance_trade(|s| s.commit(item.to_string(), qty_i32, terms.diamonds));
+
+    info!(
+        phase = "lp_add.done",
+        player = %player_name,
+        item = %item,
+        qty = qty_i32,
+        diamonds = format_args!("{:.2}", terms.diamonds),
+        shares = format_args!("{:.4}", terms.shares),
+        "LP add completed"
+    );
+    if let Err(e) = state::assert_invariants(store, "post-lp-add", true) {
+        error!(phase = "lp_add.invariant", player = %player_name, item = %item, error = %e, "Invariant violation after LP add");
+        let _ = state::save(store);
+    }
+
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Added {} {} and {:.2} diamonds to the {} pool for {:.4} shares ({:.2}% of pool). New balance: {:.2}",
+            qty_i32,
+            item,
+            terms.diamonds,
+            item,
+            terms.shares,
+            pool_fraction * 100.0,
+            new_balance
+        ),
+    )
+    .await
+}
+
+/// Burn shares, hand over the item portion via trade and credit the diamond
+/// portion to the balance. `shares = None` burns the whole position. Called
+/// by the order queue processor.
+pub async fn handle_remove_liquidity_queued(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    shares: Option<f64>,
+) -> Result<(), StoreError> {
+    info!(phase = "lp_remove.start", player = %player_name, item = %item, shares = ?shares, "LP remove starting");
+    state::assert_tradeable(store, item, user_uuid, "pre-lp-remove")?;
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    let held = held_shares(store, &user_uuid, item);
+    let shares = match shares {
+        _ if held <= SHARE_EPSILON => {
+            let msg = format!("You have no liquidity in {}.", item);
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        None => held,
+        Some(s) if s <= held + SHARE_EPSILON => s.min(held),
+        Some(_) => {
+            let msg = format!("You only hold {:.4} {} shares.", held, item);
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+    };
+    let Some(pair) = store.pairs.get(item) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let stack_size = pair.stack_size;
+    let Some(terms) = liquidity::redemption_terms(pair, shares) else {
+        let msg = format!("Cannot redeem {:.4} {} shares right now.", shares, item);
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    };
+    let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE * stack_size;
+    if terms.items > max_per_trade {
+        let msg = format!(
+            "Redeeming {:.4} shares pays out {} {}, more than one trade window holds ({}). Remove fewer shares at a time.",
+            shares, terms.items, item, max_per_trade
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+    let (withdraw_plan, planned_total) = store.storage.simulate_withdraw_plan(item, terms.items);
+    if planned_total < terms.items {
+        let msg = format!(
+            "Storage only holds {} {} but {} are owed; contact an operator.",
+            planned_total, item, terms.items
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    store.advance_trade(|s| s.begin_withdrawal(withdraw_plan.clone()));
+    // A small position can be worth less than one item; it then settles
+    // entirely to the balance without opening a trade.
+    if terms.items > 0 {
+        if let Err(e) = execute_chest_transfers(
+            store,
+            &withdraw_plan,
+            item,
+            stack_size,
+            ChestDirection::Withdraw,
+            "[LpRemove]",
+        )
+        .await
+        {
+            store.advance_trade(|s| s.rollback("lp-remove/chest-withdrawal-failed".to_string()));
+            let msg = format!(
+                "LP remove aborted: bot failed chest withdrawal step: {}",
+                e.user_message()
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "LP remove: you'll receive {} {} in the trade and {:.2} diamonds to your balance.",
+                terms.items, item, terms.diamonds
+            ),
+        )
+        .await?;
+    }
+    store.advance_trade(|s| s.begin_trading());
+
+    if terms.items > 0
+        && let Err(err) = perform_trade(
+            store,
+            player_name,
+            vec![TradeItem {
+                item: item.to_string(),
+                amount: terms.items,
+            }],
+            vec![],
+            false,
+            false,
+            "[LpRemove]",
+        )
+        .await
+    {
+        warn!(phase = "lp_remove.rollback", player = %player_name, item = %item, "LP remove trade failed, rolling back: {}", err);
+        let rb = rollback::deposit_transfers(store, &withdraw_plan, item, stack_size, "[LpRemove]")
+            .await;
+        let suffix = match rb.partial_message() {
+            Some(detail) => format!(" Rollback partial: {}.", detail),
+            None => " Items returned to storage; your shares are unchanged.".to_string(),
+        };
+        store.advance_trade(|s| s.rollback("lp-remove/trade-failed".to_string()));
+        return utils::whisper_action_aborted(
+            store,
+            player_name,
+            "LP remove",
+            &err.user_message(),
+            Some(&suffix),
+        )
+        .await;
+    }
+
+    // Commit ledgers.
+    let new_balance = {
+        let user = store.expect_user_mut(&user_uuid, "lp-remove/commit-user")?;
+        user.balance += terms.diamonds;
+        let remaining = user.lp_shares.get(item).copied().unwrap_or(0.0) - shares;
+        if remaining > SHARE_EPSILON {
+            user.lp_shares.insert(item.to_string(), remaining);
+        } else {
+            user.lp_shares.remove(item);
+        }
+        user.username = player_name.to_owned();
+        user.balance
+    };
+    store.dirty_users.insert(user_uuid.clone());
+
+    let new_item_stock = store.storage.total_item_amount(item);
+    let pair = store.expect_pair_mut(item, "lp-remove/commit-pair")?;
+    pair.lp_total_shares = (liquidity::share_supply(pair) - shares).max(0.0);
+    pair.item_stock = new_item_stock;
+    pair.currency_stock = (pair.currency_stock - terms.diamonds).max(0.0);
+    store.dirty = true;
+    store.post_ledger(
+        Account::Reserve(item.to_string()),
+        Account::Player(user_uuid.clone()),
+        terms.diamonds,
+        "lp remove",
+    );
+
+    store.trades.push(Trade::new(
+        TradeType::RemoveLiquidity,
+        ItemId::from_normalized(item.to_string()),
+        terms.items,
+        terms.diamonds,
+        user_uuid.clone(),
+    ));
+    store.advance_trade(|s| s.commit(item.to_string(), terms.items, terms.diamonds));
+
+    info!(
+        phase = "lp_remove.done",
+        player = %player_name,
+        item = %item,
+        shares = format_args!("{:.4}", shares),
+        items = terms.items,
+        diamonds = format_args!("{:.2}", terms.diamonds),
+        "LP remove completed"
+    );
+    if let Err(e) = state::assert_invariants(store, "post-lp-remove", true) {
+        error!(phase = "lp_remove.invariant", player = %player_name, item = %item, error = %e, "Invariant violation after LP remove");
+        let _ = state::save(store);
+    }
+
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Redeemed {:.4} {} shares for {} {} and {:.2} diamonds (credited to balance). New balance: {:.2}",
+            shares, item, terms.items, item, terms.diamonds, new_balance
+        ),
+    )
+    .await
+}
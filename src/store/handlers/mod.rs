@@ -4,9 +4,10 @@
 //! - Dispatchers (`player`, `operator`, `cli`) are the public entry points
 //!   called from `store::mod` (chat/operator messages) and the CLI loop. They
 //!   parse/route a message and delegate to the per-command modules below.
-//! - Command modules (`buy`, `sell`, `deposit`, `withdraw`, `info`) hold the
-//!   actual business logic, operating on `Store` state via `store::state` and
-//!   helpers from `store::utils` / `store::pricing`.
+//! - Command modules (`buy`, `sell`, `basket`, `deposit`, `withdraw`, `limit`,
+//!   `quote`, `liquidity`, `swap`, `info`, `pay_request`, `escrow`, `alert`)
+//!   hold the actual business logic, operating on `Store` state via
+//!   `store::state` and helpers from `store::utils` / `store::pricing`.
 //!
 //! `validation` is shared by the command parser (`store::command`) and
 //! handlers; it is `pub(crate)` so both can reach it.
@@ -15,9 +16,17 @@ pub mod cli;
 pub mod operator;
 pub mod player;
 
+pub(crate) mod alert;
+mod basket;
 mod buy;
 mod deposit;
+pub(crate) mod escrow;
 mod info;
+pub(crate) mod limit;
+mod liquidity;
+pub(crate) mod pay_request;
+mod quote;
 mod sell;
+mod swap;
 pub(crate) mod validation;
 mod withdraw;
@@ -2,6 +2,7 @@
 
 use tracing::{error, info, warn};
 
+use super::super::ledger::Account;
 use super::super::{Store, state, utils};
 use crate::constants::{CHEST_OP_TIMEOUT_SECS, CHESTS_PER_NODE};
 use crate::error::StoreError;
@@ -159,6 +160,7 @@ pub async fn handle_additem_order(
             position: t.position,
             item: t.item.clone(),
             amounts: vec![0; crate::types::Storage::SLOTS_PER_CHEST],
+            class: Default::default(),
         };
 
         let (tx, rx) = tokio::sync::oneshot::channel();
@@ -771,6 +773,12 @@ pub async fn handle_add_currency(
     );
     let new_reserve = pair.currency_stock;
     store.dirty = true;
+    store.post_ledger(
+        Account::Adjustment,
+        Account::Reserve(item.to_string()),
+        amount,
+        "addcurrency",
+    );
 
     store.trades.push(Trade::new(
         TradeType::AddCurrency,
@@ -873,6 +881,12 @@ pub async fn handle_remove_currency(
     );
     let new_reserve = pair.currency_stock;
     store.dirty = true;
+    store.post_ledger(
+        Account::Reserve(item.to_string()),
+        Account::Adjustment,
+        amount,
+        "removecurrency",
+    );
 
     store.trades.push(Trade::new(
         TradeType::RemoveCurrency,
@@ -950,6 +964,7 @@ mod tests {
                 username: username.to_string(),
                 balance,
                 operator: false,
+                lp_shares: Default::default(),
             },
         )
     }
@@ -988,6 +1003,11 @@ mod tests {
                 stack_size: 64,
                 item_stock,
                 currency_stock,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         )
     }
@@ -0,0 +1,274 @@
+//! Payment requests: `request <player> <amount> [memo]`, bare `request`,
+//! `accept R<id>` and `decline R<id>`, plus the periodic [`sweep`] that
+//! drops requests nobody answered in time.
+//!
+//! A request moves no diamonds. `accept` runs the same `info::transfer` as
+//! `pay`, so the payment is logged as a `Transfer` trade with the request's
+//! memo. The player who did not act is told through `utils::notify_player`,
+//! which holds the message for their next whisper when they are away.
+
+use chrono::{Duration, Utc};
+use tracing::{error, info, warn};
+
+use super::super::pay_requests::PayRequest;
+use super::super::{Store, utils};
+use super::info::{memo_suffix, transfer};
+use super::limit::format_ttl;
+use crate::constants::PAY_REQUEST_TTL_SECS;
+use crate::error::StoreError;
+
+pub(super) async fn handle_request(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    target: &str,
+    amount: f64,
+    memo: Option<String>,
+) -> Result<(), StoreError> {
+    let target_uuid = match crate::mojang::resolve_user_uuid(target).await {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            let err: StoreError = e.into();
+            return utils::whisper_error_to_player(store, player_name, &err).await;
+        }
+    };
+    if target_uuid == user_uuid {
+        return utils::send_message_to_player(store, player_name, "You cannot bill yourself.")
+            .await;
+    }
+    // An existing record's name is canonical, as in `pay`.
+    let target_name = store
+        .users
+        .get(&target_uuid)
+        .map_or_else(|| target.to_string(), |u| u.username.clone());
+
+    let ttl = Duration::seconds(PAY_REQUEST_TTL_SECS as i64);
+    let request = match store.pay_requests.open(
+        user_uuid,
+        player_name,
+        &target_uuid,
+        &target_name,
+        amount,
+        memo,
+        Utc::now(),
+        ttl,
+    ) {
+        Ok(r) => r,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+
+    let note = memo_suffix(request.memo.as_deref());
+    let bill = format!(
+        "{} requests {:.2} diamonds{}. Reply 'accept R{}' to pay it from your balance or 'decline R{}'. Expires in {}.",
+        player_name,
+        amount,
+        note,
+        request.id,
+        request.id,
+        format_ttl(PAY_REQUEST_TTL_SECS)
+    );
+    utils::notify_player(store, &target_uuid, &target_name, bill).await;
+
+    let reply = format!(
+        "Request R{} sent: {} asked for {:.2} diamonds{}. Withdraw it with 'decline R{}'.",
+        request.id, target_name, amount, note, request.id
+    );
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Whisper the open requests the caller sent or was sent.
+pub(super) async fn handle_list(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+) -> Result<(), StoreError> {
+    let now = Utc::now();
+    let parts: Vec<String> = store
+        .pay_requests
+        .involving(user_uuid)
+        .into_iter()
+        .filter(|r| !r.is_expired(now))
+        .map(|r| {
+            let left = (r.expires_at - now).num_seconds().max(0) as u64;
+            let who = if r.from_uuid == user_uuid {
+                format!("to {}", r.to_name)
+            } else {
+                format!("from {}", r.from_name)
+            };
+            format!(
+                "R{} {} {:.2} diamonds{} ({} left)",
+                r.id,
+                who,
+                r.amount,
+                memo_suffix(r.memo.as_deref()),
+                format_ttl(left)
+            )
+        })
+        .collect();
+    let message = if parts.is_empty() {
+        "You have no open payment requests. Bill someone with 'request <player> <amount> [memo]'."
+            .to_string()
+    } else {
+        format!("Your payment requests: {}", parts.join(", "))
+    };
+    utils::send_message_to_player(store, player_name, &message).await
+}
+
+/// The open request `id` if `user_uuid` is one of its two players. Anyone
+/// else is told it does not exist, so ids reveal nothing about other bills.
+fn find_own(store: &Store, user_uuid: &str, id: u64) -> Result<PayRequest, String> {
+    store
+        .pay_requests
+        .get(id)
+        .filter(|r| r.from_uuid == user_uuid || r.to_uuid == user_uuid)
+        .filter(|r| !r.is_expired(Utc::now()))
+        .cloned()
+        .ok_or_else(|| format!("Payment request R{} not found. 'request' lists yours.", id))
+}
+
+pub(super) async fn handle_accept(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let request = match find_own(store, user_uuid, id) {
+        Ok(r) if r.to_uuid == user_uuid => r,
+        Ok(_) => {
+            let msg = format!(
+                "R{} is your own request. Withdraw it with 'decline R{}'.",
+                id, id
+            );
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+
+    // Taken out before the transfer, so a crash between the two can only
+    // lose the request, never pay it twice.
+    if store.pay_requests.remove(id).is_err() {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            "Could not accept the request right now, please retry.",
+        )
+        .await;
+    }
+    if !store.users.contains_key(&request.from_uuid) {
+        utils::ensure_user_exists(store, &request.from_name, &request.from_uuid);
+    }
+    if let Err(e) = transfer(
+        store,
+        player_name,
+        user_uuid,
+        &request.from_uuid,
+        request.amount,
+        request.memo.clone(),
+    ) {
+        warn!(
+            request_id = id,
+            payer = player_name,
+            error = %e,
+            "Payment request not paid"
+        );
+        if let Err(restore_err) = store.pay_requests.restore(request.clone()) {
+            error!(
+                "[PayRequests] REQUEST LOST: R{} could not be paid ({}) or restored ({})",
+                id, e, restore_err
+            );
+        }
+        return utils::whisper_error_to_player(store, player_name, &e).await;
+    }
+
+    info!(
+        request_id = id,
+        payer = player_name,
+        payee = %request.from_name,
+        amount = request.amount,
+        "Payment request paid"
+    );
+    let note = memo_suffix(request.memo.as_deref());
+    let notice = format!(
+        "{} paid your request R{}: {:.2} diamonds{}.",
+        player_name, id, request.amount, note
+    );
+    utils::notify_player(store, &request.from_uuid, &request.from_name, notice).await;
+    let reply = format!(
+        "Paid {:.2} diamonds to {}{} (request R{}).",
+        request.amount, request.from_name, note, id
+    );
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Refuse a request addressed to the caller, or withdraw one they sent.
+pub(super) async fn handle_decline(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    id: u64,
+) -> Result<(), StoreError> {
+    let request = match find_own(store, user_uuid, id) {
+        Ok(r) => r,
+        Err(msg) => return utils::send_message_to_player(store, player_name, &msg).await,
+    };
+    if store.pay_requests.remove(id).is_err() {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            "Could not update the request right now, please retry.",
+        )
+        .await;
+    }
+
+    let note = memo_suffix(request.memo.as_deref());
+    let (other_uuid, other_name, notice, reply) = if request.to_uuid == user_uuid {
+        (
+            &request.from_uuid,
+            &request.from_name,
+            format!(
+                "{} declined your request R{} for {:.2} diamonds{}.",
+                player_name, id, request.amount, note
+            ),
+            format!("Declined request R{} from {}.", id, request.from_name),
+        )
+    } else {
+        (
+            &request.to_uuid,
+            &request.to_name,
+            format!(
+                "{} withdrew request R{} for {:.2} diamonds{}.",
+                player_name, id, request.amount, note
+            ),
+            format!("Withdrew request R{} to {}.", id, request.to_name),
+        )
+    };
+    info!(
+        request_id = id,
+        by = player_name,
+        "Payment request closed unpaid"
+    );
+    utils::notify_player(store, other_uuid, other_name, notice).await;
+    utils::send_message_to_player(store, player_name, &reply).await
+}
+
+/// Drop requests past their deadline and tell whoever sent them. Called
+/// from the run loop's periodic cleanup while any request is open; a
+/// request that fails to persist its removal is retried next time.
+pub(crate) async fn sweep(store: &mut Store) {
+    for id in store.pay_requests.expired_ids(Utc::now()) {
+        match store.pay_requests.remove(id) {
+            Ok(request) => {
+                info!(
+                    "[PayRequests] R{} expired unpaid ({} asked {} for {})",
+                    id, request.from_name, request.to_name, request.amount
+                );
+                let notice = format!(
+                    "Your request R{} to {} for {:.2} diamonds expired unpaid.",
+                    id, request.to_name, request.amount
+                );
+                utils::notify_player(store, &request.from_uuid, &request.from_name, notice).await;
+            }
+            Err(e) => warn!("[PayRequests] could not expire R{}: {}", id, e),
+        }
+    }
+}
@@ -2,32 +2,48 @@
 //!
 //! Commands are whispered to the bot, parsed by [`super::super::command::parse_command`]
 //! into a typed [`Command`], and then dispatched to sibling handler modules:
-//! - Order commands (buy/sell/deposit/withdraw) → [`buy`], [`sell`], [`deposit`], [`withdraw`].
+//! - Order commands (buy/sell/baskets/swap/deposit/withdraw/lp/escrow) → [`buy`], [`sell`],
+//!   [`basket`], [`swap`], [`deposit`], [`withdraw`], [`liquidity`], [`escrow`].
 //!   Handlers here only validate and enqueue; actual chest I/O and trade
 //!   GUI interaction happen later on the queue-processor task.
 //! - Quick commands (balance/price/help/items/pay/queue/cancel/status) →
 //!   [`info`]. These run inline because they need no bot movement.
+//! - Price and restock alerts (alert/notify) → [`alert`], also inline.
 //! - Operator admin commands (additem/removeitem/add/removecurrency) →
 //!   [`operator`]. Gated here by [`utils::is_operator`].
 //!
 //! The queued-order processor entry points (`handle_deposit_balance_queued`,
-//! `handle_withdraw_balance_queued`) and the in-process `pay_async` are
+//! `handle_withdraw_balance_queued`, the liquidity, basket and escrow handlers) and the in-process `pay_async` are
 //! re-exported so external callers (orders.rs, integration tests) can keep
 //! using `handlers::player::<fn>` paths.
 
 use tracing::{debug, warn};
 
-use super::super::command::{Command, parse_command};
+use super::super::command::{Command, Quantity, parse_command};
+use super::super::order_book::OrderSide;
 use super::super::{Store, utils};
 use super::validation::validate_username;
-use super::{buy, deposit, info, operator, sell, withdraw};
+use super::{
+    alert, basket, buy, deposit, escrow, info, limit, liquidity, operator, pay_request, quote,
+    sell, swap, withdraw,
+};
 use crate::error::StoreError;
+use crate::types::ItemId;
 
 // Back-compat re-exports: orders.rs and tests reference these via
 // `handlers::player::<fn>`. Keep them resolving through this module.
+pub use basket::{handle_buy_basket_queued, handle_sell_all_queued, handle_sell_basket_queued};
+pub use buy::max_buy_fill;
 pub use deposit::handle_deposit_balance_queued;
+pub use escrow::{
+    handle_deposit_queued as handle_escrow_deposit_queued,
+    handle_release_queued as handle_escrow_release_queued,
+    handle_return_queued as handle_escrow_return_queued,
+};
 #[cfg(test)]
 pub use info::pay_async;
+pub use liquidity::{handle_add_liquidity_queued, handle_remove_liquidity_queued};
+pub use sell::max_sell_fill;
 pub use withdraw::handle_withdraw_balance_queued;
 
 /// Compose the `n:`-prefixed rate-limit key for a raw player name.
@@ -46,6 +62,59 @@ fn name_rate_limit_key(player_name: &str) -> String {
     }
 }
 
+/// Settle a typed quantity (`64`, `3s`, `1sh+10`) against `item`'s stack
+/// size, so handlers only ever see items. `None` when it cannot be settled
+/// and the player has been told why.
+async fn resolve_quantity(
+    store: &Store,
+    player_name: &str,
+    item: &ItemId,
+    quantity: Quantity,
+) -> Result<Option<u32>, StoreError> {
+    let stack_size = if quantity.is_stack_relative() {
+        match store.pairs.get(item.as_str()) {
+            Some(pair) => pair.stack_size,
+            None => {
+                let msg = format!("Item '{}' is not available for trading", item);
+                utils::send_message_to_player(store, player_name, &msg).await?;
+                return Ok(None);
+            }
+        }
+    } else {
+        1
+    };
+    match quantity.resolve(stack_size) {
+        Ok(n) => Ok(Some(n)),
+        Err(msg) => {
+            utils::send_message_to_player(store, player_name, &msg).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Deliver notices held for a player the bot just saw join. Only players
+/// the Store already knows can have any, so the name is matched against
+/// `store.users` (case-insensitively, like the rate limiter) rather than
+/// looked up with Mojang; anyone else is ignored.
+pub async fn handle_player_joined(store: &mut Store, player_name: &str) {
+    if validate_username(player_name).is_err() {
+        return;
+    }
+    let Some(user_uuid) = store
+        .users
+        .values()
+        .find(|u| u.username.eq_ignore_ascii_case(player_name))
+        .map(|u| u.uuid.clone())
+    else {
+        return;
+    };
+    debug!(
+        player = player_name,
+        "Player joined; delivering held notices"
+    );
+    utils::deliver_held_notices(store, player_name, &user_uuid).await;
+}
+
 pub async fn handle_player_command(
     store: &mut Store,
     player_name: &str,
@@ -148,6 +217,10 @@ pub async fn handle_player_command(
         .await;
     }
 
+    // Notices held while the player was away (see `store::notices`) go out
+    // before the reply to whatever they just asked.
+    utils::deliver_held_notices(store, player_name, &user_uuid).await;
+
     let parsed = match parse_command(command) {
         Ok(cmd) => cmd,
         Err(msg) => {
@@ -163,11 +236,90 @@ pub async fn handle_player_command(
     };
 
     match parsed {
-        Command::Buy { item, quantity } => {
-            buy::handle(store, player_name, &user_uuid, &item, quantity).await
+        Command::Buy {
+            item,
+            quantity,
+            max_cost,
+        } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            buy::handle(store, player_name, &user_uuid, &item, quantity, max_cost).await
+        }
+        Command::Sell {
+            item,
+            quantity,
+            min_payout,
+        } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            sell::handle(store, player_name, &user_uuid, &item, quantity, min_payout).await
+        }
+        Command::UpTo {
+            side,
+            item,
+            quantity,
+        } => {
+            let quantity = match quantity {
+                Some(q) => match resolve_quantity(store, player_name, &item, q).await? {
+                    Some(n) => Some(n),
+                    None => return Ok(()),
+                },
+                None => None,
+            };
+            match side {
+                OrderSide::Buy => {
+                    buy::handle_up_to(store, player_name, &user_uuid, &item, quantity).await
+                }
+                OrderSide::Sell => {
+                    sell::handle_up_to(store, player_name, &user_uuid, &item, quantity).await
+                }
+            }
+        }
+        Command::SellAll { item } => sell::handle_all(store, player_name, &user_uuid, &item).await,
+        Command::Basket { side, lines } => {
+            let mut resolved = Vec::with_capacity(lines.len());
+            for (item, quantity) in lines {
+                let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+                else {
+                    return Ok(());
+                };
+                resolved.push((item, quantity));
+            }
+            basket::handle_enqueue(store, player_name, &user_uuid, side, &resolved).await
+        }
+        Command::Swap { from, quantity, to } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &from, quantity).await?
+            else {
+                return Ok(());
+            };
+            swap::handle(store, player_name, &user_uuid, &from, quantity, &to).await
         }
-        Command::Sell { item, quantity } => {
-            sell::handle(store, player_name, &user_uuid, &item, quantity).await
+        Command::Limit {
+            side,
+            item,
+            quantity,
+            limit_price,
+            ttl_secs,
+        } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            limit::handle_place(
+                store,
+                player_name,
+                &user_uuid,
+                side,
+                &item,
+                quantity,
+                limit_price,
+                ttl_secs,
+            )
+            .await
         }
         Command::Deposit { amount } => {
             deposit::handle_enqueue(store, player_name, &user_uuid, amount).await
@@ -175,20 +327,118 @@ pub async fn handle_player_command(
         Command::Withdraw { amount } => {
             withdraw::handle_enqueue(store, player_name, &user_uuid, amount).await
         }
+        Command::Quote {
+            side,
+            item,
+            quantity,
+        } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            quote::handle_quote(store, player_name, &user_uuid, side, &item, quantity).await
+        }
+        Command::Confirm { quote_id } => {
+            quote::handle_confirm(store, player_name, &user_uuid, quote_id).await
+        }
+        Command::LpAdd { item, quantity } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            liquidity::handle_add_enqueue(store, player_name, &user_uuid, &item, quantity).await
+        }
+        Command::LpRemove { item, shares } => {
+            liquidity::handle_remove_enqueue(store, player_name, &user_uuid, &item, shares).await
+        }
+        Command::LpPositions => liquidity::handle_positions(store, player_name, &user_uuid).await,
         Command::Price { item, quantity } => {
+            let quantity = match quantity {
+                Some(q) => match resolve_quantity(store, player_name, &item, q).await? {
+                    Some(n) => Some(n),
+                    None => return Ok(()),
+                },
+                None => None,
+            };
             info::handle_price(store, player_name, &item, quantity).await
         }
+        Command::Stats { item } => info::handle_stats(store, player_name, &item).await,
+        Command::History { item, window } => {
+            info::handle_history(store, player_name, &item, window).await
+        }
+        Command::MyHistory { page } => {
+            info::handle_my_history(store, player_name, &user_uuid, page).await
+        }
+        Command::Receipt { index } => {
+            info::handle_receipt(store, player_name, &user_uuid, index).await
+        }
         Command::Balance { target } => {
             info::handle_balance(store, player_name, &user_uuid, target.as_deref()).await
         }
-        Command::Pay { target, amount } => {
-            info::handle_pay(store, player_name, &user_uuid, &target, amount).await
+        Command::Pay {
+            target,
+            amount,
+            memo,
+        } => info::handle_pay(store, player_name, &user_uuid, &target, amount, memo).await,
+        Command::Request {
+            target,
+            amount,
+            memo,
+        } => {
+            pay_request::handle_request(store, player_name, &user_uuid, &target, amount, memo).await
+        }
+        Command::Requests => pay_request::handle_list(store, player_name, &user_uuid).await,
+        Command::Accept { request_id } => {
+            pay_request::handle_accept(store, player_name, &user_uuid, request_id).await
+        }
+        Command::Decline { request_id } => {
+            pay_request::handle_decline(store, player_name, &user_uuid, request_id).await
+        }
+        Command::EscrowSell {
+            buyer,
+            item,
+            quantity,
+            price,
+        } => {
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
+            escrow::handle_sell_enqueue(
+                store,
+                player_name,
+                &user_uuid,
+                &buyer,
+                &item,
+                quantity,
+                price,
+            )
+            .await
+        }
+        Command::Escrows => escrow::handle_list(store, player_name, &user_uuid).await,
+        Command::EscrowAccept { escrow_id } => {
+            escrow::handle_accept(store, player_name, &user_uuid, escrow_id).await
+        }
+        Command::EscrowCancel { escrow_id } => {
+            escrow::handle_cancel(store, player_name, &user_uuid, escrow_id).await
+        }
+        Command::Alert {
+            item,
+            trigger,
+            ttl_secs,
+        } => alert::handle_set(store, player_name, &user_uuid, &item, trigger, ttl_secs).await,
+        Command::Alerts => alert::handle_list(store, player_name, &user_uuid).await,
+        Command::CancelAlert { alert_id } => {
+            alert::handle_cancel(store, player_name, &user_uuid, alert_id).await
         }
         Command::Items { page } => info::handle_items(store, player_name, page).await,
         Command::Queue { page } => info::handle_queue(store, player_name, &user_uuid, page).await,
         Command::Cancel { order_id } => {
             info::handle_cancel(store, player_name, &user_uuid, order_id).await
         }
+        Command::CancelLimit { limit_id } => {
+            limit::handle_cancel(store, player_name, &user_uuid, limit_id).await
+        }
         Command::Status => info::handle_status(store, player_name).await,
         Command::Help { topic } => {
             info::handle_help(store, player_name, &user_uuid, topic.as_deref()).await
@@ -201,12 +451,20 @@ pub async fn handle_player_command(
             if !ensure_operator(store, player_name, &user_uuid, "additem").await? {
                 return Ok(());
             }
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
             operator::handle_additem_order(store, player_name, &item, quantity).await
         }
         Command::RemoveItem { item, quantity } => {
             if !ensure_operator(store, player_name, &user_uuid, "removeitem").await? {
                 return Ok(());
             }
+            let Some(quantity) = resolve_quantity(store, player_name, &item, quantity).await?
+            else {
+                return Ok(());
+            };
             operator::handle_removeitem_order(store, player_name, &item, quantity).await
         }
         Command::AddCurrency { item, amount } => {
@@ -310,7 +568,7 @@ mod tests {
     use super::*;
     use crate::config::Config;
     use crate::messages::BotInstruction;
-    use crate::types::{Storage, User};
+    use crate::types::{Pair, Storage, User};
     use std::collections::HashMap;
     use tokio::sync::mpsc;
     use tokio::time::{Duration, timeout};
@@ -421,6 +679,35 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn stack_quantities_resolve_against_the_pairs_stack_size() {
+        let (mut store, mut whispers) = make_store();
+        store.pairs.insert(
+            "ender_pearl".to_string(),
+            Pair {
+                item: ItemId::new("ender_pearl").unwrap(),
+                stack_size: 16,
+                item_stock: 1_000,
+                currency_stock: 1_000.0,
+                ..Pair::default()
+            },
+        );
+
+        handle_player_command(&mut store, "Alice", "price ender_pearl 2 stacks + 1")
+            .await
+            .unwrap();
+        let (_, message) = recv_whisper(&mut whispers).await;
+        assert!(message.starts_with("ender_pearl x33:"), "{message}");
+
+        // Without a pair there is no stack size to count in. Bob, since
+        // Alice is still in cooldown.
+        handle_player_command(&mut store, "Bob", "price glass 1sh")
+            .await
+            .unwrap();
+        let (_, message) = recv_whisper(&mut whispers).await;
+        assert_eq!(message, "Item 'glass' is not available for trading");
+    }
+
     #[tokio::test]
     async fn non_operator_additem_is_rejected_with_operator_message() {
         let (mut store, mut whispers) = make_store();
@@ -434,6 +721,7 @@ mod tests {
                 username: "Alice".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
 
@@ -0,0 +1,157 @@
+//! `quote buy|sell <item> <qty>` and `confirm <id>`: price an order now,
+//! then enqueue it at exactly that price.
+//!
+//! A quote lives in `store.quotes` for `QUOTE_TTL_SECS`. On `confirm` the
+//! live price is recomputed; if reserves have moved more than
+//! `QUOTE_TOLERANCE` the quote is rejected, otherwise the order is queued
+//! with `quoted_total` set. Execution re-checks the same tolerance
+//! (`orders::validate_and_plan_*`) because other orders can still run ahead
+//! of it in the queue.
+
+use chrono::{Duration, Utc};
+use tracing::{debug, info};
+
+use super::super::order_book::OrderSide;
+use super::super::{Store, pricing, utils};
+use crate::constants::{QUOTE_TOLERANCE, QUOTE_TTL_SECS};
+use crate::error::StoreError;
+use crate::messages::QueuedOrderType;
+use crate::types::ItemId;
+
+/// Live total for `quantity` of `item`: buy cost or sell payout.
+fn live_total(store: &Store, side: OrderSide, item: &str, quantity: u32) -> Option<f64> {
+    let qty = i32::try_from(quantity).ok()?;
+    match side {
+        OrderSide::Buy => pricing::calculate_buy_cost(store, item, qty),
+        OrderSide::Sell => pricing::calculate_sell_payout(store, item, qty),
+    }
+}
+
+pub(super) async fn handle_quote(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    side: OrderSide,
+    item: &ItemId,
+    quantity: u32,
+) -> Result<(), StoreError> {
+    if !store.pairs.contains_key(item.as_str()) {
+        debug!(
+            user = player_name,
+            uuid = user_uuid,
+            item = %item,
+            "Quote rejected: item not in pairs"
+        );
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    }
+
+    let Some(total) = live_total(store, side, item.as_str(), quantity) else {
+        let msg = format!(
+            "Cannot quote {} {} {}: price unavailable (insufficient reserves or stock).",
+            side.verb(),
+            quantity,
+            item
+        );
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    };
+
+    let quote = store.quotes.issue(
+        user_uuid,
+        side,
+        item.as_str(),
+        quantity,
+        total,
+        Utc::now(),
+        Duration::seconds(QUOTE_TTL_SECS as i64),
+    );
+    debug!(
+        "[Quotes] Issued Q{} to {}: {}",
+        quote.id,
+        player_name,
+        quote.description()
+    );
+    let msg = format!(
+        "Quote Q{}: {} ({:.4}/ea). Valid {}s; reply 'confirm Q{}' to lock it in.",
+        quote.id,
+        quote.description(),
+        total / f64::from(quantity),
+        QUOTE_TTL_SECS,
+        quote.id
+    );
+    utils::send_message_to_player(store, player_name, &msg).await
+}
+
+pub(super) async fn handle_confirm(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    quote_id: u64,
+) -> Result<(), StoreError> {
+    let quote = match store.quotes.take(user_uuid, quote_id, Utc::now()) {
+        Ok(q) => q,
+        Err(e) => return utils::send_message_to_player(store, player_name, &e).await,
+    };
+
+    let live = live_total(store, quote.side, &quote.item, quote.quantity);
+    let within =
+        live.is_some_and(|l| pricing::quote_within_tolerance(quote.total, l, QUOTE_TOLERANCE));
+    if !within {
+        debug!(
+            "[Quotes] Q{} for {} rejected on confirm: quoted {:.4}, live {:?}",
+            quote.id, player_name, quote.total, live
+        );
+        let msg = match live {
+            Some(l) => format!(
+                "Price moved: {} {} {} is now {:.2} diamonds, more than {:.0}% away from quote Q{} ({:.2}). Request a new quote.",
+                quote.side.verb(),
+                quote.quantity,
+                quote.item,
+                l,
+                QUOTE_TOLERANCE * 100.0,
+                quote.id,
+                quote.total
+            ),
+            None => format!(
+                "Quote Q{} can no longer be filled: price unavailable (insufficient reserves or stock).",
+                quote.id
+            ),
+        };
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    let order_type = match quote.side {
+        OrderSide::Buy => QueuedOrderType::Buy,
+        OrderSide::Sell => QueuedOrderType::Sell,
+    };
+    match store.order_queue.add_quoted(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        order_type,
+        quote.item.clone(),
+        quote.quantity,
+        quote.total,
+    ) {
+        Ok((order_id, position)) => {
+            info!(
+                "[Quotes] Q{} confirmed by {} -> queued as #{} ({})",
+                quote.id,
+                player_name,
+                order_id,
+                quote.description()
+            );
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Order #{} queued at quoted {:.2} diamonds (position {}/{}). Est. wait: {}.",
+                order_id, quote.total, position, queue_len, wait_estimate
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
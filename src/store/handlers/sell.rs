@@ -2,11 +2,19 @@
 //!
 //! Input validation (item name, quantity) happens in `store::command::parse_command`.
 //! This handler only checks runtime preconditions (is the pair tradable?) and
-//! enqueues the order.
+//! enqueues the order, carrying the optional `min_payout` slippage bound.
+//!
+//! `sell <item> upto <qty>` enqueues a partial order instead:
+//! [`max_sell_fill`] works out how much of it can fill, here as an estimate
+//! for the player and again in `orders::fill_partial` when the order runs.
+//! `sell all <item>` takes whatever the player offers, up to that fill,
+//! and credits the payout to the balance (see `basket::handle_sell_all_queued`).
 
 use tracing::debug;
 
-use super::super::{Store, utils};
+use super::super::orders::PartialFill;
+use super::super::{Store, pricing, utils};
+use crate::constants::TRADE_OFFER_SLOTS_PER_SIDE;
 use crate::error::StoreError;
 use crate::messages::QueuedOrderType;
 use crate::types::ItemId;
@@ -17,6 +25,7 @@ pub(super) async fn handle(
     user_uuid: &str,
     item: &ItemId,
     quantity: u32,
+    min_payout: Option<f64>,
 ) -> Result<(), StoreError> {
     if !store.pairs.contains_key(item.as_str()) {
         debug!(
@@ -41,12 +50,13 @@ pub(super) async fn handle(
         "Queueing sell order"
     );
 
-    match store.order_queue.add(
+    match store.order_queue.add_bounded(
         user_uuid.to_string(),
         player_name.to_string(),
         QueuedOrderType::Sell,
         item.as_str().to_string(),
         quantity,
+        min_payout,
     ) {
         Ok((order_id, position)) => {
             let queue_len = store.order_queue.len();
@@ -64,3 +74,172 @@ pub(super) async fn handle(
         Err(e) => utils::send_message_to_player(store, player_name, &e).await,
     }
 }
+
+/// The most of `item`, at most `cap`, a partial sell can fill right now:
+/// bounded by free storage space and by the diamonds the store can pay out,
+/// both in the pair's reserve and physically in storage. `Err` is the
+/// player-facing reason nothing fits.
+pub fn max_sell_fill(store: &Store, item: &str, cap: u32) -> Result<PartialFill, String> {
+    let pair = store
+        .pairs
+        .get(item)
+        .ok_or_else(|| format!("Item '{}' is not available for trading", item))?;
+    let cap = i32::try_from(cap).unwrap_or(i32::MAX);
+    let (_, space) = store
+        .storage
+        .simulate_deposit_plan(item, cap, pair.stack_size);
+    if space < 1 {
+        return Err(format!(
+            "The store has no storage space for '{}' right now. Please contact an operator.",
+            item
+        ));
+    }
+    let payable = pair
+        .currency_stock
+        .min(f64::from(store.storage.total_item_amount("diamond")));
+    let Some((quantity, total)) =
+        pricing::pair_max_sell_within(pair, payable, space, store.config.fee)
+    else {
+        return Err(format!(
+            "The store is out of diamonds to buy '{}' right now.",
+            item
+        ));
+    };
+    let limited_by = if quantity == cap {
+        None
+    } else if quantity == space {
+        Some("storage space")
+    } else {
+        Some("the store's diamond reserve")
+    };
+    Ok(PartialFill {
+        quantity: quantity as u32,
+        total,
+        limited_by,
+    })
+}
+
+/// `sell <item> upto <qty>` / `sell <item> all`: enqueue a partial sell.
+/// `quantity` is `None` for one full trade window.
+pub(super) async fn handle_up_to(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+    quantity: Option<u32>,
+) -> Result<(), StoreError> {
+    let Some(pair) = store.pairs.get(item.as_str()) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let cap = quantity.unwrap_or((TRADE_OFFER_SLOTS_PER_SIDE * pair.stack_size) as u32);
+    let fill = match max_sell_fill(store, item.as_str(), cap) {
+        Ok(fill) => fill,
+        Err(reason) => {
+            debug!(
+                player = player_name,
+                uuid = user_uuid,
+                item = %item,
+                reason = %reason,
+                "Rejected partial sell: nothing can fill"
+            );
+            return utils::send_message_to_player(store, player_name, &reason).await;
+        }
+    };
+
+    debug!(
+        player = player_name,
+        uuid = user_uuid,
+        item = %item,
+        cap = cap,
+        estimate = fill.quantity,
+        "Queueing partial sell order"
+    );
+
+    match store.order_queue.add_partial(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::Sell,
+        item.as_str().to_string(),
+        cap,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Order #{} queued (position {}/{}): sell up to {} {}. Est. wait: {}. Right now the store takes {}{} for {:.2} diamonds; the final amount is set when it runs.",
+                order_id,
+                position,
+                queue_len,
+                cap,
+                item,
+                wait_estimate,
+                fill.quantity,
+                fill.limit_note(),
+                fill.total
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
+
+/// `sell all <item>`: enqueue a sell of however many the player offers, at
+/// most one trade window. Rejected now if the store could take none.
+pub(super) async fn handle_all(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &ItemId,
+) -> Result<(), StoreError> {
+    let Some(pair) = store.pairs.get(item.as_str()) else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!("Item '{}' is not available for trading", item),
+        )
+        .await;
+    };
+    let cap = (TRADE_OFFER_SLOTS_PER_SIDE * pair.stack_size) as u32;
+    let fill = match max_sell_fill(store, item.as_str(), cap) {
+        Ok(fill) => fill,
+        Err(reason) => return utils::send_message_to_player(store, player_name, &reason).await,
+    };
+
+    debug!(
+        player = player_name,
+        uuid = user_uuid,
+        item = %item,
+        estimate = fill.quantity,
+        "Queueing sell-all order"
+    );
+
+    match store.order_queue.add(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::SellAll,
+        item.as_str().to_string(),
+        cap,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Order #{} queued (position {}/{}): sell the {} you offer. Est. wait: {}. Right now the store takes up to {}{}; the payout goes to your balance.",
+                order_id,
+                position,
+                queue_len,
+                item,
+                wait_estimate,
+                fill.quantity,
+                fill.limit_note()
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
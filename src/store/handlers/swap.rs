@@ -0,0 +1,102 @@
+//! `swap` command: enqueue an item-to-item swap.
+//!
+//! Input validation (item names, quantity, distinct items) happens in
+//! `store::command::parse_command`. This handler checks that both pairs are
+//! tradable and that the sell leg would buy at least one of the target item
+//! at current prices, then enqueues the order. Pricing is redone at
+//! execution time in `orders::handle_swap_order`, so the quoted output is
+//! only an estimate.
+
+use tracing::debug;
+
+use super::super::{Store, pricing, utils};
+use crate::constants::TRADE_OFFER_SLOTS_PER_SIDE;
+use crate::error::StoreError;
+use crate::messages::QueuedOrderType;
+use crate::types::ItemId;
+
+pub(super) async fn handle(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    from: &ItemId,
+    quantity: u32,
+    to: &ItemId,
+) -> Result<(), StoreError> {
+    for item in [from, to] {
+        if !store.pairs.contains_key(item.as_str()) {
+            debug!(
+                user = player_name,
+                uuid = user_uuid,
+                item = %item,
+                "Swap rejected: item not in pairs"
+            );
+            return utils::send_message_to_player(
+                store,
+                player_name,
+                &format!("Item '{}' is not available for trading", item),
+            )
+            .await;
+        }
+    }
+
+    let payout = i32::try_from(quantity)
+        .ok()
+        .and_then(|q| pricing::calculate_sell_payout(store, from.as_str(), q));
+    let Some(payout) = payout else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Item '{}' is not available for trading (no stock or reserves).",
+                from
+            ),
+        )
+        .await;
+    };
+    let estimate = store.pairs.get(to.as_str()).and_then(|pair| {
+        let max_out = TRADE_OFFER_SLOTS_PER_SIDE * pair.stack_size;
+        pricing::pair_max_buy_within(pair, payout, max_out, store.config.fee)
+    });
+    let Some((est_out, _)) = estimate else {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Selling {} {} pays about {:.2} diamonds, not enough for one {}.",
+                quantity, from, payout, to
+            ),
+        )
+        .await;
+    };
+
+    debug!(
+        user = player_name,
+        uuid = user_uuid,
+        from = %from,
+        to = %to,
+        quantity = quantity,
+        "Queueing swap order"
+    );
+
+    match store.order_queue.add(
+        user_uuid.to_string(),
+        player_name.to_string(),
+        QueuedOrderType::Swap {
+            to_item: to.as_str().to_string(),
+        },
+        from.as_str().to_string(),
+        quantity,
+    ) {
+        Ok((order_id, position)) => {
+            let queue_len = store.order_queue.len();
+            let wait_estimate = store.order_queue.estimate_wait(position);
+            let msg = format!(
+                "Swap order #{} queued (position {}/{}). Est. wait: {}. At current prices: {} {} -> ~{} {}.",
+                order_id, position, queue_len, wait_estimate, quantity, from, est_out, to
+            );
+            utils::send_message_to_player(store, player_name, &msg).await
+        }
+        Err(e) => utils::send_message_to_player(store, player_name, &e).await,
+    }
+}
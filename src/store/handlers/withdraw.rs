@@ -2,6 +2,7 @@
 
 use tracing::{debug, error, info, warn};
 
+use super::super::ledger::Account;
 use super::super::{Store, state, utils};
 use crate::constants::MAX_TRADE_DIAMONDS;
 use crate::error::StoreError;
@@ -409,6 +410,12 @@ pub async fn handle_withdraw_balance_queued(
         user.balance -= amount;
         user.username = player_name.to_owned();
     }
+    store.post_ledger(
+        Account::Player(user_uuid.clone()),
+        Account::Vault,
+        amount,
+        "withdraw",
+    );
     store.dirty = true;
     store.dirty_users.insert(user_uuid.clone());
     info!(
@@ -9,10 +9,10 @@
 //!
 //! The journal is a persistent record of the *current* in-flight shulker
 //! operation. Exactly one entry can be active at a time (chest I/O is
-//! serialized through the store task). On startup the bot reads any surviving
-//! entry and logs a warning so an operator can reconcile state — this is
-//! **detection**, not automatic resume. Full resume would need to replay the
-//! correct step against live world state, which is out of scope here.
+//! serialized through the store task). On startup the bot archives any
+//! surviving entry for operator review, and the store hands a copy to
+//! [`journal_replay`](super::journal_replay), which sends the bot to find the
+//! stranded shulker, put it back in its recorded slot, and re-sync the chest.
 //!
 //! ## File format
 //!
@@ -66,6 +66,45 @@ pub enum JournalState {
     ShulkerReplaced,
 }
 
+impl JournalState {
+    /// Where the shulker most likely is if the bot stopped in this state.
+    ///
+    /// A shulker taken into the cursor drops back into the inventory when the
+    /// client disconnects, so `ShulkerTaken` and `ShulkerPickedUp` agree.
+    pub fn expected_location(self) -> ShulkerLocation {
+        match self {
+            JournalState::ShulkerTaken | JournalState::ShulkerPickedUp => {
+                ShulkerLocation::Inventory
+            }
+            JournalState::ShulkerOnStation | JournalState::ItemsTransferred => {
+                ShulkerLocation::Station
+            }
+            JournalState::ShulkerReplaced => ShulkerLocation::ChestSlot,
+        }
+    }
+}
+
+/// Where a shulker is between the steps of an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShulkerLocation {
+    /// Placed as a block on the node's shulker station.
+    Station,
+    /// Loose in the bot's inventory.
+    Inventory,
+    /// Back in (or never taken from) its recorded chest slot.
+    ChestSlot,
+}
+
+impl std::fmt::Display for ShulkerLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ShulkerLocation::Station => "on the station",
+            ShulkerLocation::Inventory => "in the bot's inventory",
+            ShulkerLocation::ChestSlot => "in its chest slot",
+        })
+    }
+}
+
 /// One in-flight shulker operation.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JournalEntry {
@@ -522,6 +561,28 @@ impl Journal {
 /// `.await` points.
 pub type SharedJournal = std::sync::Arc<Mutex<Journal>>;
 
+/// Read the entry on disk at `data/journal.json` without touching the file.
+///
+/// The store calls this before the bot task starts, so it sees the same
+/// leftover the bot is about to archive.
+pub fn peek_leftover() -> io::Result<Option<JournalEntry>> {
+    read_entry(Path::new(JOURNAL_FILE))
+}
+
+/// Read the entry held in a journal file, such as a
+/// `journal.leftover-*.json` archive. A missing file holds no entry; unlike
+/// [`Journal::load`] nothing is quarantined, so a corrupt file is an error.
+pub fn read_entry(path: &Path) -> io::Result<Option<JournalEntry>> {
+    let json = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let entries: Vec<JournalEntry> =
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(entries.into_iter().next_back())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -861,4 +922,31 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn read_entry_leaves_the_file_in_place() {
+        let (mut j, dir) = temp_journal("read-entry");
+        let path = j.path.clone();
+        assert_eq!(
+            read_entry(&path).unwrap(),
+            None,
+            "missing file holds no entry"
+        );
+
+        j.begin(JournalOp::DepositToChest, 5, 17).unwrap();
+        j.advance(JournalState::ShulkerOnStation).unwrap();
+        let entry = read_entry(&path).unwrap().expect("entry on disk");
+        assert_eq!((entry.chest_id, entry.slot_index), (5, 17));
+        assert_eq!(entry.state.expected_location(), ShulkerLocation::Station);
+        assert!(
+            path.exists(),
+            "reading must not quarantine or clear the file"
+        );
+
+        std::fs::write(&path, "[{").unwrap();
+        assert!(read_entry(&path).is_err());
+        assert!(path.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
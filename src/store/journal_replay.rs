@@ -0,0 +1,272 @@
+//! Journal replay: finish a shulker operation a crash left half-done.
+//!
+//! The bot archives a leftover `data/journal.json` at startup; `Store::new`
+//! has already read the same entry into `Store::interrupted_journal`, and
+//! `Store::run` hands it to [`replay`] before crash-resume of the enclosing
+//! trade, so any rollback deposits see a chest with every shulker in place.
+//!
+//! The bot walks to the entry's node and looks for the shulker where
+//! [`JournalState::expected_location`] says it should be, but checks every
+//! place (station, inventory, chest slot) because the last journal write
+//! can trail the world by one step. It puts the shulker back in the
+//! recorded slot, then runs one ordinary journaled round trip on that slot
+//! to count it, and the count is merged with `apply_chest_sync`.
+//!
+//! Replay never touches the ledger. Items the interrupted op had already
+//! moved into the bot's inventory stay there; crash-resume returns those
+//! that belong to a trade. When the automatic replay fails the entry stays
+//! on the store so the CLI entry "Replay interrupted shulker op" can retry.
+
+use std::path::Path;
+
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+use super::Store;
+use super::journal::{self, JournalEntry, JournalOp, JournalState};
+use crate::constants::{CHEST_OP_TIMEOUT_SECS, DOUBLE_CHEST_SLOTS};
+use crate::messages::BotInstruction;
+
+/// What replay will try for `entry`, one line per step. Pure, so the dry run
+/// prints exactly what startup does.
+pub fn describe(entry: &JournalEntry) -> Vec<String> {
+    let op = match entry.operation_type {
+        JournalOp::WithdrawFromChest => "withdraw",
+        JournalOp::DepositToChest => "deposit",
+    };
+    let mut lines = vec![
+        format!(
+            "Shulker op #{} ({} at chest {} slot {}) stopped at {:?}.",
+            entry.operation_id, op, entry.chest_id, entry.slot_index, entry.state
+        ),
+        format!(
+            "Look for the shulker {} first, then the station, the bot's inventory and the slot itself.",
+            entry.state.expected_location()
+        ),
+        format!(
+            "Put it back in chest {} slot {} and re-sync that slot.",
+            entry.chest_id, entry.slot_index
+        ),
+    ];
+    if entry.state != JournalState::ShulkerTaken {
+        lines.push(
+            "Items moved before the crash may still be in the bot's inventory; crash-resume returns a trade's items, anything else needs an operator."
+                .into(),
+        );
+    }
+    lines
+}
+
+/// Drive the bot through `entry` and merge the resulting chest sync. `Err`
+/// means the shulker is not back in place; the lines already logged say how
+/// far it got.
+pub async fn replay(store: &mut Store, entry: &JournalEntry) -> Result<Vec<String>, String> {
+    let mut report = describe(entry);
+    for line in &report {
+        warn!("[Replay] {}", line);
+    }
+
+    if entry.slot_index >= DOUBLE_CHEST_SLOTS {
+        return Err(format!(
+            "Journal slot {} is outside a {}-slot chest",
+            entry.slot_index, DOUBLE_CHEST_SLOTS
+        ));
+    }
+    let Some(chest) = store
+        .storage
+        .nodes
+        .iter()
+        .flat_map(|n| &n.chests)
+        .find(|c| c.id == entry.chest_id)
+        .cloned()
+    else {
+        return Err(format!(
+            "Chest {} from the journal is not in storage",
+            entry.chest_id
+        ));
+    };
+    let node_position = store.get_node_position(entry.chest_id);
+
+    let (tx, rx) = oneshot::channel();
+    store
+        .bot_tx
+        .send(BotInstruction::ReplayJournalEntry {
+            entry: entry.clone(),
+            target_chest: chest,
+            node_position,
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| format!("Failed to send replay to bot: {}", e))?;
+
+    let outcome =
+        match tokio::time::timeout(tokio::time::Duration::from_secs(CHEST_OP_TIMEOUT_SECS), rx)
+            .await
+        {
+            Ok(Ok(Ok(outcome))) => outcome,
+            Ok(Ok(Err(e))) => return Err(format!("Bot could not finish the replay: {}", e)),
+            Ok(Err(_)) => return Err("Bot dropped the replay response channel".to_string()),
+            Err(_) => {
+                return Err(format!(
+                    "Replay timed out after {}s; the shulker's position is unknown",
+                    CHEST_OP_TIMEOUT_SECS
+                ));
+            }
+        };
+
+    let planned = report.len();
+    report.push(format!(
+        "Found the shulker {}; it is back in chest {} slot {}.",
+        outcome.found_at, entry.chest_id, entry.slot_index
+    ));
+    let counted = outcome.sync.amounts[entry.slot_index];
+    match store.apply_chest_sync(outcome.sync) {
+        Ok(()) => report.push(format!(
+            "Chest {} slot {} re-synced: {} item(s).",
+            entry.chest_id, entry.slot_index, counted
+        )),
+        Err(e) => {
+            let line = format!(
+                "Chest {} re-sync FAILED (the shulker is in place, storage view may be stale): {}",
+                entry.chest_id, e
+            );
+            error!("[Replay] {}", line);
+            report.push(line);
+        }
+    }
+    for line in &report[planned..] {
+        info!("[Replay] {}", line);
+    }
+    Ok(report)
+}
+
+/// Replay the entry held in a journal file, e.g. a `journal.leftover-*.json`
+/// archive. Backs the CLI entry when the operator names a file.
+pub async fn replay_file(store: &mut Store, path: &Path) -> Result<Vec<String>, String> {
+    match journal::read_entry(path) {
+        Ok(Some(entry)) => replay(store, &entry).await,
+        Ok(None) => Ok(vec![format!(
+            "No shulker operation recorded in {}.",
+            path.display()
+        )]),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// The replay plan for a leftover `data/journal.json`, without touching it.
+/// Printed by `cj-store --resume-dry-run` next to the trade plan.
+pub fn dry_run_report() -> Result<Vec<String>, String> {
+    match journal::peek_leftover() {
+        Ok(Some(entry)) => Ok(describe(&entry)),
+        Ok(None) => Ok(vec!["No interrupted shulker operation.".to_string()]),
+        Err(e) => Err(format!(
+            "data/journal.json is unreadable ({}); the bot will quarantine it and nothing is replayed.",
+            e
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{ChestSyncReport, JournalReplayReport};
+    use crate::store::journal::ShulkerLocation;
+    use crate::store::rollback::tests::{make_store, single_node_storage};
+    use tokio::sync::mpsc;
+
+    fn entry(state: JournalState) -> JournalEntry {
+        JournalEntry {
+            operation_id: 3,
+            operation_type: JournalOp::WithdrawFromChest,
+            chest_id: 2,
+            slot_index: 5,
+            state,
+        }
+    }
+
+    /// Answer every replay with the shulker found where the journal state
+    /// says, holding `count` items.
+    fn spawn_replay_bot(mut rx: mpsc::Receiver<BotInstruction>, count: i32) {
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let BotInstruction::ReplayJournalEntry {
+                    entry,
+                    target_chest,
+                    respond_to,
+                    ..
+                } = msg
+                {
+                    let mut amounts = [-1; DOUBLE_CHEST_SLOTS];
+                    amounts[entry.slot_index] = count;
+                    let _ = respond_to.send(Ok(JournalReplayReport {
+                        found_at: entry.state.expected_location(),
+                        sync: ChestSyncReport {
+                            chest_id: target_chest.id,
+                            item: target_chest.item.as_str().to_string(),
+                            amounts,
+                        },
+                    }));
+                }
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn replay_puts_the_slot_count_into_storage() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_replay_bot(rx, 1000);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+
+        let report = replay(&mut store, &entry(JournalState::ItemsTransferred))
+            .await
+            .unwrap();
+        assert!(
+            report
+                .iter()
+                .any(|l| l.contains("Found the shulker on the station"))
+        );
+        let chest = &store.storage.nodes[0].chests[2];
+        assert_eq!(chest.amounts[5], 1000);
+        assert_eq!(chest.amounts[4], 0, "other slots are left alone");
+    }
+
+    #[tokio::test]
+    async fn replay_rejects_a_chest_missing_from_storage() {
+        let (tx, _rx) = mpsc::channel(8);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let mut e = entry(JournalState::ShulkerTaken);
+        e.chest_id = 40;
+        let err = replay(&mut store, &e).await.unwrap_err();
+        assert!(err.contains("not in storage"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn replay_surfaces_a_bot_failure() {
+        let (tx, mut rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let BotInstruction::ReplayJournalEntry { respond_to, .. } = msg {
+                    let _ = respond_to.send(Err("shulker not found".to_string()));
+                }
+            }
+        });
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let err = replay(&mut store, &entry(JournalState::ShulkerPickedUp))
+            .await
+            .unwrap_err();
+        assert!(err.contains("shulker not found"), "{err}");
+        assert_eq!(store.storage.nodes[0].chests[2].amounts[5], 0);
+    }
+
+    #[test]
+    fn describe_points_at_the_expected_location() {
+        let lines = describe(&entry(JournalState::ShulkerTaken));
+        assert!(lines[1].contains(&ShulkerLocation::Inventory.to_string()));
+        assert_eq!(
+            lines.len(),
+            3,
+            "nothing moved before the shulker left the slot"
+        );
+        assert_eq!(describe(&entry(JournalState::ShulkerOnStation)).len(), 4);
+    }
+}
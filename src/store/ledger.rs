@@ -0,0 +1,484 @@
+//! Double-entry ledger of diamond movements.
+//!
+//! Diamonds the store owes or holds live in three places: player balances
+//! (`User::balance`), pair reserves (`Pair::currency_stock`) and the physical
+//! diamond chest. Every change to a balance or a reserve is posted here as
+//! one [`Posting`] that debits one [`Account`] and credits another by the
+//! same amount, tagged with the order being processed. A credit raises an
+//! account and a debit lowers it, so the accounts always sum to zero:
+//!
+//! | Account      | Stands for                                              |
+//! | ------------ | ------------------------------------------------------- |
+//! | `player`     | one `User::balance`                                     |
+//! | `reserve`    | one `Pair::currency_stock`                              |
+//! | `vault`      | diamonds crossing the trade window, into or out of the diamond chest |
+//! | `adjustment` | operator reserve edits no diamonds back (`addcurrency`) |
+//!
+//! `state::audit_state` reconciles sum(balances) + sum(reserves) against the
+//! diamonds counted in storage. When they agree it calls [`Ledger::mark_clean`];
+//! when they do not, the postings since the last clean reconciliation are
+//! where the discrepancy came from.
+//!
+//! Postings and clean marks are appended to `data/ledger.jsonl`. Each clean
+//! mark moves the lines up to it into the day's archive next to the live
+//! file, `ledger-<YYYY-MM-DD>.jsonl`, and restarts the live file with the
+//! mark alone, so [`Ledger::load`] only reads what the last reconciliation
+//! has not covered. Archives are never deleted by the store;
+//! [`Ledger::read_postings`] reads them ahead of the live file for
+//! `--rebuild-ledger`.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{LEDGER_FILE, MAX_PENDING_POSTINGS};
+use crate::fsutil::{append_line, write_atomic};
+
+/// Memo of a player-to-player `pay`. `--rebuild-ledger` checks each
+/// `Transfer` trade against these postings and replays the ones older than
+/// transfers, which have only the posting.
+pub const PAY_MEMO: &str = "pay";
+
+/// Memos of the balance credits a rolled-back trade leaves behind, checked
+/// against `Refund` trades the same way as [`PAY_MEMO`].
+pub const BUY_REFUND_MEMO: &str = "buy/insufficient-payment refund";
+pub const ESCROW_REFUND_MEMO: &str = "escrow/insufficient-payment refund";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Account {
+    /// A player's balance, by UUID.
+    Player(String),
+    /// A pair's diamond reserve, by item.
+    Reserve(String),
+    Vault,
+    Adjustment,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::Player(uuid) => write!(f, "player:{}", uuid),
+            Account::Reserve(item) => write!(f, "reserve:{}", item),
+            Account::Vault => f.write_str("vault"),
+            Account::Adjustment => f.write_str("adjustment"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Posting {
+    pub seq: u64,
+    pub ts: DateTime<Utc>,
+    /// The queued order being processed, `None` for immediate commands
+    /// such as `pay`.
+    pub order_id: Option<u64>,
+    pub debit: Account,
+    pub credit: Account,
+    pub amount: f64,
+    pub memo: String,
+}
+
+impl Posting {
+    pub fn describe(&self) -> String {
+        let order = self
+            .order_id
+            .map_or_else(|| "-".to_string(), |id| format!("#{}", id));
+        format!(
+            "[{}] {} {}: {:.4} {} -> {}",
+            self.ts.format("%Y-%m-%d %H:%M:%S"),
+            order,
+            self.memo,
+            self.amount,
+            self.debit,
+            self.credit
+        )
+    }
+}
+
+/// One line of `data/ledger.jsonl`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum LedgerLine {
+    Posting(Posting),
+    /// Balances and reserves matched the diamonds in storage after every
+    /// posting up to `seq`.
+    Reconciled {
+        seq: u64,
+        ts: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Ledger {
+    /// `None` for test stores: postings are kept in memory only.
+    path: Option<PathBuf>,
+    next_seq: u64,
+    /// Postings since the last clean reconciliation, oldest first, at most
+    /// `MAX_PENDING_POSTINGS`.
+    pending: VecDeque<Posting>,
+    /// Pending postings dropped by the cap since the last clean mark.
+    dropped: u64,
+    last_clean: Option<DateTime<Utc>>,
+}
+
+impl Ledger {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(LEDGER_FILE))
+    }
+
+    /// Path-parameterized load. Lines that do not parse (a write torn by a
+    /// crash) are skipped with a warning; the file is append-only, so one
+    /// bad line must not hide the rest. An unreadable file is left alone and
+    /// new postings are still appended to it: only the in-memory view of
+    /// the postings since the last clean reconciliation starts empty.
+    pub(crate) fn load_from(path: &Path) -> Self {
+        let mut ledger = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return ledger,
+            Err(e) => {
+                error!(
+                    "[Ledger] Failed to read {:?}, starting with no pending postings: {}",
+                    path, e
+                );
+                return ledger;
+            }
+        };
+        for (n, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<LedgerLine>(line) {
+                Ok(LedgerLine::Posting(p)) => {
+                    ledger.next_seq = ledger.next_seq.max(p.seq + 1);
+                    ledger.push_pending(p);
+                }
+                Ok(LedgerLine::Reconciled { seq, ts }) => {
+                    ledger.next_seq = ledger.next_seq.max(seq + 1);
+                    ledger.pending.retain(|p| p.seq > seq);
+                    ledger.dropped = 0;
+                    ledger.last_clean = Some(ts);
+                }
+                Err(e) => warn!("[Ledger] Skipping {:?} line {}: {}", path, n + 1, e),
+            }
+        }
+        info!(
+            "[Ledger] Loaded {} posting(s) since the last clean reconciliation from {:?}",
+            ledger.pending.len() as u64 + ledger.dropped,
+            path
+        );
+        ledger
+    }
+
+    /// Move `amount` diamonds from `debit` to `credit`. A zero amount is not
+    /// posted; a negative or non-finite one is a caller bug and is logged
+    /// instead, since the balances have already moved.
+    pub fn post(
+        &mut self,
+        order_id: Option<u64>,
+        debit: Account,
+        credit: Account,
+        amount: f64,
+        memo: &str,
+    ) {
+        if amount == 0.0 {
+            return;
+        }
+        if !amount.is_finite() || amount < 0.0 {
+            warn!(
+                ?order_id,
+                %debit,
+                %credit,
+                amount,
+                memo,
+                "[Ledger] Refusing to post an invalid amount; reconciliation will show the drift"
+            );
+            return;
+        }
+        let posting = Posting {
+            seq: self.next_seq,
+            ts: Utc::now(),
+            order_id,
+            debit,
+            credit,
+            amount,
+            memo: memo.to_string(),
+        };
+        self.next_seq += 1;
+        self.append(&LedgerLine::Posting(posting.clone()));
+        self.push_pending(posting);
+    }
+
+    /// Record that balances and reserves matched storage. A no-op when
+    /// nothing was posted since the last mark, so audits of an idle store
+    /// do not grow the file.
+    pub fn mark_clean(&mut self) {
+        if self.pending.is_empty() && self.dropped == 0 {
+            return;
+        }
+        let ts = Utc::now();
+        let mark = LedgerLine::Reconciled {
+            seq: self.next_seq.saturating_sub(1),
+            ts,
+        };
+        self.append(&mark);
+        self.rotate(&mark, ts);
+        self.pending.clear();
+        self.dropped = 0;
+        self.last_clean = Some(ts);
+    }
+
+    /// Postings since the last clean reconciliation, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = &Posting> {
+        self.pending.iter()
+    }
+
+    /// Postings since the last clean reconciliation that no longer fit in
+    /// memory; they are still in `data/ledger.jsonl`.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn last_clean(&self) -> Option<DateTime<Utc>> {
+        self.last_clean
+    }
+
+    /// Every posting in the archives of the ledger file at `path` and in the
+    /// file itself, oldest first, skipping lines that do not parse and
+    /// postings already read (a rotation cut short by a crash leaves them in
+    /// both). A missing file has no postings.
+    pub fn read_postings(path: &Path) -> io::Result<Vec<Posting>> {
+        let mut files = archives(path)?;
+        files.push(path.to_path_buf());
+        let mut seen = HashSet::new();
+        let mut postings = Vec::new();
+        for file in files {
+            let contents = match fs::read_to_string(&file) {
+                Ok(s) => s,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for line in contents.lines() {
+                if let Ok(LedgerLine::Posting(p)) = serde_json::from_str::<LedgerLine>(line)
+                    && seen.insert(p.seq)
+                {
+                    postings.push(p);
+                }
+            }
+        }
+        Ok(postings)
+    }
+
+    fn push_pending(&mut self, posting: Posting) {
+        if self.pending.len() >= MAX_PENDING_POSTINGS {
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+        self.pending.push_back(posting);
+    }
+
+    /// Move the live file, which ends with `mark`, into the archive for
+    /// `ts`'s day and restart it with `mark` alone, so the next load picks
+    /// up the sequence from there. The archive is written first: a crash in
+    /// between leaves the lines in both files, never in neither. On failure
+    /// the live file is left as it was and the next mark tries again.
+    fn rotate(&self, mark: &LedgerLine, ts: DateTime<Utc>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let archived = archive_path(path, ts);
+        let result = fs::read_to_string(path)
+            .and_then(|contents| append_line(&archived, contents.trim_end_matches('\n')))
+            .and_then(|()| serde_json::to_string(mark).map_err(io::Error::other))
+            .and_then(|line| write_atomic(path, &format!("{line}\n")));
+        match result {
+            Ok(()) => info!("[Ledger] Archived reconciled postings to {:?}", archived),
+            Err(e) => warn!(
+                path = %path.display(),
+                error = %e,
+                "[Ledger] Failed to archive reconciled postings; the live file keeps them until the next clean mark"
+            ),
+        }
+    }
+
+    fn append(&self, line: &LedgerLine) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(line)
+            .map_err(io::Error::other)
+            .and_then(|s| append_line(path, &s));
+        if let Err(e) = result {
+            warn!(path = %path.display(), error = %e, "[Ledger] Failed to append to the ledger file");
+        }
+    }
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "ledger".to_string())
+}
+
+/// The archive next to `path` for `ts`'s day (UTC).
+fn archive_path(path: &Path, ts: DateTime<Utc>) -> PathBuf {
+    path.with_file_name(format!("{}-{}.jsonl", stem(path), ts.format("%Y-%m-%d")))
+}
+
+/// Archives next to `path`, oldest first.
+fn archives(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => dir,
+        None => Path::new("."),
+    };
+    let prefix = format!("{}-", stem(path));
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut found: Vec<(chrono::NaiveDate, PathBuf)> = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(day) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".jsonl"))
+            .and_then(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        found.push((day, entry.path()));
+    }
+    found.sort();
+    Ok(found.into_iter().map(|(_, p)| p).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(uuid: &str) -> Account {
+        Account::Player(uuid.to_string())
+    }
+
+    #[test]
+    fn load_keeps_only_postings_after_the_last_clean_mark() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut ledger = Ledger::load_from(&path);
+        ledger.post(Some(1), Account::Vault, player("u1"), 10.0, "deposit");
+        ledger.mark_clean();
+        ledger.post(
+            Some(2),
+            player("u1"),
+            Account::Reserve("iron_ingot".into()),
+            4.5,
+            "buy",
+        );
+
+        let reloaded = Ledger::load_from(&path);
+        let pending: Vec<_> = reloaded.pending().collect();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].order_id, Some(2));
+        assert_eq!(pending[0].seq, 1);
+        assert!(reloaded.last_clean().is_some());
+        assert_eq!(reloaded.next_seq, 2, "sequence numbers continue");
+    }
+
+    #[test]
+    fn mark_clean_archives_reconciled_postings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut ledger = Ledger::load_from(&path);
+        ledger.post(Some(1), Account::Vault, player("u1"), 10.0, "deposit");
+        ledger.mark_clean();
+        ledger.post(Some(2), player("u1"), Account::Vault, 4.0, "withdraw");
+        ledger.mark_clean();
+
+        let live = fs::read_to_string(&path).unwrap();
+        assert_eq!(live.lines().count(), 1, "only the last mark stays live");
+        assert_eq!(archives(&path).unwrap().len(), 1, "one archive per day");
+        let seqs: Vec<u64> = Ledger::read_postings(&path)
+            .unwrap()
+            .iter()
+            .map(|p| p.seq)
+            .collect();
+        assert_eq!(seqs, vec![0, 1], "rebuild still sees every posting");
+
+        let reloaded = Ledger::load_from(&path);
+        assert_eq!(reloaded.pending().count(), 0);
+        assert_eq!(reloaded.next_seq, 2, "sequence numbers continue");
+    }
+
+    #[test]
+    fn read_postings_skips_lines_a_cut_short_rotation_left_in_both_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut ledger = Ledger::load_from(&path);
+        ledger.post(None, Account::Vault, player("u1"), 1.0, "deposit");
+        // The archive was written but the live file was not restarted.
+        let contents = fs::read_to_string(&path).unwrap();
+        append_line(
+            &archive_path(&path, Utc::now()),
+            contents.trim_end_matches('\n'),
+        )
+        .unwrap();
+        ledger.post(None, Account::Vault, player("u1"), 2.0, "deposit");
+
+        assert_eq!(Ledger::read_postings(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn mark_clean_without_postings_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut ledger = Ledger::load_from(&path);
+        ledger.mark_clean();
+        assert!(!path.exists());
+        assert!(ledger.last_clean().is_none());
+    }
+
+    #[test]
+    fn zero_and_invalid_amounts_are_not_posted() {
+        let mut ledger = Ledger::default();
+        ledger.post(None, Account::Vault, player("u1"), 0.0, "zero");
+        ledger.post(None, Account::Vault, player("u1"), -1.0, "negative");
+        ledger.post(None, Account::Vault, player("u1"), f64::NAN, "nan");
+        assert_eq!(ledger.pending().count(), 0);
+    }
+
+    #[test]
+    fn pending_is_capped_and_counts_what_it_drops() {
+        let mut ledger = Ledger::default();
+        for _ in 0..MAX_PENDING_POSTINGS + 3 {
+            ledger.post(None, Account::Adjustment, Account::Vault, 1.0, "x");
+        }
+        assert_eq!(ledger.pending().count(), MAX_PENDING_POSTINGS);
+        assert_eq!(ledger.dropped(), 3);
+        ledger.mark_clean();
+        assert_eq!(ledger.dropped(), 0);
+    }
+
+    #[test]
+    fn load_skips_a_torn_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut ledger = Ledger::load_from(&path);
+        ledger.post(None, Account::Vault, player("u1"), 1.0, "deposit");
+        append_line(&path, "{\"kind\":\"posting\",\"seq\":").unwrap();
+        ledger.post(None, Account::Vault, player("u1"), 2.0, "deposit");
+
+        assert_eq!(Ledger::load_from(&path).pending().count(), 2);
+    }
+}
@@ -7,14 +7,28 @@
 //! - Trades (persistent audit log of completed operations)
 //! - Storage (nodes, chests, shulker contents)
 
+pub mod alerts;
+pub mod audit_log;
 pub mod command;
+pub mod curve;
+pub mod escrow;
 pub mod handlers;
 pub mod journal;
+pub mod journal_replay;
+pub mod ledger;
+pub mod notices;
+pub mod order_book;
 pub mod orders;
+pub mod pay_requests;
+pub mod price_history;
 pub mod pricing;
 pub mod queue;
+pub mod quotes;
 pub mod rate_limit;
+pub mod rebuild;
+pub mod resume;
 pub mod rollback;
+pub mod snapshot;
 pub mod state;
 pub mod trade_state;
 pub mod utils;
@@ -26,8 +40,17 @@ use tracing::{debug, error, info, warn};
 
 use crate::config::Config;
 use crate::messages::{BotInstruction, BotMessage, ChestSyncReport, StoreMessage};
-use crate::types::{ItemId, Order, Pair, Storage, Trade, User};
-
+use crate::types::{ItemId, Order, Pair, PairStats, Storage, Trade, User};
+
+use self::alerts::Alerts;
+use self::audit_log::{AuditEvent, AuditLog};
+use self::escrow::EscrowBook;
+use self::ledger::{Account, Ledger};
+use self::notices::Notices;
+use self::order_book::OrderBook;
+use self::pay_requests::PayRequests;
+use self::price_history::PriceHistory;
+use self::quotes::QuoteBook;
 use self::queue::OrderQueue;
 use self::rate_limit::RateLimiter;
 
@@ -76,6 +99,19 @@ pub struct Store {
 
     /// Queue of pending orders waiting to be processed
     pub order_queue: OrderQueue,
+    /// Resting limit orders, promoted into `order_queue` when their price
+    /// is reached (see `handlers::limit::sweep`)
+    pub order_book: OrderBook,
+    /// Unconfirmed `quote` price locks; in-memory only, expire in seconds
+    pub quotes: QuoteBook,
+    /// Open player-to-player payment requests (`pay_requests`)
+    pub pay_requests: PayRequests,
+    /// Open player-to-player escrows (`escrow`)
+    pub escrow: EscrowBook,
+    /// Open price alerts and restock notifications (`alerts`)
+    pub alerts: Alerts,
+    /// Hourly/daily OHLC candles per item, fed by every committed trade
+    pub price_history: PriceHistory,
     /// Rate limiter for anti-spam protection
     pub rate_limiter: RateLimiter,
     /// Flag to prevent concurrent order processing
@@ -84,6 +120,19 @@ pub struct Store {
     /// `None` when idle; set to `Some(TradeState::Queued(..))` when an order
     /// is popped and advanced through phases until a terminal state.
     pub current_trade: Option<trade_state::TradeState>,
+    /// Non-terminal trade left over from a crash, found by `Store::new` and
+    /// handed to `resume::resume` when `run` starts. `None` once handled.
+    pub interrupted_trade: Option<trade_state::TradeState>,
+    /// Shulker operation left half-done by a crash, read by `Store::new`
+    /// and replayed when `run` starts. Kept if that replay fails so the CLI
+    /// can retry it.
+    pub interrupted_journal: Option<journal::JournalEntry>,
+    /// Whispers held for players until their next message (`notices`)
+    pub notices: Notices,
+    /// Order lifecycle events (`audit_log`); the queue holds a clone
+    pub audit_log: AuditLog,
+    /// Double-entry postings for every balance and reserve change (`ledger`)
+    pub ledger: Ledger,
 }
 
 impl Store {
@@ -127,7 +176,7 @@ impl Store {
             pair.item = item_id;
             normalized_pairs.insert(normalized_item, pair);
         }
-        let pairs = normalized_pairs;
+        let mut pairs = normalized_pairs;
 
         let users = User::load_all()?;
 
@@ -179,6 +228,22 @@ impl Store {
         let orders = std::collections::VecDeque::new();
 
         let trades = Trade::load_all_with_limit(config.max_trades_in_memory)?;
+
+        // Pairs that predate `Pair::stats` start from whatever history is
+        // still loaded. Fees are missing for trades recorded before
+        // `Trade::fee` existed; see `PairStats`.
+        for pair in pairs.values_mut().filter(|p| p.stats.is_empty()) {
+            let stats = PairStats::from_trades(pair.item.as_str(), &trades);
+            if !stats.is_empty() {
+                info!(
+                    "Backfilled stats for pair '{}' from {} loaded trade(s)",
+                    pair.item,
+                    stats.trade_count()
+                );
+                pair.stats = stats;
+                needs_save = true;
+            }
+        }
         let mut storage =
             Storage::load(&config.position).map_err(|e| io::Error::other(e.to_string()))?;
 
@@ -195,7 +260,8 @@ impl Store {
         // bad file to a `queue.json.{corrupt,unreadable}-<unix_ms>-<seq>.json`
         // sibling and returns Ok(empty) so the raw bytes survive for forensic
         // recovery before the next `save()` overwrites the active path.
-        let order_queue = match OrderQueue::load() {
+        let audit_log = AuditLog::open();
+        let mut order_queue = match OrderQueue::load() {
             Ok(queue) => queue,
             Err(e) => {
                 error!(
@@ -205,6 +271,69 @@ impl Store {
                 OrderQueue::new()
             }
         };
+        order_queue.set_audit_log(audit_log.clone());
+
+        // Same quarantine-and-continue contract as the queue above.
+        let order_book = match OrderBook::load() {
+            Ok(book) => book,
+            Err(e) => {
+                error!(
+                    "RESTING LIMIT ORDERS LOST: failed to load order book, starting fresh: {}",
+                    e
+                );
+                OrderBook::new()
+            }
+        };
+
+        let price_history = PriceHistory::load_or_rebuild(&trades);
+
+        // A corrupt file is quarantined inside `load`; only an unreadable
+        // one lands here, and is left alone rather than overwritten.
+        let pay_requests = match PayRequests::load() {
+            Ok(requests) => requests,
+            Err(e) => {
+                error!(
+                    "OPEN PAYMENT REQUESTS LOST: failed to load payment requests, new ones are kept in memory only: {}",
+                    e
+                );
+                PayRequests::default()
+            }
+        };
+
+        let escrow = match EscrowBook::load() {
+            Ok(book) => book,
+            Err(e) => {
+                error!(
+                    "OPEN ESCROWS LOST: failed to load escrows, new ones are kept in memory only: {}",
+                    e
+                );
+                EscrowBook::default()
+            }
+        };
+
+        let alerts = match Alerts::load() {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                error!(
+                    "ALERTS LOST: failed to load alerts, new ones are kept in memory only: {}",
+                    e
+                );
+                Alerts::default()
+            }
+        };
+
+        let notices = match Notices::load() {
+            Ok(notices) => notices,
+            Err(e) => {
+                error!(
+                    "HELD PLAYER NOTICES LOST: failed to load notices, starting fresh: {}",
+                    e
+                );
+                Notices::new()
+            }
+        };
+
+        let ledger = Ledger::load();
 
         let rate_limiter = RateLimiter::new();
 
@@ -212,11 +341,11 @@ impl Store {
         // We surface the incident loudly and ARCHIVE the file (rename to a
         // timestamped sibling) rather than deleting it: a leftover trade
         // state is the highest-stakes piece of crash evidence and must
-        // survive the next startup so an operator can reconcile. Automatic
-        // recovery (rollback/re-queue) is deliberately out of scope here
-        // because it would need to touch physical chests and trade state,
-        // which must be done with the operator in the loop. We do NOT block
+        // survive the next startup so an operator can reconcile. A
+        // non-terminal state is kept for `resume::resume`, which needs the
+        // bot and so runs at the top of `run`, not here. We do NOT block
         // startup: auto-restart deployments would crash-loop forever.
+        let mut interrupted_trade = None;
         match trade_state::load_persisted() {
             Ok(Some(state)) => {
                 let phase = state.phase();
@@ -246,9 +375,10 @@ impl Store {
                     _ => {
                         tracing::error!(
                             "Found interrupted trade on startup: {}. The previous session crashed mid-trade - \
-                             operator should inspect in-world state (bot inventory, chests, player) before resuming.",
+                             crash-resume will act on it when the store starts; check its [Resume] report.",
                             state
                         );
+                        interrupted_trade = Some(state.clone());
                     }
                 }
                 match trade_state::archive_persisted() {
@@ -276,6 +406,15 @@ impl Store {
             ),
         }
 
+        // Read-only: the bot task archives the same leftover when it starts.
+        let interrupted_journal = match journal::peek_leftover() {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Failed to read leftover journal for replay: {} - the bot will quarantine it", e);
+                None
+            }
+        };
+
         info!(
             "Store initialized successfully with {} pairs, {} users, {} orders, {} trades, {} nodes",
             pairs.len(),
@@ -302,9 +441,20 @@ impl Store {
             saved_trades_count,
             bot_tx,
             order_queue,
+            order_book,
+            quotes: QuoteBook::new(),
+            pay_requests,
+            escrow,
+            alerts,
+            price_history,
             rate_limiter,
             processing_order: false,
             current_trade: None,
+            interrupted_trade,
+            interrupted_journal,
+            notices,
+            audit_log,
+            ledger,
         })
     }
 
@@ -335,6 +485,14 @@ impl Store {
         );
         let mut last_save = tokio::time::Instant::now();
         let mut last_cleanup = tokio::time::Instant::now();
+        let mut last_book_sweep = tokio::time::Instant::now();
+        // `None` until the first run so startup compacts whatever closed
+        // while the store was down.
+        let mut last_compaction: Option<tokio::time::Instant> = None;
+        let mut compaction: Option<tokio::task::JoinHandle<()>> = None;
+        // The first scheduled snapshot waits one interval, so a crash loop
+        // cannot rotate the good snapshots out.
+        let mut last_snapshot = tokio::time::Instant::now();
         // Throttle repeated autosave-failure log lines so a persistent ENOSPC
         // or permissions issue doesn't flood the log at one error per
         // autosave_interval_secs. We still retry every interval (to flush as
@@ -343,6 +501,8 @@ impl Store {
         let save_error_log_interval = tokio::time::Duration::from_secs(600);
         let cleanup_interval =
             tokio::time::Duration::from_secs(crate::constants::CLEANUP_INTERVAL_SECS);
+        let book_sweep_interval =
+            tokio::time::Duration::from_secs(crate::constants::ORDER_BOOK_SWEEP_INTERVAL_SECS);
         let rate_limit_stale_after =
             std::time::Duration::from_secs(crate::constants::RATE_LIMIT_STALE_AFTER_SECS);
         // Re-read each iteration so hot-reload of `autosave_interval_secs`
@@ -350,6 +510,25 @@ impl Store {
         let mut min_save_interval =
             tokio::time::Duration::from_secs(self.config.autosave_interval_secs);
 
+        // Put a stranded shulker back first: crash-resume below may deposit
+        // into the same chest and needs every slot where the plan expects it.
+        if let Some(entry) = self.interrupted_journal.clone() {
+            match journal_replay::replay(&mut self, &entry).await {
+                Ok(_) => self.interrupted_journal = None,
+                Err(e) => error!(
+                    "[Replay] Shulker op #{} NOT replayed: {} - retry from the CLI (\"Replay interrupted shulker op\") or follow RECOVERY.md section 2",
+                    entry.operation_id, e
+                ),
+            }
+        }
+
+        // Settle a trade the previous process left mid-flight before taking
+        // new orders, so a re-queued order keeps its place at the front.
+        if let Some(state) = self.interrupted_trade.take() {
+            let report = resume::resume(&mut self, state).await;
+            info!("[Store] Crash-resume finished ({} report line(s))", report.len());
+        }
+
         // Each iteration either drains one order from the queue OR blocks on
         // one incoming message — never both concurrently. Orders take strict
         // priority (PRIORITY 1 below) so an in-flight trade cannot be starved
@@ -378,10 +557,55 @@ impl Store {
             if last_cleanup.elapsed() >= cleanup_interval {
                 self.rate_limiter.cleanup_stale(rate_limit_stale_after);
                 crate::mojang::cleanup_uuid_cache();
+                self.quotes.purge_expired(chrono::Utc::now());
+                if !self.pay_requests.is_empty() {
+                    handlers::pay_request::sweep(&mut self).await;
+                }
+                if !self.escrow.is_empty() {
+                    handlers::escrow::sweep(&mut self).await;
+                }
+                if !self.alerts.is_empty() {
+                    handlers::alert::sweep(&mut self).await;
+                }
                 debug!("[Store] Periodic cleanup completed");
                 last_cleanup = tokio::time::Instant::now();
             }
 
+            // Roll closed days of trade files into segments off the store
+            // task. At most one run at a time; a run overlapping an autosave
+            // is safe because it only touches files of days already closed.
+            let compaction_due = last_compaction.is_none_or(|t| {
+                t.elapsed()
+                    >= tokio::time::Duration::from_secs(
+                        crate::constants::TRADE_COMPACTION_INTERVAL_SECS,
+                    )
+            });
+            if compaction_due && compaction.as_ref().is_none_or(|h| h.is_finished()) {
+                let retention = self.config.trade_retention_days;
+                compaction = Some(tokio::task::spawn_blocking(move || {
+                    let today = chrono::Utc::now().date_naive();
+                    match crate::types::trade_segment::compact(today, retention) {
+                        Ok(r) if r == Default::default() => {
+                            debug!("[Store] Trade compaction: nothing to do")
+                        }
+                        Ok(r) => info!(
+                            "[Store] Trade compaction: {} file(s) into {} day segment(s), {} segment(s) archived",
+                            r.files_compacted, r.days_compacted, r.segments_archived
+                        ),
+                        Err(e) => error!("[Store] Trade compaction failed: {}", e),
+                    }
+                }));
+                last_compaction = Some(tokio::time::Instant::now());
+            }
+
+            // Expire and promote resting limit orders. Reserves only move when
+            // an order executes (swept below) or an operator edits a pair, so
+            // the timer mainly serves expiry and operator-driven moves.
+            if !self.order_book.is_empty() && last_book_sweep.elapsed() >= book_sweep_interval {
+                handlers::limit::sweep(&mut self).await;
+                last_book_sweep = tokio::time::Instant::now();
+            }
+
             // Idle autosave: if the loop has been sitting on `recv()` while a
             // prior order left `dirty = true`, the message-branch debounced
             // autosave never runs. The timer arm in PRIORITY 2 falls through
@@ -415,6 +639,20 @@ impl Store {
                 }
             }
 
+            // Scheduled snapshot of data/. Runs here, between orders, so the
+            // copy sees no half-applied order; `snapshot::take` flushes any
+            // pending save first and this task waits out the copy, which
+            // runs on the blocking pool.
+            if let Some(secs) = self.config.snapshot_interval_secs
+                && !self.processing_order
+                && last_snapshot.elapsed() >= tokio::time::Duration::from_secs(secs)
+            {
+                if let Err(e) = snapshot::take(&mut self, "scheduled").await {
+                    error!("[Store] Scheduled snapshot failed: {}", e);
+                }
+                last_snapshot = tokio::time::Instant::now();
+            }
+
             // PRIORITY 1: drain an order if one is waiting.
             if !self.processing_order && !self.order_queue.is_empty() {
                 debug!(
@@ -473,6 +711,17 @@ impl Store {
                     }
                 }
 
+                // The order just changed reserves; promote any limit orders
+                // it pushed across their price before taking the next one,
+                // and fire any alerts it tripped.
+                if !self.order_book.is_empty() {
+                    handlers::limit::sweep(&mut self).await;
+                    last_book_sweep = tokio::time::Instant::now();
+                }
+                if !self.alerts.is_empty() {
+                    handlers::alert::sweep(&mut self).await;
+                }
+
                 // Between orders, non-blockingly drain any pending messages so
                 // operator Shutdown/ClearStuckOrder (and anything else) is not
                 // starved behind a long queue. Without this, a backlog of N
@@ -505,7 +754,11 @@ impl Store {
             // of the loop about dropped oneshot receivers).
             let time_to_autosave = min_save_interval.saturating_sub(last_save.elapsed());
             let time_to_cleanup = cleanup_interval.saturating_sub(last_cleanup.elapsed());
-            let wake_after = std::cmp::min(time_to_autosave, time_to_cleanup);
+            let mut wake_after = std::cmp::min(time_to_autosave, time_to_cleanup);
+            if !self.order_book.is_empty() {
+                wake_after =
+                    wake_after.min(book_sweep_interval.saturating_sub(last_book_sweep.elapsed()));
+            }
 
             let msg = tokio::select! {
                 m = store_rx.recv() => m,
@@ -649,6 +902,12 @@ impl Store {
 
         self.processing_order = true;
         self.current_trade = Some(queued_state);
+        self.audit_log.record(
+            order.id,
+            AuditEvent::Started {
+                description: order.description(),
+            },
+        );
 
         let started = std::time::Instant::now();
         info!(
@@ -685,6 +944,11 @@ impl Store {
                 "order processing failed"
             ),
         }
+        let (ok, detail) = match &result {
+            Ok(summary) => (true, summary.clone()),
+            Err(error_msg) => (false, error_msg.to_string()),
+        };
+        self.audit_log.record(order.id, AuditEvent::Finished { ok, detail });
 
         self.processing_order = false;
         // If the handler returned without driving the trade state machine to
@@ -745,6 +1009,8 @@ impl Store {
     /// Hot-reloadable:
     /// - `fee` — next priced order uses the new rate.
     /// - `autosave_interval_secs` — next loop iteration uses the new debounce.
+    /// - `trade_retention_days` — next trade-history compaction uses it.
+    /// - `snapshot_interval_secs`, `snapshot_keep` — next snapshot check uses them.
     ///
     /// Restart-required (warns on change):
     /// - `trade_timeout_ms`, `pathfinding_timeout_ms` — cached in bot task.
@@ -765,6 +1031,27 @@ impl Store {
             ));
             self.config.autosave_interval_secs = new.autosave_interval_secs;
         }
+        if self.config.trade_retention_days != new.trade_retention_days {
+            applied.push(format!(
+                "trade_retention_days {:?} -> {:?}",
+                self.config.trade_retention_days, new.trade_retention_days
+            ));
+            self.config.trade_retention_days = new.trade_retention_days;
+        }
+        if self.config.snapshot_interval_secs != new.snapshot_interval_secs {
+            applied.push(format!(
+                "snapshot_interval_secs {:?} -> {:?}",
+                self.config.snapshot_interval_secs, new.snapshot_interval_secs
+            ));
+            self.config.snapshot_interval_secs = new.snapshot_interval_secs;
+        }
+        if self.config.snapshot_keep != new.snapshot_keep {
+            applied.push(format!(
+                "snapshot_keep {} -> {}",
+                self.config.snapshot_keep, new.snapshot_keep
+            ));
+            self.config.snapshot_keep = new.snapshot_keep;
+        }
 
         // Warn on restart-only fields that were edited.
         if self.config.trade_timeout_ms != new.trade_timeout_ms {
@@ -811,6 +1098,13 @@ impl Store {
         }
     }
 
+    /// Post a balance or reserve change to the ledger under the order being
+    /// processed, if any. Call it next to the mutation it records.
+    pub(crate) fn post_ledger(&mut self, debit: Account, credit: Account, amount: f64, memo: &str) {
+        let order_id = self.current_trade.as_ref().map(|t| t.order().id);
+        self.ledger.post(order_id, debit, credit, amount, memo);
+    }
+
     /// Advance the in-flight trade through the state machine.
     ///
     /// Takes a closure that receives the current `TradeState` by value and
@@ -843,6 +1137,17 @@ impl Store {
                         phase = next.phase(),
                         "trade state advanced"
                     );
+                    let detail = match &next {
+                        trade_state::TradeState::RolledBack { reason, .. } => Some(reason.clone()),
+                        _ => None,
+                    };
+                    self.audit_log.record(
+                        order.id,
+                        AuditEvent::Phase {
+                            phase: next.phase().to_string(),
+                            detail,
+                        },
+                    );
                     // Mirror the new phase to disk so a crash between here
                     // and the next transition leaves enough information on
                     // disk for the operator to detect and investigate on
@@ -894,6 +1199,10 @@ impl Store {
                 info!("Processing command from {}: {}", player_name, command);
                 handlers::player::handle_player_command(self, &player_name, &command).await
             }
+            BotMessage::PlayerJoined { player_name } => {
+                handlers::player::handle_player_joined(self, &player_name).await;
+                Ok(())
+            }
         }
     }
 
@@ -1004,9 +1313,20 @@ impl Store {
             saved_trades_count: 0,
             bot_tx,
             order_queue: queue::OrderQueue::new(),
+            order_book: order_book::OrderBook::new(),
+            quotes: quotes::QuoteBook::new(),
+            pay_requests: PayRequests::default(),
+            escrow: EscrowBook::default(),
+            alerts: Alerts::default(),
+            price_history: PriceHistory::new(),
             rate_limiter: RateLimiter::new(),
             processing_order: false,
             current_trade: None,
+            interrupted_trade: None,
+            interrupted_journal: None,
+            notices: Notices::new(),
+            audit_log: AuditLog::disabled(),
+            ledger: Ledger::default(),
         }
     }
 }
@@ -1043,6 +1363,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: 42,
                 currency_stock: 3.5,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
         let mut users = HashMap::new();
@@ -1053,6 +1378,7 @@ mod tests {
                 username: "alice".to_string(),
                 balance: 10.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
 
@@ -1080,6 +1406,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: 7,
                 currency_stock: 1.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
         let store = make_store(pairs, HashMap::new());
@@ -1136,6 +1467,7 @@ mod tests {
                 username: "alice".to_string(),
                 balance: 5.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let mut store = make_store(HashMap::new(), users);
@@ -0,0 +1,200 @@
+//! Whispers held for a player until their next message to the bot or join.
+//!
+//! Some outcomes are decided while the player is not around to hear them,
+//! for example an order cancelled by crash-resume at startup. They are held
+//! here per UUID and whispered by `utils::deliver_held_notices` the next
+//! time that player whispers the bot or is seen joining. The file is
+//! rewritten on every mutation so a restart neither drops nor repeats a
+//! notice.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{MAX_NOTICES_PER_USER, NOTICES_FILE};
+use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
+
+/// Per-process disambiguator for quarantined notice files. Mirrors the
+/// same-named static in `queue.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notice {
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Held notices keyed by user UUID, oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Notices {
+    by_user: BTreeMap<String, Vec<Notice>>,
+}
+
+impl Notices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(Path::new(NOTICES_FILE))
+    }
+
+    /// Path-parameterized load. A corrupt file is quarantined to a
+    /// `notices.corrupt-<unix_ms>-<seq>.json` sibling and the store starts
+    /// with no held notices, like the queue and the order book.
+    pub(crate) fn load_from(path: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+        match serde_json::from_str::<Notices>(&contents) {
+            Ok(notices) => {
+                info!(
+                    "[Notices] Loaded {} held notice(s) from {:?}",
+                    notices.len(),
+                    path
+                );
+                Ok(notices)
+            }
+            Err(parse_err) => {
+                let archived = Self::quarantine(path)?;
+                error!(
+                    "[Notices] HELD NOTICES LOST: corrupt {:?} moved to {:?}; parse error: {}",
+                    path, archived, parse_err
+                );
+                Ok(Self::new())
+            }
+        }
+    }
+
+    fn quarantine(path: &Path) -> io::Result<PathBuf> {
+        let archived = pick_archive_path(path.parent(), "notices", "corrupt", &ARCHIVE_SEQ)?;
+        archive_aside(path, &archived)?;
+        Ok(archived)
+    }
+
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, &json)
+    }
+
+    /// Hold `message` for `user_uuid` in the file at `path` (`NOTICES_FILE`
+    /// in production), dropping their oldest notice past
+    /// `MAX_NOTICES_PER_USER`. A save failure is logged and the notice kept
+    /// in memory: losing it on a later crash is no worse than never holding
+    /// it.
+    pub(crate) fn push_at(&mut self, user_uuid: &str, message: String, path: &Path) {
+        let held = self.by_user.entry(user_uuid.to_string()).or_default();
+        held.push(Notice {
+            message,
+            created_at: Utc::now(),
+        });
+        if held.len() > MAX_NOTICES_PER_USER {
+            let excess = held.len() - MAX_NOTICES_PER_USER;
+            held.drain(..excess);
+            warn!(
+                "[Notices] Dropped {} oldest notice(s) for {} past the per-user cap",
+                excess, user_uuid
+            );
+        }
+        if let Err(e) = self.save_to(path) {
+            warn!("[Notices] Failed to persist notices: {}", e);
+        }
+    }
+
+    /// Hold `message` for `user_uuid` in `NOTICES_FILE`; see `push_at`.
+    pub fn push(&mut self, user_uuid: &str, message: String) {
+        self.push_at(user_uuid, message, Path::new(NOTICES_FILE))
+    }
+
+    /// Remove and return everything held for `user_uuid`, oldest first.
+    pub fn take(&mut self, user_uuid: &str) -> Vec<Notice> {
+        self.take_at(user_uuid, Path::new(NOTICES_FILE))
+    }
+
+    fn take_at(&mut self, user_uuid: &str, path: &Path) -> Vec<Notice> {
+        let Some(held) = self.by_user.remove(user_uuid) else {
+            return Vec::new();
+        };
+        if let Err(e) = self.save_to(path) {
+            // Delivering anyway risks a repeat after a crash, which beats
+            // holding the notice back until the disk recovers.
+            warn!("[Notices] Failed to persist notices after delivery: {}", e);
+        }
+        held
+    }
+
+    /// Notices held for `user_uuid`.
+    #[cfg(test)]
+    pub(crate) fn pending(&self, user_uuid: &str) -> &[Notice] {
+        self.by_user.get(user_uuid).map_or(&[], Vec::as_slice)
+    }
+
+    /// Total notices held across all users.
+    fn len(&self) -> usize {
+        self.by_user.values().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_take_round_trip_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notices.json");
+        let mut notices = Notices::new();
+        notices.push_at("u1", "first".into(), &path);
+        notices.push_at("u1", "second".into(), &path);
+        notices.push_at("u2", "other".into(), &path);
+
+        let mut loaded = Notices::load_from(&path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        let taken: Vec<String> = loaded
+            .take_at("u1", &path)
+            .into_iter()
+            .map(|n| n.message)
+            .collect();
+        assert_eq!(taken, ["first", "second"]);
+        assert!(loaded.take_at("u1", &path).is_empty());
+
+        let reloaded = Notices::load_from(&path).unwrap();
+        assert!(reloaded.pending("u1").is_empty());
+        assert_eq!(reloaded.pending("u2").len(), 1);
+    }
+
+    #[test]
+    fn push_drops_oldest_past_the_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notices.json");
+        let mut notices = Notices::new();
+        for i in 0..MAX_NOTICES_PER_USER + 2 {
+            notices.push_at("u1", format!("n{i}"), &path);
+        }
+        let held = notices.pending("u1");
+        assert_eq!(held.len(), MAX_NOTICES_PER_USER);
+        assert_eq!(held[0].message, "n2");
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notices.json");
+        fs::write(&path, "{ not json").unwrap();
+        assert!(Notices::load_from(&path).unwrap().by_user.is_empty());
+        assert!(!path.exists());
+        let archived = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().contains(".corrupt-"));
+        assert!(archived);
+    }
+}
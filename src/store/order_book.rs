@@ -0,0 +1,697 @@
+//! Persistent book of resting limit orders.
+//!
+//! `buy <item> <qty> limit <price>` and `sell <item> <qty> limit <price>` do
+//! not go straight into the [`OrderQueue`](super::queue::OrderQueue). They
+//! rest here until the average per-item price for their quantity crosses the
+//! limit, at which point `handlers::limit::sweep` moves them into the queue as an
+//! ordinary buy/sell. Orders that never cross are dropped at `expires_at`.
+//!
+//! The book is persisted on every mutation to `ORDER_BOOK_FILE` (next to
+//! `data/queue.json`) using the same save-before-commit and
+//! quarantine-on-corruption rules as the queue, so a restart never loses or
+//! resurrects a resting order.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{MAX_LIMIT_ORDERS_PER_USER, MAX_ORDER_BOOK_SIZE, ORDER_BOOK_FILE};
+use crate::fsutil::{archive_aside, write_atomic};
+
+/// Per-process disambiguator for order-book archive filenames. Mirrors the
+/// same-named static in `queue.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Which side of the pair an order trades on, from the player's point of
+/// view. Shared by limit orders and quotes.
+///
+/// Persisted inside [`LimitOrder`]; variant renames are a format break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    pub fn verb(self) -> &'static str {
+        match self {
+            OrderSide::Buy => "buy",
+            OrderSide::Sell => "sell",
+        }
+    }
+}
+
+/// A limit order waiting for the market to reach `limit_price`.
+///
+/// Serialized as part of the on-disk book (see [`OrderBookPersist`]); any
+/// field rename is a persisted-format break.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitOrder {
+    pub id: u64,
+    pub user_uuid: String,
+    pub username: String,
+    pub side: OrderSide,
+    pub item: String,
+    pub quantity: u32,
+    /// Per-item price bound in diamonds: the most a buyer will pay, or the
+    /// least a seller will accept.
+    pub limit_price: f64,
+    pub placed_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl LimitOrder {
+    /// Short human-readable summary used in player messages and log lines.
+    pub fn description(&self) -> String {
+        match self.side {
+            OrderSide::Buy => format!(
+                "buy {} {} at <= {:.4}/ea",
+                self.item, self.quantity, self.limit_price
+            ),
+            OrderSide::Sell => format!(
+                "sell {} {} at >= {:.4}/ea",
+                self.item, self.quantity, self.limit_price
+            ),
+        }
+    }
+
+    /// `true` when a per-item price of `price` satisfies this order's limit:
+    /// at or below the limit for a buy, at or above it for a sell.
+    pub fn is_crossed_by(&self, price: f64) -> bool {
+        if !price.is_finite() {
+            return false;
+        }
+        match self.side {
+            OrderSide::Buy => price <= self.limit_price,
+            OrderSide::Sell => price >= self.limit_price,
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug)]
+pub struct OrderBook {
+    orders: Vec<LimitOrder>,
+    /// Monotonic limit-order ID counter; persisted so IDs don't recycle
+    /// across restarts. Independent of the queue's counter — limit orders
+    /// are shown to players as `L<id>` so the two ID spaces never collide.
+    next_id: u64,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self {
+            orders: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Load the book from `ORDER_BOOK_FILE`, or return an empty book if the
+    /// file is absent.
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(ORDER_BOOK_FILE)
+    }
+
+    /// Path-parameterized load. A corrupt or unreadable file is quarantined
+    /// to an `order_book.json.{corrupt,unreadable}-<unix_ms>-<seq>.json`
+    /// sibling and an empty book is returned, exactly like
+    /// `OrderQueue::load_from`.
+    pub(crate) fn load_from(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            info!("[OrderBook] No order book at {:?}, starting empty", path);
+            return Ok(Self::new());
+        }
+
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(read_err) => {
+                return match Self::quarantine_to(path, "unreadable") {
+                    Ok(archived) => {
+                        error!(
+                            "[OrderBook] RESTING LIMIT ORDERS LOST: quarantined unreadable order book {:?} to {:?} ({})",
+                            path, archived, read_err
+                        );
+                        Ok(Self::new())
+                    }
+                    Err(quarantine_err) => {
+                        error!(
+                            "[OrderBook] could not quarantine unreadable order book {:?}: {quarantine_err}",
+                            path
+                        );
+                        Err(read_err)
+                    }
+                };
+            }
+        };
+        let data: OrderBookPersist = match serde_json::from_str(&contents) {
+            Ok(d) => d,
+            Err(e) => {
+                return match Self::quarantine_to(path, "corrupt") {
+                    Ok(archived) => {
+                        error!(
+                            "[OrderBook] RESTING LIMIT ORDERS LOST: corrupt order book {:?} moved to {:?}; parse error: {}",
+                            path, archived, e
+                        );
+                        Ok(Self::new())
+                    }
+                    Err(quarantine_err) => {
+                        error!(
+                            "[OrderBook] corrupt order book {:?}; parse error: {}; quarantine also failed: {}",
+                            path, e, quarantine_err
+                        );
+                        Err(io::Error::new(io::ErrorKind::InvalidData, e))
+                    }
+                };
+            }
+        };
+
+        info!(
+            "[OrderBook] Loaded {} resting limit orders from {:?} (next_id={})",
+            data.orders.len(),
+            path,
+            data.next_id
+        );
+        Ok(Self {
+            orders: data.orders,
+            next_id: data.next_id,
+        })
+    }
+
+    fn quarantine_to(path: &Path, kind: &str) -> io::Result<PathBuf> {
+        let unix_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let seq = ARCHIVE_SEQ.fetch_add(1, Ordering::Relaxed);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "order_book.json".to_string());
+        let archived_name = format!("{file_name}.{kind}-{unix_ms}-{seq}.json");
+        let archived = match path.parent() {
+            Some(parent) => parent.join(archived_name),
+            None => PathBuf::from(archived_name),
+        };
+        archive_aside(path, &archived)?;
+        Ok(archived)
+    }
+
+    /// Serialize `orders` (which may differ from `self.orders` when the
+    /// caller is persisting a projected state) with the current `next_id`.
+    fn write_projection(&self, orders: &[LimitOrder], next_id: u64, path: &Path) -> io::Result<()> {
+        let data = OrderBookPersist {
+            orders: orders.to_vec(),
+            next_id,
+        };
+        let json = serde_json::to_string_pretty(&data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_atomic(path, &json)
+    }
+
+    /// Rest a new limit order in the book.
+    ///
+    /// Returns the stored order on success, `Err(message)` when a cap is hit
+    /// or the save fails (all recoverable rejections safe to whisper).
+    #[allow(clippy::too_many_arguments)]
+    pub fn place(
+        &mut self,
+        user_uuid: String,
+        username: String,
+        side: OrderSide,
+        item: String,
+        quantity: u32,
+        limit_price: f64,
+        ttl: Duration,
+    ) -> Result<LimitOrder, String> {
+        self.place_at_path(
+            user_uuid,
+            username,
+            side,
+            item,
+            quantity,
+            limit_price,
+            Utc::now(),
+            ttl,
+            Path::new(ORDER_BOOK_FILE),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn place_at_path(
+        &mut self,
+        user_uuid: String,
+        username: String,
+        side: OrderSide,
+        item: String,
+        quantity: u32,
+        limit_price: f64,
+        now: DateTime<Utc>,
+        ttl: Duration,
+        path: &Path,
+    ) -> Result<LimitOrder, String> {
+        if self.orders.len() >= MAX_ORDER_BOOK_SIZE {
+            warn!(
+                "[OrderBook] Rejected limit order from {} ({}): book full ({}/{})",
+                username,
+                user_uuid,
+                self.orders.len(),
+                MAX_ORDER_BOOK_SIZE
+            );
+            return Err(format!(
+                "The limit order book is currently full ({} orders). Please try again later.",
+                self.orders.len()
+            ));
+        }
+        let user_count = self.user_order_count(&user_uuid);
+        if user_count >= MAX_LIMIT_ORDERS_PER_USER {
+            return Err(format!(
+                "You already have {} resting limit orders (max {}). Cancel one with 'cancel L<id>'.",
+                user_count, MAX_LIMIT_ORDERS_PER_USER
+            ));
+        }
+
+        let id = self.next_id;
+        let order = LimitOrder {
+            id,
+            user_uuid,
+            username,
+            side,
+            item,
+            quantity,
+            limit_price,
+            placed_at: now,
+            expires_at: now + ttl,
+        };
+
+        let mut projected = self.orders.clone();
+        projected.push(order.clone());
+        if let Err(e) = self.write_projection(&projected, id + 1, path) {
+            error!(
+                "[OrderBook] Failed to persist limit order L{}: {} (not placed)",
+                id, e
+            );
+            return Err("Order book temporarily unavailable, please retry.".to_string());
+        }
+        self.orders = projected;
+        self.next_id = id + 1;
+
+        info!(
+            "[OrderBook] Limit order L{} placed (user={} uuid={} {} expires_at={})",
+            id,
+            order.username,
+            order.user_uuid,
+            order.description(),
+            order.expires_at.to_rfc3339()
+        );
+        Ok(order)
+    }
+
+    /// Cancel limit order `id` if it belongs to `user_uuid`.
+    pub fn cancel(&mut self, user_uuid: &str, id: u64) -> Result<LimitOrder, String> {
+        self.cancel_at_path(user_uuid, id, Path::new(ORDER_BOOK_FILE))
+    }
+
+    fn cancel_at_path(
+        &mut self,
+        user_uuid: &str,
+        id: u64,
+        path: &Path,
+    ) -> Result<LimitOrder, String> {
+        match self.orders.iter().find(|o| o.id == id) {
+            None => Err(format!("Limit order L{} not found.", id)),
+            Some(o) if o.user_uuid != user_uuid => {
+                warn!(
+                    "[OrderBook] uuid={} tried to cancel limit order L{} owned by another user",
+                    user_uuid, id
+                );
+                Err("You can only cancel your own orders.".to_string())
+            }
+            Some(_) => self
+                .remove_at_path(id, path)
+                .map_err(|_| "Cancellation failed to persist; please retry.".to_string()),
+        }
+    }
+
+    /// Remove order `id` regardless of owner (promotion and expiry paths).
+    /// The shrunken book is written BEFORE the in-memory removal so a failed
+    /// save leaves both views unchanged.
+    pub fn remove(&mut self, id: u64) -> Result<LimitOrder, String> {
+        self.remove_at_path(id, Path::new(ORDER_BOOK_FILE))
+    }
+
+    fn remove_at_path(&mut self, id: u64, path: &Path) -> Result<LimitOrder, String> {
+        let pos = self
+            .orders
+            .iter()
+            .position(|o| o.id == id)
+            .ok_or_else(|| format!("Limit order L{} not found.", id))?;
+        let projected: Vec<LimitOrder> =
+            self.orders.iter().filter(|o| o.id != id).cloned().collect();
+        if let Err(e) = self.write_projection(&projected, self.next_id, path) {
+            error!(
+                "[OrderBook] Failed to persist removal of limit order L{}: {} (leaving in book)",
+                id, e
+            );
+            return Err(format!("failed to persist order book: {}", e));
+        }
+        Ok(self.orders.remove(pos))
+    }
+
+    /// Put a previously removed order back, keeping its original id. Used
+    /// when a triggered order could not be enqueued (queue full) so it gets
+    /// another chance on the next sweep.
+    pub fn restore(&mut self, order: LimitOrder) -> Result<(), String> {
+        self.restore_at_path(order, Path::new(ORDER_BOOK_FILE))
+    }
+
+    fn restore_at_path(&mut self, order: LimitOrder, path: &Path) -> Result<(), String> {
+        let mut projected = self.orders.clone();
+        projected.push(order);
+        projected.sort_by_key(|o| o.id);
+        self.write_projection(&projected, self.next_id, path)
+            .map_err(|e| format!("failed to persist order book: {}", e))?;
+        self.orders = projected;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn user_order_count(&self, user_uuid: &str) -> usize {
+        self.orders
+            .iter()
+            .filter(|o| o.user_uuid == user_uuid)
+            .count()
+    }
+
+    /// All resting orders for `user_uuid`, oldest first.
+    pub fn get_user_orders(&self, user_uuid: &str) -> Vec<&LimitOrder> {
+        self.orders
+            .iter()
+            .filter(|o| o.user_uuid == user_uuid)
+            .collect()
+    }
+
+    /// IDs of orders whose `expires_at` has passed.
+    pub fn expired_ids(&self, now: DateTime<Utc>) -> Vec<u64> {
+        self.orders
+            .iter()
+            .filter(|o| o.is_expired(now))
+            .map(|o| o.id)
+            .collect()
+    }
+
+    /// `(id, price)` for every unexpired order whose limit is crossed, in
+    /// price-time priority: the most aggressive limit first (highest buy bid,
+    /// lowest sell ask), then oldest first. `quote` returns the average
+    /// per-item price the order would execute at right now, or `None` when
+    /// the pair cannot be quoted for that quantity.
+    pub fn triggered(
+        &self,
+        now: DateTime<Utc>,
+        quote: impl Fn(&LimitOrder) -> Option<f64>,
+    ) -> Vec<(u64, f64)> {
+        let mut hits: Vec<(&LimitOrder, f64)> = self
+            .orders
+            .iter()
+            .filter(|o| !o.is_expired(now))
+            .filter_map(|o| {
+                let price = quote(o)?;
+                o.is_crossed_by(price).then_some((o, price))
+            })
+            .collect();
+        hits.sort_by(|(a, _), (b, _)| {
+            let by_price = match (a.side, b.side) {
+                (OrderSide::Buy, OrderSide::Buy) => b.limit_price.total_cmp(&a.limit_price),
+                (OrderSide::Sell, OrderSide::Sell) => a.limit_price.total_cmp(&b.limit_price),
+                _ => std::cmp::Ordering::Equal,
+            };
+            by_price.then(a.placed_at.cmp(&b.placed_at))
+        });
+        hits.into_iter().map(|(o, price)| (o.id, price)).collect()
+    }
+}
+
+/// On-disk shape for the book. Field renames break existing book files.
+#[derive(Serialize, Deserialize)]
+struct OrderBookPersist {
+    orders: Vec<LimitOrder>,
+    next_id: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir() -> tempfile::TempDir {
+        tempfile::tempdir().unwrap()
+    }
+
+    fn place(
+        book: &mut OrderBook,
+        path: &Path,
+        uuid: &str,
+        side: OrderSide,
+        limit: f64,
+        now: DateTime<Utc>,
+    ) -> Result<LimitOrder, String> {
+        book.place_at_path(
+            uuid.to_string(),
+            format!("player-{uuid}"),
+            side,
+            "cobblestone".to_string(),
+            64,
+            limit,
+            now,
+            Duration::hours(1),
+            path,
+        )
+    }
+
+    #[test]
+    fn buy_limit_crosses_at_or_below_and_sell_at_or_above() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let buy = place(&mut book, &path, "u1", OrderSide::Buy, 2.0, now).unwrap();
+        let sell = place(&mut book, &path, "u1", OrderSide::Sell, 2.0, now).unwrap();
+
+        assert!(buy.is_crossed_by(2.0));
+        assert!(buy.is_crossed_by(1.5));
+        assert!(!buy.is_crossed_by(2.01));
+        assert!(sell.is_crossed_by(2.0));
+        assert!(sell.is_crossed_by(3.0));
+        assert!(!sell.is_crossed_by(1.99));
+        assert!(!buy.is_crossed_by(f64::NAN));
+    }
+
+    #[test]
+    fn place_assigns_sequential_ids_and_sets_expiry() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let a = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        let b = place(&mut book, &path, "u2", OrderSide::Sell, 1.0, now).unwrap();
+        assert_eq!((a.id, b.id), (1, 2));
+        assert_eq!(a.expires_at, now + Duration::hours(1));
+        assert_eq!(book.len(), 2);
+    }
+
+    #[test]
+    fn per_user_cap_rejects_extra_orders_but_other_users_unaffected() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        for _ in 0..MAX_LIMIT_ORDERS_PER_USER {
+            place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        }
+        let err = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap_err();
+        assert!(err.contains(&MAX_LIMIT_ORDERS_PER_USER.to_string()));
+        assert!(place(&mut book, &path, "u2", OrderSide::Buy, 1.0, now).is_ok());
+    }
+
+    #[test]
+    fn place_save_failure_leaves_book_and_counter_unchanged() {
+        let now = Utc::now();
+        let d = dir();
+        // A regular file where the parent directory should be makes the
+        // write's `create_dir_all` fail portably.
+        let blocker = d.path().join("blocker");
+        fs::write(&blocker, b"x").unwrap();
+        let path = blocker.join("order_book.json");
+        let mut book = OrderBook::new();
+        let err = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap_err();
+        assert!(err.contains("temporarily unavailable"));
+        assert!(book.is_empty());
+        assert_eq!(book.next_id, 1);
+    }
+
+    #[test]
+    fn cancel_rejects_other_users_order_and_accepts_own() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let o = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+
+        let err = book.cancel_at_path("u2", o.id, &path).unwrap_err();
+        assert!(err.contains("your own"));
+        assert_eq!(book.len(), 1);
+
+        let removed = book.cancel_at_path("u1", o.id, &path).unwrap();
+        assert_eq!(removed.id, o.id);
+        assert!(book.is_empty());
+
+        let err = book.cancel_at_path("u1", o.id, &path).unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn expired_ids_reports_only_past_deadline() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let old = place(
+            &mut book,
+            &path,
+            "u1",
+            OrderSide::Buy,
+            1.0,
+            now - Duration::hours(2),
+        )
+        .unwrap();
+        place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        assert_eq!(book.expired_ids(now), vec![old.id]);
+    }
+
+    #[test]
+    fn triggered_orders_follow_price_time_priority_and_skip_expired() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let low_bid = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        let high_bid = place(
+            &mut book,
+            &path,
+            "u2",
+            OrderSide::Buy,
+            1.5,
+            now + Duration::seconds(1),
+        )
+        .unwrap();
+        let _expired = place(
+            &mut book,
+            &path,
+            "u3",
+            OrderSide::Buy,
+            5.0,
+            now - Duration::hours(2),
+        )
+        .unwrap();
+        let _uncrossed = place(&mut book, &path, "u4", OrderSide::Buy, 0.5, now).unwrap();
+        let ask = place(&mut book, &path, "u5", OrderSide::Sell, 0.8, now).unwrap();
+
+        let hits = book.triggered(now, |o| match o.side {
+            OrderSide::Buy => Some(0.9),
+            OrderSide::Sell => Some(0.85),
+        });
+        let ids: Vec<u64> = hits.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids.len(), 3);
+        // Higher bid outranks an older, lower bid on the same side.
+        let pos = |id| ids.iter().position(|x| *x == id).unwrap();
+        assert!(pos(high_bid.id) < pos(low_bid.id));
+        assert!(ids.contains(&ask.id));
+    }
+
+    #[test]
+    fn triggered_skips_unquotable_pairs() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        place(&mut book, &path, "u1", OrderSide::Buy, 100.0, now).unwrap();
+        assert!(book.triggered(now, |_| None).is_empty());
+    }
+
+    #[test]
+    fn remove_then_restore_keeps_original_id_and_order() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let a = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        let b = place(&mut book, &path, "u1", OrderSide::Buy, 1.0, now).unwrap();
+        let removed = book.remove_at_path(a.id, &path).unwrap();
+        book.restore_at_path(removed, &path).unwrap();
+        let ids: Vec<u64> = book.get_user_orders("u1").iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![a.id, b.id]);
+        assert_eq!(book.next_id, 3);
+    }
+
+    #[test]
+    fn save_and_load_round_trip_preserves_orders_and_next_id() {
+        let now = Utc::now();
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        let mut book = OrderBook::new();
+        let o = place(&mut book, &path, "u1", OrderSide::Sell, 2.5, now).unwrap();
+
+        let loaded = OrderBook::load_from(&path).unwrap();
+        assert_eq!(loaded.next_id, 2);
+        let orders = loaded.get_user_orders("u1");
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].id, o.id);
+        assert_eq!(orders[0].side, OrderSide::Sell);
+        assert_eq!(orders[0].limit_price, 2.5);
+        assert_eq!(orders[0].expires_at, o.expires_at);
+    }
+
+    #[test]
+    fn load_from_quarantines_corrupt_file_and_returns_empty() {
+        let d = dir();
+        let path = d.path().join("order_book.json");
+        fs::write(&path, "{ not json").unwrap();
+        let book = OrderBook::load_from(&path).unwrap();
+        assert!(book.is_empty());
+        assert!(!path.exists(), "corrupt file must be moved aside");
+        let archived = fs::read_dir(d.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().contains(".corrupt-"));
+        assert!(archived);
+    }
+
+    #[test]
+    fn load_returns_empty_when_file_missing() {
+        let d = dir();
+        let book = OrderBook::load_from(d.path().join("missing.json")).unwrap();
+        assert!(book.is_empty());
+        assert_eq!(book.next_id, 1);
+    }
+}
@@ -1,4 +1,4 @@
-//! # Order execution handlers (buy/sell/deposit/withdraw)
+//! # Order execution handlers (buy/sell/swap/deposit/withdraw)
 //!
 //! High-level flow for any trade is always the same four phases:
 //!   1. **Validate** — basic input/pair/balance/stock checks.
@@ -14,13 +14,23 @@
 //! the per-step bot plumbing (send instruction, await with timeout, apply
 //! sync report) so the handlers read as a linear phase list instead of the
 //! ~470-line monoliths we had before.
+//!
+//! Liquidity adds/removals (`handlers::liquidity`) and basket orders
+//! (`handlers::basket`) follow the same phases and reuse both helpers.
 
 use tokio::sync::oneshot;
 use tracing::{Instrument, error, info, info_span, warn};
 
-use super::queue::QueuedOrder;
+use super::audit_log::AuditEvent;
+use super::ledger::{Account, BUY_REFUND_MEMO};
+use super::order_book::OrderSide;
+use super::queue::{QueuedOrder, SessionProgress};
+use super::trade_state::{self, TradeState};
 use super::{Store, pricing, rollback, state, utils};
-use crate::constants::{CHEST_OP_TIMEOUT_SECS, TRADE_OFFER_SLOTS_PER_SIDE};
+use crate::constants::{
+    CHEST_OP_TIMEOUT_SECS, MAX_ORDER_SESSIONS, MAX_TRADE_DIAMONDS, QUOTE_TOLERANCE,
+    TRADE_OFFER_SLOTS_PER_SIDE,
+};
 use crate::error::StoreError;
 use crate::messages::{BotInstruction, ChestAction, QueuedOrderType, TradeItem};
 use crate::types::storage::ChestTransfer;
@@ -243,6 +253,118 @@ pub(crate) async fn perform_trade(
 // Buy order
 // ===========================================================================
 
+/// How one `/trade` session of a buy or sell is priced.
+#[derive(Debug, Clone, Copy)]
+enum SessionPrice {
+    /// Live reserves, checked against the player's slippage bound and quote
+    /// lock (see `QueuedOrder::price_bound` / `QueuedOrder::quoted_total`).
+    Live {
+        bound: Option<f64>,
+        quoted_total: Option<f64>,
+    },
+    /// This session's share of a multi-session order's total, fixed before
+    /// the first session (see [`run_order_sessions`]).
+    Locked(f64),
+}
+
+/// Price `qty` of `item` at the live reserves and apply the player's slippage
+/// `bound` and quote lock. `None` means the order was rejected and the player
+/// has been told why.
+async fn price_buy(
+    store: &mut Store,
+    player_name: &str,
+    item: &str,
+    qty_i32: i32,
+    bound: Option<f64>,
+    quoted_total: Option<f64>,
+) -> Result<Option<f64>, StoreError> {
+    let total_cost = match pricing::calculate_buy_cost(store, item, qty_i32) {
+        Some(cost) => cost,
+        None => {
+            let pair = store.expect_pair(item, "buy/price-fail")?;
+            // Zero-stock must be handled BEFORE the qty>=item_stock branch:
+            // when `pair.item_stock == 0` the latter would otherwise tell the
+            // player to "try a smaller amount" of an architecturally
+            // impossible smaller quantity. The doc-mandated wording for
+            // drained reserves is the "no stock or reserves" message — see
+            // RECOVERY.md §1.
+            let msg = if pair.item_stock <= 0 {
+                format!(
+                    "Item '{}' is not available for trading (no stock or reserves).",
+                    item
+                )
+            } else if qty_i32 >= pair.item_stock {
+                format!(
+                    "Cannot buy {} {} - would exceed available stock ({}). Try a smaller amount.",
+                    qty_i32, item, pair.item_stock
+                )
+            } else {
+                format!(
+                    "Item '{}' is not available for trading (no stock or reserves).",
+                    item
+                )
+            };
+            utils::send_message_to_player(store, player_name, &msg).await?;
+            return Ok(None);
+        }
+    };
+
+    if !total_cost.is_finite() || total_cost <= 0.0 {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            "Internal error: computed price is invalid.",
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    // Slippage guard: reserves may have moved since the player queued the
+    // order. Re-check against the live price here, before any chest I/O.
+    if let Some(max) = bound
+        && total_cost > max
+    {
+        info!(phase = "buy.validate", player = %player_name, item = %item, total_cost, max_cost = max, "Buy rejected: price above player's max");
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Price moved: {} {} now costs {:.2} diamonds, above your max of {:.2}. Order cancelled, nothing was charged.",
+                qty_i32, item, total_cost, max
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    // Quote lock: charge exactly what `confirm` promised, as long as the pool
+    // has not drifted past the tolerance since.
+    let total_cost = match quoted_total {
+        None => total_cost,
+        Some(quoted) if pricing::quote_within_tolerance(quoted, total_cost, QUOTE_TOLERANCE) => {
+            quoted
+        }
+        Some(quoted) => {
+            info!(phase = "buy.validate", player = %player_name, item = %item, total_cost, quoted, "Buy rejected: live price outside quote tolerance");
+            utils::send_message_to_player(
+                store,
+                player_name,
+                &format!(
+                    "Price moved: {} {} now costs {:.2} diamonds, more than {:.0}% away from your quote of {:.2}. Order cancelled, nothing was charged.",
+                    qty_i32,
+                    item,
+                    total_cost,
+                    QUOTE_TOLERANCE * 100.0,
+                    quoted
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(total_cost))
+}
+
 /// Outcome of buy-order validation: either an accepted plan or a rejection
 /// message to forward to the player.
 struct BuyPlan {
@@ -266,6 +388,7 @@ async fn validate_and_plan_buy(
     user_uuid: &str,
     item: &str,
     quantity: u32,
+    price: SessionPrice,
 ) -> Result<Option<BuyPlan>, StoreError> {
     utils::ensure_user_exists(store, player_name, user_uuid);
     let user_uuid = user_uuid.to_string();
@@ -289,47 +412,17 @@ async fn validate_and_plan_buy(
         return Ok(None);
     }
 
-    let total_cost = match pricing::calculate_buy_cost(store, item, qty_i32) {
-        Some(cost) => cost,
-        None => {
-            let pair = store.expect_pair(item, "buy/price-fail")?;
-            // Zero-stock must be handled BEFORE the qty>=item_stock branch:
-            // when `pair.item_stock == 0` the latter would otherwise tell the
-            // player to "try a smaller amount" of an architecturally
-            // impossible smaller quantity. The doc-mandated wording for
-            // drained reserves is the "no stock or reserves" message — see
-            // RECOVERY.md §1.
-            let msg = if pair.item_stock <= 0 {
-                format!(
-                    "Item '{}' is not available for trading (no stock or reserves).",
-                    item
-                )
-            } else if qty_i32 >= pair.item_stock {
-                format!(
-                    "Cannot buy {} {} - would exceed available stock ({}). Try a smaller amount.",
-                    qty_i32, item, pair.item_stock
-                )
-            } else {
-                format!(
-                    "Item '{}' is not available for trading (no stock or reserves).",
-                    item
-                )
-            };
-            utils::send_message_to_player(store, player_name, &msg).await?;
-            return Ok(None);
-        }
+    let total_cost = match price {
+        SessionPrice::Locked(total) => total,
+        SessionPrice::Live {
+            bound,
+            quoted_total,
+        } => match price_buy(store, player_name, item, qty_i32, bound, quoted_total).await? {
+            Some(total) => total,
+            None => return Ok(None),
+        },
     };
 
-    if !total_cost.is_finite() || total_cost <= 0.0 {
-        utils::send_message_to_player(
-            store,
-            player_name,
-            "Internal error: computed price is invalid.",
-        )
-        .await?;
-        return Ok(None);
-    }
-
     let physical_stock = store.storage.total_item_amount(item);
     if physical_stock < qty_i32 {
         utils::send_message_to_player(
@@ -363,8 +456,9 @@ async fn validate_and_plan_buy(
     // most 12 full stacks of an item in one trade. A larger order withdraws into
     // the bot's inventory fine, but then cannot all be placed into the offer grid
     // — it fails with "Bot trade offer slots are full" mid-trade and forces a
-    // rollback (see the 800x gunpowder incident). Reject up front with a clear
-    // cap, mirroring how the diamond side is capped at MAX_TRADE_DIAMONDS.
+    // rollback (see the 800x gunpowder incident). `handle_buy_order` splits
+    // such orders into sessions before they get here; this guards any other
+    // caller.
     let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE * stack_size;
     if qty_i32 > max_per_trade {
         utils::send_message_to_player(
@@ -439,7 +533,7 @@ async fn validate_and_plan_buy(
 /// Diamonds the buyer must place in the trade GUI to cover a `total_cost`
 /// given their current `balance`. Returns `None` if the shortfall overflows
 /// `i32` (the bot can't offer more than that in a single trade).
-fn diamonds_to_offer_for_buy(total_cost: f64, balance: f64) -> Option<i32> {
+pub(crate) fn diamonds_to_offer_for_buy(total_cost: f64, balance: f64) -> Option<i32> {
     let shortfall = total_cost - balance;
     if shortfall <= 0.0 {
         return Some(0);
@@ -454,7 +548,7 @@ fn diamonds_to_offer_for_buy(total_cost: f64, balance: f64) -> Option<i32> {
 /// Split a sell payout into the whole-diamond portion handed over in the
 /// trade GUI and the fractional portion credited to the seller's balance.
 /// Returns `None` when the whole-diamond portion overflows `i32`.
-fn split_sell_payout(total_payout: f64) -> Option<(i32, f64)> {
+pub(crate) fn split_sell_payout(total_payout: f64) -> Option<(i32, f64)> {
     let floor_value = total_payout.floor();
     if floor_value > i32::MAX as f64 {
         return None;
@@ -464,23 +558,57 @@ fn split_sell_payout(total_payout: f64) -> Option<(i32, f64)> {
     Some((whole, fractional))
 }
 
-/// Handle buy orders.
+/// Handle buy orders. `max_cost` is the player's optional ceiling on the
+/// total price (see `QueuedOrder::price_bound`); `quoted_total` is a price
+/// locked in by `confirm` (see `QueuedOrder::quoted_total`). Orders larger
+/// than one trade window run as several sessions via [`run_order_sessions`].
 pub async fn handle_buy_order(
     store: &mut Store,
     player_name: &str,
     user_uuid: &str,
     item: &str,
     quantity: u32,
+    max_cost: Option<f64>,
+    quoted_total: Option<f64>,
+) -> Result<(), StoreError> {
+    let price = SessionPrice::Live {
+        bound: max_cost,
+        quoted_total,
+    };
+    if exceeds_one_trade(store, item, quantity) {
+        return run_order_sessions(
+            store,
+            OrderSide::Buy,
+            player_name,
+            user_uuid,
+            item,
+            quantity,
+            price,
+        )
+        .await;
+    }
+    buy_session(store, player_name, user_uuid, item, quantity, price).await
+}
+
+/// One buy `/trade`: validate, withdraw, trade, commit.
+async fn buy_session(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    quantity: u32,
+    price: SessionPrice,
 ) -> Result<(), StoreError> {
     info!(phase = "buy.start", player = %player_name, item = %item, qty = quantity, "Buy order starting");
     // Scoped pre-trade gate: a stock drift on some *other* item must not block
     // this buy (that blast radius bricked the whole store in the incident).
     state::assert_tradeable(store, item, user_uuid, "pre-buy")?;
 
-    let plan = match validate_and_plan_buy(store, player_name, user_uuid, item, quantity).await? {
-        Some(p) => p,
-        None => return Ok(()), // player-facing rejection already sent
-    };
+    let plan =
+        match validate_and_plan_buy(store, player_name, user_uuid, item, quantity, price).await? {
+            Some(p) => p,
+            None => return Ok(()), // player-facing rejection already sent
+        };
 
     // Advance: Queued -> Withdrawing
     store.advance_trade(|s| s.begin_withdrawal(plan.withdraw_plan.clone()));
@@ -645,6 +773,18 @@ pub async fn handle_buy_order(
             if credited > 0 {
                 if let Some(u) = store.users.get_mut(&plan.user_uuid) {
                     u.balance += credited as f64;
+                    store.post_ledger(
+                        Account::Vault,
+                        Account::Player(plan.user_uuid.clone()),
+                        credited as f64,
+                        BUY_REFUND_MEMO,
+                    );
+                    store.trades.push(Trade::refund(
+                        ItemId::from_normalized(item.to_string()),
+                        credited as f64,
+                        plan.user_uuid.clone(),
+                    ));
+                    store.dirty = true;
                 }
                 store.dirty_users.insert(plan.user_uuid.clone());
             }
@@ -752,29 +892,49 @@ pub async fn handle_buy_order(
     store.dirty = true;
     store.dirty_users.insert(plan.user_uuid.clone());
 
+    let buy_fee = store
+        .pairs
+        .get(item)
+        .map_or(store.config.fee, |p| p.effective_buy_fee(store.config.fee));
+    let fee_amount = plan.total_cost - (plan.total_cost / (1.0 + buy_fee));
+    let mut trade = Trade::new(
+        TradeType::Buy,
+        ItemId::from_normalized(item.to_string()),
+        plan.qty_i32,
+        plan.total_cost,
+        plan.user_uuid.clone(),
+    )
+    .with_fee(fee_amount)
+    .with_balance_change(surplus - balance_deduction);
+
     let new_item_stock = store.storage.total_item_amount(item);
     let pair = store.expect_pair_mut(item, "buy/commit-pair")?;
     pair.item_stock = new_item_stock;
+    pair.stats.record(&trade);
     // Grow reserves by what's actually backed: physical diamonds in storage
     // plus the virtual balance the player paid from. Crediting `total_cost`
     // would over-state reserves whenever the deposit-rollback partially
-    // failed (`physical_diamonds < diamonds_received`).
-    pair.currency_stock += physical_diamonds_f64 + balance_deduction;
+    // failed (`physical_diamonds < diamonds_received`). The surplus already
+    // went to the player's balance, so it is not the pair's as well.
+    let tendered_to_reserve = physical_diamonds_f64 - surplus;
+    pair.currency_stock += tendered_to_reserve + balance_deduction;
     debug_assert!(pair.item_stock >= 0, "item_stock went negative after buy");
     debug_assert!(
         pair.currency_stock.is_finite() && pair.currency_stock >= 0.0,
         "currency_stock invalid after buy: {}",
         pair.currency_stock
     );
+    let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+    trade = trade.with_reserves(pair.item_stock, pair.currency_stock, spot);
     store.dirty = true;
-
-    store.trades.push(Trade::new(
-        TradeType::Buy,
-        ItemId::from_normalized(item.to_string()),
-        plan.qty_i32,
-        plan.total_cost,
-        plan.user_uuid.clone(),
-    ));
+    let reserve = Account::Reserve(item.to_string());
+    let player = Account::Player(plan.user_uuid.clone());
+    store.post_ledger(Account::Vault, reserve.clone(), tendered_to_reserve, "buy");
+    store.post_ledger(player.clone(), reserve, balance_deduction, "buy");
+    store.post_ledger(Account::Vault, player, surplus, "buy/surplus");
+
+    store.price_history.record(&trade);
+    store.trades.push(trade);
     store.orders.push_back(Order::buy(
         ItemId::from_normalized(item.to_string()),
         plan.qty_i32,
@@ -804,7 +964,6 @@ pub async fn handle_buy_order(
     }
 
     let pickup_summary = utils::summarize_transfers(&plan.withdraw_plan, 3);
-    let fee_amount = plan.total_cost - (plan.total_cost / (1.0 + store.config.fee));
     let payment_msg = if surplus > 0.001 {
         format!(" {:.2} surplus credited to balance.", surplus)
     } else if balance_deduction > 0.001 {
@@ -832,6 +991,87 @@ pub async fn handle_buy_order(
 // Sell order
 // ===========================================================================
 
+/// Sell-side counterpart of [`price_buy`]: `bound` is the least the payout
+/// may be.
+async fn price_sell(
+    store: &mut Store,
+    player_name: &str,
+    item: &str,
+    qty_i32: i32,
+    bound: Option<f64>,
+    quoted_total: Option<f64>,
+) -> Result<Option<f64>, StoreError> {
+    let total_payout = match pricing::calculate_sell_payout(store, item, qty_i32) {
+        Some(p) => p,
+        None => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                &format!(
+                    "Item '{}' is not available for trading (no stock or reserves).",
+                    item
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+    };
+
+    if !total_payout.is_finite() || total_payout <= 0.0 {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            "Internal error: computed payout is invalid.",
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    // Slippage guard, mirroring the buy side.
+    if let Some(min) = bound
+        && total_payout < min
+    {
+        info!(phase = "sell.validate", player = %player_name, item = %item, total_payout, min_payout = min, "Sell rejected: payout below player's min");
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Price moved: {} {} now pays {:.2} diamonds, below your min of {:.2}. Order cancelled, nothing was traded.",
+                qty_i32, item, total_payout, min
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    // Quote lock, mirroring the buy side. Runs before the caller's reserve
+    // check so the reserve is validated against the amount actually paid out.
+    let total_payout = match quoted_total {
+        None => total_payout,
+        Some(quoted) if pricing::quote_within_tolerance(quoted, total_payout, QUOTE_TOLERANCE) => {
+            quoted
+        }
+        Some(quoted) => {
+            info!(phase = "sell.validate", player = %player_name, item = %item, total_payout, quoted, "Sell rejected: live payout outside quote tolerance");
+            utils::send_message_to_player(
+                store,
+                player_name,
+                &format!(
+                    "Price moved: {} {} now pays {:.2} diamonds, more than {:.0}% away from your quote of {:.2}. Order cancelled, nothing was traded.",
+                    qty_i32,
+                    item,
+                    total_payout,
+                    QUOTE_TOLERANCE * 100.0,
+                    quoted
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+    };
+    Ok(Some(total_payout))
+}
+
 struct SellPlan {
     user_uuid: String,
     qty_i32: i32,
@@ -848,6 +1088,7 @@ async fn validate_and_plan_sell(
     user_uuid: &str,
     item: &str,
     quantity: u32,
+    price: SessionPrice,
 ) -> Result<Option<SellPlan>, StoreError> {
     utils::ensure_user_exists(store, player_name, user_uuid);
     let user_uuid = user_uuid.to_string();
@@ -871,32 +1112,17 @@ async fn validate_and_plan_sell(
         return Ok(None);
     }
 
-    let total_payout = match pricing::calculate_sell_payout(store, item, qty_i32) {
-        Some(p) => p,
-        None => {
-            utils::send_message_to_player(
-                store,
-                player_name,
-                &format!(
-                    "Item '{}' is not available for trading (no stock or reserves).",
-                    item
-                ),
-            )
-            .await?;
-            return Ok(None);
-        }
+    let total_payout = match price {
+        SessionPrice::Locked(total) => total,
+        SessionPrice::Live {
+            bound,
+            quoted_total,
+        } => match price_sell(store, player_name, item, qty_i32, bound, quoted_total).await? {
+            Some(total) => total,
+            None => return Ok(None),
+        },
     };
 
-    if !total_payout.is_finite() || total_payout <= 0.0 {
-        utils::send_message_to_player(
-            store,
-            player_name,
-            "Internal error: computed payout is invalid.",
-        )
-        .await?;
-        return Ok(None);
-    }
-
     let pair = store.expect_pair(item, "sell/reserve-check")?;
     if pair.currency_stock < total_payout {
         utils::send_message_to_player(
@@ -915,8 +1141,9 @@ async fn validate_and_plan_sell(
 
     // The trade GUI exposes only 12 player-offer slots, so the player can hand
     // over at most 12 full stacks of an item in one trade. A larger sell can
-    // never be completed (the player physically cannot place the items), so
-    // reject up front with a clear cap rather than failing validation mid-trade.
+    // never be completed in one trade (the player physically cannot place the
+    // items). `handle_sell_order` splits such orders into sessions before they
+    // get here; this guards any other caller.
     let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE * stack_size;
     if qty_i32 > max_per_trade {
         utils::send_message_to_player(
@@ -971,23 +1198,56 @@ async fn validate_and_plan_sell(
     }))
 }
 
-/// Handle sell orders.
+/// Handle sell orders. `min_payout` is the player's optional floor on the
+/// total payout (see `QueuedOrder::price_bound`); `quoted_total` and the
+/// multi-session split as for [`handle_buy_order`].
 pub async fn handle_sell_order(
     store: &mut Store,
     player_name: &str,
     user_uuid: &str,
     item: &str,
     quantity: u32,
+    min_payout: Option<f64>,
+    quoted_total: Option<f64>,
+) -> Result<(), StoreError> {
+    let price = SessionPrice::Live {
+        bound: min_payout,
+        quoted_total,
+    };
+    if exceeds_one_trade(store, item, quantity) {
+        return run_order_sessions(
+            store,
+            OrderSide::Sell,
+            player_name,
+            user_uuid,
+            item,
+            quantity,
+            price,
+        )
+        .await;
+    }
+    sell_session(store, player_name, user_uuid, item, quantity, price).await
+}
+
+/// One sell `/trade`: validate, withdraw the payout, trade, deposit, commit.
+async fn sell_session(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    quantity: u32,
+    price: SessionPrice,
 ) -> Result<(), StoreError> {
     info!(phase = "sell.start", player = %player_name, item = %item, qty = quantity, "Sell order starting");
     // Scoped pre-trade gate: a stock drift on some *other* item must not block
     // this sell (that blast radius bricked the whole store in the incident).
     state::assert_tradeable(store, item, user_uuid, "pre-sell")?;
 
-    let plan = match validate_and_plan_sell(store, player_name, user_uuid, item, quantity).await? {
-        Some(p) => p,
-        None => return Ok(()),
-    };
+    let plan =
+        match validate_and_plan_sell(store, player_name, user_uuid, item, quantity, price).await? {
+            Some(p) => p,
+            None => return Ok(()),
+        };
 
     // Advance: Queued -> Withdrawing (diamonds for payout). The diamond
     // withdrawal plan is not yet known at this point; vec![] is an honest
@@ -1231,7 +1491,22 @@ pub async fn handle_sell_order(
         // that doesn't exist). Diamonds have already moved physically — we
         // can't return Err — but the error must be operator-visible.
         match store.pairs.get_mut(item) {
-            Some(pair) => pair.currency_stock -= plan.total_payout,
+            Some(pair) => {
+                pair.currency_stock -= plan.total_payout;
+                let reserve = Account::Reserve(item.to_string());
+                store.post_ledger(
+                    reserve.clone(),
+                    Account::Vault,
+                    f64::from(plan.whole_diamonds),
+                    "sell/deposit-failed",
+                );
+                store.post_ledger(
+                    reserve,
+                    Account::Player(plan.user_uuid.clone()),
+                    plan.fractional_diamonds,
+                    "sell/deposit-failed",
+                );
+            }
             None => tracing::error!(
                 item = %item,
                 payout = plan.total_payout,
@@ -1239,9 +1514,13 @@ pub async fn handle_sell_order(
                 plan.total_payout
             ),
         }
+        let mut credited = 0.0;
         if plan.fractional_diamonds > 0.0 {
             match store.users.get_mut(&plan.user_uuid) {
-                Some(u) => u.balance += plan.fractional_diamonds,
+                Some(u) => {
+                    u.balance += plan.fractional_diamonds;
+                    credited = plan.fractional_diamonds;
+                }
                 None => tracing::error!(
                     user_uuid = %plan.user_uuid,
                     fractional = plan.fractional_diamonds,
@@ -1251,6 +1530,20 @@ pub async fn handle_sell_order(
             }
             store.dirty_users.insert(plan.user_uuid.clone());
         }
+        // The payout stands although the sell did not, so the trade log
+        // needs it for `--rebuild-ledger` to agree with the files.
+        let mut refund = Trade::new(
+            TradeType::Refund,
+            ItemId::from_normalized(item.to_string()),
+            0,
+            plan.total_payout,
+            plan.user_uuid.clone(),
+        )
+        .with_balance_change(credited);
+        if let Some(pair) = store.pairs.get(item) {
+            refund = refund.with_reserves(pair.item_stock, pair.currency_stock, None);
+        }
+        store.trades.push(refund);
         store.dirty = true;
         store.advance_trade(|s| s.rollback("sell/deposit-failed".to_string()));
         let msg = match return_trade_result {
@@ -1275,9 +1568,25 @@ pub async fn handle_sell_order(
     }
     store.dirty = true;
     store.dirty_users.insert(plan.user_uuid.clone());
+    let sell_fee = store
+        .pairs
+        .get(item)
+        .map_or(store.config.fee, |p| p.effective_sell_fee(store.config.fee));
+    let fee_amount = plan.total_payout / (1.0 - sell_fee) - plan.total_payout;
+    let mut trade = Trade::new(
+        TradeType::Sell,
+        ItemId::from_normalized(item.to_string()),
+        plan.qty_i32,
+        plan.total_payout,
+        plan.user_uuid.clone(),
+    )
+    .with_fee(fee_amount)
+    .with_balance_change(plan.fractional_diamonds);
+
     let new_item_stock = store.storage.total_item_amount(item);
     let pair = store.expect_pair_mut(item, "sell/commit-pair")?;
     pair.item_stock = new_item_stock;
+    pair.stats.record(&trade);
     pair.currency_stock -= plan.total_payout;
     debug_assert!(pair.item_stock >= 0, "item_stock went negative after sell");
     debug_assert!(
@@ -1285,15 +1594,25 @@ pub async fn handle_sell_order(
         "currency_stock invalid after sell: {}",
         pair.currency_stock
     );
+    let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+    trade = trade.with_reserves(pair.item_stock, pair.currency_stock, spot);
     store.dirty = true;
+    let reserve = Account::Reserve(item.to_string());
+    store.post_ledger(
+        reserve.clone(),
+        Account::Vault,
+        f64::from(plan.whole_diamonds),
+        "sell",
+    );
+    store.post_ledger(
+        reserve,
+        Account::Player(plan.user_uuid.clone()),
+        plan.fractional_diamonds,
+        "sell",
+    );
 
-    store.trades.push(Trade::new(
-        TradeType::Sell,
-        ItemId::from_normalized(item.to_string()),
-        plan.qty_i32,
-        plan.total_payout,
-        plan.user_uuid.clone(),
-    ));
+    store.price_history.record(&trade);
+    store.trades.push(trade);
     store.orders.push_back(Order::sell(
         ItemId::from_normalized(item.to_string()),
         plan.qty_i32,
@@ -1322,7 +1641,6 @@ pub async fn handle_sell_order(
     }
 
     let deposit_summary = utils::summarize_transfers(&plan.deposit_plan, 3);
-    let fee_amount = plan.total_payout / (1.0 - store.config.fee) - plan.total_payout;
     let alert_suffix = if invariant_ok {
         String::new()
     } else {
@@ -1340,186 +1658,1209 @@ pub async fn handle_sell_order(
 }
 
 // ===========================================================================
-// Queue dispatcher
+// Multi-session orders
 // ===========================================================================
 
-/// Build the dispatch-summary line for a buy/sell handler that returned
-/// `Ok(())`.
-///
-/// A buy/sell handler returning `Ok(())` means "handled to a clean conclusion"
-/// — which is EITHER a committed trade OR a graceful abort (rollback after a
-/// failed chest open, an underpaid/under-delivered trade, etc.) OR a pre-trade
-/// validation rejection. Only a *committed* trade is a genuine success. The
-/// dispatcher used to label every `Ok(())` as "<verb> order completed", so an
-/// aborted order (e.g. the bot failing to open a chest) showed up in the logs
-/// as "Buy order completed" even though nothing was delivered. Inspect the
-/// terminal trade state so the summary reflects what actually happened.
-fn buy_sell_outcome_summary(store: &Store, verb: &str, order: &QueuedOrder) -> String {
-    use super::trade_state::TradeState;
-    match store.current_trade.as_ref() {
-        Some(TradeState::Committed(_)) => format!(
-            "{} order completed: {} {} for {}",
-            verb, order.quantity, order.item, order.username
-        ),
-        Some(TradeState::RolledBack { reason, .. }) => format!(
-            "{} order aborted ({}): {} {} for {}",
-            verb, reason, order.quantity, order.item, order.username
-        ),
-        // Queued (validation rejected before the trade machine started),
-        // None, or any other non-terminal state: the order did not complete.
-        _ => format!(
-            "{} order not completed: {} {} for {}",
-            verb, order.quantity, order.item, order.username
-        ),
+/// Whether `quantity` of `item` is more than one trade window can carry.
+/// Unknown items report `false` so the single-session path sends the usual
+/// rejection.
+fn exceeds_one_trade(store: &Store, item: &str, quantity: u32) -> bool {
+    store.pairs.get(item).is_some_and(|pair| {
+        let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE.saturating_mul(pair.stack_size);
+        i64::from(quantity) > i64::from(max_per_trade)
+    })
+}
+
+/// Split `quantity` into the fewest near-equal sessions that each fit one
+/// trade window: at most `max_items` items, and a pro-rata share of `total`
+/// of at most `MAX_TRADE_DIAMONDS` so the diamonds also fit. Stops growing
+/// once past `MAX_ORDER_SESSIONS`; the caller rejects anything over the cap.
+fn session_sizes(quantity: u32, max_items: u32, total: f64) -> Vec<u32> {
+    let quantity = u64::from(quantity);
+    if quantity == 0 {
+        return Vec::new();
+    }
+    let mut sessions = quantity.div_ceil(u64::from(max_items.max(1)));
+    while sessions < quantity && sessions <= u64::from(MAX_ORDER_SESSIONS) {
+        let largest = quantity.div_ceil(sessions);
+        if total * largest as f64 / quantity as f64 <= f64::from(MAX_TRADE_DIAMONDS) {
+            break;
+        }
+        sessions += 1;
     }
+    (0..sessions)
+        .map(|i| (quantity * (i + 1) / sessions - quantity * i / sessions) as u32)
+        .collect()
 }
 
-/// Execute a queued order.
+/// Run a buy or sell too large for one trade window as consecutive `/trade`
+/// sessions under the same order id.
 ///
-/// Dispatches to the appropriate handler based on order type. Returns a
-/// success message on completion or an error message on failure. The
-/// handlers themselves send messages to the player during execution.
-pub async fn execute_queued_order(
+/// The whole quantity is priced once, up front, against the live reserves
+/// and the player's bound or quote. Each session then executes at its
+/// pro-rata share of that total as a locked price, so a completed sequence
+/// costs (or pays) exactly what the whole order was quoted at. On the
+/// bonding curve an order's average price is worse than its first slice's
+/// own price, so a locked share never gives away more than the pool would
+/// for that slice alone.
+///
+/// Every session is a complete trade: it commits its ledgers and reaches
+/// `Committed` before the next one starts, and the order's
+/// `QueuedOrder::sessions` progress is persisted with each new session's
+/// `current_trade.json`. If a session does not commit (the player walks
+/// away, declines, underpays, or a check fails) it rolls back as any single
+/// trade would and the sequence stops there: earlier sessions stay
+/// committed, the rest of the order is cancelled, and nothing further is
+/// charged or paid.
+async fn run_order_sessions(
     store: &mut Store,
-    order: &QueuedOrder,
-) -> Result<String, StoreError> {
-    info!(
-        order_id = order.id,
-        phase = "order.start",
-        player = %order.username,
-        order_type = ?order.order_type,
-        item = %order.item,
-        qty = order.quantity,
-        "Executing queued order"
-    );
+    side: OrderSide,
+    player_name: &str,
+    user_uuid: &str,
+    item: &str,
+    quantity: u32,
+    price: SessionPrice,
+) -> Result<(), StoreError> {
+    let (label, done_verb) = match side {
+        OrderSide::Buy => ("Buy", "bought"),
+        OrderSide::Sell => ("Sell", "sold"),
+    };
+    let qty_i32: i32 = quantity
+        .try_into()
+        .map_err(|_| StoreError::ValidationError("Quantity too large".to_string()))?;
 
-    let start_time = std::time::Instant::now();
+    let total = match price {
+        SessionPrice::Locked(total) => Some(total),
+        SessionPrice::Live {
+            bound,
+            quoted_total,
+        } => match side {
+            OrderSide::Buy => {
+                price_buy(store, player_name, item, qty_i32, bound, quoted_total).await?
+            }
+            OrderSide::Sell => {
+                price_sell(store, player_name, item, qty_i32, bound, quoted_total).await?
+            }
+        },
+    };
+    let Some(total) = total else {
+        return Ok(());
+    };
 
-    // Attach order_id to every log line emitted by the inner handler so an
-    // operator can trace a single order end-to-end through withdraw, trade,
-    // deposit, commit, and rollback phases.
-    let order_span = info_span!("order", order_id = order.id, player = %order.username);
-    let result = async {
-        match &order.order_type {
-            QueuedOrderType::Buy => {
-                handle_buy_order(
-                    store,
-                    &order.username,
-                    &order.user_uuid,
-                    &order.item,
-                    order.quantity,
+    // Whole-order checks, so a sequence that cannot finish never starts.
+    // Each session still re-validates its own slice before any chest I/O.
+    let pair = store.expect_pair(item, "sessions/plan")?;
+    let stack_size = pair.stack_size;
+    let currency_stock = pair.currency_stock;
+    let shortfall = match side {
+        OrderSide::Buy => {
+            let physical_stock = store.storage.total_item_amount(item);
+            (physical_stock < qty_i32).then(|| {
+                format!(
+                    "Out of physical stock for '{}'. Storage has {}, requested {}.",
+                    item, physical_stock, qty_i32
                 )
-                .await?;
-                // `Ok(())` covers commit, graceful abort, and validation
-                // rejection alike — derive the real outcome from the trade state.
-                Ok(buy_sell_outcome_summary(store, "Buy", order))
-            }
-            QueuedOrderType::Sell => {
-                handle_sell_order(
-                    store,
-                    &order.username,
-                    &order.user_uuid,
-                    &order.item,
-                    order.quantity,
+            })
+        }
+        OrderSide::Sell if currency_stock < total => Some(format!(
+            "Store has insufficient diamonds to buy that. Available reserve: {:.2}, needed: {:.2}",
+            currency_stock, total
+        )),
+        OrderSide::Sell => {
+            let (_, planned) = store
+                .storage
+                .simulate_deposit_plan(item, qty_i32, stack_size);
+            (planned < qty_i32).then(|| {
+                format!(
+                    "Storage space validation failed for '{}': can only store {} items, but {} requested. Please contact an operator to add more storage nodes.",
+                    item, planned, qty_i32
                 )
-                .await?;
-                Ok(buy_sell_outcome_summary(store, "Sell", order))
-            }
-            QueuedOrderType::Deposit { amount } => {
-                super::handlers::player::handle_deposit_balance_queued(
+            })
+        }
+    };
+    if let Some(msg) = shortfall {
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    let max_per_trade = TRADE_OFFER_SLOTS_PER_SIDE.saturating_mul(stack_size).max(1) as u32;
+    let sizes = session_sizes(quantity, max_per_trade, total);
+    let sessions = u32::try_from(sizes.len()).unwrap_or(u32::MAX);
+    if sessions > MAX_ORDER_SESSIONS {
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "{} {} {} would take {} trades; one order may use at most {}. Please split it into smaller orders.",
+                label, quantity, item, sessions, MAX_ORDER_SESSIONS
+            ),
+        )
+        .await;
+    }
+
+    let largest = sizes.iter().copied().max().unwrap_or(0);
+    info!(phase = "sessions.plan", player = %player_name, item = %item, qty = quantity, sessions, largest, total = format_args!("{:.2}", total), "Splitting order into trade sessions");
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "{} {} {}: too large for one trade, so it runs as {} trades of up to {}. Total {:.2} diamonds, locked for every trade. Accept each trade as it opens; if you stop partway, finished trades stand and the rest is cancelled.",
+            label, quantity, item, sessions, largest, total
+        ),
+    )
+    .await?;
+
+    let mut order = match store.current_trade.as_ref() {
+        Some(state) => state.order().clone(),
+        None => QueuedOrder::new(
+            0,
+            user_uuid.to_string(),
+            player_name.to_string(),
+            match side {
+                OrderSide::Buy => QueuedOrderType::Buy,
+                OrderSide::Sell => QueuedOrderType::Sell,
+            },
+            item.to_string(),
+            quantity,
+        ),
+    };
+    let mut progress = SessionProgress {
+        session: 0,
+        sessions,
+        filled: 0,
+        settled: 0.0,
+        total,
+    };
+    for size in sizes {
+        progress.session += 1;
+        // Shares are taken off the cumulative total so they sum to exactly
+        // `total` with no rounding drift across sessions.
+        let share = total * f64::from(progress.filled + size) / f64::from(quantity)
+            - total * f64::from(progress.filled) / f64::from(quantity);
+
+        // Each session starts a fresh trade-state cycle carrying the progress
+        // so far; the previous session is already committed.
+        order.sessions = Some(progress);
+        let state = TradeState::new(order.clone());
+        if let Err(e) = trade_state::persist(&state) {
+            warn!("[Store] Failed to persist trade state: {}", e);
+        }
+        store.current_trade = Some(state);
+        store.audit_log.record(
+            order.id,
+            AuditEvent::Phase {
+                phase: "queued".to_string(),
+                detail: Some(format!("session {}/{}", progress.session, sessions)),
+            },
+        );
+
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Trade {}/{}: {} {} for {:.2} diamonds.",
+                progress.session, sessions, size, item, share
+            ),
+        )
+        .await?;
+        match side {
+            OrderSide::Buy => {
+                buy_session(
                     store,
-                    &order.username,
-                    &order.user_uuid,
-                    *amount,
+                    player_name,
+                    user_uuid,
+                    item,
+                    size,
+                    SessionPrice::Locked(share),
                 )
-                .await
-                .map(|()| format!("Deposit completed for {}", order.username))
+                .await?
             }
-            QueuedOrderType::Withdraw { amount } => {
-                super::handlers::player::handle_withdraw_balance_queued(
+            OrderSide::Sell => {
+                sell_session(
                     store,
-                    &order.username,
-                    &order.user_uuid,
-                    *amount,
+                    player_name,
+                    user_uuid,
+                    item,
+                    size,
+                    SessionPrice::Locked(share),
                 )
-                .await
-                .map(|()| format!("Withdraw completed for {}", order.username))
+                .await?
             }
         }
-    }
-    .instrument(order_span)
-    .await;
 
-    let elapsed = start_time.elapsed();
-    match &result {
-        Ok(msg) => info!(
-            order_id = order.id,
-            phase = "order.done",
-            player = %order.username,
-            duration_ms = elapsed.as_millis() as u64,
-            "Order completed: {}", msg
-        ),
-        Err(msg) => error!(
-            order_id = order.id,
-            phase = "order.failed",
-            player = %order.username,
-            duration_ms = elapsed.as_millis() as u64,
-            "Order failed: {}", msg
-        ),
+        if !matches!(store.current_trade, Some(TradeState::Committed(_))) {
+            // A check that failed before any chest I/O leaves the session
+            // `Queued`; close it out so the order ends in a terminal state
+            // with its partial fill on record.
+            if matches!(store.current_trade, Some(TradeState::Queued(_))) {
+                let reason = format!("sessions/rejected-{}-of-{}", progress.session, sessions);
+                store.advance_trade(|s| s.rollback(reason));
+            }
+            warn!(phase = "sessions.stopped", player = %player_name, item = %item, session = progress.session, sessions, filled = progress.filled, "Multi-session order stopped early");
+            if progress.filled == 0 {
+                return Ok(());
+            }
+            return utils::send_message_to_player(
+                store,
+                player_name,
+                &format!(
+                    "{} order stopped after {} of {} trades: {} of {} {} {} for {:.2} diamonds. The remaining {} were cancelled; nothing more was traded.",
+                    label,
+                    progress.session - 1,
+                    sessions,
+                    progress.filled,
+                    quantity,
+                    item,
+                    done_verb,
+                    progress.settled,
+                    quantity - progress.filled
+                ),
+            )
+            .await;
+        }
+        progress.filled += size;
+        progress.settled += share;
     }
 
-    result
+    info!(phase = "sessions.done", player = %player_name, item = %item, qty = quantity, sessions, total = format_args!("{:.2}", progress.settled), "Multi-session order completed");
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "{} order complete: {} {} {} in {} trades for {:.2} diamonds.",
+            label, quantity, item, done_verb, sessions, progress.settled
+        ),
+    )
+    .await
 }
 
-#[cfg(test)]
-mod tests {
-    //! Integration tests for order handlers.
-    //!
-    //! These tests construct a `Store` entirely in-memory via
-    //! `Store::new_for_test` and spawn a mock bot task that auto-responds to
-    //! every `BotInstruction`. Username→UUID resolution is stubbed in
-    //! `crate::mojang::resolve_user_uuid` under `#[cfg(test)]` so no Mojang
-    //! API calls happen.
+// ===========================================================================
+// Partial orders
+// ===========================================================================
 
-    use super::*;
-    use crate::config::Config;
-    use crate::messages::{BotInstruction, ChestSyncReport};
-    use crate::store::handlers::player;
-    use crate::types::{Chest, Node, Pair, Position, Storage, User};
-    use std::collections::HashMap;
-    use tokio::sync::mpsc;
+/// How much of a partial (`upto` / `all`) buy or sell can fill right now.
+/// Computed by `max_buy_fill` / `max_sell_fill` in the buy and sell handlers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartialFill {
+    pub quantity: u32,
+    /// Cost (buy) or payout (sell) of `quantity` at the current reserves.
+    pub total: f64,
+    /// What held the fill below the requested amount, phrased for the
+    /// player ("stock", "your balance", ...). `None` when all of it fits.
+    pub limited_by: Option<&'static str>,
+}
 
-    fn test_config() -> Config {
-        Config::test_default()
+impl PartialFill {
+    /// " (limited by ...)" for player messages, empty when nothing limited
+    /// the fill.
+    pub fn limit_note(&self) -> String {
+        self.limited_by
+            .map(|limit| format!(" (limited by {})", limit))
+            .unwrap_or_default()
     }
+}
 
-    fn test_uuid(username: &str) -> String {
-        crate::mojang::fixture_uuid(username)
+/// Settle how much of a queued partial order fills, now that it is about to
+/// run, and tell the player the amount and price before the trade opens.
+/// Returns the order with `quantity` cut to the fill (unchanged for a normal
+/// order), or `None` when nothing can fill and the player has been told why.
+async fn fill_partial(
+    store: &mut Store,
+    order: &QueuedOrder,
+) -> Result<Option<QueuedOrder>, StoreError> {
+    if !order.partial {
+        return Ok(Some(order.clone()));
     }
+    let fill = match order.order_type {
+        QueuedOrderType::Sell => {
+            super::handlers::player::max_sell_fill(store, &order.item, order.quantity)
+        }
+        _ => super::handlers::player::max_buy_fill(
+            store,
+            &order.user_uuid,
+            &order.item,
+            order.quantity,
+        ),
+    };
+    let fill = match fill {
+        Ok(fill) => fill,
+        Err(reason) => {
+            info!(
+                order_id = order.id,
+                reason = %reason,
+                "Partial order has nothing to fill"
+            );
+            utils::send_message_to_player(
+                store,
+                &order.username,
+                &format!("Order #{} cancelled, nothing traded: {}", order.id, reason),
+            )
+            .await?;
+            return Ok(None);
+        }
+    };
+    info!(
+        order_id = order.id,
+        requested = order.quantity,
+        filled = fill.quantity,
+        limited_by = fill.limited_by.unwrap_or("-"),
+        "Partial order fill settled"
+    );
+    utils::send_message_to_player(
+        store,
+        &order.username,
+        &format!(
+            "Order #{}: filling {} of the {} {} requested{}, {:.2} diamonds.",
+            order.id,
+            fill.quantity,
+            order.quantity,
+            order.item,
+            fill.limit_note(),
+            fill.total
+        ),
+    )
+    .await?;
+    let mut filled = order.clone();
+    filled.quantity = fill.quantity;
+    Ok(Some(filled))
+}
 
-    fn make_user(username: &str, balance: f64) -> (String, User) {
-        let uuid = test_uuid(username);
-        (
-            uuid.clone(),
-            User {
-                uuid,
-                username: username.to_string(),
-                balance,
-                operator: false,
-            },
-        )
-    }
+// ===========================================================================
+// Swap order
+// ===========================================================================
 
-    /// Build a minimal single-node storage pre-seeded with `stock` items of
-    /// `item` spread across chest 2 (arbitrary non-reserved chest of node 0).
-    fn make_storage(item: &str, stock: i32) -> Storage {
-        let origin = Position { x: 0, y: 64, z: 0 };
-        let mut storage = Storage::new(&origin);
-        let node = Node::new(0, &origin);
-        storage.nodes.push(node);
-        // Fill chest index 2 (not reserved diamond/overflow) with the item.
-        // Pack into a few shulker slots; the planner splits as needed.
-        let chest: &mut Chest = &mut storage.nodes[0].chests[2];
+/// Accepted swap: both legs priced and planned against current reserves.
+/// The sell leg's payout funds the buy leg; `payout - cost` goes to the
+/// player's balance, so no diamonds cross the trade window.
+struct SwapPlan {
+    user_uuid: String,
+    qty_in: i32,
+    payout: f64,
+    qty_out: i32,
+    cost: f64,
+    deposit_plan: Vec<ChestTransfer>,
+    withdraw_plan: Vec<ChestTransfer>,
+    from_stack_size: i32,
+    to_stack_size: i32,
+}
+
+async fn validate_and_plan_swap(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    from: &str,
+    quantity: u32,
+    to: &str,
+) -> Result<Option<SwapPlan>, StoreError> {
+    utils::ensure_user_exists(store, player_name, user_uuid);
+    let user_uuid = user_uuid.to_string();
+
+    for item in [from, to] {
+        if !store.pairs.contains_key(item) {
+            warn!(phase = "swap.validate", player = %player_name, item = %item, "Attempted to swap unavailable item");
+            utils::send_message_to_player(
+                store,
+                player_name,
+                &format!("Item '{}' is not available for trading", item),
+            )
+            .await?;
+            return Ok(None);
+        }
+    }
+
+    let qty_in: i32 = quantity
+        .try_into()
+        .map_err(|_| StoreError::ValidationError("Quantity too large".to_string()))?;
+    if qty_in <= 0 {
+        utils::send_message_to_player(store, player_name, "Quantity must be positive").await?;
+        return Ok(None);
+    }
+
+    let from_stack_size = store.expect_pair(from, "swap/from-pair")?.stack_size;
+    let to_stack_size = store.expect_pair(to, "swap/to-pair")?.stack_size;
+    // Each side of the trade window holds 12 stacks, so both legs are capped.
+    let max_in = TRADE_OFFER_SLOTS_PER_SIDE * from_stack_size;
+    if qty_in > max_in {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Cannot swap {} {} in one trade - the trade window holds at most {} ({} stacks of {}). Please swap {} or fewer at a time.",
+                qty_in, from, max_in, TRADE_OFFER_SLOTS_PER_SIDE, from_stack_size, max_in
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    let payout = match pricing::calculate_sell_payout(store, from, qty_in) {
+        Some(p) if p.is_finite() && p > 0.0 => p,
+        _ => {
+            utils::send_message_to_player(
+                store,
+                player_name,
+                &format!(
+                    "Item '{}' is not available for trading (no stock or reserves).",
+                    from
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+    };
+    let from_reserve = store
+        .expect_pair(from, "swap/reserve-check")?
+        .currency_stock;
+    if from_reserve < payout {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Store has insufficient diamonds to buy that. Available reserve: {:.2}, needed: {:.2}",
+                from_reserve, payout
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    let max_out = TRADE_OFFER_SLOTS_PER_SIDE * to_stack_size;
+    let to_pair = store.expect_pair(to, "swap/to-price")?;
+    let Some((qty_out, cost)) =
+        pricing::pair_max_buy_within(to_pair, payout, max_out, store.config.fee)
+    else {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Selling {} {} pays {:.2} diamonds, not enough for one {}.",
+                qty_in, from, payout, to
+            ),
+        )
+        .await?;
+        return Ok(None);
+    };
+
+    let (withdraw_plan, planned_out) = store.storage.simulate_withdraw_plan(to, qty_out);
+    if planned_out != qty_out {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Failed to plan withdrawal for '{}' from storage. Planned {}, needed {}.",
+                to, planned_out, qty_out
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+    let (deposit_plan, planned_in) =
+        store
+            .storage
+            .simulate_deposit_plan(from, qty_in, from_stack_size);
+    if planned_in < qty_in {
+        utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Storage space validation failed for '{}': can only store {} items, but {} requested. Please contact an operator to add more storage nodes.",
+                from, planned_in, qty_in
+            ),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some(SwapPlan {
+        user_uuid,
+        qty_in,
+        payout,
+        qty_out,
+        cost,
+        deposit_plan,
+        withdraw_plan,
+        from_stack_size,
+        to_stack_size,
+    }))
+}
+
+/// Handle swap orders: sell `quantity` of `from` and buy as many `to` as the
+/// payout covers, in one `TradeState` lifecycle and one `/trade`.
+///
+/// Phases: withdraw the `to` items, trade them for the `from` items,
+/// deposit the `from` items, then commit both legs. Any failure before the
+/// commit unwinds both legs, so the player ends up with either both sides
+/// of the swap or neither.
+pub async fn handle_swap_order(
+    store: &mut Store,
+    player_name: &str,
+    user_uuid: &str,
+    from: &str,
+    quantity: u32,
+    to: &str,
+) -> Result<(), StoreError> {
+    info!(phase = "swap.start", player = %player_name, from = %from, to = %to, qty = quantity, "Swap order starting");
+    state::assert_tradeable(store, from, user_uuid, "pre-swap")?;
+    state::assert_tradeable(store, to, user_uuid, "pre-swap")?;
+
+    let plan =
+        match validate_and_plan_swap(store, player_name, user_uuid, from, quantity, to).await? {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+    let remainder = plan.payout - plan.cost;
+
+    // Advance: Queued -> Withdrawing (the `to` leg)
+    store.advance_trade(|s| s.begin_withdrawal(plan.withdraw_plan.clone()));
+    if let Err(e) = execute_chest_transfers(
+        store,
+        &plan.withdraw_plan,
+        to,
+        plan.to_stack_size,
+        ChestDirection::Withdraw,
+        "[Swap]",
+    )
+    .await
+    {
+        store.advance_trade(|s| s.rollback("swap/chest-withdrawal-failed".to_string()));
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Swap aborted: bot failed chest withdrawal step: {}",
+                e.user_message()
+            ),
+        )
+        .await;
+    }
+
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Swap {} {} for {} {}: Please offer {} {} in the trade. {:.2} diamonds left over go to your balance.",
+            plan.qty_in, from, plan.qty_out, to, plan.qty_in, from, remainder
+        ),
+    )
+    .await?;
+
+    // Advance: Withdrawing -> Trading
+    store.advance_trade(|s| s.begin_trading());
+    info!(
+        phase = "swap.trade",
+        player = %player_name,
+        from = %from,
+        qty_in = plan.qty_in,
+        to = %to,
+        qty_out = plan.qty_out,
+        "Initiating swap trade"
+    );
+    let trade_result = perform_trade(
+        store,
+        player_name,
+        vec![TradeItem {
+            item: to.to_string(),
+            amount: plan.qty_out,
+        }],
+        vec![TradeItem {
+            item: from.to_string(),
+            amount: plan.qty_in,
+        }],
+        true, // swap: require EXACT amount, as for sells
+        false,
+        "[Swap]",
+    )
+    .await;
+
+    let actual_received = match trade_result {
+        Err(err) => {
+            warn!(phase = "swap.rollback", player = %player_name, "Swap trade failed, rolling back: {}", err);
+            let rb = rollback::deposit_transfers(
+                store,
+                &plan.withdraw_plan,
+                to,
+                plan.to_stack_size,
+                "[Swap]",
+            )
+            .await;
+            let msg = match rb.partial_message() {
+                Some(detail) => format!(
+                    "Swap aborted: trade failed: {}. Rollback partial: {}.",
+                    err.user_message(),
+                    detail
+                ),
+                None => format!(
+                    "Swap aborted: trade failed: {} (items rolled back to storage)",
+                    err.user_message()
+                ),
+            };
+            store.advance_trade(|s| s.rollback("swap/trade-failed".to_string()));
+            return utils::send_message_to_player(store, player_name, &msg).await;
+        }
+        Ok(r) => r,
+    };
+
+    // Defensive recheck, as for sells.
+    let target_item_id = crate::bot::Bot::normalize_item_id(from);
+    let items_received: i32 = actual_received
+        .iter()
+        .filter(|t| crate::bot::Bot::normalize_item_id(&t.item) == target_item_id)
+        .map(|t| t.amount)
+        .sum();
+    if items_received != plan.qty_in {
+        warn!(
+            phase = "swap.validation",
+            player = %player_name,
+            item = %from,
+            expected = plan.qty_in,
+            received = items_received,
+            "Swap validation failed: item count mismatch"
+        );
+        let _ = rollback::deposit_transfers(
+            store,
+            &plan.withdraw_plan,
+            to,
+            plan.to_stack_size,
+            "[Swap] validation-failed",
+        )
+        .await;
+        if items_received > 0 {
+            let _ = perform_trade(
+                store,
+                player_name,
+                vec![TradeItem {
+                    item: from.to_string(),
+                    amount: items_received,
+                }],
+                vec![],
+                false,
+                false,
+                "[Swap] return-items",
+            )
+            .await;
+        }
+        store.advance_trade(|s| s.rollback("swap/item-count-mismatch".to_string()));
+        return utils::send_message_to_player(
+            store,
+            player_name,
+            &format!(
+                "Swap REJECTED: You only put {} {} in the trade but promised {}. Trade cancelled, items returned.",
+                items_received, from, plan.qty_in
+            ),
+        )
+        .await;
+    }
+
+    // Advance: Trading -> Depositing (the `from` leg)
+    store.advance_trade(|s| {
+        s.begin_depositing(
+            super::trade_state::TradeResult {
+                items_received: actual_received.clone(),
+            },
+            plan.deposit_plan.clone(),
+        )
+    });
+    if let Err(err) = execute_chest_transfers(
+        store,
+        &plan.deposit_plan,
+        from,
+        plan.from_stack_size,
+        ChestDirection::Deposit,
+        "[Swap]",
+    )
+    .await
+    {
+        // Unwind both legs with a reverse trade: the player hands the `to`
+        // items back and gets their `from` items. As with a failed sell
+        // deposit, steps that already landed cannot be unwound here.
+        let reversed = perform_trade(
+            store,
+            player_name,
+            vec![TradeItem {
+                item: from.to_string(),
+                amount: plan.qty_in,
+            }],
+            vec![TradeItem {
+                item: to.to_string(),
+                amount: plan.qty_out,
+            }],
+            true,
+            false,
+            "[Swap] deposit-failed",
+        )
+        .await;
+        let msg = match reversed {
+            Ok(_) => {
+                let rb = rollback::deposit_transfers(
+                    store,
+                    &plan.withdraw_plan,
+                    to,
+                    plan.to_stack_size,
+                    "[Swap] deposit-failed",
+                )
+                .await;
+                match rb.partial_message() {
+                    Some(detail) => format!(
+                        "Swap aborted: failed to deposit items into storage: {}. Swap reversed via trade; rollback partial: {}. Contact an operator.",
+                        err.user_message(),
+                        detail
+                    ),
+                    None => format!(
+                        "Swap aborted: failed to deposit items into storage: {}. Swap reversed via trade.",
+                        err.user_message()
+                    ),
+                }
+            }
+            Err(rerr) => {
+                error!(
+                    phase = "swap.deposit_failed",
+                    player = %player_name,
+                    from = %from,
+                    qty_in = plan.qty_in,
+                    to = %to,
+                    qty_out = plan.qty_out,
+                    "Swap deposit failed and reverse trade failed ({}); operator must reconcile stock of both pairs",
+                    rerr
+                );
+                format!(
+                    "Swap aborted: failed to deposit items into storage: {}. Reverse trade also failed ({}). Contact an operator.",
+                    err.user_message(),
+                    rerr.user_message()
+                )
+            }
+        };
+        store.advance_trade(|s| s.rollback("swap/deposit-failed".to_string()));
+        return utils::send_message_to_player(store, player_name, &msg).await;
+    }
+
+    // Commit both legs.
+    {
+        let user = store.expect_user_mut(&plan.user_uuid, "swap/commit-user")?;
+        user.balance += remainder;
+        user.username = player_name.to_owned();
+    }
+    store.dirty = true;
+    store.dirty_users.insert(plan.user_uuid.clone());
+
+    let sell_fee = store
+        .pairs
+        .get(from)
+        .map_or(store.config.fee, |p| p.effective_sell_fee(store.config.fee));
+    let buy_fee = store
+        .pairs
+        .get(to)
+        .map_or(store.config.fee, |p| p.effective_buy_fee(store.config.fee));
+    let sell_fee_amount = plan.payout / (1.0 - sell_fee) - plan.payout;
+    let buy_fee_amount = plan.cost - (plan.cost / (1.0 + buy_fee));
+    let mut sell_trade = Trade::new(
+        TradeType::Sell,
+        ItemId::from_normalized(from.to_string()),
+        plan.qty_in,
+        plan.payout,
+        plan.user_uuid.clone(),
+    )
+    .with_fee(sell_fee_amount)
+    .with_balance_change(remainder);
+    let mut buy_trade = Trade::new(
+        TradeType::Buy,
+        ItemId::from_normalized(to.to_string()),
+        plan.qty_out,
+        plan.cost,
+        plan.user_uuid.clone(),
+    )
+    .with_fee(buy_fee_amount)
+    .with_balance_change(0.0);
+
+    let from_stock = store.storage.total_item_amount(from);
+    let pair = store.expect_pair_mut(from, "swap/commit-from")?;
+    pair.item_stock = from_stock;
+    pair.stats.record(&sell_trade);
+    pair.currency_stock -= plan.payout;
+    let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+    sell_trade = sell_trade.with_reserves(pair.item_stock, pair.currency_stock, spot);
+
+    let to_stock = store.storage.total_item_amount(to);
+    let pair = store.expect_pair_mut(to, "swap/commit-to")?;
+    pair.item_stock = to_stock;
+    pair.stats.record(&buy_trade);
+    pair.currency_stock += plan.cost;
+    let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+    buy_trade = buy_trade.with_reserves(pair.item_stock, pair.currency_stock, spot);
+    store.dirty = true;
+    let from_reserve = Account::Reserve(from.to_string());
+    store.post_ledger(
+        from_reserve.clone(),
+        Account::Reserve(to.to_string()),
+        plan.cost,
+        "swap",
+    );
+    store.post_ledger(
+        from_reserve,
+        Account::Player(plan.user_uuid.clone()),
+        remainder,
+        "swap/remainder",
+    );
+
+    store.price_history.record(&sell_trade);
+    store.price_history.record(&buy_trade);
+    store.trades.push(sell_trade);
+    store.trades.push(buy_trade);
+    store.orders.push_back(Order::sell(
+        ItemId::from_normalized(from.to_string()),
+        plan.qty_in,
+        plan.payout,
+        plan.user_uuid.clone(),
+    ));
+    store.orders.push_back(Order::buy(
+        ItemId::from_normalized(to.to_string()),
+        plan.qty_out,
+        plan.cost,
+        plan.user_uuid.clone(),
+    ));
+
+    // Advance: Depositing -> Committed
+    store.advance_trade(|s| s.commit(from.to_string(), plan.qty_in, plan.payout));
+
+    info!(
+        phase = "swap.done",
+        player = %player_name,
+        from = %from,
+        qty_in = plan.qty_in,
+        to = %to,
+        qty_out = plan.qty_out,
+        payout = format_args!("{:.2}", plan.payout),
+        cost = format_args!("{:.2}", plan.cost),
+        "Swap order completed"
+    );
+
+    let invariant_ok = state::assert_invariants(store, "post-swap", true).is_ok();
+    if !invariant_ok {
+        error!(phase = "swap.invariant", player = %player_name, "Invariant violation after swap — operator must audit store state");
+        let _ = state::save(store);
+    }
+
+    let remainder_msg = if remainder > 0.001 {
+        format!(" {:.2} credited to balance.", remainder)
+    } else {
+        String::new()
+    };
+    let alert_suffix = if invariant_ok {
+        String::new()
+    } else {
+        " (Note: store self-check flagged an inconsistency; operator notified.)".to_string()
+    };
+    utils::send_message_to_player(
+        store,
+        player_name,
+        &format!(
+            "Swapped {} {} for {} {} (sold for {:.2}, bought for {:.2}, fees {:.2}).{} Trade complete.{}",
+            plan.qty_in,
+            from,
+            plan.qty_out,
+            to,
+            plan.payout,
+            plan.cost,
+            sell_fee_amount + buy_fee_amount,
+            remainder_msg,
+            alert_suffix
+        ),
+    )
+    .await
+}
+
+// ===========================================================================
+// Queue dispatcher
+// ===========================================================================
+
+/// Build the dispatch-summary line for a buy/sell handler that returned
+/// `Ok(())`.
+///
+/// A buy/sell handler returning `Ok(())` means "handled to a clean conclusion"
+/// — which is EITHER a committed trade OR a graceful abort (rollback after a
+/// failed chest open, an underpaid/under-delivered trade, etc.) OR a pre-trade
+/// validation rejection. Only a *committed* trade is a genuine success. The
+/// dispatcher used to label every `Ok(())` as "<verb> order completed", so an
+/// aborted order (e.g. the bot failing to open a chest) showed up in the logs
+/// as "Buy order completed" even though nothing was delivered. Inspect the
+/// terminal trade state so the summary reflects what actually happened.
+fn buy_sell_outcome_summary(store: &Store, verb: &str, order: &QueuedOrder) -> String {
+    match store.current_trade.as_ref() {
+        Some(TradeState::Committed(_)) => format!(
+            "{} order completed: {} {} for {}",
+            verb, order.quantity, order.item, order.username
+        ),
+        Some(TradeState::RolledBack {
+            order: stopped,
+            reason,
+        }) => match stopped.sessions {
+            // A multi-session order that stopped after some sessions
+            // committed is a partial fill, not a clean abort.
+            Some(p) if p.filled > 0 => format!(
+                "{} order partially filled ({} of {} {}, stopped: {}) for {}",
+                verb, p.filled, order.quantity, order.item, reason, order.username
+            ),
+            _ => format!(
+                "{} order aborted ({}): {} {} for {}",
+                verb, reason, order.quantity, order.item, order.username
+            ),
+        },
+        // Queued (validation rejected before the trade machine started),
+        // None, or any other non-terminal state: the order did not complete.
+        _ => format!(
+            "{} order not completed: {} {} for {}",
+            verb, order.quantity, order.item, order.username
+        ),
+    }
+}
+
+/// Execute a queued order.
+///
+/// Dispatches to the appropriate handler based on order type. Returns a
+/// success message on completion or an error message on failure. The
+/// handlers themselves send messages to the player during execution.
+pub async fn execute_queued_order(
+    store: &mut Store,
+    order: &QueuedOrder,
+) -> Result<String, StoreError> {
+    info!(
+        order_id = order.id,
+        phase = "order.start",
+        player = %order.username,
+        order_type = ?order.order_type,
+        item = %order.item,
+        qty = order.quantity,
+        "Executing queued order"
+    );
+
+    let start_time = std::time::Instant::now();
+
+    // Attach order_id to every log line emitted by the inner handler so an
+    // operator can trace a single order end-to-end through withdraw, trade,
+    // deposit, commit, and rollback phases.
+    let order_span = info_span!("order", order_id = order.id, player = %order.username);
+    let result = async {
+        match &order.order_type {
+            QueuedOrderType::Buy => {
+                // A partial order trades, and reports, the filled quantity.
+                let Some(order) = fill_partial(store, order).await? else {
+                    return Ok(buy_sell_outcome_summary(store, "Buy", order));
+                };
+                handle_buy_order(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    &order.item,
+                    order.quantity,
+                    order.price_bound,
+                    order.quoted_total,
+                )
+                .await?;
+                // `Ok(())` covers commit, graceful abort, and validation
+                // rejection alike — derive the real outcome from the trade state.
+                Ok(buy_sell_outcome_summary(store, "Buy", &order))
+            }
+            QueuedOrderType::Sell => {
+                let Some(order) = fill_partial(store, order).await? else {
+                    return Ok(buy_sell_outcome_summary(store, "Sell", order));
+                };
+                handle_sell_order(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    &order.item,
+                    order.quantity,
+                    order.price_bound,
+                    order.quoted_total,
+                )
+                .await?;
+                Ok(buy_sell_outcome_summary(store, "Sell", &order))
+            }
+            QueuedOrderType::Swap { to_item } => {
+                handle_swap_order(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    &order.item,
+                    order.quantity,
+                    to_item,
+                )
+                .await?;
+                Ok(buy_sell_outcome_summary(store, "Swap", order))
+            }
+            QueuedOrderType::Deposit { amount } => {
+                super::handlers::player::handle_deposit_balance_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    *amount,
+                )
+                .await
+                .map(|()| format!("Deposit completed for {}", order.username))
+            }
+            QueuedOrderType::Withdraw { amount } => {
+                super::handlers::player::handle_withdraw_balance_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    *amount,
+                )
+                .await
+                .map(|()| format!("Withdraw completed for {}", order.username))
+            }
+            QueuedOrderType::AddLiquidity => super::handlers::player::handle_add_liquidity_queued(
+                store,
+                &order.username,
+                &order.user_uuid,
+                &order.item,
+                order.quantity,
+            )
+            .await
+            .map(|()| format!("LP add handled for {}", order.username)),
+            QueuedOrderType::RemoveLiquidity { shares } => {
+                super::handlers::player::handle_remove_liquidity_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    &order.item,
+                    *shares,
+                )
+                .await
+                .map(|()| format!("LP remove handled for {}", order.username))
+            }
+            QueuedOrderType::BuyBasket { lines } => {
+                super::handlers::player::handle_buy_basket_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    lines,
+                )
+                .await
+                .map(|()| format!("Basket buy handled for {}", order.username))
+            }
+            QueuedOrderType::SellBasket { lines } => {
+                super::handlers::player::handle_sell_basket_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    lines,
+                )
+                .await
+                .map(|()| format!("Basket sell handled for {}", order.username))
+            }
+            QueuedOrderType::SellAll => super::handlers::player::handle_sell_all_queued(
+                store,
+                &order.username,
+                &order.user_uuid,
+                &order.item,
+                order.quantity,
+            )
+            .await
+            .map(|()| format!("Sell all handled for {}", order.username)),
+            QueuedOrderType::EscrowDeposit { escrow_id } => {
+                super::handlers::player::handle_escrow_deposit_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    *escrow_id,
+                )
+                .await
+                .map(|()| format!("Escrow E{} deposit handled", escrow_id))
+            }
+            QueuedOrderType::EscrowRelease { escrow_id } => {
+                super::handlers::player::handle_escrow_release_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    *escrow_id,
+                )
+                .await
+                .map(|()| format!("Escrow E{} release handled", escrow_id))
+            }
+            QueuedOrderType::EscrowReturn { escrow_id } => {
+                super::handlers::player::handle_escrow_return_queued(
+                    store,
+                    &order.username,
+                    &order.user_uuid,
+                    *escrow_id,
+                )
+                .await
+                .map(|()| format!("Escrow E{} return handled", escrow_id))
+            }
+        }
+    }
+    .instrument(order_span)
+    .await;
+
+    let elapsed = start_time.elapsed();
+    match &result {
+        Ok(msg) => info!(
+            order_id = order.id,
+            phase = "order.done",
+            player = %order.username,
+            duration_ms = elapsed.as_millis() as u64,
+            "Order completed: {}", msg
+        ),
+        Err(msg) => error!(
+            order_id = order.id,
+            phase = "order.failed",
+            player = %order.username,
+            duration_ms = elapsed.as_millis() as u64,
+            "Order failed: {}", msg
+        ),
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    //! Integration tests for order handlers.
+    //!
+    //! These tests construct a `Store` entirely in-memory via
+    //! `Store::new_for_test` and spawn a mock bot task that auto-responds to
+    //! every `BotInstruction`. Username→UUID resolution is stubbed in
+    //! `crate::mojang::resolve_user_uuid` under `#[cfg(test)]` so no Mojang
+    //! API calls happen.
+
+    use super::*;
+    use crate::config::Config;
+    use crate::messages::{BotInstruction, ChestSyncReport};
+    use crate::store::handlers::player;
+    use crate::types::{Chest, Node, Pair, Position, Storage, User};
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+
+    fn test_config() -> Config {
+        Config::test_default()
+    }
+
+    fn test_uuid(username: &str) -> String {
+        crate::mojang::fixture_uuid(username)
+    }
+
+    fn make_user(username: &str, balance: f64) -> (String, User) {
+        let uuid = test_uuid(username);
+        (
+            uuid.clone(),
+            User {
+                uuid,
+                username: username.to_string(),
+                balance,
+                operator: false,
+                lp_shares: Default::default(),
+            },
+        )
+    }
+
+    /// Build a minimal single-node storage pre-seeded with `stock` items of
+    /// `item` spread across chest 2 (arbitrary non-reserved chest of node 0).
+    fn make_storage(item: &str, stock: i32) -> Storage {
+        let origin = Position { x: 0, y: 64, z: 0 };
+        let mut storage = Storage::new(&origin);
+        let node = Node::new(0, &origin);
+        storage.nodes.push(node);
+        // Fill chest index 2 (not reserved diamond/overflow) with the item.
+        // Pack into a few shulker slots; the planner splits as needed.
+        let chest: &mut Chest = &mut storage.nodes[0].chests[2];
         chest.item = ItemId::from_normalized(item.to_string());
         // Put all stock in slot 0 for simplicity (within default shulker capacity).
         chest.amounts = vec![0; crate::constants::DOUBLE_CHEST_SLOTS];
@@ -1535,121 +2876,705 @@ mod tests {
                 stack_size: 64,
                 item_stock,
                 currency_stock,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         )
     }
 
-    /// Spawn a mock bot task that auto-responds to every `BotInstruction` with
-    /// a synthetic success. For `InteractWithChestAndSync`, the response
-    /// adjusts the reported per-slot counts to match what a real bot would
-    /// have done (subtracting on withdraw, adding on deposit) for a single
-    /// slot at index 0 of the target chest.
-    fn spawn_mock_bot(mut rx: mpsc::Receiver<BotInstruction>) {
+    /// Spawn a mock bot task that auto-responds to every `BotInstruction` with
+    /// a synthetic success. For `InteractWithChestAndSync`, the response
+    /// adjusts the reported per-slot counts to match what a real bot would
+    /// have done (subtracting on withdraw, adding on deposit) for a single
+    /// slot at index 0 of the target chest.
+    fn spawn_mock_bot(mut rx: mpsc::Receiver<BotInstruction>) {
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                match msg {
+                    BotInstruction::Whisper { respond_to, .. } => {
+                        let _ = respond_to.send(Ok(()));
+                    }
+                    BotInstruction::InteractWithChestAndSync {
+                        target_chest,
+                        action,
+                        respond_to,
+                        ..
+                    } => {
+                        // Mirror the action into a slot-count report. The
+                        // real bot returns -1 for "unchanged" on untouched
+                        // slots; we emit a single non-negative value for
+                        // slot 0 so `apply_chest_sync` has something to
+                        // merge and the rest stay as-is.
+                        let (item, delta) = match action {
+                            crate::messages::ChestAction::Withdraw { item, amount, .. } => {
+                                (item, -amount)
+                            }
+                            crate::messages::ChestAction::Deposit { item, amount, .. } => {
+                                (item, amount)
+                            }
+                        };
+                        let mut amounts = [-1i32; crate::constants::DOUBLE_CHEST_SLOTS];
+                        // Compute new value for slot 0 based on the prior state
+                        let prior = target_chest.amounts.first().copied().unwrap_or(0);
+                        amounts[0] = (prior + delta).max(0);
+                        let _ = respond_to.send(Ok(ChestSyncReport {
+                            chest_id: target_chest.id,
+                            item,
+                            amounts,
+                        }));
+                    }
+                    BotInstruction::TradeWithPlayer {
+                        bot_offers: _,
+                        player_offers: items_player_must_give,
+                        respond_to,
+                        ..
+                    } => {
+                        // Simulate a successful trade: the bot reports that
+                        // the player delivered exactly what was requested.
+                        // `perform_trade` returns the items the bot received
+                        // from the player, which is always `player_offers`
+                        // from the instruction (diamonds on a buy, the traded
+                        // item on a sell). Callers use this list for
+                        // post-trade accounting.
+                        let _ = respond_to.send(Ok(items_player_must_give));
+                    }
+                    _ => {
+                        // Other variants aren't exercised by these tests.
+                    }
+                }
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_buy_out_of_stock_rejected() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Alice", 1000.0);
+        users.insert(uuid.clone(), user);
+
+        let mut pairs = HashMap::new();
+        // Pair stock matches physical storage (invariant) but is below qty.
+        let (k, p) = make_pair("cobblestone", 50, 500.0);
+        pairs.insert(k, p);
+
+        let storage = make_storage("cobblestone", 50);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        // Request more than physical storage holds — handler rejects during
+        // validation, before any bot instruction is sent.
+        let result = handle_buy_order(
+            &mut store,
+            "Alice",
+            &test_uuid("Alice"),
+            "cobblestone",
+            500,
+            None,
+            None,
+        )
+        .await;
+
+        assert!(
+            result.is_ok(),
+            "handler should not propagate error: {:?}",
+            result
+        );
+        assert_eq!(store.users.get(&uuid).unwrap().balance, 1000.0);
+        assert_eq!(store.pairs.get("cobblestone").unwrap().item_stock, 50);
+    }
+
+    #[tokio::test]
+    async fn test_buy_unknown_item_rejected() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Bob", 100.0);
+        users.insert(uuid, user);
+
+        let storage = make_storage("cobblestone", 0);
+        let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
+
+        let result = handle_buy_order(
+            &mut store,
+            "Bob",
+            &test_uuid("Bob"),
+            "gunpowder",
+            10,
+            None,
+            None,
+        )
+        .await;
+        assert!(result.is_ok());
+        // No pair created, no user balance change.
+        assert!(!store.pairs.contains_key("gunpowder"));
+    }
+
+    /// Mock bot that acks whispers and counts every other instruction, so a
+    /// test can assert that a rejection happened before any chest or trade I/O.
+    fn spawn_whisper_only_bot(
+        mut rx: mpsc::Receiver<BotInstruction>,
+    ) -> std::sync::Arc<std::sync::atomic::AtomicUsize> {
+        let other = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = other.clone();
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
                 match msg {
                     BotInstruction::Whisper { respond_to, .. } => {
                         let _ = respond_to.send(Ok(()));
                     }
-                    BotInstruction::InteractWithChestAndSync {
-                        target_chest,
-                        action,
-                        respond_to,
-                        ..
-                    } => {
-                        // Mirror the action into a slot-count report. The
-                        // real bot returns -1 for "unchanged" on untouched
-                        // slots; we emit a single non-negative value for
-                        // slot 0 so `apply_chest_sync` has something to
-                        // merge and the rest stay as-is.
-                        let (item, delta) = match action {
-                            crate::messages::ChestAction::Withdraw { item, amount, .. } => {
-                                (item, -amount)
-                            }
-                            crate::messages::ChestAction::Deposit { item, amount, .. } => {
-                                (item, amount)
-                            }
-                        };
-                        let mut amounts = [-1i32; crate::constants::DOUBLE_CHEST_SLOTS];
-                        // Compute new value for slot 0 based on the prior state
-                        let prior = target_chest.amounts.first().copied().unwrap_or(0);
-                        amounts[0] = (prior + delta).max(0);
-                        let _ = respond_to.send(Ok(ChestSyncReport {
-                            chest_id: target_chest.id,
-                            item,
-                            amounts,
-                        }));
-                    }
-                    BotInstruction::TradeWithPlayer {
-                        bot_offers: _,
-                        player_offers: items_player_must_give,
-                        respond_to,
-                        ..
-                    } => {
-                        // Simulate a successful trade: the bot reports that
-                        // the player delivered exactly what was requested.
-                        // `perform_trade` returns the items the bot received
-                        // from the player, which is always `player_offers`
-                        // from the instruction (diamonds on a buy, the traded
-                        // item on a sell). Callers use this list for
-                        // post-trade accounting.
-                        let _ = respond_to.send(Ok(items_player_must_give));
-                    }
                     _ => {
-                        // Other variants aren't exercised by these tests.
+                        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
                     }
                 }
             }
         });
+        other
+    }
+
+    #[tokio::test]
+    async fn test_buy_above_max_cost_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        let other_instructions = spawn_whisper_only_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Careful", 10_000.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let live_cost = pricing::calculate_buy_cost(&store, "cobblestone", 10).unwrap();
+        let result = handle_buy_order(
+            &mut store,
+            "Careful",
+            &test_uuid("Careful"),
+            "cobblestone",
+            10,
+            Some(live_cost - 0.01),
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "rejection is not an error: {:?}", result);
+
+        assert!(store.trades.is_empty());
+        assert_eq!(store.users.get(&uuid).unwrap().balance, 10_000.0);
+        assert_eq!(store.pairs.get("cobblestone").unwrap().item_stock, 64);
+        assert_eq!(
+            other_instructions.load(std::sync::atomic::Ordering::SeqCst),
+            0,
+            "no chest or trade instruction may be sent when the bound is violated"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_buy_at_max_cost_executes() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Exact", 10_000.0);
+        users.insert(uuid, user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let live_cost = pricing::calculate_buy_cost(&store, "cobblestone", 10).unwrap();
+        let result = handle_buy_order(
+            &mut store,
+            "Exact",
+            &test_uuid("Exact"),
+            "cobblestone",
+            10,
+            Some(live_cost),
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "buy failed: {:?}", result);
+        assert_eq!(store.trades.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sell_below_min_payout_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        let other_instructions = spawn_whisper_only_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Picky", 0.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let live_payout = pricing::calculate_sell_payout(&store, "cobblestone", 10).unwrap();
+        let result = handle_sell_order(
+            &mut store,
+            "Picky",
+            &test_uuid("Picky"),
+            "cobblestone",
+            10,
+            Some(live_payout + 0.01),
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "rejection is not an error: {:?}", result);
+
+        assert!(store.trades.is_empty());
+        assert_eq!(store.users.get(&uuid).unwrap().balance, 0.0);
+        assert_eq!(
+            store.pairs.get("cobblestone").unwrap().currency_stock,
+            500.0
+        );
+        assert_eq!(
+            other_instructions.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quoted_buy_within_tolerance_charges_quoted_total() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Quoted", 10_000.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let live_cost = pricing::calculate_buy_cost(&store, "cobblestone", 10).unwrap();
+        // Pool drifted half the tolerance since the quote was issued.
+        let quoted = live_cost / (1.0 + QUOTE_TOLERANCE / 2.0);
+        let currency_before = store.pairs.get("cobblestone").unwrap().currency_stock;
+        let result = handle_buy_order(
+            &mut store,
+            "Quoted",
+            &test_uuid("Quoted"),
+            "cobblestone",
+            10,
+            None,
+            Some(quoted),
+        )
+        .await;
+        assert!(result.is_ok(), "buy failed: {:?}", result);
+
+        assert_eq!(store.trades.len(), 1);
+        let charged = 10_000.0 - store.users.get(&uuid).unwrap().balance;
+        assert!(
+            (charged - quoted).abs() < 1e-9,
+            "charged {charged}, quoted {quoted}"
+        );
+        let currency_after = store.pairs.get("cobblestone").unwrap().currency_stock;
+        assert!((currency_after - currency_before - quoted).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_buy_records_pair_stats_and_fee() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Counted", 10_000.0);
+        users.insert(uuid, user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let result = handle_buy_order(
+            &mut store,
+            "Counted",
+            &test_uuid("Counted"),
+            "cobblestone",
+            10,
+            None,
+            None,
+        )
+        .await;
+        assert!(result.is_ok(), "buy failed: {:?}", result);
+
+        let trade = store.trades.last().unwrap().clone();
+        let fee = trade.fee.expect("customer buy records its fee");
+        let expected_fee = trade.amount_currency - trade.amount_currency / (1.0 + store.config.fee);
+        assert!((fee - expected_fee).abs() < 1e-9);
+
+        let stats = &store.pairs.get("cobblestone").unwrap().stats;
+        assert_eq!(stats.buy_count, 1);
+        assert_eq!(stats.items_bought, 10);
+        assert!((stats.diamonds_in - trade.amount_currency).abs() < 1e-9);
+        assert!((stats.fees_collected - fee).abs() < 1e-9);
+        assert_eq!(stats.last_trade, Some(trade.timestamp));
+
+        let pair = store.pairs.get("cobblestone").unwrap();
+        assert_eq!(trade.item_stock_after, Some(pair.item_stock));
+        assert_eq!(trade.currency_stock_after, Some(pair.currency_stock));
+        // The mock bot's storage recount can leave the pool empty, in which
+        // case there is no spot price and nothing reaches price history.
+        let spot = pair.curve.mid_price(pair.item_stock, pair.currency_stock);
+        assert_eq!(trade.spot_price, spot);
+        assert_eq!(
+            store.price_history.get("cobblestone").is_some(),
+            spot.is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lp_add_then_remove_round_trips_shares_and_balance() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Provider", 100.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        // 16 of 64 items at 500 diamonds: 125 diamonds is more than the
+        // balance, so the add is refused before any trade.
+        player::handle_add_liquidity_queued(&mut store, "Provider", &uuid, "cobblestone", 16)
+            .await
+            .unwrap();
+        assert!(store.users[&uuid].lp_shares.is_empty());
+        assert!(store.trades.is_empty());
+
+        // 8 items need 62.5 diamonds and mint 8 shares against the implicit
+        // house supply of 64.
+        player::handle_add_liquidity_queued(&mut store, "Provider", &uuid, "cobblestone", 8)
+            .await
+            .unwrap();
+        assert_eq!(store.users[&uuid].lp_shares.get("cobblestone"), Some(&8.0));
+        assert!((store.users[&uuid].balance - 37.5).abs() < 1e-9);
+        let pair = &store.pairs["cobblestone"];
+        assert_eq!(pair.lp_total_shares, 72.0);
+        assert!((pair.currency_stock - 562.5).abs() < 1e-9);
+        let added = store.trades.last().unwrap();
+        assert_eq!(added.trade_type, TradeType::AddLiquidity);
+        assert_eq!(added.amount, 8);
+        assert!(pair.stats.is_empty(), "LP flows are not trading volume");
+
+        player::handle_remove_liquidity_queued(&mut store, "Provider", &uuid, "cobblestone", None)
+            .await
+            .unwrap();
+        let removed = store.trades.last().unwrap().clone();
+        assert_eq!(removed.trade_type, TradeType::RemoveLiquidity);
+        assert!(store.users[&uuid].lp_shares.is_empty());
+        assert_eq!(store.pairs["cobblestone"].lp_total_shares, 64.0);
+        assert!((store.users[&uuid].balance - (37.5 + removed.amount_currency)).abs() < 1e-9);
+        assert!(removed.amount_currency <= 62.5 + 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_escrow_deposit_then_release_pays_seller_and_leaves_stock_alone() {
+        use crate::store::escrow::EscrowStatus;
+
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (seller, user) = make_user("Seller", 0.0);
+        users.insert(seller.clone(), user);
+        let (buyer, user) = make_user("Buyer", 4.5);
+        users.insert(buyer.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        let escrow = store
+            .escrow
+            .open(
+                &seller,
+                "Seller",
+                &buyer,
+                "Buyer",
+                "cobblestone",
+                32,
+                10.0,
+                chrono::Utc::now(),
+                chrono::Duration::hours(1),
+            )
+            .unwrap();
+        // Both present, so notices are whispered rather than held in
+        // `data/notices.json`.
+        for uuid in [&seller, &buyer] {
+            store.rate_limiter.check(&format!("u:{}", uuid)).unwrap();
+        }
+        player::handle_escrow_deposit_queued(&mut store, "Seller", &seller, escrow.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.escrow.get(escrow.id).unwrap().status,
+            EscrowStatus::Held
+        );
+        assert_eq!(store.storage.escrow_item_amount("cobblestone"), 32);
+        assert_eq!(store.storage.total_item_amount("cobblestone"), 64);
+        assert_eq!(store.pairs["cobblestone"].item_stock, 64);
+
+        // A release only runs for the escrow's buyer once it is accepted.
+        player::handle_escrow_release_queued(&mut store, "Buyer", &buyer, escrow.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.escrow.get(escrow.id).unwrap().status,
+            EscrowStatus::Held
+        );
+        store
+            .escrow
+            .update(escrow.id, |e| e.status = EscrowStatus::Releasing);
+
+        // 10 diamonds against a 4.5 balance: 6 in the trade, 4 from balance.
+        player::handle_escrow_release_queued(&mut store, "Buyer", &buyer, escrow.id)
+            .await
+            .unwrap();
+        assert!(store.escrow.get(escrow.id).is_none());
+        assert!((store.users[&seller].balance - 10.0).abs() < 1e-9);
+        assert!((store.users[&buyer].balance - 0.5).abs() < 1e-9);
+        assert_eq!(store.storage.escrow_item_amount("cobblestone"), 0);
+        assert!(store.storage.nodes[0].chests[3].is_unassigned());
+        assert_eq!(store.pairs["cobblestone"].item_stock, 64);
+        let released = store.trades.last().unwrap();
+        assert_eq!(released.trade_type, TradeType::EscrowRelease);
+        assert_eq!(released.counterparty.as_deref(), Some(seller.as_str()));
+        assert_eq!(released.balance_change, Some(-4.0));
+    }
+
+    #[tokio::test]
+    async fn test_escrow_sweep_settles_escrows_whose_order_is_gone() {
+        use crate::store::escrow::EscrowStatus;
+
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+        let storage = make_storage("cobblestone", 0);
+        let mut store =
+            Store::new_for_test(tx, test_config(), HashMap::new(), HashMap::new(), storage);
+        let open = |store: &mut Store| {
+            store
+                .escrow
+                .open(
+                    &test_uuid("Seller"),
+                    "Seller",
+                    &test_uuid("Buyer"),
+                    "Buyer",
+                    "cobblestone",
+                    8,
+                    1.0,
+                    chrono::Utc::now(),
+                    chrono::Duration::hours(1),
+                )
+                .unwrap()
+        };
+        let never_deposited = open(&mut store);
+        let paying = open(&mut store);
+        store.escrow.update(paying.id, |e| {
+            e.status = EscrowStatus::Releasing;
+            e.order_id = Some(99);
+        });
+        // Present, so the "did not go through" notice is whispered rather
+        // than held in `data/notices.json`.
+        store
+            .rate_limiter
+            .check(&format!("u:{}", test_uuid("Buyer")))
+            .unwrap();
+
+        crate::store::handlers::escrow::sweep(&mut store).await;
+        assert!(store.escrow.get(never_deposited.id).is_none());
+        let paying = store.escrow.get(paying.id).unwrap();
+        assert_eq!(paying.status, EscrowStatus::Held);
+        assert_eq!(paying.order_id, None);
+    }
+
+    /// Storage with `iron_ingot` in chest 2 and `gold_ingot` in chest 3.
+    fn make_swap_store(tx: mpsc::Sender<BotInstruction>, balance: f64) -> (Store, String) {
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Swapper", balance);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        for (item, stock, currency) in [("iron_ingot", 640, 320.0), ("gold_ingot", 64, 320.0)] {
+            let (k, p) = make_pair(item, stock, currency);
+            pairs.insert(k, p);
+        }
+        let mut storage = make_storage("iron_ingot", 640);
+        let chest: &mut Chest = &mut storage.nodes[0].chests[3];
+        chest.item = ItemId::from_normalized("gold_ingot".to_string());
+        chest.amounts = vec![0; crate::constants::DOUBLE_CHEST_SLOTS];
+        chest.amounts[0] = 64;
+        let store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+        (store, uuid)
     }
 
     #[tokio::test]
-    async fn test_buy_out_of_stock_rejected() {
+    async fn test_swap_commits_both_legs_and_credits_remainder() {
         let (tx, rx) = mpsc::channel(64);
         spawn_mock_bot(rx);
+        let (mut store, uuid) = make_swap_store(tx, 0.0);
 
-        let mut users = HashMap::new();
-        let (uuid, user) = make_user("Alice", 1000.0);
-        users.insert(uuid.clone(), user);
+        let payout = pricing::calculate_sell_payout(&store, "iron_ingot", 64).unwrap();
+        let (expected_out, cost) =
+            pricing::pair_max_buy_within(&store.pairs["gold_ingot"], payout, 768, store.config.fee)
+                .unwrap();
 
-        let mut pairs = HashMap::new();
-        // Pair stock matches physical storage (invariant) but is below qty.
-        let (k, p) = make_pair("cobblestone", 50, 500.0);
-        pairs.insert(k, p);
+        handle_swap_order(&mut store, "Swapper", &uuid, "iron_ingot", 64, "gold_ingot")
+            .await
+            .unwrap();
+
+        assert_eq!(store.trades.len(), 2);
+        let (sold, bought) = (&store.trades[0], &store.trades[1]);
+        assert_eq!(sold.trade_type, TradeType::Sell);
+        assert_eq!(sold.item.as_str(), "iron_ingot");
+        assert_eq!(sold.amount, 64);
+        assert_eq!(bought.trade_type, TradeType::Buy);
+        assert_eq!(bought.item.as_str(), "gold_ingot");
+        assert_eq!(bought.amount, expected_out);
+
+        // Diamonds only move between the two reserves and the balance.
+        let balance = store.users[&uuid].balance;
+        assert!((balance - (payout - cost)).abs() < 1e-9);
+        assert!((store.pairs["iron_ingot"].currency_stock - (320.0 - payout)).abs() < 1e-9);
+        assert!((store.pairs["gold_ingot"].currency_stock - (320.0 + cost)).abs() < 1e-9);
+        assert_eq!(store.pairs["iron_ingot"].stats.sell_count, 1);
+        assert_eq!(store.pairs["gold_ingot"].stats.buy_count, 1);
+        assert_eq!(store.orders.len(), 2);
+    }
 
-        let storage = make_storage("cobblestone", 50);
-        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+    #[tokio::test]
+    async fn test_swap_too_small_for_one_item_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+        let (mut store, uuid) = make_swap_store(tx, 0.0);
 
-        // Request more than physical storage holds — handler rejects during
-        // validation, before any bot instruction is sent.
-        let result =
-            handle_buy_order(&mut store, "Alice", &test_uuid("Alice"), "cobblestone", 500).await;
+        // One iron ingot pays under 0.5 diamonds; gold costs about 5.
+        handle_swap_order(&mut store, "Swapper", &uuid, "iron_ingot", 1, "gold_ingot")
+            .await
+            .unwrap();
 
-        assert!(
-            result.is_ok(),
-            "handler should not propagate error: {:?}",
-            result
-        );
-        assert_eq!(store.users.get(&uuid).unwrap().balance, 1000.0);
-        assert_eq!(store.pairs.get("cobblestone").unwrap().item_stock, 50);
+        assert!(store.trades.is_empty());
+        assert_eq!(store.users[&uuid].balance, 0.0);
+        assert_eq!(store.pairs["gold_ingot"].currency_stock, 320.0);
+        assert_eq!(store.storage.total_item_amount("gold_ingot"), 64);
+    }
+
+    fn basket(lines: &[(&str, i32)]) -> Vec<TradeItem> {
+        lines
+            .iter()
+            .map(|(item, amount)| TradeItem {
+                item: item.to_string(),
+                amount: *amount,
+            })
+            .collect()
     }
 
     #[tokio::test]
-    async fn test_buy_unknown_item_rejected() {
+    async fn test_buy_basket_commits_every_line_from_balance() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+        let (mut store, uuid) = make_swap_store(tx, 1_000.0);
+        let lines = basket(&[("iron_ingot", 64), ("gold_ingot", 8)]);
+        let iron_cost = pricing::calculate_buy_cost(&store, "iron_ingot", 64).unwrap();
+        let gold_cost = pricing::calculate_buy_cost(&store, "gold_ingot", 8).unwrap();
+
+        player::handle_buy_basket_queued(&mut store, "Swapper", &uuid, &lines)
+            .await
+            .unwrap();
+
+        assert_eq!(store.trades.len(), 2);
+        assert!(store.trades.iter().all(|t| t.trade_type == TradeType::Buy));
+        let balance = store.users[&uuid].balance;
+        assert!((balance - (1_000.0 - iron_cost - gold_cost)).abs() < 1e-9);
+        assert!((store.pairs["iron_ingot"].currency_stock - (320.0 + iron_cost)).abs() < 1e-9);
+        assert!((store.pairs["gold_ingot"].currency_stock - (320.0 + gold_cost)).abs() < 1e-9);
+        assert_eq!(store.orders.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_buy_basket_over_balance_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+        let (mut store, uuid) = make_swap_store(tx, 5.0);
+        let lines = basket(&[("iron_ingot", 64), ("gold_ingot", 8)]);
+
+        player::handle_buy_basket_queued(&mut store, "Swapper", &uuid, &lines)
+            .await
+            .unwrap();
+
+        assert!(store.trades.is_empty());
+        assert_eq!(store.users[&uuid].balance, 5.0);
+        assert_eq!(store.storage.total_item_amount("iron_ingot"), 640);
+        assert_eq!(store.storage.total_item_amount("gold_ingot"), 64);
+    }
+
+    #[tokio::test]
+    async fn test_sell_basket_credits_total_payout() {
         let (tx, rx) = mpsc::channel(64);
         spawn_mock_bot(rx);
+        let (mut store, uuid) = make_swap_store(tx, 0.0);
+        let lines = basket(&[("iron_ingot", 32), ("gold_ingot", 4)]);
+        let payout = pricing::calculate_sell_payout(&store, "iron_ingot", 32).unwrap()
+            + pricing::calculate_sell_payout(&store, "gold_ingot", 4).unwrap();
+
+        player::handle_sell_basket_queued(&mut store, "Swapper", &uuid, &lines)
+            .await
+            .unwrap();
+
+        assert_eq!(store.trades.len(), 2);
+        assert!(store.trades.iter().all(|t| t.trade_type == TradeType::Sell));
+        assert!((store.users[&uuid].balance - payout).abs() < 1e-9);
+        assert_eq!(store.pairs["iron_ingot"].stats.items_sold, 32);
+        assert_eq!(store.pairs["gold_ingot"].stats.items_sold, 4);
+    }
+
+    #[tokio::test]
+    async fn test_quoted_sell_outside_tolerance_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        let other_instructions = spawn_whisper_only_bot(rx);
 
         let mut users = HashMap::new();
-        let (uuid, user) = make_user("Bob", 100.0);
-        users.insert(uuid, user);
+        let (uuid, user) = make_user("Stale", 0.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 64, 500.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 64);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
 
-        let storage = make_storage("cobblestone", 0);
-        let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
+        let live_payout = pricing::calculate_sell_payout(&store, "cobblestone", 10).unwrap();
+        let stale_quote = live_payout * (1.0 + 2.0 * QUOTE_TOLERANCE);
+        let result = handle_sell_order(
+            &mut store,
+            "Stale",
+            &test_uuid("Stale"),
+            "cobblestone",
+            10,
+            None,
+            Some(stale_quote),
+        )
+        .await;
+        assert!(result.is_ok(), "rejection is not an error: {:?}", result);
 
-        let result = handle_buy_order(&mut store, "Bob", &test_uuid("Bob"), "gunpowder", 10).await;
-        assert!(result.is_ok());
-        // No pair created, no user balance change.
-        assert!(!store.pairs.contains_key("gunpowder"));
+        assert!(store.trades.is_empty());
+        assert_eq!(store.users.get(&uuid).unwrap().balance, 0.0);
+        assert_eq!(
+            other_instructions.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
     }
 
     #[tokio::test]
@@ -1666,13 +3591,51 @@ mod tests {
         let storage = Storage::new(&Position { x: 0, y: 64, z: 0 });
         let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
 
-        let result =
-            player::pay_async(&mut store, "Payer", &test_uuid("Payer"), "Payee", 20.0).await;
+        let result = player::pay_async(
+            &mut store,
+            "Payer",
+            &test_uuid("Payer"),
+            "Payee",
+            20.0,
+            None,
+        )
+        .await;
         assert!(result.is_ok(), "pay failed: {:?}", result);
         assert_eq!(store.users.get(&payer_uuid).unwrap().balance, 30.0);
         assert_eq!(store.users.get(&payee_uuid).unwrap().balance, 30.0);
     }
 
+    #[tokio::test]
+    async fn test_pay_records_a_transfer_trade_with_its_memo() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+
+        let mut users = HashMap::new();
+        let (payer_uuid, payer) = make_user("Payer", 50.0);
+        let (payee_uuid, payee) = make_user("Payee", 0.0);
+        users.insert(payer_uuid.clone(), payer);
+        users.insert(payee_uuid.clone(), payee);
+
+        let storage = Storage::new(&Position { x: 0, y: 64, z: 0 });
+        let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
+
+        let memo = Some("iron farm rent".to_string());
+        let paid = player::pay_async(&mut store, "Payer", &payer_uuid, "Payee", 7.5, memo).await;
+        assert_eq!(paid.unwrap(), payee_uuid);
+
+        assert_eq!(store.trades.len(), 1);
+        let t = &store.trades[0];
+        assert_eq!(t.trade_type, crate::types::TradeType::Transfer);
+        assert_eq!(t.user_uuid, payer_uuid);
+        assert_eq!(t.counterparty.as_deref(), Some(payee_uuid.as_str()));
+        assert_eq!(t.amount_currency, 7.5);
+        assert_eq!(t.memo.as_deref(), Some("iron farm rent"));
+
+        let broke = player::pay_async(&mut store, "Payee", &payee_uuid, "Payer", 99.0, None).await;
+        assert!(broke.is_err());
+        assert_eq!(store.trades.len(), 1, "a refused payment records nothing");
+    }
+
     #[tokio::test]
     async fn test_pay_insufficient_balance_rejected() {
         let (tx, rx) = mpsc::channel(64);
@@ -1687,7 +3650,8 @@ mod tests {
         let storage = Storage::new(&Position { x: 0, y: 64, z: 0 });
         let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
 
-        let result = player::pay_async(&mut store, "Poor", &test_uuid("Poor"), "Rich", 50.0).await;
+        let result =
+            player::pay_async(&mut store, "Poor", &test_uuid("Poor"), "Rich", 50.0, None).await;
         assert!(result.is_err());
         // Balances unchanged.
         assert_eq!(store.users.get(&payer_uuid).unwrap().balance, 5.0);
@@ -1706,8 +3670,16 @@ mod tests {
         let storage = make_storage("cobblestone", 0);
         let mut store = Store::new_for_test(tx, test_config(), HashMap::new(), users, storage);
 
-        let result =
-            handle_sell_order(&mut store, "Seller", &test_uuid("Seller"), "gunpowder", 10).await;
+        let result = handle_sell_order(
+            &mut store,
+            "Seller",
+            &test_uuid("Seller"),
+            "gunpowder",
+            10,
+            None,
+            None,
+        )
+        .await;
         assert!(result.is_ok());
         assert_eq!(store.users.get(&uuid).unwrap().balance, 0.0);
         assert!(!store.pairs.contains_key("gunpowder"));
@@ -1729,8 +3701,16 @@ mod tests {
         let storage = make_storage("cobblestone", 100);
         let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
 
-        let result =
-            handle_sell_order(&mut store, "Zed", &test_uuid("Zed"), "cobblestone", 0).await;
+        let result = handle_sell_order(
+            &mut store,
+            "Zed",
+            &test_uuid("Zed"),
+            "cobblestone",
+            0,
+            None,
+            None,
+        )
+        .await;
         assert!(result.is_ok());
         // Reserves unchanged.
         assert_eq!(store.pairs.get("cobblestone").unwrap().item_stock, 100);
@@ -1938,6 +3918,8 @@ mod tests {
             &test_uuid("HappyBuyer"),
             "cobblestone",
             10,
+            None,
+            None,
         )
         .await;
         assert!(result.is_ok(), "buy failed: {:?}", result);
@@ -2047,6 +4029,8 @@ mod tests {
             &test_uuid("ShortPayer"),
             "cobblestone",
             1,
+            None,
+            None,
         )
         .await;
         assert!(
@@ -2070,6 +4054,43 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rebuild_after_an_insufficient_payment_refund_reports_no_diff() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot_underpay(rx, 3);
+
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("ShortPayer", 0.0);
+        users.insert(uuid.clone(), user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 10, 200.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 10);
+        let mut store = Store::new_for_test(tx, test_config(), pairs, users, storage);
+
+        handle_buy_order(
+            &mut store,
+            "ShortPayer",
+            &test_uuid("ShortPayer"),
+            "cobblestone",
+            1,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(store.users[&uuid].balance, 3.0);
+
+        let postings: Vec<_> = store.ledger.pending().cloned().collect();
+        let replay = crate::store::rebuild::replay(Default::default(), &store.trades, &postings);
+        assert_eq!(
+            replay.ledger_only, 0,
+            "the refund posting matches its trade"
+        );
+        let diffs = crate::store::rebuild::diff(&replay, &store.users, &store.pairs);
+        assert!(diffs.is_empty(), "{:?}", diffs);
+    }
+
     #[tokio::test]
     async fn test_sell_happy_path_records_trade_and_order() {
         let (tx, rx) = mpsc::channel(64);
@@ -2101,6 +4122,8 @@ mod tests {
             &test_uuid("HappySeller"),
             "cobblestone",
             10,
+            None,
+            None,
         )
         .await;
         assert!(result.is_ok(), "sell failed: {:?}", result);
@@ -2327,6 +4350,8 @@ mod tests {
             &test_uuid("DepFail"),
             "cobblestone",
             10,
+            None,
+            None,
         )
         .await;
         assert!(
@@ -2385,6 +4410,8 @@ mod tests {
             &test_uuid("NoDiamonds"),
             "cobblestone",
             10,
+            None,
+            None,
         )
         .await;
         assert!(
@@ -2841,6 +4868,7 @@ mod tests {
             &test_uuid("Self"),
             "Self", // resolves to the same fixture UUID
             5.0,
+            None,
         )
         .await;
         let err = result.expect_err("self-pay must be rejected by UUID equality");
@@ -2851,4 +4879,237 @@ mod tests {
         // Balance must be unchanged — neither debit nor credit happened.
         assert_eq!(store.users.get(&uuid).unwrap().balance, 100.0);
     }
+
+    // ---- multi-session orders ----
+
+    #[test]
+    fn session_sizes_fit_the_trade_window() {
+        let sizes = session_sizes(2000, 768, 100.0);
+        assert_eq!(sizes, vec![666, 667, 667]);
+
+        // 1000 items for 3000 diamonds: two sessions would fit the items but
+        // need 1500 diamonds each, so the split grows until a share fits.
+        let sizes = session_sizes(1000, 768, 3000.0);
+        assert_eq!(sizes, vec![250; 4]);
+        assert!(
+            sizes
+                .iter()
+                .all(|&s| 3000.0 * f64::from(s) / 1000.0 <= 768.0)
+        );
+
+        assert_eq!(session_sizes(768, 768, 10.0), vec![768]);
+        assert!(session_sizes(0, 768, 10.0).is_empty());
+    }
+
+    /// Like `spawn_mock_bot`, but remembers slot 0 of every chest across
+    /// calls (seeded from `slots` as `(chest_id, amount)`) so consecutive
+    /// sessions see each other's withdrawals, and only the first `ok_trades`
+    /// trades succeed; later ones fail as if the player walked away.
+    fn spawn_stateful_mock_bot(
+        mut rx: mpsc::Receiver<BotInstruction>,
+        slots: &[(i32, i32)],
+        ok_trades: usize,
+    ) {
+        let mut slots: HashMap<i32, i32> = slots.iter().copied().collect();
+        tokio::spawn(async move {
+            let mut trades = 0usize;
+            while let Some(msg) = rx.recv().await {
+                match msg {
+                    BotInstruction::Whisper { respond_to, .. } => {
+                        let _ = respond_to.send(Ok(()));
+                    }
+                    BotInstruction::InteractWithChestAndSync {
+                        target_chest,
+                        action,
+                        respond_to,
+                        ..
+                    } => {
+                        let (item, delta) = match action {
+                            crate::messages::ChestAction::Withdraw { item, amount, .. } => {
+                                (item, -amount)
+                            }
+                            crate::messages::ChestAction::Deposit { item, amount, .. } => {
+                                (item, amount)
+                            }
+                        };
+                        let slot = slots.entry(target_chest.id).or_insert(0);
+                        *slot = (*slot + delta).max(0);
+                        let mut amounts = [-1i32; crate::constants::DOUBLE_CHEST_SLOTS];
+                        amounts[0] = *slot;
+                        let _ = respond_to.send(Ok(ChestSyncReport {
+                            chest_id: target_chest.id,
+                            item,
+                            amounts,
+                        }));
+                    }
+                    BotInstruction::TradeWithPlayer {
+                        player_offers,
+                        respond_to,
+                        ..
+                    } => {
+                        trades += 1;
+                        let reply = if trades <= ok_trades {
+                            Ok(player_offers)
+                        } else {
+                            Err("Trade timed out".to_string())
+                        };
+                        let _ = respond_to.send(reply);
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    /// Chest 2 of node 0, where `make_storage` puts the item.
+    const SESSION_STOCK: [(i32, i32); 1] = [(2, 1500)];
+
+    fn make_session_store(tx: mpsc::Sender<BotInstruction>) -> Store {
+        let mut users = HashMap::new();
+        let (uuid, user) = make_user("Bulk", 1000.0);
+        users.insert(uuid, user);
+        let mut pairs = HashMap::new();
+        let (k, p) = make_pair("cobblestone", 1500, 150.0);
+        pairs.insert(k, p);
+        let storage = make_storage("cobblestone", 1500);
+        Store::new_for_test(tx, test_config(), pairs, users, storage)
+    }
+
+    fn bulk_buy_order(quantity: u32) -> QueuedOrder {
+        QueuedOrder::new(
+            1,
+            test_uuid("Bulk"),
+            "Bulk".to_string(),
+            QueuedOrderType::Buy,
+            "cobblestone".to_string(),
+            quantity,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_buy_over_one_trade_runs_sessions_at_the_upfront_price() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_stateful_mock_bot(rx, &SESSION_STOCK, usize::MAX);
+        let mut store = make_session_store(tx);
+        let total = pricing::calculate_buy_cost(&store, "cobblestone", 1000).unwrap();
+
+        let order = bulk_buy_order(1000);
+        store.current_trade = Some(TradeState::new(order.clone()));
+        let summary = execute_queued_order(&mut store, &order).await.unwrap();
+
+        assert!(summary.contains("completed"), "{summary}");
+        assert_eq!(store.orders.len(), 2, "one order record per session");
+        assert_eq!(store.pairs["cobblestone"].item_stock, 500);
+        let balance = store.users[&test_uuid("Bulk")].balance;
+        assert!(
+            (balance - (1000.0 - total)).abs() < 1e-6,
+            "charged {} instead of the up-front total {total}",
+            1000.0 - balance
+        );
+        match store.current_trade.as_ref() {
+            Some(TradeState::Committed(c)) => {
+                let p = c.order.sessions.expect("progress recorded on the order");
+                assert_eq!((p.session, p.sessions, p.filled), (2, 2, 500));
+                assert_eq!(c.quantity, 500);
+            }
+            other => panic!("expected committed final session, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buy_sessions_stop_when_player_walks_away() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_stateful_mock_bot(rx, &SESSION_STOCK, 1);
+        let mut store = make_session_store(tx);
+        let total = pricing::calculate_buy_cost(&store, "cobblestone", 1000).unwrap();
+
+        let order = bulk_buy_order(1000);
+        store.current_trade = Some(TradeState::new(order.clone()));
+        let summary = execute_queued_order(&mut store, &order).await.unwrap();
+
+        assert!(
+            summary
+                .contains("partially filled (500 of 1000 cobblestone, stopped: buy/trade-failed)"),
+            "{summary}"
+        );
+        assert_eq!(
+            store.current_trade.as_ref().map(|t| t.phase()),
+            Some("rolled_back")
+        );
+        // Only the first session was charged, at half the up-front total.
+        assert_eq!(store.orders.len(), 1);
+        let balance = store.users[&test_uuid("Bulk")].balance;
+        assert!((balance - (1000.0 - total / 2.0)).abs() < 1e-6, "{balance}");
+    }
+
+    #[tokio::test]
+    async fn test_buy_beyond_session_cap_rejected_before_chest_io() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot_withdraw_fail(rx);
+        let mut store = make_session_store(tx);
+        // Single-item stacks make every trade carry at most 12 items.
+        store.pairs.get_mut("cobblestone").unwrap().stack_size = 1;
+
+        let limit = TRADE_OFFER_SLOTS_PER_SIDE as u32 * MAX_ORDER_SESSIONS;
+        handle_buy_order(
+            &mut store,
+            "Bulk",
+            &test_uuid("Bulk"),
+            "cobblestone",
+            limit + 1,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(store.orders.is_empty());
+        assert_eq!(store.users[&test_uuid("Bulk")].balance, 1000.0);
+    }
+
+    #[tokio::test]
+    async fn test_partial_buy_fills_what_the_balance_covers() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_stateful_mock_bot(rx, &SESSION_STOCK, usize::MAX);
+        let mut store = make_session_store(tx);
+        let uuid = test_uuid("Bulk");
+        store.users.get_mut(&uuid).unwrap().balance = 20.0;
+        let (expected, cost) =
+            pricing::pair_max_buy_within(&store.pairs["cobblestone"], 20.0, 640, store.config.fee)
+                .unwrap();
+        assert!(expected < 640);
+
+        let mut order = bulk_buy_order(640);
+        order.partial = true;
+        store.current_trade = Some(TradeState::new(order.clone()));
+        let summary = execute_queued_order(&mut store, &order).await.unwrap();
+
+        assert_eq!(
+            summary,
+            format!("Buy order completed: {expected} cobblestone for Bulk")
+        );
+        let trade = store.trades.last().expect("trade recorded");
+        assert_eq!(
+            trade.amount, expected,
+            "trade log records the filled amount"
+        );
+        assert!((store.users[&uuid].balance - (20.0 - cost)).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_partial_sell_with_no_diamonds_cancels_before_trade() {
+        let (tx, rx) = mpsc::channel(64);
+        spawn_mock_bot(rx);
+        let mut store = make_session_store(tx);
+        let mut order = bulk_buy_order(64);
+        order.order_type = QueuedOrderType::Sell;
+        order.partial = true;
+        store.current_trade = Some(TradeState::new(order.clone()));
+
+        // `make_storage` holds no diamonds, so nothing can be paid out.
+        let summary = execute_queued_order(&mut store, &order).await.unwrap();
+
+        assert!(summary.contains("not completed"), "{summary}");
+        assert!(store.trades.is_empty());
+        assert_eq!(store.pairs["cobblestone"].item_stock, 1500);
+    }
 }
@@ -0,0 +1,333 @@
+//! Open payment requests between players.
+//!
+//! `request <player> <amount> [memo]` bills another player; the billed
+//! player settles it with `accept R<id>` or refuses with `decline R<id>`,
+//! and either side may decline. Requests that are never answered are dropped
+//! at `expires_at` by `handlers::pay_request::sweep`.
+//!
+//! The set is rewritten to `PAY_REQUESTS_FILE` on every change, before the
+//! in-memory view changes, like the order book: a failed write leaves both
+//! untouched, so a restart never loses or resurrects a request. A corrupt
+//! file is quarantined and the store starts with no open requests.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicU64;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::constants::{MAX_PAY_REQUESTS, MAX_PAY_REQUESTS_PER_USER, PAY_REQUESTS_FILE};
+use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
+
+/// Per-process disambiguator for quarantined request files. Mirrors the
+/// same-named static in `queue.rs`.
+static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A bill from `from` to `to`. Persisted as part of [`PayRequests`]; any
+/// field rename is a persisted-format break.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayRequest {
+    pub id: u64,
+    /// The player asking to be paid.
+    pub from_uuid: String,
+    pub from_name: String,
+    /// The player asked to pay.
+    pub to_uuid: String,
+    pub to_name: String,
+    pub amount: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PayRequest {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PayRequestsPersist {
+    requests: Vec<PayRequest>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+pub struct PayRequests {
+    /// `None` for test stores: requests are kept in memory only.
+    path: Option<PathBuf>,
+    requests: Vec<PayRequest>,
+    /// Persisted so ids never recycle across restarts. Shown as `R<id>`.
+    next_id: u64,
+}
+
+impl Default for PayRequests {
+    fn default() -> Self {
+        Self {
+            path: None,
+            requests: Vec::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl PayRequests {
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(Path::new(PAY_REQUESTS_FILE))
+    }
+
+    /// Path-parameterized load. A corrupt file is quarantined to a
+    /// `pay_requests.corrupt-<unix_ms>-<seq>.json` sibling and the set
+    /// starts empty, like the queue and the order book.
+    pub(crate) fn load_from(path: &Path) -> io::Result<Self> {
+        let mut loaded = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(loaded),
+            Err(e) => return Err(e),
+        };
+        match serde_json::from_str::<PayRequestsPersist>(&contents) {
+            Ok(data) => {
+                info!(
+                    "[PayRequests] Loaded {} open request(s) from {:?} (next_id={})",
+                    data.requests.len(),
+                    path,
+                    data.next_id
+                );
+                loaded.requests = data.requests;
+                loaded.next_id = data.next_id;
+            }
+            Err(parse_err) => {
+                let archived =
+                    pick_archive_path(path.parent(), "pay_requests", "corrupt", &ARCHIVE_SEQ)?;
+                archive_aside(path, &archived)?;
+                error!(
+                    "[PayRequests] OPEN PAYMENT REQUESTS LOST: corrupt {:?} moved to {:?}; parse error: {}",
+                    path, archived, parse_err
+                );
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Write `requests` with `next_id`, which may differ from the current
+    /// state when the caller is persisting a projection.
+    fn write_projection(&self, requests: &[PayRequest], next_id: u64) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = PayRequestsPersist {
+            requests: requests.to_vec(),
+            next_id,
+        };
+        let json = serde_json::to_string_pretty(&data).map_err(io::Error::other)?;
+        write_atomic(path, &json)
+    }
+
+    /// Open a request for `amount` from `to` to `from`, valid for `ttl`.
+    ///
+    /// `Err(message)` when a cap is hit or the save fails; the message is
+    /// safe to whisper.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &mut self,
+        from_uuid: &str,
+        from_name: &str,
+        to_uuid: &str,
+        to_name: &str,
+        amount: f64,
+        memo: Option<String>,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Result<PayRequest, String> {
+        if self.requests.len() >= MAX_PAY_REQUESTS {
+            warn!(
+                "[PayRequests] Rejected request from {} ({}): {} open, cap {}",
+                from_name,
+                from_uuid,
+                self.requests.len(),
+                MAX_PAY_REQUESTS
+            );
+            return Err(
+                "Too many open payment requests right now. Please try again later.".to_string(),
+            );
+        }
+        let sent = self
+            .requests
+            .iter()
+            .filter(|r| r.from_uuid == from_uuid)
+            .count();
+        if sent >= MAX_PAY_REQUESTS_PER_USER {
+            return Err(format!(
+                "You already have {} open payment requests (max {}). Withdraw one with 'decline R<id>'.",
+                sent, MAX_PAY_REQUESTS_PER_USER
+            ));
+        }
+
+        let request = PayRequest {
+            id: self.next_id,
+            from_uuid: from_uuid.to_string(),
+            from_name: from_name.to_string(),
+            to_uuid: to_uuid.to_string(),
+            to_name: to_name.to_string(),
+            amount,
+            memo,
+            created_at: now,
+            expires_at: now + ttl,
+        };
+        let mut projected = self.requests.clone();
+        projected.push(request.clone());
+        if let Err(e) = self.write_projection(&projected, request.id + 1) {
+            error!(
+                "[PayRequests] Failed to persist request R{}: {} (not opened)",
+                request.id, e
+            );
+            return Err("Payment requests are temporarily unavailable, please retry.".to_string());
+        }
+        self.requests = projected;
+        self.next_id = request.id + 1;
+        info!(
+            "[PayRequests] R{} opened: {} asks {} for {} (expires_at={})",
+            request.id,
+            request.from_name,
+            request.to_name,
+            request.amount,
+            request.expires_at.to_rfc3339()
+        );
+        Ok(request)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&PayRequest> {
+        self.requests.iter().find(|r| r.id == id)
+    }
+
+    /// Remove request `id`. The shrunken set is written before the
+    /// in-memory removal so a failed save leaves both views unchanged.
+    pub fn remove(&mut self, id: u64) -> Result<PayRequest, String> {
+        let pos = self
+            .requests
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| format!("Payment request R{} not found.", id))?;
+        let projected: Vec<PayRequest> = self
+            .requests
+            .iter()
+            .filter(|r| r.id != id)
+            .cloned()
+            .collect();
+        if let Err(e) = self.write_projection(&projected, self.next_id) {
+            error!(
+                "[PayRequests] Failed to persist removal of R{}: {} (leaving it open)",
+                id, e
+            );
+            return Err(format!("failed to persist payment requests: {}", e));
+        }
+        Ok(self.requests.remove(pos))
+    }
+
+    /// Put a removed request back under its original id, for an `accept`
+    /// whose transfer was refused.
+    pub fn restore(&mut self, request: PayRequest) -> Result<(), String> {
+        let mut projected = self.requests.clone();
+        projected.push(request);
+        projected.sort_by_key(|r| r.id);
+        self.write_projection(&projected, self.next_id)
+            .map_err(|e| format!("failed to persist payment requests: {}", e))?;
+        self.requests = projected;
+        Ok(())
+    }
+
+    /// Open requests `user_uuid` sent or was sent, oldest first.
+    pub fn involving(&self, user_uuid: &str) -> Vec<&PayRequest> {
+        self.requests
+            .iter()
+            .filter(|r| r.from_uuid == user_uuid || r.to_uuid == user_uuid)
+            .collect()
+    }
+
+    pub fn expired_ids(&self, now: DateTime<Utc>) -> Vec<u64> {
+        self.requests
+            .iter()
+            .filter(|r| r.is_expired(now))
+            .map(|r| r.id)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(requests: &mut PayRequests, from: &str, to: &str) -> Result<PayRequest, String> {
+        requests.open(
+            from,
+            from,
+            to,
+            to,
+            5.0,
+            Some("rent".into()),
+            Utc::now(),
+            Duration::hours(1),
+        )
+    }
+
+    #[test]
+    fn requests_round_trip_through_disk_and_ids_never_recycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pay_requests.json");
+        let mut requests = PayRequests::load_from(&path).unwrap();
+        let first = open(&mut requests, "u1", "u2").unwrap();
+        let second = open(&mut requests, "u2", "u1").unwrap();
+        requests.remove(second.id).unwrap();
+
+        let mut reloaded = PayRequests::load_from(&path).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded.get(first.id), Some(&first));
+        assert_eq!(reloaded.involving("u2").len(), 1);
+        assert_eq!(open(&mut reloaded, "u1", "u3").unwrap().id, 3);
+    }
+
+    #[test]
+    fn per_user_cap_counts_only_requests_the_player_sent() {
+        let mut requests = PayRequests::default();
+        for _ in 0..MAX_PAY_REQUESTS_PER_USER {
+            open(&mut requests, "u1", "u2").unwrap();
+        }
+        assert!(open(&mut requests, "u1", "u3").is_err());
+        assert!(open(&mut requests, "u2", "u1").is_ok());
+    }
+
+    #[test]
+    fn expired_ids_lists_requests_past_their_deadline() {
+        let mut requests = PayRequests::default();
+        let r = open(&mut requests, "u1", "u2").unwrap();
+        assert!(requests.expired_ids(Utc::now()).is_empty());
+        assert_eq!(requests.expired_ids(r.expires_at), vec![r.id]);
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pay_requests.json");
+        fs::write(&path, "{not json").unwrap();
+        let requests = PayRequests::load_from(&path).unwrap();
+        assert!(requests.is_empty());
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,536 @@
+//! Per-item price history: hourly and daily OHLC candles with volume.
+//!
+//! Derived from customer trades. Every committed buy or sell records the
+//! spot price its post-trade reserves imply (`Trade::spot_price`), and
+//! [`PriceHistory::record`] folds that into the candle for the trade's hour
+//! and day. Admin adjustments move reserves too but are not market prices,
+//! so they are skipped.
+//!
+//! Persisted as one `<item>.json` per item under `PRICE_HISTORY_DIR`, written
+//! only for items that traded since the last save. The series is derived
+//! data: a corrupt file is quarantined and that item starts over, and if
+//! the directory is missing at startup it is rebuilt from the trades still
+//! in memory (`from_trades`). Trades recorded before `spot_price` existed
+//! carry no price and are not counted.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::constants::{
+    PRICE_HISTORY_DAILY_CANDLES, PRICE_HISTORY_DIR, PRICE_HISTORY_HOURLY_CANDLES,
+};
+use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
+use crate::types::{Trade, TradeType};
+
+/// Mirrors the `ARCHIVE_SEQ` statics in `queue.rs` / `order_book.rs`.
+static PRICE_HISTORY_ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    fn bucket(self) -> Duration {
+        match self {
+            Resolution::Hourly => Duration::hours(1),
+            Resolution::Daily => Duration::days(1),
+        }
+    }
+
+    fn cap(self) -> usize {
+        match self {
+            Resolution::Hourly => PRICE_HISTORY_HOURLY_CANDLES,
+            Resolution::Daily => PRICE_HISTORY_DAILY_CANDLES,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Resolution::Hourly => "hourly",
+            Resolution::Daily => "daily",
+        }
+    }
+
+    /// Start of the UTC hour or day containing `ts`.
+    fn start_of(self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        ts.duration_trunc(self.bucket()).unwrap_or(ts)
+    }
+}
+
+/// Lookback for the `history` whisper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryWindow {
+    Day,
+    Week,
+}
+
+impl HistoryWindow {
+    /// Accepts `24h` / `1d` and `7d` / `1w`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "24h" | "1d" => Some(HistoryWindow::Day),
+            "7d" | "1w" => Some(HistoryWindow::Week),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HistoryWindow::Day => "24h",
+            HistoryWindow::Week => "7d",
+        }
+    }
+
+    fn duration(self) -> Duration {
+        match self {
+            HistoryWindow::Day => Duration::hours(24),
+            HistoryWindow::Week => Duration::days(7),
+        }
+    }
+}
+
+/// One OHLC bucket. Prices are spot per item in diamonds, fees excluded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Items traded in either direction.
+    pub volume_items: u64,
+    /// Diamonds that changed hands (buy costs plus sell payouts).
+    pub volume_diamonds: f64,
+    pub trades: u32,
+}
+
+impl Candle {
+    fn new(start: DateTime<Utc>, price: f64, items: u64, diamonds: f64) -> Self {
+        Self {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume_items: items,
+            volume_diamonds: diamonds,
+            trades: 1,
+        }
+    }
+
+    /// Fold in a trade. `close` only moves for trades at or after the last
+    /// one seen, which callers guarantee by recording in commit order.
+    fn add(&mut self, price: f64, items: u64, diamonds: f64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume_items += items;
+        self.volume_diamonds += diamonds;
+        self.trades += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemHistory {
+    #[serde(default)]
+    pub hourly: Vec<Candle>,
+    #[serde(default)]
+    pub daily: Vec<Candle>,
+}
+
+impl ItemHistory {
+    pub fn candles(&self, resolution: Resolution) -> &[Candle] {
+        match resolution {
+            Resolution::Hourly => &self.hourly,
+            Resolution::Daily => &self.daily,
+        }
+    }
+
+    fn add(&mut self, ts: DateTime<Utc>, price: f64, items: u64, diamonds: f64) {
+        for resolution in [Resolution::Hourly, Resolution::Daily] {
+            let series = match resolution {
+                Resolution::Hourly => &mut self.hourly,
+                Resolution::Daily => &mut self.daily,
+            };
+            let start = resolution.start_of(ts);
+            match series.binary_search_by_key(&start, |c| c.start) {
+                Ok(i) => series[i].add(price, items, diamonds),
+                Err(i) => series.insert(i, Candle::new(start, price, items, diamonds)),
+            }
+            let excess = series.len().saturating_sub(resolution.cap());
+            series.drain(..excess);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PriceHistory {
+    items: HashMap<String, ItemHistory>,
+    /// Items with candles not yet written to disk.
+    dirty: HashSet<String>,
+}
+
+impl PriceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build from scratch by replaying `trades` in order.
+    pub fn from_trades<'a>(trades: impl IntoIterator<Item = &'a Trade>) -> Self {
+        let mut history = Self::new();
+        for trade in trades {
+            history.record(trade);
+        }
+        history
+    }
+
+    /// Load every item file from `PRICE_HISTORY_DIR`. When the directory
+    /// does not exist yet, rebuild from `trades` instead.
+    pub fn load_or_rebuild(trades: &[Trade]) -> Self {
+        let dir = Path::new(PRICE_HISTORY_DIR);
+        if dir.exists() {
+            return Self::load_from_dir(dir);
+        }
+        let history = Self::from_trades(trades);
+        if !history.items.is_empty() {
+            info!(
+                "[PriceHistory] Rebuilt candles for {} item(s) from {} loaded trade(s)",
+                history.items.len(),
+                trades.len()
+            );
+        }
+        history
+    }
+
+    fn load_from_dir(dir: &Path) -> Self {
+        let mut history = Self::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!(
+                    "[PriceHistory] Cannot read {:?}: {}; starting empty",
+                    dir, e
+                );
+                return history;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(item) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str::<ItemHistory>(&s).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(series) => {
+                    history.items.insert(item.to_string(), series);
+                }
+                Err(e) => {
+                    warn!(
+                        "[PriceHistory] Dropping history for '{}' ({}); it restarts from the next trade",
+                        item, e
+                    );
+                    if let Err(qe) = quarantine(&path) {
+                        warn!("[PriceHistory] Quarantine of {:?} failed: {}", path, qe);
+                    }
+                }
+            }
+        }
+        info!(
+            "[PriceHistory] Loaded candles for {} item(s) from {:?}",
+            history.items.len(),
+            dir
+        );
+        history
+    }
+
+    /// Fold a trade into its item's candles. Returns `false` (no change)
+    /// for admin adjustments and trades without a usable spot price.
+    pub fn record(&mut self, trade: &Trade) -> bool {
+        if !matches!(trade.trade_type, TradeType::Buy | TradeType::Sell) {
+            return false;
+        }
+        let Some(price) = trade.spot_price.filter(|p| p.is_finite() && *p > 0.0) else {
+            return false;
+        };
+        let items = u64::try_from(trade.amount).unwrap_or(0);
+        let item = trade.item.as_str();
+        self.items.entry(item.to_string()).or_default().add(
+            trade.timestamp,
+            price,
+            items,
+            trade.amount_currency,
+        );
+        self.dirty.insert(item.to_string());
+        true
+    }
+
+    pub fn get(&self, item: &str) -> Option<&ItemHistory> {
+        self.items.get(item)
+    }
+
+    /// Compact text summary of `item` over `window` ending at `now`, from
+    /// hourly candles. `None` when no trade fell inside the window.
+    pub fn summary(&self, item: &str, window: HistoryWindow, now: DateTime<Utc>) -> Option<String> {
+        let since = Resolution::Hourly.start_of(now - window.duration());
+        let candles: Vec<&Candle> = self
+            .items
+            .get(item)?
+            .hourly
+            .iter()
+            .filter(|c| c.start >= since && c.start <= now)
+            .collect();
+        let (first, last) = (candles.first()?, candles.last()?);
+        let high = candles.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+        let low = candles.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+        let trades: u32 = candles.iter().map(|c| c.trades).sum();
+        let volume_items: u64 = candles.iter().map(|c| c.volume_items).sum();
+        let volume_diamonds: f64 = candles.iter().map(|c| c.volume_diamonds).sum();
+        let change = (last.close - first.open) / first.open * 100.0;
+        Some(format!(
+            "{} {}: open {:.4}, high {:.4}, low {:.4}, close {:.4} ({:+.1}%). {} trades, {} items, {:.2} diamonds.",
+            item,
+            window.label(),
+            first.open,
+            high,
+            low,
+            last.close,
+            change,
+            trades,
+            volume_items,
+            volume_diamonds
+        ))
+    }
+
+    /// Write every item whose candles changed since the last save. An item
+    /// stays dirty if its write fails, so the next save retries it.
+    pub fn save_dirty(&mut self) -> io::Result<()> {
+        self.save_dirty_in_dir(Path::new(PRICE_HISTORY_DIR))
+    }
+
+    fn save_dirty_in_dir(&mut self, dir: &Path) -> io::Result<()> {
+        let mut first_err = None;
+        let dirty: Vec<String> = self.dirty.iter().cloned().collect();
+        for item in dirty {
+            let Some(series) = self.items.get(&item) else {
+                self.dirty.remove(&item);
+                continue;
+            };
+            let result = serde_json::to_string(series)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                .and_then(|json| write_atomic(dir.join(format!("{item}.json")), &json));
+            match result {
+                Ok(()) => {
+                    self.dirty.remove(&item);
+                }
+                Err(e) => {
+                    warn!("[PriceHistory] Failed to save '{}': {}", item, e);
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+}
+
+/// CSV with a header row, one line per candle, oldest first.
+pub fn to_csv(candles: &[Candle]) -> String {
+    let mut out = String::from("start,open,high,low,close,volume_items,volume_diamonds,trades\n");
+    for c in candles {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            c.start.to_rfc3339(),
+            c.open,
+            c.high,
+            c.low,
+            c.close,
+            c.volume_items,
+            c.volume_diamonds,
+            c.trades
+        ));
+    }
+    out
+}
+
+fn quarantine(path: &Path) -> io::Result<()> {
+    let base = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "price_history.json".to_string());
+    let archived = pick_archive_path(path.parent(), &base, "corrupt", &PRICE_HISTORY_ARCHIVE_SEQ)?;
+    archive_aside(path, &archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ItemId;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 4, 12, h, m, 0).unwrap()
+    }
+
+    fn trade(kind: TradeType, amount: i32, currency: f64, spot: f64, ts: DateTime<Utc>) -> Trade {
+        let mut t = Trade::new(
+            kind,
+            ItemId::new("iron_ingot").unwrap(),
+            amount,
+            currency,
+            "uuid".to_string(),
+        )
+        .with_reserves(100, 1000.0, Some(spot));
+        t.timestamp = ts;
+        t
+    }
+
+    #[test]
+    fn trades_in_one_hour_share_a_candle() {
+        let mut h = PriceHistory::new();
+        assert!(h.record(&trade(TradeType::Buy, 10, 25.0, 2.0, at(10, 5))));
+        assert!(h.record(&trade(TradeType::Buy, 5, 15.0, 3.0, at(10, 20))));
+        assert!(h.record(&trade(TradeType::Sell, 8, 10.0, 1.5, at(10, 55))));
+
+        let series = h.get("iron_ingot").unwrap();
+        assert_eq!(series.hourly.len(), 1);
+        let c = &series.hourly[0];
+        assert_eq!(c.start, at(10, 0));
+        assert_eq!((c.open, c.high, c.low, c.close), (2.0, 3.0, 1.5, 1.5));
+        assert_eq!(c.volume_items, 23);
+        assert_eq!(c.volume_diamonds, 50.0);
+        assert_eq!(c.trades, 3);
+        assert_eq!(series.daily.len(), 1);
+        assert_eq!(series.daily[0].trades, 3);
+    }
+
+    #[test]
+    fn new_hour_opens_a_new_candle_in_order() {
+        let mut h = PriceHistory::new();
+        h.record(&trade(TradeType::Buy, 1, 2.0, 2.0, at(11, 0)));
+        h.record(&trade(TradeType::Buy, 1, 2.0, 1.0, at(9, 30)));
+        let starts: Vec<_> = h
+            .get("iron_ingot")
+            .unwrap()
+            .hourly
+            .iter()
+            .map(|c| c.start)
+            .collect();
+        assert_eq!(starts, vec![at(9, 0), at(11, 0)]);
+    }
+
+    #[test]
+    fn hourly_series_is_capped() {
+        let mut h = PriceHistory::new();
+        let base = at(0, 0);
+        for i in 0..(PRICE_HISTORY_HOURLY_CANDLES as i64 + 5) {
+            h.record(&trade(
+                TradeType::Buy,
+                1,
+                1.0,
+                1.0,
+                base + Duration::hours(i),
+            ));
+        }
+        let hourly = &h.get("iron_ingot").unwrap().hourly;
+        assert_eq!(hourly.len(), PRICE_HISTORY_HOURLY_CANDLES);
+        assert_eq!(hourly[0].start, base + Duration::hours(5));
+    }
+
+    #[test]
+    fn admin_and_unpriced_trades_are_skipped() {
+        let mut h = PriceHistory::new();
+        assert!(!h.record(&trade(TradeType::AddStock, 64, 0.0, 2.0, at(10, 0))));
+        let unpriced = Trade::new(
+            TradeType::Buy,
+            ItemId::new("iron_ingot").unwrap(),
+            1,
+            2.0,
+            "uuid".to_string(),
+        );
+        assert!(!h.record(&unpriced));
+        assert!(h.get("iron_ingot").is_none());
+    }
+
+    #[test]
+    fn summary_covers_only_the_window() {
+        let now = at(23, 0);
+        let mut h = PriceHistory::new();
+        h.record(&trade(TradeType::Buy, 4, 8.0, 5.0, now - Duration::days(3)));
+        h.record(&trade(TradeType::Buy, 4, 8.0, 2.0, at(1, 0)));
+        h.record(&trade(TradeType::Sell, 2, 3.0, 2.5, at(22, 0)));
+
+        let day = h.summary("iron_ingot", HistoryWindow::Day, now).unwrap();
+        assert!(day.contains("open 2.0000"), "{day}");
+        assert!(day.contains("close 2.5000 (+25.0%)"), "{day}");
+        assert!(day.contains("2 trades, 6 items"), "{day}");
+
+        let week = h.summary("iron_ingot", HistoryWindow::Week, now).unwrap();
+        assert!(week.contains("open 5.0000"), "{week}");
+        assert!(week.contains("3 trades"), "{week}");
+
+        assert!(h.summary("gold_ingot", HistoryWindow::Day, now).is_none());
+        let later = now + Duration::days(30);
+        assert!(
+            h.summary("iron_ingot", HistoryWindow::Week, later)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn window_parses_aliases() {
+        assert_eq!(HistoryWindow::parse("24h"), Some(HistoryWindow::Day));
+        assert_eq!(HistoryWindow::parse("1D"), Some(HistoryWindow::Day));
+        assert_eq!(HistoryWindow::parse("7d"), Some(HistoryWindow::Week));
+        assert_eq!(HistoryWindow::parse("1w"), Some(HistoryWindow::Week));
+        assert_eq!(HistoryWindow::parse("30d"), None);
+    }
+
+    #[test]
+    fn csv_has_header_and_one_row_per_candle() {
+        let mut h = PriceHistory::new();
+        h.record(&trade(TradeType::Buy, 3, 7.5, 2.5, at(10, 0)));
+        let csv = to_csv(h.get("iron_ingot").unwrap().candles(Resolution::Hourly));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("start,open,high,low,close"));
+        assert!(
+            lines[1].ends_with(",2.5,2.5,2.5,2.5,3,7.5,1"),
+            "{}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn save_then_load_round_trips_and_clears_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut h = PriceHistory::new();
+        h.record(&trade(TradeType::Buy, 3, 7.5, 2.5, at(10, 0)));
+        h.save_dirty_in_dir(dir.path()).unwrap();
+        assert!(h.dirty.is_empty());
+
+        let loaded = PriceHistory::load_from_dir(dir.path());
+        assert_eq!(loaded.get("iron_ingot"), h.get("iron_ingot"));
+    }
+
+    #[test]
+    fn corrupt_file_is_quarantined_and_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("iron_ingot.json"), "{not json").unwrap();
+        let loaded = PriceHistory::load_from_dir(dir.path());
+        assert!(loaded.get("iron_ingot").is_none());
+        assert!(!dir.path().join("iron_ingot.json").exists());
+    }
+}
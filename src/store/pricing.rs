@@ -10,9 +10,15 @@
 //!
 //! `k` is conserved by the base AMM identity and strictly increases with
 //! every fee-bearing trade — that is how fee revenue accrues in the pool.
+//!
+//! This is the default curve. Pairs can select another via `Pair::curve`;
+//! [`pair_buy_cost`] / [`pair_sell_payout`] dispatch through it (see
+//! [`super::curve`]).
 
 use super::Store;
+use super::curve::PricingCurve;
 use crate::constants::{FEE_MAX, FEE_MIN, MIN_RESERVE_FOR_PRICE};
+use crate::types::Pair;
 
 /// Returns `true` iff `fee` is finite and within `[FEE_MIN, FEE_MAX]`.
 pub fn validate_fee(fee: f64) -> bool {
@@ -32,16 +38,23 @@ pub fn reserves_sufficient(item_stock: i32, currency_stock: f64) -> bool {
 
 /// Cost in currency to buy `amount` items from `item`'s pair.
 ///
-/// Returns `None` if the item is unknown, reserves are insufficient, fee
-/// is invalid, `amount` is non-positive, or `amount >= item_stock`.
-/// See [`buy_cost_pure`] for the math.
+/// Uses the pair's curve and its `buy_fee` override when set, else the
+/// global fee. Returns `None` if the item is unknown or the curve cannot
+/// quote (see [`pair_buy_cost`]).
 pub fn calculate_buy_cost(store: &Store, item: &str, amount: i32) -> Option<f64> {
-    let pair = store.pairs.get(item)?;
-    buy_cost_pure(
+    pair_buy_cost(store.pairs.get(item)?, amount, store.config.fee)
+}
+
+/// Cost to buy `amount` items from `pair` on its own curve and buy fee.
+/// For the default constant-product curve this is exactly
+/// [`buy_cost_pure`]: `None` on insufficient reserves, invalid fee,
+/// non-positive `amount`, or `amount >= item_stock`.
+pub fn pair_buy_cost(pair: &Pair, amount: i32, global_fee: f64) -> Option<f64> {
+    pair.curve.buy_cost(
         pair.item_stock,
         pair.currency_stock,
         amount,
-        store.config.fee,
+        pair.effective_buy_fee(global_fee),
     )
 }
 
@@ -87,10 +100,10 @@ pub fn buy_cost_pure(item_stock: i32, currency_stock: f64, amount: i32, fee: f64
 
 /// Indicative spot buy price (currency per 1 item) for chat lookups.
 ///
-/// This is the marginal AMM cost of buying a single item. It is the same
-/// value [`buy_cost_pure`] would return at `amount = 1` — share the
-/// underlying math so the chat tool cannot drift from the trade bot's
-/// quoting.
+/// This is the marginal cost of buying a single item on the pair's curve.
+/// It is the same value [`pair_buy_cost`] would return at `amount = 1` —
+/// share the underlying math so the chat tool cannot drift from the trade
+/// bot's quoting.
 ///
 /// Returns `None` when reserves are below [`MIN_RESERVE_FOR_PRICE`] or any
 /// other condition that would block a real buy quote — chat surfaces this
@@ -99,28 +112,44 @@ pub fn buy_cost_pure(item_stock: i32, currency_stock: f64, amount: i32, fee: f64
 /// Caveat: this is a **spot** price. Real order quotes scale with
 /// slippage; chat must label the answer as indicative and refuse to use
 /// it for large-order math.
-pub fn indicative_spot_buy_price(item_stock: i32, currency_stock: f64, fee: f64) -> Option<f64> {
-    buy_cost_pure(item_stock, currency_stock, 1, fee)
+pub fn indicative_spot_buy_price(
+    curve: &PricingCurve,
+    item_stock: i32,
+    currency_stock: f64,
+    fee: f64,
+) -> Option<f64> {
+    curve.buy_cost(item_stock, currency_stock, 1, fee)
 }
 
 /// Indicative spot sell price (currency per 1 item) for chat lookups.
 /// See [`indicative_spot_buy_price`] for the rationale.
-pub fn indicative_spot_sell_price(item_stock: i32, currency_stock: f64, fee: f64) -> Option<f64> {
-    sell_payout_pure(item_stock, currency_stock, 1, fee)
+pub fn indicative_spot_sell_price(
+    curve: &PricingCurve,
+    item_stock: i32,
+    currency_stock: f64,
+    fee: f64,
+) -> Option<f64> {
+    curve.sell_payout(item_stock, currency_stock, 1, fee)
 }
 
 /// Payout in currency for selling `amount` items into `item`'s pair.
 ///
-/// Returns `None` if the item is unknown, reserves are insufficient, fee
-/// is invalid, `amount` is non-positive, or the result is not a positive
-/// finite number. See [`sell_payout_pure`] for the math.
+/// Uses the pair's curve and its `sell_fee` override when set, else the
+/// global fee. Returns `None` if the item is unknown or the curve cannot
+/// quote (see [`pair_sell_payout`]).
 pub fn calculate_sell_payout(store: &Store, item: &str, amount: i32) -> Option<f64> {
-    let pair = store.pairs.get(item)?;
-    sell_payout_pure(
+    pair_sell_payout(store.pairs.get(item)?, amount, store.config.fee)
+}
+
+/// Payout for selling `amount` items into `pair` on its own curve and sell
+/// fee. For the default constant-product curve this is exactly
+/// [`sell_payout_pure`].
+pub fn pair_sell_payout(pair: &Pair, amount: i32, global_fee: f64) -> Option<f64> {
+    pair.curve.sell_payout(
         pair.item_stock,
         pair.currency_stock,
         amount,
-        store.config.fee,
+        pair.effective_sell_fee(global_fee),
     )
 }
 
@@ -162,6 +191,71 @@ pub fn sell_payout_pure(
     }
 }
 
+/// `true` when a live total is within `tolerance` (a fraction, e.g. `0.01`)
+/// of a previously quoted total, in either direction. Used by `confirm` and
+/// again at execution time to decide whether a quote can still be honored.
+pub fn quote_within_tolerance(quoted: f64, live: f64, tolerance: f64) -> bool {
+    if !quoted.is_finite() || !live.is_finite() || quoted <= 0.0 {
+        return false;
+    }
+    (live - quoted).abs() <= quoted * tolerance
+}
+
+/// Largest quantity of `pair`'s item, at most `max_qty`, whose buy cost fits
+/// in `budget`, together with that cost. `None` when not even one item fits.
+/// `swap` uses this to spend one pair's sell payout on another pair; the
+/// binary search relies on buy cost growing with quantity, which holds for
+/// every curve.
+pub fn pair_max_buy_within(
+    pair: &Pair,
+    budget: f64,
+    max_qty: i32,
+    global_fee: f64,
+) -> Option<(i32, f64)> {
+    max_qty_within(budget, max_qty, |n| pair_buy_cost(pair, n, global_fee))
+}
+
+/// Largest quantity of `pair`'s item, at most `max_qty`, whose sell payout
+/// stays within `max_payout`, together with that payout. Partial (`upto`)
+/// sells use this to stay inside the diamonds the store can actually pay.
+pub fn pair_max_sell_within(
+    pair: &Pair,
+    max_payout: f64,
+    max_qty: i32,
+    global_fee: f64,
+) -> Option<(i32, f64)> {
+    max_qty_within(max_payout, max_qty, |n| {
+        pair_sell_payout(pair, n, global_fee)
+    })
+}
+
+/// Binary search for the largest `n` in `1..=max_qty` whose `total(n)` is
+/// within `limit`. `total` must grow with `n`, as both buy cost and sell
+/// payout do on every curve.
+fn max_qty_within(
+    limit: f64,
+    max_qty: i32,
+    total: impl Fn(i32) -> Option<f64>,
+) -> Option<(i32, f64)> {
+    if max_qty < 1 {
+        return None;
+    }
+    let fits = |n: i32| total(n).filter(|t| *t <= limit);
+    let mut best = (1, fits(1)?);
+    let (mut lo, mut hi) = (2, max_qty);
+    while lo <= hi {
+        let mid = lo + (hi - lo) / 2;
+        match fits(mid) {
+            Some(t) => {
+                best = (mid, t);
+                lo = mid + 1;
+            }
+            None => hi = mid - 1,
+        }
+    }
+    Some(best)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,6 +357,23 @@ mod tests {
         assert_eq!(sell_payout_pure(100, f64::NEG_INFINITY, 1, 0.125), None);
     }
 
+    // -- quote_within_tolerance ----------------------------------------------
+
+    #[test]
+    fn quote_tolerance_is_symmetric_and_inclusive() {
+        assert!(quote_within_tolerance(100.0, 101.0, 0.01));
+        assert!(quote_within_tolerance(100.0, 99.0, 0.01));
+        assert!(!quote_within_tolerance(100.0, 101.01, 0.01));
+        assert!(!quote_within_tolerance(100.0, 98.99, 0.01));
+    }
+
+    #[test]
+    fn quote_tolerance_rejects_non_finite_and_non_positive_quotes() {
+        assert!(!quote_within_tolerance(f64::NAN, 1.0, 0.01));
+        assert!(!quote_within_tolerance(1.0, f64::INFINITY, 0.01));
+        assert!(!quote_within_tolerance(0.0, 0.0, 0.01));
+    }
+
     // -- indicative_spot_* direct tests --------------------------------------
 
     #[test]
@@ -272,7 +383,7 @@ mod tests {
         // false`; a future "fix" of the `>=` bound would silently change the
         // chat contract — pin it.
         assert_eq!(
-            indicative_spot_buy_price(1, 1_000_000.0, 0.125),
+            indicative_spot_buy_price(&PricingCurve::ConstantProduct, 1, 1_000_000.0, 0.125),
             None,
             "1-stock pool must be un-quotable on buy side"
         );
@@ -284,7 +395,7 @@ mod tests {
         let currency = 1000.0;
         let fee = 0.125;
         assert_eq!(
-            indicative_spot_buy_price(stock, currency, fee),
+            indicative_spot_buy_price(&PricingCurve::ConstantProduct, stock, currency, fee),
             buy_cost_pure(stock, currency, 1, fee),
         );
     }
@@ -295,7 +406,7 @@ mod tests {
         let currency = 1000.0;
         let fee = 0.125;
         assert_eq!(
-            indicative_spot_sell_price(stock, currency, fee),
+            indicative_spot_sell_price(&PricingCurve::ConstantProduct, stock, currency, fee),
             sell_payout_pure(stock, currency, 1, fee),
         );
     }
@@ -306,19 +417,39 @@ mod tests {
         // so chat surfaces `price_available = false` rather than a number
         // produced from a pool too small to quote reliably.
         assert_eq!(
-            indicative_spot_buy_price(100, MIN_RESERVE_FOR_PRICE, 0.125),
+            indicative_spot_buy_price(
+                &PricingCurve::ConstantProduct,
+                100,
+                MIN_RESERVE_FOR_PRICE,
+                0.125
+            ),
             None,
         );
         assert_eq!(
-            indicative_spot_buy_price(100, MIN_RESERVE_FOR_PRICE / 2.0, 0.125),
+            indicative_spot_buy_price(
+                &PricingCurve::ConstantProduct,
+                100,
+                MIN_RESERVE_FOR_PRICE / 2.0,
+                0.125
+            ),
             None,
         );
         assert_eq!(
-            indicative_spot_sell_price(100, MIN_RESERVE_FOR_PRICE, 0.125),
+            indicative_spot_sell_price(
+                &PricingCurve::ConstantProduct,
+                100,
+                MIN_RESERVE_FOR_PRICE,
+                0.125
+            ),
             None,
         );
         assert_eq!(
-            indicative_spot_sell_price(100, MIN_RESERVE_FOR_PRICE / 2.0, 0.125),
+            indicative_spot_sell_price(
+                &PricingCurve::ConstantProduct,
+                100,
+                MIN_RESERVE_FOR_PRICE / 2.0,
+                0.125
+            ),
             None,
         );
     }
@@ -494,6 +625,43 @@ mod tests {
         assert_eq!(got, expected);
     }
 
+    #[test]
+    fn calculate_wrappers_use_per_pair_fee_overrides() {
+        let mut store = build_store_with(vec![("cobblestone", 100, 1000.0)]);
+        let pair = store.pairs.get_mut("cobblestone").unwrap();
+        pair.buy_fee = Some(0.02);
+        pair.sell_fee = Some(0.3);
+        assert_eq!(
+            calculate_buy_cost(&store, "cobblestone", 10),
+            buy_cost_pure(100, 1000.0, 10, 0.02)
+        );
+        assert_eq!(
+            calculate_sell_payout(&store, "cobblestone", 10),
+            sell_payout_pure(100, 1000.0, 10, 0.3)
+        );
+    }
+
+    #[test]
+    fn calculate_wrappers_and_spot_prices_dispatch_through_pair_curve() {
+        let curve = PricingCurve::Fixed {
+            price: 3.0,
+            max_stock: None,
+        };
+        let mut store = build_store_with(vec![("cobblestone", 100, 1000.0)]);
+        store.pairs.get_mut("cobblestone").unwrap().curve = curve;
+        let fee = store.config.fee;
+        assert_eq!(
+            calculate_buy_cost(&store, "cobblestone", 10),
+            curve.buy_cost(100, 1000.0, 10, fee)
+        );
+        assert_eq!(
+            calculate_sell_payout(&store, "cobblestone", 10),
+            curve.sell_payout(100, 1000.0, 10, fee)
+        );
+        let spot = indicative_spot_buy_price(&curve, 100, 1000.0, fee).unwrap();
+        assert!((spot - 3.0 * (1.0 + fee)).abs() < 1e-12);
+    }
+
     /// Minimal `Store` for pricing wrapper tests — the pricing code reads
     /// only `store.pairs` and `store.config.fee`, so the mock bot channel
     /// and empty storage/users are fine.
@@ -519,6 +687,11 @@ mod tests {
                     stack_size: 64,
                     item_stock,
                     currency_stock,
+                    buy_fee: None,
+                    sell_fee: None,
+                    curve: Default::default(),
+                    stats: Default::default(),
+                    lp_total_shares: 0.0,
                 },
             );
         }
@@ -664,6 +837,66 @@ mod tests {
                 "round-trip not lossy: paid {} got back {}", cost, payout);
         }
 
+        /// `pair_max_buy_within` returns the largest affordable quantity: its
+        /// cost fits the budget and one more item would not.
+        #[test]
+        fn max_buy_within_is_largest_affordable(
+            stock in 2i32..10_000,
+            currency in 1.0f64..100_000.0,
+            budget in 0.0f64..50_000.0,
+            max_qty in 1i32..2_000,
+        ) {
+            let pair = Pair {
+                item_stock: stock,
+                currency_stock: currency,
+                ..Default::default()
+            };
+            match pair_max_buy_within(&pair, budget, max_qty, TEST_FEE) {
+                Some((n, cost)) => {
+                    prop_assert!(n >= 1 && n <= max_qty);
+                    prop_assert!(cost <= budget, "cost {} over budget {}", cost, budget);
+                    if n < max_qty {
+                        let next = pair_buy_cost(&pair, n + 1, TEST_FEE);
+                        prop_assert!(next.is_none_or(|c| c > budget), "{} more fits", n + 1);
+                    }
+                }
+                None => {
+                    let one = pair_buy_cost(&pair, 1, TEST_FEE);
+                    prop_assert!(one.is_none_or(|c| c > budget));
+                }
+            }
+        }
+
+        /// `pair_max_sell_within` mirrors the buy search: the payout fits the
+        /// cap and one more item would not.
+        #[test]
+        fn max_sell_within_is_largest_payable(
+            stock in 1i32..10_000,
+            currency in 1.0f64..100_000.0,
+            max_payout in 0.0f64..50_000.0,
+            max_qty in 1i32..2_000,
+        ) {
+            let pair = Pair {
+                item_stock: stock,
+                currency_stock: currency,
+                ..Default::default()
+            };
+            match pair_max_sell_within(&pair, max_payout, max_qty, TEST_FEE) {
+                Some((n, payout)) => {
+                    prop_assert!(n >= 1 && n <= max_qty);
+                    prop_assert!(payout <= max_payout, "payout {} over cap {}", payout, max_payout);
+                    if n < max_qty {
+                        let next = pair_sell_payout(&pair, n + 1, TEST_FEE);
+                        prop_assert!(next.is_none_or(|p| p > max_payout), "{} more fits", n + 1);
+                    }
+                }
+                None => {
+                    let one = pair_sell_payout(&pair, 1, TEST_FEE);
+                    prop_assert!(one.is_none_or(|p| p > max_payout));
+                }
+            }
+        }
+
         /// Both pricing functions reject non-positive quantities — no "free
         /// trade" escape hatch at qty == 0 or negative.
         #[test]
@@ -21,9 +21,10 @@ use tracing::{debug, error, info, warn};
 /// `unwrap_or(0)` from a clock error) would otherwise overwrite each other.
 static ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
 
+use super::audit_log::{AuditEvent, AuditLog};
 use crate::constants::{MAX_ORDERS_PER_USER, MAX_QUEUE_SIZE, QUEUE_FILE};
 use crate::fsutil::{archive_aside, write_atomic};
-use crate::messages::QueuedOrderType;
+use crate::messages::{QueuedOrderType, TradeItem};
 
 /// An order waiting to be processed.
 ///
@@ -41,6 +42,42 @@ pub struct QueuedOrder {
     /// amount inside `order_type` (or `None` for flexible).
     pub quantity: u32,
     pub queued_at: DateTime<Utc>,
+    /// Player-supplied slippage bound in total diamonds: the most a `Buy`
+    /// may cost, or the least a `Sell` must pay out. Re-checked against the
+    /// live reserves at execution time, before any chest I/O. Ignored for
+    /// deposit/withdraw. Absent in queue files written before the field
+    /// existed, hence `serde(default)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_bound: Option<f64>,
+    /// Total diamonds locked in by `confirm` on a quote. When set, the order
+    /// executes at exactly this total provided the live price is still within
+    /// `QUOTE_TOLERANCE` of it, and is cancelled before chest I/O otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted_total: Option<f64>,
+    /// `upto` / `all` order: `quantity` is a ceiling, and the order fills as
+    /// much of it as stock, reserves, balance and storage space allow when
+    /// it runs (see `orders::fill_partial`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Progress of a buy/sell too large for one `/trade`, set while it runs
+    /// as consecutive sessions. Never set on an order still in the queue; it
+    /// lives in the `current_trade.json` copy of the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionProgress>,
+}
+
+/// Where a multi-session order stands (see `orders::run_order_sessions`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SessionProgress {
+    /// 1-based index of the session in flight.
+    pub session: u32,
+    pub sessions: u32,
+    /// Items moved by the sessions committed so far.
+    pub filled: u32,
+    /// Diamonds charged or paid by the sessions committed so far.
+    pub settled: f64,
+    /// Whole-order total, priced once before the first session.
+    pub total: f64,
 }
 
 impl QueuedOrder {
@@ -60,14 +97,52 @@ impl QueuedOrder {
             item,
             quantity,
             queued_at: Utc::now(),
+            price_bound: None,
+            quoted_total: None,
+            partial: false,
+            sessions: None,
         }
     }
 
     /// Short human-readable summary used in player messages and log lines.
     pub fn description(&self) -> String {
+        let base = self.base_description();
+        match self.sessions {
+            Some(p) => format!(
+                "{} [trade {}/{}, {} done]",
+                base, p.session, p.sessions, p.filled
+            ),
+            None => base,
+        }
+    }
+
+    fn base_description(&self) -> String {
+        if let Some(total) = self.quoted_total {
+            let verb = match self.order_type {
+                QueuedOrderType::Sell => "sell",
+                _ => "buy",
+            };
+            return format!(
+                "{} {} {} (quoted {:.2})",
+                verb, self.item, self.quantity, total
+            );
+        }
+        if self.partial {
+            let verb = match self.order_type {
+                QueuedOrderType::Sell => "sell",
+                _ => "buy",
+            };
+            return format!("{} {} up to {}", verb, self.item, self.quantity);
+        }
         match &self.order_type {
-            QueuedOrderType::Buy => format!("buy {} {}", self.item, self.quantity),
-            QueuedOrderType::Sell => format!("sell {} {}", self.item, self.quantity),
+            QueuedOrderType::Buy => match self.price_bound {
+                Some(max) => format!("buy {} {} (max {:.2})", self.item, self.quantity, max),
+                None => format!("buy {} {}", self.item, self.quantity),
+            },
+            QueuedOrderType::Sell => match self.price_bound {
+                Some(min) => format!("sell {} {} (min {:.2})", self.item, self.quantity, min),
+                None => format!("sell {} {}", self.item, self.quantity),
+            },
             QueuedOrderType::Deposit { amount } => match amount {
                 Some(amt) => format!("deposit {:.2}", amt),
                 None => "deposit (flexible)".to_string(),
@@ -76,15 +151,58 @@ impl QueuedOrder {
                 Some(amt) => format!("withdraw {:.2}", amt),
                 None => "withdraw (full balance)".to_string(),
             },
+            QueuedOrderType::BuyBasket { lines } => format!("buy {}", basket_label(lines)),
+            QueuedOrderType::SellBasket { lines } => format!("sell {}", basket_label(lines)),
+            QueuedOrderType::SellAll => {
+                format!("sell {} offered (up to {})", self.item, self.quantity)
+            }
+            QueuedOrderType::Swap { to_item } => {
+                format!("swap {} {} -> {}", self.item, self.quantity, to_item)
+            }
+            QueuedOrderType::AddLiquidity => format!("lp add {} {}", self.item, self.quantity),
+            QueuedOrderType::RemoveLiquidity { shares } => match shares {
+                Some(s) => format!("lp remove {} {:.4}", self.item, s),
+                None => format!("lp remove {} (all shares)", self.item),
+            },
+            QueuedOrderType::EscrowDeposit { escrow_id } => {
+                format!(
+                    "escrow E{} deposit {} {}",
+                    escrow_id, self.item, self.quantity
+                )
+            }
+            QueuedOrderType::EscrowRelease { escrow_id } => {
+                format!(
+                    "escrow E{} release {} {}",
+                    escrow_id, self.item, self.quantity
+                )
+            }
+            QueuedOrderType::EscrowReturn { escrow_id } => {
+                format!(
+                    "escrow E{} return {} {}",
+                    escrow_id, self.item, self.quantity
+                )
+            }
         }
     }
 }
 
+/// `cobblestone 64, glass 32`: a basket's lines as the player typed them.
+pub fn basket_label(lines: &[TradeItem]) -> String {
+    lines
+        .iter()
+        .map(|l| format!("{} {}", l.item, l.amount))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[derive(Debug)]
 pub struct OrderQueue {
     orders: VecDeque<QueuedOrder>,
     /// Monotonic order ID counter; persisted so IDs don't recycle across restarts.
     next_id: u64,
+    /// Receives `queued` and `cancelled` events. Disabled until
+    /// [`set_audit_log`](Self::set_audit_log), so test queues write nothing.
+    audit: AuditLog,
 }
 
 impl Default for OrderQueue {
@@ -98,9 +216,14 @@ impl OrderQueue {
         Self {
             orders: VecDeque::new(),
             next_id: 1,
+            audit: AuditLog::disabled(),
         }
     }
 
+    pub fn set_audit_log(&mut self, audit: AuditLog) {
+        self.audit = audit;
+    }
+
     /// Load queue from `QUEUE_FILE`, or return an empty queue if the file is
     /// absent. Called once at startup to restore pending orders across restarts.
     pub fn load() -> io::Result<Self> {
@@ -120,7 +243,7 @@ impl OrderQueue {
     /// quarantined to a `queue.json.{corrupt,unreadable}-<unix_ms>-<seq>.json`
     /// sibling and an empty queue is returned. Mirrors the patterns in
     /// `journal.rs::load_from` and `trade_state::load_persisted_from`.
-    fn load_from(path: impl AsRef<Path>) -> io::Result<Self> {
+    pub(crate) fn load_from(path: impl AsRef<Path>) -> io::Result<Self> {
         let path = path.as_ref();
 
         if !path.exists() {
@@ -183,6 +306,7 @@ impl OrderQueue {
         Ok(Self {
             orders: queue_data.orders.into_iter().collect(),
             next_id: queue_data.next_id,
+            audit: AuditLog::disabled(),
         })
     }
 
@@ -233,6 +357,43 @@ impl OrderQueue {
         order_type: QueuedOrderType,
         item: String,
         quantity: u32,
+    ) -> Result<(u64, usize), String> {
+        self.add_bounded(user_uuid, username, order_type, item, quantity, None)
+    }
+
+    /// [`add`](Self::add) with a slippage bound stored on the order (see
+    /// [`QueuedOrder::price_bound`]).
+    pub fn add_bounded(
+        &mut self,
+        user_uuid: String,
+        username: String,
+        order_type: QueuedOrderType,
+        item: String,
+        quantity: u32,
+        price_bound: Option<f64>,
+    ) -> Result<(u64, usize), String> {
+        self.add_at_path(
+            user_uuid,
+            username,
+            order_type,
+            item,
+            quantity,
+            price_bound,
+            None,
+            false,
+            Path::new(QUEUE_FILE),
+        )
+    }
+
+    /// [`add`](Self::add) for an `upto` / `all` order, which fills as much of
+    /// `quantity` as it can (see [`QueuedOrder::partial`]).
+    pub fn add_partial(
+        &mut self,
+        user_uuid: String,
+        username: String,
+        order_type: QueuedOrderType,
+        item: String,
+        quantity: u32,
     ) -> Result<(u64, usize), String> {
         self.add_at_path(
             user_uuid,
@@ -240,12 +401,40 @@ impl OrderQueue {
             order_type,
             item,
             quantity,
+            None,
+            None,
+            true,
+            Path::new(QUEUE_FILE),
+        )
+    }
+
+    /// [`add`](Self::add) for an order confirmed from a quote; the quoted
+    /// total travels with the order (see [`QueuedOrder::quoted_total`]).
+    pub fn add_quoted(
+        &mut self,
+        user_uuid: String,
+        username: String,
+        order_type: QueuedOrderType,
+        item: String,
+        quantity: u32,
+        quoted_total: f64,
+    ) -> Result<(u64, usize), String> {
+        self.add_at_path(
+            user_uuid,
+            username,
+            order_type,
+            item,
+            quantity,
+            None,
+            Some(quoted_total),
+            false,
             Path::new(QUEUE_FILE),
         )
     }
 
     /// Path-parameterized enqueue, separated so tests can simulate a save
     /// failure without touching the production `QUEUE_FILE`.
+    #[allow(clippy::too_many_arguments)]
     fn add_at_path(
         &mut self,
         user_uuid: String,
@@ -253,6 +442,9 @@ impl OrderQueue {
         order_type: QueuedOrderType,
         item: String,
         quantity: u32,
+        price_bound: Option<f64>,
+        quoted_total: Option<f64>,
+        partial: bool,
         path: &Path,
     ) -> Result<(u64, usize), String> {
         // Global backpressure. MAX_ORDERS_PER_USER alone is not enough — a
@@ -291,7 +483,7 @@ impl OrderQueue {
         let id = self.next_id;
         self.next_id += 1;
 
-        let order = QueuedOrder::new(
+        let mut order = QueuedOrder::new(
             id,
             user_uuid.clone(),
             username.clone(),
@@ -299,6 +491,9 @@ impl OrderQueue {
             item.clone(),
             quantity,
         );
+        order.price_bound = price_bound;
+        order.quoted_total = quoted_total;
+        order.partial = partial;
         self.orders.push_back(order);
 
         let position = self.orders.len();
@@ -341,6 +536,16 @@ impl OrderQueue {
             "[Queue] Order #{} queued at position {} (user={} uuid={} item={} qty={})",
             id, position, username, user_uuid, item, quantity
         );
+        if let Some(order) = self.orders.back() {
+            self.audit.record(
+                id,
+                AuditEvent::Queued {
+                    user_uuid,
+                    username,
+                    description: order.description(),
+                },
+            );
+        }
         Ok((id, position))
     }
 
@@ -442,6 +647,44 @@ impl OrderQueue {
         Ok(popped)
     }
 
+    /// Put an order that was already admitted back at the front, ahead of
+    /// everything queued since. Crash-resume uses this for an order that
+    /// was popped but never started. Caps are not re-checked: the order held
+    /// its slot when it was popped. Returns `Ok(false)` without change when
+    /// an order with the same id is still queued, which is the case after a
+    /// crash between persisting the trade-state mirror and `pop_committed`.
+    ///
+    /// Takes the queue file's path (`QUEUE_FILE` in production) because its
+    /// one caller, `resume::resume_at`, is path-parameterized for tests.
+    /// Saves before mutating, like [`pop_committed`].
+    pub(crate) fn requeue_front_at_path(
+        &mut self,
+        order: QueuedOrder,
+        path: &Path,
+    ) -> Result<bool, String> {
+        if self.orders.iter().any(|o| o.id == order.id) {
+            return Ok(false);
+        }
+        let projected = QueuePersist {
+            orders: std::iter::once(order.clone())
+                .chain(self.orders.iter().cloned())
+                .collect(),
+            next_id: self.next_id.max(order.id + 1),
+        };
+        let json = serde_json::to_string_pretty(&projected)
+            .map_err(|e| format!("failed to serialize queue: {}", e))?;
+        write_atomic(path, &json).map_err(|e| format!("failed to persist queue: {}", e))?;
+        info!(
+            "[Queue] Re-queued order #{} at the front: {} for {}",
+            order.id,
+            order.description(),
+            order.username
+        );
+        self.next_id = projected.next_id;
+        self.orders.push_front(order);
+        Ok(true)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.orders.is_empty()
     }
@@ -450,7 +693,6 @@ impl OrderQueue {
         self.orders.len()
     }
 
-    #[cfg(test)]
     pub fn get_position(&self, order_id: u64) -> Option<usize> {
         self.orders
             .iter()
@@ -531,6 +773,13 @@ impl OrderQueue {
                     description,
                     pos + 1
                 );
+                self.audit.record(
+                    order_id,
+                    AuditEvent::Cancelled {
+                        by: order.username,
+                        reason: format!("cancelled from queue position {}", pos + 1),
+                    },
+                );
 
                 Ok(())
             }
@@ -630,6 +879,9 @@ mod tests {
             order_type,
             item.to_string(),
             quantity,
+            None,
+            None,
+            false,
             path,
         )
     }
@@ -748,6 +1000,35 @@ mod tests {
         assert!(err.contains("9999"));
     }
 
+    #[test]
+    fn add_and_cancel_reach_the_audit_log() {
+        let dir = TmpDir::new("audit");
+        let path = dir.path("queue.json");
+        let audit_path = dir.path("orders.jsonl");
+        let mut queue = OrderQueue::new();
+        queue.set_audit_log(AuditLog::at(&audit_path));
+
+        let (id, _) = add_to(
+            &mut queue,
+            &path,
+            "uuid1",
+            "player1",
+            QueuedOrderType::Sell,
+            "cobblestone",
+            64,
+        )
+        .unwrap();
+        queue.cancel_at_path("uuid1", id, &path).unwrap();
+
+        let story = crate::store::audit_log::story_at(&audit_path, id).unwrap();
+        assert_eq!(story.len(), 2);
+        assert!(matches!(story[0].event, AuditEvent::Queued { .. }));
+        assert!(matches!(
+            &story[1].event,
+            AuditEvent::Cancelled { by, .. } if by == "player1"
+        ));
+    }
+
     #[test]
     fn global_cap_rejects_even_fresh_users() {
         let dir = TmpDir::new("global-cap");
@@ -840,6 +1121,99 @@ mod tests {
         assert_eq!(orders[1].0.item, "z");
     }
 
+    #[test]
+    fn price_bound_round_trips_and_defaults_when_absent() {
+        let dir = TmpDir::new("price-bound");
+        let path = dir.path("queue.json");
+        let mut queue = OrderQueue::new();
+        queue
+            .add_at_path(
+                "a".into(),
+                "pa".into(),
+                QueuedOrderType::Buy,
+                "x".into(),
+                4,
+                Some(9.5),
+                None,
+                false,
+                &path,
+            )
+            .unwrap();
+        add_to(&mut queue, &path, "a", "pa", QueuedOrderType::Sell, "x", 4).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            json.matches("price_bound").count(),
+            1,
+            "unbounded orders must not serialize the field: {json}"
+        );
+
+        let loaded = OrderQueue::load_from(&path).unwrap();
+        let orders = loaded.get_user_orders("a");
+        assert_eq!(orders[0].0.price_bound, Some(9.5));
+        assert_eq!(orders[1].0.price_bound, None);
+    }
+
+    #[test]
+    fn partial_flag_round_trips_and_shows_in_description() {
+        let dir = TmpDir::new("partial");
+        let path = dir.path("queue.json");
+        let mut queue = OrderQueue::new();
+        queue
+            .add_at_path(
+                "a".into(),
+                "pa".into(),
+                QueuedOrderType::Sell,
+                "iron_ingot".into(),
+                768,
+                None,
+                None,
+                true,
+                &path,
+            )
+            .unwrap();
+        add_to(&mut queue, &path, "a", "pa", QueuedOrderType::Buy, "x", 4).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert_eq!(json.matches("partial").count(), 1, "{json}");
+
+        let loaded = OrderQueue::load_from(&path).unwrap();
+        let orders = loaded.get_user_orders("a");
+        assert!(orders[0].0.partial);
+        assert!(!orders[1].0.partial);
+        assert_eq!(orders[0].0.description(), "sell iron_ingot up to 768");
+    }
+
+    #[test]
+    fn description_shows_session_progress() {
+        let mut order = QueuedOrder::new(
+            1,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::Buy,
+            "cobblestone".into(),
+            2000,
+        );
+        order.sessions = Some(SessionProgress {
+            session: 2,
+            sessions: 3,
+            filled: 667,
+            settled: 40.0,
+            total: 120.0,
+        });
+        assert_eq!(
+            order.description(),
+            "buy cobblestone 2000 [trade 2/3, 667 done]"
+        );
+
+        let json = serde_json::to_string(&order).unwrap();
+        let back: QueuedOrder = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.sessions, order.sessions);
+        order.sessions = None;
+        let json = serde_json::to_string(&order).unwrap();
+        assert!(!json.contains("sessions"), "{json}");
+    }
+
     #[test]
     fn description_renders_every_order_variant() {
         let buy = QueuedOrder::new(
@@ -901,6 +1275,81 @@ mod tests {
             0,
         );
         assert_eq!(wd_full.description(), "withdraw (full balance)");
+
+        let lp_add = QueuedOrder::new(
+            7,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::AddLiquidity,
+            "iron".into(),
+            64,
+        );
+        assert_eq!(lp_add.description(), "lp add iron 64");
+
+        let swap = QueuedOrder::new(
+            9,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::Swap {
+                to_item: "gold".into(),
+            },
+            "iron".into(),
+            32,
+        );
+        assert_eq!(swap.description(), "swap iron 32 -> gold");
+
+        let basket = QueuedOrder::new(
+            10,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::BuyBasket {
+                lines: vec![
+                    TradeItem {
+                        item: "cobblestone".into(),
+                        amount: 64,
+                    },
+                    TradeItem {
+                        item: "glass".into(),
+                        amount: 32,
+                    },
+                ],
+            },
+            "cobblestone".into(),
+            96,
+        );
+        assert_eq!(basket.description(), "buy cobblestone 64, glass 32");
+
+        let lp_all = QueuedOrder::new(
+            8,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::RemoveLiquidity { shares: None },
+            "iron".into(),
+            0,
+        );
+        assert_eq!(lp_all.description(), "lp remove iron (all shares)");
+
+        let escrow = QueuedOrder::new(
+            9,
+            "u".into(),
+            "p".into(),
+            QueuedOrderType::EscrowRelease { escrow_id: 3 },
+            "iron".into(),
+            64,
+        );
+        assert_eq!(escrow.description(), "escrow E3 release iron 64");
+
+        let mut bounded_buy = buy.clone();
+        bounded_buy.price_bound = Some(12.0);
+        assert_eq!(bounded_buy.description(), "buy diamond 5 (max 12.00)");
+
+        let mut bounded_sell = sell.clone();
+        bounded_sell.price_bound = Some(3.5);
+        assert_eq!(bounded_sell.description(), "sell iron 10 (min 3.50)");
+
+        let mut quoted = sell.clone();
+        quoted.quoted_total = Some(7.25);
+        assert_eq!(quoted.description(), "sell iron 10 (quoted 7.25)");
     }
 
     #[test]
@@ -1023,6 +1472,9 @@ mod tests {
             QueuedOrderType::Buy,
             "diamond".to_string(),
             1,
+            None,
+            None,
+            false,
             &dest,
         );
 
@@ -1054,6 +1506,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn requeue_front_goes_ahead_of_later_orders_once() {
+        let dir = TmpDir::new("requeue-front");
+        let path = dir.path("queue.json");
+        let mut queue = OrderQueue::new();
+        let (first, _) = add_to(
+            &mut queue,
+            &path,
+            "a",
+            "pa",
+            QueuedOrderType::Buy,
+            "dirt",
+            1,
+        )
+        .unwrap();
+        let popped = queue.pop_committed_at_path(first, &path).unwrap();
+        add_to(
+            &mut queue,
+            &path,
+            "b",
+            "pb",
+            QueuedOrderType::Buy,
+            "dirt",
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(queue.requeue_front_at_path(popped.clone(), &path), Ok(true));
+        assert_eq!(queue.requeue_front_at_path(popped, &path), Ok(false));
+
+        let loaded = OrderQueue::load_from(&path).unwrap();
+        let ids: Vec<u64> = loaded.orders.iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![first, first + 1]);
+        assert_eq!(loaded.next_id, first + 2);
+    }
+
     #[test]
     fn pop_committed_save_failure_leaves_queue_intact() {
         // Simulate a save failure by pointing `pop_committed_at_path` at a
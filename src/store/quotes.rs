@@ -0,0 +1,221 @@
+//! Short-lived price locks for the `quote` / `confirm` flow.
+//!
+//! `quote buy|sell <item> <qty>` prices the order against the live reserves
+//! and parks the result here for `QUOTE_TTL_SECS`. `confirm <id>` takes the
+//! quote back out and, if the pool has not drifted more than
+//! `QUOTE_TOLERANCE`, enqueues the order with the quoted total locked in
+//! (`QueuedOrder::quoted_total`).
+//!
+//! Quotes are deliberately NOT persisted: they expire within seconds, and a
+//! restart in that window simply means the player asks for a new one.
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::debug;
+
+use crate::constants::MAX_QUOTES_PER_USER;
+
+use super::order_book::OrderSide;
+
+/// A priced, not-yet-confirmed buy or sell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub id: u64,
+    pub user_uuid: String,
+    pub side: OrderSide,
+    pub item: String,
+    pub quantity: u32,
+    /// Total diamonds: the cost of a buy or the payout of a sell.
+    pub total: f64,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Quote {
+    pub fn description(&self) -> String {
+        format!(
+            "{} {} {} for {:.2} diamonds",
+            self.side.verb(),
+            self.quantity,
+            self.item,
+            self.total
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct QuoteBook {
+    quotes: Vec<Quote>,
+    next_id: u64,
+}
+
+impl Default for QuoteBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteBook {
+    pub fn new() -> Self {
+        Self {
+            quotes: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Record a new quote valid for `ttl` from `now`. A player already at
+    /// `MAX_QUOTES_PER_USER` loses their oldest quote rather than being
+    /// refused, since re-quoting is the normal way to refresh a price.
+    #[allow(clippy::too_many_arguments)]
+    pub fn issue(
+        &mut self,
+        user_uuid: &str,
+        side: OrderSide,
+        item: &str,
+        quantity: u32,
+        total: f64,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Quote {
+        self.purge_expired(now);
+        let held: Vec<u64> = self
+            .quotes
+            .iter()
+            .filter(|q| q.user_uuid == user_uuid)
+            .map(|q| q.id)
+            .collect();
+        if held.len() >= MAX_QUOTES_PER_USER
+            && let Some(oldest) = held.first()
+        {
+            debug!(
+                "[Quotes] Dropping Q{} for {}: per-user cap",
+                oldest, user_uuid
+            );
+            self.quotes.retain(|q| q.id != *oldest);
+        }
+
+        let quote = Quote {
+            id: self.next_id,
+            user_uuid: user_uuid.to_string(),
+            side,
+            item: item.to_string(),
+            quantity,
+            total,
+            expires_at: now + ttl,
+        };
+        self.next_id += 1;
+        self.quotes.push(quote.clone());
+        quote
+    }
+
+    /// Remove and return quote `id` for `user_uuid`. A quote is single-use:
+    /// it is consumed even when the caller then rejects it for drift.
+    pub fn take(&mut self, user_uuid: &str, id: u64, now: DateTime<Utc>) -> Result<Quote, String> {
+        let pos = self
+            .quotes
+            .iter()
+            .position(|q| q.id == id && q.user_uuid == user_uuid)
+            .ok_or_else(|| {
+                format!(
+                    "Quote Q{} not found. Quotes last a few seconds; request a new one with 'quote'.",
+                    id
+                )
+            })?;
+        let quote = self.quotes.remove(pos);
+        if now >= quote.expires_at {
+            return Err(format!(
+                "Quote Q{} has expired. Request a new one with 'quote {} {} {}'.",
+                id,
+                quote.side.verb(),
+                quote.item,
+                quote.quantity
+            ));
+        }
+        Ok(quote)
+    }
+
+    /// Drop every quote past its `expires_at`.
+    pub fn purge_expired(&mut self, now: DateTime<Utc>) {
+        self.quotes.retain(|q| now < q.expires_at);
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(book: &mut QuoteBook, uuid: &str, now: DateTime<Utc>) -> Quote {
+        book.issue(
+            uuid,
+            OrderSide::Buy,
+            "cobblestone",
+            64,
+            12.5,
+            now,
+            Duration::seconds(30),
+        )
+    }
+
+    #[test]
+    fn take_returns_quote_once() {
+        let now = Utc::now();
+        let mut book = QuoteBook::new();
+        let q = issue(&mut book, "u1", now);
+        assert_eq!(book.take("u1", q.id, now).unwrap(), q);
+        let err = book.take("u1", q.id, now).unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn take_refuses_other_users_quote_without_consuming_it() {
+        let now = Utc::now();
+        let mut book = QuoteBook::new();
+        let q = issue(&mut book, "u1", now);
+        assert!(book.take("u2", q.id, now).is_err());
+        assert!(book.take("u1", q.id, now).is_ok());
+    }
+
+    #[test]
+    fn take_rejects_expired_quote() {
+        let now = Utc::now();
+        let mut book = QuoteBook::new();
+        let q = issue(&mut book, "u1", now);
+        let err = book
+            .take("u1", q.id, now + Duration::seconds(30))
+            .unwrap_err();
+        assert!(err.contains("expired"));
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn issue_past_per_user_cap_drops_oldest() {
+        let now = Utc::now();
+        let mut book = QuoteBook::new();
+        let first = issue(&mut book, "u1", now);
+        for _ in 0..MAX_QUOTES_PER_USER {
+            issue(&mut book, "u1", now);
+        }
+        issue(&mut book, "u2", now);
+        assert_eq!(book.len(), MAX_QUOTES_PER_USER + 1);
+        assert!(book.take("u1", first.id, now).is_err());
+    }
+
+    #[test]
+    fn purge_expired_keeps_live_quotes() {
+        let now = Utc::now();
+        let mut book = QuoteBook::new();
+        issue(&mut book, "u1", now - Duration::seconds(60));
+        let live = issue(&mut book, "u1", now);
+        book.purge_expired(now);
+        assert_eq!(book.len(), 1);
+        assert!(book.take("u1", live.id, now).is_ok());
+    }
+}
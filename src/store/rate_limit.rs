@@ -492,6 +492,12 @@ impl RateLimiter {
         }
     }
 
+    /// When `key` last sent anything, accepted or throttled. `None` once the
+    /// entry has been evicted by `cleanup_stale` or was never seen.
+    pub fn last_seen(&self, key: &str) -> Option<Instant> {
+        self.limits.get(key).map(|l| l.last_attempt_time)
+    }
+
     /// Test-only: backdate a user's `last_message_time`, `last_attempt_time`,
     /// and (when present) `last_warn_time` / `last_whisper_time` by `by` so
     /// time-dependent paths (reset window, staleness, warn/whisper gates) can
@@ -589,6 +595,17 @@ mod tests {
         assert!(limiter.check("user1").is_ok());
     }
 
+    #[test]
+    fn last_seen_counts_throttled_attempts() {
+        let mut limiter = RateLimiter::new();
+        assert!(limiter.last_seen("user1").is_none());
+        assert!(limiter.check("user1").is_ok());
+        limiter.backdate("user1", Duration::from_secs(60));
+        let _ = limiter.check("user1");
+        let seen = limiter.last_seen("user1").unwrap();
+        assert!(seen.elapsed() < Duration::from_secs(60));
+    }
+
     #[test]
     fn immediate_second_message_is_rejected() {
         let mut limiter = RateLimiter::new();
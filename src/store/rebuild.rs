@@ -0,0 +1,511 @@
+//! `--rebuild-ledger`: recompute balances and reserves from the trade log.
+//!
+//! When a `data/users/<uuid>.json` or `data/pairs/<item>.json` is lost or
+//! corrupted (RECOVERY.md § 1), [`replay`] walks `data/trades/` oldest first
+//! from a baseline (zero, or the users and pairs of a snapshot taken at a
+//! known time) and applies each trade the way `orders.rs` and the handlers
+//! applied it. [`load_trades`] includes the segments `trade_retention_days`
+//! moved to `data/trades/archive/`, which startup no longer loads:
+//!
+//! | Trade                           | Balance                       | Reserve                          |
+//! | ------------------------------- | ----------------------------- | -------------------------------- |
+//! | `Buy` / `Sell`                  | `balance_change`              | `currency_stock_after`           |
+//! | `DepositBalance` / `Withdraw…`  | ± `amount_currency`           | —                                |
+//! | `AddCurrency` / `RemoveCurrency`| —                             | ± `amount_currency`              |
+//! | `AddLiquidity` / `RemoveLiquid…`| ∓ `amount_currency`           | ± `amount_currency` (floored at 0) |
+//! | `EscrowRelease`                 | buyer `balance_change`, seller + `amount_currency` | —       |
+//! | `Transfer`                      | payer − / payee + `amount_currency` | —                          |
+//! | `Refund`                        | `balance_change`              | `currency_stock_after`, if set   |
+//!
+//! Trades recorded before `balance_change` existed fall back to the same
+//! arithmetic the trade window uses: a sell credits the fractional part of
+//! the payout (`split_sell_payout`), and a buy is assumed paid in physical
+//! diamonds, which is counted in [`Replay::estimated`]. A customer trade
+//! without `currency_stock_after` moves the reserve by its amount.
+//!
+//! Payments and refunds are also posted to the ledger. Each `pay` and
+//! refund posting is matched against its `Transfer` or `Refund` trade; one
+//! with no trade (made before those were recorded, or whose trade file is
+//! lost) is replayed from the posting and counted in
+//! [`Replay::ledger_only`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use super::ledger::{Account, BUY_REFUND_MEMO, ESCROW_REFUND_MEMO, PAY_MEMO, Posting};
+use super::orders::split_sell_payout;
+use crate::types::{Pair, Trade, TradeType, User, trade_segment};
+
+/// Differences smaller than this are float noise, not damage.
+const REBUILD_TOLERANCE: f64 = 1e-6;
+
+/// How far apart a posting and the trade recording the same payment or
+/// refund may be stamped. Both are written by the same handler call.
+const POSTING_MATCH_SECS: i64 = 60;
+
+/// Where the replay starts: empty, or the balances and reserves of a
+/// snapshot with the time it was taken.
+#[derive(Debug, Default)]
+pub struct Baseline {
+    pub balances: HashMap<String, f64>,
+    pub reserves: HashMap<String, f64>,
+    /// Only trades and payments strictly after this are replayed.
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Baseline {
+    pub fn from_snapshot(
+        users: &HashMap<String, User>,
+        pairs: &HashMap<String, Pair>,
+        since: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            balances: users.iter().map(|(k, u)| (k.clone(), u.balance)).collect(),
+            reserves: pairs
+                .iter()
+                .map(|(k, p)| (k.clone(), p.currency_stock))
+                .collect(),
+            since: Some(since),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub balances: BTreeMap<String, f64>,
+    pub reserves: BTreeMap<String, f64>,
+    pub trades: usize,
+    pub payments: usize,
+    /// Buys recorded without `balance_change`, assumed paid in full with
+    /// physical diamonds.
+    pub estimated: usize,
+    /// Payments and refunds replayed from a ledger posting because no
+    /// trade records them.
+    pub ledger_only: usize,
+}
+
+/// One account whose replayed value disagrees with the file on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub account: Account,
+    /// `None` when the file is missing (or was quarantined).
+    pub on_disk: Option<f64>,
+    pub replayed: f64,
+}
+
+/// Every trade in `data/trades/`. See [`load_trades_in_dir`].
+pub fn load_trades() -> io::Result<Vec<Trade>> {
+    load_trades_in_dir(Path::new(Trade::TRADES_DIR))
+}
+
+/// The per-trade files and live segments in `trades_dir` plus its archived
+/// segments. A trade in both (an archive move cut short) is read once.
+pub fn load_trades_in_dir(trades_dir: &Path) -> io::Result<Vec<Trade>> {
+    let mut trades = trade_segment::load_archived_in_dir(trades_dir)?;
+    let archived: HashSet<DateTime<Utc>> = trades.iter().map(|t| t.timestamp).collect();
+    trades.extend(
+        Trade::load_all_with_limit_in_dir(trades_dir, usize::MAX)?
+            .into_iter()
+            .filter(|t| !archived.contains(&t.timestamp)),
+    );
+    Ok(trades)
+}
+
+/// Replay `trades` (any order) on top of `baseline`, plus the `pay` and
+/// refund postings among `postings` that no trade records.
+pub fn replay(baseline: Baseline, trades: &[Trade], postings: &[Posting]) -> Replay {
+    let since = baseline.since;
+    let after = |ts: &DateTime<Utc>| since.is_none_or(|s| *ts > s);
+    let mut out = Replay {
+        balances: baseline.balances.into_iter().collect(),
+        reserves: baseline.reserves.into_iter().collect(),
+        ..Replay::default()
+    };
+
+    let mut ordered: Vec<&Trade> = trades.iter().filter(|t| after(&t.timestamp)).collect();
+    ordered.sort_by_key(|t| t.timestamp);
+    // Transfers and refunds not yet matched to a ledger posting.
+    let mut unposted: Vec<&Trade> = Vec::new();
+    for t in ordered {
+        let item = t.item.as_str().to_string();
+        let amount = t.amount_currency;
+        let (balance, reserve): (f64, Option<f64>) = match t.trade_type {
+            TradeType::Buy => {
+                let balance = t.balance_change.unwrap_or_else(|| {
+                    out.estimated += 1;
+                    0.0
+                });
+                (balance, Some(amount))
+            }
+            TradeType::Sell => {
+                let balance = t.balance_change.unwrap_or_else(|| {
+                    split_sell_payout(amount).map_or(0.0, |(_, fractional)| fractional)
+                });
+                (balance, Some(-amount))
+            }
+            TradeType::DepositBalance => (amount, None),
+            TradeType::WithdrawBalance => (-amount, None),
+            TradeType::AddCurrency => (0.0, Some(amount)),
+            TradeType::RemoveCurrency => (0.0, Some(-amount)),
+            TradeType::AddLiquidity => (-amount, Some(amount)),
+            TradeType::RemoveLiquidity => (amount, Some(-amount)),
+            TradeType::AddStock | TradeType::RemoveStock => (0.0, None),
+            TradeType::EscrowDeposit | TradeType::EscrowReturn => (0.0, None),
+            // The seller is credited the price; the buyer's side is
+            // `balance_change`, as on a buy.
+            TradeType::EscrowRelease => {
+                if let Some(seller) = &t.counterparty {
+                    *out.balances.entry(seller.clone()).or_insert(0.0) += amount;
+                }
+                (t.balance_change.unwrap_or(-amount), None)
+            }
+            TradeType::Transfer => {
+                if let Some(payee) = &t.counterparty {
+                    *out.balances.entry(payee.clone()).or_insert(0.0) += amount;
+                }
+                *out.balances.entry(t.user_uuid.clone()).or_insert(0.0) -= amount;
+                out.payments += 1;
+                unposted.push(t);
+                continue;
+            }
+            TradeType::Refund => {
+                unposted.push(t);
+                (
+                    t.balance_change.unwrap_or(amount),
+                    t.currency_stock_after.map(|_| -amount),
+                )
+            }
+        };
+        if balance != 0.0 {
+            *out.balances.entry(t.user_uuid.clone()).or_insert(0.0) += balance;
+        }
+        if let Some(delta) = reserve {
+            let r = out.reserves.entry(item).or_insert(0.0);
+            *r = match (&t.trade_type, t.currency_stock_after) {
+                (TradeType::Buy | TradeType::Sell | TradeType::Refund, Some(after)) => after,
+                (TradeType::RemoveLiquidity, _) => (*r + delta).max(0.0),
+                _ => *r + delta,
+            };
+        }
+        out.trades += 1;
+    }
+
+    for p in postings.iter().filter(|p| after(&p.ts)) {
+        let is_pay = p.memo == PAY_MEMO;
+        if !is_pay && p.memo != BUY_REFUND_MEMO && p.memo != ESCROW_REFUND_MEMO {
+            continue;
+        }
+        if let Some(i) = unposted.iter().position(|t| records(t, p)) {
+            unposted.swap_remove(i);
+            continue;
+        }
+        if let Account::Player(payer) = &p.debit {
+            *out.balances.entry(payer.clone()).or_insert(0.0) -= p.amount;
+        }
+        if let Account::Player(payee) = &p.credit {
+            *out.balances.entry(payee.clone()).or_insert(0.0) += p.amount;
+        }
+        if is_pay {
+            out.payments += 1;
+        }
+        out.ledger_only += 1;
+    }
+    out
+}
+
+/// Whether `t` is the `Transfer` or `Refund` trade recording posting `p`.
+fn records(t: &Trade, p: &Posting) -> bool {
+    let player = |uuid: &str| Account::Player(uuid.to_string());
+    let same_accounts = match t.trade_type {
+        TradeType::Transfer => {
+            p.memo == PAY_MEMO
+                && p.debit == player(&t.user_uuid)
+                && t.counterparty.as_deref().map(player).as_ref() == Some(&p.credit)
+        }
+        TradeType::Refund => p.memo != PAY_MEMO && p.credit == player(&t.user_uuid),
+        _ => false,
+    };
+    let amount = match t.trade_type {
+        TradeType::Refund => t.balance_change.unwrap_or(t.amount_currency),
+        _ => t.amount_currency,
+    };
+    same_accounts
+        && (p.amount - amount).abs() <= REBUILD_TOLERANCE
+        && (p.ts - t.timestamp).num_seconds().abs() <= POSTING_MATCH_SECS
+}
+
+/// Every balance and reserve where `replay` and the files disagree. A user
+/// or pair on disk that the replay never touched is left out: with no
+/// trades it has nothing to compare against.
+pub fn diff(
+    replay: &Replay,
+    users: &HashMap<String, User>,
+    pairs: &HashMap<String, Pair>,
+) -> Vec<Difference> {
+    let differs = |on_disk: Option<f64>, replayed: f64| {
+        on_disk.is_none_or(|d| (d - replayed).abs() > REBUILD_TOLERANCE)
+    };
+    let mut out = Vec::new();
+    for (uuid, &replayed) in &replay.balances {
+        let on_disk = users.get(uuid).map(|u| u.balance);
+        if differs(on_disk, replayed) {
+            out.push(Difference {
+                account: Account::Player(uuid.clone()),
+                on_disk,
+                replayed,
+            });
+        }
+    }
+    for (item, &replayed) in &replay.reserves {
+        let on_disk = pairs.get(item).map(|p| p.currency_stock);
+        if differs(on_disk, replayed) {
+            out.push(Difference {
+                account: Account::Reserve(item.clone()),
+                on_disk,
+                replayed,
+            });
+        }
+    }
+    out
+}
+
+/// Apply `diffs` to copies of the affected users and pairs, ready to save.
+/// A missing user is recreated with its UUID as the username (the next
+/// whisper corrects it) and no operator flag or LP shares. A missing pair
+/// cannot be recreated from the trade log, so it is returned as a line for
+/// the operator instead.
+pub fn apply(
+    diffs: &[Difference],
+    users: &HashMap<String, User>,
+    pairs: &HashMap<String, Pair>,
+) -> (Vec<User>, Vec<Pair>, Vec<String>) {
+    let mut fixed_users = Vec::new();
+    let mut fixed_pairs = Vec::new();
+    let mut skipped = Vec::new();
+    for d in diffs {
+        match &d.account {
+            Account::Player(uuid) => {
+                let mut user = users.get(uuid).cloned().unwrap_or_else(|| User {
+                    uuid: uuid.clone(),
+                    username: uuid.clone(),
+                    ..User::default()
+                });
+                user.balance = d.replayed;
+                fixed_users.push(user);
+            }
+            Account::Reserve(item) => match pairs.get(item) {
+                Some(pair) => {
+                    let mut pair = pair.clone();
+                    pair.currency_stock = d.replayed;
+                    fixed_pairs.push(pair);
+                }
+                None => skipped.push(format!(
+                    "Pair {} is missing on disk; recreate it with \"Add pair\" and set its reserve to {:.4}",
+                    item, d.replayed
+                )),
+            },
+            Account::Vault | Account::Adjustment => {}
+        }
+    }
+    (fixed_users, fixed_pairs, skipped)
+}
+
+impl Difference {
+    pub fn describe(&self, users: &HashMap<String, User>) -> String {
+        let who = match &self.account {
+            Account::Player(uuid) => users.get(uuid).map_or_else(
+                || self.account.to_string(),
+                |u| format!("{} ({})", self.account, u.username),
+            ),
+            _ => self.account.to_string(),
+        };
+        match self.on_disk {
+            Some(d) => format!(
+                "{}: {:.4} on disk, {:.4} replayed ({:+.4})",
+                who,
+                d,
+                self.replayed,
+                self.replayed - d
+            ),
+            None => format!("{}: missing on disk, {:.4} replayed", who, self.replayed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ItemId;
+    use chrono::TimeZone;
+
+    fn trade(
+        n: u32,
+        trade_type: TradeType,
+        user: &str,
+        amount: f64,
+        reserve_after: Option<f64>,
+    ) -> Trade {
+        let mut t = Trade::new(
+            trade_type,
+            ItemId::new("cobblestone").unwrap(),
+            1,
+            amount,
+            user.to_string(),
+        );
+        t.timestamp = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, n).unwrap();
+        t.currency_stock_after = reserve_after;
+        t
+    }
+
+    fn pay(n: u32, from: &str, to: &str, amount: f64) -> Posting {
+        Posting {
+            seq: u64::from(n),
+            ts: Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, n).unwrap(),
+            order_id: None,
+            debit: Account::Player(from.to_string()),
+            credit: Account::Player(to.to_string()),
+            amount,
+            memo: PAY_MEMO.to_string(),
+        }
+    }
+
+    #[test]
+    fn replay_follows_each_trade_type() {
+        let trades = vec![
+            trade(1, TradeType::AddCurrency, "op", 100.0, None),
+            trade(2, TradeType::DepositBalance, "u1", 20.0, None),
+            trade(3, TradeType::Buy, "u1", 12.5, Some(112.5)).with_balance_change(-12.5),
+            // Recorded before `balance_change`: fractional part to balance.
+            trade(4, TradeType::Sell, "u1", 3.25, Some(109.25)),
+            trade(5, TradeType::WithdrawBalance, "u1", 5.0, None),
+            trade(6, TradeType::AddLiquidity, "u1", 2.0, None),
+        ];
+        let r = replay(Baseline::default(), &trades, &[pay(7, "u1", "u2", 0.5)]);
+        assert_eq!(r.trades, 6);
+        assert_eq!(r.payments, 1);
+        assert_eq!(r.ledger_only, 1, "a payment older than transfers");
+        assert_eq!(r.estimated, 0);
+        assert!((r.balances["u1"] - (20.0 - 12.5 + 0.25 - 5.0 - 2.0 - 0.5)).abs() < 1e-9);
+        assert!((r.balances["u2"] - 0.5).abs() < 1e-9);
+        assert!(!r.balances.contains_key("op"));
+        assert!((r.reserves["cobblestone"] - 111.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_rebuild_from_zero_reads_the_archived_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let trades: Vec<Trade> = (1..=3)
+            .map(|d| {
+                let mut t = trade(0, TradeType::DepositBalance, "u1", 10.0, None);
+                t.timestamp = Utc.with_ymd_and_hms(2026, 3, d, 12, 0, 0).unwrap();
+                t
+            })
+            .collect();
+        Trade::save_all_in_dir(&trades, dir.path(), trades.len()).unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        let report = trade_segment::compact_in_dir(dir.path(), today, Some(1)).unwrap();
+        assert_eq!(report.segments_archived, 1, "day 1 is past retention");
+        assert_eq!(
+            Trade::load_all_with_limit_in_dir(dir.path(), usize::MAX)
+                .unwrap()
+                .len(),
+            2,
+            "startup no longer sees the archived day"
+        );
+
+        let loaded = load_trades_in_dir(dir.path()).unwrap();
+        assert_eq!(loaded.len(), 3);
+        let r = replay(Baseline::default(), &loaded, &[]);
+        assert_eq!(r.balances["u1"], 30.0);
+    }
+
+    #[test]
+    fn replay_from_a_snapshot_skips_what_it_already_holds() {
+        let mut baseline = Baseline {
+            since: Some(Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 2).unwrap()),
+            ..Baseline::default()
+        };
+        baseline.balances.insert("u1".into(), 20.0);
+        let trades = vec![
+            trade(1, TradeType::DepositBalance, "u1", 20.0, None),
+            trade(3, TradeType::Buy, "u1", 4.0, Some(4.0)),
+        ];
+        let r = replay(baseline, &trades, &[pay(2, "u1", "u2", 1.0)]);
+        assert_eq!(r.trades, 1);
+        assert_eq!(r.payments, 0);
+        assert_eq!(r.estimated, 1, "a buy without balance_change is estimated");
+        assert!((r.balances["u1"] - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn transfers_and_refunds_replay_from_trades_and_match_their_postings() {
+        let mut transfer = Trade::transfer("u1".into(), "u2".into(), 3.0, None);
+        transfer.timestamp = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 2).unwrap();
+        let mut refund = Trade::refund(ItemId::new("cobblestone").unwrap(), 4.0, "u2".into());
+        refund.timestamp = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 3).unwrap();
+        let trades = vec![
+            trade(1, TradeType::DepositBalance, "u1", 10.0, None),
+            transfer,
+            refund,
+        ];
+        let mut refund_posting = pay(3, "-", "u2", 4.0);
+        refund_posting.debit = Account::Vault;
+        refund_posting.memo = BUY_REFUND_MEMO.to_string();
+
+        // The postings match the trades and are not applied twice.
+        let r = replay(
+            Baseline::default(),
+            &trades,
+            &[pay(2, "u1", "u2", 3.0), refund_posting],
+        );
+        assert_eq!(r.payments, 1);
+        assert_eq!(r.ledger_only, 0);
+        assert!((r.balances["u1"] - 7.0).abs() < 1e-9);
+        assert!((r.balances["u2"] - 7.0).abs() < 1e-9);
+
+        // Without the ledger the trade log alone gives the same balances.
+        let alone = replay(Baseline::default(), &trades, &[]);
+        assert_eq!(alone.balances, r.balances);
+    }
+
+    #[test]
+    fn a_refund_that_left_a_reserve_sets_it() {
+        let refund = trade(2, TradeType::Refund, "u1", 3.25, Some(96.75)).with_balance_change(0.25);
+        let trades = vec![trade(1, TradeType::AddCurrency, "op", 100.0, None), refund];
+        let r = replay(Baseline::default(), &trades, &[]);
+        assert!((r.balances["u1"] - 0.25).abs() < 1e-9);
+        assert!((r.reserves["cobblestone"] - 96.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn diff_and_apply_repair_a_lost_user_and_a_drifted_reserve() {
+        let trades = vec![
+            trade(1, TradeType::DepositBalance, "u1", 20.0, None),
+            trade(2, TradeType::AddCurrency, "op", 50.0, None),
+        ];
+        let r = replay(Baseline::default(), &trades, &[]);
+        let pair = Pair {
+            item: ItemId::new("cobblestone").unwrap(),
+            currency_stock: 49.0,
+            ..Pair::default()
+        };
+        let pairs = HashMap::from([("cobblestone".to_string(), pair)]);
+
+        let diffs = diff(&r, &HashMap::new(), &pairs);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].on_disk, None);
+        assert!(diffs[1].describe(&HashMap::new()).contains("+1.0000"));
+
+        let (users, fixed_pairs, skipped) = apply(&diffs, &HashMap::new(), &pairs);
+        assert_eq!(users[0].uuid, "u1");
+        assert!((users[0].balance - 20.0).abs() < 1e-9);
+        assert!((fixed_pairs[0].currency_stock - 50.0).abs() < 1e-9);
+        assert!(skipped.is_empty());
+
+        let (_, _, skipped) = apply(&diffs, &HashMap::new(), &HashMap::new());
+        assert_eq!(skipped.len(), 1, "a missing pair is reported, not invented");
+    }
+}
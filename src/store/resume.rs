@@ -0,0 +1,714 @@
+//! Crash-resume for a trade that was in flight when the process died.
+//!
+//! `Store::new` archives a leftover `current_trade.json` as crash evidence
+//! and keeps a non-terminal state on `Store::interrupted_trade`; `Store::run`
+//! hands it to [`resume`] before taking the first order. What happens
+//! depends on how far the trade got (see [`plan`]):
+//!
+//! | Phase         | Action                                                      |
+//! | ------------- | ----------------------------------------------------------- |
+//! | `Queued`      | Re-queue at the front: nothing had happened yet             |
+//! | `Withdrawing` | Return the withdraw plan to storage, cancel the order       |
+//! | `Trading`     | Return the withdraw plan to storage, cancel, flag the trade |
+//! | `Depositing`  | Put what the bot received into storage, cancel the order    |
+//!
+//! A plan is only as good as the crash let it get: a withdrawal may have
+//! stopped halfway, and a completed `/trade` took the withdrawn items away.
+//! So each return is first cut down to what the bot's inventory actually
+//! holds ([`clamp_to_held`]); storage never gains items the bot never had.
+//!
+//! A cancelled order never reaches the ledger, which only changes at
+//! `Committed`. The player hears about it from a held notice on their next
+//! whisper (see `notices`). `cj-store --resume-dry-run` prints the same plan
+//! for the file on disk without acting on it.
+
+use std::path::Path;
+
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+use super::Store;
+use super::audit_log::AuditEvent;
+use super::queue::QueuedOrder;
+use super::rollback;
+use super::trade_state::{self, TradeState};
+use crate::constants::{CHEST_OP_TIMEOUT_SECS, NOTICES_FILE, QUEUE_FILE};
+use crate::messages::{BotInstruction, TradeItem};
+use crate::types::storage::ChestTransfer;
+
+/// What crash-resume does with an interrupted trade.
+#[derive(Debug, Clone)]
+pub enum ResumeAction {
+    /// Put the order back at the front of the queue.
+    Requeue(QueuedOrder),
+    /// Deposit `transfers`, cut down to what the bot holds, back into
+    /// storage from its inventory, then cancel.
+    ReturnToStorage {
+        transfers: Vec<ChestTransfer>,
+        trade: TradeOutcome,
+    },
+    /// Cancel the rest of a multi-session order, without chest I/O.
+    Cancel,
+    /// Terminal state: nothing to resume.
+    Nothing,
+}
+
+/// How far the `/trade` got before the crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeOutcome {
+    NotStarted,
+    /// The crash hit an open `/trade`: it may have gone through, leaving
+    /// the player with what the bot offered.
+    Unknown,
+    /// The `/trade` went through, so the player has what the bot offered
+    /// and the ledger needs an operator audit.
+    Completed,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResumePlan {
+    pub order: QueuedOrder,
+    pub phase: &'static str,
+    pub action: ResumeAction,
+    /// Held for the player and whispered on their next message.
+    pub notice: Option<String>,
+}
+
+/// Decide what to do with `state`. Pure, so the dry run reports exactly
+/// what startup would do.
+pub fn plan(state: &TradeState) -> ResumePlan {
+    let order = state.order().clone();
+    let what = format!("#{} ({})", order.id, order.description());
+    let (action, notice) = match state {
+        // A multi-session order stops after the sessions that committed,
+        // as it would have had the next session failed.
+        TradeState::Queued(o) => match o.sessions {
+            Some(p) if p.filled > 0 => (
+                ResumeAction::Cancel,
+                Some(format!(
+                    "Your order {} stopped after {} of {} when the store restarted. The rest was cancelled; order it again if you still want it.",
+                    what, p.filled, o.quantity
+                )),
+            ),
+            _ => {
+                let mut requeued = o.clone();
+                requeued.sessions = None;
+                (ResumeAction::Requeue(requeued), None)
+            }
+        },
+        TradeState::Withdrawing { plan, .. } => (
+            ResumeAction::ReturnToStorage {
+                transfers: plan.clone(),
+                trade: TradeOutcome::NotStarted,
+            },
+            Some(format!(
+                "Your order {} was cancelled because the store restarted before the trade. Nothing was charged or traded.",
+                what
+            )),
+        ),
+        TradeState::Trading { withdrawn, .. } => (
+            ResumeAction::ReturnToStorage {
+                transfers: withdrawn.clone(),
+                trade: TradeOutcome::Unknown,
+            },
+            Some(format!(
+                "Your order {} was interrupted by a store restart during the trade and has been cancelled; your balance was not changed. If anything changed hands in that trade, contact an operator.",
+                what
+            )),
+        ),
+        TradeState::Depositing { deposit_plan, .. } => (
+            ResumeAction::ReturnToStorage {
+                transfers: deposit_plan.clone(),
+                trade: TradeOutcome::Completed,
+            },
+            Some(format!(
+                "Your order {} was interrupted by a store restart after the trade and cancelled before your balance was updated. Contact an operator to settle it.",
+                what
+            )),
+        ),
+        TradeState::Committed(_) | TradeState::RolledBack { .. } => (ResumeAction::Nothing, None),
+    };
+    ResumePlan {
+        order,
+        phase: state.phase(),
+        action,
+        notice,
+    }
+}
+
+impl ResumePlan {
+    /// Operator-facing description, one line per step.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Order #{} ({}) for {} was interrupted in phase '{}'.",
+            self.order.id,
+            self.order.description(),
+            self.order.username,
+            self.phase
+        )];
+        match &self.action {
+            ResumeAction::Requeue(_) => {
+                lines.push("Re-queue it at the front; no chest or ledger work had started.".into())
+            }
+            ResumeAction::ReturnToStorage { transfers, trade } => {
+                for (item, group) in group_by_item(transfers) {
+                    let amount: i32 = group.iter().map(|t| t.amount.max(0)).sum();
+                    lines.push(format!(
+                        "Deposit up to {} x {} from the bot back into {} chest(s), as much as it holds, then cancel.",
+                        amount,
+                        item,
+                        group.len()
+                    ));
+                }
+                if transfers.is_empty() {
+                    lines.push("No planned transfers to return; cancel.".into());
+                }
+                match trade {
+                    TradeOutcome::NotStarted => {}
+                    TradeOutcome::Unknown => lines.push(
+                        "The crash hit an open /trade: reconcile the bot's inventory and the player's side (RECOVERY.md section 4)."
+                            .into(),
+                    ),
+                    TradeOutcome::Completed => lines.push(
+                        "The /trade had completed: audit the ledger for this order (RECOVERY.md section 4)."
+                            .into(),
+                    ),
+                }
+            }
+            ResumeAction::Cancel => {
+                lines.push("Cancel the rest; earlier sessions stay committed.".into())
+            }
+            ResumeAction::Nothing => {
+                lines.push("Terminal phase; nothing to resume, audit the ledger.".into())
+            }
+        }
+        if let Some(notice) = &self.notice {
+            lines.push(format!("Player notice: {}", notice));
+        }
+        lines
+    }
+}
+
+/// Transfers grouped by item in first-seen order, since
+/// `rollback::deposit_transfers` takes one item per call.
+fn group_by_item(transfers: &[ChestTransfer]) -> Vec<(String, Vec<ChestTransfer>)> {
+    let mut groups: Vec<(String, Vec<ChestTransfer>)> = Vec::new();
+    for t in transfers {
+        match groups.iter_mut().find(|(item, _)| item == t.item.as_str()) {
+            Some((_, group)) => group.push(t.clone()),
+            None => groups.push((t.item.as_str().to_string(), vec![t.clone()])),
+        }
+    }
+    groups
+}
+
+/// `transfers` cut down to what `held` says the bot has: per item, steps
+/// are kept in order until the held amount runs out, the last one
+/// shortened. A withdrawal fills the plan's steps in order, so the steps
+/// kept are the ones that had run.
+fn clamp_to_held(transfers: &[ChestTransfer], held: &[TradeItem]) -> Vec<ChestTransfer> {
+    let mut left: Vec<(&str, i32)> = held.iter().map(|t| (t.item.as_str(), t.amount)).collect();
+    let mut out = Vec::new();
+    for t in transfers {
+        let Some((_, remaining)) = left.iter_mut().find(|(item, _)| *item == t.item.as_str())
+        else {
+            continue;
+        };
+        let amount = t.amount.min(*remaining);
+        if amount <= 0 {
+            continue;
+        }
+        *remaining -= amount;
+        out.push(ChestTransfer {
+            amount,
+            ..t.clone()
+        });
+    }
+    out
+}
+
+/// Ask the bot what it is holding.
+async fn count_held(store: &Store) -> Result<Vec<TradeItem>, String> {
+    let (tx, rx) = oneshot::channel();
+    store
+        .bot_tx
+        .send(BotInstruction::CountInventory { respond_to: tx })
+        .await
+        .map_err(|e| format!("Failed to send the inventory count to the bot: {}", e))?;
+    match tokio::time::timeout(tokio::time::Duration::from_secs(CHEST_OP_TIMEOUT_SECS), rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("Bot dropped the inventory count response channel".to_string()),
+        Err(_) => Err(format!(
+            "Inventory count timed out after {}s",
+            CHEST_OP_TIMEOUT_SECS
+        )),
+    }
+}
+
+/// Deposit `transfers`, clamped to `held`, back into storage and report
+/// each item's outcome.
+async fn return_held(
+    store: &mut Store,
+    transfers: &[ChestTransfer],
+    held: &[TradeItem],
+    report: &mut Vec<String>,
+) {
+    let clamped = clamp_to_held(transfers, held);
+    for (item, group) in group_by_item(transfers) {
+        let planned: i32 = group.iter().map(|t| t.amount.max(0)).sum();
+        let returnable: Vec<ChestTransfer> = clamped
+            .iter()
+            .filter(|t| t.item.as_str() == item)
+            .cloned()
+            .collect();
+        if returnable.is_empty() {
+            report.push(format!("The bot holds no {}; nothing to return.", item));
+            continue;
+        }
+        let stack_size = store.pairs.get(&item).map_or(64, |p| p.stack_size);
+        let rb =
+            rollback::deposit_transfers(store, &returnable, &item, stack_size, "[Resume]").await;
+        let line = match rb.partial_message() {
+            None if rb.items_returned < planned => format!(
+                "Returned {} x {} to storage; the bot held only that much of the {} planned.",
+                rb.items_returned, item, planned
+            ),
+            None => format!("Returned {} x {} to storage.", rb.items_returned, item),
+            Some(residue) => format!(
+                "Returning {} to storage finished with failures: {}. Check the bot's inventory.",
+                item, residue
+            ),
+        };
+        if rb.has_failures() {
+            error!("[Resume] {}", line);
+        }
+        report.push(line);
+    }
+}
+
+/// Act on an interrupted trade and return the operator report, which is
+/// also logged line by line.
+pub async fn resume(store: &mut Store, state: TradeState) -> Vec<String> {
+    resume_at(store, state, Path::new(QUEUE_FILE), Path::new(NOTICES_FILE)).await
+}
+
+/// Path-parameterized [`resume`] so tests do not write into `data/`.
+async fn resume_at(
+    store: &mut Store,
+    state: TradeState,
+    queue_path: &Path,
+    notices_path: &Path,
+) -> Vec<String> {
+    let plan = plan(&state);
+    let mut report = plan.describe();
+    for line in &report {
+        warn!("[Resume] {}", line);
+    }
+    let planned = report.len();
+    let mut notice = plan.notice;
+
+    match plan.action {
+        ResumeAction::Requeue(order) => {
+            let id = order.id;
+            match store.order_queue.requeue_front_at_path(order, queue_path) {
+                Ok(true) => report.push(format!("Re-queued order #{} at the front.", id)),
+                Ok(false) => report.push(format!(
+                    "Order #{} was still in the queue; left in place.",
+                    id
+                )),
+                Err(e) => {
+                    error!("[Resume] Failed to re-queue order #{}: {}", id, e);
+                    report.push(format!("FAILED to re-queue order #{}: {}", id, e));
+                    notice = Some(format!(
+                        "Your order #{} was lost when the store restarted. Nothing was charged; please order again.",
+                        id
+                    ));
+                }
+            }
+        }
+        ResumeAction::ReturnToStorage { transfers, .. } if transfers.is_empty() => {}
+        ResumeAction::ReturnToStorage { transfers, .. } => match count_held(store).await {
+            Err(e) => {
+                let line = format!(
+                    "Could not count the bot's inventory ({}); nothing was returned. Return the items by hand (RECOVERY.md section 4).",
+                    e
+                );
+                error!("[Resume] {}", line);
+                report.push(line);
+            }
+            Ok(held) => return_held(store, &transfers, &held, &mut report).await,
+        },
+        ResumeAction::Cancel | ResumeAction::Nothing => {}
+    }
+
+    if let Some(message) = notice {
+        store
+            .notices
+            .push_at(&plan.order.user_uuid, message, notices_path);
+        report.push(format!(
+            "Notice held for {} until their next whisper.",
+            plan.order.username
+        ));
+    }
+    store.dirty = true;
+    for line in &report[planned..] {
+        info!("[Resume] {}", line);
+    }
+    store.audit_log.record(
+        plan.order.id,
+        AuditEvent::Resumed {
+            phase: plan.phase.to_string(),
+            outcome: report[planned..].to_vec(),
+        },
+    );
+    report
+}
+
+/// Print what startup would do with `data/current_trade.json`, without
+/// touching it. Backs `cj-store --resume-dry-run`.
+pub fn dry_run_report() -> Result<Vec<String>, String> {
+    let path = Path::new(trade_state::TRADE_STATE_FILE);
+    let contents = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(vec![format!(
+                "No interrupted trade: {} does not exist.",
+                path.display()
+            )]);
+        }
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let state: TradeState = serde_json::from_str(&contents).map_err(|e| {
+        format!(
+            "{} is unreadable ({}); startup will quarantine it and resume nothing.",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(plan(&state).describe())
+}
+
+#[cfg(test)]
+mod tests {
+    //! One test per persisted phase, driving `resume_at` against the mock
+    //! bots from the `rollback` tests.
+    use super::*;
+    use crate::messages::{BotInstruction, QueuedOrderType};
+    use crate::store::queue::SessionProgress;
+    use crate::store::rollback::tests::{
+        make_store, single_node_storage, spawn_auto_ack_bot, spawn_bot_error_bot, transfer,
+    };
+    use crate::store::trade_state::TradeResult;
+    use tokio::sync::mpsc;
+
+    fn order() -> QueuedOrder {
+        QueuedOrder::new(
+            7,
+            "uuid-alice".to_string(),
+            "alice".to_string(),
+            QueuedOrderType::Buy,
+            "cobblestone".to_string(),
+            64,
+        )
+    }
+
+    struct Paths {
+        _dir: tempfile::TempDir,
+        queue: std::path::PathBuf,
+        notices: std::path::PathBuf,
+    }
+
+    fn paths() -> Paths {
+        let dir = tempfile::tempdir().unwrap();
+        Paths {
+            queue: dir.path().join("queue.json"),
+            notices: dir.path().join("notices.json"),
+            _dir: dir,
+        }
+    }
+
+    async fn run(store: &mut Store, state: TradeState) -> (Vec<String>, Paths) {
+        let p = paths();
+        let report = resume_at(store, state, &p.queue, &p.notices).await;
+        (report, p)
+    }
+
+    fn slot0(store: &Store) -> i32 {
+        store.storage.nodes[0].chests[2].amounts[0]
+    }
+
+    /// Answer inventory counts with `held` and pass every other
+    /// instruction on to the returned receiver, for one of the `rollback`
+    /// mock bots to handle.
+    fn spawn_holding_bot(
+        mut rx: mpsc::Receiver<BotInstruction>,
+        held: &[(&str, i32)],
+    ) -> mpsc::Receiver<BotInstruction> {
+        let held: Vec<TradeItem> = held
+            .iter()
+            .map(|(item, amount)| TradeItem {
+                item: item.to_string(),
+                amount: *amount,
+            })
+            .collect();
+        let (fwd_tx, fwd_rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                match msg {
+                    BotInstruction::CountInventory { respond_to } => {
+                        let _ = respond_to.send(Ok(held.clone()));
+                    }
+                    other => {
+                        if fwd_tx.send(other).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        fwd_rx
+    }
+
+    #[tokio::test]
+    async fn queued_order_goes_back_to_the_front_of_the_queue() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+
+        let (report, p) = run(&mut store, TradeState::new(order())).await;
+
+        assert_eq!(store.order_queue.peek_front().map(|o| o.id), Some(7));
+        assert!(p.queue.exists(), "re-queue must be persisted");
+        assert!(store.notices.pending("uuid-alice").is_empty());
+        assert!(report.iter().any(|l| l.contains("Re-queued order #7")));
+        assert!(rx.try_recv().is_err(), "no bot work for a queued order");
+    }
+
+    #[tokio::test]
+    async fn queued_session_after_fills_is_cancelled_not_requeued() {
+        let (tx, _rx) = mpsc::channel(8);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let mut o = order();
+        o.quantity = 1000;
+        o.sessions = Some(SessionProgress {
+            session: 2,
+            sessions: 2,
+            filled: 500,
+            settled: 50.0,
+            total: 100.0,
+        });
+
+        run(&mut store, TradeState::new(o)).await;
+
+        assert!(store.order_queue.is_empty());
+        let held = store.notices.pending("uuid-alice");
+        assert_eq!(held.len(), 1);
+        assert!(held[0].message.contains("stopped after 500 of 1000"));
+    }
+
+    #[tokio::test]
+    async fn withdrawing_returns_the_plan_to_storage_and_cancels() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_auto_ack_bot(spawn_holding_bot(rx, &[("cobblestone", 64)]));
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert_eq!(slot0(&store), 64);
+        assert!(store.order_queue.is_empty());
+        assert!(
+            report
+                .iter()
+                .any(|l| l == "Returned 64 x cobblestone to storage.")
+        );
+        assert!(
+            store.notices.pending("uuid-alice")[0]
+                .message
+                .contains("Nothing was charged")
+        );
+    }
+
+    #[tokio::test]
+    async fn withdrawing_only_returns_what_the_bot_holds() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_auto_ack_bot(spawn_holding_bot(rx, &[("cobblestone", 40)]));
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        // The crash cut the withdrawal short.
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert_eq!(slot0(&store), 40);
+        assert!(
+            report
+                .iter()
+                .any(|l| l.contains("Returned 40 x cobblestone") && l.contains("of the 64 planned"))
+        );
+    }
+
+    #[tokio::test]
+    async fn withdrawing_with_nothing_held_touches_no_chest() {
+        let (tx, rx) = mpsc::channel(8);
+        let mut chest_ops = spawn_holding_bot(rx, &[("dirt", 10)]);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert!(chest_ops.try_recv().is_err(), "nothing to deposit");
+        assert_eq!(slot0(&store), 0);
+        assert!(
+            report
+                .iter()
+                .any(|l| l == "The bot holds no cobblestone; nothing to return.")
+        );
+    }
+
+    #[tokio::test]
+    async fn withdrawing_returns_nothing_when_the_inventory_count_fails() {
+        let (tx, mut rx) = mpsc::channel::<BotInstruction>(8);
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let BotInstruction::CountInventory { respond_to } = msg {
+                    let _ = respond_to.send(Err("not connected".to_string()));
+                }
+            }
+        });
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert_eq!(slot0(&store), 0);
+        assert!(
+            report
+                .iter()
+                .any(|l| l.contains("Could not count the bot's inventory (not connected)"))
+        );
+        assert_eq!(store.notices.pending("uuid-alice").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn withdrawing_reports_steps_the_bot_could_not_return() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_bot_error_bot(spawn_holding_bot(rx, &[("cobblestone", 64)]));
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert!(report.iter().any(|l| l.contains("finished with failures")));
+        assert_eq!(store.notices.pending("uuid-alice").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn trading_returns_what_the_bot_still_holds() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_auto_ack_bot(spawn_holding_bot(rx, &[("cobblestone", 64)]));
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .and_then(TradeState::begin_trading)
+            .unwrap();
+
+        let (report, _p) = run(&mut store, state).await;
+
+        assert_eq!(slot0(&store), 64, "withdrawn items must not be stranded");
+        assert!(store.order_queue.is_empty());
+        assert!(
+            report
+                .iter()
+                .any(|l| l.contains("reconcile the bot's inventory"))
+        );
+        let held = store.notices.pending("uuid-alice");
+        assert!(held[0].message.contains("during the trade"));
+    }
+
+    #[tokio::test]
+    async fn trading_that_went_through_returns_nothing() {
+        let (tx, rx) = mpsc::channel(8);
+        let mut chest_ops = spawn_holding_bot(rx, &[]);
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let state = TradeState::new(order())
+            .begin_withdrawal(vec![transfer(2, "cobblestone", 64)])
+            .and_then(TradeState::begin_trading)
+            .unwrap();
+
+        run(&mut store, state).await;
+
+        assert!(chest_ops.try_recv().is_err(), "the player has the items");
+        assert_eq!(slot0(&store), 0);
+    }
+
+    #[test]
+    fn clamp_keeps_steps_in_order_until_the_held_amount_runs_out() {
+        let held = vec![TradeItem {
+            item: "cobblestone".to_string(),
+            amount: 70,
+        }];
+        let clamped = clamp_to_held(
+            &[
+                transfer(2, "cobblestone", 64),
+                transfer(3, "cobblestone", 64),
+                transfer(4, "cobblestone", 64),
+                transfer(5, "dirt", 64),
+            ],
+            &held,
+        );
+        let got: Vec<(i32, i32)> = clamped.iter().map(|t| (t.chest_id, t.amount)).collect();
+        assert_eq!(got, vec![(2, 64), (3, 6)]);
+    }
+
+    #[tokio::test]
+    async fn depositing_stores_received_items_and_flags_the_ledger() {
+        let (tx, rx) = mpsc::channel(8);
+        spawn_auto_ack_bot(spawn_holding_bot(rx, &[("cobblestone", 32)]));
+        let mut store = make_store(tx, single_node_storage("cobblestone"));
+        let mut o = order();
+        o.order_type = QueuedOrderType::Sell;
+        let state = TradeState::new(o)
+            .begin_withdrawal(Vec::new())
+            .and_then(TradeState::begin_trading)
+            .and_then(|s| {
+                s.begin_depositing(
+                    TradeResult {
+                        items_received: Vec::new(),
+                    },
+                    vec![transfer(2, "cobblestone", 32)],
+                )
+            })
+            .unwrap();
+
+        let (report, p) = run(&mut store, state).await;
+
+        assert_eq!(slot0(&store), 32);
+        assert!(report.iter().any(|l| l.contains("audit the ledger")));
+        // The notice survives a restart.
+        let reloaded = crate::store::notices::Notices::load_from(&p.notices).unwrap();
+        assert!(
+            reloaded.pending("uuid-alice")[0]
+                .message
+                .contains("Contact an operator")
+        );
+    }
+
+    #[test]
+    fn terminal_states_plan_nothing() {
+        let state = TradeState::new(order())
+            .rollback("test".to_string())
+            .unwrap();
+        let plan = plan(&state);
+        assert!(matches!(plan.action, ResumeAction::Nothing));
+        assert!(plan.notice.is_none());
+    }
+}
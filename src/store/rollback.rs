@@ -9,12 +9,14 @@
 //! report with a timeout, and applies it. Step failures are logged at `error!`
 //! but do NOT short-circuit the loop: we attempt every step so partial recovery
 //! is possible even when one chest is unreachable, and report the aggregate via
-//! `RollbackResult`.
+//! `RollbackResult`. A rollback run while an order is being processed is
+//! also recorded in the order's audit log.
 
 use tokio::sync::oneshot;
 use tracing::{error, info, warn};
 
 use super::Store;
+use super::audit_log::AuditEvent;
 use crate::constants::{CHEST_OP_TIMEOUT_SECS, CHESTS_PER_NODE};
 use crate::messages::{BotInstruction, ChestAction};
 use crate::types::storage::ChestTransfer;
@@ -106,6 +108,7 @@ impl RollbackResult {
 /// Only identity fields (`id`, `node_id`, `index`, `position`, `item`) are
 /// meaningful — the `amounts` vector is zero-filled because the bot reads real
 /// per-slot state from the world on arrival and returns it via the sync report.
+/// The bot does not look at `class`, so the stub is always `Stock`.
 pub fn chest_from_transfer(t: &ChestTransfer) -> crate::types::Chest {
     crate::types::Chest {
         id: t.chest_id,
@@ -114,6 +117,7 @@ pub fn chest_from_transfer(t: &ChestTransfer) -> crate::types::Chest {
         position: t.position,
         item: t.item.clone(),
         amounts: vec![0; crate::types::Storage::SLOTS_PER_CHEST],
+        class: Default::default(),
     }
 }
 
@@ -135,6 +139,30 @@ pub async fn deposit_transfers(
     item: &str,
     stack_size: i32,
     context: &str,
+) -> RollbackResult {
+    let result = run_deposits(store, transfers, item, stack_size, context).await;
+    if !transfers.is_empty() {
+        audit(store, context, &result);
+    }
+    result
+}
+
+/// Record `result` under the order being processed. Rollbacks outside an
+/// order (crash-resume) are covered by the caller's own audit event.
+fn audit(store: &Store, context: &str, result: &RollbackResult) {
+    if let Some(trade) = &store.current_trade {
+        store
+            .audit_log
+            .record(trade.order().id, AuditEvent::rollback(context, result));
+    }
+}
+
+async fn run_deposits(
+    store: &mut Store,
+    transfers: &[ChestTransfer],
+    item: &str,
+    stack_size: i32,
+    context: &str,
 ) -> RollbackResult {
     let mut result = RollbackResult::default();
     if transfers.is_empty() {
@@ -383,20 +411,22 @@ pub async fn rollback_amount_to_storage(
     // Populate `items_unplanned` here, BEFORE delegating: `deposit_transfers`
     // is also called by buy/operator handlers with caller-supplied plans where
     // an "unplanned shortfall" is not a meaningful concept.
-    let mut result = deposit_transfers(store, &plan, item, stack_size, context).await;
+    let mut result = run_deposits(store, &plan, item, stack_size, context).await;
     result.items_unplanned = unplanned;
+    audit(store, context, &result);
     result
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     //! Unit tests for the rollback primitives.
     //!
     //! `deposit_transfers` owns the bot channel, so each async test spawns a
     //! small mock receiver that either auto-acks, returns a bot-side error, or
     //! drops the response channel, depending on the scenario under test. The
     //! `Store` is constructed via `Store::new_for_test` so no disk I/O or real
-    //! Azalea client is involved.
+    //! Azalea client is involved. The store and mock-bot helpers are
+    //! `pub(crate)` because `resume` tests replay rollbacks the same way.
     use super::*;
     use crate::config::Config;
     use crate::messages::{BotInstruction, ChestSyncReport};
@@ -410,7 +440,7 @@ mod tests {
 
     /// Storage with a single node whose chest 2 is pre-assigned to `item`.
     /// Chest 2 is used because chests 0 and 1 are reserved for diamonds/overflow.
-    fn single_node_storage(item: &str) -> Storage {
+    pub(crate) fn single_node_storage(item: &str) -> Storage {
         let origin = Position { x: 0, y: 64, z: 0 };
         let mut storage = Storage::new(&origin);
         storage.nodes.push(Node::new(0, &origin));
@@ -420,7 +450,7 @@ mod tests {
         storage
     }
 
-    fn make_store(bot_tx: mpsc::Sender<BotInstruction>, storage: Storage) -> Store {
+    pub(crate) fn make_store(bot_tx: mpsc::Sender<BotInstruction>, storage: Storage) -> Store {
         Store::new_for_test(
             bot_tx,
             test_config(),
@@ -433,7 +463,7 @@ mod tests {
     /// Auto-ack every `InteractWithChestAndSync` with a sync report whose
     /// slot-0 value matches the deposited amount — the one slot `apply_chest_sync`
     /// will merge, leaving the rest of the chest untouched.
-    fn spawn_auto_ack_bot(mut rx: mpsc::Receiver<BotInstruction>) {
+    pub(crate) fn spawn_auto_ack_bot(mut rx: mpsc::Receiver<BotInstruction>) {
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
                 if let BotInstruction::InteractWithChestAndSync {
@@ -462,7 +492,7 @@ mod tests {
 
     /// Respond with a bot-side error string for every instruction — simulates
     /// the bot being unable to perform the physical transfer.
-    fn spawn_bot_error_bot(mut rx: mpsc::Receiver<BotInstruction>) {
+    pub(crate) fn spawn_bot_error_bot(mut rx: mpsc::Receiver<BotInstruction>) {
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
                 if let BotInstruction::InteractWithChestAndSync { respond_to, .. } = msg {
@@ -513,7 +543,7 @@ mod tests {
         });
     }
 
-    fn transfer(chest_id: i32, item: &str, amount: i32) -> ChestTransfer {
+    pub(crate) fn transfer(chest_id: i32, item: &str, amount: i32) -> ChestTransfer {
         ChestTransfer {
             chest_id,
             position: Position { x: 0, y: 64, z: 0 },
@@ -0,0 +1,540 @@
+//! Point-in-time snapshots of `data/` and `--restore`.
+//!
+//! A snapshot is a plain copy of `data/` under
+//! `data/backups/<YYYY-MM-DDTHH-MM-SSZ>-<reason>/` with a `manifest.json`
+//! saying when and why it was taken. [`take`] flushes pending saves on the
+//! Store task, only while no order is processing, then copies on the
+//! blocking pool while the Store task waits for it: nothing else in the
+//! Store writes while the files are copied, so the copy is consistent
+//! without stopping the bot or tying up a runtime worker for the length of
+//! the copy. It is written to a `.partial`
+//! directory and renamed into place, so an interrupted copy is never
+//! mistaken for a snapshot.
+//!
+//! Taken every `snapshot_interval_secs` and before Remove node / Remove
+//! pair; the newest `snapshot_keep` are kept.
+//!
+//! `cj-store --restore <snapshot>` (store stopped) copies the snapshot to
+//! `data/.restore-staging/`, loads it with the same loaders the Store
+//! uses at startup, and only if nothing is rejected or quarantined takes a
+//! `pre-restore` snapshot of the current `data/` and swaps the staged copy
+//! in. `backups/`, `logs/` and `store.lock` are never copied or replaced.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::Store;
+use super::state;
+use crate::config::Config;
+use crate::constants::{BACKUPS_DIR, DATA_DIR};
+use crate::fsutil::write_atomic;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const PARTIAL_SUFFIX: &str = ".partial";
+const RESTORE_STAGING: &str = ".restore-staging";
+/// Top-level entries of `data/` that belong to this process or to the
+/// snapshots themselves, not to the store's state.
+const EXCLUDED: [&str; 4] = ["backups", "logs", "store.lock", RESTORE_STAGING];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub taken_at: DateTime<Utc>,
+    /// `scheduled`, `remove-node-<id>`, `remove-pair-<item>`, `pre-restore`.
+    pub reason: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Snapshot {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({}, {} file(s), {:.1} MiB)",
+            self.path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+            self.manifest.reason,
+            self.manifest.files,
+            self.manifest.bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Flush pending saves and snapshot `data/`, then rotate. Refused while an
+/// order is processing: its trade state is mid-flight on disk.
+pub async fn take(store: &mut Store, reason: &str) -> Result<Snapshot, String> {
+    if store.processing_order {
+        return Err("an order is being processed".to_string());
+    }
+    if store.dirty {
+        state::save(store).map_err(|e| format!("save before snapshot failed: {}", e))?;
+        store.dirty = false;
+        store.dirty_users.clear();
+    }
+    let reason = reason.to_string();
+    let keep = store.config.snapshot_keep;
+    tokio::task::spawn_blocking(move || {
+        let snapshot = take_in_dir(
+            Path::new(DATA_DIR),
+            Path::new(BACKUPS_DIR),
+            &reason,
+            Utc::now(),
+        )
+        .map_err(|e| format!("snapshot failed: {}", e))?;
+        info!("[Snapshot] Took {}", snapshot.describe());
+        match rotate_in_dir(Path::new(BACKUPS_DIR), keep) {
+            Ok(0) => {}
+            Ok(n) => info!("[Snapshot] Rotated out {} old snapshot(s)", n),
+            Err(e) => warn!("[Snapshot] Rotation failed: {}", e),
+        }
+        Ok(snapshot)
+    })
+    .await
+    .map_err(|e| format!("snapshot task failed: {}", e))?
+}
+
+/// Copy `data_dir` into a new snapshot under `backups_dir`.
+pub(crate) fn take_in_dir(
+    data_dir: &Path,
+    backups_dir: &Path,
+    reason: &str,
+    now: DateTime<Utc>,
+) -> io::Result<Snapshot> {
+    let stem = format!("{}-{}", now.format("%Y-%m-%dT%H-%M-%SZ"), reason);
+    let mut name = stem.clone();
+    let mut n = 1;
+    while backups_dir.join(&name).exists() {
+        n += 1;
+        name = format!("{stem}-{n}");
+    }
+    let path = backups_dir.join(&name);
+    let partial = backups_dir.join(format!("{name}{PARTIAL_SUFFIX}"));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+    fs::create_dir_all(&partial)?;
+
+    let mut manifest = Manifest {
+        taken_at: now,
+        reason: reason.to_string(),
+        files: 0,
+        bytes: 0,
+    };
+    if let Err(e) = copy_tree(data_dir, &partial, true, &mut manifest) {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(e);
+    }
+    let json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    write_atomic(partial.join(MANIFEST_FILE), &json)?;
+    fs::rename(&partial, &path)?;
+    Ok(Snapshot { path, manifest })
+}
+
+/// Complete snapshots under `backups_dir`, newest first.
+pub fn list_in_dir(backups_dir: &Path) -> io::Result<Vec<Snapshot>> {
+    let entries = match fs::read_dir(backups_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        match read_manifest(&path) {
+            Ok(manifest) => out.push(Snapshot { path, manifest }),
+            // `.partial` leftovers and foreign directories.
+            Err(_) => continue,
+        }
+    }
+    out.sort_by_key(|s| std::cmp::Reverse(s.manifest.taken_at));
+    Ok(out)
+}
+
+pub fn read_manifest(snapshot_dir: &Path) -> io::Result<Manifest> {
+    let s = fs::read_to_string(snapshot_dir.join(MANIFEST_FILE))?;
+    serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Delete all but the newest `keep` snapshots, and any `.partial` copy an
+/// interrupted run left behind. Returns how many snapshots were deleted.
+pub(crate) fn rotate_in_dir(backups_dir: &Path, keep: usize) -> io::Result<usize> {
+    let snapshots = list_in_dir(backups_dir)?;
+    let mut removed = 0;
+    for old in snapshots.iter().skip(keep) {
+        fs::remove_dir_all(&old.path)?;
+        removed += 1;
+    }
+    for entry in fs::read_dir(backups_dir)?.flatten() {
+        let path = entry.path();
+        if path.is_dir()
+            && path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().ends_with(PARTIAL_SUFFIX))
+        {
+            fs::remove_dir_all(&path)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// `arg` as a snapshot directory: a name under `backups_dir`, `latest`, or
+/// a path to a directory holding a manifest.
+pub fn resolve_in_dir(backups_dir: &Path, arg: &str) -> Result<Snapshot, String> {
+    if arg == "latest" {
+        return list_in_dir(backups_dir)
+            .map_err(|e| format!("cannot list {}: {}", backups_dir.display(), e))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("no snapshots in {}", backups_dir.display()));
+    }
+    let named = backups_dir.join(arg);
+    let path = if named.is_dir() {
+        named
+    } else {
+        PathBuf::from(arg)
+    };
+    read_manifest(&path)
+        .map(|manifest| Snapshot {
+            path: path.clone(),
+            manifest,
+        })
+        .map_err(|e| format!("{} is not a snapshot: {}", path.display(), e))
+}
+
+/// Copy `snapshot` into `data_dir/.restore-staging/` and load it with the
+/// startup loaders. Returns a summary of what it holds; `Err` when anything
+/// fails to load or is quarantined, in which case nothing was staged.
+pub fn stage_in_dir(data_dir: &Path, snapshot: &Path) -> Result<Vec<String>, String> {
+    let staging = data_dir.join(RESTORE_STAGING);
+    discard_staging_in_dir(data_dir).map_err(|e| format!("cannot clear staging: {}", e))?;
+    fs::create_dir_all(&staging).map_err(|e| format!("cannot create staging: {}", e))?;
+    let mut counts = Manifest {
+        taken_at: Utc::now(),
+        reason: String::new(),
+        files: 0,
+        bytes: 0,
+    };
+    let result = copy_tree(snapshot, &staging, true, &mut counts)
+        .and_then(|()| fs::remove_file(staging.join(MANIFEST_FILE)))
+        .map_err(|e| format!("cannot copy {}: {}", snapshot.display(), e))
+        .and_then(|()| validate(&staging));
+    if result.is_err() {
+        let _ = discard_staging_in_dir(data_dir);
+    }
+    result
+}
+
+/// Snapshot the current `data_dir` as `pre-restore`, then replace its
+/// contents with the staged copy. Returns the pre-restore snapshot.
+pub fn swap_in_dir(
+    data_dir: &Path,
+    backups_dir: &Path,
+    now: DateTime<Utc>,
+) -> Result<Snapshot, String> {
+    let staging = data_dir.join(RESTORE_STAGING);
+    if !staging.is_dir() {
+        return Err("nothing staged".to_string());
+    }
+    let pre = take_in_dir(data_dir, backups_dir, "pre-restore", now)
+        .map_err(|e| format!("pre-restore snapshot failed, nothing replaced: {}", e))?;
+    let swap = || -> io::Result<()> {
+        for entry in fs::read_dir(data_dir)?.flatten() {
+            if is_excluded(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        for entry in fs::read_dir(&staging)?.flatten() {
+            fs::rename(entry.path(), data_dir.join(entry.file_name()))?;
+        }
+        fs::remove_dir(&staging)
+    };
+    swap().map_err(|e| {
+        format!(
+            "swap failed part-way ({}); data/ is incomplete, restore {} the same way",
+            e,
+            pre.path.display()
+        )
+    })?;
+    Ok(pre)
+}
+
+pub fn discard_staging_in_dir(data_dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(data_dir.join(RESTORE_STAGING)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Load a staged `data/` copy the way `Store::new` would. The loaders skip
+/// or quarantine what they cannot parse rather than fail, so any file that
+/// appears or disappears during loading counts as a rejection.
+fn validate(dir: &Path) -> Result<Vec<String>, String> {
+    let before = list_files(dir).map_err(|e| e.to_string())?;
+
+    let config_path = dir.join("config.json");
+    let config: Config = fs::read_to_string(&config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+        .map_err(|e| format!("config.json: {}", e))?;
+    config
+        .validate()
+        .map_err(|e| format!("config.json: {}", e))?;
+    let pairs = crate::types::Pair::load_all_in_dir(&dir.join("pairs"))
+        .map_err(|e| format!("pairs: {}", e))?;
+    let users = crate::types::User::load_all_in_dir(&dir.join("users"))
+        .map_err(|e| format!("users: {}", e))?;
+    let storage = crate::types::Storage::load_from_dir(&config.position, &dir.join("storage"))
+        .map_err(|e| format!("storage: {}", e))?;
+    let trades = crate::types::Trade::load_all_with_limit_in_dir(
+        &dir.join("trades"),
+        config.max_trades_in_memory,
+    )
+    .map_err(|e| format!("trades: {}", e))?;
+    let queue = super::queue::OrderQueue::load_from(dir.join("queue.json"))
+        .map_err(|e| format!("queue.json: {}", e))?;
+    let book = super::order_book::OrderBook::load_from(dir.join("order_book.json"))
+        .map_err(|e| format!("order_book.json: {}", e))?;
+    super::notices::Notices::load_from(&dir.join("notices.json"))
+        .map_err(|e| format!("notices.json: {}", e))?;
+    let requests = super::pay_requests::PayRequests::load_from(&dir.join("pay_requests.json"))
+        .map_err(|e| format!("pay_requests.json: {}", e))?;
+    let escrows = super::escrow::EscrowBook::load_from(&dir.join("escrow.json"))
+        .map_err(|e| format!("escrow.json: {}", e))?;
+    let alerts = super::alerts::Alerts::load_from(&dir.join("alerts.json"))
+        .map_err(|e| format!("alerts.json: {}", e))?;
+
+    let after = list_files(dir).map_err(|e| e.to_string())?;
+    if before != after {
+        let changed: Vec<_> = before
+            .symmetric_difference(&after)
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(format!(
+            "the loaders rejected or quarantined files: {}",
+            changed.join(", ")
+        ));
+    }
+    Ok(vec![
+        format!("{} pair(s), {} user(s)", pairs.len(), users.len()),
+        format!("{} storage node(s)", storage.nodes.len()),
+        format!("{} recent trade(s) loaded", trades.len()),
+        format!(
+            "{} queued order(s), {} resting limit order(s), {} open payment request(s), {} escrow(s), {} alert(s)",
+            queue.len(),
+            book.len(),
+            requests.len(),
+            escrows.len(),
+            alerts.len()
+        ),
+    ])
+}
+
+fn is_excluded(name: &str) -> bool {
+    EXCLUDED.contains(&name)
+}
+
+/// Copy `src` into `dst` recursively, leaving out `EXCLUDED` at the top
+/// level and `*.tmp` leftovers of interrupted atomic writes.
+fn copy_tree(src: &Path, dst: &Path, top: bool, counts: &mut Manifest) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name_str = name.to_string_lossy();
+        if (top && is_excluded(&name_str)) || name_str.ends_with(".tmp") {
+            continue;
+        }
+        let from = entry.path();
+        let to = dst.join(&name);
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&to)?;
+            copy_tree(&from, &to, false, counts)?;
+        } else {
+            counts.bytes += fs::copy(&from, &to)?;
+            counts.files += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Every file under `dir`, relative to it.
+fn list_files(dir: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    fn walk(root: &Path, dir: &Path, out: &mut BTreeSet<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(root, &path, out)?;
+            } else if let Ok(rel) = path.strip_prefix(root) {
+                out.insert(rel.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    let mut out = BTreeSet::new();
+    walk(dir, dir, &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ItemId, Pair, Storage, User};
+    use chrono::TimeZone;
+
+    fn at(secs: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 4, 1, 12, 0, secs).unwrap()
+    }
+
+    /// A minimal `data/` the startup loaders accept.
+    fn seed(data: &Path) {
+        let config = Config::test_default();
+        fs::write(
+            data.join("config.json"),
+            serde_json::to_string(&config).unwrap(),
+        )
+        .unwrap();
+        let mut storage = Storage::new(&config.position);
+        let node = storage.add_node().clone();
+        fs::create_dir_all(data.join("storage")).unwrap();
+        fs::write(
+            data.join("storage/0.json"),
+            serde_json::to_string(&node).unwrap(),
+        )
+        .unwrap();
+        let pair = Pair {
+            item: ItemId::new("cobblestone").unwrap(),
+            stack_size: 64,
+            ..Pair::default()
+        };
+        fs::create_dir_all(data.join("pairs")).unwrap();
+        fs::write(
+            data.join("pairs/cobblestone.json"),
+            serde_json::to_string(&pair).unwrap(),
+        )
+        .unwrap();
+        let user = User {
+            uuid: "00000000-0000-0000-0000-000000000001".into(),
+            username: "alice".into(),
+            balance: 5.0,
+            ..User::default()
+        };
+        fs::create_dir_all(data.join("users")).unwrap();
+        fs::write(
+            data.join(format!("users/{}.json", user.uuid)),
+            serde_json::to_string(&user).unwrap(),
+        )
+        .unwrap();
+        fs::create_dir_all(data.join("logs")).unwrap();
+        fs::write(data.join("logs/store.log"), "log").unwrap();
+        fs::write(data.join("store.lock"), "{}").unwrap();
+        fs::write(data.join("queue.json.tmp"), "torn").unwrap();
+    }
+
+    #[test]
+    fn take_copies_state_but_not_logs_lock_or_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path();
+        seed(data);
+        let backups = data.join("backups");
+        let snap = take_in_dir(data, &backups, "scheduled", at(0)).unwrap();
+
+        assert!(snap.path.ends_with("2026-04-01T12-00-00Z-scheduled"));
+        assert!(snap.path.join("config.json").exists());
+        assert!(snap.path.join("pairs/cobblestone.json").exists());
+        assert!(!snap.path.join("logs").exists());
+        assert!(!snap.path.join("store.lock").exists());
+        assert!(!snap.path.join("backups").exists());
+        assert!(!snap.path.join("queue.json.tmp").exists());
+        assert_eq!(read_manifest(&snap.path).unwrap(), snap.manifest);
+
+        // Same second: a second directory, not an overwrite.
+        let again = take_in_dir(data, &backups, "scheduled", at(0)).unwrap();
+        assert!(again.path.ends_with("2026-04-01T12-00-00Z-scheduled-2"));
+    }
+
+    #[test]
+    fn rotate_keeps_the_newest_and_clears_partials() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path();
+        seed(data);
+        let backups = data.join("backups");
+        for s in 0..4 {
+            take_in_dir(data, &backups, "scheduled", at(s)).unwrap();
+        }
+        fs::create_dir_all(backups.join("x.partial")).unwrap();
+
+        assert_eq!(rotate_in_dir(&backups, 2).unwrap(), 2);
+        let left = list_in_dir(&backups).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[0].manifest.taken_at, at(3));
+        assert!(!backups.join("x.partial").exists());
+        assert_eq!(
+            resolve_in_dir(&backups, "latest").unwrap().path,
+            left[0].path
+        );
+    }
+
+    #[test]
+    fn restore_swaps_in_the_snapshot_and_keeps_a_pre_restore_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path();
+        seed(data);
+        let backups = data.join("backups");
+        let snap = take_in_dir(data, &backups, "scheduled", at(0)).unwrap();
+
+        // Damage after the snapshot: a lost user and a stray file.
+        fs::remove_dir_all(data.join("users")).unwrap();
+        fs::write(data.join("current_trade.json"), "{}").unwrap();
+
+        let summary = stage_in_dir(data, &snap.path).unwrap();
+        assert!(summary[0].contains("1 pair(s), 1 user(s)"), "{summary:?}");
+        let pre = swap_in_dir(data, &backups, at(5)).unwrap();
+
+        assert_eq!(pre.manifest.reason, "pre-restore");
+        assert!(pre.path.join("current_trade.json").exists());
+        assert!(data.join("users").is_dir());
+        assert!(!data.join("current_trade.json").exists());
+        assert!(!data.join(MANIFEST_FILE).exists());
+        assert!(!data.join(RESTORE_STAGING).exists());
+        assert!(data.join("logs/store.log").exists(), "logs are left alone");
+        assert!(data.join("store.lock").exists(), "the lease is left alone");
+    }
+
+    #[test]
+    fn a_snapshot_the_loaders_reject_is_not_staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path();
+        seed(data);
+        let backups = data.join("backups");
+        let snap = take_in_dir(data, &backups, "scheduled", at(0)).unwrap();
+        fs::write(snap.path.join("pairs/cobblestone.json"), "{ torn").unwrap();
+
+        let err = stage_in_dir(data, &snap.path).unwrap_err();
+        assert!(err.contains("quarantined"), "{err}");
+        assert!(!data.join(RESTORE_STAGING).exists());
+        assert!(swap_in_dir(data, &backups, at(5)).is_err());
+        assert!(
+            snap.path.join("pairs/cobblestone.json").exists(),
+            "validation never touches the snapshot itself"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 //! State management and persistence
 
+use std::collections::HashMap;
+
 use tracing::{debug, warn};
 
+use chrono::{DateTime, Utc};
+
 use super::Store;
+use super::ledger::Posting;
+use crate::constants::RECONCILE_TOLERANCE;
 use crate::error::StoreError;
 use crate::messages::ChestSyncReport;
+use crate::types::liquidity::SHARE_EPSILON;
 use crate::types::{ItemId, Order, Pair, Trade, User};
 
 /// Merge a bot-reported [`ChestSyncReport`] into the store's authoritative
@@ -161,7 +168,7 @@ pub fn save(store: &mut Store) -> Result<(), Box<dyn std::error::Error + Send +
         store.storage.nodes.len()
     );
 
-    // First-error-keep-going: attempt all five sub-saves regardless of which
+    // First-error-keep-going: attempt all six sub-saves regardless of which
     // one fails first, then surface the first error to the caller. The bare
     // `?`-early-exit pattern stranded later sub-saves on an early failure
     // (e.g. a Pair-side ENOSPC silently lost the User/Order/Trade/Storage
@@ -215,6 +222,14 @@ pub fn save(store: &mut Store) -> Result<(), Box<dyn std::error::Error + Send +
         // tail dirty so the next autosave retries the same trades.
         store.saved_trades_count = store.trades.len();
     }
+    record(
+        "price_history",
+        store
+            .price_history
+            .save_dirty()
+            .map_err(|e| Box::new(e) as DynErr),
+        &mut first_err,
+    );
     record(
         "storage",
         store
@@ -248,10 +263,13 @@ pub fn save(store: &mut Store) -> Result<(), Box<dyn std::error::Error + Send +
 /// leaves it `false`). Callers use it to decide whether to persist the store. Keeping the two
 /// fields separate avoids the old fragile coupling where repair status was
 /// smuggled as a "Repair applied..." string at position 0 of the vec.
+/// `reconciliation` is set only when the diamond reconciliation failed; it
+/// is reported but never counted as an issue, so it does not block trades.
 #[derive(Debug, Clone, Default)]
 pub struct AuditReport {
     pub issues: Vec<String>,
     pub repair_applied: bool,
+    pub reconciliation: Option<Reconciliation>,
 }
 
 impl AuditReport {
@@ -264,10 +282,110 @@ impl AuditReport {
             out.push("Repair applied: recomputed Pair.item_stock from Storage".to_string());
         }
         out.extend(self.issues.iter().cloned());
+        if let Some(r) = &self.reconciliation {
+            out.extend(r.to_lines());
+        }
+        out
+    }
+}
+
+/// Postings listed by [`Reconciliation::to_lines`]; older ones are counted.
+const RECONCILIATION_POSTINGS_SHOWN: usize = 20;
+
+/// sum(balances) + sum(reserves) against the diamonds counted in storage,
+/// with the ledger postings since the last time the two agreed.
+#[derive(Debug, Clone)]
+pub struct Reconciliation {
+    pub balances: f64,
+    pub reserves: f64,
+    pub physical: i32,
+    /// Diamond-chest slots the bot has not counted yet (`-1`); `physical`
+    /// leaves them out.
+    pub uncounted_slots: usize,
+    /// Oldest first.
+    pub postings: Vec<Posting>,
+    /// Postings since the last clean reconciliation that are only in
+    /// `data/ledger.jsonl`, ahead of `postings`.
+    pub older_postings: u64,
+    pub last_clean: Option<DateTime<Utc>>,
+}
+
+impl Reconciliation {
+    /// Storage minus what balances and reserves claim: positive means the
+    /// chest holds diamonds nobody is credited with.
+    pub fn discrepancy(&self) -> f64 {
+        f64::from(self.physical) - (self.balances + self.reserves)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.discrepancy().abs() <= RECONCILE_TOLERANCE
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let discrepancy = self.discrepancy();
+        let mut out = vec![format!(
+            "Diamond reconciliation off by {:+.4}: storage holds {}, balances {:.4} + reserves {:.4} = {:.4}",
+            discrepancy,
+            self.physical,
+            self.balances,
+            self.reserves,
+            self.balances + self.reserves
+        )];
+        if self.uncounted_slots > 0 {
+            out.push(format!(
+                "{} diamond chest slot(s) not yet counted by the bot; storage total leaves them out",
+                self.uncounted_slots
+            ));
+        }
+        let total = self.older_postings + self.postings.len() as u64;
+        out.push(match self.last_clean {
+            Some(ts) => format!(
+                "{} ledger posting(s) since the last clean reconciliation at {}",
+                total,
+                ts.format("%Y-%m-%d %H:%M:%S")
+            ),
+            None => format!(
+                "{} ledger posting(s); the ledger has never reconciled cleanly",
+                total
+            ),
+        });
+        let shown = self.postings.len().min(RECONCILIATION_POSTINGS_SHOWN);
+        let hidden = total - shown as u64;
+        if hidden > 0 {
+            out.push(format!(
+                "  ... {} earlier posting(s) in data/ledger.jsonl",
+                hidden
+            ));
+        }
+        for p in &self.postings[self.postings.len() - shown..] {
+            out.push(format!("  {}", p.describe()));
+        }
         out
     }
 }
 
+/// Compare sum(balances) + sum(reserves) with the diamonds in storage.
+pub fn reconcile(store: &Store) -> Reconciliation {
+    let uncounted_slots = store
+        .storage
+        .nodes
+        .iter()
+        .flat_map(|n| &n.chests)
+        .filter(|c| c.item == "diamond")
+        .flat_map(|c| &c.amounts)
+        .filter(|a| **a < 0)
+        .count();
+    Reconciliation {
+        balances: store.users.values().map(|u| u.balance).sum(),
+        reserves: store.pairs.values().map(|p| p.currency_stock).sum(),
+        physical: store.storage.total_item_amount("diamond"),
+        uncounted_slots,
+        postings: store.ledger.pending().cloned().collect(),
+        older_postings: store.ledger.dropped(),
+        last_clean: store.ledger.last_clean(),
+    }
+}
+
 /// Audit store state and optionally repair issues.
 ///
 /// Walks users, storage chests and pairs looking for broken invariants and
@@ -377,6 +495,34 @@ pub fn audit_state(store: &mut Store, repair: bool) -> AuditReport {
         }
     }
 
+    // Player LP holdings can never exceed a pair's share supply: the house
+    // owns the difference, and a negative difference means shares were
+    // minted without being recorded on the pair.
+    let mut held_shares: HashMap<&str, f64> = HashMap::new();
+    for user in store.users.values() {
+        for (item, shares) in &user.lp_shares {
+            if !shares.is_finite() || *shares < 0.0 {
+                issues.push(format!(
+                    "User {} has invalid LP shares in {}: {}",
+                    user.username, item, shares
+                ));
+            }
+            *held_shares.entry(item.as_str()).or_insert(0.0) += shares;
+        }
+    }
+    for (item, held) in held_shares {
+        match store.pairs.get(item) {
+            None => issues.push(format!("LP shares held in unknown pair {}", item)),
+            Some(pair) if held > pair.lp_total_shares * (1.0 + 1e-9) + SHARE_EPSILON => {
+                issues.push(format!(
+                    "Pair {} players hold {:.6} LP shares but supply is {:.6}",
+                    item, held, pair.lp_total_shares
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
     if !issues.is_empty() {
         warn!(count = issues.len(), repair, "audit found invariant issues");
     }
@@ -384,9 +530,27 @@ pub fn audit_state(store: &mut Store, repair: bool) -> AuditReport {
         warn!(item = %item, before, after, "audit repaired pair item_stock drift");
     }
 
+    // Balances and reserves are claims on the diamonds in storage, so the
+    // two must add up. A mismatch is reported, not made an issue: an
+    // operator `addcurrency` opens one by design, and `assert_invariants`
+    // must not halt trading over it.
+    let reconciliation = reconcile(store);
+    let reconciliation = if reconciliation.is_clean() {
+        store.ledger.mark_clean();
+        None
+    } else {
+        warn!(
+            discrepancy = reconciliation.discrepancy(),
+            postings = reconciliation.postings.len() as u64 + reconciliation.older_postings,
+            "audit found diamond reconciliation drift"
+        );
+        Some(reconciliation)
+    };
+
     AuditReport {
         issues,
         repair_applied: !repairs.is_empty(),
+        reconciliation,
     }
 }
 
@@ -838,6 +1002,56 @@ mod tests {
         assert!(!report.repair_applied);
     }
 
+    #[test]
+    fn audit_state_reports_diamond_drift_with_postings_without_blocking() {
+        let mut users = HashMap::new();
+        users.insert(
+            "u1".to_string(),
+            User {
+                uuid: "u1".to_string(),
+                username: "alice".to_string(),
+                balance: 10.0,
+                operator: false,
+                lp_shares: Default::default(),
+            },
+        );
+        let mut store = build_store(HashMap::new(), users, test_storage());
+        store.post_ledger(
+            crate::store::ledger::Account::Vault,
+            crate::store::ledger::Account::Player("u1".to_string()),
+            10.0,
+            "deposit",
+        );
+
+        // The credited diamonds never reached the chest.
+        let report = audit_state(&mut store, false);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        let recon = report.reconciliation.as_ref().expect("drift is reported");
+        assert!((recon.discrepancy() + 10.0).abs() < 1e-9);
+        let lines = report.to_lines();
+        assert!(lines[0].contains("off by -10.0000"), "{lines:?}");
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.contains("deposit") && l.contains("player:u1"))
+        );
+        assert_eq!(
+            store.ledger.pending().count(),
+            1,
+            "drift keeps the postings"
+        );
+
+        store.users.get_mut("u1").unwrap().balance = 0.0;
+        let report = audit_state(&mut store, false);
+        assert!(report.reconciliation.is_none());
+        assert_eq!(
+            store.ledger.pending().count(),
+            0,
+            "a clean audit marks the ledger"
+        );
+        assert!(store.ledger.last_clean().is_some());
+    }
+
     #[test]
     fn audit_state_flags_nonfinite_and_negative_balances() {
         let mut users = HashMap::new();
@@ -848,6 +1062,7 @@ mod tests {
                 username: "alice".to_string(),
                 balance: f64::NAN,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -857,6 +1072,7 @@ mod tests {
                 username: "bob".to_string(),
                 balance: -5.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let mut store = build_store(HashMap::new(), users, test_storage());
@@ -875,6 +1091,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn audit_state_flags_lp_shares_beyond_pair_supply() {
+        let mut users = HashMap::new();
+        users.insert(
+            "u1".to_string(),
+            User {
+                uuid: "u1".to_string(),
+                username: "alice".to_string(),
+                balance: 0.0,
+                operator: false,
+                lp_shares: HashMap::from([("cobblestone".to_string(), 150.0)]),
+            },
+        );
+        let mut pairs = HashMap::new();
+        pairs.insert(
+            "cobblestone".to_string(),
+            Pair {
+                item: ItemId::new("cobblestone").unwrap(),
+                stack_size: 64,
+                item_stock: 0,
+                currency_stock: 10.0,
+                lp_total_shares: 100.0,
+                ..Default::default()
+            },
+        );
+        let mut store = build_store(pairs, users, test_storage());
+        let report = audit_state(&mut store, false);
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|i| i.contains("cobblestone") && i.contains("LP shares")),
+            "{:?}",
+            report.issues
+        );
+
+        store.pairs.get_mut("cobblestone").unwrap().lp_total_shares = 150.0;
+        let report = audit_state(&mut store, false);
+        assert!(!report.issues.iter().any(|i| i.contains("LP shares")));
+    }
+
     #[test]
     fn audit_state_flags_slot_below_sentinel_and_above_capacity() {
         let mut store = build_store(HashMap::new(), HashMap::new(), test_storage());
@@ -920,6 +1177,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: 42,
                 currency_stock: 0.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
         let mut store = build_store(pairs, HashMap::new(), storage);
@@ -970,6 +1232,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: -1,
                 currency_stock: -2.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
         let mut store = build_store(pairs, HashMap::new(), test_storage());
@@ -1006,6 +1273,7 @@ mod tests {
                 username: "eve".to_string(),
                 balance: -1.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let mut store = build_store(HashMap::new(), users, test_storage());
@@ -1029,6 +1297,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: 10, // drift; only issue
                 currency_stock: 0.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
         let mut store = build_store(pairs, HashMap::new(), storage);
@@ -1048,6 +1321,11 @@ mod tests {
             stack_size: 64,
             item_stock: count,
             currency_stock: 0.0,
+            buy_fee: None,
+            sell_fee: None,
+            curve: Default::default(),
+            stats: Default::default(),
+            lp_total_shares: 0.0,
         }
     }
 
@@ -1072,6 +1350,7 @@ mod tests {
                 username: "trader".to_string(),
                 balance: 10.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let mut store = build_store(pairs, users, storage);
@@ -1126,6 +1405,7 @@ mod tests {
                 username: "me".to_string(),
                 balance: f64::NAN,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -1135,6 +1415,7 @@ mod tests {
                 username: "other".to_string(),
                 balance: f64::NAN,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         let mut store = build_store(pairs, users, test_storage());
@@ -1152,6 +1433,7 @@ mod tests {
                 username: "me2".to_string(),
                 balance: 5.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         assert!(
@@ -1175,6 +1457,7 @@ mod tests {
         let r = AuditReport {
             issues: vec!["issue A".to_string()],
             repair_applied: true,
+            reconciliation: None,
         };
         let lines = r.to_lines();
         assert_eq!(lines.len(), 2);
@@ -1187,6 +1470,7 @@ mod tests {
         let r = AuditReport {
             issues: vec!["x".to_string(), "y".to_string()],
             repair_applied: false,
+            reconciliation: None,
         };
         assert_eq!(r.to_lines(), vec!["x".to_string(), "y".to_string()]);
     }
@@ -3,10 +3,10 @@
 use std::time::Duration;
 
 use tokio::sync::oneshot;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use super::Store;
-use crate::constants::WHISPER_ACK_TIMEOUT_SECS;
+use crate::constants::{PLAYER_PRESENCE_SECS, WHISPER_ACK_TIMEOUT_SECS};
 use crate::messages::BotInstruction;
 use crate::types::User;
 
@@ -39,6 +39,7 @@ pub fn ensure_user_exists(store: &mut Store, username: &str, uuid: &str) {
                 username: username.to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         store.dirty = true;
@@ -141,6 +142,48 @@ pub async fn send_message_to_player(
     }
 }
 
+/// `true` when the player has whispered the bot within
+/// `PLAYER_PRESENCE_SECS`, the closest the Store gets to knowing they are
+/// online.
+pub fn is_present(store: &Store, user_uuid: &str) -> bool {
+    store
+        .rate_limiter
+        .last_seen(&format!("u:{}", user_uuid))
+        .is_some_and(|t| t.elapsed() < Duration::from_secs(PLAYER_PRESENCE_SECS))
+}
+
+/// Tell a player about something another player (or a timer) did to their
+/// account. A present player is whispered now; anyone else, or a whisper
+/// the bot could not send, is held in `store.notices` until their next
+/// whisper, since a whisper to an offline player is silently lost.
+pub async fn notify_player(store: &mut Store, user_uuid: &str, player_name: &str, message: String) {
+    if is_present(store, user_uuid) {
+        match send_message_to_player(store, player_name, &message).await {
+            Ok(()) => return,
+            Err(e) => debug!(
+                player = player_name,
+                error = %e,
+                "Whisper failed, holding notice instead"
+            ),
+        }
+    }
+    store.notices.push(user_uuid, message);
+}
+
+/// Whisper everything held in `store.notices` for `user_uuid`, oldest first.
+/// Called when the player whispers the bot or is seen joining.
+pub async fn deliver_held_notices(store: &mut Store, player_name: &str, user_uuid: &str) {
+    for notice in store.notices.take(user_uuid) {
+        if let Err(e) = send_message_to_player(store, player_name, &notice.message).await {
+            warn!(
+                player = player_name,
+                error = %e,
+                "Failed to deliver held notice"
+            );
+        }
+    }
+}
+
 /// Whisper a sanitized rendering of `err` to the player.
 ///
 /// Canonical "tell the player about a [`StoreError`]" path: every handler
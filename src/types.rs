@@ -8,12 +8,15 @@
 
 pub mod chest;
 pub mod item_id;
+pub mod liquidity;
 pub mod node;
 pub mod order;
 pub mod pair;
+pub mod pair_stats;
 pub mod position;
 pub mod storage;
 pub mod trade;
+pub mod trade_segment;
 pub mod user;
 
 pub use chest::Chest;
@@ -21,6 +24,7 @@ pub use item_id::ItemId;
 pub use node::Node;
 pub use order::Order;
 pub use pair::Pair;
+pub use pair_stats::PairStats;
 pub use position::Position;
 pub use storage::Storage;
 pub use trade::Trade;
@@ -14,6 +14,11 @@
 //! ## Position Calculation
 //! Chest positions are derived from node position and chest index.
 //! See `Chest::new()` for offset calculations.
+//!
+//! ## Classes
+//! A chest is either store stock or escrow (see [`ChestClass`]). Escrow
+//! chests hold items players have handed over for `escrow` trades; they
+//! belong to no pair and are invisible to the stock planners in `storage.rs`.
 
 use serde::{Deserialize, Serialize};
 
@@ -22,6 +27,23 @@ use crate::error::StoreError;
 use crate::types::item_id::ItemId;
 use crate::types::position::Position;
 
+/// What a chest's contents belong to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChestClass {
+    /// The store's own stock, counted in `Pair::item_stock`.
+    #[default]
+    Stock,
+    /// Items held for open escrows (`store::escrow`). Claimed from empty
+    /// chests on demand and handed back to `Stock` once emptied.
+    Escrow,
+}
+
+impl ChestClass {
+    fn is_stock(&self) -> bool {
+        *self == ChestClass::Stock
+    }
+}
+
 /// Represents a single chest in the storage system.
 ///
 /// **Model**: 54-slot double chest where each slot contains 1 shulker box.
@@ -61,6 +83,10 @@ pub struct Chest {
     /// Item count per slot (54 slots, each contains 1 shulker box)
     /// `amounts[i]` = items inside the shulker in slot `i`
     pub amounts: Vec<i32>,
+    /// Absent from node files written before escrow existed, hence
+    /// `serde(default)`; omitted for stock chests.
+    #[serde(default, skip_serializing_if = "ChestClass::is_stock")]
+    pub class: ChestClass,
 }
 
 impl Chest {
@@ -68,7 +94,7 @@ impl Chest {
     ///
     /// # Panics
     /// Panics if `index` is not in `0..4`. Invalid indices indicate a bug in
-    /// the caller (Node::new, Node::load) — these callers control the index
+    /// the caller (Node::new, Node::load_from_dir) — these callers control the index
     /// directly, so an out-of-range value is unrecoverable, not a runtime error.
     pub fn new(node_id: i32, node_position: &Position, index: i32) -> Chest {
         assert!(
@@ -85,9 +111,25 @@ impl Chest {
             position,
             item: ItemId::EMPTY,
             amounts: vec![0; DOUBLE_CHEST_SLOTS],
+            class: ChestClass::Stock,
         }
     }
 
+    /// Whether this is a stock chest assigned to `item`.
+    pub fn is_stock_of(&self, item: &str) -> bool {
+        self.class == ChestClass::Stock && self.item == item
+    }
+
+    /// Whether this is an escrow chest assigned to `item`.
+    pub fn is_escrow_of(&self, item: &str) -> bool {
+        self.class == ChestClass::Escrow && self.item == item
+    }
+
+    /// Whether the chest is free to be assigned to any item or class.
+    pub fn is_unassigned(&self) -> bool {
+        self.class == ChestClass::Stock && self.item.is_empty()
+    }
+
     /// Verifies the on-struct invariants documented above (`amounts.len()`,
     /// `id == node_id * CHESTS_PER_NODE + index`, redundant id-field
     /// agreement, and that `index` is in range). Save/load boundary helper —
@@ -171,7 +213,7 @@ impl Chest {
     ///
     /// # Panics
     /// Panics if `index` is not in range 0-3. This is a programming error;
-    /// all callers (Node::new, Node::load, bot validation) control the index
+    /// all callers (Node::new, Node::load_from_dir, bot validation) control the index
     /// parameter directly.
     pub fn calc_position(node_position: &Position, index: i32) -> Position {
         match index {
@@ -224,9 +266,26 @@ mod tests {
             assert_eq!(c.amounts.len(), DOUBLE_CHEST_SLOTS);
             assert!(c.item.is_empty());
             assert!(c.amounts.iter().all(|&a| a == 0));
+            assert!(c.is_unassigned());
         }
     }
 
+    #[test]
+    fn class_defaults_to_stock_and_is_omitted_from_json() {
+        let mut c = Chest::new(1, &node_origin(), 2);
+        let json = serde_json::to_string(&c).unwrap();
+        assert!(!json.contains("class"));
+        let back: Chest = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.class, ChestClass::Stock);
+
+        c.class = ChestClass::Escrow;
+        c.item = ItemId::new("cobblestone").unwrap();
+        let back: Chest = serde_json::from_str(&serde_json::to_string(&c).unwrap()).unwrap();
+        assert!(back.is_escrow_of("cobblestone"));
+        assert!(!back.is_stock_of("cobblestone"));
+        assert!(!back.is_unassigned());
+    }
+
     #[test]
     fn calc_position_matches_layout_for_all_indices() {
         let n = node_origin();
@@ -0,0 +1,168 @@
+//! Liquidity-provider (LP) share accounting.
+//!
+//! A pair's reserves are owned pro rata by `Pair::lp_total_shares` shares.
+//! Players hold theirs in `User::lp_shares`; whatever no player holds
+//! belongs to the house (the operator's seed liquidity). Until the first
+//! player deposit the field is zero and the supply is implicitly
+//! `item_stock`, one house share per seeded item, so that deposit mints at
+//! the current ratio without diluting anyone.
+//!
+//! Trading fees stay in the reserves, so `k` and with it the value of every
+//! share grows with each trade: that is how providers earn. Operator
+//! `additem` / `addcurrency` adjustments change the reserves without minting
+//! shares and are therefore shared by every holder, house included.
+
+use super::Pair;
+
+/// Shares below this are treated as zero, so float dust left after a full
+/// redemption does not linger as a phantom position.
+pub const SHARE_EPSILON: f64 = 1e-9;
+
+/// What an `lp add` of some items costs and mints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LpDeposit {
+    /// Diamonds debited from the provider's balance to match the items at
+    /// the pool's current ratio.
+    pub diamonds: f64,
+    pub shares: f64,
+}
+
+/// What burning some shares pays out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LpRedemption {
+    /// Whole items handed over in the trade. The fractional remainder stays
+    /// in the pool for the remaining holders.
+    pub items: i32,
+    /// Diamonds credited to the provider's balance.
+    pub diamonds: f64,
+}
+
+/// Total shares outstanding, counting the implicit house supply of a pair
+/// no player has funded yet.
+pub fn share_supply(pair: &Pair) -> f64 {
+    if pair.lp_total_shares > 0.0 {
+        pair.lp_total_shares
+    } else {
+        f64::from(pair.item_stock.max(0))
+    }
+}
+
+/// Terms for depositing `items` into `pair`. `None` when the pool has no
+/// price to match (either reserve empty) or `items` is not positive.
+pub fn deposit_terms(pair: &Pair, items: i32) -> Option<LpDeposit> {
+    if items <= 0 || pair.item_stock <= 0 || pair.currency_stock <= 0.0 {
+        return None;
+    }
+    let fraction = f64::from(items) / f64::from(pair.item_stock);
+    let terms = LpDeposit {
+        diamonds: pair.currency_stock * fraction,
+        shares: share_supply(pair) * fraction,
+    };
+    (terms.diamonds.is_finite() && terms.shares.is_finite()).then_some(terms)
+}
+
+/// Terms for burning `shares` of `pair`. `None` when `shares` is not
+/// positive or exceeds the supply.
+pub fn redemption_terms(pair: &Pair, shares: f64) -> Option<LpRedemption> {
+    let supply = share_supply(pair);
+    if !shares.is_finite() || shares <= 0.0 || supply <= 0.0 || shares > supply + SHARE_EPSILON {
+        return None;
+    }
+    let fraction = (shares / supply).min(1.0);
+    Some(LpRedemption {
+        items: (f64::from(pair.item_stock.max(0)) * fraction).floor() as i32,
+        diamonds: pair.currency_stock.max(0.0) * fraction,
+    })
+}
+
+/// One-line description of a position, e.g. for the `lp` whisper.
+pub fn position_summary(pair: &Pair, shares: f64) -> String {
+    let supply = share_supply(pair);
+    let fraction = if supply > 0.0 { shares / supply } else { 0.0 };
+    format!(
+        "{}: {:.4} shares ({:.2}% of pool) = {:.1} {} + {:.2} diamonds",
+        pair.item,
+        shares,
+        fraction * 100.0,
+        f64::from(pair.item_stock.max(0)) * fraction,
+        pair.item,
+        pair.currency_stock.max(0.0) * fraction
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ItemId;
+    use proptest::prelude::*;
+
+    fn pair(item_stock: i32, currency_stock: f64, lp_total_shares: f64) -> Pair {
+        Pair {
+            item: ItemId::new("iron_ingot").unwrap(),
+            stack_size: 64,
+            item_stock,
+            currency_stock,
+            lp_total_shares,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_deposit_mints_against_implicit_house_supply() {
+        let p = pair(1000, 500.0, 0.0);
+        assert_eq!(share_supply(&p), 1000.0);
+        let d = deposit_terms(&p, 100).unwrap();
+        assert_eq!(d.diamonds, 50.0);
+        assert_eq!(d.shares, 100.0);
+    }
+
+    #[test]
+    fn deposit_needs_both_reserves() {
+        assert!(deposit_terms(&pair(0, 500.0, 0.0), 10).is_none());
+        assert!(deposit_terms(&pair(1000, 0.0, 0.0), 10).is_none());
+        assert!(deposit_terms(&pair(1000, 500.0, 0.0), 0).is_none());
+    }
+
+    #[test]
+    fn fee_growth_raises_redemption_value() {
+        // 100 of 1100 shares; a trade then adds 11 diamonds of fees.
+        let p = pair(1100, 561.0, 1100.0);
+        let r = redemption_terms(&p, 100.0).unwrap();
+        assert_eq!(r.items, 100);
+        assert!((r.diamonds - 51.0).abs() < 1e-9, "{}", r.diamonds);
+    }
+
+    #[test]
+    fn redemption_rejects_more_than_supply() {
+        let p = pair(1000, 500.0, 1200.0);
+        assert!(redemption_terms(&p, 1200.1).is_none());
+        assert!(redemption_terms(&p, 0.0).is_none());
+        assert!(redemption_terms(&p, f64::NAN).is_none());
+        assert!(redemption_terms(&p, 1200.0).is_some());
+    }
+
+    proptest! {
+        /// Depositing and immediately redeeming never returns more than was
+        /// put in: otherwise a provider could drain the pool by cycling.
+        #[test]
+        fn deposit_then_redeem_is_not_profitable(
+            item_stock in 1i32..1_000_000,
+            currency_stock in 0.01f64..1e7,
+            house_extra in 0.0f64..1e6,
+            items in 1i32..10_000,
+        ) {
+            let mut p = pair(item_stock, currency_stock, 0.0);
+            if house_extra > 0.0 {
+                p.lp_total_shares = f64::from(item_stock) + house_extra;
+            }
+            let d = deposit_terms(&p, items).unwrap();
+            p.lp_total_shares = share_supply(&p) + d.shares;
+            p.item_stock += items;
+            p.currency_stock += d.diamonds;
+
+            let r = redemption_terms(&p, d.shares).unwrap();
+            prop_assert!(r.items <= items);
+            prop_assert!(r.diamonds <= d.diamonds * (1.0 + 1e-9));
+        }
+    }
+}
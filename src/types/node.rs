@@ -33,7 +33,7 @@ use crate::types::chest::Chest;
 use crate::types::position::Position;
 
 /// On-disk directory for per-node files. Single source of truth shared by
-/// `Node::load`, `Node::save`, `Storage::load`, and the CLI removeNode path.
+/// `Node::load_from_dir`, `Node::save`, `Storage::load`, and the CLI removeNode path.
 pub(crate) const STORAGE_DIR: &str = "data/storage";
 
 /// On-disk path for node `id`. Mirrors the convention encoded by
@@ -47,7 +47,7 @@ pub(crate) fn node_file_path(id: i32) -> std::path::PathBuf {
 ///
 /// `chests` always has exactly [`CHESTS_PER_NODE`] entries (indices 0..=3).
 /// Node 0 reserves chest 0 for diamonds and chest 1 for overflow; these
-/// assignments are re-enforced on every [`Node::load`].
+/// assignments are re-enforced on every [`Node::load_from_dir`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     /// Node ID, also the filename stem in `data/storage/{id}.json`.
@@ -97,7 +97,7 @@ impl Node {
         }
     }
 
-    /// Loads a node from `data/storage/{id}.json` and reconciles it with the
+    /// Loads a node from `base/{id}.json` and reconciles it with the
     /// current storage origin.
     ///
     /// Positions in the file are derivable state: the node position and all
@@ -109,14 +109,10 @@ impl Node {
     /// For node 0, the reserved chest invariants (chest 0 = diamond,
     /// chest 1 = overflow) are re-enforced even on load in case the file was
     /// edited manually, and any correction is persisted back to disk.
-    pub fn load(id: i32, storage_position: &Position) -> Result<Self, StoreError> {
-        Self::load_from_dir(id, storage_position, Path::new(STORAGE_DIR))
-    }
-
-    /// Same as [`Self::load`], but reads from `base/{id}.json` instead of the
-    /// hard-coded [`STORAGE_DIR`]. Exists so unit tests can exercise the JSON
-    /// invariant checks against a temp dir without polluting the real data dir.
-    fn load_from_dir(
+    ///
+    /// `Storage::load` passes [`STORAGE_DIR`] as `base`, unit
+    /// tests a temp dir, and `--restore` a staged snapshot.
+    pub(crate) fn load_from_dir(
         id: i32,
         storage_position: &Position,
         base: &Path,
@@ -555,7 +551,7 @@ mod tests {
     // -----------------------------------------------------------------
     // save_to_dir / load_from_dir round-trip + on-disk invariant tests.
     //
-    // These pin every JSON-shaped invariant in `Node::load`: chest count,
+    // These pin every JSON-shaped invariant in `Node::load_from_dir`: chest count,
     // duplicate / out-of-range indices, redundant-id agreement,
     // amounts.len, and the node-0 reserved-chest fixup including the
     // "refuse to relabel a non-empty reserved chest" guard. Without
@@ -2,8 +2,8 @@
 //!
 //! Orders are an in-memory transient session log of recently-issued user
 //! requests. The file at `data/orders.json` is rewritten on each save and
-//! deleted unconditionally on startup; the persistent audit log lives in
-//! `data/trades/`.
+//! deleted unconditionally on startup; committed trades persist in
+//! `data/trades/` and every order event in `data/audit/orders.jsonl`.
 //!
 //! The maximum number of orders kept in memory can be configured in
 //! `data/config.json` via the `max_orders` field. The default is 10,000.
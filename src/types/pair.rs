@@ -8,6 +8,11 @@
 //! - **Buy price** = `(currency_stock / item_stock) * (1 + fee)`
 //! - **Sell price** = `(currency_stock / item_stock) * (1 - fee)`
 //!
+//! `fee` is the global `Config::fee` unless the pair carries a `buy_fee` /
+//! `sell_fee` override. The formulas above are the default
+//! constant-product curve; a pair can select another one via `curve`
+//! (see `store::curve`).
+//!
 //! This implements a simple constant product market maker (CPMM) model.
 //! See `README.md` "Reserve-based pricing" for details.
 
@@ -20,8 +25,11 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::constants::{FEE_MAX, FEE_MIN};
 use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
-use crate::types::ItemId;
+use crate::store::curve::PricingCurve;
+use crate::store::pricing::validate_fee;
+use crate::types::{ItemId, PairStats};
 
 use tracing::{info, warn};
 
@@ -41,6 +49,8 @@ static PAIR_ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
 /// **Pricing**: Prices are **not stored** but derived from reserves:
 /// - Buy: `(currency_stock / item_stock) * (1 + fee)` - player pays more
 /// - Sell: `(currency_stock / item_stock) * (1 - fee)` - player receives less
+/// - Those are the constant-product spot prices; `curve` can select another
+///   pricing curve (see `store::curve`)
 ///
 /// **Reserves**:
 /// - `item_stock`: Total items available in storage (sum of all chests for this item)
@@ -52,10 +62,14 @@ static PAIR_ARCHIVE_SEQ: AtomicU64 = AtomicU64::new(0);
 /// - When `item_stock == 0`, buy orders fail (no items to sell)
 /// - When `currency_stock == 0`, sell orders fail (no diamonds to pay)
 ///
-/// **Future Enhancements**:
-/// - Track trading volumes, fees collected, number of trades
-/// - Consider separate buy/sell fees
-/// - Add statistics computed from Trade history
+/// **Fees**: `buy_fee` / `sell_fee` override the global `Config::fee` for
+/// this pair only. Invalid overrides are dropped at load (see `load_all`).
+///
+/// **Statistics**: `stats` accumulates volume, fee revenue, and trade count
+/// from every customer trade (see `PairStats`).
+///
+/// **Liquidity**: `lp_total_shares` is the share supply over the reserves;
+/// players' holdings live in `User::lp_shares` (see `types::liquidity`).
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Pair {
     pub item: ItemId,
@@ -66,12 +80,65 @@ pub struct Pair {
     pub item_stock: i32,
     /// Reserve of the base currency (diamonds).
     pub currency_stock: f64,
+    /// Per-pair fee on buys; `None` uses the global `Config::fee`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buy_fee: Option<f64>,
+    /// Per-pair fee on sells; `None` uses the global `Config::fee`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sell_fee: Option<f64>,
+    /// How this pair turns reserves into prices. Omitted (and defaulted)
+    /// for the original constant-product curve.
+    #[serde(default, skip_serializing_if = "PricingCurve::is_constant_product")]
+    pub curve: PricingCurve,
+    /// Cumulative trading statistics. Omitted until the first trade.
+    #[serde(default, skip_serializing_if = "PairStats::is_empty")]
+    pub stats: PairStats,
+    /// LP shares outstanding, house included. Zero (and omitted) until the
+    /// first player deposit; see `liquidity::share_supply`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub lp_total_shares: f64,
 }
 
 impl Pair {
     // One file per pair keeps diffs small and avoids rewriting the catalog on every update.
     const PAIRS_DIR: &str = "data/pairs";
 
+    /// Fee applied to buys from this pair: the override, else `global_fee`.
+    pub fn effective_buy_fee(&self, global_fee: f64) -> f64 {
+        self.buy_fee.unwrap_or(global_fee)
+    }
+
+    /// Fee applied to sells into this pair: the override, else `global_fee`.
+    pub fn effective_sell_fee(&self, global_fee: f64) -> f64 {
+        self.sell_fee.unwrap_or(global_fee)
+    }
+
+    /// `true` when either side carries a fee override.
+    pub fn has_fee_override(&self) -> bool {
+        self.buy_fee.is_some() || self.sell_fee.is_some()
+    }
+
+    /// Clear any override that fails `validate_fee`, returning a description
+    /// of each one dropped. A hand-edited `"buy_fee": 5` (meaning 5 %) would
+    /// otherwise make every quote for the pair fail; falling back to the
+    /// global fee keeps the pair tradable while the warning surfaces it.
+    pub(crate) fn drop_invalid_fee_overrides(&mut self) -> Vec<String> {
+        let mut dropped = Vec::new();
+        if let Some(f) = self.buy_fee
+            && !validate_fee(f)
+        {
+            dropped.push(format!("buy_fee {f}"));
+            self.buy_fee = None;
+        }
+        if let Some(f) = self.sell_fee
+            && !validate_fee(f)
+        {
+            dropped.push(format!("sell_fee {f}"));
+            self.sell_fee = None;
+        }
+        dropped
+    }
+
     pub fn shulker_capacity_for_stack_size(stack_size: i32) -> i32 {
         crate::constants::SHULKER_BOX_SLOTS as i32 * stack_size
     }
@@ -111,12 +178,9 @@ impl Pair {
     /// Returns an error if the item name is empty/sentinel or `stack_size` is
     /// not one of Minecraft's three legal values (1, 16, 64).
     ///
-    /// Thin wrapper over `save_in_dir` rooted at `PAIRS_DIR`. Production
-    /// callsites today reach the disk through `Pair::save_all` /
-    /// `save_all_in_dir`, so the per-pair entry point is exercised only by
-    /// tests — `#[allow(dead_code)]` keeps the symbol available for any
-    /// future single-pair write path without spamming the warning channel.
-    #[allow(dead_code)]
+    /// Thin wrapper over `save_in_dir` rooted at `PAIRS_DIR`. The Store
+    /// reaches the disk through `Pair::save_all` / `save_all_in_dir`; this
+    /// per-pair entry point writes the pairs `--rebuild-ledger` repaired.
     pub fn save(&self) -> io::Result<()> {
         self.save_in_dir(Path::new(Self::PAIRS_DIR))
     }
@@ -195,7 +259,12 @@ impl Pair {
     /// silently delete them, and so subsequent `load_all` calls won't retry.
     /// If the directory does not exist, it returns an empty `HashMap<String, Pair>`.
     pub fn load_all() -> io::Result<HashMap<String, Self>> {
-        let dir_path = Path::new(Self::PAIRS_DIR);
+        Self::load_all_in_dir(Path::new(Self::PAIRS_DIR))
+    }
+
+    /// Directory-parameterized form of `load_all`, used to read the pairs of
+    /// a snapshot for `--rebuild-ledger`.
+    pub(crate) fn load_all_in_dir(dir_path: &Path) -> io::Result<HashMap<String, Self>> {
         let mut pairs = HashMap::new();
 
         if !dir_path.exists() {
@@ -301,6 +370,26 @@ impl Pair {
                                 }
                                 quarantined += 1;
                             } else {
+                                let mut pair = pair;
+                                // An invalid curve is kept as-is: pricing
+                                // refuses to quote it, which is safer than
+                                // silently repricing on another curve.
+                                if let Err(e) = pair.curve.validate() {
+                                    warn!(
+                                        "[Pair] '{}': invalid pricing curve ({}); pair will not quote until fixed",
+                                        item_name, e
+                                    );
+                                }
+                                let dropped = pair.drop_invalid_fee_overrides();
+                                if !dropped.is_empty() {
+                                    warn!(
+                                        "[Pair] '{}': ignoring invalid fee override(s) {} (must be within [{}, {}]); using the global fee",
+                                        item_name,
+                                        dropped.join(", "),
+                                        FEE_MIN,
+                                        FEE_MAX
+                                    );
+                                }
                                 pairs.insert(item_name, pair);
                             }
                         }
@@ -466,6 +555,10 @@ impl Pair {
     }
 }
 
+fn is_zero(v: &f64) -> bool {
+    *v == 0.0
+}
+
 /// Rename a malformed/unreadable pair file aside so the next `save_all`
 /// orphan-cleanup cannot delete it (extension is no longer `.json`) and
 /// subsequent `load_all` calls do not retry deserializing it.
@@ -646,6 +739,11 @@ mod tests {
                 stack_size: 64,
                 item_stock: 1,
                 currency_stock: 0.0,
+                buy_fee: None,
+                sell_fee: None,
+                curve: Default::default(),
+                stats: Default::default(),
+                lp_total_shares: 0.0,
             },
         );
 
@@ -678,4 +776,58 @@ mod tests {
             let _ = fs::remove_file(&real_guard);
         }
     }
+
+    #[test]
+    fn fee_overrides_default_to_none_and_are_omitted_when_unset() {
+        let legacy = r#"{"item":"diamond","stack_size":64,"item_stock":5,"currency_stock":10.0}"#;
+        let pair: Pair = serde_json::from_str(legacy).unwrap();
+        assert_eq!(pair.buy_fee, None);
+        assert_eq!(pair.sell_fee, None);
+        assert!(!pair.has_fee_override());
+        let json = serde_json::to_string(&pair).unwrap();
+        assert!(!json.contains("buy_fee") && !json.contains("sell_fee"));
+    }
+
+    #[test]
+    fn curve_defaults_to_constant_product_and_is_omitted() {
+        let legacy = r#"{"item":"diamond","stack_size":64,"item_stock":5,"currency_stock":10.0}"#;
+        let mut pair: Pair = serde_json::from_str(legacy).unwrap();
+        assert!(pair.curve.is_constant_product());
+        assert!(!serde_json::to_string(&pair).unwrap().contains("curve"));
+
+        pair.curve = PricingCurve::Fixed {
+            price: 2.0,
+            max_stock: None,
+        };
+        let json = serde_json::to_string(&pair).unwrap();
+        let back: Pair = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, pair);
+    }
+
+    #[test]
+    fn effective_fee_prefers_override_per_side() {
+        let pair = Pair {
+            item: ItemId::new("diamond").unwrap(),
+            stack_size: 64,
+            item_stock: 5,
+            currency_stock: 10.0,
+            buy_fee: Some(0.05),
+            sell_fee: None,
+            curve: Default::default(),
+            stats: Default::default(),
+            lp_total_shares: 0.0,
+        };
+        assert!((pair.effective_buy_fee(0.125) - 0.05).abs() < 1e-12);
+        assert!((pair.effective_sell_fee(0.125) - 0.125).abs() < 1e-12);
+    }
+
+    #[test]
+    fn drop_invalid_fee_overrides_keeps_valid_side() {
+        let json = r#"{"item":"diamond","stack_size":64,"item_stock":5,"currency_stock":10.0,"buy_fee":5.0,"sell_fee":0.2}"#;
+        let mut pair: Pair = serde_json::from_str(json).unwrap();
+        let dropped = pair.drop_invalid_fee_overrides();
+        assert_eq!(dropped, vec!["buy_fee 5".to_string()]);
+        assert_eq!(pair.buy_fee, None);
+        assert_eq!(pair.sell_fee, Some(0.2));
+    }
 }
@@ -0,0 +1,177 @@
+//! Cumulative per-pair trading statistics.
+//!
+//! Stored on `Pair::stats` and persisted with the pair file, because the
+//! in-memory trade history is capped at `max_trades_in_memory` and older
+//! trade files can be pruned: totals derived only from loaded trades would
+//! shrink as history ages out. Each customer `Buy` / `Sell` trade is folded
+//! in with [`PairStats::record`] as it commits; admin adjustments
+//! (`AddStock`, `AddCurrency`, ...) are not trading activity and are
+//! ignored.
+//!
+//! Fee revenue comes from `Trade::fee`. Trades written before that field
+//! existed carry no fee, so a pair backfilled from old history reports
+//! volume and trade count accurately but under-reports fees.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Trade, TradeType};
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PairStats {
+    /// Items players bought from the store.
+    #[serde(default)]
+    pub items_bought: u64,
+    /// Items players sold to the store.
+    #[serde(default)]
+    pub items_sold: u64,
+    /// Diamonds paid by players on buys, fees included.
+    #[serde(default)]
+    pub diamonds_in: f64,
+    /// Diamonds paid out to players on sells, after fees.
+    #[serde(default)]
+    pub diamonds_out: f64,
+    /// Fee portion of `diamonds_in` and the fees withheld from
+    /// `diamonds_out`. Stays in the pair's reserve.
+    #[serde(default)]
+    pub fees_collected: f64,
+    #[serde(default)]
+    pub buy_count: u64,
+    #[serde(default)]
+    pub sell_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_trade: Option<DateTime<Utc>>,
+}
+
+impl PairStats {
+    /// Fold every customer trade for `item` in `trades` into fresh stats.
+    pub fn from_trades<'a>(item: &str, trades: impl IntoIterator<Item = &'a Trade>) -> Self {
+        let mut stats = Self::default();
+        for trade in trades {
+            if trade.item.as_str() == item {
+                stats.record(trade);
+            }
+        }
+        stats
+    }
+
+    /// Add one trade. Returns `false` (and changes nothing) for trade types
+    /// that are not customer buys or sells. The caller is responsible for
+    /// passing only trades of this pair's item.
+    pub fn record(&mut self, trade: &Trade) -> bool {
+        let amount = u64::try_from(trade.amount).unwrap_or(0);
+        match trade.trade_type {
+            TradeType::Buy => {
+                self.items_bought += amount;
+                self.diamonds_in += trade.amount_currency;
+                self.buy_count += 1;
+            }
+            TradeType::Sell => {
+                self.items_sold += amount;
+                self.diamonds_out += trade.amount_currency;
+                self.sell_count += 1;
+            }
+            _ => return false,
+        }
+        self.fees_collected += trade.fee.unwrap_or(0.0);
+        self.last_trade = Some(match self.last_trade {
+            Some(prev) => prev.max(trade.timestamp),
+            None => trade.timestamp,
+        });
+        true
+    }
+
+    pub fn trade_count(&self) -> u64 {
+        self.buy_count + self.sell_count
+    }
+
+    /// `true` until the first trade is recorded. Used to omit the field
+    /// from pair files that have never traded.
+    pub fn is_empty(&self) -> bool {
+        self.trade_count() == 0
+    }
+
+    /// One-line operator/player summary, e.g. for the `stats` whisper.
+    pub fn summary(&self) -> String {
+        let last = match self.last_trade {
+            Some(t) => t.format("%Y-%m-%d %H:%M UTC").to_string(),
+            None => "never".to_string(),
+        };
+        format!(
+            "{} trades ({} buys, {} sells). Volume: {} bought / {} sold, {:.2} diamonds in / {:.2} out. Fees: {:.2}. Last trade: {}.",
+            self.trade_count(),
+            self.buy_count,
+            self.sell_count,
+            self.items_bought,
+            self.items_sold,
+            self.diamonds_in,
+            self.diamonds_out,
+            self.fees_collected,
+            last
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ItemId;
+
+    fn trade(kind: TradeType, item: &str, amount: i32, currency: f64, fee: Option<f64>) -> Trade {
+        let t = Trade::new(
+            kind,
+            ItemId::new(item).unwrap(),
+            amount,
+            currency,
+            "uuid".to_string(),
+        );
+        match fee {
+            Some(f) => t.with_fee(f),
+            None => t,
+        }
+    }
+
+    #[test]
+    fn record_accumulates_buys_and_sells() {
+        let mut stats = PairStats::default();
+        assert!(stats.is_empty());
+        assert!(stats.record(&trade(TradeType::Buy, "iron_ingot", 10, 22.5, Some(2.5))));
+        assert!(stats.record(&trade(TradeType::Sell, "iron_ingot", 4, 7.0, Some(1.0))));
+
+        assert_eq!(stats.items_bought, 10);
+        assert_eq!(stats.items_sold, 4);
+        assert_eq!(stats.diamonds_in, 22.5);
+        assert_eq!(stats.diamonds_out, 7.0);
+        assert_eq!(stats.fees_collected, 3.5);
+        assert_eq!(stats.trade_count(), 2);
+        assert!(stats.last_trade.is_some());
+    }
+
+    #[test]
+    fn record_ignores_admin_adjustments() {
+        let mut stats = PairStats::default();
+        assert!(!stats.record(&trade(TradeType::AddStock, "iron_ingot", 64, 0.0, None)));
+        assert!(!stats.record(&trade(TradeType::AddCurrency, "iron_ingot", 0, 100.0, None)));
+        assert_eq!(stats, PairStats::default());
+    }
+
+    #[test]
+    fn from_trades_filters_by_item_and_tolerates_missing_fee() {
+        let trades = vec![
+            trade(TradeType::Buy, "iron_ingot", 1, 2.0, None),
+            trade(TradeType::Buy, "gold_ingot", 5, 9.0, Some(1.0)),
+            trade(TradeType::Sell, "iron_ingot", 2, 3.0, Some(0.5)),
+        ];
+        let stats = PairStats::from_trades("iron_ingot", &trades);
+        assert_eq!(stats.buy_count, 1);
+        assert_eq!(stats.sell_count, 1);
+        assert_eq!(stats.fees_collected, 0.5);
+    }
+
+    #[test]
+    fn empty_stats_round_trip_through_empty_object() {
+        let stats: PairStats = serde_json::from_str("{}").unwrap();
+        assert_eq!(stats, PairStats::default());
+        assert!(stats.summary().contains("Last trade: never"));
+    }
+}
@@ -46,7 +46,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::fsutil::{archive_aside, pick_archive_path};
 use crate::types::ItemId;
-use crate::types::chest::Chest;
+use crate::types::chest::{Chest, ChestClass};
 use crate::types::node::Node;
 use crate::types::position::Position;
 
@@ -55,7 +55,7 @@ use crate::types::position::Position;
 /// Mirrors the `ARCHIVE_SEQ` pattern in `store::queue`, `store::journal`,
 /// `store::trade_state`, and the sibling `types::{user, pair, trade}`
 /// quarantine sites. Node-side is consistency-with-siblings: a
-/// `Node::load` failure (malformed JSON / unreadable file) previously only
+/// `Node::load_from_dir` failure (malformed JSON / unreadable file) previously only
 /// `warn!`ed and skipped, leaving a misleading `.json` in place that a
 /// re-run would parse-fail on again. Renaming it aside takes it out of
 /// the live load set and preserves forensic evidence.
@@ -130,12 +130,22 @@ impl Storage {
     ///
     /// Creates `data/storage/` on first run and returns an empty storage.
     pub fn load(storage_position: &Position) -> Result<Self, Box<dyn std::error::Error>> {
-        let storage_path = crate::types::node::STORAGE_DIR;
+        Self::load_from_dir(
+            storage_position,
+            Path::new(crate::types::node::STORAGE_DIR),
+        )
+    }
 
-        if !Path::new(storage_path).exists() {
+    /// Directory-parameterized form of `load`, also used to validate a
+    /// snapshot's `storage/` before `--restore` swaps it in.
+    pub(crate) fn load_from_dir(
+        storage_position: &Position,
+        storage_path: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !storage_path.exists() {
             fs::create_dir_all(storage_path)?;
             tracing::info!(
-                path = storage_path,
+                path = %storage_path.display(),
                 "[Storage] created empty storage directory"
             );
             return Ok(Storage {
@@ -165,7 +175,7 @@ impl Storage {
             let path = entry.path();
 
             // Cap node_id so `node_id * CHESTS_PER_NODE + 3` (computed in
-            // `Chest::new` and `Node::load`) cannot overflow i32. Hand-edited
+            // `Chest::new` and `Node::load_from_dir`) cannot overflow i32. Hand-edited
             // filenames like `2147483647.json` would otherwise wrap to a
             // negative chest id and silently collide with a real chest.
             let max_node_id = i32::MAX / crate::constants::CHESTS_PER_NODE as i32 - 1;
@@ -205,7 +215,7 @@ impl Storage {
                     continue;
                 }
             };
-            match Node::load(node_id, storage_position) {
+            match Node::load_from_dir(node_id, storage_position, storage_path) {
                 Ok(node) => nodes.push(node),
                 Err(e) => {
                     skipped += 1;
@@ -215,7 +225,7 @@ impl Storage {
                         "[Storage] failed to load node; quarantining",
                     );
                     if let Err(qe) =
-                        quarantine_node_file(&path, &format!("Node::load failed: {e}"))
+                        quarantine_node_file(&path, &format!("Node::load_from_dir failed: {e}"))
                     {
                         tracing::warn!(
                             node_id,
@@ -299,7 +309,7 @@ impl Storage {
         self.nodes
             .iter()
             .flat_map(|n| &n.chests)
-            .filter(|c| c.item == item)
+            .filter(|c| c.is_stock_of(item))
             .flat_map(|c| c.amounts.iter().copied())
             .filter(|a| *a > 0)
             .sum()
@@ -327,6 +337,16 @@ impl Storage {
     /// Returns the plan plus the total amount that could actually be planned
     /// (may be less than `qty` if storage is short).
     pub fn simulate_withdraw_plan(&self, item: &str, qty: i32) -> (Vec<ChestTransfer>, i32) {
+        self.simulate_withdraw_from(ChestClass::Stock, item, qty)
+    }
+
+    /// [`Self::simulate_withdraw_plan`] over the chests of one `class`.
+    fn simulate_withdraw_from(
+        &self,
+        class: ChestClass,
+        item: &str,
+        qty: i32,
+    ) -> (Vec<ChestTransfer>, i32) {
         if qty <= 0 || item.is_empty() {
             // Empty `item` would match every unassigned (EMPTY-sentinel)
             // chest via `chest.item != item` returning false — silently
@@ -344,7 +364,7 @@ impl Storage {
                 if remaining <= 0 {
                     break;
                 }
-                if chest.item != item {
+                if chest.class != class || chest.item != item {
                     continue;
                 }
                 let mut chest_taken = 0i32;
@@ -450,7 +470,7 @@ impl Storage {
                 if Self::is_reserved_chest_blocked_for(item, node_idx, chest_idx) {
                     continue;
                 }
-                if chest.item != item {
+                if !chest.is_stock_of(item) {
                     continue;
                 }
                 for slot_val in chest.amounts.iter() {
@@ -478,7 +498,7 @@ impl Storage {
                          plan: &mut Vec<ChestTransfer>,
                          claimed: &mut std::collections::HashSet<i32>,
                          chest: &Chest| {
-            if *remaining <= 0 || !chest.item.is_empty() || claimed.contains(&chest.id) {
+            if *remaining <= 0 || !chest.is_unassigned() || claimed.contains(&chest.id) {
                 return;
             }
             claimed.insert(chest.id);
@@ -539,6 +559,123 @@ impl Storage {
         (plan, qty - remaining)
     }
 
+    /// Sums `item` held in escrow chests. Escrowed items belong to a pending
+    /// player-to-player trade, so they never count toward a pair's stock.
+    pub fn escrow_item_amount(&self, item: &str) -> i32 {
+        self.nodes
+            .iter()
+            .flat_map(|n| &n.chests)
+            .filter(|c| c.is_escrow_of(item))
+            .flat_map(|c| c.amounts.iter().copied())
+            .filter(|a| *a > 0)
+            .sum()
+    }
+
+    /// Plans withdrawal of `qty` escrowed `item` **without mutating** storage
+    /// state. Same walk as `simulate_withdraw_plan`, over escrow chests only.
+    pub fn simulate_escrow_withdraw_plan(&self, item: &str, qty: i32) -> (Vec<ChestTransfer>, i32) {
+        self.simulate_withdraw_from(ChestClass::Escrow, item, qty)
+    }
+
+    /// Plans a deposit of `qty` items into escrow **without mutating** storage
+    /// state: partial shulkers in escrow chests of `item` first, then
+    /// unassigned chests in node order. The reserved chests on node 0 are
+    /// never used. Like `simulate_deposit_plan` this never grows storage.
+    ///
+    /// Pass the plan to [`Self::claim_escrow_chests`] before the bot runs it,
+    /// so the unassigned chests it picked are no longer offered to stock.
+    pub fn simulate_escrow_deposit_plan(
+        &self,
+        item: &str,
+        qty: i32,
+        stack_size: i32,
+    ) -> (Vec<ChestTransfer>, i32) {
+        if qty <= 0 || item.is_empty() {
+            return (Vec::new(), 0);
+        }
+        let shulker_capacity = crate::types::Pair::shulker_capacity_for_stack_size(stack_size);
+        let empty_chest_capacity = (Self::SLOTS_PER_CHEST as i32) * shulker_capacity;
+        let mut plan: Vec<ChestTransfer> = Vec::new();
+        let mut remaining = qty;
+        let mut push = |chest: &Chest, amount: i32| {
+            plan.push(ChestTransfer {
+                chest_id: chest.id,
+                position: chest.position,
+                item: ItemId::from_normalized(item.to_string()),
+                amount,
+            });
+        };
+
+        for chest in self.nodes.iter().flat_map(|n| &n.chests) {
+            if remaining <= 0 {
+                break;
+            }
+            if !chest.is_escrow_of(item) {
+                continue;
+            }
+            let room: i32 = chest
+                .amounts
+                .iter()
+                .filter(|a| (0..shulker_capacity).contains(*a))
+                .map(|a| shulker_capacity - a)
+                .sum();
+            let add = room.min(remaining);
+            if add > 0 {
+                remaining -= add;
+                push(chest, add);
+            }
+        }
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            for (chest_idx, chest) in node.chests.iter().enumerate() {
+                if remaining <= 0 {
+                    return (plan, qty);
+                }
+                let reserved = node_idx == 0
+                    && (chest_idx == crate::constants::DIAMOND_CHEST_ID as usize
+                        || chest_idx == crate::constants::OVERFLOW_CHEST_ID as usize);
+                if reserved || !chest.is_unassigned() {
+                    continue;
+                }
+                let add = empty_chest_capacity.min(remaining);
+                remaining -= add;
+                push(chest, add);
+            }
+        }
+        (plan, qty - remaining)
+    }
+
+    /// Marks every unassigned chest in `plan` as an escrow chest for `item`.
+    /// Chests already holding escrowed `item` are left as they are.
+    pub fn claim_escrow_chests(&mut self, plan: &[ChestTransfer], item: &str) {
+        for transfer in plan {
+            if let Some(chest) = self.get_chest_mut(transfer.chest_id)
+                && chest.is_unassigned()
+            {
+                chest.class = ChestClass::Escrow;
+                chest.item = ItemId::from_normalized(item.to_string());
+                tracing::info!(
+                    item,
+                    chest_id = chest.id,
+                    "[Storage] assigned empty chest to escrow",
+                );
+            }
+        }
+    }
+
+    /// Hands escrow chests that hold nothing back to stock as unassigned
+    /// chests. Returns how many were released.
+    pub fn release_empty_escrow_chests(&mut self) -> usize {
+        let mut released = 0;
+        for chest in self.nodes.iter_mut().flat_map(|n| &mut n.chests) {
+            if chest.class == ChestClass::Escrow && chest.amounts.iter().all(|a| *a <= 0) {
+                chest.class = ChestClass::Stock;
+                chest.item = ItemId::EMPTY;
+                released += 1;
+            }
+        }
+        released
+    }
+
     /// Plans withdrawal of `qty` items from storage.
     ///
     /// **Mutates** storage state (removes items from `Chest.amounts`) and returns
@@ -574,7 +711,7 @@ impl Storage {
                 }
 
                 let chest = &mut self.nodes[node_idx].chests[chest_idx];
-                if chest.item != item {
+                if !chest.is_stock_of(item) {
                     continue;
                 }
 
@@ -676,7 +813,7 @@ impl Storage {
                     continue;
                 }
                 let chest = &mut self.nodes[node_idx].chests[chest_idx];
-                if chest.item != item {
+                if !chest.is_stock_of(item) {
                     continue;
                 }
                 let deposited_here = Self::deposit_into_chest(chest, &mut qty, stack_size);
@@ -804,19 +941,19 @@ impl Storage {
             let node_0 = &nodes[0];
             if item == "diamond" {
                 let idx = crate::constants::DIAMOND_CHEST_ID as usize;
-                if node_0.chests.get(idx).is_some_and(|c| c.item.is_empty()) {
+                if node_0.chests.get(idx).is_some_and(|c| c.is_unassigned()) {
                     return Some((0, idx));
                 }
             }
             if item == crate::constants::OVERFLOW_CHEST_ITEM {
                 let idx = crate::constants::OVERFLOW_CHEST_ID as usize;
-                if node_0.chests.get(idx).is_some_and(|c| c.item.is_empty()) {
+                if node_0.chests.get(idx).is_some_and(|c| c.is_unassigned()) {
                     return Some((0, idx));
                 }
             }
             // General-purpose chests on node 0 start at index 2 (0 and 1 are reserved).
             for ci in 2..node_0.chests.len() {
-                if node_0.chests[ci].item.is_empty() {
+                if node_0.chests[ci].is_unassigned() {
                     return Some((0, ci));
                 }
             }
@@ -827,7 +964,7 @@ impl Storage {
                 continue;
             }
             for (ci, chest) in node.chests.iter().enumerate() {
-                if chest.item.is_empty() {
+                if chest.is_unassigned() {
                     return Some((ni, ci));
                 }
             }
@@ -1255,6 +1392,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn escrow_chests_are_invisible_to_stock_and_released_when_empty() {
+        let mut storage = test_storage();
+        let (plan, planned) = storage.simulate_escrow_deposit_plan("iron_ingot", 100, 64);
+        assert_eq!(planned, 100);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].chest_id, storage.nodes[0].chests[2].id);
+        storage.claim_escrow_chests(&plan, "iron_ingot");
+        storage.nodes[0].chests[2].amounts[0] = 100;
+
+        assert_eq!(storage.escrow_item_amount("iron_ingot"), 100);
+        assert_eq!(storage.total_item_amount("iron_ingot"), 0);
+        assert_eq!(storage.simulate_withdraw_plan("iron_ingot", 10).1, 0);
+        let (stock_plan, _) = storage.simulate_deposit_plan("iron_ingot", 10, 64);
+        assert_ne!(stock_plan[0].chest_id, storage.nodes[0].chests[2].id);
+        let (again, _) = storage.simulate_escrow_deposit_plan("iron_ingot", 10, 64);
+        assert_eq!(again[0].chest_id, storage.nodes[0].chests[2].id);
+        assert_eq!(storage.simulate_escrow_withdraw_plan("iron_ingot", 40).1, 40);
+
+        assert_eq!(storage.release_empty_escrow_chests(), 0);
+        storage.nodes[0].chests[2].amounts[0] = 0;
+        assert_eq!(storage.release_empty_escrow_chests(), 1);
+        assert!(storage.nodes[0].chests[2].is_unassigned());
+    }
+
+    #[test]
+    fn escrow_deposit_never_plans_into_reserved_chests() {
+        let mut storage = test_storage();
+        for ci in 2..storage.nodes[0].chests.len() {
+            storage.nodes[0].chests[ci].item = ItemId::new("cobblestone").unwrap();
+        }
+        let (plan, planned) = storage.simulate_escrow_deposit_plan("iron_ingot", 10, 64);
+        assert_eq!(planned, 0);
+        assert!(plan.is_empty());
+    }
+
     // -----------------------------------------------------------------
     // Reserved-chest policy & empty-chest allocation
     // -----------------------------------------------------------------
@@ -1,13 +1,15 @@
 //! Trade History Management
 //!
 //! Trades represent executed transactions with timestamps.
-//! Each trade is stored as an individual file in `data/trades/`.
-//! Old trades can be pruned to limit storage growth.
+//! Each trade is stored as an individual file in `data/trades/` until its
+//! UTC day closes; the background compactor then rolls the day into a
+//! segment file (see [`super::trade_segment`]).
 //!
 //! The maximum number of trades loaded into memory can be configured in
 //! `data/config.json` via the `max_trades_in_memory` field. The default is 50,000.
 
 use std::{
+    collections::HashSet,
     fs, io,
     path::{Path, PathBuf},
     sync::atomic::AtomicU64,
@@ -16,6 +18,7 @@ use std::{
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::trade_segment;
 use crate::fsutil::{archive_aside, pick_archive_path, write_atomic};
 
 /// Per-module monotonic counter appended to quarantine filenames so two
@@ -51,6 +54,29 @@ pub enum TradeType {
     AddCurrency,
     /// Admin adjustment: currency removed from the store's treasury.
     RemoveCurrency,
+    /// Player added matching items (via trade) and diamonds (from balance)
+    /// to a pair's reserves in exchange for LP shares.
+    AddLiquidity,
+    /// Player burned LP shares for their cut of the reserves: items via
+    /// trade, diamonds to balance.
+    RemoveLiquidity,
+    /// Player paid another player from balance (`pay`, or an accepted
+    /// `request`). `user_uuid` is the payer and `counterparty` the payee.
+    Transfer,
+    /// Seller traded items into escrow for `counterparty`, the buyer.
+    /// `amount_currency` is the asking price; no diamonds moved.
+    EscrowDeposit,
+    /// Buyer paid for an escrow and received the items. `user_uuid` is the
+    /// buyer, `counterparty` the seller, who was credited `amount_currency`;
+    /// `balance_change` is the buyer's side.
+    EscrowRelease,
+    /// Escrowed items went back to the seller (`user_uuid`) unsold.
+    EscrowReturn,
+    /// A rolled-back trade still credited `user_uuid`: the diamonds handed
+    /// over on a short payment, or the payout of a sell whose items could
+    /// not be stored. `balance_change` is the credit; `currency_stock_after`
+    /// is set only when `amount_currency` left the pair's reserve.
+    Refund,
 }
 
 /// A single executed trade. Persisted one-file-per-trade in
@@ -65,10 +91,37 @@ pub struct Trade {
     pub amount_currency: f64,
     pub user_uuid: String,
     pub timestamp: DateTime<Utc>,
+    /// Fee portion of `amount_currency` for customer buys and sells. Absent
+    /// on admin adjustments and on trades recorded before fees were tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<f64>,
+    /// Pair reserves right after a customer buy or sell committed. Absent
+    /// on admin adjustments and older trades.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_stock_after: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_stock_after: Option<f64>,
+    /// Spot (mid) price per item implied by those reserves under the pair's
+    /// pricing curve at commit time, fees excluded. Feeds price history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spot_price: Option<f64>,
+    /// What the trade did to the player's balance: the part of a customer
+    /// buy or sell not settled in physical diamonds. Absent on other trade
+    /// types (their balance effect follows from the type) and on trades
+    /// recorded before it was tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_change: Option<f64>,
+    /// The payee's UUID on a `Transfer`, the other player on an escrow
+    /// trade; absent on every other type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty: Option<String>,
+    /// The note the payer attached to a `Transfer`, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
 }
 
 impl Trade {
-    const TRADES_DIR: &str = "data/trades";
+    pub(crate) const TRADES_DIR: &str = "data/trades";
 
     /// Construct a trade stamped with the current wall-clock time. The timestamp
     /// is authoritative — it becomes the on-disk filename.
@@ -86,9 +139,74 @@ impl Trade {
             amount_currency,
             user_uuid,
             timestamp: Utc::now(),
+            fee: None,
+            item_stock_after: None,
+            currency_stock_after: None,
+            spot_price: None,
+            balance_change: None,
+            counterparty: None,
+            memo: None,
         }
     }
 
+    /// A `Transfer` of `amount` diamonds from `payer_uuid` to `payee_uuid`.
+    pub fn transfer(
+        payer_uuid: String,
+        payee_uuid: String,
+        amount: f64,
+        memo: Option<String>,
+    ) -> Self {
+        Self {
+            counterparty: Some(payee_uuid),
+            memo,
+            ..Self::new(
+                TradeType::Transfer,
+                crate::types::ItemId::from_normalized("diamond".to_string()),
+                0,
+                amount,
+                payer_uuid,
+            )
+        }
+    }
+
+    /// A `Refund` of `amount` diamonds to `user_uuid`'s balance after a
+    /// rolled-back trade for `item`.
+    pub fn refund(item: crate::types::ItemId, amount: f64, user_uuid: String) -> Self {
+        Self::new(TradeType::Refund, item, 0, amount, user_uuid).with_balance_change(amount)
+    }
+
+    /// Record the other player on an escrow trade (see `Trade::counterparty`).
+    pub fn with_counterparty(mut self, uuid: String) -> Self {
+        self.counterparty = Some(uuid);
+        self
+    }
+
+    /// Attach the fee charged on this trade (see `Trade::fee`).
+    pub fn with_fee(mut self, fee: f64) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Attach the trade's effect on the player's balance (see
+    /// `Trade::balance_change`).
+    pub fn with_balance_change(mut self, delta: f64) -> Self {
+        self.balance_change = Some(delta);
+        self
+    }
+
+    /// Attach the post-trade reserves and the spot price they imply.
+    pub fn with_reserves(
+        mut self,
+        item_stock: i32,
+        currency_stock: f64,
+        spot_price: Option<f64>,
+    ) -> Self {
+        self.item_stock_after = Some(item_stock);
+        self.currency_stock_after = Some(currency_stock);
+        self.spot_price = spot_price;
+        self
+    }
+
     // Colons are reserved on Windows (NTFS), so RFC3339 timestamps must have
     // them replaced before use as a filename.
     //
@@ -154,9 +272,18 @@ impl Trade {
     /// Returns trades sorted by timestamp (oldest first).
     ///
     /// **Memory limit**: Only loads the most recent `max_trades` trades.
-    /// Older trades remain on disk but aren't loaded into memory.
+    /// Older trades remain on disk but aren't loaded into memory. The
+    /// per-trade files are the newest history, so daily segments are only
+    /// read when the files alone fall short of the limit.
     pub fn load_all_with_limit(max_trades: usize) -> io::Result<Vec<Self>> {
-        let dir_path = Path::new(Self::TRADES_DIR);
+        Self::load_all_with_limit_in_dir(Path::new(Self::TRADES_DIR), max_trades)
+    }
+
+    /// Directory-parameterized form of `load_all_with_limit`.
+    pub(crate) fn load_all_with_limit_in_dir(
+        dir_path: &Path,
+        max_trades: usize,
+    ) -> io::Result<Vec<Self>> {
         let mut trades = Vec::new();
 
         if !dir_path.exists() {
@@ -266,6 +393,22 @@ impl Trade {
             }
         }
 
+        // Top up from the daily segments. A file whose trade is also in a
+        // segment is a leftover of an interrupted compaction, not a second
+        // trade, so the segment copy is skipped.
+        let remaining = max_trades.saturating_sub(paths_to_load.len());
+        let mut from_segments = 0usize;
+        if remaining > 0 {
+            let loaded: HashSet<DateTime<Utc>> = trades.iter().map(|t| t.timestamp).collect();
+            match trade_segment::load_recent_in_dir(dir_path, remaining, &loaded) {
+                Ok(older) => {
+                    from_segments = older.len();
+                    trades.extend(older);
+                }
+                Err(e) => tracing::warn!("[Trade] failed to read trade segments: {e}"),
+            }
+        }
+
         // Trades are already in chronological order due to sorted filenames,
         // but sort by timestamp field to be safe against any filename anomalies.
         trades.sort_by_key(|a| a.timestamp);
@@ -314,9 +457,10 @@ impl Trade {
         }
 
         tracing::info!(
-            "[Trade] loaded {} of {} trades (limit {}, quarantined {}, quarantine_failed {})",
-            trades.len(),
+            "[Trade] loaded {} trades from {} files and {} from segments (limit {}, quarantined {}, quarantine_failed {})",
+            trades.len().saturating_sub(from_segments),
             file_count,
+            from_segments,
             max_trades,
             quarantined,
             quarantine_failed,
@@ -349,7 +493,7 @@ impl Trade {
     /// here (not just in the public wrapper) so tests can exercise the
     /// wipe-refusal invariant directly against a temp dir; the public
     /// `save_all` is a thin one-liner over this helper.
-    fn save_all_in_dir(
+    pub(crate) fn save_all_in_dir(
         trades: &Vec<Self>,
         dir_path: &Path,
         dirty_tail_count: usize,
@@ -582,6 +726,13 @@ mod tests {
             TradeType::WithdrawBalance,
             TradeType::AddCurrency,
             TradeType::RemoveCurrency,
+            TradeType::AddLiquidity,
+            TradeType::RemoveLiquidity,
+            TradeType::Transfer,
+            TradeType::EscrowDeposit,
+            TradeType::EscrowRelease,
+            TradeType::EscrowReturn,
+            TradeType::Refund,
         ] {
             let json = serde_json::to_string(&variant).unwrap();
             let back: TradeType = serde_json::from_str(&json).unwrap();
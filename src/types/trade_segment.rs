@@ -0,0 +1,505 @@
+//! Daily segment files for closed days of trade history.
+//!
+//! `Trade::save` writes one file per trade into `data/trades/`, which keeps
+//! the live tail crash-atomic but leaves the directory growing by one entry
+//! per trade. [`compact`] rolls every per-trade file from a closed UTC day
+//! (any day before today) into `data/trades/segments/<YYYY-MM-DD>.jsonl`,
+//! one trade per line, and records the day in `segments/index.json`.
+//!
+//! A day is compacted in three steps, each safe to crash after: the merged
+//! segment (existing lines plus the new files, deduplicated by timestamp) is
+//! written with `write_atomic`, the index is rewritten, and only then are
+//! the per-trade files removed. A crash in between leaves trades both in a
+//! segment and as files; readers skip the duplicate and the next run
+//! removes the files.
+//!
+//! With `trade_retention_days` set, segments older than the cutoff are moved
+//! to `data/trades/archive/` and dropped from the index, so neither startup
+//! nor the chat trade scan reads them again. Nothing is ever deleted:
+//! `--rebuild-ledger` reads the archive too ([`load_archived_in_dir`]),
+//! since a replay from zero needs every trade.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::Trade;
+use crate::fsutil::{archive_aside, write_atomic};
+
+/// Subdirectory of the trades directory holding the daily segments.
+pub const SEGMENTS_DIR: &str = "segments";
+/// Subdirectory of the trades directory that retention moves segments to.
+pub const ARCHIVE_DIR: &str = "archive";
+const INDEX_FILE: &str = "index.json";
+
+/// One compacted day in `segments/index.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub day: NaiveDate,
+    pub trades: usize,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+
+/// `segments/index.json`: every live segment, oldest day first.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SegmentIndex {
+    pub segments: Vec<SegmentInfo>,
+}
+
+/// What one [`compact`] run did, for the log line.
+#[derive(Debug, Default, PartialEq)]
+pub struct CompactionReport {
+    pub days_compacted: usize,
+    pub files_compacted: usize,
+    pub segments_archived: usize,
+}
+
+impl SegmentIndex {
+    /// Load the index of `trades_dir`. A missing or unreadable index, or one
+    /// that disagrees with the segment files on disk (a crash between the
+    /// segment write and the index write), is rebuilt from the segments.
+    pub fn load_in_dir(trades_dir: &Path) -> io::Result<Self> {
+        let on_disk = segment_days(trades_dir)?;
+        let path = trades_dir.join(SEGMENTS_DIR).join(INDEX_FILE);
+        let parsed = match fs::read_to_string(&path) {
+            Ok(s) => serde_json::from_str::<Self>(&s).ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(index) = parsed
+            && index
+                .segments
+                .iter()
+                .map(|s| s.day)
+                .eq(on_disk.iter().copied())
+        {
+            return Ok(index);
+        }
+        if on_disk.is_empty() {
+            return Ok(Self::default());
+        }
+        tracing::warn!(
+            "[Trade] segment index {} is missing or stale; rebuilding from {} segment(s)",
+            path.display(),
+            on_disk.len()
+        );
+        let mut index = Self::default();
+        for day in on_disk {
+            let trades = read_segment(&segment_path(trades_dir, day))?;
+            if let Some(info) = SegmentInfo::summarize(day, &trades) {
+                index.segments.push(info);
+            }
+        }
+        if let Err(e) = index.save_in_dir(trades_dir) {
+            tracing::warn!("[Trade] failed to save rebuilt segment index: {e}");
+        }
+        Ok(index)
+    }
+
+    fn save_in_dir(&self, trades_dir: &Path) -> io::Result<()> {
+        let path = trades_dir.join(SEGMENTS_DIR).join(INDEX_FILE);
+        write_atomic(&path, &serde_json::to_string_pretty(self)?)
+    }
+
+    fn upsert(&mut self, info: SegmentInfo) {
+        match self.segments.binary_search_by_key(&info.day, |s| s.day) {
+            Ok(i) => self.segments[i] = info,
+            Err(i) => self.segments.insert(i, info),
+        }
+    }
+}
+
+impl SegmentInfo {
+    fn summarize(day: NaiveDate, trades: &[Trade]) -> Option<Self> {
+        Some(Self {
+            day,
+            trades: trades.len(),
+            first: trades.iter().map(|t| t.timestamp).min()?,
+            last: trades.iter().map(|t| t.timestamp).max()?,
+        })
+    }
+}
+
+/// Path of the segment for `day` under `trades_dir`.
+pub fn segment_path(trades_dir: &Path, day: NaiveDate) -> PathBuf {
+    trades_dir.join(SEGMENTS_DIR).join(format!("{day}.jsonl"))
+}
+
+/// Read one segment, oldest trade first. A line that does not parse (a torn
+/// write from before `write_atomic` was used, or a hand edit) is skipped
+/// with a warning rather than hiding the rest of the day.
+pub fn read_segment(path: &Path) -> io::Result<Vec<Trade>> {
+    let contents = fs::read_to_string(path)?;
+    let mut trades = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Trade>(line) {
+            Ok(t) => trades.push(t),
+            Err(e) => tracing::warn!("[Trade] skipping {} line {}: {e}", path.display(), n + 1),
+        }
+    }
+    trades.sort_by_key(|t| t.timestamp);
+    Ok(trades)
+}
+
+/// The newest `max_trades` trades held in segments, oldest first, skipping
+/// any whose timestamp is in `skip` (already loaded from a per-trade file
+/// that an interrupted compaction left behind). Reads segments newest day
+/// first and stops as soon as enough trades are in hand.
+pub fn load_recent_in_dir(
+    trades_dir: &Path,
+    max_trades: usize,
+    skip: &HashSet<DateTime<Utc>>,
+) -> io::Result<Vec<Trade>> {
+    let mut out: Vec<Trade> = Vec::new();
+    if max_trades == 0 {
+        return Ok(out);
+    }
+    let index = SegmentIndex::load_in_dir(trades_dir)?;
+    for info in index.segments.iter().rev() {
+        let path = segment_path(trades_dir, info.day);
+        let mut day = match read_segment(&path) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!(
+                    "[Trade] skipping unreadable segment {}: {e}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        day.retain(|t| !skip.contains(&t.timestamp));
+        // Days are visited newest first, so prepend.
+        day.append(&mut out);
+        out = day;
+        if out.len() >= max_trades {
+            break;
+        }
+    }
+    if out.len() > max_trades {
+        out.drain(..out.len() - max_trades);
+    }
+    Ok(out)
+}
+
+/// Every trade in the archive under `trades_dir`, oldest first. An
+/// unreadable segment is an error rather than a skip: a replay missing a
+/// day would compute wrong balances.
+pub fn load_archived_in_dir(trades_dir: &Path) -> io::Result<Vec<Trade>> {
+    let dir = trades_dir.join(ARCHIVE_DIR);
+    let mut out = Vec::new();
+    for day in jsonl_days(&dir)? {
+        out.extend(read_segment(&dir.join(format!("{day}.jsonl")))?);
+    }
+    Ok(out)
+}
+
+/// Compact `data/trades/`. See [`compact_in_dir`].
+pub fn compact(today: NaiveDate, retention_days: Option<u32>) -> io::Result<CompactionReport> {
+    compact_in_dir(Path::new(Trade::TRADES_DIR), today, retention_days)
+}
+
+/// Roll every per-trade file in `trades_dir` dated before `today` into its
+/// day's segment, then archive segments older than `retention_days` before
+/// `today`. A per-trade file that does not parse, or whose embedded
+/// timestamp falls on another day than its name, is left in place for the
+/// loader's quarantine.
+pub fn compact_in_dir(
+    trades_dir: &Path,
+    today: NaiveDate,
+    retention_days: Option<u32>,
+) -> io::Result<CompactionReport> {
+    let mut report = CompactionReport::default();
+    if !trades_dir.exists() {
+        return Ok(report);
+    }
+
+    let mut by_day: BTreeMap<NaiveDate, Vec<PathBuf>> = BTreeMap::new();
+    for entry in fs::read_dir(trades_dir)? {
+        let path = match entry {
+            Ok(e) => e.path(),
+            Err(e) => {
+                tracing::warn!("[Trade] compaction: skipping unreadable directory entry: {e}");
+                continue;
+            }
+        };
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        // Stems are `YYYY-MM-DDTHH-MM-SS...`; the UTC day is the first ten bytes.
+        let day = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.get(..10))
+            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+        if let Some(day) = day
+            && day < today
+        {
+            by_day.entry(day).or_default().push(path);
+        }
+    }
+
+    let mut index = SegmentIndex::load_in_dir(trades_dir)?;
+    for (day, paths) in by_day {
+        let segment = segment_path(trades_dir, day);
+        let mut merged: BTreeMap<DateTime<Utc>, Trade> = BTreeMap::new();
+        if segment.exists() {
+            for t in read_segment(&segment)? {
+                merged.insert(t.timestamp, t);
+            }
+        }
+        let mut compacted: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            let trade = match fs::read_to_string(&path) {
+                Ok(s) => serde_json::from_str::<Trade>(&s).ok(),
+                // Swept or compacted by someone else since the listing.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    tracing::warn!("[Trade] compaction: cannot read {}: {e}", path.display());
+                    continue;
+                }
+            };
+            match trade {
+                Some(t) if t.timestamp.date_naive() == day => {
+                    merged.entry(t.timestamp).or_insert(t);
+                    compacted.push(path);
+                }
+                _ => tracing::warn!(
+                    "[Trade] compaction: leaving {} for the loader to quarantine",
+                    path.display()
+                ),
+            }
+        }
+        if compacted.is_empty() {
+            continue;
+        }
+
+        let trades: Vec<Trade> = merged.into_values().collect();
+        let mut body = String::new();
+        for t in &trades {
+            body.push_str(&serde_json::to_string(t)?);
+            body.push('\n');
+        }
+        write_atomic(&segment, &body)?;
+        if let Some(info) = SegmentInfo::summarize(day, &trades) {
+            index.upsert(info);
+        }
+        index.save_in_dir(trades_dir)?;
+        for path in &compacted {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!(
+                    "[Trade] compaction: {} is in the segment but could not be removed: {e}",
+                    path.display()
+                ),
+            }
+        }
+        report.days_compacted += 1;
+        report.files_compacted += compacted.len();
+    }
+
+    if let Some(days) = retention_days
+        && let Some(cutoff) = today.checked_sub_days(Days::new(u64::from(days)))
+    {
+        let archive = trades_dir.join(ARCHIVE_DIR);
+        let expired: Vec<NaiveDate> = index
+            .segments
+            .iter()
+            .map(|s| s.day)
+            .filter(|day| *day < cutoff)
+            .collect();
+        for day in expired {
+            fs::create_dir_all(&archive)?;
+            let dst = archive.join(format!("{day}.jsonl"));
+            if dst.exists() {
+                tracing::warn!(
+                    "[Trade] retention: {} already exists; leaving segment {day} in place",
+                    dst.display()
+                );
+                continue;
+            }
+            archive_aside(&segment_path(trades_dir, day), &dst)?;
+            index.segments.retain(|s| s.day != day);
+            index.save_in_dir(trades_dir)?;
+            report.segments_archived += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Days with a segment file on disk, oldest first.
+fn segment_days(trades_dir: &Path) -> io::Result<Vec<NaiveDate>> {
+    jsonl_days(&trades_dir.join(SEGMENTS_DIR))
+}
+
+/// Days with a `<YYYY-MM-DD>.jsonl` file in `dir`, oldest first.
+fn jsonl_days(dir: &Path) -> io::Result<Vec<NaiveDate>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut days = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "jsonl")
+            && let Some(day) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+        {
+            days.push(day);
+        }
+    }
+    days.sort();
+    Ok(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ItemId, TradeType};
+    use chrono::TimeZone;
+
+    fn trade_at(ts: DateTime<Utc>) -> Trade {
+        let mut t = Trade::new(
+            TradeType::Buy,
+            ItemId::new("cobblestone").unwrap(),
+            1,
+            1.0,
+            "u".to_string(),
+        );
+        t.timestamp = ts;
+        t
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    fn at(d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, d, h, 0, 0).unwrap()
+    }
+
+    fn json_files(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+            .count()
+    }
+
+    #[test]
+    fn compaction_rolls_closed_days_and_keeps_today_as_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let trades = vec![
+            trade_at(at(1, 9)),
+            trade_at(at(1, 17)),
+            trade_at(at(2, 12)),
+            trade_at(at(3, 8)),
+        ];
+        Trade::save_all_in_dir(&trades, dir.path(), trades.len()).unwrap();
+
+        let report = compact_in_dir(dir.path(), day(3), None).unwrap();
+        assert_eq!(report.days_compacted, 2);
+        assert_eq!(report.files_compacted, 3);
+        assert_eq!(json_files(dir.path()), 1, "today's trade stays a file");
+
+        let index = SegmentIndex::load_in_dir(dir.path()).unwrap();
+        assert_eq!(index.segments.len(), 2);
+        assert_eq!(index.segments[0].trades, 2);
+        assert_eq!(index.segments[0].last, at(1, 17));
+        assert_eq!(
+            read_segment(&segment_path(dir.path(), day(1)))
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let loaded = Trade::load_all_with_limit_in_dir(dir.path(), 10).unwrap();
+        assert_eq!(loaded, trades);
+    }
+
+    #[test]
+    fn a_late_file_merges_into_an_existing_segment_without_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        Trade::save_all_in_dir(&vec![trade_at(at(1, 9))], dir.path(), 1).unwrap();
+        compact_in_dir(dir.path(), day(2), None).unwrap();
+
+        // A crash between the segment write and the file removal leaves the
+        // same trade in both places; a late autosave adds another.
+        let late = vec![trade_at(at(1, 9)), trade_at(at(1, 23))];
+        Trade::save_all_in_dir(&late, dir.path(), 2).unwrap();
+        assert_eq!(
+            Trade::load_all_with_limit_in_dir(dir.path(), 10)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        compact_in_dir(dir.path(), day(2), None).unwrap();
+        let segment = read_segment(&segment_path(dir.path(), day(1))).unwrap();
+        assert_eq!(segment.len(), 2);
+        assert_eq!(json_files(dir.path()), 0);
+    }
+
+    #[test]
+    fn load_takes_only_the_newest_trades_across_segments_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let trades: Vec<Trade> = (1..=4).map(|d| trade_at(at(d, 12))).collect();
+        Trade::save_all_in_dir(&trades, dir.path(), trades.len()).unwrap();
+        compact_in_dir(dir.path(), day(4), None).unwrap();
+
+        let loaded = Trade::load_all_with_limit_in_dir(dir.path(), 2).unwrap();
+        assert_eq!(loaded, trades[2..].to_vec());
+    }
+
+    #[test]
+    fn retention_moves_old_segments_to_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let trades: Vec<Trade> = (1..=5).map(|d| trade_at(at(d, 12))).collect();
+        Trade::save_all_in_dir(&trades, dir.path(), trades.len()).unwrap();
+
+        let report = compact_in_dir(dir.path(), day(5), Some(2)).unwrap();
+        assert_eq!(
+            report.segments_archived, 2,
+            "days 1 and 2 are before the cutoff"
+        );
+        assert!(
+            dir.path()
+                .join(ARCHIVE_DIR)
+                .join("2026-03-01.jsonl")
+                .exists()
+        );
+        let index = SegmentIndex::load_in_dir(dir.path()).unwrap();
+        assert_eq!(index.segments.first().map(|s| s.day), Some(day(3)));
+        assert_eq!(
+            Trade::load_all_with_limit_in_dir(dir.path(), 10)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn a_stale_index_is_rebuilt_from_the_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        Trade::save_all_in_dir(&vec![trade_at(at(1, 9))], dir.path(), 1).unwrap();
+        compact_in_dir(dir.path(), day(2), None).unwrap();
+        fs::write(dir.path().join(SEGMENTS_DIR).join(INDEX_FILE), "{").unwrap();
+
+        let index = SegmentIndex::load_in_dir(dir.path()).unwrap();
+        assert_eq!(index.segments.len(), 1);
+        assert_eq!(index.segments[0].first, at(1, 9));
+    }
+}
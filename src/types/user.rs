@@ -98,6 +98,10 @@ pub struct User {
     /// as non-operators instead of failing the whole load.
     #[serde(default)]
     pub operator: bool,
+    /// Liquidity-provider shares held, keyed by pair item id. Omitted when
+    /// the user has never provided liquidity.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub lp_shares: HashMap<String, f64>,
 }
 
 #[cfg_attr(test, allow(dead_code))]
@@ -543,14 +547,10 @@ impl User {
     /// the directory if needed. Uses `write_atomic` so a crash mid-write
     /// cannot leave a partially written user file.
     ///
-    /// Retained as a one-liner over `save_in_dir` for symmetry with the
-    /// other `Type::save` methods on the storage types (and as a future
-    /// callsite if a single-user write path is needed). Production code
-    /// reaches the same logic through
-    /// `save_dirty` → `save_dirty_in_dir` → `save_in_dir`, so this wrapper
-    /// has no live callers — `#[allow(dead_code)]` keeps it available
-    /// without a warning.
-    #[allow(dead_code)]
+    /// A one-liner over `save_in_dir` for symmetry with the other
+    /// `Type::save` methods on the storage types. The Store reaches the same
+    /// logic through `save_dirty` → `save_dirty_in_dir` → `save_in_dir`;
+    /// this wrapper writes the users `--rebuild-ledger` repaired.
     pub fn save(&self) -> io::Result<()> {
         self.save_in_dir(Path::new(Self::USERS_DIR))
     }
@@ -591,7 +591,7 @@ impl User {
     /// Directory-parameterized form of `load_all`. Same skip-and-quarantine
     /// rules; tests target this directly with a `tempfile::tempdir()` to
     /// pin the malformed-entry guards without touching `data/users/`.
-    fn load_all_in_dir(dir_path: &Path) -> io::Result<HashMap<String, Self>> {
+    pub(crate) fn load_all_in_dir(dir_path: &Path) -> io::Result<HashMap<String, Self>> {
         let mut users = HashMap::new();
 
         if !dir_path.exists() {
@@ -982,6 +982,7 @@ mod tests {
             username: "alice".into(),
             balance: 42.5,
             operator: true,
+            lp_shares: Default::default(),
         };
         let json = serde_json::to_string(&u).unwrap();
         let back: User = serde_json::from_str(&json).unwrap();
@@ -1089,6 +1090,7 @@ mod tests {
             username: "alice".to_string(),
             balance: 7.5,
             operator: true,
+            lp_shares: Default::default(),
         };
         fs::write(&path, serde_json::to_string(&user).unwrap()).unwrap();
 
@@ -1112,12 +1114,14 @@ mod tests {
             username: "alice".to_string(),
             balance: 1.0,
             operator: false,
+            lp_shares: Default::default(),
         };
         let bogus = User {
             uuid: "../etc/passwd".to_string(),
             username: "mallory".to_string(),
             balance: 0.0,
             operator: false,
+            lp_shares: Default::default(),
         };
 
         let mut users = HashMap::new();
@@ -1373,6 +1377,7 @@ mod tests {
                 username: "a".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -1382,6 +1387,7 @@ mod tests {
                 username: "b".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -1391,6 +1397,7 @@ mod tests {
                 username: "c".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
 
@@ -1416,6 +1423,7 @@ mod tests {
                 username: "a".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -1425,6 +1433,7 @@ mod tests {
                 username: "b".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
         users.insert(
@@ -1434,6 +1443,7 @@ mod tests {
                 username: "c".to_string(),
                 balance: 0.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
 
@@ -1462,6 +1472,7 @@ mod tests {
                 username: "alice".to_string(),
                 balance: 1.0,
                 operator: false,
+                lp_shares: Default::default(),
             },
         );
 